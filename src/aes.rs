@@ -10,12 +10,15 @@ const IVSIZE: usize = 12;
 bytes!(Block, BLOCKSIZE);
 bytes!(Word, 4);
 bytes!(Key, BLOCKSIZE);
+bytes!(Key256, 32);
 bytes!(Nonce, IVSIZE);
 bytes!(SBox, 256);
 bytes!(RCon, 11);
 
 bytes!(Bytes144, 144);
 bytes!(Bytes176, 176);
+bytes!(Bytes208, 208);
+bytes!(Bytes240, 240);
 
 const SBOX: SBox = SBox([
     0x63, 0x7C, 0x77, 0x7B, 0xF2, 0x6B, 0x6F, 0xC5, 0x30, 0x01, 0x67, 0x2B, 0xFE, 0xD7, 0xAB, 0x76,
@@ -36,6 +39,25 @@ const SBOX: SBox = SBox([
     0x8C, 0xA1, 0x89, 0x0D, 0xBF, 0xE6, 0x42, 0x68, 0x41, 0x99, 0x2D, 0x0F, 0xB0, 0x54, 0xBB, 0x16,
 ]);
 
+const INV_SBOX: SBox = SBox([
+    0x52, 0x09, 0x6A, 0xD5, 0x30, 0x36, 0xA5, 0x38, 0xBF, 0x40, 0xA3, 0x9E, 0x81, 0xF3, 0xD7, 0xFB,
+    0x7C, 0xE3, 0x39, 0x82, 0x9B, 0x2F, 0xFF, 0x87, 0x34, 0x8E, 0x43, 0x44, 0xC4, 0xDE, 0xE9, 0xCB,
+    0x54, 0x7B, 0x94, 0x32, 0xA6, 0xC2, 0x23, 0x3D, 0xEE, 0x4C, 0x95, 0x0B, 0x42, 0xFA, 0xC3, 0x4E,
+    0x08, 0x2E, 0xA1, 0x66, 0x28, 0xD9, 0x24, 0xB2, 0x76, 0x5B, 0xA2, 0x49, 0x6D, 0x8B, 0xD1, 0x25,
+    0x72, 0xF8, 0xF6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xD4, 0xA4, 0x5C, 0xCC, 0x5D, 0x65, 0xB6, 0x92,
+    0x6C, 0x70, 0x48, 0x50, 0xFD, 0xED, 0xB9, 0xDA, 0x5E, 0x15, 0x46, 0x57, 0xA7, 0x8D, 0x9D, 0x84,
+    0x90, 0xD8, 0xAB, 0x00, 0x8C, 0xBC, 0xD3, 0x0A, 0xF7, 0xE4, 0x58, 0x05, 0xB8, 0xB3, 0x45, 0x06,
+    0xD0, 0x2C, 0x1E, 0x8F, 0xCA, 0x3F, 0x0F, 0x02, 0xC1, 0xAF, 0xBD, 0x03, 0x01, 0x13, 0x8A, 0x6B,
+    0x3A, 0x91, 0x11, 0x41, 0x4F, 0x67, 0xDC, 0xEA, 0x97, 0xF2, 0xCF, 0xCE, 0xF0, 0xB4, 0xE6, 0x73,
+    0x96, 0xAC, 0x74, 0x22, 0xE7, 0xAD, 0x35, 0x85, 0xE2, 0xF9, 0x37, 0xE8, 0x1C, 0x75, 0xDF, 0x6E,
+    0x47, 0xF1, 0x1A, 0x71, 0x1D, 0x29, 0xC5, 0x89, 0x6F, 0xB7, 0x62, 0x0E, 0xAA, 0x18, 0xBE, 0x1B,
+    0xFC, 0x56, 0x3E, 0x4B, 0xC6, 0xD2, 0x79, 0x20, 0x9A, 0xDB, 0xC0, 0xFE, 0x78, 0xCD, 0x5A, 0xF4,
+    0x1F, 0xDD, 0xA8, 0x33, 0x88, 0x07, 0xC7, 0x31, 0xB1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xEC, 0x5F,
+    0x60, 0x51, 0x7F, 0xA9, 0x19, 0xB5, 0x4A, 0x0D, 0x2D, 0xE5, 0x7A, 0x9F, 0x93, 0xC9, 0x9C, 0xEF,
+    0xA0, 0xE0, 0x3B, 0x4D, 0xAE, 0x2A, 0xF5, 0xB0, 0xC8, 0xEB, 0xBB, 0x3C, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2B, 0x04, 0x7E, 0xBA, 0x77, 0xD6, 0x26, 0xE1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0C, 0x7D,
+]);
+
 const RCON: RCon = RCon([
     0x8d, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36,
 ]);
@@ -65,6 +87,21 @@ fn shift_rows(state: Block) -> Block {
     shift_row(3, 3, state)
 }
 
+fn inv_sub_bytes(state: Block) -> Block {
+    let mut st = state;
+    for i in 0..16 {
+        st[i] = INV_SBOX[state[i]];
+    }
+    st
+}
+
+// Shifting a row by 4 - n undoes shifting it by n.
+fn inv_shift_rows(state: Block) -> Block {
+    let state = shift_row(1, 3, state);
+    let state = shift_row(2, 2, state);
+    shift_row(3, 1, state)
+}
+
 fn xtime(x: u8) -> u8 {
     let x1 = x << 1;
     let x7 = x >> 7;
@@ -96,6 +133,28 @@ fn mix_columns(state: Block) -> Block {
     mix_column(3, state)
 }
 
+// InvMixColumns is MixColumns after multiplying the column by
+// {04}x^2 + {05} (The Design of Rijndael, 4.1.3).
+#[pre(c < 4)]
+fn inv_mix_column(c: usize, state: Block) -> Block {
+    let i0 = 4 * c;
+    let u = xtime(xtime(state[i0] ^ state[i0 + 2]));
+    let v = xtime(xtime(state[i0 + 1] ^ state[i0 + 3]));
+    let mut st = state;
+    st[i0] ^= u;
+    st[i0 + 1] ^= v;
+    st[i0 + 2] ^= u;
+    st[i0 + 3] ^= v;
+    mix_column(c, st)
+}
+
+fn inv_mix_columns(state: Block) -> Block {
+    let state = inv_mix_column(0, state);
+    let state = inv_mix_column(1, state);
+    let state = inv_mix_column(2, state);
+    inv_mix_column(3, state)
+}
+
 fn add_round_key(state: Block, key: Key) -> Block {
     let mut out = state;
     for i in 0..16 {
@@ -117,6 +176,19 @@ fn aes_enc_last(state: Block, round_key: Key) -> Block {
     add_round_key(state, round_key)
 }
 
+fn aes_dec(state: Block, round_key: Key) -> Block {
+    let state = inv_shift_rows(state);
+    let state = inv_sub_bytes(state);
+    let state = add_round_key(state, round_key);
+    inv_mix_columns(state)
+}
+
+fn aes_dec_last(state: Block, round_key: Key) -> Block {
+    let state = inv_shift_rows(state);
+    let state = inv_sub_bytes(state);
+    add_round_key(state, round_key)
+}
+
 fn rounds(state: Block, key: Bytes144) -> Block {
     let mut out = state;
     for i in 0..9 {
@@ -134,6 +206,42 @@ fn block_cipher(input: Block, key: Bytes176) -> Block {
     aes_enc_last(state, kn)
 }
 
+fn rounds_256(state: Block, key: Bytes208) -> Block {
+    let mut out = state;
+    for i in 0..13 {
+        out = aes_enc(out, key.get(16 * i..16 * i + 16));
+    }
+    out
+}
+
+fn block_cipher_256(input: Block, key: Bytes240) -> Block {
+    let k0: Key = key.get(0..16);
+    let k: Bytes208 = key.get(16..14 * 16);
+    let kn: Key = key.get(14 * 16..15 * 16);
+    let state = add_round_key(input, k0);
+    let state = rounds_256(state, k);
+    aes_enc_last(state, kn)
+}
+
+// The inverse cipher of FIPS 197, 5.3: the round keys in reverse order.
+fn inv_block_cipher(input: Block, key: Bytes176) -> Block {
+    let kn: Key = key.get(10 * 16..11 * 16);
+    let mut state = add_round_key(input, kn);
+    for i in (1..10).rev() {
+        state = aes_dec(state, key.get(16 * i..16 * i + 16));
+    }
+    aes_dec_last(state, key.get(0..16))
+}
+
+fn inv_block_cipher_256(input: Block, key: Bytes240) -> Block {
+    let kn: Key = key.get(14 * 16..15 * 16);
+    let mut state = add_round_key(input, kn);
+    for i in (1..14).rev() {
+        state = aes_dec(state, key.get(16 * i..16 * i + 16));
+    }
+    aes_dec_last(state, key.get(0..16))
+}
+
 fn rotate_word(w: Word) -> Word {
     Word([w[1], w[2], w[3], w[0]])
 }
@@ -161,6 +269,22 @@ fn key_expansion_word(w0: Word, w1: Word, i: usize) -> Word {
     k
 }
 
+// For 256-bit keys every eighth word is also substituted four words after
+// the RCON one (FIPS 197, 5.2).
+fn key_expansion_word_256(w0: Word, w1: Word, i: usize) -> Word {
+    assert!(i < 60);
+    let mut k = w1;
+    if i % 8 == 0 {
+        k = aes_keygen_assist(k, RCON[i / 8]);
+    } else if i % 8 == 4 {
+        k = sub_word(k);
+    }
+    for i in 0..4 {
+        k[i] ^= w0[i];
+    }
+    k
+}
+
 fn key_expansion(key: Key) -> Bytes176 {
     let mut key_ex = Bytes176::new();
     // TODO: get rid of all `into`
@@ -178,16 +302,55 @@ fn key_expansion(key: Key) -> Bytes176 {
     key_ex
 }
 
-fn aes128_encrypt_block(k: Key, input: Block) -> Block {
+fn key_expansion_256(key: Key256) -> Bytes240 {
+    let mut key_ex = Bytes240::new();
+    key_ex.update_vec(0, key.into());
+    let mut i: usize;
+    for j in 0..52 {
+        i = j + 8;
+        let word = key_expansion_word_256(
+            key_ex.get(4 * i - 32..4 * i - 28),
+            key_ex.get(4 * i - 4..4 * i),
+            i,
+        );
+        key_ex.update_vec(4 * i, word.into());
+    }
+    key_ex
+}
+
+pub fn aes128_encrypt_block(k: Key, input: Block) -> Block {
     let key_ex = key_expansion(k);
     block_cipher(input, key_ex)
 }
 
-pub(crate) fn aes128_ctr_keyblock(k: Key, n: Nonce, c: u32) -> Block {
+pub fn aes128_decrypt_block(k: Key, input: Block) -> Block {
+    let key_ex = key_expansion(k);
+    inv_block_cipher(input, key_ex)
+}
+
+pub fn aes256_encrypt_block(k: Key256, input: Block) -> Block {
+    let key_ex = key_expansion_256(k);
+    block_cipher_256(input, key_ex)
+}
+
+pub fn aes256_decrypt_block(k: Key256, input: Block) -> Block {
+    let key_ex = key_expansion_256(k);
+    inv_block_cipher_256(input, key_ex)
+}
+
+fn ctr_input(n: Nonce, c: u32) -> Block {
     let mut input = Block::new();
     input.update_vec(0, n.into());
     input.update_raw(12, &c.to_be_bytes());
-    aes128_encrypt_block(k, input)
+    input
+}
+
+pub(crate) fn aes128_ctr_keyblock(k: Key, n: Nonce, c: u32) -> Block {
+    aes128_encrypt_block(k, ctr_input(n, c))
+}
+
+pub(crate) fn aes256_ctr_keyblock(k: Key256, n: Nonce, c: u32) -> Block {
+    aes256_encrypt_block(k, ctr_input(n, c))
 }
 
 pub(crate) fn xor_block(block: Block, keyblock: Block) -> Block {
@@ -198,33 +361,40 @@ pub(crate) fn xor_block(block: Block, keyblock: Block) -> Block {
     out
 }
 
-fn aes128_counter_mode(key: Key, nonce: Nonce, counter: u32, msg: ByteSlice) -> Bytes {
+// Counter mode over the keyblock function of either key size.
+fn counter_mode(keyblock: impl Fn(u32) -> Block, counter: u32, msg: ByteSlice) -> Bytes {
     let l = msg.len();
     let n_blocks: usize = l / BLOCKSIZE;
     let rem = l % BLOCKSIZE;
     let mut ctr = counter;
     let mut blocks_out = Bytes::new_len(l);
     for i in 0..n_blocks {
-        let keyblock = aes128_ctr_keyblock(key, nonce, ctr);
         let k = i * BLOCKSIZE;
-        blocks_out.update_vec(k, xor_block(msg.get(k..k + BLOCKSIZE), keyblock).into());
+        blocks_out.update_vec(k, xor_block(msg.get(k..k + BLOCKSIZE), keyblock(ctr)).into());
         ctr += 1;
     }
-    let keyblock = aes128_ctr_keyblock(key, nonce, ctr);
     let k = n_blocks * BLOCKSIZE;
     let mut last_block = Block::new();
     last_block.update_raw(0, &msg[k..k + rem]);
     // TODO: this doesn't work with `update_vec` because we have to truncate.
-    blocks_out.update_raw(k, &xor_block(last_block, keyblock)[0..rem]);
+    blocks_out.update_raw(k, &xor_block(last_block, keyblock(ctr))[0..rem]);
     blocks_out
 }
 
 pub fn aes128_encrypt(key: Key, nonce: Nonce, counter: u32, msg: ByteSlice) -> Bytes {
-    aes128_counter_mode(key, nonce, counter, msg)
+    counter_mode(|c| aes128_ctr_keyblock(key, nonce, c), counter, msg)
 }
 
 pub fn aes128_decrypt(key: Key, nonce: Nonce, counter: u32, ctxt: ByteSlice) -> Bytes {
-    aes128_counter_mode(key, nonce, counter, ctxt)
+    counter_mode(|c| aes128_ctr_keyblock(key, nonce, c), counter, ctxt)
+}
+
+pub fn aes256_encrypt(key: Key256, nonce: Nonce, counter: u32, msg: ByteSlice) -> Bytes {
+    counter_mode(|c| aes256_ctr_keyblock(key, nonce, c), counter, msg)
+}
+
+pub fn aes256_decrypt(key: Key256, nonce: Nonce, counter: u32, ctxt: ByteSlice) -> Bytes {
+    counter_mode(|c| aes256_ctr_keyblock(key, nonce, c), counter, ctxt)
 }
 
 // Testing some internal functions.
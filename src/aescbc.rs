@@ -0,0 +1,289 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::aes;
+use crate::aes::{aes128_decrypt_block, aes128_encrypt_block};
+use crate::aes::{aes256_decrypt_block, aes256_encrypt_block, Block};
+use crate::hash::HashSpec;
+use crate::hmac::hmac;
+
+// AES-CBC and the record transforms of the TLS 1.2 CBC suites:
+// MAC-then-encrypt (RFC 5246, section 6.2.3.2) and encrypt-then-MAC (RFC
+// 7366). Keys of 16 bytes select AES-128, keys of 32 bytes AES-256.
+
+const BLOCKSIZE: usize = 16;
+
+fn check_key(key: &Bytes) -> Result<(), String> {
+    if key.len() == 16 || key.len() == 32 {
+        Ok(())
+    } else {
+        Err("Invalid key length".to_string())
+    }
+}
+
+fn encrypt_block(key: &Bytes, block: Block) -> Block {
+    if key.len() == 16 {
+        aes128_encrypt_block(aes::Key::from(key.raw()), block)
+    } else {
+        aes256_encrypt_block(aes::Key256::from(key.raw()), block)
+    }
+}
+
+fn decrypt_block(key: &Bytes, block: Block) -> Block {
+    if key.len() == 16 {
+        aes128_decrypt_block(aes::Key::from(key.raw()), block)
+    } else {
+        aes256_decrypt_block(aes::Key256::from(key.raw()), block)
+    }
+}
+
+// C_i = E(K, P_i ^ C_i-1) with C_0 = IV, over whole blocks only.
+pub fn cbc_encrypt(key: Bytes, iv: Block, msg: Bytes) -> Result<Bytes, String> {
+    check_key(&key)?;
+    if msg.len() % BLOCKSIZE != 0 {
+        return Err("Message is not a multiple of the block size".to_string());
+    }
+    let mut out = Bytes::new_len(msg.len());
+    let mut prev = iv;
+    for i in 0..msg.len() / BLOCKSIZE {
+        let k = i * BLOCKSIZE;
+        prev = encrypt_block(&key, aes::xor_block(Block::from(&msg[k..k + BLOCKSIZE]), prev));
+        out.update_raw(k, &prev[..]);
+    }
+    Ok(out)
+}
+
+// P_i = D(K, C_i) ^ C_i-1 with C_0 = IV.
+pub fn cbc_decrypt(key: Bytes, iv: Block, cipher_text: Bytes) -> Result<Bytes, String> {
+    check_key(&key)?;
+    if cipher_text.len() % BLOCKSIZE != 0 {
+        return Err("Cipher text is not a multiple of the block size".to_string());
+    }
+    let mut out = Bytes::new_len(cipher_text.len());
+    let mut prev = iv;
+    for i in 0..cipher_text.len() / BLOCKSIZE {
+        let k = i * BLOCKSIZE;
+        let block = Block::from(&cipher_text[k..k + BLOCKSIZE]);
+        out.update_raw(k, &aes::xor_block(decrypt_block(&key, block), prev)[..]);
+        prev = block;
+    }
+    Ok(out)
+}
+
+// The keys of one direction of a connection.
+pub struct CbcKeys {
+    pub mac_key: Bytes,
+    pub enc_key: Bytes,
+}
+
+// MAC(MAC_write_key, seq_num + type + version + length + content)
+fn record_mac<H: HashSpec>(
+    mac_key: &Bytes,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    content: &Bytes,
+) -> Bytes {
+    let mut data = Bytes::new_len(13 + content.len());
+    data.update_raw(0, &seq.to_be_bytes());
+    data[8] = content_type;
+    data.update_raw(9, &version.to_be_bytes());
+    data.update_raw(11, &(content.len() as u16).to_be_bytes());
+    data.update(13, content);
+    hmac::<H>(mac_key.clone(), data)
+}
+
+// The smallest padding_length that fills the last block. Senders may add up
+// to 255 bytes of padding in all, in whole blocks beyond this.
+pub fn padding_length<H: HashSpec>(content_len: usize) -> u8 {
+    ((BLOCKSIZE - (content_len + H::HASH_LEN + 1) % BLOCKSIZE) % BLOCKSIZE) as u8
+}
+
+// The record is the explicit IV followed by
+//   E(content + MAC + padding + padding_length)
+// where each of the padding_length padding bytes holds padding_length.
+pub fn seal<H: HashSpec>(
+    keys: &CbcKeys,
+    iv: Block,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    content: Bytes,
+    padding_length: u8,
+) -> Result<Bytes, String> {
+    let mac = record_mac::<H>(&keys.mac_key, seq, content_type, version, &content);
+    let l = content.len() + mac.len() + padding_length as usize + 1;
+    if l % BLOCKSIZE != 0 {
+        return Err("Invalid padding length".to_string());
+    }
+    let mut plain_text = Bytes::new_len(l);
+    plain_text.update(0, &content);
+    plain_text.update(content.len(), &mac);
+    for i in content.len() + mac.len()..l {
+        plain_text[i] = padding_length;
+    }
+    let cipher_text = cbc_encrypt(keys.enc_key.clone(), iv, plain_text)?;
+    let mut record = Bytes::new_len(BLOCKSIZE + l);
+    record.update_raw(0, &iv[..]);
+    record.update(BLOCKSIZE, &cipher_text);
+    Ok(record)
+}
+
+// The length of a decrypted record without its padding: the last byte is
+// padding_length, and it and the padding_length bytes before it must all
+// hold that value.
+pub fn check_padding(plain_text: &Bytes) -> Result<usize, String> {
+    let l = plain_text.len();
+    if l == 0 {
+        return Err("Bad record padding".to_string());
+    }
+    let padding_length = plain_text[l - 1] as usize;
+    if padding_length + 1 > l {
+        return Err("Bad record padding".to_string());
+    }
+    for i in l - padding_length - 1..l {
+        if plain_text[i] as usize != padding_length {
+            return Err("Bad record padding".to_string());
+        }
+    }
+    Ok(l - padding_length - 1)
+}
+
+// Decrypts a record, checks its padding and then its MAC. The errors tell bad
+// padding from a bad MAC so that padding oracles can be stated against this
+// spec; implementations must answer both with bad_record_mac, in the same
+// time.
+pub fn open<H: HashSpec>(
+    keys: &CbcKeys,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    record: Bytes,
+) -> Result<Bytes, String> {
+    if record.len() < 2 * BLOCKSIZE || record.len() % BLOCKSIZE != 0 {
+        return Err("Bad record length".to_string());
+    }
+    let iv = Block::from(&record[0..BLOCKSIZE]);
+    let cipher_text = Bytes::from(&record[BLOCKSIZE..record.len()]);
+    let plain_text = cbc_decrypt(keys.enc_key.clone(), iv, cipher_text)?;
+    let l = check_padding(&plain_text)?;
+    if l < H::HASH_LEN {
+        return Err("Bad record MAC".to_string());
+    }
+    let content = Bytes::from(&plain_text[0..l - H::HASH_LEN]);
+    let mac = record_mac::<H>(&keys.mac_key, seq, content_type, version, &content);
+    if mac[..] != plain_text[l - H::HASH_LEN..l] {
+        return Err("Bad record MAC".to_string());
+    }
+    Ok(content)
+}
+
+// How a CBC record is put together: MAC-then-encrypt unless both peers sent
+// the encrypt_then_mac extension (RFC 7366, section 2).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Construction {
+    MacThenEncrypt,
+    EncryptThenMac,
+}
+
+// The smallest padding_length that fills the last block when the MAC is
+// outside the encryption.
+pub fn padding_length_etm(content_len: usize) -> u8 {
+    ((BLOCKSIZE - (content_len + 1) % BLOCKSIZE) % BLOCKSIZE) as u8
+}
+
+// With encrypt-then-MAC the record is
+//   IV + E(content + padding + padding_length) + MAC
+// where the MAC covers the IV and cipher text in place of the content, and
+// its length field is theirs (RFC 7366, section 3).
+pub fn seal_etm<H: HashSpec>(
+    keys: &CbcKeys,
+    iv: Block,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    content: Bytes,
+    padding_length: u8,
+) -> Result<Bytes, String> {
+    let l = content.len() + padding_length as usize + 1;
+    if l % BLOCKSIZE != 0 {
+        return Err("Invalid padding length".to_string());
+    }
+    let mut plain_text = Bytes::new_len(l);
+    plain_text.update(0, &content);
+    for i in content.len()..l {
+        plain_text[i] = padding_length;
+    }
+    let cipher_text = cbc_encrypt(keys.enc_key.clone(), iv, plain_text)?;
+    let mut body = Bytes::new_len(BLOCKSIZE + l);
+    body.update_raw(0, &iv[..]);
+    body.update(BLOCKSIZE, &cipher_text);
+    let mac = record_mac::<H>(&keys.mac_key, seq, content_type, version, &body);
+    let mut record = Bytes::new_len(body.len() + mac.len());
+    record.update(0, &body);
+    record.update(body.len(), &mac);
+    Ok(record)
+}
+
+// Checks the MAC before decrypting anything, so a bad record never reaches
+// the padding check and there is no padding oracle to state.
+pub fn open_etm<H: HashSpec>(
+    keys: &CbcKeys,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    record: Bytes,
+) -> Result<Bytes, String> {
+    if record.len() < 2 * BLOCKSIZE + H::HASH_LEN || (record.len() - H::HASH_LEN) % BLOCKSIZE != 0 {
+        return Err("Bad record length".to_string());
+    }
+    let l = record.len() - H::HASH_LEN;
+    let body = Bytes::from(&record[0..l]);
+    let mac = record_mac::<H>(&keys.mac_key, seq, content_type, version, &body);
+    if mac[..] != record[l..record.len()] {
+        return Err("Bad record MAC".to_string());
+    }
+    let iv = Block::from(&body[0..BLOCKSIZE]);
+    let cipher_text = Bytes::from(&body[BLOCKSIZE..l]);
+    let plain_text = cbc_decrypt(keys.enc_key.clone(), iv, cipher_text)?;
+    let l = check_padding(&plain_text)?;
+    Ok(Bytes::from(&plain_text[0..l]))
+}
+
+// `seal` or `seal_etm`, as the connection negotiated.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_record<H: HashSpec>(
+    construction: Construction,
+    keys: &CbcKeys,
+    iv: Block,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    content: Bytes,
+    padding_length: u8,
+) -> Result<Bytes, String> {
+    match construction {
+        Construction::MacThenEncrypt => {
+            seal::<H>(keys, iv, seq, content_type, version, content, padding_length)
+        }
+        Construction::EncryptThenMac => {
+            seal_etm::<H>(keys, iv, seq, content_type, version, content, padding_length)
+        }
+    }
+}
+
+// `open` or `open_etm`, as the connection negotiated.
+pub fn open_record<H: HashSpec>(
+    construction: Construction,
+    keys: &CbcKeys,
+    seq: u64,
+    content_type: u8,
+    version: u16,
+    record: Bytes,
+) -> Result<Bytes, String> {
+    match construction {
+        Construction::MacThenEncrypt => open::<H>(keys, seq, content_type, version, record),
+        Construction::EncryptThenMac => open_etm::<H>(keys, seq, content_type, version, record),
+    }
+}
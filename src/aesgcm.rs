@@ -5,8 +5,11 @@ hacspec_imports!();
 // Import aes and gcm
 use crate::aes;
 use crate::aes::{aes128_ctr_keyblock, aes128_decrypt, aes128_encrypt};
-use crate::gf128;
-use crate::gf128::{gmac, Tag};
+use crate::aes::{aes256_ctr_keyblock, aes256_decrypt, aes256_encrypt};
+use crate::gf128::Tag;
+use crate::ghash::ghash;
+
+const TAGSIZE: usize = 16;
 
 fn pad_aad_msg(aad: ByteSlice, msg: ByteSlice) -> Bytes {
     let laad = aad.len();
@@ -29,6 +32,14 @@ fn pad_aad_msg(aad: ByteSlice, msg: ByteSlice) -> Bytes {
     padded_msg
 }
 
+// The tag is GHASH over the padded AAD and cipher text, keyed with the zero
+// block's encryption and masked with the encryption of the first counter.
+fn tag(mac_key: aes::Block, tag_mix: aes::Block, aad: ByteSlice, cipher_text: ByteSlice) -> Tag {
+    let padded_msg = pad_aad_msg(aad, cipher_text);
+    let s = ghash(mac_key.raw().into(), padded_msg);
+    aes::xor_block(s.raw().into(), tag_mix).raw().into()
+}
+
 // FIXME: fix type conversions :(
 pub fn encrypt(key: aes::Key, iv: aes::Nonce, aad: ByteSlice, msg: ByteSlice) -> (Bytes, Tag) {
     let iv0 = aes::Nonce::new();
@@ -37,11 +48,9 @@ pub fn encrypt(key: aes::Key, iv: aes::Nonce, aad: ByteSlice, msg: ByteSlice) ->
     let tag_mix = aes128_ctr_keyblock(key, iv, 1);
 
     let cipher_text = aes128_encrypt(key, iv, 2, msg);
-    let padded_msg = pad_aad_msg(aad, cipher_text.get_slice());
-    let tag = gmac(padded_msg, mac_key.raw().into());
-    let tag = aes::xor_block(tag.raw().into(), tag_mix);
+    let tag = tag(mac_key, tag_mix, aad, cipher_text.get_slice());
 
-    (cipher_text, tag.raw().into())
+    (cipher_text, tag)
 }
 
 pub fn decrypt(
@@ -56,10 +65,7 @@ pub fn decrypt(
     let mac_key = aes128_ctr_keyblock(key, iv0, 0);
     let tag_mix = aes128_ctr_keyblock(key, iv, 1);
 
-    let padded_msg = pad_aad_msg(aad, cipher_text);
-    let my_tag = gmac(padded_msg, mac_key.raw().into());
-    let my_tag = aes::xor_block(my_tag.raw().into(), tag_mix);
-    let my_tag: Tag = my_tag.raw().into();
+    let my_tag = self::tag(mac_key, tag_mix, aad, cipher_text);
 
     if my_tag == tag {
         Ok(aes128_decrypt(key, iv, 2, cipher_text))
@@ -67,3 +73,84 @@ pub fn decrypt(
         Err("Mac verification failed".to_string())
     }
 }
+
+pub fn encrypt_256(
+    key: aes::Key256,
+    iv: aes::Nonce,
+    aad: ByteSlice,
+    msg: ByteSlice,
+) -> (Bytes, Tag) {
+    let iv0 = aes::Nonce::new();
+
+    let mac_key = aes256_ctr_keyblock(key, iv0, 0);
+    let tag_mix = aes256_ctr_keyblock(key, iv, 1);
+
+    let cipher_text = aes256_encrypt(key, iv, 2, msg);
+    let tag = tag(mac_key, tag_mix, aad, cipher_text.get_slice());
+
+    (cipher_text, tag)
+}
+
+pub fn decrypt_256(
+    key: aes::Key256,
+    iv: aes::Nonce,
+    aad: ByteSlice,
+    cipher_text: ByteSlice,
+    tag: Tag,
+) -> Result<Bytes, String> {
+    let iv0 = aes::Nonce::new();
+
+    let mac_key = aes256_ctr_keyblock(key, iv0, 0);
+    let tag_mix = aes256_ctr_keyblock(key, iv, 1);
+
+    let my_tag = self::tag(mac_key, tag_mix, aad, cipher_text);
+
+    if my_tag == tag {
+        Ok(aes256_decrypt(key, iv, 2, cipher_text))
+    } else {
+        Err("Mac verification failed".to_string())
+    }
+}
+
+// AEAD record protection as TLS calls it (RFC 5116): AES-128-GCM or
+// AES-256-GCM by the key length, a 12 byte nonce, and the tag appended to the
+// cipher text.
+pub fn seal(key: Bytes, nonce: Bytes, aad: Bytes, msg: Bytes) -> Result<Bytes, String> {
+    if nonce.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
+    let iv = aes::Nonce::from(nonce.raw());
+    let (cipher_text, tag) = match key.len() {
+        16 => encrypt(aes::Key::from(key.raw()), iv, aad.get_slice(), msg.get_slice()),
+        32 => encrypt_256(aes::Key256::from(key.raw()), iv, aad.get_slice(), msg.get_slice()),
+        _ => return Err("Invalid key length".to_string()),
+    };
+    let mut out = Bytes::new_len(cipher_text.len() + TAGSIZE);
+    out.update(0, &cipher_text);
+    out.update_raw(cipher_text.len(), tag.raw());
+    Ok(out)
+}
+
+pub fn open(key: Bytes, nonce: Bytes, aad: Bytes, cipher_text: Bytes) -> Result<Bytes, String> {
+    if nonce.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
+    if cipher_text.len() < TAGSIZE {
+        return Err("Cipher text too short".to_string());
+    }
+    let iv = aes::Nonce::from(nonce.raw());
+    let l = cipher_text.len() - TAGSIZE;
+    let tag = Tag::from(&cipher_text[l..l + TAGSIZE]);
+    let cipher_text = Bytes::from(&cipher_text[0..l]);
+    match key.len() {
+        16 => decrypt(aes::Key::from(key.raw()), iv, aad.get_slice(), cipher_text.get_slice(), tag),
+        32 => decrypt_256(
+            aes::Key256::from(key.raw()),
+            iv,
+            aad.get_slice(),
+            cipher_text.get_slice(),
+            tag,
+        ),
+        _ => Err("Invalid key length".to_string()),
+    }
+}
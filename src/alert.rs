@@ -0,0 +1,224 @@
+use crate::codec::{CodecError, Reader};
+use crate::record::{ContentType, Record};
+use crate::tls12::{AlertDescription, AlertLevel, Peer, State};
+use std::fmt;
+
+/// Alerts are two bytes on the wire (RFC 5246, 7.2):
+///
+/// ```text
+/// struct {
+///     AlertLevel level;
+///     AlertDescription description;
+/// } Alert;
+/// ```
+///
+/// TLS 1.3 ignores the level, treating every alert but close_notify and
+/// user_canceled as fatal, and carries each alert in a record of its own
+/// (RFC 8446, 5.1 and 6).
+pub const ALERT_LEN: usize = 2;
+
+pub fn level(level: AlertLevel) -> u8 {
+    match level {
+        AlertLevel::Warning => 1,
+        AlertLevel::Fatal => 2,
+    }
+}
+
+pub fn from_level(v: u8) -> Option<AlertLevel> {
+    match v {
+        1 => Some(AlertLevel::Warning),
+        2 => Some(AlertLevel::Fatal),
+        _ => None,
+    }
+}
+
+/// The alert's value in the IANA TLS Alerts registry.
+pub fn description(desc: AlertDescription) -> u8 {
+    use AlertDescription::*;
+    match desc {
+        CloseNotify => 0,
+        UnexpectedMessage => 10,
+        BadRecordMac => 20,
+        DecryptionFailed => 21,
+        RecordOverflow => 22,
+        DecompressionFailure => 30,
+        HandshakeFailure => 40,
+        NoCertificate => 41,
+        BadCertificate => 42,
+        UnsupportedCertificate => 43,
+        CertificateRevoked => 44,
+        CertificateExpired => 45,
+        CertificateUnknown => 46,
+        IllegalParameter => 47,
+        UnknownCa => 48,
+        AccessDenied => 49,
+        DecodeError => 50,
+        DecryptError => 51,
+        ExportRestriction => 60,
+        ProtocolVersion => 70,
+        InsufficientSecurity => 71,
+        InternalError => 80,
+        InappropriateFallback => 86,
+        UserCanceled => 90,
+        NoRenegotiation => 100,
+        MissingExtension => 109,
+        UnsupportedExtension => 110,
+        CertificateUnobtainable => 111,
+        UnrecognizedName => 112,
+        BadCertificateStatusResponse => 113,
+        BadCertificateHashValue => 114,
+        UnknownPskIdentity => 115,
+        CertificateRequired => 116,
+        NoApplicationProtocol => 120,
+        EchRequired => 121,
+    }
+}
+
+pub fn from_description(v: u8) -> Option<AlertDescription> {
+    AlertDescription::ALL.iter().copied().find(|&d| description(d) == v)
+}
+
+/// Why an alert couldn't be decoded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlertError {
+    /// The alert isn't exactly two bytes.
+    Codec(CodecError),
+    UnknownLevel(u8),
+    UnknownDescription(u8),
+    /// The record isn't an alert record.
+    NotAnAlert(ContentType),
+}
+
+impl fmt::Display for AlertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlertError::Codec(e) => e.fmt(f),
+            AlertError::UnknownLevel(v) => write!(f, "unknown alert level {}", v),
+            AlertError::UnknownDescription(v) => write!(f, "unknown alert description {}", v),
+            AlertError::NotAnAlert(t) => write!(f, "{:?} record is not an alert", t),
+        }
+    }
+}
+
+impl std::error::Error for AlertError {}
+
+impl From<CodecError> for AlertError {
+    fn from(e: CodecError) -> AlertError {
+        AlertError::Codec(e)
+    }
+}
+
+impl AlertError {
+    /// The alert a receiver answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            AlertError::Codec(e) => e.alert(),
+            AlertError::UnknownLevel(_) | AlertError::UnknownDescription(_) => {
+                AlertDescription::IllegalParameter
+            }
+            AlertError::NotAnAlert(_) => AlertDescription::UnexpectedMessage,
+        }
+    }
+}
+
+pub fn encode_alert(lvl: AlertLevel, desc: AlertDescription) -> [u8; ALERT_LEN] {
+    [level(lvl), description(desc)]
+}
+
+pub fn decode_alert(input: &[u8]) -> Result<(AlertLevel, AlertDescription), AlertError> {
+    let mut r = Reader::new(input);
+    let lvl = r.read_u8()?;
+    let desc = r.read_u8()?;
+    r.end()?;
+    let lvl = from_level(lvl).ok_or(AlertError::UnknownLevel(lvl))?;
+    let desc = from_description(desc).ok_or(AlertError::UnknownDescription(desc))?;
+    Ok((lvl, desc))
+}
+
+/// The record carrying the alert, at its usual level.
+pub fn alert_record(desc: AlertDescription, version: u16) -> Record {
+    Record {
+        content_type: ContentType::Alert,
+        version,
+        fragment: encode_alert(desc.level(), desc).to_vec(),
+    }
+}
+
+pub fn decode_alert_record(record: &Record) -> Result<(AlertLevel, AlertDescription), AlertError> {
+    if record.content_type != ContentType::Alert {
+        return Err(AlertError::NotAnAlert(record.content_type));
+    }
+    decode_alert(&record.fragment)
+}
+
+/// The alert a TLS 1.2 state sends, and who sends it, as bytes.
+pub fn sent_alert(state: State) -> Option<(Peer, [u8; ALERT_LEN])> {
+    match state {
+        State::SendsAlert(peer, desc) => Some((peer, encode_alert(desc.level(), desc))),
+        State::ClientRefusesRenegotiation => {
+            let desc = AlertDescription::NoRenegotiation;
+            Some((Peer::Client, encode_alert(AlertLevel::Warning, desc)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::record::{self, Limit};
+    use crate::tls12::fail;
+
+    #[test]
+    fn description_codec() {
+        for &d in AlertDescription::ALL.iter() {
+            assert_eq!(from_description(description(d)), Some(d));
+        }
+        let mut values = AlertDescription::ALL.iter().map(|&d| description(d)).collect::<Vec<_>>();
+        values.dedup();
+        assert_eq!(values.len(), AlertDescription::ALL.len());
+        assert_eq!(from_description(117), None);
+        for &l in &[AlertLevel::Warning, AlertLevel::Fatal] {
+            assert_eq!(from_level(level(l)), Some(l));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for &d in AlertDescription::ALL.iter() {
+            let bytes = encode_alert(d.level(), d);
+            assert_eq!(decode_alert(&bytes), Ok((d.level(), d)));
+        }
+        assert_eq!(encode_alert(AlertLevel::Warning, AlertDescription::CloseNotify), [1, 0]);
+        assert_eq!(encode_alert(AlertLevel::Fatal, AlertDescription::ProtocolVersion), [2, 70]);
+    }
+
+    #[test]
+    fn malformed() {
+        let truncated = AlertError::Codec(CodecError::Truncated);
+        assert_eq!(decode_alert(&[2]), Err(truncated));
+        // Two alerts coalesced into one record.
+        let coalesced = AlertError::Codec(CodecError::TrailingBytes);
+        assert_eq!(decode_alert(&[1, 0, 1, 0]), Err(coalesced));
+        assert_eq!(decode_alert(&[3, 40]), Err(AlertError::UnknownLevel(3)));
+        assert_eq!(decode_alert(&[2, 255]), Err(AlertError::UnknownDescription(255)));
+    }
+
+    #[test]
+    fn traces_to_bytes() {
+        let state = fail(State::default(), Peer::Server, AlertDescription::HandshakeFailure);
+        assert_eq!(sent_alert(state), Some((Peer::Server, [2, 40])));
+        assert_eq!(sent_alert(State::ClientRefusesRenegotiation), Some((Peer::Client, [1, 100])));
+        assert_eq!(sent_alert(State::default()), None);
+
+        // The record OpenSSL 3.0 sends in answer to a ClientHello without a
+        // cipher suite it shares.
+        let captured = [0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        let (rec, _) = record::decode_record(&captured, Limit::Plaintext).unwrap();
+        let alert = (AlertLevel::Fatal, AlertDescription::HandshakeFailure);
+        assert_eq!(decode_alert_record(&rec), Ok(alert));
+        assert_eq!(rec, alert_record(AlertDescription::HandshakeFailure, 0x0303));
+        let hs = Record { content_type: ContentType::Handshake, ..rec };
+        assert_eq!(decode_alert_record(&hs), Err(AlertError::NotAnAlert(ContentType::Handshake)));
+    }
+}
@@ -0,0 +1,88 @@
+//! proptest strategies for the TLS 1.2 machine, for property-testing
+//! implementations against it.
+
+use crate::tls12::{MessageType, Peer, ProtocolOptions, Trace};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+use std::ops::Range;
+
+/// Only consistent options, drawn from `ProtocolOptions::all_valid`.
+impl Arbitrary for ProtocolOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<ProtocolOptions>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        select(ProtocolOptions::all_valid().collect::<Vec<_>>()).boxed()
+    }
+}
+
+impl Arbitrary for MessageType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MessageType>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        select(MessageType::ALL.to_vec()).boxed()
+    }
+}
+
+impl Arbitrary for Peer {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Peer>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        select(vec![Peer::Client, Peer::Server]).boxed()
+    }
+}
+
+/// Options together with the handshake they describe.
+pub fn handshakes() -> impl Strategy<Value = (ProtocolOptions, Trace)> {
+    any::<ProtocolOptions>().prop_map(|opts| (opts, Trace::linearise(opts)))
+}
+
+/// Sequences of messages with no regard for the protocol, `len` long, for
+/// checking that an implementation refuses what it should.
+pub fn message_sequences(len: Range<usize>) -> impl Strategy<Value = Vec<(Peer, MessageType)>> {
+    vec((any::<Peer>(), any::<MessageType>()), len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tls12::ServerMachine;
+
+    proptest! {
+        #[test]
+        fn options_are_valid(opts in any::<ProtocolOptions>()) {
+            prop_assert_eq!(opts.validate(), Ok(()));
+        }
+
+        #[test]
+        fn handshake_is_the_trace((opts, trace) in handshakes()) {
+            prop_assert_eq!(trace, Trace::linearise(opts));
+        }
+
+        // The server takes each message the client is meant to send next, and
+        // nothing else.
+        #[test]
+        fn server_refuses_the_unexpected(
+            opts in any::<ProtocolOptions>(),
+            sequence in message_sequences(0..20),
+        ) {
+            let expected = Trace::linearise(opts)
+                .steps()
+                .iter()
+                .filter(|(_, peer, _)| *peer == Peer::Client)
+                .map(|(_, _, msg)| *msg)
+                .collect::<Vec<_>>();
+            let mut server = ServerMachine::new(opts);
+            for (i, (_, msg)) in sequence.into_iter().enumerate() {
+                let accepted = server.on_receive(msg).is_ok();
+                prop_assert_eq!(accepted, expected.get(i) == Some(&msg));
+                if !accepted {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,8 @@
 use hacspec::*;
 hacspec_imports!();
 
+use crate::hash::HashSpec;
+
 type IV = [u64; 8];
 type Counter = [u64; 2];
 bytes!(Buffer, 128);
@@ -143,3 +145,14 @@ pub fn blake2b(data: Bytes) -> Digest {
     h = compress(h, m, t, true);
     h.into()
 }
+
+pub struct Blake2b;
+
+impl HashSpec for Blake2b {
+    const BLOCK_LEN: usize = 128;
+    const HASH_LEN: usize = 64;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&blake2b(msg)[..])
+    }
+}
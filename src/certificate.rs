@@ -0,0 +1,842 @@
+use crate::codec::{self, CodecError, Reader};
+use crate::extensions::{self, Extension, ExtensionError};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// The upper bound of a certificate and of the whole certificate_list.
+const MAX_LIST_LEN: usize = (1 << 24) - 1;
+
+/// The TLS 1.2 Certificate message body (RFC 5246, 7.4.2):
+///
+/// ```text
+/// opaque ASN.1Cert<1..2^24-1>;
+///
+/// struct {
+///     ASN.1Cert certificate_list<0..2^24-1>;
+/// } Certificate;
+/// ```
+///
+/// The sender's certificate comes first, and each following one certifies
+/// the one before it. A client without a suitable certificate sends an
+/// empty list.
+pub fn encode_certificate(chain: &[Vec<u8>]) -> Result<Vec<u8>, CodecError> {
+    let mut list = Vec::new();
+    for cert in chain {
+        codec::write_u24_vec(&mut list, 1, MAX_LIST_LEN, "ASN.1Cert", cert)?;
+    }
+    let mut out = Vec::with_capacity(3 + list.len());
+    codec::write_u24_vec(&mut out, 0, MAX_LIST_LEN, "certificate_list", &list)?;
+    Ok(out)
+}
+
+pub fn decode_certificate(body: &[u8]) -> Result<Vec<Vec<u8>>, CodecError> {
+    let mut r = Reader::new(body);
+    let mut list = Reader::new(r.read_u24_vec(0, MAX_LIST_LEN, "certificate_list")?);
+    r.end()?;
+    let mut chain = Vec::new();
+    while !list.is_empty() {
+        chain.push(list.read_u24_vec(1, MAX_LIST_LEN, "ASN.1Cert")?.to_vec());
+    }
+    Ok(chain)
+}
+
+/// What a Certificate message carries, as the client_certificate_type and
+/// server_certificate_type extensions negotiate it (RFC 7250, 3). Without
+/// them it is X.509. OpenPGP (RFC 6091) is left out, as TLS 1.3 forbids it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CertificateType {
+    X509,
+    RawPublicKey,
+}
+
+impl CertificateType {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            CertificateType::X509 => 0,
+            CertificateType::RawPublicKey => 2,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<CertificateType> {
+        match v {
+            0 => Some(CertificateType::X509),
+            2 => Some(CertificateType::RawPublicKey),
+            _ => None,
+        }
+    }
+}
+
+/// The data of a client's certificate type extension, its types in order
+/// of preference: `CertificateType certificate_types<1..2^8-1>`. A server
+/// answers with the one type it picked, as a single byte.
+pub fn encode_certificate_types(types: &[CertificateType]) -> Result<Vec<u8>, CodecError> {
+    let types = types.iter().map(|t| t.to_u8()).collect::<Vec<_>>();
+    let mut out = Vec::with_capacity(1 + types.len());
+    codec::write_u8_vec(&mut out, 1, 0xff, "certificate_types", &types)?;
+    Ok(out)
+}
+
+/// Decodes a client's list, leaving out the types this crate doesn't know.
+pub fn decode_certificate_types(data: &[u8]) -> Result<Vec<CertificateType>, CodecError> {
+    let mut r = Reader::new(data);
+    let types = r.read_u8_vec(1, 0xff, "certificate_types")?;
+    r.end()?;
+    Ok(types.iter().filter_map(|&t| CertificateType::from_u8(t)).collect())
+}
+
+/// The type a server picks for one direction: X.509 if the client sent no
+/// extension for it, else the first of the client's types it supports.
+/// `None` means the handshake fails with unsupported_certificate (RFC
+/// 7250, 4.2).
+pub fn negotiate_certificate_type(
+    offered: Option<&[CertificateType]>,
+    supported: &[CertificateType],
+) -> Option<CertificateType> {
+    match offered {
+        None => Some(CertificateType::X509).filter(|t| supported.contains(t)),
+        Some(offered) => offered.iter().copied().find(|t| supported.contains(t)),
+    }
+}
+
+/// The TLS 1.2 Certificate message body of a raw public key (RFC 7250,
+/// 3): the DER SubjectPublicKeyInfo alone, in place of the chain.
+///
+/// ```text
+/// opaque ASN.1_subjectPublicKeyInfo<1..2^24-1>;
+/// ```
+///
+/// A TLS 1.3 Certificate carries it as the cert_data of its only entry.
+pub fn encode_raw_public_key(spki: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut out = Vec::with_capacity(3 + spki.len());
+    codec::write_u24_vec(&mut out, 1, MAX_LIST_LEN, "ASN.1_subjectPublicKeyInfo", spki)?;
+    Ok(out)
+}
+
+pub fn decode_raw_public_key(body: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut r = Reader::new(body);
+    let spki = r.read_u24_vec(1, MAX_LIST_LEN, "ASN.1_subjectPublicKeyInfo")?.to_vec();
+    r.end()?;
+    Ok(spki)
+}
+
+/// How long the TLS 1.2 Certificate body is, without encoding it: each
+/// certificate of an X.509 chain takes three bytes of length besides its
+/// own, and the list three more. A raw public key is the chain's first
+/// entry, the SubjectPublicKeyInfo, on its own.
+pub fn certificate_len(certificate_type: CertificateType, chain: &[Vec<u8>]) -> usize {
+    match certificate_type {
+        CertificateType::X509 => 3 + chain.iter().map(|c| 3 + c.len()).sum::<usize>(),
+        CertificateType::RawPublicKey => 3 + chain.first().map_or(0, |k| k.len()),
+    }
+}
+
+/// What the URLs of a CertificateURL point to (RFC 6066, 5): each to one
+/// DER certificate, or all together to a single PkiPath.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CertChainType {
+    IndividualCerts,
+    PkiPath,
+}
+
+/// ```text
+/// struct {
+///     opaque url<1..2^16-1>;
+///     uint8 padding;
+///     opaque SHA1Hash[20];
+/// } URLAndHash;
+/// ```
+///
+/// The padding byte is always 1, where RFC 3546 had the hash optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlAndHash {
+    pub url: Vec<u8>,
+    pub sha1_hash: [u8; 20],
+}
+
+/// The CertificateURL message a client that negotiated
+/// client_certificate_url may send in place of its Certificate:
+///
+/// ```text
+/// struct {
+///     CertChainType type;
+///     URLAndHash url_and_hash_list<1..2^16-1>;
+/// } CertificateURL;
+/// ```
+///
+/// The server fetches each URL and checks what it got against the hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateUrl {
+    pub chain_type: CertChainType,
+    pub url_and_hash_list: Vec<UrlAndHash>,
+}
+
+/// Why a CertificateURL couldn't be decoded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CertificateUrlError {
+    Codec(CodecError),
+    /// The type isn't one of `CertChainType`.
+    UnknownChainType(u8),
+    /// The padding byte isn't 1.
+    BadPadding(u8),
+}
+
+impl fmt::Display for CertificateUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CertificateUrlError::Codec(e) => e.fmt(f),
+            CertificateUrlError::UnknownChainType(t) => write!(f, "unknown chain type {}", t),
+            CertificateUrlError::BadPadding(p) => write!(f, "URLAndHash padding {}", p),
+        }
+    }
+}
+
+impl std::error::Error for CertificateUrlError {}
+
+impl From<CodecError> for CertificateUrlError {
+    fn from(e: CodecError) -> CertificateUrlError {
+        CertificateUrlError::Codec(e)
+    }
+}
+
+impl CertificateUrlError {
+    /// The alert the server answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            CertificateUrlError::UnknownChainType(_) => AlertDescription::IllegalParameter,
+            _ => AlertDescription::DecodeError,
+        }
+    }
+}
+
+const URL_AND_HASH_PADDING: u8 = 1;
+
+pub fn encode_certificate_url(msg: &CertificateUrl) -> Result<Vec<u8>, CodecError> {
+    let mut list = Vec::new();
+    for entry in &msg.url_and_hash_list {
+        codec::write_u16_vec(&mut list, 1, 0xffff, "url", &entry.url)?;
+        list.push(URL_AND_HASH_PADDING);
+        list.extend_from_slice(&entry.sha1_hash);
+    }
+    let chain_type = match msg.chain_type {
+        CertChainType::IndividualCerts => 0,
+        CertChainType::PkiPath => 1,
+    };
+    let mut out = vec![chain_type];
+    codec::write_u16_vec(&mut out, 1, 0xffff, "url_and_hash_list", &list)?;
+    Ok(out)
+}
+
+pub fn decode_certificate_url(body: &[u8]) -> Result<CertificateUrl, CertificateUrlError> {
+    let mut r = Reader::new(body);
+    let chain_type = match r.read_u8()? {
+        0 => CertChainType::IndividualCerts,
+        1 => CertChainType::PkiPath,
+        t => return Err(CertificateUrlError::UnknownChainType(t)),
+    };
+    let mut list = Reader::new(r.read_u16_vec(1, 0xffff, "url_and_hash_list")?);
+    r.end()?;
+    let mut url_and_hash_list = Vec::new();
+    while !list.is_empty() {
+        let url = list.read_u16_vec(1, 0xffff, "url")?.to_vec();
+        let padding = list.read_u8()?;
+        if padding != URL_AND_HASH_PADDING {
+            return Err(CertificateUrlError::BadPadding(padding));
+        }
+        let mut sha1_hash = [0; 20];
+        sha1_hash.copy_from_slice(list.take(20)?);
+        url_and_hash_list.push(UrlAndHash { url, sha1_hash });
+    }
+    Ok(CertificateUrl { chain_type, url_and_hash_list })
+}
+
+/// A certificate of a TLS 1.3 Certificate message, with its extensions
+/// (RFC 8446, 4.4.2):
+///
+/// ```text
+/// struct {
+///     opaque cert_data<1..2^24-1>;
+///     Extension extensions<0..2^16-1>;
+/// } CertificateEntry;
+/// ```
+///
+/// With raw public keys the list holds a single entry, whose cert_data is
+/// the SubjectPublicKeyInfo (RFC 7250, 3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateEntry {
+    pub cert_data: Vec<u8>,
+    /// status_request and signed_certificate_timestamp, as a rule.
+    pub extensions: Vec<Extension>,
+}
+
+/// The TLS 1.3 Certificate message body:
+///
+/// ```text
+/// struct {
+///     opaque certificate_request_context<0..2^8-1>;
+///     CertificateEntry certificate_list<0..2^24-1>;
+/// } Certificate;
+/// ```
+///
+/// The context is empty in the main handshake, and echoes the
+/// CertificateRequest's in answer to one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tls13Certificate {
+    pub certificate_request_context: Vec<u8>,
+    pub certificate_list: Vec<CertificateEntry>,
+}
+
+impl Tls13Certificate {
+    /// The certificates without their extensions, in the TLS 1.2 form.
+    pub fn chain(&self) -> Vec<Vec<u8>> {
+        self.certificate_list.iter().map(|e| e.cert_data.clone()).collect()
+    }
+}
+
+pub fn encode_tls13_certificate(msg: &Tls13Certificate) -> Result<Vec<u8>, ExtensionError> {
+    let mut list = Vec::new();
+    for entry in &msg.certificate_list {
+        codec::write_u24_vec(&mut list, 1, MAX_LIST_LEN, "cert_data", &entry.cert_data)?;
+        list.extend(extensions::encode_extensions(&entry.extensions)?);
+    }
+    let mut out = Vec::new();
+    let context = &msg.certificate_request_context;
+    codec::write_u8_vec(&mut out, 0, 0xff, "certificate_request_context", context)?;
+    codec::write_u24_vec(&mut out, 0, MAX_LIST_LEN, "certificate_list", &list)?;
+    Ok(out)
+}
+
+pub fn decode_tls13_certificate(body: &[u8]) -> Result<Tls13Certificate, ExtensionError> {
+    let mut r = Reader::new(body);
+    let context = r.read_u8_vec(0, 0xff, "certificate_request_context")?.to_vec();
+    let mut list = Reader::new(r.read_u24_vec(0, MAX_LIST_LEN, "certificate_list")?);
+    r.end()?;
+    let mut certificate_list = Vec::new();
+    while !list.is_empty() {
+        let cert_data = list.read_u24_vec(1, MAX_LIST_LEN, "cert_data")?.to_vec();
+        let (extensions, len) = extensions::decode_extensions(list.rest())?;
+        list.take(len)?;
+        certificate_list.push(CertificateEntry { cert_data, extensions });
+    }
+    Ok(Tls13Certificate { certificate_request_context: context, certificate_list })
+}
+
+/// The algorithms of certificate compression (RFC 8879, 3 and 7.3).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CertificateCompressionAlgorithm {
+    Zlib,
+    Brotli,
+    Zstd,
+}
+
+impl CertificateCompressionAlgorithm {
+    pub(crate) const ALL: [CertificateCompressionAlgorithm; 3] = {
+        use CertificateCompressionAlgorithm::*;
+        [Zlib, Brotli, Zstd]
+    };
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CertificateCompressionAlgorithm::Zlib => 1,
+            CertificateCompressionAlgorithm::Brotli => 2,
+            CertificateCompressionAlgorithm::Zstd => 3,
+        }
+    }
+
+    pub fn from_u16(v: u16) -> Option<CertificateCompressionAlgorithm> {
+        CertificateCompressionAlgorithm::ALL.iter().copied().find(|a| a.to_u16() == v)
+    }
+}
+
+/// The data of a compress_certificate extension, which a client sends in
+/// its ClientHello and a server in its CertificateRequest to take the
+/// other's Certificate compressed:
+/// `CertificateCompressionAlgorithm algorithms<2..2^8-2>`.
+pub fn encode_compression_algorithms(
+    algorithms: &[CertificateCompressionAlgorithm],
+) -> Result<Vec<u8>, CodecError> {
+    let data = algorithms.iter().flat_map(|a| a.to_u16().to_be_bytes()).collect::<Vec<_>>();
+    let mut out = Vec::with_capacity(1 + data.len());
+    codec::write_u8_vec(&mut out, 2, 0xfe, "algorithms", &data)?;
+    Ok(out)
+}
+
+/// Decodes the list, leaving out the algorithms this crate doesn't know.
+pub fn decode_compression_algorithms(
+    data: &[u8],
+) -> Result<Vec<CertificateCompressionAlgorithm>, CodecError> {
+    let mut r = Reader::new(data);
+    let list = r.read_u8_vec(2, 0xfe, "algorithms")?;
+    r.end()?;
+    if list.len() % 2 != 0 {
+        return Err(CodecError::BadLength("algorithms"));
+    }
+    let algorithms = list.chunks(2).map(|v| u16::from_be_bytes([v[0], v[1]]));
+    Ok(algorithms.filter_map(CertificateCompressionAlgorithm::from_u16).collect())
+}
+
+/// An implementation of one of the algorithms, which this crate leaves to
+/// its users.
+pub trait CertificateCompression {
+    const ALGORITHM: CertificateCompressionAlgorithm;
+
+    fn compress(input: &[u8]) -> Vec<u8>;
+
+    /// `None` if `input` is malformed or would decompress to more than
+    /// `max_len` bytes.
+    fn decompress(input: &[u8], max_len: usize) -> Option<Vec<u8>>;
+}
+
+/// The CompressedCertificate message that takes the place of a TLS 1.3
+/// Certificate (RFC 8879, 4):
+///
+/// ```text
+/// struct {
+///     CertificateCompressionAlgorithm algorithm;
+///     uint24 uncompressed_length;
+///     opaque compressed_certificate_message<1..2^24-1>;
+/// } CompressedCertificate;
+/// ```
+///
+/// What's compressed is the Certificate body, without its handshake
+/// header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedCertificate {
+    pub algorithm: CertificateCompressionAlgorithm,
+    pub uncompressed_length: usize,
+    pub compressed_certificate_message: Vec<u8>,
+}
+
+/// Why a CompressedCertificate couldn't be decoded or decompressed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CompressedCertificateError {
+    Codec(CodecError),
+    /// The algorithm isn't one of `CertificateCompressionAlgorithm`.
+    UnknownAlgorithm(u16),
+    /// The algorithm isn't the one being decompressed with, so it wasn't
+    /// offered.
+    UnexpectedAlgorithm(CertificateCompressionAlgorithm),
+    /// The message doesn't decompress to uncompressed_length bytes.
+    BadCompression,
+}
+
+impl fmt::Display for CompressedCertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressedCertificateError::Codec(e) => e.fmt(f),
+            CompressedCertificateError::UnknownAlgorithm(a) => {
+                write!(f, "unknown certificate compression algorithm {}", a)
+            }
+            CompressedCertificateError::UnexpectedAlgorithm(a) => {
+                write!(f, "certificate compressed with {:?}", a)
+            }
+            CompressedCertificateError::BadCompression => {
+                write!(f, "certificate doesn't decompress")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompressedCertificateError {}
+
+impl From<CodecError> for CompressedCertificateError {
+    fn from(e: CodecError) -> CompressedCertificateError {
+        CompressedCertificateError::Codec(e)
+    }
+}
+
+impl CompressedCertificateError {
+    /// The alert the receiver answers with (RFC 8879, 4).
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            CompressedCertificateError::Codec(_) => AlertDescription::DecodeError,
+            CompressedCertificateError::UnknownAlgorithm(_)
+            | CompressedCertificateError::UnexpectedAlgorithm(_) => {
+                AlertDescription::IllegalParameter
+            }
+            CompressedCertificateError::BadCompression => AlertDescription::BadCertificate,
+        }
+    }
+}
+
+pub fn encode_compressed_certificate(msg: &CompressedCertificate) -> Result<Vec<u8>, CodecError> {
+    let compressed = &msg.compressed_certificate_message;
+    let mut out = Vec::with_capacity(compressed_certificate_len(compressed.len()));
+    codec::write_u16(&mut out, msg.algorithm.to_u16());
+    codec::write_u24(&mut out, msg.uncompressed_length)?;
+    codec::write_u24_vec(&mut out, 1, MAX_LIST_LEN, "compressed_certificate_message", compressed)?;
+    Ok(out)
+}
+
+pub fn decode_compressed_certificate(
+    body: &[u8],
+) -> Result<CompressedCertificate, CompressedCertificateError> {
+    let mut r = Reader::new(body);
+    let algorithm = r.read_u16()?;
+    let algorithm = CertificateCompressionAlgorithm::from_u16(algorithm)
+        .ok_or(CompressedCertificateError::UnknownAlgorithm(algorithm))?;
+    let uncompressed_length = r.read_u24()? as usize;
+    let compressed = r.read_u24_vec(1, MAX_LIST_LEN, "compressed_certificate_message")?;
+    r.end()?;
+    Ok(CompressedCertificate {
+        algorithm,
+        uncompressed_length,
+        compressed_certificate_message: compressed.to_vec(),
+    })
+}
+
+/// Compresses a TLS 1.3 Certificate body with `C`.
+pub fn compress_certificate<C: CertificateCompression>(
+    certificate: &[u8],
+) -> CompressedCertificate {
+    CompressedCertificate {
+        algorithm: C::ALGORITHM,
+        uncompressed_length: certificate.len(),
+        compressed_certificate_message: C::compress(certificate),
+    }
+}
+
+/// The Certificate body of `msg`, which must have been compressed with `C`
+/// and decompress to exactly its uncompressed_length.
+pub fn decompress_certificate<C: CertificateCompression>(
+    msg: &CompressedCertificate,
+) -> Result<Vec<u8>, CompressedCertificateError> {
+    if msg.algorithm != C::ALGORITHM {
+        return Err(CompressedCertificateError::UnexpectedAlgorithm(msg.algorithm));
+    }
+    C::decompress(&msg.compressed_certificate_message, msg.uncompressed_length)
+        .filter(|certificate| certificate.len() == msg.uncompressed_length)
+        .ok_or(CompressedCertificateError::BadCompression)
+}
+
+/// How long a CompressedCertificate body is, given how long its compressed
+/// Certificate is: the algorithm, the uncompressed length and the
+/// compressed data's own length take eight bytes.
+pub fn compressed_certificate_len(compressed_len: usize) -> usize {
+    2 + 3 + 3 + compressed_len
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::handshake;
+
+    // A P-256 chain made with OpenSSL 3.0: example.com, issued by an
+    // intermediate CA, issued by a self-signed root. All are ECDSA with
+    // SHA-256.
+    pub(crate) const LEAF: &str = concat!(
+        "308201b93082015ea003020102020103300a06082a8648ce3d04030230243122302006035504030c19686163",
+        "73706563207465737420696e7465726d656469617465301e170d3236313031343036323233395a170d323930",
+        "3131363036323233395a30163114301206035504030c0b6578616d706c652e636f6d3059301306072a8648ce",
+        "3d020106082a8648ce3d03010703420004def9cdd158279fd27e2a80c996d93b2577fcc3e4af026f9f868177",
+        "177c020b0cdc3b945424971fc1a122e4ccd74ef8bf7334bc308a11ebcaf330030b368edaf8a3818e30818b30",
+        "0c0603551d130101ff04023000300e0603551d0f0101ff04040302078030130603551d25040c300a06082b06",
+        "01050507030130160603551d11040f300d820b6578616d706c652e636f6d301f0603551d2304183016801452",
+        "4012bffb573f030f5854f790876f1b0779d1ee301d0603551d0e041604146564c5acaa4b72d335cce8839a66",
+        "dc30f54c30a6300a06082a8648ce3d04030203490030460221009190689bdf53274dd6dddc68d03a2da0c09d",
+        "393cfbbca4afbe715cb0a0ffc52b022100f2fe821e4f53f73134ea9d91580c1ef26591a90203cb592cd2d8f7",
+        "437db78e5f",
+    );
+    pub(crate) const INTERMEDIATE: &str = concat!(
+        "308201943082013ba003020102020102300a06082a8648ce3d040302301c311a301806035504030c11686163",
+        "73706563207465737420726f6f74301e170d3236313031343036323233395a170d3336313031313036323233",
+        "395a30243122302006035504030c1968616373706563207465737420696e7465726d65646961746530593013",
+        "06072a8648ce3d020106082a8648ce3d03010703420004e86eb53d0d061cbfbc74eafc42a9d129966607277b",
+        "686497befcc7bb85dd1ed0cb6aba69d0203759ef630387764f13cc04625d3e34a9240d9ec47411881a4324a3",
+        "66306430120603551d130101ff040830060101ff020100300e0603551d0f0101ff040403020106301d060355",
+        "1d0e04160414524012bffb573f030f5854f790876f1b0779d1ee301f0603551d230418301680149124f2e48d",
+        "50170cac7100e57f08fbeea5b18753300a06082a8648ce3d040302034700304402203ef2a37b37f05ad659ab",
+        "81dd1705321ca292e0d080ff7610b7d88c951788da7902203ef82f85edd960d812d1f999cec12160c8e3f14b",
+        "ef4da60f3d4812bdf4079922",
+    );
+    pub(crate) const ROOT: &str = concat!(
+        "3082016a3082010fa003020102020101300a06082a8648ce3d040302301c311a301806035504030c11686163",
+        "73706563207465737420726f6f74301e170d3236313031343036323233395a170d3436313030393036323233",
+        "395a301c311a301806035504030c1168616373706563207465737420726f6f743059301306072a8648ce3d02",
+        "0106082a8648ce3d030107034200047e96e75b4be6cc41103e2a97d3a2ec1715573e5367e81864bb1471f237",
+        "79b70cbd327b4a4405d3d640375dc4f89b65f539bc4469fd9f93d970b34f751db03f71a3423040300f060355",
+        "1d130101ff040530030101ff300e0603551d0f0101ff040403020106301d0603551d0e041604149124f2e48d",
+        "50170cac7100e57f08fbeea5b18753300a06082a8648ce3d0403020349003046022100a725b154855bdaa061",
+        "4cda5608c07f9b80faa1a0a254788267bbb6c53686c16b022100d7188426ad8ed0299b1a04f6122cb922f35e",
+        "eb3c61a20d4405b6cf7eeb95dfe3",
+    );
+
+    // The leaf's SubjectPublicKeyInfo, as a raw public key.
+    const LEAF_SPKI: &str = concat!(
+        "3059301306072a8648ce3d020106082a8648ce3d03010703420004def9cdd158279fd27e2a80c996d93b2577",
+        "fcc3e4af026f9f868177177c020b0cdc3b945424971fc1a122e4ccd74ef8bf7334bc308a11ebcaf330030b36",
+        "8edaf8",
+    );
+
+    const LEAF_SHA1: &str = "1660b3e2fd1cab0c0e91e77b371cb2a36a72ac89";
+    const INTERMEDIATE_SHA1: &str = "5341fda0eefb7ce69972cd7217b93ef199d48201";
+
+    pub(crate) fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn u24(v: usize) -> Vec<u8> {
+        (v as u32).to_be_bytes()[1..].to_vec()
+    }
+
+    #[test]
+    fn tls12_chain() {
+        let chain = vec![hex(LEAF), hex(INTERMEDIATE)];
+        let body = encode_certificate(&chain).unwrap();
+
+        // Byte for byte the Certificate message OpenSSL 3.0 sent for this
+        // chain in a TLS 1.2 handshake.
+        let mut captured = vec![11, 0x00, 0x03, 0x5e, 0x00, 0x03, 0x5b, 0x00, 0x01, 0xbd];
+        captured.extend(hex(LEAF));
+        captured.extend(&[0x00, 0x01, 0x98]);
+        captured.extend(hex(INTERMEDIATE));
+        assert_eq!(handshake::frame(11, &body), Ok(captured));
+        assert_eq!(decode_certificate(&body), Ok(chain));
+
+        let full = vec![hex(LEAF), hex(INTERMEDIATE), hex(ROOT)];
+        assert_eq!(decode_certificate(&encode_certificate(&full).unwrap()), Ok(full));
+        assert_eq!(encode_certificate(&[]), Ok(vec![0, 0, 0]));
+        assert_eq!(decode_certificate(&[0, 0, 0]), Ok(vec![]));
+    }
+
+    #[test]
+    fn tls12_malformed() {
+        let body = encode_certificate(&[hex(LEAF)]).unwrap();
+        assert_eq!(decode_certificate(&body[..body.len() - 1]), Err(CodecError::Truncated));
+        let mut trailing = body.clone();
+        trailing.push(0);
+        assert_eq!(decode_certificate(&trailing), Err(CodecError::TrailingBytes));
+        // An empty ASN.1Cert.
+        assert_eq!(
+            decode_certificate(&[0, 0, 3, 0, 0, 0]),
+            Err(CodecError::BadLength("ASN.1Cert"))
+        );
+        assert_eq!(encode_certificate(&[vec![]]), Err(CodecError::BadLength("ASN.1Cert")));
+    }
+
+    #[test]
+    fn certificate_types() {
+        use CertificateType::*;
+        let data = encode_certificate_types(&[RawPublicKey, X509]).unwrap();
+        assert_eq!(data, vec![2, 2, 0]);
+        assert_eq!(decode_certificate_types(&data), Ok(vec![RawPublicKey, X509]));
+        // OpenPGP, and a type of no registry.
+        assert_eq!(decode_certificate_types(&[3, 1, 2, 9]), Ok(vec![RawPublicKey]));
+        let empty = CodecError::BadLength("certificate_types");
+        assert_eq!(decode_certificate_types(&[0]), Err(empty));
+        assert_eq!(encode_certificate_types(&[]), Err(empty));
+
+        assert_eq!(negotiate_certificate_type(None, &[RawPublicKey, X509]), Some(X509));
+        assert_eq!(negotiate_certificate_type(None, &[RawPublicKey]), None);
+        // The client's preference wins.
+        let offered = [RawPublicKey, X509];
+        let picked = negotiate_certificate_type(Some(&offered), &[X509, RawPublicKey]);
+        assert_eq!(picked, Some(RawPublicKey));
+        assert_eq!(negotiate_certificate_type(Some(&offered[1..]), &[RawPublicKey]), None);
+    }
+
+    #[test]
+    fn raw_public_key() {
+        let spki = hex(LEAF_SPKI);
+        let body = encode_raw_public_key(&spki).unwrap();
+        assert_eq!(&body[..3], &[0x00, 0x00, 0x5b]);
+        assert_eq!(&body[3..], &spki[..]);
+        assert_eq!(decode_raw_public_key(&body), Ok(spki.clone()));
+        assert_eq!(decode_raw_public_key(&body[..50]), Err(CodecError::Truncated));
+        let field = "ASN.1_subjectPublicKeyInfo";
+        assert_eq!(decode_raw_public_key(&[0, 0, 0]), Err(CodecError::BadLength(field)));
+        // A chain of one decodes as a raw key too, with the wrong contents:
+        // only the negotiated type tells the two apart.
+        let chain = encode_certificate(std::slice::from_ref(&spki)).unwrap();
+        assert_eq!(decode_raw_public_key(&chain), Ok(body.clone()));
+
+        // The sizes, against the chain of tls12_chain.
+        let chain = vec![hex(LEAF), hex(INTERMEDIATE)];
+        let x509 = encode_certificate(&chain).unwrap().len();
+        assert_eq!(certificate_len(CertificateType::X509, &chain), x509);
+        assert_eq!(x509, 0x35e);
+        let raw = certificate_len(CertificateType::RawPublicKey, std::slice::from_ref(&spki));
+        assert_eq!(raw, body.len());
+        assert_eq!(raw, 94);
+
+        let entry = CertificateEntry { cert_data: spki, extensions: vec![] };
+        let msg =
+            Tls13Certificate { certificate_request_context: vec![], certificate_list: vec![entry] };
+        let body = encode_tls13_certificate(&msg).unwrap();
+        assert_eq!(body.len(), 1 + 3 + raw + 2);
+        assert_eq!(decode_tls13_certificate(&body), Ok(msg));
+    }
+
+    #[test]
+    fn certificate_url() {
+        // The leaf and intermediate of tls12_chain, at made-up URLs, with
+        // their SHA-1 hashes.
+        let entry = |url: &str, hash: &str| {
+            let mut sha1_hash = [0; 20];
+            sha1_hash.copy_from_slice(&hex(hash));
+            UrlAndHash { url: url.as_bytes().to_vec(), sha1_hash }
+        };
+        let msg = CertificateUrl {
+            chain_type: CertChainType::IndividualCerts,
+            url_and_hash_list: vec![
+                entry("http://example.com/leaf.der", LEAF_SHA1),
+                entry("http://example.com/ca.der", INTERMEDIATE_SHA1),
+            ],
+        };
+        let body = encode_certificate_url(&msg).unwrap();
+        assert_eq!(&body[..5], &[0, 0, 98, 0, 27]);
+        assert_eq!(&body[32..33], &[1]);
+        assert_eq!(body.len(), 3 + 2 * (2 + 1 + 20) + 27 + 25);
+        assert_eq!(decode_certificate_url(&body), Ok(msg.clone()));
+        assert_eq!(handshake::frame(21, &body).unwrap()[..4], [21, 0, 0, 101]);
+
+        for len in 0..body.len() {
+            assert!(decode_certificate_url(&body[..len]).is_err());
+        }
+        let mut bad = body.clone();
+        bad[0] = 2;
+        let unknown = CertificateUrlError::UnknownChainType(2);
+        assert_eq!(decode_certificate_url(&bad), Err(unknown));
+        assert_eq!(unknown.alert(), AlertDescription::IllegalParameter);
+        let mut bad = body;
+        bad[32] = 0;
+        assert_eq!(decode_certificate_url(&bad), Err(CertificateUrlError::BadPadding(0)));
+        let empty = CertificateUrl { url_and_hash_list: vec![], ..msg };
+        let field = CodecError::BadLength("url_and_hash_list");
+        assert_eq!(encode_certificate_url(&empty), Err(field));
+    }
+
+    #[test]
+    fn tls13_chain() {
+        // The leaf with an OCSP response and an SCT list, as a server
+        // staples them.
+        let leaf = CertificateEntry {
+            cert_data: hex(LEAF),
+            extensions: vec![
+                Extension { extension_type: 5, data: vec![1, 0, 0, 2, 0x30, 0x00] },
+                Extension { extension_type: 18, data: vec![0, 0] },
+            ],
+        };
+        let intermediate = CertificateEntry { cert_data: hex(INTERMEDIATE), extensions: vec![] };
+        let msg = Tls13Certificate {
+            certificate_request_context: vec![],
+            certificate_list: vec![leaf, intermediate],
+        };
+        let body = encode_tls13_certificate(&msg).unwrap();
+
+        let leaf_len = 3 + hex(LEAF).len() + 2 + 4 + 6 + 4 + 2;
+        let list_len = leaf_len + 3 + hex(INTERMEDIATE).len() + 2;
+        let mut expected = vec![0];
+        expected.extend(u24(list_len));
+        expected.extend(u24(hex(LEAF).len()));
+        expected.extend(hex(LEAF));
+        expected.extend(&[0, 16, 0, 5, 0, 6, 1, 0, 0, 2, 0x30, 0x00, 0, 18, 0, 2, 0, 0]);
+        expected.extend(u24(hex(INTERMEDIATE).len()));
+        expected.extend(hex(INTERMEDIATE));
+        expected.extend(&[0, 0]);
+        assert_eq!(body, expected);
+        assert_eq!(decode_tls13_certificate(&body), Ok(msg.clone()));
+        assert_eq!(msg.chain(), vec![hex(LEAF), hex(INTERMEDIATE)]);
+
+        // In answer to a CertificateRequest, with its context.
+        let answer = Tls13Certificate { certificate_request_context: vec![7; 32], ..msg };
+        let body = encode_tls13_certificate(&answer).unwrap();
+        assert_eq!(decode_tls13_certificate(&body), Ok(answer));
+    }
+
+    // Stands in for Brotli: runs of a byte as (count, byte) pairs.
+    struct RunLength;
+
+    impl CertificateCompression for RunLength {
+        const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::Brotli;
+
+        fn compress(input: &[u8]) -> Vec<u8> {
+            let mut out: Vec<u8> = Vec::new();
+            for &b in input {
+                match out.len() {
+                    n if n >= 2 && out[n - 1] == b && out[n - 2] < 0xff => out[n - 2] += 1,
+                    _ => out.extend_from_slice(&[1, b]),
+                }
+            }
+            out
+        }
+
+        fn decompress(input: &[u8], max_len: usize) -> Option<Vec<u8>> {
+            let mut out = Vec::new();
+            for pair in input.chunks(2) {
+                if pair.len() != 2 || out.len() + pair[0] as usize > max_len {
+                    return None;
+                }
+                out.resize(out.len() + pair[0] as usize, pair[1]);
+            }
+            Some(out)
+        }
+    }
+
+    #[test]
+    fn compressed_certificate() {
+        use CertificateCompressionAlgorithm::*;
+        let algorithms = encode_compression_algorithms(&[Brotli, Zlib]).unwrap();
+        assert_eq!(algorithms, vec![4, 0, 2, 0, 1]);
+        assert_eq!(decode_compression_algorithms(&algorithms), Ok(vec![Brotli, Zlib]));
+        assert_eq!(decode_compression_algorithms(&[4, 0, 9, 0, 3]), Ok(vec![Zstd]));
+        assert!(decode_compression_algorithms(&[3, 0, 1, 0]).is_err());
+        assert!(encode_compression_algorithms(&[]).is_err());
+
+        // An entry of zeros compresses well.
+        let entry = CertificateEntry { cert_data: vec![0; 600], extensions: vec![] };
+        let msg =
+            Tls13Certificate { certificate_request_context: vec![], certificate_list: vec![entry] };
+        let certificate = encode_tls13_certificate(&msg).unwrap();
+        let compressed = compress_certificate::<RunLength>(&certificate);
+        assert_eq!(compressed.uncompressed_length, 1 + 3 + 3 + 600 + 2);
+        let body = encode_compressed_certificate(&compressed).unwrap();
+        assert_eq!(&body[..5], &[0, 2, 0, 2, 0x61]);
+        assert_eq!(body.len(), compressed_certificate_len(18));
+        assert!(body.len() < certificate.len());
+        assert_eq!(decode_compressed_certificate(&body), Ok(compressed.clone()));
+        assert_eq!(decompress_certificate::<RunLength>(&compressed), Ok(certificate));
+        assert_eq!(handshake::frame(25, &body).unwrap()[..4], [25, 0, 0, 26]);
+
+        for len in 0..body.len() {
+            assert!(decode_compressed_certificate(&body[..len]).is_err());
+        }
+        let mut bad = body.clone();
+        bad[1] = 4;
+        let unknown = CompressedCertificateError::UnknownAlgorithm(4);
+        assert_eq!(decode_compressed_certificate(&bad), Err(unknown));
+        assert_eq!(unknown.alert(), AlertDescription::IllegalParameter);
+        let zlib = CompressedCertificate { algorithm: Zlib, ..compressed.clone() };
+        let unexpected = CompressedCertificateError::UnexpectedAlgorithm(Zlib);
+        assert_eq!(decompress_certificate::<RunLength>(&zlib), Err(unexpected));
+        let short = CompressedCertificate { uncompressed_length: 100, ..compressed.clone() };
+        let err = decompress_certificate::<RunLength>(&short).unwrap_err();
+        assert_eq!(err, CompressedCertificateError::BadCompression);
+        assert_eq!(err.alert(), AlertDescription::BadCertificate);
+        let long = CompressedCertificate { uncompressed_length: 1000, ..compressed };
+        assert_eq!(decompress_certificate::<RunLength>(&long), Err(err));
+    }
+
+    #[test]
+    fn tls13_malformed() {
+        let entry = CertificateEntry { cert_data: hex(ROOT), extensions: vec![] };
+        let msg = Tls13Certificate {
+            certificate_request_context: vec![],
+            certificate_list: vec![entry.clone()],
+        };
+        let body = encode_tls13_certificate(&msg).unwrap();
+        for len in 0..body.len() {
+            assert!(decode_tls13_certificate(&body[..len]).is_err());
+        }
+        // The TLS 1.2 form, without the context and extensions.
+        let tls12 = encode_certificate(&[hex(ROOT)]).unwrap();
+        assert!(decode_tls13_certificate(&tls12).is_err());
+
+        let ext = Extension { extension_type: 5, data: vec![] };
+        let duplicated = CertificateEntry { extensions: vec![ext.clone(), ext], ..entry };
+        let msg = Tls13Certificate { certificate_list: vec![duplicated], ..msg };
+        assert_eq!(encode_tls13_certificate(&msg), Err(ExtensionError::Duplicate(5)));
+    }
+}
@@ -93,11 +93,18 @@ pub fn block(key: Key, ctr: u32, iv: IV) -> StateBytes {
     state_to_bytes(state)
 }
 
-pub fn chacha(key: Key, iv: IV, m: Bytes) -> Result<Bytes, String> {
+// The ChaCha20 encryption of RFC 8439, section 2.4: the message XORed with
+// the key stream of the blocks from the initial counter on. Encryption and
+// decryption are the same.
+pub fn chacha20(key: Key, counter: u32, iv: IV, m: Bytes) -> Result<Bytes, String> {
     let l = m.len();
     let n_blocks: usize = l / 64;
     let rem = l % 64;
-    let mut ctr = 1;
+    let used = n_blocks as u64 + if rem != 0 { 1 } else { 0 };
+    if used > (1u64 << 32) - u64::from(counter) {
+        return Err("Message too long for the block counter".to_string());
+    }
+    let mut ctr = counter;
     let mut blocks_out = Bytes::new_len(l);
     for i in 0..n_blocks {
         let key_block = block(key, ctr, iv);
@@ -105,7 +112,7 @@ pub fn chacha(key: Key, iv: IV, m: Bytes) -> Result<Bytes, String> {
             let k = (i * 64) + j;
             blocks_out[k] = m[k] ^ key_block[j];
         }
-        ctr += 1;
+        ctr = ctr.wrapping_add(1);
     }
     // Last block might not be full
     if rem != 0 {
@@ -117,3 +124,8 @@ pub fn chacha(key: Key, iv: IV, m: Bytes) -> Result<Bytes, String> {
     }
     Ok(blocks_out)
 }
+
+// ChaCha20 from counter 1, as the AEAD uses it.
+pub fn chacha(key: Key, iv: IV, m: Bytes) -> Result<Bytes, String> {
+    chacha20(key, 1, iv, m)
+}
@@ -6,6 +6,12 @@ hacspec_imports!();
 use crate::chacha20::*;
 use crate::poly1305::*;
 
+const TAGSIZE: usize = 16;
+
+// The Poly1305 input of RFC 8439, section 2.8:
+//   aad | pad16(aad) | cipher text | pad16(cipher text) |
+//   aad length as a u64 | cipher text length as a u64
+// with the lengths in bytes, little endian.
 // TODO: can we do without borrow?
 fn pad_aad_msg(aad: Bytes, msg: Bytes) -> Bytes {
     let laad = aad.len();
@@ -29,8 +35,7 @@ fn pad_aad_msg(aad: Bytes, msg: Bytes) -> Bytes {
 }
 
 pub fn encrypt(key: Key, iv: IV, aad: Bytes, msg: Bytes) -> Result<(Bytes, Tag), String> {
-    let key_block = block(key, 0, iv);
-    let mac_key = Key::from(&key_block[0..32]);
+    let mac_key = key_gen(key, iv);
     let cipher_text = match chacha(key, iv, msg) {
         Ok(c) => c,
         Err(r) => {
@@ -50,8 +55,7 @@ pub fn decrypt(
     cipher_text: Bytes,
     tag: Tag,
 ) -> Result<Bytes, String> {
-    let key_block = block(key, 0, iv);
-    let mac_key = Key::from_array(key_block.get(0..32));
+    let mac_key = key_gen(key, iv);
     let padded_msg = pad_aad_msg(aad, cipher_text.clone());
     let my_tag = poly(padded_msg, mac_key);
     if my_tag == tag {
@@ -66,3 +70,35 @@ pub fn decrypt(
         Err("Mac verification failed".to_string())
     }
 }
+
+// AEAD record protection as TLS calls it (RFC 5116): a 32 byte key, a 12 byte
+// nonce, and the tag appended to the cipher text.
+pub fn seal(key: Bytes, nonce: Bytes, aad: Bytes, msg: Bytes) -> Result<Bytes, String> {
+    if key.len() != 32 {
+        return Err("Invalid key length".to_string());
+    }
+    if nonce.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
+    let (cipher_text, tag) = encrypt(Key::from(key.raw()), IV::from(nonce.raw()), aad, msg)?;
+    let mut out = Bytes::new_len(cipher_text.len() + TAGSIZE);
+    out.update(0, &cipher_text);
+    out.update_raw(cipher_text.len(), &tag[..]);
+    Ok(out)
+}
+
+pub fn open(key: Bytes, nonce: Bytes, aad: Bytes, cipher_text: Bytes) -> Result<Bytes, String> {
+    if key.len() != 32 {
+        return Err("Invalid key length".to_string());
+    }
+    if nonce.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
+    if cipher_text.len() < TAGSIZE {
+        return Err("Cipher text too short".to_string());
+    }
+    let l = cipher_text.len() - TAGSIZE;
+    let tag = Tag::from(&cipher_text[l..l + TAGSIZE]);
+    let cipher_text = Bytes::from(&cipher_text[0..l]);
+    decrypt(Key::from(key.raw()), IV::from(nonce.raw()), aad, cipher_text, tag)
+}
@@ -0,0 +1,348 @@
+use crate::tls12::{KeyExchange, ProtocolOptions};
+use std::fmt;
+
+/// How the premaster secret is established.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyAgreement {
+    /// The client encrypts it to the server's RSA key.
+    Rsa,
+    /// Static (EC)DH with the key in the server's certificate.
+    Dh,
+    Ecdh,
+    /// Ephemeral (EC)DH, signed by the server unless anonymous.
+    Dhe,
+    Ecdhe,
+    Psk,
+    DhePsk,
+    EcdhePsk,
+    RsaPsk,
+    Srp,
+}
+
+/// How the server is authenticated.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Authentication {
+    Rsa,
+    Dss,
+    Ecdsa,
+    /// Only by knowing the pre-shared key or the SRP verifier.
+    Psk,
+    Srp,
+    Anonymous,
+}
+
+/// The bulk cipher of the record layer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cipher {
+    Rc4,
+    TripleDesCbc,
+    Aes128Cbc,
+    Aes256Cbc,
+    Aes128Gcm,
+    Aes256Gcm,
+    Aes128Ccm,
+    Chacha20Poly1305,
+}
+
+impl Cipher {
+    /// Whether the cipher is an AEAD, which needs no separate MAC.
+    pub fn is_aead(self) -> bool {
+        use Cipher::*;
+        matches!(self, Aes128Gcm | Aes256Gcm | Aes128Ccm | Chacha20Poly1305)
+    }
+
+    pub fn key_len(self) -> usize {
+        use Cipher::*;
+        match self {
+            Rc4 | Aes128Cbc | Aes128Gcm | Aes128Ccm => 16,
+            TripleDesCbc => 24,
+            Aes256Cbc | Aes256Gcm | Chacha20Poly1305 => 32,
+        }
+    }
+}
+
+/// The MAC of the record layer, or `Aead` if the cipher has its own.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mac {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Aead,
+}
+
+/// The hash of the TLS 1.2 PRF and the Finished messages, or of HKDF in
+/// TLS 1.3.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hash {
+    Sha256,
+    Sha384,
+}
+
+/// An entry of the IANA TLS Cipher Suites registry, taken apart. Suites
+/// defined before TLS 1.2 use its default PRF hash, SHA-256 (RFC 5246,
+/// 5).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CipherSuite {
+    pub id: u16,
+    pub name: &'static str,
+    /// The shape of the handshake the suite leads to.
+    pub key_exchange: KeyExchange,
+    pub key_agreement: KeyAgreement,
+    pub authentication: Authentication,
+    pub cipher: Cipher,
+    pub mac: Mac,
+    pub prf_hash: Hash,
+}
+
+impl fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl CipherSuite {
+    /// The options of a handshake under this suite, with everything else
+    /// at its default.
+    pub fn protocol_options(self) -> ProtocolOptions {
+        ProtocolOptions { key_exchange: self.key_exchange, ..ProtocolOptions::default() }
+    }
+}
+
+// The handshake a key agreement and authentication lead to. Other pairs
+// don't occur in the registry, and fail to compile in a table entry.
+const fn shape(key_agreement: KeyAgreement, authentication: Authentication) -> KeyExchange {
+    match (key_agreement, authentication) {
+        (Ka::Rsa, _) => Kx::RsaKem,
+        (Ka::Dh, _) | (Ka::Ecdh, _) => Kx::StaticDh,
+        (Ka::Dhe, Au::Anonymous) | (Ka::Ecdhe, Au::Anonymous) => Kx::DhAnon,
+        (Ka::Dhe, Au::Rsa) => Kx::DheRsa,
+        (Ka::Dhe, Au::Dss) => Kx::DheDss,
+        (Ka::Ecdhe, Au::Rsa) => Kx::EcdheRsa,
+        (Ka::Ecdhe, Au::Ecdsa) => Kx::EcdheEcdsa,
+        (Ka::Psk, _) => Kx::Psk,
+        (Ka::DhePsk, _) | (Ka::EcdhePsk, _) => Kx::DhePsk,
+        (Ka::RsaPsk, _) => Kx::RsaPsk,
+        (Ka::Srp, Au::Srp) => Kx::Srp,
+        (Ka::Srp, Au::Rsa) => Kx::SrpRsa,
+        (Ka::Srp, Au::Dss) => Kx::SrpDss,
+        _ => panic!("no such key exchange"),
+    }
+}
+
+const fn suite(
+    id: u16,
+    name: &'static str,
+    (key_agreement, authentication): (KeyAgreement, Authentication),
+    (cipher, mac, prf_hash): (Cipher, Mac, Hash),
+) -> CipherSuite {
+    let key_exchange = shape(key_agreement, authentication);
+    CipherSuite { id, name, key_exchange, key_agreement, authentication, cipher, mac, prf_hash }
+}
+
+use Authentication as Au;
+use Cipher::*;
+use KeyAgreement as Ka;
+use KeyExchange as Kx;
+
+const RSA: (KeyAgreement, Authentication) = (Ka::Rsa, Au::Rsa);
+const DH_DSS: (KeyAgreement, Authentication) = (Ka::Dh, Au::Dss);
+const DH_RSA: (KeyAgreement, Authentication) = (Ka::Dh, Au::Rsa);
+const DHE_DSS: (KeyAgreement, Authentication) = (Ka::Dhe, Au::Dss);
+const DHE_RSA: (KeyAgreement, Authentication) = (Ka::Dhe, Au::Rsa);
+const DH_ANON: (KeyAgreement, Authentication) = (Ka::Dhe, Au::Anonymous);
+const PSK: (KeyAgreement, Authentication) = (Ka::Psk, Au::Psk);
+const DHE_PSK: (KeyAgreement, Authentication) = (Ka::DhePsk, Au::Psk);
+const RSA_PSK: (KeyAgreement, Authentication) = (Ka::RsaPsk, Au::Rsa);
+const ECDH_ECDSA: (KeyAgreement, Authentication) = (Ka::Ecdh, Au::Ecdsa);
+const ECDHE_ECDSA: (KeyAgreement, Authentication) = (Ka::Ecdhe, Au::Ecdsa);
+const ECDH_RSA: (KeyAgreement, Authentication) = (Ka::Ecdh, Au::Rsa);
+const ECDHE_RSA: (KeyAgreement, Authentication) = (Ka::Ecdhe, Au::Rsa);
+const ECDH_ANON: (KeyAgreement, Authentication) = (Ka::Ecdhe, Au::Anonymous);
+const SRP: (KeyAgreement, Authentication) = (Ka::Srp, Au::Srp);
+const SRP_RSA: (KeyAgreement, Authentication) = (Ka::Srp, Au::Rsa);
+const SRP_DSS: (KeyAgreement, Authentication) = (Ka::Srp, Au::Dss);
+const ECDHE_PSK: (KeyAgreement, Authentication) = (Ka::EcdhePsk, Au::Psk);
+
+const RC4_MD5: (Cipher, Mac, Hash) = (Rc4, Mac::Md5, Hash::Sha256);
+const RC4_SHA: (Cipher, Mac, Hash) = (Rc4, Mac::Sha1, Hash::Sha256);
+const DES3_SHA: (Cipher, Mac, Hash) = (TripleDesCbc, Mac::Sha1, Hash::Sha256);
+const AES128_SHA: (Cipher, Mac, Hash) = (Aes128Cbc, Mac::Sha1, Hash::Sha256);
+const AES256_SHA: (Cipher, Mac, Hash) = (Aes256Cbc, Mac::Sha1, Hash::Sha256);
+const AES128_SHA256: (Cipher, Mac, Hash) = (Aes128Cbc, Mac::Sha256, Hash::Sha256);
+const AES256_SHA256: (Cipher, Mac, Hash) = (Aes256Cbc, Mac::Sha256, Hash::Sha256);
+const AES256_SHA384: (Cipher, Mac, Hash) = (Aes256Cbc, Mac::Sha384, Hash::Sha384);
+const AES128_GCM: (Cipher, Mac, Hash) = (Aes128Gcm, Mac::Aead, Hash::Sha256);
+const AES256_GCM: (Cipher, Mac, Hash) = (Aes256Gcm, Mac::Aead, Hash::Sha384);
+const AES128_CCM: (Cipher, Mac, Hash) = (Aes128Ccm, Mac::Aead, Hash::Sha256);
+const CHACHA20: (Cipher, Mac, Hash) = (Chacha20Poly1305, Mac::Aead, Hash::Sha256);
+
+/// The TLS 1.2 suites this crate knows, by code point. ECDHE_PSK has the
+/// messages of DHE_PSK (RFC 5489, 2), and ECDH_anon those of DH_anon.
+pub const CIPHER_SUITES: [CipherSuite; 70] = [
+    suite(0x0004, "TLS_RSA_WITH_RC4_128_MD5", RSA, RC4_MD5),
+    suite(0x0005, "TLS_RSA_WITH_RC4_128_SHA", RSA, RC4_SHA),
+    suite(0x000a, "TLS_RSA_WITH_3DES_EDE_CBC_SHA", RSA, DES3_SHA),
+    suite(0x0013, "TLS_DHE_DSS_WITH_3DES_EDE_CBC_SHA", DHE_DSS, DES3_SHA),
+    suite(0x0016, "TLS_DHE_RSA_WITH_3DES_EDE_CBC_SHA", DHE_RSA, DES3_SHA),
+    suite(0x002f, "TLS_RSA_WITH_AES_128_CBC_SHA", RSA, AES128_SHA),
+    suite(0x0030, "TLS_DH_DSS_WITH_AES_128_CBC_SHA", DH_DSS, AES128_SHA),
+    suite(0x0031, "TLS_DH_RSA_WITH_AES_128_CBC_SHA", DH_RSA, AES128_SHA),
+    suite(0x0032, "TLS_DHE_DSS_WITH_AES_128_CBC_SHA", DHE_DSS, AES128_SHA),
+    suite(0x0033, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA", DHE_RSA, AES128_SHA),
+    suite(0x0034, "TLS_DH_anon_WITH_AES_128_CBC_SHA", DH_ANON, AES128_SHA),
+    suite(0x0035, "TLS_RSA_WITH_AES_256_CBC_SHA", RSA, AES256_SHA),
+    suite(0x0036, "TLS_DH_DSS_WITH_AES_256_CBC_SHA", DH_DSS, AES256_SHA),
+    suite(0x0037, "TLS_DH_RSA_WITH_AES_256_CBC_SHA", DH_RSA, AES256_SHA),
+    suite(0x0038, "TLS_DHE_DSS_WITH_AES_256_CBC_SHA", DHE_DSS, AES256_SHA),
+    suite(0x0039, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA", DHE_RSA, AES256_SHA),
+    suite(0x003a, "TLS_DH_anon_WITH_AES_256_CBC_SHA", DH_ANON, AES256_SHA),
+    suite(0x003c, "TLS_RSA_WITH_AES_128_CBC_SHA256", RSA, AES128_SHA256),
+    suite(0x003d, "TLS_RSA_WITH_AES_256_CBC_SHA256", RSA, AES256_SHA256),
+    suite(0x0040, "TLS_DHE_DSS_WITH_AES_128_CBC_SHA256", DHE_DSS, AES128_SHA256),
+    suite(0x0067, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA256", DHE_RSA, AES128_SHA256),
+    suite(0x006a, "TLS_DHE_DSS_WITH_AES_256_CBC_SHA256", DHE_DSS, AES256_SHA256),
+    suite(0x006b, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA256", DHE_RSA, AES256_SHA256),
+    suite(0x006c, "TLS_DH_anon_WITH_AES_128_CBC_SHA256", DH_ANON, AES128_SHA256),
+    suite(0x006d, "TLS_DH_anon_WITH_AES_256_CBC_SHA256", DH_ANON, AES256_SHA256),
+    suite(0x008c, "TLS_PSK_WITH_AES_128_CBC_SHA", PSK, AES128_SHA),
+    suite(0x008d, "TLS_PSK_WITH_AES_256_CBC_SHA", PSK, AES256_SHA),
+    suite(0x0090, "TLS_DHE_PSK_WITH_AES_128_CBC_SHA", DHE_PSK, AES128_SHA),
+    suite(0x0091, "TLS_DHE_PSK_WITH_AES_256_CBC_SHA", DHE_PSK, AES256_SHA),
+    suite(0x0094, "TLS_RSA_PSK_WITH_AES_128_CBC_SHA", RSA_PSK, AES128_SHA),
+    suite(0x0095, "TLS_RSA_PSK_WITH_AES_256_CBC_SHA", RSA_PSK, AES256_SHA),
+    suite(0x009c, "TLS_RSA_WITH_AES_128_GCM_SHA256", RSA, AES128_GCM),
+    suite(0x009d, "TLS_RSA_WITH_AES_256_GCM_SHA384", RSA, AES256_GCM),
+    suite(0x009e, "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256", DHE_RSA, AES128_GCM),
+    suite(0x009f, "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384", DHE_RSA, AES256_GCM),
+    suite(0x00a2, "TLS_DHE_DSS_WITH_AES_128_GCM_SHA256", DHE_DSS, AES128_GCM),
+    suite(0x00a3, "TLS_DHE_DSS_WITH_AES_256_GCM_SHA384", DHE_DSS, AES256_GCM),
+    suite(0x00a6, "TLS_DH_anon_WITH_AES_128_GCM_SHA256", DH_ANON, AES128_GCM),
+    suite(0x00a7, "TLS_DH_anon_WITH_AES_256_GCM_SHA384", DH_ANON, AES256_GCM),
+    suite(0x00a8, "TLS_PSK_WITH_AES_128_GCM_SHA256", PSK, AES128_GCM),
+    suite(0x00a9, "TLS_PSK_WITH_AES_256_GCM_SHA384", PSK, AES256_GCM),
+    suite(0x00aa, "TLS_DHE_PSK_WITH_AES_128_GCM_SHA256", DHE_PSK, AES128_GCM),
+    suite(0x00ac, "TLS_RSA_PSK_WITH_AES_128_GCM_SHA256", RSA_PSK, AES128_GCM),
+    suite(0xc004, "TLS_ECDH_ECDSA_WITH_AES_128_CBC_SHA", ECDH_ECDSA, AES128_SHA),
+    suite(0xc009, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA", ECDHE_ECDSA, AES128_SHA),
+    suite(0xc00a, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA", ECDHE_ECDSA, AES256_SHA),
+    suite(0xc00e, "TLS_ECDH_RSA_WITH_AES_128_CBC_SHA", ECDH_RSA, AES128_SHA),
+    suite(0xc011, "TLS_ECDHE_RSA_WITH_RC4_128_SHA", ECDHE_RSA, RC4_SHA),
+    suite(0xc012, "TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA", ECDHE_RSA, DES3_SHA),
+    suite(0xc013, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA", ECDHE_RSA, AES128_SHA),
+    suite(0xc014, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA", ECDHE_RSA, AES256_SHA),
+    suite(0xc018, "TLS_ECDH_anon_WITH_AES_128_CBC_SHA", ECDH_ANON, AES128_SHA),
+    suite(0xc01a, "TLS_SRP_SHA_WITH_3DES_EDE_CBC_SHA", SRP, DES3_SHA),
+    suite(0xc01b, "TLS_SRP_SHA_RSA_WITH_3DES_EDE_CBC_SHA", SRP_RSA, DES3_SHA),
+    suite(0xc01c, "TLS_SRP_SHA_DSS_WITH_3DES_EDE_CBC_SHA", SRP_DSS, DES3_SHA),
+    suite(0xc01d, "TLS_SRP_SHA_WITH_AES_128_CBC_SHA", SRP, AES128_SHA),
+    suite(0xc01e, "TLS_SRP_SHA_RSA_WITH_AES_128_CBC_SHA", SRP_RSA, AES128_SHA),
+    suite(0xc01f, "TLS_SRP_SHA_DSS_WITH_AES_128_CBC_SHA", SRP_DSS, AES128_SHA),
+    suite(0xc023, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256", ECDHE_ECDSA, AES128_SHA256),
+    suite(0xc024, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384", ECDHE_ECDSA, AES256_SHA384),
+    suite(0xc027, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256", ECDHE_RSA, AES128_SHA256),
+    suite(0xc028, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384", ECDHE_RSA, AES256_SHA384),
+    suite(0xc02b, "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", ECDHE_ECDSA, AES128_GCM),
+    suite(0xc02c, "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384", ECDHE_ECDSA, AES256_GCM),
+    suite(0xc02f, "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256", ECDHE_RSA, AES128_GCM),
+    suite(0xc030, "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384", ECDHE_RSA, AES256_GCM),
+    suite(0xc035, "TLS_ECDHE_PSK_WITH_AES_128_CBC_SHA", ECDHE_PSK, AES128_SHA),
+    suite(0xc0ac, "TLS_ECDHE_ECDSA_WITH_AES_128_CCM", ECDHE_ECDSA, AES128_CCM),
+    suite(0xcca8, "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256", ECDHE_RSA, CHACHA20),
+    suite(0xcca9, "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256", ECDHE_ECDSA, CHACHA20),
+];
+
+/// The TLS 1.2 suite with code point `id`. Signalling values such as
+/// TLS_EMPTY_RENEGOTIATION_INFO_SCSV and TLS_FALLBACK_SCSV aren't suites.
+pub fn cipher_suite(id: u16) -> Option<CipherSuite> {
+    CIPHER_SUITES.iter().copied().find(|s| s.id == id)
+}
+
+/// The handshake shape suite `id` leads to.
+pub fn key_exchange(id: u16) -> Option<KeyExchange> {
+    cipher_suite(id).map(|s| s.key_exchange)
+}
+
+/// The options of a handshake under suite `id`, with everything else at
+/// its default.
+pub fn protocol_options(id: u16) -> Option<ProtocolOptions> {
+    cipher_suite(id).map(CipherSuite::protocol_options)
+}
+
+/// The AEAD and HKDF hash of a TLS 1.3 suite (RFC 8446, B.4), which says
+/// nothing about the key exchange or authentication.
+pub fn tls13_cipher_suite(id: u16) -> Option<(Cipher, Hash)> {
+    match id {
+        0x1301 => Some((Aes128Gcm, Hash::Sha256)),
+        0x1302 => Some((Aes256Gcm, Hash::Sha384)),
+        0x1303 => Some((Chacha20Poly1305, Hash::Sha256)),
+        0x1304 => Some((Aes128Ccm, Hash::Sha256)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tls12::{MessageType, Trace};
+
+    #[test]
+    fn lookup() {
+        let suite = cipher_suite(0xc02f).unwrap();
+        assert_eq!(suite.to_string(), "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256");
+        assert_eq!(
+            (suite.key_agreement, suite.authentication, suite.cipher, suite.mac, suite.prf_hash),
+            (Ka::Ecdhe, Au::Rsa, Aes128Gcm, Mac::Aead, Hash::Sha256)
+        );
+        assert_eq!(key_exchange(0x002f), Some(Kx::RsaKem));
+        assert_eq!(cipher_suite(0xc024).unwrap().prf_hash, Hash::Sha384);
+        // The SCSVs, and the TLS 1.3 suites.
+        for &id in &[0x00ff, 0x5600, 0x1301] {
+            assert_eq!(cipher_suite(id), None);
+        }
+        assert_eq!(tls13_cipher_suite(0x1302), Some((Aes256Gcm, Hash::Sha384)));
+        assert_eq!(tls13_cipher_suite(0xc02f), None);
+    }
+
+    #[test]
+    fn registry() {
+        for (i, suite) in CIPHER_SUITES.iter().enumerate() {
+            // Sorted by code point, without duplicates.
+            assert!(i == 0 || CIPHER_SUITES[i - 1].id < suite.id, "{}", suite);
+            assert!(suite.name.starts_with("TLS_"));
+            assert_eq!(suite.cipher.is_aead(), suite.mac == Mac::Aead, "{}", suite);
+
+            // The components agree with the shape of the handshake.
+            let kx = suite.key_exchange;
+            let anonymous = matches!(suite.authentication, Au::Anonymous | Au::Psk | Au::Srp);
+            assert_eq!(kx.server_certificate(), !anonymous, "{}", suite);
+            let ka = suite.key_agreement;
+            let psk = matches!(ka, Ka::Psk | Ka::DhePsk | Ka::EcdhePsk | Ka::RsaPsk);
+            assert_eq!(kx.is_psk(), psk, "{}", suite);
+            assert_eq!(kx.is_srp(), ka == Ka::Srp, "{}", suite);
+            let ephemeral = matches!(ka, Ka::Dhe | Ka::Ecdhe | Ka::DhePsk | Ka::EcdhePsk);
+            assert_eq!(kx.forward_secret(), ephemeral, "{}", suite);
+            assert_eq!(protocol_options(suite.id).unwrap().validate(), Ok(()));
+        }
+        for kx in KeyExchange::ALL.iter() {
+            assert!(CIPHER_SUITES.iter().any(|s| s.key_exchange == *kx), "{:?}", kx);
+        }
+    }
+
+    #[test]
+    fn handshake_shape() {
+        // The suites of the captured OpenSSL ClientHello lead to different
+        // handshakes.
+        let messages = |id| Trace::linearise(protocol_options(id).unwrap()).messages();
+        assert_eq!(protocol_options(0xc02f), Some(ProtocolOptions::default()));
+        assert!(messages(0xc02f).contains(&MessageType::ServerKeyExchange));
+        assert!(!messages(0x002f).contains(&MessageType::ServerKeyExchange));
+        let psk = protocol_options(0x00a8).unwrap();
+        assert!(!psk.key_exchange.server_certificate());
+    }
+}
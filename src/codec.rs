@@ -0,0 +1,295 @@
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+// The presentation language's integers and vectors (RFC 8446, 3), shared by
+// the message codecs. A vector `T foo<min..max>` is prefixed by its length
+// in bytes, in as many bytes as max needs; the bounds are checked both
+// ways, naming the field when they fail.
+
+/// Why bytes couldn't be read as, or written from, a structure.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CodecError {
+    /// The input ends before a field does.
+    Truncated,
+    /// Bytes follow the last field.
+    TrailingBytes,
+    /// A vector's length is outside its bounds; names the field.
+    BadLength(&'static str),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::Truncated => write!(f, "truncated input"),
+            CodecError::TrailingBytes => write!(f, "trailing bytes"),
+            CodecError::BadLength(field) => write!(f, "bad length of {}", field),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl CodecError {
+    /// The alert a receiver answers with (RFC 8446, 6.2).
+    pub fn alert(self) -> AlertDescription {
+        AlertDescription::DecodeError
+    }
+}
+
+/// Reads fields off the front of its input.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a [u8]) -> Reader<'a> {
+        Reader { input }
+    }
+
+    /// What is left to read.
+    pub fn rest(&self) -> &'a [u8] {
+        self.input
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Fails unless everything has been read.
+    pub fn end(&self) -> Result<(), CodecError> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(CodecError::TrailingBytes)
+        }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], CodecError> {
+        if self.input.len() < len {
+            return Err(CodecError::Truncated);
+        }
+        let (head, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(head)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, CodecError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn read_u24(&mut self) -> Result<u32, CodecError> {
+        let b = self.take(3)?;
+        Ok(u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, CodecError> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn read_vec(
+        &mut self,
+        len: usize,
+        min: usize,
+        max: usize,
+        field: &'static str,
+    ) -> Result<&'a [u8], CodecError> {
+        if len < min || len > max {
+            return Err(CodecError::BadLength(field));
+        }
+        self.take(len)
+    }
+
+    /// `opaque field<min..max>` for max < 2^8.
+    pub fn read_u8_vec(
+        &mut self,
+        min: usize,
+        max: usize,
+        field: &'static str,
+    ) -> Result<&'a [u8], CodecError> {
+        let len = self.read_u8()? as usize;
+        self.read_vec(len, min, max, field)
+    }
+
+    /// `opaque field<min..max>` for max < 2^16.
+    pub fn read_u16_vec(
+        &mut self,
+        min: usize,
+        max: usize,
+        field: &'static str,
+    ) -> Result<&'a [u8], CodecError> {
+        let len = self.read_u16()? as usize;
+        self.read_vec(len, min, max, field)
+    }
+
+    /// `opaque field<min..max>` for max < 2^24.
+    pub fn read_u24_vec(
+        &mut self,
+        min: usize,
+        max: usize,
+        field: &'static str,
+    ) -> Result<&'a [u8], CodecError> {
+        let len = self.read_u24()? as usize;
+        self.read_vec(len, min, max, field)
+    }
+
+    /// `uint16 field<min..max>`: a vector of 16-bit values, whose length in
+    /// bytes must also be even.
+    pub fn read_u16_list(
+        &mut self,
+        min: usize,
+        max: usize,
+        field: &'static str,
+    ) -> Result<Vec<u16>, CodecError> {
+        let list = self.read_u16_vec(min, max, field)?;
+        if list.len() % 2 != 0 {
+            return Err(CodecError::BadLength(field));
+        }
+        Ok(list.chunks(2).map(|v| u16::from_be_bytes([v[0], v[1]])).collect())
+    }
+}
+
+pub fn write_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Fails if `v` doesn't fit in 24 bits.
+pub fn write_u24(out: &mut Vec<u8>, v: usize) -> Result<(), CodecError> {
+    if v >= 1 << 24 {
+        return Err(CodecError::BadLength("uint24"));
+    }
+    out.extend_from_slice(&(v as u32).to_be_bytes()[1..]);
+    Ok(())
+}
+
+fn check_len(len: usize, min: usize, max: usize, field: &'static str) -> Result<(), CodecError> {
+    if len < min || len > max {
+        Err(CodecError::BadLength(field))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn write_u8_vec(
+    out: &mut Vec<u8>,
+    min: usize,
+    max: usize,
+    field: &'static str,
+    data: &[u8],
+) -> Result<(), CodecError> {
+    check_len(data.len(), min, max.min(0xff), field)?;
+    out.push(data.len() as u8);
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+pub fn write_u16_vec(
+    out: &mut Vec<u8>,
+    min: usize,
+    max: usize,
+    field: &'static str,
+    data: &[u8],
+) -> Result<(), CodecError> {
+    check_len(data.len(), min, max.min(0xffff), field)?;
+    write_u16(out, data.len() as u16);
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+pub fn write_u24_vec(
+    out: &mut Vec<u8>,
+    min: usize,
+    max: usize,
+    field: &'static str,
+    data: &[u8],
+) -> Result<(), CodecError> {
+    check_len(data.len(), min, max.min(0xff_ffff), field)?;
+    write_u24(out, data.len())?;
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+pub fn write_u16_list(
+    out: &mut Vec<u8>,
+    min: usize,
+    max: usize,
+    field: &'static str,
+    list: &[u16],
+) -> Result<(), CodecError> {
+    let data = list.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<_>>();
+    write_u16_vec(out, min, max, field, &data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        let mut r = Reader::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(r.read_u8(), Ok(1));
+        assert_eq!(r.read_u16(), Ok(0x0203));
+        assert_eq!(r.read_u24(), Ok(0x040506));
+        assert_eq!(r.read_u32(), Ok(0x0708090a));
+        assert_eq!(r.read_u8(), Err(CodecError::Truncated));
+        assert_eq!(r.end(), Ok(()));
+
+        let mut out = Vec::new();
+        write_u16(&mut out, 0x0102);
+        write_u24(&mut out, 0x030405).unwrap();
+        assert_eq!(out, vec![1, 2, 3, 4, 5]);
+        assert_eq!(write_u24(&mut out, 1 << 24), Err(CodecError::BadLength("uint24")));
+    }
+
+    #[test]
+    fn vectors_round_trip() {
+        let data = [0xaa; 300];
+        for &(len, prefix) in &[(0, 1), (1, 1), (255, 1), (0, 2), (300, 2), (300, 3)] {
+            let mut out = Vec::new();
+            match prefix {
+                1 => write_u8_vec(&mut out, 0, 255, "v", &data[..len]).unwrap(),
+                2 => write_u16_vec(&mut out, 0, 0xffff, "v", &data[..len]).unwrap(),
+                _ => write_u24_vec(&mut out, 0, 0xff_ffff, "v", &data[..len]).unwrap(),
+            }
+            assert_eq!(out.len(), prefix + len);
+            let mut r = Reader::new(&out);
+            let read = match prefix {
+                1 => r.read_u8_vec(0, 255, "v"),
+                2 => r.read_u16_vec(0, 0xffff, "v"),
+                _ => r.read_u24_vec(0, 0xff_ffff, "v"),
+            };
+            assert_eq!(read, Ok(&data[..len]));
+            assert!(r.is_empty());
+        }
+
+        let mut out = Vec::new();
+        write_u16_list(&mut out, 2, 0xfffe, "groups", &[0x001d, 0x0017]).unwrap();
+        assert_eq!(out, vec![0, 4, 0, 0x1d, 0, 0x17]);
+        assert_eq!(Reader::new(&out).read_u16_list(2, 0xfffe, "groups"), Ok(vec![29, 23]));
+    }
+
+    #[test]
+    fn bounds() {
+        let mut out = Vec::new();
+        assert_eq!(write_u8_vec(&mut out, 1, 255, "v", &[]), Err(CodecError::BadLength("v")));
+        assert_eq!(write_u8_vec(&mut out, 0, 32, "v", &[0; 33]), Err(CodecError::BadLength("v")));
+        // The prefix bounds the length whatever max says.
+        assert_eq!(
+            write_u8_vec(&mut out, 0, 1000, "v", &[0; 256]),
+            Err(CodecError::BadLength("v"))
+        );
+        assert!(out.is_empty());
+
+        assert_eq!(Reader::new(&[0]).read_u8_vec(1, 255, "v"), Err(CodecError::BadLength("v")));
+        assert_eq!(Reader::new(&[3, 1, 2]).read_u8_vec(0, 255, "v"), Err(CodecError::Truncated));
+        let odd = Reader::new(&[0, 3, 1, 2, 3]).read_u16_list(2, 0xfffe, "g");
+        assert_eq!(odd, Err(CodecError::BadLength("g")));
+        assert_eq!(Reader::new(&[0]).end(), Err(CodecError::TrailingBytes));
+    }
+}
@@ -2,6 +2,8 @@
 use hacspec::*;
 hacspec_imports!();
 
+use crate::montgomery::montgomery_ladder;
+
 // Define field mod 2^255-19
 #[field(7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed)]
 struct FieldElement;
@@ -10,10 +12,14 @@ struct FieldElement;
 #[bits(255)]
 struct Scalar;
 
-type Point = (FieldElement, FieldElement);
 bytes!(SerializedPoint, 32);
 bytes!(SerializedScalar, 32);
 
+// Curve25519 has A = 486662; clamped scalars have bit 254 at the top.
+montgomery_ladder!(FieldElement, Scalar, 255, 121665);
+
+// Clamping clears the three low bits, so that the scalar is a multiple of the
+// cofactor 8, and sets bit 254 and clears bit 255 (RFC 7748, section 5).
 fn mask_scalar(s: SerializedScalar) -> SerializedScalar {
     let mut k = s;
     k[0] &= 248;
@@ -28,6 +34,7 @@ fn decode_scalar(s: SerializedScalar) -> Scalar {
     Scalar::from_bytes_le(&k.raw())
 }
 
+// Reading u as a 255-bit number masks its top bit, as RFC 7748 requires.
 fn decode_point(u: SerializedPoint) -> Point {
     let u_ = Scalar::from_bytes_le(&u.raw());
     (
@@ -42,42 +49,6 @@ fn encode_point(p: Point) -> SerializedPoint {
     SerializedPoint::from_field(b)
 }
 
-fn point_add_and_double(q: Point, nq: Point, nqp1: Point) -> (Point, Point) {
-    let (x_1, _z_1) = q;
-    let (x_2, z_2) = nq;
-    let (x_3, z_3) = nqp1;
-    let a = x_2 + z_2;
-    let aa = a.pow(2);
-    let b = x_2 - z_2;
-    let bb = b * b;
-    let e = aa - bb;
-    let c = x_3 + z_3;
-    let d = x_3 - z_3;
-    let da = d * a;
-    let cb = c * b;
-
-    let x_3 = (da + cb).pow(2);
-    let z_3 = x_1 * ((da - cb).pow(2));
-    let x_2 = aa * bb;
-    let e121665: FieldElement = 121665.into();
-    let z_2 = e * (aa + (e121665 * e));
-    ((x_2, z_2), (x_3, z_3))
-}
-
-fn montgomery_ladder(k: Scalar, init: Point) -> Point {
-    let mut acc: (Point, Point) = ((1.into(), 0.into()), init);
-    for i in 0..256 {
-        if k.bit(255 - i) == 1 {
-            // TODO: this is ugly
-            let tmp = point_add_and_double(init, acc.1, acc.0);
-            acc = (tmp.1, tmp.0);
-        } else {
-            acc = point_add_and_double(init, acc.0, acc.1);
-        }
-    }
-    acc.0
-}
-
 pub fn scalarmult(s: SerializedScalar, p: SerializedPoint) -> SerializedPoint {
     let s_ = decode_scalar(s);
     let p_ = decode_point(p);
@@ -85,6 +56,33 @@ pub fn scalarmult(s: SerializedScalar, p: SerializedPoint) -> SerializedPoint {
     encode_point(r)
 }
 
+// X25519(k, u) of RFC 7748, section 5.
+pub fn x25519(scalar: SerializedScalar, point: SerializedPoint) -> SerializedPoint {
+    scalarmult(scalar, point)
+}
+
+// The public key of a scalar: X25519 of the base point u = 9.
+pub fn x25519_base(scalar: SerializedScalar) -> SerializedPoint {
+    let mut base = SerializedPoint::new();
+    base[0] = 9;
+    x25519(scalar, base)
+}
+
+// The shared secret of an (EC)DHE exchange. A peer may send a point of small
+// order, for which the result is all zeros; TLS then aborts (RFC 8422,
+// section 5.11 and RFC 8446, section 7.4.2).
+pub fn x25519_shared(
+    scalar: SerializedScalar,
+    point: SerializedPoint,
+) -> Result<SerializedPoint, String> {
+    let shared = x25519(scalar, point);
+    if shared == SerializedPoint::new() {
+        Err("X25519 shared secret is all zeros".to_string())
+    } else {
+        Ok(shared)
+    }
+}
+
 // Test some internal functions.
 
 #[test]
@@ -0,0 +1,517 @@
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// The subset of DER (X.690) that X.509 certificates need: definite-length
+/// TLVs with single-byte tags, and the universal types below. Each TLV is
+///
+/// ```text
+/// tag || length || value
+/// ```
+///
+/// with the length in its shortest form: one byte below 128, otherwise
+/// 0x80 + n followed by n big-endian bytes.
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const PRINTABLE_STRING: u8 = 0x13;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+/// The tag of `[n] EXPLICIT`, or of a constructed `[n] IMPLICIT`.
+pub fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+/// Why DER couldn't be decoded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DerError {
+    Truncated,
+    TrailingBytes,
+    /// A tag of more than one byte, or a length over 2^32 - 1.
+    Unsupported,
+    /// A length not in its shortest form, or the indefinite length of BER.
+    NonCanonicalLength,
+    UnexpectedTag {
+        expected: u8,
+        found: u8,
+    },
+    /// An INTEGER that is empty, padded, or negative where it can't be.
+    BadInteger,
+    /// A BIT STRING with unused bits in a key or signature.
+    BadBitString,
+    BadBoolean,
+    BadOid,
+    BadTime,
+}
+
+impl fmt::Display for DerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DerError::Truncated => write!(f, "truncated DER"),
+            DerError::TrailingBytes => write!(f, "trailing bytes after DER"),
+            DerError::Unsupported => write!(f, "unsupported DER tag or length"),
+            DerError::NonCanonicalLength => write!(f, "non-canonical DER length"),
+            DerError::UnexpectedTag { expected, found } => {
+                write!(f, "expected DER tag {:#04x}, found {:#04x}", expected, found)
+            }
+            DerError::BadInteger => write!(f, "malformed INTEGER"),
+            DerError::BadBitString => write!(f, "malformed BIT STRING"),
+            DerError::BadBoolean => write!(f, "malformed BOOLEAN"),
+            DerError::BadOid => write!(f, "malformed OBJECT IDENTIFIER"),
+            DerError::BadTime => write!(f, "malformed time"),
+        }
+    }
+}
+
+impl std::error::Error for DerError {}
+
+impl DerError {
+    /// The alert a receiver answers with: a certificate that can't be
+    /// decoded is a bad certificate, not a bad handshake message.
+    pub fn alert(self) -> AlertDescription {
+        AlertDescription::BadCertificate
+    }
+}
+
+/// A TLV, with its whole encoding alongside: a signature covers the
+/// encoding of tbsCertificate, not just its contents.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub value: &'a [u8],
+    pub encoded: &'a [u8],
+}
+
+/// Reads TLVs one after another from a slice, as `codec::Reader` reads TLS
+/// structures.
+#[derive(Debug, Clone)]
+pub struct Parser<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a [u8]) -> Parser<'a> {
+        Parser { input }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn end(&self) -> Result<(), DerError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(DerError::TrailingBytes)
+        }
+    }
+
+    /// The tag of the next TLV, if there is one.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DerError> {
+        if self.input.len() < len {
+            return Err(DerError::Truncated);
+        }
+        let (head, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(head)
+    }
+
+    pub fn read_tlv(&mut self) -> Result<Tlv<'a>, DerError> {
+        let start = self.input;
+        let tag = self.take(1)?[0];
+        if tag & 0x1f == 0x1f {
+            return Err(DerError::Unsupported);
+        }
+        let first = self.take(1)?[0] as usize;
+        let len = match first {
+            0..=0x7f => first,
+            0x80 => return Err(DerError::NonCanonicalLength),
+            0x81..=0x84 => {
+                let bytes = self.take(first - 0x80)?;
+                if bytes[0] == 0 {
+                    return Err(DerError::NonCanonicalLength);
+                }
+                let len = bytes.iter().fold(0, |len, &b| len << 8 | b as usize);
+                if len < 0x80 {
+                    return Err(DerError::NonCanonicalLength);
+                }
+                len
+            }
+            _ => return Err(DerError::Unsupported),
+        };
+        let value = self.take(len)?;
+        let encoded = &start[..start.len() - self.input.len()];
+        Ok(Tlv { tag, value, encoded })
+    }
+
+    /// The next TLV, which must have the tag `tag`.
+    pub fn read(&mut self, tag: u8) -> Result<Tlv<'a>, DerError> {
+        let tlv = self.read_tlv()?;
+        if tlv.tag != tag {
+            return Err(DerError::UnexpectedTag { expected: tag, found: tlv.tag });
+        }
+        Ok(tlv)
+    }
+
+    /// The next TLV if it has the tag `tag`, for OPTIONAL and DEFAULT
+    /// fields.
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<Tlv<'a>>, DerError> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A parser over the contents of the next SEQUENCE.
+    pub fn read_sequence(&mut self) -> Result<Parser<'a>, DerError> {
+        Ok(Parser::new(self.read(SEQUENCE)?.value))
+    }
+
+    /// A non-negative INTEGER, as its big-endian bytes without the sign
+    /// byte DER puts in front of a leading 1 bit.
+    pub fn read_unsigned(&mut self) -> Result<&'a [u8], DerError> {
+        let value = self.read(INTEGER)?.value;
+        match value {
+            [] => Err(DerError::BadInteger),
+            [0] => Ok(value),
+            [0, next, ..] if next & 0x80 == 0 => Err(DerError::BadInteger),
+            [0, ..] => Ok(&value[1..]),
+            [first, ..] if first & 0x80 != 0 => Err(DerError::BadInteger),
+            _ => Ok(value),
+        }
+    }
+
+    /// A non-negative INTEGER that fits in a u64, like a version.
+    pub fn read_small_unsigned(&mut self) -> Result<u64, DerError> {
+        let value = self.read_unsigned()?;
+        if value.len() > 8 {
+            return Err(DerError::BadInteger);
+        }
+        Ok(value.iter().fold(0, |v, &b| v << 8 | b as u64))
+    }
+
+    /// A BOOLEAN, whose only encodings in DER are 0x00 and 0xff.
+    pub fn read_boolean(&mut self) -> Result<bool, DerError> {
+        match self.read(BOOLEAN)?.value {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(DerError::BadBoolean),
+        }
+    }
+
+    /// An OBJECT IDENTIFIER, as its encoded contents. Compare it with the
+    /// constants below, or see its arcs with `oid_arcs`.
+    pub fn read_oid(&mut self) -> Result<&'a [u8], DerError> {
+        let value = self.read(OID)?.value;
+        oid_arcs(value)?;
+        Ok(value)
+    }
+
+    /// A BIT STRING of whole bytes, as keys and signatures are.
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8], DerError> {
+        match self.read(BIT_STRING)?.value {
+            [0, bits @ ..] => Ok(bits),
+            _ => Err(DerError::BadBitString),
+        }
+    }
+
+    /// A UTCTime or GeneralizedTime.
+    pub fn read_time(&mut self) -> Result<Time, DerError> {
+        let tlv = self.read_tlv()?;
+        match tlv.tag {
+            UTC_TIME => Time::from_utc_time(tlv.value),
+            GENERALIZED_TIME => Time::from_generalized_time(tlv.value),
+            found => Err(DerError::UnexpectedTag { expected: UTC_TIME, found }),
+        }
+    }
+}
+
+/// The parser over all of `input`, which must hold exactly one TLV with the
+/// tag `tag`.
+pub fn parse_one(input: &[u8], tag: u8) -> Result<Tlv<'_>, DerError> {
+    let mut p = Parser::new(input);
+    let tlv = p.read(tag)?;
+    p.end()?;
+    Ok(tlv)
+}
+
+/// The arcs of an encoded OBJECT IDENTIFIER. Each subidentifier is base 128,
+/// high bit set on all but its last byte, and the first one packs the first
+/// two arcs as 40 * x + y.
+pub fn oid_arcs(value: &[u8]) -> Result<Vec<u64>, DerError> {
+    if value.is_empty() || value[value.len() - 1] & 0x80 != 0 {
+        return Err(DerError::BadOid);
+    }
+    let mut subidentifiers = Vec::new();
+    let mut v: u64 = 0;
+    let mut start = true;
+    for &b in value {
+        if start && b == 0x80 {
+            // A padded subidentifier.
+            return Err(DerError::BadOid);
+        }
+        if v >> 57 != 0 {
+            return Err(DerError::BadOid);
+        }
+        v = v << 7 | (b & 0x7f) as u64;
+        start = b & 0x80 == 0;
+        if start {
+            subidentifiers.push(v);
+            v = 0;
+        }
+    }
+    let first = subidentifiers[0];
+    let mut arcs = match first {
+        0..=39 => vec![0, first],
+        40..=79 => vec![1, first - 40],
+        _ => vec![2, first - 80],
+    };
+    arcs.extend(&subidentifiers[1..]);
+    Ok(arcs)
+}
+
+/// 1.2.840.10045.2.1
+pub const ID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+/// 1.2.840.10045.3.1.7, the named curve P-256.
+pub const PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+/// 1.3.132.0.34, the named curve P-384.
+pub const SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+/// 1.2.840.10045.4.3.2
+pub const ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+/// 1.2.840.10045.4.3.3
+pub const ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+/// 1.2.840.113549.1.1.1
+pub const RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+/// 1.2.840.113549.1.1.11
+pub const SHA256_WITH_RSA_ENCRYPTION: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+/// 1.3.101.112
+pub const ID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+/// A time to the second, in UTC. Fields are in order of significance, so
+/// times compare as they should.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// YYMMDDHHMMSSZ, where YY of 50 and up is 19YY and the rest 20YY
+    /// (RFC 5280, 4.1.2.5.1).
+    pub fn from_utc_time(value: &[u8]) -> Result<Time, DerError> {
+        if value.len() != 13 {
+            return Err(DerError::BadTime);
+        }
+        let yy = digits(&value[..2])? as u16;
+        let year = if yy >= 50 { 1900 + yy } else { 2000 + yy };
+        Time::from_parts(year, &value[2..])
+    }
+
+    /// YYYYMMDDHHMMSSZ, without fractions of a second (RFC 5280,
+    /// 4.1.2.5.2).
+    pub fn from_generalized_time(value: &[u8]) -> Result<Time, DerError> {
+        if value.len() != 15 {
+            return Err(DerError::BadTime);
+        }
+        Time::from_parts(digits(&value[..4])? as u16, &value[4..])
+    }
+
+    // MMDDHHMMSSZ
+    fn from_parts(year: u16, rest: &[u8]) -> Result<Time, DerError> {
+        if rest[10] != b'Z' {
+            return Err(DerError::BadTime);
+        }
+        let field = |i: usize| digits(&rest[i..i + 2]).map(|v| v as u8);
+        let time = Time {
+            year,
+            month: field(0)?,
+            day: field(2)?,
+            hour: field(4)?,
+            minute: field(6)?,
+            second: field(8)?,
+        };
+        let february = match (year % 400, year % 100, year % 4) {
+            (0, _, _) => 29,
+            (_, 0, _) => 28,
+            (_, _, 0) => 29,
+            _ => 28,
+        };
+        let days = match time.month {
+            2 => february,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        if time.month == 0
+            || time.month > 12
+            || time.day == 0
+            || time.day > days
+            || time.hour > 23
+            || time.minute > 59
+            || time.second > 59
+        {
+            return Err(DerError::BadTime);
+        }
+        Ok(time)
+    }
+}
+
+fn digits(s: &[u8]) -> Result<u32, DerError> {
+    s.iter().try_fold(0, |v, &c| {
+        if c.is_ascii_digit() {
+            Ok(v * 10 + (c - b'0') as u32)
+        } else {
+            Err(DerError::BadTime)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::certificate::test::{hex, INTERMEDIATE, LEAF, ROOT};
+
+    #[test]
+    fn lengths() {
+        let tlv = Parser::new(&[OCTET_STRING, 2, 7, 8, 9]).read_tlv().unwrap();
+        assert_eq!(tlv, Tlv { tag: OCTET_STRING, value: &[7, 8], encoded: &[4, 2, 7, 8] });
+
+        let mut long = vec![OCTET_STRING, 0x82, 0x01, 0x00];
+        long.extend(vec![0; 256]);
+        assert_eq!(parse_one(&long, OCTET_STRING).unwrap().value.len(), 256);
+        assert_eq!(parse_one(&long[..259], OCTET_STRING), Err(DerError::Truncated));
+
+        let non_canonical: [&[u8]; 3] = [&[4, 0x81, 0x05], &[4, 0x82, 0x00, 0x80], &[4, 0x80]];
+        for input in non_canonical.iter() {
+            assert_eq!(Parser::new(input).read_tlv(), Err(DerError::NonCanonicalLength));
+        }
+        assert_eq!(Parser::new(&[0x1f, 0x81, 0x00]).read_tlv(), Err(DerError::Unsupported));
+        assert_eq!(parse_one(&[5, 0, 5, 0], NULL), Err(DerError::TrailingBytes));
+        assert_eq!(
+            parse_one(&[5, 0], SEQUENCE),
+            Err(DerError::UnexpectedTag { expected: SEQUENCE, found: NULL })
+        );
+    }
+
+    #[test]
+    fn integers() {
+        fn unsigned(input: &[u8]) -> Result<&[u8], DerError> {
+            Parser::new(input).read_unsigned()
+        }
+        assert_eq!(unsigned(&[2, 1, 0]), Ok(&[0][..]));
+        assert_eq!(unsigned(&[2, 2, 0, 0x80]), Ok(&[0x80][..]));
+        assert_eq!(unsigned(&[2, 2, 0x01, 0x00]), Ok(&[1, 0][..]));
+        assert_eq!(unsigned(&[2, 2, 0, 0x7f]), Err(DerError::BadInteger));
+        assert_eq!(unsigned(&[2, 1, 0xff]), Err(DerError::BadInteger));
+        assert_eq!(unsigned(&[2, 0]), Err(DerError::BadInteger));
+        assert_eq!(Parser::new(&[2, 2, 0x01, 0x02]).read_small_unsigned(), Ok(0x0102));
+
+        let boolean = |input: &[u8]| Parser::new(input).read_boolean();
+        assert_eq!(boolean(&[1, 1, 0xff]), Ok(true));
+        assert_eq!(boolean(&[1, 1, 0x01]), Err(DerError::BadBoolean));
+        assert_eq!(Parser::new(&[3, 2, 1, 0x80]).read_bit_string(), Err(DerError::BadBitString));
+    }
+
+    #[test]
+    fn oids() {
+        assert_eq!(oid_arcs(ECDSA_WITH_SHA256), Ok(vec![1, 2, 840, 10045, 4, 3, 2]));
+        assert_eq!(oid_arcs(SECP384R1), Ok(vec![1, 3, 132, 0, 34]));
+        assert_eq!(oid_arcs(&[0x88, 0x37]), Ok(vec![2, 999]));
+        assert_eq!(oid_arcs(&[]), Err(DerError::BadOid));
+        assert_eq!(oid_arcs(&[0x2a, 0x86]), Err(DerError::BadOid));
+        assert_eq!(oid_arcs(&[0x2a, 0x80, 0x01]), Err(DerError::BadOid));
+    }
+
+    #[test]
+    fn times() {
+        let t = Time { year: 2026, month: 10, day: 14, hour: 6, minute: 22, second: 39 };
+        assert_eq!(Time::from_utc_time(b"261014062239Z"), Ok(t));
+        assert_eq!(Time::from_generalized_time(b"20261014062239Z"), Ok(t));
+        assert_eq!(Time::from_utc_time(b"500101000000Z").unwrap().year, 1950);
+        assert!(Time::from_utc_time(b"491231235959Z").unwrap() > t);
+        assert_eq!(Time::from_utc_time(b"240229000000Z").unwrap().day, 29);
+        let bad: [&[u8]; 4] = [b"230229000000Z", b"261014062239", b"2610140622Z", b"261314062239Z"];
+        for bad in bad.iter() {
+            assert_eq!(Time::from_utc_time(bad), Err(DerError::BadTime));
+        }
+        assert_eq!(Time::from_generalized_time(b"20261014062239.5Z"), Err(DerError::BadTime));
+    }
+
+    #[test]
+    fn certificate_fields() {
+        // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm,
+        // signatureValue } (RFC 5280, 4.1)
+        let leaf = hex(LEAF);
+        let mut cert = Parser::new(parse_one(&leaf, SEQUENCE).unwrap().value);
+        let tbs = cert.read(SEQUENCE).unwrap();
+        assert_eq!(tbs.encoded, &leaf[4..4 + 354]);
+        assert_eq!(cert.read_sequence().unwrap().read_oid(), Ok(ECDSA_WITH_SHA256));
+        let signature = cert.read_bit_string().unwrap();
+        cert.end().unwrap();
+
+        // Ecdsa-Sig-Value ::= SEQUENCE { r INTEGER, s INTEGER }
+        let mut sig = Parser::new(parse_one(signature, SEQUENCE).unwrap().value);
+        assert!(sig.read_unsigned().unwrap().len() <= 32);
+        assert!(sig.read_unsigned().unwrap().len() <= 32);
+        sig.end().unwrap();
+
+        let mut tbs = Parser::new(tbs.value);
+        let mut version = Parser::new(tbs.read(context(0)).unwrap().value);
+        assert_eq!(version.read_small_unsigned(), Ok(2));
+        assert_eq!(tbs.read_small_unsigned(), Ok(3));
+        assert_eq!(tbs.read_sequence().unwrap().read_oid(), Ok(ECDSA_WITH_SHA256));
+        let issuer = tbs.read(SEQUENCE).unwrap();
+        let mut validity = tbs.read_sequence().unwrap();
+        let not_before = validity.read_time().unwrap();
+        let not_after = validity.read_time().unwrap();
+        assert_eq!((not_before.year, not_after.year, not_after.month), (2026, 2029, 1));
+        tbs.read(SEQUENCE).unwrap();
+
+        // SubjectPublicKeyInfo ::= SEQUENCE { algorithm, subjectPublicKey }
+        let mut spki = tbs.read_sequence().unwrap();
+        let mut algorithm = spki.read_sequence().unwrap();
+        assert_eq!(algorithm.read_oid(), Ok(ID_EC_PUBLIC_KEY));
+        assert_eq!(algorithm.read_oid(), Ok(PRIME256V1));
+        let public_key = concat!(
+            "04def9cdd158279fd27e2a80c996d93b2577fcc3e4af026f9f868177177c020b0cdc3b945424971fc1a1",
+            "22e4ccd74ef8bf7334bc308a11ebcaf330030b368edaf8",
+        );
+        assert_eq!(spki.read_bit_string(), Ok(&hex(public_key)[..]));
+        assert!(tbs.read_optional(context(1)).unwrap().is_none());
+        assert!(tbs.read_optional(context(3)).unwrap().is_some());
+        tbs.end().unwrap();
+
+        // The leaf's issuer is the intermediate's subject.
+        let intermediate = hex(INTERMEDIATE);
+        let mut cert = Parser::new(parse_one(&intermediate, SEQUENCE).unwrap().value);
+        let mut tbs = cert.read_sequence().unwrap();
+        for _ in 0..4 {
+            tbs.read_tlv().unwrap();
+        }
+        tbs.read(SEQUENCE).unwrap();
+        assert_eq!(tbs.read(SEQUENCE).unwrap(), issuer);
+    }
+
+    #[test]
+    fn truncated_certificates() {
+        for cert in &[hex(LEAF), hex(INTERMEDIATE), hex(ROOT)] {
+            assert!(parse_one(cert, SEQUENCE).is_ok());
+            assert_eq!(parse_one(&cert[..cert.len() - 1], SEQUENCE), Err(DerError::Truncated));
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use crate::graph;
+use crate::tls12::{self, Peer};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolOptions {
+    tls: tls12::ProtocolOptions,
+    cookie_exchange: bool,
+}
+
+impl ProtocolOptions {
+    /// Walk the handshake these options describe, cookie exchange and all.
+    pub fn iter_handshake(self) -> HandshakeIter {
+        HandshakeIter { state: State::default(), opts: self }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageType {
+    HelloVerifyRequest,
+    Tls(tls12::MessageType),
+}
+
+/// DTLS 1.2 runs the TLS 1.2 handshake (RFC 6347, 4.2). The only additional
+/// states are the stateless cookie exchange in front of it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    #[default]
+    ClientSendsInitialClientHello,
+    ServerSendsHelloVerifyRequest,
+    Tls(tls12::State),
+}
+
+impl State {
+    pub fn sends(self) -> Option<(Peer, MessageType)> {
+        use State::*;
+        match self {
+            ClientSendsInitialClientHello => {
+                Some((Peer::Client, MessageType::Tls(tls12::MessageType::ClientHello)))
+            }
+            ServerSendsHelloVerifyRequest => Some((Peer::Server, MessageType::HelloVerifyRequest)),
+            Tls(st) => st.sends().map(|(peer, msg)| (peer, MessageType::Tls(msg))),
+        }
+    }
+}
+
+pub fn step(st: State, opts: ProtocolOptions) -> State {
+    use State::*;
+
+    match st {
+        ClientSendsInitialClientHello => {
+            if opts.cookie_exchange {
+                ServerSendsHelloVerifyRequest
+            } else {
+                Tls(tls12::step(tls12::State::ClientSendsClientHello, opts.tls))
+            }
+        }
+        // The client repeats its ClientHello, now carrying the cookie.
+        ServerSendsHelloVerifyRequest => Tls(tls12::State::ClientSendsClientHello),
+        Tls(st) => Tls(tls12::step(st, opts.tls)),
+    }
+}
+
+/// The messages of a handshake in order, with the state sending each. See
+/// `ProtocolOptions::iter_handshake`.
+#[derive(Debug, Clone)]
+pub struct HandshakeIter {
+    state: State,
+    opts: ProtocolOptions,
+}
+
+impl Iterator for HandshakeIter {
+    type Item = (State, Peer, MessageType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let st = self.state;
+        let (peer, msg) = st.sends()?;
+        self.state = step(st, self.opts);
+        Some((st, peer, msg))
+    }
+}
+
+/// An event of the handshake: one of TLS 1.2, or a peer moving on to a new
+/// epoch, as specified by `dtls_record`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Tls(tls12::Action),
+    /// The sender of a ChangeCipherSpec writes in its next epoch from then
+    /// on (`dtls_record::next_dtls12_epoch`), counting its sequence numbers
+    /// from 0 again (RFC 6347, 4.1).
+    NextWriteEpoch(Peer),
+    /// And its receiver expects records in the next epoch, with a replay
+    /// window of its own (`dtls_record::ReplayWindow`).
+    NextReadEpoch(Peer),
+}
+
+/// Like `step`, but also returns what the peers do with their keys and
+/// epochs once the message sent in `st` has gone over: the actions of
+/// `tls12::step_with_actions`, each switch of keys with its new epoch.
+pub fn step_with_actions(st: State, opts: ProtocolOptions) -> (State, Vec<Action>) {
+    let actions = match st {
+        State::Tls(tls) => {
+            let mut actions = Vec::new();
+            for action in tls12::step_with_actions(tls, opts.tls).1 {
+                actions.push(Action::Tls(action));
+                match action {
+                    tls12::Action::SwitchToWriteKeys(peer) => {
+                        actions.push(Action::NextWriteEpoch(peer))
+                    }
+                    tls12::Action::SwitchToReadKeys(peer) => {
+                        actions.push(Action::NextReadEpoch(peer))
+                    }
+                    _ => {}
+                }
+            }
+            actions
+        }
+        _ => vec![],
+    };
+    (step(st, opts), actions)
+}
+
+/// The handshake's messages grouped into flights, as in `tls12::Trace::flights`,
+/// with the cookie exchange in front when there is one.
+pub fn flights(opts: ProtocolOptions) -> Vec<(Peer, Vec<MessageType>)> {
+    let mut flights: Vec<(Peer, Vec<MessageType>)> = Vec::new();
+    for (_, peer, msg) in opts.iter_handshake() {
+        match flights.last_mut() {
+            Some((last, msgs)) if *last == peer => msgs.push(msg),
+            _ => flights.push((peer, vec![msg])),
+        }
+    }
+    flights
+}
+
+/// The retransmission timer starts at a second and doubles on each timeout,
+/// up to a minute (RFC 6347, 4.2.4.1).
+pub const INITIAL_TIMEOUT_MS: u32 = 1000;
+pub const MAX_TIMEOUT_MS: u32 = 60_000;
+
+/// One step of a handshake over a lossy network. Flights are indices into
+/// `flights`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// `peer` sends a flight, for the first time or again, and it is lost or
+    /// arrives.
+    Send { peer: Peer, flight: usize, retransmission: bool, lost: bool },
+    /// The timer `peer` started when it last sent a flight expires.
+    Timeout { peer: Peer, timeout_ms: u32 },
+}
+
+/// The handshake with the transmissions `losses` marks lost, in order;
+/// those past its end arrive. Every schedule ends with the handshake
+/// complete, so the traces of all schedules of some length enumerate the
+/// ways the handshake recovers from that much loss.
+///
+/// The rules are those of RFC 6347, 4.2.4:
+///
+/// - A peer that sends a flight starts a timer, and retransmits the flight
+///   when it expires before the next one arrives. Neither the server after
+///   a HelloVerifyRequest, which keeps no state, nor the sender of the last
+///   flight, which expects no answer, runs a timer.
+/// - A peer that receives the other's previous flight again takes it that
+///   its own flight was lost, and retransmits it.
+///
+/// When a flight is lost, the peer waiting for it started its timer first,
+/// so that one expires as long as it is running.
+pub fn retransmissions(opts: ProtocolOptions, losses: &[bool]) -> Vec<Event> {
+    let flights = flights(opts);
+    let mut losses = losses.iter().copied();
+    let mut events = Vec::new();
+    let mut timeouts = [INITIAL_TIMEOUT_MS; 2];
+    let mut sent = 0;
+    // The first flight not yet received.
+    let mut next = 0;
+    let mut flight = 0;
+
+    loop {
+        let peer = flights[flight].0;
+        let lost = losses.next().unwrap_or(false);
+        events.push(Event::Send { peer, flight, retransmission: flight < sent, lost });
+        sent = sent.max(flight + 1);
+
+        if !lost {
+            if flight == next {
+                timeouts[peer.other() as usize] = INITIAL_TIMEOUT_MS;
+                next += 1;
+                if next == flights.len() {
+                    return events;
+                }
+            }
+            // Either the answer to a new flight, or a retransmission of one
+            // already answered.
+            flight = next;
+            continue;
+        }
+
+        let waiting = next > 0 && flights[next - 1].1 != [MessageType::HelloVerifyRequest];
+        flight = if waiting { next - 1 } else { next };
+        let peer = flights[flight].0;
+        let timeout_ms = timeouts[peer as usize];
+        events.push(Event::Timeout { peer, timeout_ms });
+        timeouts[peer as usize] = (timeout_ms * 2).min(MAX_TIMEOUT_MS);
+    }
+}
+
+/// Every loss schedule of `len` transmissions.
+pub fn loss_schedules(len: usize) -> Vec<Vec<bool>> {
+    (0..1usize << len).map(|n| (0..len).map(|i| n & (1 << i) != 0).collect()).collect()
+}
+
+impl graph::Protocol for ProtocolOptions {
+    type State = State;
+
+    const NAME: &'static str = "dtls12";
+
+    fn step(self, st: State) -> State {
+        step(st, self)
+    }
+
+    fn sends(st: State) -> Option<(Peer, String)> {
+        st.sends().map(|(peer, msg)| (peer, format!("{:?}", msg)))
+    }
+
+    fn states() -> Vec<State> {
+        let mut all =
+            vec![State::ClientSendsInitialClientHello, State::ServerSendsHelloVerifyRequest];
+        all.extend(tls12::ProtocolOptions::states().into_iter().map(State::Tls));
+        all
+    }
+
+    fn combinations() -> Vec<Self> {
+        let mut all = Vec::new();
+        for tls in tls12::ProtocolOptions::combinations() {
+            for cookie_exchange in &[false, true] {
+                all.push(ProtocolOptions { tls, cookie_exchange: *cookie_exchange });
+            }
+        }
+        all
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = self.tls.settings();
+        settings.push(("cookie_exchange", self.cookie_exchange.to_string()));
+        settings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn linearise(opts: ProtocolOptions) -> Vec<(Peer, MessageType)> {
+        opts.iter_handshake().map(|(_, peer, msg)| (peer, msg)).collect()
+    }
+
+    #[test]
+    fn no_cookie_exchange() {
+        let trace = linearise(ProtocolOptions::default());
+        assert_eq!(trace[0], (Peer::Client, MessageType::Tls(tls12::MessageType::ClientHello)));
+        assert_eq!(trace[1], (Peer::Server, MessageType::Tls(tls12::MessageType::ServerHello)));
+        assert!(!trace.contains(&(Peer::Server, MessageType::HelloVerifyRequest)));
+    }
+
+    #[test]
+    fn cookie_exchange() {
+        let trace = linearise(ProtocolOptions { cookie_exchange: true, ..Default::default() });
+        assert_eq!(
+            trace[..4],
+            [
+                (Peer::Client, MessageType::Tls(tls12::MessageType::ClientHello)),
+                (Peer::Server, MessageType::HelloVerifyRequest),
+                (Peer::Client, MessageType::Tls(tls12::MessageType::ClientHello)),
+                (Peer::Server, MessageType::Tls(tls12::MessageType::ServerHello)),
+            ]
+        );
+        // Apart from the extra round trip this is the TLS 1.2 handshake.
+        let plain = linearise(ProtocolOptions::default());
+        assert_eq!(trace.len(), plain.len() + 2);
+        assert_eq!(trace[2..], plain[..]);
+    }
+
+    #[test]
+    fn cookie_exchange_resume() {
+        let opts = ProtocolOptions {
+            tls: tls12::ProtocolOptions { resuming: true, ..Default::default() },
+            cookie_exchange: true,
+        };
+        let trace = linearise(opts);
+        assert_eq!(trace[1], (Peer::Server, MessageType::HelloVerifyRequest));
+        assert_eq!(
+            trace.last(),
+            Some(&(Peer::Client, MessageType::Tls(tls12::MessageType::Finished)))
+        );
+    }
+
+    #[test]
+    fn epochs() {
+        let opts = ProtocolOptions { cookie_exchange: true, ..Default::default() };
+        let (_, actions) =
+            step_with_actions(State::Tls(tls12::State::ClientSendsChangeCipherSpec), opts);
+        assert_eq!(
+            actions,
+            [
+                Action::Tls(tls12::Action::SwitchToWriteKeys(Peer::Client)),
+                Action::NextWriteEpoch(Peer::Client),
+                Action::Tls(tls12::Action::SwitchToReadKeys(Peer::Server)),
+                Action::NextReadEpoch(Peer::Server),
+            ]
+        );
+        assert_eq!(step_with_actions(State::ServerSendsHelloVerifyRequest, opts).1, []);
+
+        // Each peer ends the handshake in epoch 1, writing as its peer reads.
+        let mut state = State::default();
+        let mut epochs = Vec::new();
+        while state.sends().is_some() {
+            let (next, actions) = step_with_actions(state, opts);
+            for action in actions {
+                match action {
+                    Action::NextWriteEpoch(peer) => epochs.push((peer, true)),
+                    Action::NextReadEpoch(peer) => epochs.push((peer, false)),
+                    Action::Tls(_) => {}
+                }
+            }
+            state = next;
+        }
+        let (client, server) = (Peer::Client, Peer::Server);
+        assert_eq!(epochs, [(client, true), (server, false), (server, true), (client, false)]);
+    }
+
+    fn send(peer: Peer, flight: usize, retransmission: bool, lost: bool) -> Event {
+        Event::Send { peer, flight, retransmission, lost }
+    }
+
+    fn timeout(peer: Peer, timeout_ms: u32) -> Event {
+        Event::Timeout { peer, timeout_ms }
+    }
+
+    #[test]
+    fn flights() {
+        let opts = ProtocolOptions { cookie_exchange: true, ..Default::default() };
+        let flights = super::flights(opts);
+        assert_eq!(flights[1], (Peer::Server, vec![MessageType::HelloVerifyRequest]));
+        // Past the cookie exchange the flights are those of TLS 1.2.
+        let tls = tls12::Trace::linearise(opts.tls).flights();
+        let tls = tls
+            .into_iter()
+            .map(|(peer, msgs)| (peer, msgs.into_iter().map(MessageType::Tls).collect::<Vec<_>>()));
+        assert!(flights[2..].iter().cloned().eq(tls));
+    }
+
+    #[test]
+    fn no_loss() {
+        let opts = ProtocolOptions::default();
+        let events = retransmissions(opts, &[]);
+        let expected = super::flights(opts)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (peer, _))| send(peer, i, false, false))
+            .collect::<Vec<_>>();
+        assert_eq!(events, expected);
+        assert_eq!(retransmissions(opts, &[false, false]), expected);
+    }
+
+    #[test]
+    fn lost_client_hello() {
+        // Nobody else is waiting, so the client's timer expires, and keeps
+        // backing off until a ClientHello gets through.
+        let events = retransmissions(ProtocolOptions::default(), &[true, true, true]);
+        assert_eq!(
+            events[..8],
+            [
+                send(Peer::Client, 0, false, true),
+                timeout(Peer::Client, 1000),
+                send(Peer::Client, 0, true, true),
+                timeout(Peer::Client, 2000),
+                send(Peer::Client, 0, true, true),
+                timeout(Peer::Client, 4000),
+                send(Peer::Client, 0, true, false),
+                send(Peer::Server, 1, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn timer_backs_off_to_a_minute() {
+        let events = retransmissions(ProtocolOptions::default(), &[true; 10]);
+        let timeouts = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Timeout { timeout_ms, .. } => Some(*timeout_ms),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(timeouts, [1000, 2000, 4000, 8000, 16000, 32000, 60000, 60000, 60000, 60000]);
+    }
+
+    #[test]
+    fn lost_server_flight() {
+        // The client retransmits its ClientHello, and the server answers the
+        // duplicate by retransmitting its own flight.
+        let events = retransmissions(ProtocolOptions::default(), &[false, true]);
+        assert_eq!(
+            events[..6],
+            [
+                send(Peer::Client, 0, false, false),
+                send(Peer::Server, 1, false, true),
+                timeout(Peer::Client, 1000),
+                send(Peer::Client, 0, true, false),
+                send(Peer::Server, 1, true, false),
+                send(Peer::Client, 2, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn lost_last_flight() {
+        // Its sender expects no answer, so the client recovers it by
+        // retransmitting the flight before.
+        let opts = ProtocolOptions::default();
+        let last = super::flights(opts).len() - 1;
+        assert_eq!(super::flights(opts)[last].0, Peer::Server);
+        let mut losses = vec![false; last];
+        losses.push(true);
+        let events = retransmissions(opts, &losses);
+        assert_eq!(
+            events[last..],
+            [
+                send(Peer::Server, last, false, true),
+                timeout(Peer::Client, 1000),
+                send(Peer::Client, last - 1, true, false),
+                send(Peer::Server, last, true, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn lost_cookie_exchange() {
+        let opts = ProtocolOptions { cookie_exchange: true, ..Default::default() };
+        // A lost HelloVerifyRequest is recovered by the client, and the
+        // server answers the repeated ClientHello with a fresh one.
+        let events = retransmissions(opts, &[false, true]);
+        assert_eq!(
+            events[1..5],
+            [
+                send(Peer::Server, 1, false, true),
+                timeout(Peer::Client, 1000),
+                send(Peer::Client, 0, true, false),
+                send(Peer::Server, 1, true, false),
+            ]
+        );
+        // The server keeps no state after it, so only the client's timer
+        // runs while the second ClientHello is in flight.
+        let events = retransmissions(opts, &[false, false, true]);
+        assert_eq!(
+            events[2..5],
+            [
+                send(Peer::Client, 2, false, true),
+                timeout(Peer::Client, 1000),
+                send(Peer::Client, 2, true, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn loss_traces() {
+        assert_eq!(loss_schedules(0), [Vec::<bool>::new()]);
+        assert_eq!(loss_schedules(2), [[false, false], [true, false], [false, true], [true, true]]);
+        for cookie_exchange in [false, true] {
+            let opts = ProtocolOptions { cookie_exchange, ..Default::default() };
+            let flights = super::flights(opts);
+            for losses in loss_schedules(6) {
+                let events = retransmissions(opts, &losses);
+                // Each flight goes out once in order, whatever is repeated
+                // around it.
+                let first = events.iter().filter_map(|event| match event {
+                    Event::Send { flight, retransmission: false, .. } => Some(*flight),
+                    _ => None,
+                });
+                assert!(first.eq(0..flights.len()));
+                // Every loss is answered by exactly one timeout.
+                for pair in events.windows(2) {
+                    let lost = matches!(pair[0], Event::Send { lost: true, .. });
+                    assert_eq!(lost, matches!(pair[1], Event::Timeout { .. }));
+                }
+                // And the handshake completes.
+                let last = flights.len() - 1;
+                match events.last() {
+                    Some(Event::Send { peer, flight, lost: false, .. }) => {
+                        assert_eq!((*peer, *flight), (flights[last].0, last))
+                    }
+                    other => panic!("unexpected {:?}", other),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use crate::graph;
+use crate::tls12::Peer;
+use crate::tls13::{self, Keys};
+use std::collections::HashMap;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolOptions {
+    tls: tls13::ProtocolOptions,
+    cookie: bool,
+}
+
+impl ProtocolOptions {
+    /// The TLS 1.3 handshake underneath. DTLS 1.3 has no middlebox
+    /// compatibility mode (RFC 9147, 5), so it is left off whatever `tls`
+    /// says.
+    pub fn with_tls(mut self, tls: tls13::ProtocolOptions) -> Self {
+        self.tls = tls.with_middlebox_compat(false);
+        self
+    }
+
+    /// The server has the client prove it can receive at its address before
+    /// keeping any state, with a cookie in a HelloRetryRequest that the
+    /// second ClientHello echoes (RFC 9147, 5.1). DTLS 1.3 has no
+    /// HelloVerifyRequest, so a cookie brings a HelloRetryRequest with it,
+    /// whatever the `tls` options say, set before or after.
+    pub fn with_cookie(mut self, cookie: bool) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// The TLS 1.3 options the handshake runs with: those of `with_tls`,
+    /// without middlebox compatibility mode and with the HelloRetryRequest a
+    /// cookie needs.
+    pub fn tls(&self) -> tls13::ProtocolOptions {
+        self.tls
+            .with_middlebox_compat(false)
+            .with_hello_retry(self.tls.hello_retry() || self.cookie)
+    }
+
+    /// Walk the handshake these options describe, ACKs and all.
+    pub fn iter_handshake(self) -> HandshakeIter {
+        HandshakeIter { state: State::default(), opts: self }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageType {
+    Ack,
+    Tls(tls13::MessageType),
+}
+
+/// DTLS 1.3 runs the TLS 1.3 handshake without its EndOfEarlyData
+/// (RFC 9147, 5.6) or ChangeCipherSpec. A flight that gets no answer of its
+/// own is acknowledged instead with an ACK (RFC 9147, 7): the client's last
+/// flight of the handshake or of post-handshake authentication, the
+/// server's tickets, and every KeyUpdate (RFC 9147, 8). The ACK states hold
+/// the state whose message ended the acknowledged flight.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    Tls(tls13::State),
+    ServerSendsAck(tls13::State),
+    ClientSendsAck(tls13::State),
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::Tls(tls13::State::default())
+    }
+}
+
+impl State {
+    pub fn sends(self) -> Option<(Peer, MessageType)> {
+        match self {
+            State::Tls(st) => st.sends().map(|(peer, msg)| (peer, MessageType::Tls(msg))),
+            State::ServerSendsAck(_) => Some((Peer::Server, MessageType::Ack)),
+            State::ClientSendsAck(_) => Some((Peer::Client, MessageType::Ack)),
+        }
+    }
+
+    /// Whether the message sent in this state carries a cookie: the
+    /// HelloRetryRequest of a server asking for one, and the second
+    /// ClientHello giving it back.
+    pub fn cookie(self, opts: ProtocolOptions) -> bool {
+        opts.cookie
+            && matches!(
+                self,
+                State::Tls(tls13::State::ServerSendsHelloRetryRequest)
+                    | State::Tls(tls13::State::ClientSendsSecondClientHello)
+            )
+    }
+}
+
+/// `tls13::step_with_actions`, going straight past the EndOfEarlyData but
+/// keeping what the client does with its keys there.
+fn tls_step(st: tls13::State, opts: tls13::ProtocolOptions) -> (tls13::State, Vec<tls13::Action>) {
+    let (next, mut actions) = tls13::step_with_actions(st, opts);
+    if next == tls13::State::ClientSendsEndOfEarlyData {
+        let (next, more) = tls13::step_with_actions(next, opts);
+        actions.extend(more);
+        (next, actions)
+    } else {
+        (next, actions)
+    }
+}
+
+/// Whether the flight that the message sent in `st` ends needs an ACK,
+/// with `next` the TLS 1.3 state after it.
+fn acknowledged(st: tls13::State, next: tls13::State) -> bool {
+    use tls13::State::*;
+    match st {
+        ClientSendsFinished
+        | ClientSendsPostHandshakeFinished
+        | ClientSendsKeyUpdate
+        | ServerSendsKeyUpdate => true,
+        // The tickets the server sends together are one flight.
+        ServerSendsNewSessionTicket(_) => !matches!(next, ServerSendsNewSessionTicket(_)),
+        _ => false,
+    }
+}
+
+pub fn step(st: State, opts: ProtocolOptions) -> State {
+    use State::*;
+
+    match st {
+        Tls(st) => {
+            let (next, _) = tls_step(st, opts.tls());
+            match st.sends() {
+                Some((Peer::Client, _)) if acknowledged(st, next) => ServerSendsAck(st),
+                Some((Peer::Server, _)) if acknowledged(st, next) => ClientSendsAck(st),
+                _ => Tls(next),
+            }
+        }
+        ServerSendsAck(st) | ClientSendsAck(st) => Tls(tls_step(st, opts.tls()).0),
+    }
+}
+
+/// The messages of a handshake in order, with the state sending each. See
+/// `ProtocolOptions::iter_handshake`.
+#[derive(Debug, Clone)]
+pub struct HandshakeIter {
+    state: State,
+    opts: ProtocolOptions,
+}
+
+impl Iterator for HandshakeIter {
+    type Item = (State, Peer, MessageType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let st = self.state;
+        let (peer, msg) = st.sends()?;
+        self.state = step(st, self.opts);
+        Some((st, peer, msg))
+    }
+}
+
+/// The epoch of the records protected with these keys. Records sent in the
+/// clear are in epoch 0, and each KeyUpdate moves its sender on to the
+/// epoch after its current one (RFC 9147, 6.1).
+pub fn epoch(keys: Keys) -> u64 {
+    match keys {
+        Keys::Early => 1,
+        Keys::Handshake => 2,
+        Keys::Application => 3,
+    }
+}
+
+/// An event of the handshake: one of TLS 1.3, or a peer moving on to a new
+/// epoch, as specified by `dtls_record`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Tls(tls13::Action),
+    /// The peer writes in this epoch from then on, counting its sequence
+    /// numbers in it (`dtls_record::next_sequence_number`). Above epoch 0,
+    /// it encrypts them with the sn_key of its traffic secret
+    /// (`dtls_record::sn_key`).
+    StartWriteEpoch(Peer, u64),
+    /// The peer expects records in this epoch, with a replay window of its
+    /// own (`dtls_record::ReplayWindow`).
+    StartReadEpoch(Peer, u64),
+    /// The sender of a KeyUpdate writes in its next epoch
+    /// (`dtls_record::next_dtls13_epoch`), and the receiver reads in it.
+    NextWriteEpoch(Peer),
+    NextReadEpoch(Peer),
+}
+
+/// Like `step`, but also returns what the peers do with their keys and
+/// epochs once the message sent in `st` has gone over: the actions of
+/// `tls13::step_with_actions`, each change of keys with its epoch.
+pub fn step_with_actions(st: State, opts: ProtocolOptions) -> (State, Vec<Action>) {
+    use tls13::Action::*;
+    let actions = match st {
+        State::Tls(tls) => {
+            let mut actions = Vec::new();
+            for action in tls_step(tls, opts.tls()).1 {
+                actions.push(Action::Tls(action));
+                match action {
+                    SwitchToWriteKeys(peer, keys) => {
+                        actions.push(Action::StartWriteEpoch(peer, epoch(keys)))
+                    }
+                    SwitchToReadKeys(peer, keys) => {
+                        actions.push(Action::StartReadEpoch(peer, epoch(keys)))
+                    }
+                    DiscardWriteKeys(peer) => actions.push(Action::StartWriteEpoch(peer, 0)),
+                    UpdateWriteKeys(peer) => actions.push(Action::NextWriteEpoch(peer)),
+                    UpdateReadKeys(peer) => actions.push(Action::NextReadEpoch(peer)),
+                    _ => {}
+                }
+            }
+            actions
+        }
+        _ => vec![],
+    };
+    (step(st, opts), actions)
+}
+
+/// A record's number, which its nonce is made from and an ACK names it by:
+/// the epoch of its keys, and its sender's count of records in that epoch
+/// before it (RFC 9147, 4 and 7).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordNumber {
+    pub epoch: u64,
+    pub sequence_number: u64,
+}
+
+/// A message of the handshake in the one record that carries it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record {
+    pub peer: Peer,
+    pub msg: MessageType,
+    pub number: RecordNumber,
+    /// For an ACK, the records of the flight it acknowledges.
+    pub acknowledges: Vec<RecordNumber>,
+}
+
+/// Every record of the handshake in order, numbered as each peer moves
+/// through its epochs.
+pub fn records(opts: ProtocolOptions) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut epochs: HashMap<Peer, u64> = HashMap::new();
+    let mut sequence_numbers: HashMap<(Peer, u64), u64> = HashMap::new();
+    // What each peer has received since it last sent anything, but for
+    // ACKs, which are never acknowledged themselves.
+    let mut received: HashMap<Peer, Vec<RecordNumber>> = HashMap::new();
+
+    let mut state = State::default();
+    while let Some((peer, msg)) = state.sends() {
+        let epoch = *epochs.get(&peer).unwrap_or(&0);
+        let sequence_number = sequence_numbers.entry((peer, epoch)).or_insert(0);
+        let number = RecordNumber { epoch, sequence_number: *sequence_number };
+        *sequence_number += 1;
+
+        let flight = received.remove(&peer).unwrap_or_default();
+        let acknowledges = if msg == MessageType::Ack { flight } else { Vec::new() };
+        if msg != MessageType::Ack {
+            received.entry(peer.other()).or_default().push(number);
+        }
+        records.push(Record { peer, msg, number, acknowledges });
+
+        let (next, actions) = step_with_actions(state, opts);
+        for action in actions {
+            match action {
+                Action::StartWriteEpoch(peer, epoch) => {
+                    epochs.insert(peer, epoch);
+                }
+                Action::NextWriteEpoch(peer) => *epochs.entry(peer).or_insert(0) += 1,
+                _ => {}
+            }
+        }
+        state = next;
+    }
+    records
+}
+
+impl graph::Protocol for ProtocolOptions {
+    type State = State;
+
+    const NAME: &'static str = "dtls13";
+
+    fn step(self, st: State) -> State {
+        step(st, self)
+    }
+
+    fn sends(st: State) -> Option<(Peer, String)> {
+        st.sends().map(|(peer, msg)| (peer, format!("{:?}", msg)))
+    }
+
+    fn states() -> Vec<State> {
+        use tls13::State::*;
+        let mut all = tls13::ProtocolOptions::states()
+            .into_iter()
+            .filter(|st| {
+                !matches!(
+                    st,
+                    ClientSendsChangeCipherSpec
+                        | ServerSendsChangeCipherSpec
+                        | ClientSendsEndOfEarlyData
+                )
+            })
+            .map(State::Tls)
+            .collect::<Vec<_>>();
+        all.extend(
+            [ClientSendsFinished, ClientSendsPostHandshakeFinished, ClientSendsKeyUpdate]
+                .iter()
+                .map(|st| State::ServerSendsAck(*st)),
+        );
+        let tickets = (0..tls13::MAX_TICKETS).map(ServerSendsNewSessionTicket);
+        all.extend(tickets.map(State::ClientSendsAck));
+        all.push(State::ClientSendsAck(ServerSendsKeyUpdate));
+        all
+    }
+
+    /// A cookie comes in a HelloRetryRequest.
+    fn combinations() -> Vec<Self> {
+        let mut all = Vec::new();
+        for tls in tls13::ProtocolOptions::combinations() {
+            if tls.middlebox_compat() {
+                continue;
+            }
+            for cookie in &[false, true] {
+                if tls.hello_retry() || !*cookie {
+                    all.push(ProtocolOptions { tls, cookie: *cookie });
+                }
+            }
+        }
+        all
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = self.tls().settings();
+        settings.retain(|(name, _)| *name != "middlebox_compat");
+        settings.push(("cookie", self.cookie.to_string()));
+        settings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::{reachable_states, Protocol};
+
+    fn linearise(opts: ProtocolOptions) -> Vec<(Peer, MessageType)> {
+        opts.iter_handshake().map(|(_, peer, msg)| (peer, msg)).collect()
+    }
+
+    fn tls(msg: tls13::MessageType) -> MessageType {
+        MessageType::Tls(msg)
+    }
+
+    fn number(epoch: u64, sequence_number: u64) -> RecordNumber {
+        RecordNumber { epoch, sequence_number }
+    }
+
+    #[test]
+    fn full() {
+        use tls13::MessageType::*;
+        let trace = linearise(ProtocolOptions::default());
+        assert_eq!(
+            trace,
+            vec![
+                (Peer::Client, tls(ClientHello)),
+                (Peer::Server, tls(ServerHello)),
+                (Peer::Server, tls(EncryptedExtensions)),
+                (Peer::Server, tls(Certificate)),
+                (Peer::Server, tls(CertificateVerify)),
+                (Peer::Server, tls(Finished)),
+                (Peer::Client, tls(Finished)),
+                (Peer::Server, MessageType::Ack),
+            ]
+        );
+    }
+
+    #[test]
+    fn tickets() {
+        use tls13::MessageType::*;
+        let opts = tls13::ProtocolOptions::default().with_tickets(2);
+        let trace = linearise(ProtocolOptions::default().with_tls(opts));
+        assert_eq!(
+            trace[6..],
+            [
+                (Peer::Client, tls(Finished)),
+                (Peer::Server, MessageType::Ack),
+                (Peer::Server, tls(NewSessionTicket)),
+                (Peer::Server, tls(NewSessionTicket)),
+                (Peer::Client, MessageType::Ack),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_update() {
+        use tls13::MessageType::*;
+        let tls = tls13::ProtocolOptions::default().with_key_update(Some(Peer::Client), true);
+        let trace = linearise(ProtocolOptions::default().with_tls(tls));
+        // Neither peer sends its own KeyUpdate before the other's is
+        // acknowledged.
+        assert_eq!(
+            trace[8..],
+            [
+                (Peer::Client, self::tls(KeyUpdate)),
+                (Peer::Server, MessageType::Ack),
+                (Peer::Server, self::tls(KeyUpdate)),
+                (Peer::Client, MessageType::Ack),
+            ]
+        );
+    }
+
+    #[test]
+    fn cookie() {
+        let opts = ProtocolOptions::default().with_cookie(true);
+        let mut state = State::default();
+        let mut cookies = Vec::new();
+        while let Some((peer, msg)) = state.sends() {
+            if state.cookie(opts) {
+                cookies.push((peer, msg));
+            }
+            state = step(state, opts);
+        }
+        assert_eq!(
+            cookies,
+            [
+                (Peer::Server, tls(tls13::MessageType::HelloRetryRequest)),
+                (Peer::Client, tls(tls13::MessageType::ClientHello)),
+            ]
+        );
+
+        // Without one, there's no HelloRetryRequest unless the key share
+        // calls for it, and then no cookie in it.
+        let opts = ProtocolOptions::default();
+        let trace = linearise(opts);
+        assert!(!trace.contains(&(Peer::Server, tls(tls13::MessageType::HelloRetryRequest))));
+        let opts = opts.with_tls(tls13::ProtocolOptions::default().with_hello_retry(true));
+        let state = State::Tls(tls13::State::ServerSendsHelloRetryRequest);
+        assert!(!state.cookie(opts));
+        assert_eq!(linearise(opts), linearise(ProtocolOptions::default().with_cookie(true)));
+
+        // The cookie's HelloRetryRequest survives options set after it, and
+        // goes with it.
+        let tls = tls13::ProtocolOptions::default().with_tickets(1);
+        let cookie = ProtocolOptions::default().with_cookie(true).with_tls(tls);
+        assert!(cookie.tls().hello_retry());
+        let state = State::Tls(tls13::State::ServerSendsHelloRetryRequest);
+        assert!(linearise(cookie).contains(&state.sends().unwrap()));
+        assert!(state.cookie(cookie));
+        assert!(!cookie.with_cookie(false).tls().hello_retry());
+        assert!(opts.with_cookie(true).with_cookie(false).tls().hello_retry());
+    }
+
+    #[test]
+    fn no_change_cipher_spec_or_end_of_early_data() {
+        let states = ProtocolOptions::states();
+        for opts in ProtocolOptions::combinations() {
+            for (_, msg) in linearise(opts) {
+                assert_ne!(msg, tls(tls13::MessageType::ChangeCipherSpec));
+                assert_ne!(msg, tls(tls13::MessageType::EndOfEarlyData));
+            }
+            assert!(reachable_states(opts).iter().all(|st| states.contains(st)));
+        }
+
+        // Not even when the TLS 1.3 options ask for middlebox compatibility,
+        // through `with_tls` or not.
+        let compat = tls13::ProtocolOptions::default().with_middlebox_compat(true);
+        let early = compat.with_psk(true).with_early_data(true);
+        let all = [
+            ProtocolOptions::default().with_tls(compat),
+            ProtocolOptions::default().with_tls(early.with_hello_retry(true)),
+            ProtocolOptions { tls: early, cookie: true },
+        ];
+        let ccs = tls(tls13::MessageType::ChangeCipherSpec);
+        for opts in &all {
+            assert!(!opts.tls().middlebox_compat());
+            assert!(linearise(*opts).iter().all(|(_, msg)| *msg != ccs), "{:?}", opts);
+        }
+    }
+
+    #[test]
+    fn record_numbers() {
+        let records = records(ProtocolOptions::default());
+        let numbers = records.iter().map(|r| (r.peer, r.number)).collect::<Vec<_>>();
+        assert_eq!(
+            numbers,
+            [
+                (Peer::Client, number(0, 0)),
+                (Peer::Server, number(0, 0)),
+                (Peer::Server, number(2, 0)),
+                (Peer::Server, number(2, 1)),
+                (Peer::Server, number(2, 2)),
+                (Peer::Server, number(2, 3)),
+                (Peer::Client, number(2, 0)),
+                (Peer::Server, number(3, 0)),
+            ]
+        );
+        assert_eq!(records[7].acknowledges, [number(2, 0)]);
+        assert!(records[..7].iter().all(|r| r.acknowledges.is_empty()));
+    }
+
+    #[test]
+    fn early_data_epochs() {
+        use tls13::MessageType::*;
+        let early = tls13::ProtocolOptions::default()
+            .with_psk(true)
+            .with_early_data(true)
+            .with_accept_early_data(true);
+        let records = records(ProtocolOptions::default().with_tls(early));
+        assert_eq!(records[1].msg, tls(EarlyData));
+        assert_eq!(records[1].number, number(1, 0));
+        // Without an EndOfEarlyData, the client's Finished is the first
+        // record of its handshake epoch.
+        let finished = records.iter().find(|r| r.peer == Peer::Client && r.msg == tls(Finished));
+        assert_eq!(finished.unwrap().number, number(2, 0));
+
+        // After a HelloRetryRequest the second ClientHello goes in the clear
+        // again, after the first.
+        let records = super::records(ProtocolOptions::default().with_tls(early).with_cookie(true));
+        let hellos = records.iter().filter(|r| r.msg == tls(ClientHello));
+        let hellos = hellos.map(|r| r.number).collect::<Vec<_>>();
+        assert_eq!(hellos, [number(0, 0), number(0, 1)]);
+    }
+
+    #[test]
+    fn epoch_actions() {
+        let opts = ProtocolOptions::default();
+        let hello = State::Tls(tls13::State::ServerSendsServerHello);
+        let (_, actions) = step_with_actions(hello, opts);
+        assert_eq!(
+            actions[1..],
+            [
+                Action::Tls(tls13::Action::SwitchToWriteKeys(Peer::Server, Keys::Handshake)),
+                Action::StartWriteEpoch(Peer::Server, 2),
+                Action::Tls(tls13::Action::SwitchToReadKeys(Peer::Client, Keys::Handshake)),
+                Action::StartReadEpoch(Peer::Client, 2),
+            ]
+        );
+        let ack = State::ServerSendsAck(tls13::State::ClientSendsFinished);
+        assert_eq!(step_with_actions(ack, opts).1, []);
+        let tls = tls13::ProtocolOptions::default().with_key_update(Some(Peer::Client), false);
+        let (_, actions) = step_with_actions(
+            State::Tls(tls13::State::ClientSendsKeyUpdate),
+            ProtocolOptions::default().with_tls(tls),
+        );
+        assert!(actions.contains(&Action::NextWriteEpoch(Peer::Client)));
+        assert!(actions.contains(&Action::NextReadEpoch(Peer::Server)));
+    }
+
+    #[test]
+    fn key_update_epochs() {
+        use tls13::MessageType::*;
+        let tls = tls13::ProtocolOptions::default()
+            .with_tickets(1)
+            .with_key_update(Some(Peer::Server), true);
+        let records = records(ProtocolOptions::default().with_tls(tls));
+        let records = &records[records.len() - 6..];
+        let summary = records.iter().map(|r| (r.peer, r.msg, r.number)).collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (Peer::Server, self::tls(NewSessionTicket), number(3, 1)),
+                (Peer::Client, MessageType::Ack, number(3, 0)),
+                (Peer::Server, self::tls(KeyUpdate), number(3, 2)),
+                (Peer::Client, MessageType::Ack, number(3, 1)),
+                (Peer::Client, self::tls(KeyUpdate), number(3, 2)),
+                (Peer::Server, MessageType::Ack, number(4, 0)),
+            ]
+        );
+        assert_eq!(records[1].acknowledges, [number(3, 1)]);
+        assert_eq!(records[3].acknowledges, [number(3, 2)]);
+        assert_eq!(records[5].acknowledges, [number(3, 2)]);
+    }
+}
@@ -0,0 +1,186 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::aes;
+use crate::chacha20;
+use crate::hash::HashSpec;
+use crate::tls13_keysched::hkdf_expand_label;
+use crate::tls13_record::Aead;
+
+// Epochs and sequence numbers of DTLS records (RFC 6347, 4.1 and RFC 9147,
+// 4). Each record is named by the epoch of the keys that protect it and its
+// sequence number within that epoch: a sender counts its records from 0 in
+// each epoch, and a receiver keeps a window per epoch of the sequence
+// numbers it has seen, to drop replayed records.
+
+// Sequence numbers fill 48 bits of the headers that carry them whole, and
+// may not wrap: a sender rekeys or closes the connection first.
+pub const MAX_SEQUENCE_NUMBER: u64 = (1 << 48) - 1;
+
+pub fn next_sequence_number(seq: u64) -> Result<u64, String> {
+    if seq >= MAX_SEQUENCE_NUMBER {
+        return Err("Sequence number exhausted".to_string());
+    }
+    Ok(seq + 1)
+}
+
+// A DTLS 1.2 epoch is a u16 that starts at 0, and each ChangeCipherSpec
+// moves its sender on to the next one, in a renegotiation too.
+pub const MAX_DTLS12_EPOCH: u64 = 0xffff;
+
+pub fn next_dtls12_epoch(epoch: u64) -> Result<u64, String> {
+    if epoch >= MAX_DTLS12_EPOCH {
+        return Err("Epoch exhausted".to_string());
+    }
+    Ok(epoch + 1)
+}
+
+// DTLS 1.3 fixes the first epochs by their keys (RFC 9147, 6.1):
+//     0: plaintext, 1: early data, 2: handshake, 3: application data
+// after which each KeyUpdate moves its sender on to the next application
+// data epoch. Epochs are u64s, of which the header carries the low two bits.
+pub const DTLS13_APPLICATION_EPOCH: u64 = 3;
+
+pub fn next_dtls13_epoch(epoch: u64) -> Result<u64, String> {
+    if epoch < DTLS13_APPLICATION_EPOCH {
+        return Err("Only application data epochs are updated".to_string());
+    }
+    if epoch == u64::MAX {
+        return Err("Epoch exhausted".to_string());
+    }
+    Ok(epoch + 1)
+}
+
+// The anti-replay window (RFC 6347, 4.1.2.6): a bit for each of the 64
+// sequence numbers up to the highest received in the epoch. A record is
+// checked against the window before it is deprotected, and only marked in
+// it once it has been, so that forged records can't move the window on.
+pub const REPLAY_WINDOW_LEN: u64 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct ReplayWindow {
+    // The highest sequence number received, if any; bit i of the bitmap
+    // is set if the one i below it was received.
+    right: Option<u64>,
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    pub fn new() -> ReplayWindow {
+        ReplayWindow::default()
+    }
+
+    // Whether a record with this sequence number could be fresh: past the
+    // right edge of the window, or inside it and not yet received. Records
+    // left of the window are dropped.
+    pub fn check(&self, seq: u64) -> bool {
+        match self.right {
+            None => true,
+            Some(right) if seq > right => true,
+            Some(right) => {
+                let i = right - seq;
+                i < REPLAY_WINDOW_LEN && self.bitmap & (1 << i) == 0
+            }
+        }
+    }
+
+    // Marks a deprotected record as received, sliding the window right if it
+    // is the highest yet.
+    pub fn update(&mut self, seq: u64) {
+        match self.right {
+            Some(right) if seq <= right => {
+                let i = right - seq;
+                if i < REPLAY_WINDOW_LEN {
+                    self.bitmap |= 1 << i;
+                }
+            }
+            Some(right) => {
+                let shift = seq - right;
+                self.bitmap = if shift < REPLAY_WINDOW_LEN { self.bitmap << shift } else { 0 };
+                self.bitmap |= 1;
+                self.right = Some(seq);
+            }
+            None => {
+                self.bitmap = 1;
+                self.right = Some(seq);
+            }
+        }
+    }
+}
+
+// A DTLS 1.3 header carries only the low 8 or 16 bits of the sequence
+// number. The receiver takes the sequence number with those bits closest to
+// `expected`, one past the highest it has deprotected in the epoch
+// (RFC 9147, 4.2.2).
+pub fn reconstruct_sequence_number(expected: u64, low: u64, bits: u32) -> u64 {
+    let window = 1u64 << bits;
+    let candidate = (expected & !(window - 1)) | (low & (window - 1));
+    let mut best = candidate;
+    if candidate >= window && (candidate - window).abs_diff(expected) < best.abs_diff(expected) {
+        best = candidate - window;
+    }
+    let above = candidate + window;
+    if above <= MAX_SEQUENCE_NUMBER && above.abs_diff(expected) < best.abs_diff(expected) {
+        best = above;
+    }
+    best
+}
+
+// Record number encryption (RFC 9147, 4.2.3). The sequence number in a
+// DTLS 1.3 header is XORed with a mask made from the sn_key of the sender's
+// traffic secret and the first 16 bytes of the record's ciphertext:
+//     sn_key = HKDF-Expand-Label(Secret, "sn", "", key_length)
+pub const SAMPLE_LEN: usize = 16;
+
+pub fn sn_key<H: HashSpec>(traffic_secret: Bytes, aead: Aead) -> Bytes {
+    hkdf_expand_label::<H>(traffic_secret, "sn", Bytes::new_len(0), aead.key_len()).unwrap()
+}
+
+// For AES, Mask = AES-ECB(sn_key, Ciphertext[0..15]); for ChaCha20, the key
+// stream of ChaCha20 keyed with sn_key, with Ciphertext[0..3] as its little
+// endian counter and Ciphertext[4..15] as its nonce.
+pub fn record_number_mask(aead: Aead, sn_key: &Bytes, ciphertext: &Bytes) -> Result<Bytes, String> {
+    if sn_key.len() != aead.key_len() {
+        return Err("Invalid key length".to_string());
+    }
+    if ciphertext.len() < SAMPLE_LEN {
+        return Err("Ciphertext too short to sample".to_string());
+    }
+    let sample = aes::Block::from(&ciphertext[0..SAMPLE_LEN]);
+    match aead {
+        Aead::Aes128Gcm => {
+            let mask = aes::aes128_encrypt_block(aes::Key::from(sn_key.raw()), sample);
+            Ok(Bytes::from(mask.raw()))
+        }
+        Aead::Aes256Gcm => {
+            let mask = aes::aes256_encrypt_block(aes::Key256::from(sn_key.raw()), sample);
+            Ok(Bytes::from(mask.raw()))
+        }
+        Aead::ChaCha20Poly1305 => {
+            let counter = u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+            let iv = chacha20::IV::from(&ciphertext[4..SAMPLE_LEN]);
+            let key = chacha20::Key::from(sn_key.raw());
+            chacha20::chacha20(key, counter, iv, Bytes::new_len(SAMPLE_LEN))
+        }
+    }
+}
+
+// The 1 or 2 byte sequence number field of a header, encrypted or, the same
+// way, decrypted.
+pub fn encrypt_sequence_number(
+    aead: Aead,
+    sn_key: &Bytes,
+    seq: &Bytes,
+    ciphertext: &Bytes,
+) -> Result<Bytes, String> {
+    if seq.len() != 1 && seq.len() != 2 {
+        return Err("Invalid sequence number length".to_string());
+    }
+    let mask = record_number_mask(aead, sn_key, ciphertext)?;
+    let mut out = seq.clone();
+    for i in 0..seq.len() {
+        out[i] ^= mask[i];
+    }
+    Ok(out)
+}
@@ -0,0 +1,154 @@
+// ECDSA (SEC 1, section 4.1) over the curves of `weierstrass_curve!`, with
+// the deterministic nonces of RFC 6979 as an option. Messages are hashed with
+// any H, whose output is cut to the length of the group order n.
+//
+// A curve is given by the element type of the field mod n and its scalar
+// type; the other items used are those `weierstrass_curve!` defines. n must
+// have as many bytes as the field, with a whole number of bytes, as it does
+// for P-256 and P-384.
+macro_rules! ecdsa {
+    ($order:ident, $scalar:ident) => {
+        // r and s as big-endian integers of the field's byte length.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Signature {
+            pub r: Bytes,
+            pub s: Bytes,
+        }
+
+        fn decode_order(b: FieldBytes) -> $order {
+            let mut le = [0u8; FIELD_LEN];
+            for i in 0..FIELD_LEN {
+                le[i] = b[FIELD_LEN - 1 - i];
+            }
+            $order::from_bytes_le(&le)
+        }
+
+        fn encode_order(x: $order) -> FieldBytes {
+            let le = x.to_bytes_le();
+            let mut out = FieldBytes::new();
+            for i in 0..le.len().min(FIELD_LEN) {
+                out[FIELD_LEN - 1 - i] = le[i];
+            }
+            out
+        }
+
+        fn order_to_scalar(x: $order) -> $scalar {
+            $scalar::from_bytes_le(&x.to_bytes_le())
+        }
+
+        // Private keys, nonces, r and s are all in [1, n - 1].
+        fn decode_nonzero(b: &Bytes) -> Result<$order, String> {
+            if b.len() != FIELD_LEN {
+                return Err("Invalid scalar length".to_string());
+            }
+            let b = FieldBytes::from(&b[..]);
+            let x = decode_order(b);
+            if encode_order(x) != b || x == $order::from(0) {
+                return Err("Scalar out of range".to_string());
+            }
+            Ok(x)
+        }
+
+        // bits2int of RFC 6979, section 2.3.2: the leftmost bits of the hash,
+        // as many as n has, reduced mod n.
+        fn bits2int(h: Bytes) -> $order {
+            let mut e = FieldBytes::new();
+            if h.len() >= FIELD_LEN {
+                e.update_raw(0, &h[0..FIELD_LEN]);
+            } else {
+                e.update_raw(FIELD_LEN - h.len(), &h[..]);
+            }
+            decode_order(e)
+        }
+
+        // The nonce of RFC 6979, section 3.2: HMAC_DRBG over H, seeded with
+        // the private key and the message hash.
+        pub fn rfc6979_nonce<H: crate::hash::HashSpec>(sk: Bytes, msg: Bytes) -> Bytes {
+            let h = encode_order(bits2int(H::hash(msg)));
+            let mut v = Bytes::from_vec(vec![1; H::HASH_LEN]);
+            let mut k = Bytes::new_len(H::HASH_LEN);
+            for &sep in [0u8, 1].iter() {
+                let mut data = Bytes::new_len(H::HASH_LEN + 1 + 2 * FIELD_LEN);
+                data.update(0, &v);
+                data[H::HASH_LEN] = sep;
+                data.update(H::HASH_LEN + 1, &sk);
+                data.update_raw(H::HASH_LEN + 1 + FIELD_LEN, &h[..]);
+                k = crate::hmac::hmac::<H>(k, data);
+                v = crate::hmac::hmac::<H>(k.clone(), v);
+            }
+            loop {
+                let mut t = Bytes::new_len(FIELD_LEN);
+                let mut filled = 0;
+                while filled < FIELD_LEN {
+                    v = crate::hmac::hmac::<H>(k.clone(), v);
+                    let n = v.len().min(FIELD_LEN - filled);
+                    t.update_raw(filled, &v[0..n]);
+                    filled += n;
+                }
+                if decode_nonzero(&t).is_ok() {
+                    return t;
+                }
+                let mut data = Bytes::new_len(H::HASH_LEN + 1);
+                data.update(0, &v);
+                k = crate::hmac::hmac::<H>(k, data);
+                v = crate::hmac::hmac::<H>(k.clone(), v);
+            }
+        }
+
+        // r = x(k * G) mod n and s = k^-1 * (e + r * d) mod n, for the private
+        // key d, the nonce k and the message hash e.
+        pub fn ecdsa_sign<H: crate::hash::HashSpec>(
+            sk: Bytes,
+            nonce: Bytes,
+            msg: Bytes,
+        ) -> Result<Signature, String> {
+            let d = decode_nonzero(&sk)?;
+            let k = decode_nonzero(&nonce)?;
+            let e = bits2int(H::hash(msg));
+            let r = decode_order(encode_field(point_mul(order_to_scalar(k)).0));
+            let s = k.inv() * (e + r * d);
+            if r == $order::from(0) || s == $order::from(0) {
+                return Err("Nonce gives an invalid signature".to_string());
+            }
+            Ok(Signature {
+                r: Bytes::from(&encode_order(r)[..]),
+                s: Bytes::from(&encode_order(s)[..]),
+            })
+        }
+
+        pub fn ecdsa_sign_deterministic<H: crate::hash::HashSpec>(
+            sk: Bytes,
+            msg: Bytes,
+        ) -> Result<Signature, String> {
+            let nonce = rfc6979_nonce::<H>(sk.clone(), msg.clone());
+            ecdsa_sign::<H>(sk, nonce, msg)
+        }
+
+        // The signature is valid if r = x(u1 * G + u2 * Q) mod n, with
+        // u1 = e * s^-1 and u2 = r * s^-1, for the public key Q.
+        pub fn ecdsa_verify<H: crate::hash::HashSpec>(
+            pk: Bytes,
+            msg: Bytes,
+            signature: &Signature,
+        ) -> Result<(), String> {
+            let q = decode_point(pk)?;
+            let r = decode_nonzero(&signature.r).map_err(|_| "Invalid signature".to_string())?;
+            let s = decode_nonzero(&signature.s).map_err(|_| "Invalid signature".to_string())?;
+            let e = bits2int(H::hash(msg));
+            let w = s.inv();
+            let u1 = montgomery_ladder(order_to_scalar(e * w), base_point());
+            let u2 = montgomery_ladder(order_to_scalar(r * w), affine_to_jacobian(q));
+            let x = point_add(u1, u2);
+            if is_point_at_infinity(x) {
+                return Err("Invalid signature".to_string());
+            }
+            if decode_order(encode_field(jacobian_to_affine(x).0)) == r {
+                Ok(())
+            } else {
+                Err("Invalid signature".to_string())
+            }
+        }
+    };
+}
+
+pub(crate) use ecdsa;
@@ -0,0 +1,236 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::sha512::sha512;
+
+// Ed25519 as in RFC 8032, section 5.1: EdDSA over the twisted Edwards curve
+// -x^2 + y^2 = 1 + d x^2 y^2 mod 2^255-19, with SHA-512.
+
+// Define field mod 2^255-19
+#[field(7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed)]
+struct FieldElement;
+
+// Define the field mod the group order L = 2^252 + 27742317777372353535851937790883648493
+#[field(1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed)]
+struct ScalarElement;
+
+// Scalars and exponents are 256-bit integers
+#[bits(256)]
+struct Scalar;
+
+bytes!(SecretKey, 32);
+bytes!(PublicKey, 32);
+bytes!(Signature, 64);
+bytes!(SerializedPoint, 32);
+
+// d = -121665/121666
+static D: &str = "52036cee2b6ffe738cc740797779e89800700a4d4141d8ab75eb4dca135978a3";
+// 2^((p-1)/4), a square root of -1
+static SQRT_M1: &str = "2b8324804fc1df0b2b4d00993dfbd7a72f431806ad2fe478c4ee1b274a0ea0b0";
+// (p-5)/8
+static P_5_8: &str = "0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd";
+
+static BX: &str = "216936d3cd6e53fec0a4e231fdd6dc5c692cc7609525a7b2c9562d608f25d51a";
+static BY: &str = "6666666666666666666666666666666666666666666666666666666666666658";
+
+// Points in extended coordinates (X, Y, Z, T) with x = X/Z, y = Y/Z and
+// x * y = T/Z (RFC 8032, section 5.1.4).
+#[derive(Copy, Clone, Debug)]
+struct Point(FieldElement, FieldElement, FieldElement, FieldElement);
+
+fn base_point() -> Point {
+    let x = FieldElement::from(BX);
+    let y = FieldElement::from(BY);
+    Point(x, y, FieldElement::from(1), x * y)
+}
+
+fn neutral() -> Point {
+    Point(
+        FieldElement::from(0),
+        FieldElement::from(1),
+        FieldElement::from(1),
+        FieldElement::from(0),
+    )
+}
+
+// The addition formulas are complete: they double a point as well.
+fn point_add(p: Point, q: Point) -> Point {
+    let Point(x1, y1, z1, t1) = p;
+    let Point(x2, y2, z2, t2) = q;
+    let d = FieldElement::from(D);
+    let two = FieldElement::from(2);
+    let a = (y1 - x1) * (y2 - x2);
+    let b = (y1 + x1) * (y2 + x2);
+    let c = t1 * two * d * t2;
+    let d = z1 * two * z2;
+    let e = b - a;
+    let f = d - c;
+    let g = d + c;
+    let h = b + a;
+    Point(e * f, g * h, f * g, e * h)
+}
+
+fn point_mul(k: Scalar, p: Point) -> Point {
+    let mut q = neutral();
+    for i in 0..256 {
+        q = point_add(q, q);
+        if k.bit(255 - i) == 1 {
+            q = point_add(q, p);
+        }
+    }
+    q
+}
+
+// [8]P, which clears any small-order component.
+fn mul_by_cofactor(p: Point) -> Point {
+    let p2 = point_add(p, p);
+    let p4 = point_add(p2, p2);
+    point_add(p4, p4)
+}
+
+// Two points are equal if x1/z1 = x2/z2 and y1/z1 = y2/z2.
+fn point_eq(p: Point, q: Point) -> bool {
+    p.0 * q.2 == q.0 * p.2 && p.1 * q.2 == q.1 * p.2
+}
+
+// x^e for a 256-bit exponent e.
+fn pow(x: FieldElement, e: Scalar) -> FieldElement {
+    let mut r = FieldElement::from(1);
+    for i in 0..256 {
+        r = r * r;
+        if e.bit(255 - i) == 1 {
+            r = r * x;
+        }
+    }
+    r
+}
+
+fn encode_field(x: FieldElement) -> SerializedPoint {
+    let le = x.to_bytes_le();
+    let mut out = SerializedPoint::new();
+    out.update_raw(0, &le[0..le.len().min(32)]);
+    out
+}
+
+// A point is encoded as y in little-endian, with the low bit of x in the top
+// bit of the last byte (RFC 8032, section 5.1.2).
+fn encode_point(p: Point) -> SerializedPoint {
+    let zi = p.2.inv();
+    let x = encode_field(p.0 * zi);
+    let mut out = encode_field(p.1 * zi);
+    out[31] |= (x[0] & 1) << 7;
+    out
+}
+
+// Decoding recovers x from y as in RFC 8032, section 5.1.3, and rejects
+// encodings with y >= p, with no square root for x, or of x = 0 with the sign
+// bit set.
+fn decode_point(b: SerializedPoint) -> Result<Point, String> {
+    let x_0 = b[31] >> 7;
+    let mut y_bytes = b;
+    y_bytes[31] &= 0x7f;
+    let y = FieldElement::from_bytes_le(&y_bytes.raw());
+    if encode_field(y) != y_bytes {
+        return Err("Point coordinate out of range".to_string());
+    }
+
+    // x^2 = u/v, with a candidate root x = u v^3 (u v^7)^((p-5)/8).
+    let one = FieldElement::from(1);
+    let u = y * y - one;
+    let v = FieldElement::from(D) * y * y + one;
+    let mut x = u * v.pow(3) * pow(u * v.pow(7), Scalar::from_hex(P_5_8));
+    if v * x * x != u {
+        if v * x * x == FieldElement::from(0) - u {
+            x = x * FieldElement::from(SQRT_M1);
+        } else {
+            return Err("Point is not on the curve".to_string());
+        }
+    }
+    if x == FieldElement::from(0) && x_0 == 1 {
+        return Err("Invalid point encoding".to_string());
+    }
+    if encode_field(x)[0] & 1 != x_0 {
+        x = FieldElement::from(0) - x;
+    }
+    Ok(Point(x, y, one, x * y))
+}
+
+fn concat(a: &[u8], b: &[u8]) -> Bytes {
+    let mut out = Bytes::new_len(a.len() + b.len());
+    out.update_raw(0, a);
+    out.update_raw(a.len(), b);
+    out
+}
+
+// SHA-512 of the input as a little-endian integer mod L.
+fn hash_to_scalar(input: Bytes) -> ScalarElement {
+    ScalarElement::from_bytes_le(&sha512(input)[..])
+}
+
+fn encode_scalar(x: ScalarElement) -> [u8; 32] {
+    let le = x.to_bytes_le();
+    let n = le.len().min(32);
+    let mut out = [0u8; 32];
+    out[..n].copy_from_slice(&le[..n]);
+    out
+}
+
+fn to_scalar(x: ScalarElement) -> Scalar {
+    Scalar::from_bytes_le(&x.to_bytes_le())
+}
+
+// The secret scalar s is the first half of SHA-512(sk), clamped as for
+// X25519; the second half is the prefix that derives nonces.
+fn expand_secret(sk: SecretKey) -> (Scalar, Bytes) {
+    let h = sha512(Bytes::from(&sk[..]));
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&h[0..32]);
+    s[0] &= 248;
+    s[31] &= 127;
+    s[31] |= 64;
+    (Scalar::from_bytes_le(&s), Bytes::from(&h[32..64]))
+}
+
+// A = [s]B (RFC 8032, section 5.1.5).
+pub fn public_key(sk: SecretKey) -> PublicKey {
+    let (s, _) = expand_secret(sk);
+    PublicKey::from(&encode_point(point_mul(s, base_point()))[..])
+}
+
+// R = [r]B for r = SHA-512(prefix || M), and S = r + k * s mod L for
+// k = SHA-512(R || A || M); the signature is R || S (RFC 8032, section 5.1.6).
+pub fn sign(sk: SecretKey, msg: Bytes) -> Signature {
+    let (s, prefix) = expand_secret(sk);
+    let a = encode_point(point_mul(s, base_point()));
+    let r = hash_to_scalar(concat(&prefix[..], &msg[..]));
+    let r_point = encode_point(point_mul(to_scalar(r), base_point()));
+    let k = hash_to_scalar(concat(&concat(&r_point[..], &a[..])[..], &msg[..]));
+    let s = ScalarElement::from_bytes_le(&s.to_bytes_le());
+    let s_bytes = encode_scalar(r + k * s);
+
+    let mut signature = Signature::new();
+    signature.update_raw(0, &r_point[..]);
+    signature.update_raw(32, &s_bytes);
+    signature
+}
+
+// The signature is valid if S < L and [8][S]B = [8]R + [8][k]A', for
+// k = SHA-512(R || A || M) (RFC 8032, section 5.1.7).
+pub fn verify(pk: PublicKey, msg: Bytes, signature: Signature) -> Result<(), String> {
+    let a = decode_point(SerializedPoint::from(&pk[..]))?;
+    let r = decode_point(SerializedPoint::from(&signature[0..32]))?;
+    let s = ScalarElement::from_bytes_le(&signature[32..64]);
+    if encode_scalar(s)[..] != signature[32..64] {
+        return Err("Invalid signature".to_string());
+    }
+    let k = hash_to_scalar(concat(&concat(&signature[0..32], &pk[..])[..], &msg[..]));
+
+    let sb = point_mul(to_scalar(s), base_point());
+    let rka = point_add(r, point_mul(to_scalar(k), a));
+    if point_eq(mul_by_cofactor(sb), mul_by_cofactor(rka)) {
+        Ok(())
+    } else {
+        Err("Invalid signature".to_string())
+    }
+}
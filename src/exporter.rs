@@ -0,0 +1,158 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+use crate::prf::{prf, prf_legacy};
+use crate::tls13_keysched::{derive_secret, hkdf_expand_label};
+
+// Keying material exporters: RFC 5705 for TLS 1.0 to 1.2, and RFC 8446,
+// section 7.5 for TLS 1.3. DTLS-SRTP (RFC 5764), token binding (RFC 8471)
+// and channel binding (RFC 9266) all take their keys from here.
+
+// The labels the TLS 1.2 key schedule itself uses, which an exporter must
+// not (RFC 5705, section 4).
+const RESERVED_LABELS: [&str; 5] = [
+    "client finished",
+    "server finished",
+    "master secret",
+    "extended master secret",
+    "key expansion",
+];
+
+// seed = client_random + server_random [+ context_length + context_value],
+// with the context, if any, under 2^16 bytes.
+fn seed(
+    label: &str,
+    client_random: &Bytes,
+    server_random: &Bytes,
+    context: &Option<Bytes>,
+) -> Result<Bytes, String> {
+    if RESERVED_LABELS.contains(&label) {
+        return Err("Reserved exporter label".to_string());
+    }
+    let context_len = match context {
+        Some(c) if c.len() > 0xffff => return Err("Exporter context too long".to_string()),
+        Some(c) => 2 + c.len(),
+        None => 0,
+    };
+    let randoms = client_random.len() + server_random.len();
+    let mut out = Bytes::new_len(randoms + context_len);
+    out.update(0, client_random);
+    out.update(client_random.len(), server_random);
+    if let Some(c) = context {
+        out.update_raw(randoms, &(c.len() as u16).to_be_bytes());
+        out.update(randoms + 2, c);
+    }
+    Ok(out)
+}
+
+// PRF(SecurityParameters.master_secret, label, seed)[0..len-1] with the PRF
+// of TLS 1.2. No context and an empty one give different keys.
+pub fn export_keying_material<H: HashSpec>(
+    master_secret: Bytes,
+    label: &str,
+    client_random: Bytes,
+    server_random: Bytes,
+    context: Option<Bytes>,
+    len: usize,
+) -> Result<Bytes, String> {
+    let seed = seed(label, &client_random, &server_random, &context)?;
+    let label = Bytes::from_vec(label.as_bytes().to_vec());
+    Ok(prf::<H>(master_secret, label, seed, len))
+}
+
+// The same with the MD5 and SHA-1 PRF of TLS 1.0 and 1.1.
+pub fn export_keying_material_legacy(
+    master_secret: Bytes,
+    label: &str,
+    client_random: Bytes,
+    server_random: Bytes,
+    context: Option<Bytes>,
+    len: usize,
+) -> Result<Bytes, String> {
+    let seed = seed(label, &client_random, &server_random, &context)?;
+    let label = Bytes::from_vec(label.as_bytes().to_vec());
+    Ok(prf_legacy(master_secret, label, seed, len))
+}
+
+// TLS-Exporter(label, context_value, key_length) =
+//     HKDF-Expand-Label(Derive-Secret(Secret, label, ""),
+//                       "exporter", Hash(context_value), key_length)
+// where Secret is the exporter_master_secret, or the
+// early_exporter_master_secret for keys used with early data. TLS 1.3 makes
+// no difference between no context and an empty one.
+pub fn tls13_exporter<H: HashSpec>(
+    exporter_master_secret: Bytes,
+    label: &str,
+    context: Bytes,
+    len: usize,
+) -> Result<Bytes, String> {
+    let secret = derive_secret::<H>(exporter_master_secret, label, H::hash(Bytes::new_len(0)));
+    hkdf_expand_label::<H>(secret, "exporter", H::hash(context), len)
+}
+
+// DTLS-SRTP keys (RFC 5764, 4.2): once the handshake is done, each peer
+// exports 2 * (key_len + salt_len) bytes with this label and no context,
+// and splits them as
+//     client_write_SRTP_master_key[key_len]
+//     server_write_SRTP_master_key[key_len]
+//     client_write_SRTP_master_salt[salt_len]
+//     server_write_SRTP_master_salt[salt_len]
+// with the lengths of the negotiated protection profile; see
+// `srtp::SrtpProtectionProfile::key_and_salt_len`.
+pub const DTLS_SRTP_LABEL: &str = "EXTRACTOR-dtls_srtp";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SrtpKeys {
+    pub client_key: Bytes,
+    pub server_key: Bytes,
+    pub client_salt: Bytes,
+    pub server_salt: Bytes,
+}
+
+pub fn srtp_keys(material: &Bytes, key_len: usize, salt_len: usize) -> Result<SrtpKeys, String> {
+    if material.len() != 2 * (key_len + salt_len) {
+        return Err("Invalid keying material length".to_string());
+    }
+    let salts = 2 * key_len;
+    Ok(SrtpKeys {
+        client_key: Bytes::from(&material[0..key_len]),
+        server_key: Bytes::from(&material[key_len..salts]),
+        client_salt: Bytes::from(&material[salts..salts + salt_len]),
+        server_salt: Bytes::from(&material[salts + salt_len..material.len()]),
+    })
+}
+
+// The keys of DTLS 1.2, with the PRF of its cipher suite.
+pub fn dtls_srtp_keys<H: HashSpec>(
+    master_secret: Bytes,
+    client_random: Bytes,
+    server_random: Bytes,
+    key_len: usize,
+    salt_len: usize,
+) -> Result<SrtpKeys, String> {
+    let len = 2 * (key_len + salt_len);
+    let material = export_keying_material::<H>(
+        master_secret,
+        DTLS_SRTP_LABEL,
+        client_random,
+        server_random,
+        None,
+        len,
+    )?;
+    srtp_keys(&material, key_len, salt_len)
+}
+
+// The keys of DTLS 1.3, exported from the exporter_master_secret with an
+// empty context (RFC 9147, 1 leaves RFC 5764 as it is).
+pub fn dtls13_srtp_keys<H: HashSpec>(
+    exporter_master_secret: Bytes,
+    key_len: usize,
+    salt_len: usize,
+) -> Result<SrtpKeys, String> {
+    let len = 2 * (key_len + salt_len);
+    let material =
+        tls13_exporter::<H>(exporter_master_secret, DTLS_SRTP_LABEL, Bytes::new_len(0), len)?;
+    srtp_keys(&material, key_len, salt_len)
+}
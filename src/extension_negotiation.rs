@@ -0,0 +1,394 @@
+use crate::certificate::CertificateType;
+use crate::extensions::ExtensionType;
+use crate::tls12::{AlertDescription, KeyExchange, Peer, ProtocolOptions};
+use std::fmt;
+
+/// The handshake messages that carry extensions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExtensionMessage {
+    ClientHello,
+    ServerHello,
+    HelloRetryRequest,
+    EncryptedExtensions,
+    CertificateRequest,
+    /// A TLS 1.3 Certificate's entries, sent by the given peer.
+    Certificate(Peer),
+    NewSessionTicket,
+}
+
+impl fmt::Display for ExtensionMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ExtensionMessage::*;
+        match self {
+            ClientHello => write!(f, "client_hello"),
+            ServerHello => write!(f, "server_hello"),
+            HelloRetryRequest => write!(f, "hello_retry_request"),
+            EncryptedExtensions => write!(f, "encrypted_extensions"),
+            CertificateRequest => write!(f, "certificate_request"),
+            Certificate(peer) => write!(f, "{} certificate", peer),
+            NewSessionTicket => write!(f, "new_session_ticket"),
+        }
+    }
+}
+
+impl ExtensionMessage {
+    /// The message whose extensions this one's answer, if it answers any:
+    /// an endpoint sends no extension the other didn't ask for first (RFC
+    /// 5246, 7.4.1.4 and RFC 8446, 4.2). CertificateRequest and
+    /// NewSessionTicket make requests of their own.
+    fn answers(self) -> Option<ExtensionMessage> {
+        use ExtensionMessage::*;
+        match self {
+            ServerHello | HelloRetryRequest | EncryptedExtensions => Some(ClientHello),
+            Certificate(Peer::Server) => Some(ClientHello),
+            Certificate(Peer::Client) => Some(CertificateRequest),
+            ClientHello | CertificateRequest | NewSessionTicket => None,
+        }
+    }
+}
+
+use ExtensionMessage::{Certificate as CT, ClientHello as CH, EncryptedExtensions as EE};
+use ExtensionMessage::{CertificateRequest as CR, HelloRetryRequest as HRR};
+use ExtensionMessage::{NewSessionTicket as NST, ServerHello as SH};
+
+/// Where a TLS 1.3 extension may appear, after the table of RFC 8446, 4.2.
+/// The TLS 1.2 extensions a client offers for the sake of TLS 1.2 servers
+/// belong in the ClientHello only.
+const TLS13_RULES: [(ExtensionType, &[ExtensionMessage]); 31] = {
+    use ExtensionType::*;
+    const CERTS: ExtensionMessage = CT(Peer::Server);
+    const CLIENT_CERTS: ExtensionMessage = CT(Peer::Client);
+    [
+        (ServerName, &[CH, EE]),
+        (MaxFragmentLength, &[CH, EE]),
+        (ClientCertificateUrl, &[CH]),
+        (StatusRequest, &[CH, CR, CERTS, CLIENT_CERTS]),
+        (UserMapping, &[CH]),
+        (SupportedGroups, &[CH, EE]),
+        (EcPointFormats, &[CH]),
+        (SignatureAlgorithms, &[CH, CR]),
+        (UseSrtp, &[CH, EE]),
+        (Heartbeat, &[CH, EE]),
+        (Alpn, &[CH, EE]),
+        (SignedCertificateTimestamp, &[CH, CR, CERTS, CLIENT_CERTS]),
+        (ClientCertificateType, &[CH, EE]),
+        (ServerCertificateType, &[CH, EE]),
+        (Padding, &[CH]),
+        (EncryptThenMac, &[CH]),
+        (ExtendedMasterSecret, &[CH]),
+        (CompressCertificate, &[CH, CR]),
+        (SessionTicket, &[CH]),
+        (PreSharedKey, &[CH, SH]),
+        (EarlyData, &[CH, EE, NST]),
+        (SupportedVersions, &[CH, SH, HRR]),
+        (Cookie, &[CH, HRR]),
+        (PskKeyExchangeModes, &[CH]),
+        (CertificateAuthorities, &[CH, CR]),
+        (PostHandshakeAuth, &[CH]),
+        (SignatureAlgorithmsCert, &[CH, CR]),
+        (KeyShare, &[CH, SH, HRR]),
+        (NextProtocolNegotiation, &[CH]),
+        // The server's retry_configs, or its acceptance confirmation in a
+        // HelloRetryRequest (RFC 9849, 5).
+        (EncryptedClientHello, &[CH, EE, HRR]),
+        (RenegotiationInfo, &[CH]),
+    ]
+};
+
+/// The extensions a TLS 1.2 server may answer in its ServerHello. The rest
+/// are the client's alone: signature_algorithms and supported_groups (RFC
+/// 5246, 7.4.1.4.1 and RFC 8422, 5.2), padding, and the TLS 1.3 ones a
+/// client offering both versions sends.
+const TLS12_SERVER_HELLO: [ExtensionType; 17] = {
+    use ExtensionType::*;
+    [
+        ServerName,
+        MaxFragmentLength,
+        ClientCertificateUrl,
+        StatusRequest,
+        UserMapping,
+        EcPointFormats,
+        UseSrtp,
+        Heartbeat,
+        Alpn,
+        SignedCertificateTimestamp,
+        ClientCertificateType,
+        ServerCertificateType,
+        EncryptThenMac,
+        ExtendedMasterSecret,
+        SessionTicket,
+        NextProtocolNegotiation,
+        RenegotiationInfo,
+    ]
+};
+
+/// Whether `message` may carry an extension of type `t` in TLS 1.3, or in
+/// TLS 1.2, where only the hellos carry any. Unknown extensions are never
+/// ruled out; only what was offered limits them.
+pub fn allowed_in(t: ExtensionType, message: ExtensionMessage, tls13: bool) -> bool {
+    if let ExtensionType::Unknown(_) = t {
+        return true;
+    }
+    if tls13 {
+        TLS13_RULES.iter().any(|(r, messages)| *r == t && messages.contains(&message))
+    } else {
+        match message {
+            CH => true,
+            SH => TLS12_SERVER_HELLO.contains(&t),
+            _ => false,
+        }
+    }
+}
+
+/// An extension in the wrong place.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExtensionViolation {
+    /// An answer to an extension that wasn't offered.
+    Unsolicited(ExtensionMessage, ExtensionType),
+    /// An extension the message may not carry at all.
+    Forbidden(ExtensionMessage, ExtensionType),
+    /// The same extension twice in one message.
+    Duplicate(ExtensionMessage, ExtensionType),
+}
+
+impl fmt::Display for ExtensionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtensionViolation::Unsolicited(m, t) => write!(f, "unsolicited {} in {}", t, m),
+            ExtensionViolation::Forbidden(m, t) => write!(f, "{} not allowed in {}", t, m),
+            ExtensionViolation::Duplicate(m, t) => write!(f, "duplicate {} in {}", t, m),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionViolation {}
+
+impl ExtensionViolation {
+    /// The alert the receiver answers with (RFC 8446, 4.2 and 4.1.2).
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            ExtensionViolation::Unsolicited(..) => AlertDescription::UnsupportedExtension,
+            ExtensionViolation::Forbidden(..) => AlertDescription::IllegalParameter,
+            ExtensionViolation::Duplicate(..) => AlertDescription::DecodeError,
+        }
+    }
+}
+
+/// The extensions each message of a handshake carried, in the order the
+/// messages were sent.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExtensionSet(Vec<(ExtensionMessage, Vec<ExtensionType>)>);
+
+impl ExtensionSet {
+    pub fn new() -> ExtensionSet {
+        ExtensionSet::default()
+    }
+
+    pub fn with(mut self, message: ExtensionMessage, extensions: &[ExtensionType]) -> Self {
+        self.0.push((message, extensions.to_vec()));
+        self
+    }
+
+    pub fn messages(&self) -> &[(ExtensionMessage, Vec<ExtensionType>)] {
+        &self.0
+    }
+
+    /// The extensions `message` carried, if it was sent.
+    pub fn extensions(&self, message: ExtensionMessage) -> Option<&[ExtensionType]> {
+        self.0.iter().rev().find(|(m, _)| *m == message).map(|(_, e)| &e[..])
+    }
+
+    /// The extensions of the TLS 1.2 handshake `opts` describes, which
+    /// `tls12::Trace::linearise(opts)` walks: those the options decide,
+    /// plus the ECC ones and signature_algorithms every client sends.
+    pub fn tls12(opts: &ProtocolOptions) -> ExtensionSet {
+        use ExtensionType::*;
+        let ecc = matches!(opts.key_exchange, KeyExchange::EcdheRsa | KeyExchange::EcdheEcdsa);
+        let mut client = vec![SignatureAlgorithms];
+        let mut server = vec![];
+        if ecc {
+            client.extend_from_slice(&[SupportedGroups, EcPointFormats]);
+            server.push(EcPointFormats);
+        }
+        if opts.client_offers_ticket {
+            client.push(SessionTicket);
+        }
+        if opts.issues_ticket() {
+            server.push(SessionTicket);
+        }
+        if opts.server_stapled_ocsp {
+            client.push(StatusRequest);
+            if !opts.abbreviated() {
+                server.push(StatusRequest);
+            }
+        }
+        if opts.npn {
+            client.push(NextProtocolNegotiation);
+            server.push(NextProtocolNegotiation);
+        }
+        if opts.encrypt_then_mac {
+            client.push(EncryptThenMac);
+            server.push(EncryptThenMac);
+        }
+        if opts.heartbeat {
+            client.push(Heartbeat);
+            server.push(Heartbeat);
+        }
+        // The client's SupplementalData is taken for the user mapping; the
+        // formats a server sends have no extension this crate knows.
+        if opts.supplemental_data == Some(Peer::Client) {
+            client.push(UserMapping);
+            server.push(UserMapping);
+        }
+        if opts.client_certificate_url {
+            client.push(ClientCertificateUrl);
+            server.push(ClientCertificateUrl);
+        }
+        if opts.client_cert_type != CertificateType::X509 {
+            client.push(ClientCertificateType);
+            server.push(ClientCertificateType);
+        }
+        if opts.server_cert_type != CertificateType::X509 {
+            client.push(ServerCertificateType);
+            server.push(ServerCertificateType);
+        }
+        if opts.extended_master_secret {
+            client.push(ExtendedMasterSecret);
+            server.push(ExtendedMasterSecret);
+        }
+        if opts.secure_renegotiation {
+            client.push(RenegotiationInfo);
+            server.push(RenegotiationInfo);
+        }
+        ExtensionSet::new().with(CH, &client).with(SH, &server)
+    }
+
+    /// Checks every extension is in a message that may carry it, answers
+    /// one that was asked for, and appears once per message. A client
+    /// sending the renegotiation SCSV should be recorded as offering
+    /// renegotiation_info (RFC 5746, 3.6).
+    pub fn check(&self, tls13: bool) -> Result<(), ExtensionViolation> {
+        for (i, (message, extensions)) in self.0.iter().enumerate() {
+            let requests = message.answers().map(|r| {
+                self.0[..i].iter().rev().find(|(m, _)| *m == r).map_or(&[][..], |(_, e)| &e[..])
+            });
+            for (j, &t) in extensions.iter().enumerate() {
+                if extensions[..j].contains(&t) {
+                    return Err(ExtensionViolation::Duplicate(*message, t));
+                }
+                if !allowed_in(t, *message, tls13) {
+                    return Err(ExtensionViolation::Forbidden(*message, t));
+                }
+                // The cookie is the one extension a HelloRetryRequest sends
+                // unasked (RFC 8446, 4.2).
+                let cookie = *message == HRR && t == ExtensionType::Cookie;
+                if requests.is_some_and(|r| !r.contains(&t)) && !cookie {
+                    return Err(ExtensionViolation::Unsolicited(*message, t));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ExtensionType::*;
+
+    // The extensions of the OpenSSL 3.0 TLS 1.3 ClientHello in hello's
+    // tests.
+    const OPENSSL_TLS13: [ExtensionType; 11] = [
+        ServerName,
+        EcPointFormats,
+        SupportedGroups,
+        SessionTicket,
+        EncryptThenMac,
+        ExtendedMasterSecret,
+        SignatureAlgorithms,
+        SupportedVersions,
+        PskKeyExchangeModes,
+        KeyShare,
+        Padding,
+    ];
+
+    #[test]
+    fn tls12_options() {
+        for opts in ProtocolOptions::all_valid() {
+            assert_eq!(ExtensionSet::tls12(&opts).check(false), Ok(()), "{:?}", opts);
+        }
+        let etm = ProtocolOptions::builder().encrypt_then_mac(true).build().unwrap();
+        let set = ExtensionSet::tls12(&etm);
+        assert!(set.extensions(SH).unwrap().contains(&EncryptThenMac));
+        assert_eq!(set.check(false), Ok(()));
+        let raw = ProtocolOptions::builder()
+            .server_cert_type(CertificateType::RawPublicKey)
+            .build()
+            .unwrap();
+        let set = ExtensionSet::tls12(&raw);
+        assert_eq!(set.extensions(SH), Some(&[EcPointFormats, ServerCertificateType][..]));
+        assert_eq!(set.check(false), Ok(()));
+    }
+
+    #[test]
+    fn tls12_server_hello() {
+        let offer = [SignatureAlgorithms, SupportedGroups, KeyShare, SupportedVersions, ServerName];
+        let ok = ExtensionSet::new().with(CH, &offer).with(SH, &[ServerName]);
+        assert_eq!(ok.check(false), Ok(()));
+        let unasked = ExtensionSet::new().with(CH, &offer).with(SH, &[SessionTicket]);
+        let err = unasked.check(false).unwrap_err();
+        assert_eq!(err, ExtensionViolation::Unsolicited(SH, SessionTicket));
+        assert_eq!(err.alert(), AlertDescription::UnsupportedExtension);
+        for &t in &[SignatureAlgorithms, SupportedGroups, KeyShare, SupportedVersions] {
+            let set = ExtensionSet::new().with(CH, &offer).with(SH, &[t]);
+            assert_eq!(set.check(false), Err(ExtensionViolation::Forbidden(SH, t)));
+        }
+        // Unknown extensions are fine as long as they were offered.
+        let grease = ExtensionSet::new().with(CH, &[Unknown(0x0a0a)]);
+        assert_eq!(grease.clone().with(SH, &[Unknown(0x0a0a)]).check(false), Ok(()));
+        let unasked = grease.with(SH, &[Unknown(0x1a1a)]).check(false);
+        assert_eq!(unasked, Err(ExtensionViolation::Unsolicited(SH, Unknown(0x1a1a))));
+    }
+
+    #[test]
+    fn tls13_handshake() {
+        let full = ExtensionSet::new()
+            .with(CH, &OPENSSL_TLS13)
+            .with(SH, &[SupportedVersions, KeyShare])
+            .with(EE, &[ServerName, SupportedGroups])
+            .with(CR, &[SignatureAlgorithms, CertificateAuthorities])
+            .with(CT(Peer::Server), &[])
+            .with(CT(Peer::Client), &[])
+            .with(NST, &[EarlyData]);
+        assert_eq!(full.check(true), Ok(()));
+        assert_eq!(full.extensions(EE), Some(&[ServerName, SupportedGroups][..]));
+
+        let early = ExtensionSet::new().with(CH, &OPENSSL_TLS13).with(EE, &[EncryptThenMac]);
+        assert_eq!(early.check(true), Err(ExtensionViolation::Forbidden(EE, EncryptThenMac)));
+        let in_sh = ExtensionSet::new().with(CH, &OPENSSL_TLS13).with(SH, &[ServerName]);
+        assert_eq!(in_sh.check(true), Err(ExtensionViolation::Forbidden(SH, ServerName)));
+        // A client certificate answers the CertificateRequest, not the
+        // ClientHello.
+        let sct = ExtensionSet::new()
+            .with(CH, &[SignedCertificateTimestamp])
+            .with(CR, &[SignatureAlgorithms])
+            .with(CT(Peer::Client), &[SignedCertificateTimestamp]);
+        let err = ExtensionViolation::Unsolicited(CT(Peer::Client), SignedCertificateTimestamp);
+        assert_eq!(sct.check(true), Err(err));
+    }
+
+    #[test]
+    fn hello_retry_request() {
+        let hrr = ExtensionSet::new()
+            .with(CH, &OPENSSL_TLS13)
+            .with(HRR, &[SupportedVersions, KeyShare, Cookie])
+            .with(CH, &[SupportedVersions, KeyShare, Cookie])
+            .with(SH, &[SupportedVersions, KeyShare]);
+        assert_eq!(hrr.check(true), Ok(()));
+        let twice = ExtensionSet::new().with(CH, &[KeyShare, KeyShare]);
+        let err = twice.check(true).unwrap_err();
+        assert_eq!(err, ExtensionViolation::Duplicate(CH, KeyShare));
+        assert_eq!(err.to_string(), "duplicate key_share in client_hello");
+    }
+}
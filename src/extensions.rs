@@ -0,0 +1,453 @@
+use crate::codec::{self, CodecError, Reader};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// An extension as it appears on the wire (RFC 5246, 7.4.1.4):
+///
+/// ```text
+/// struct {
+///     ExtensionType extension_type;
+///     opaque extension_data<0..2^16-1>;
+/// } Extension;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    pub extension_type: u16,
+    pub data: Vec<u8>,
+}
+
+/// The extension types of the IANA registry that this crate's handshakes
+/// use. Anything else is `Unknown` and carried opaquely.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExtensionType {
+    ServerName,
+    MaxFragmentLength,
+    ClientCertificateUrl,
+    StatusRequest,
+    UserMapping,
+    SupportedGroups,
+    EcPointFormats,
+    SignatureAlgorithms,
+    UseSrtp,
+    Heartbeat,
+    Alpn,
+    SignedCertificateTimestamp,
+    ClientCertificateType,
+    ServerCertificateType,
+    Padding,
+    EncryptThenMac,
+    ExtendedMasterSecret,
+    CompressCertificate,
+    SessionTicket,
+    PreSharedKey,
+    EarlyData,
+    SupportedVersions,
+    Cookie,
+    PskKeyExchangeModes,
+    CertificateAuthorities,
+    PostHandshakeAuth,
+    SignatureAlgorithmsCert,
+    KeyShare,
+    NextProtocolNegotiation,
+    EncryptedClientHello,
+    RenegotiationInfo,
+    Unknown(u16),
+}
+
+impl ExtensionType {
+    pub(crate) const ALL: [ExtensionType; 31] = {
+        use ExtensionType::*;
+        [
+            ServerName,
+            MaxFragmentLength,
+            ClientCertificateUrl,
+            StatusRequest,
+            UserMapping,
+            SupportedGroups,
+            EcPointFormats,
+            SignatureAlgorithms,
+            UseSrtp,
+            Heartbeat,
+            Alpn,
+            SignedCertificateTimestamp,
+            ClientCertificateType,
+            ServerCertificateType,
+            Padding,
+            EncryptThenMac,
+            ExtendedMasterSecret,
+            CompressCertificate,
+            SessionTicket,
+            PreSharedKey,
+            EarlyData,
+            SupportedVersions,
+            Cookie,
+            PskKeyExchangeModes,
+            CertificateAuthorities,
+            PostHandshakeAuth,
+            SignatureAlgorithmsCert,
+            KeyShare,
+            NextProtocolNegotiation,
+            EncryptedClientHello,
+            RenegotiationInfo,
+        ]
+    };
+
+    pub fn to_u16(self) -> u16 {
+        use ExtensionType::*;
+        match self {
+            ServerName => 0,
+            MaxFragmentLength => 1,
+            ClientCertificateUrl => 2,
+            StatusRequest => 5,
+            UserMapping => 6,
+            SupportedGroups => 10,
+            EcPointFormats => 11,
+            SignatureAlgorithms => 13,
+            UseSrtp => 14,
+            Heartbeat => 15,
+            Alpn => 16,
+            SignedCertificateTimestamp => 18,
+            ClientCertificateType => 19,
+            ServerCertificateType => 20,
+            Padding => 21,
+            EncryptThenMac => 22,
+            ExtendedMasterSecret => 23,
+            CompressCertificate => 27,
+            SessionTicket => 35,
+            PreSharedKey => 41,
+            EarlyData => 42,
+            SupportedVersions => 43,
+            Cookie => 44,
+            PskKeyExchangeModes => 45,
+            CertificateAuthorities => 47,
+            PostHandshakeAuth => 49,
+            SignatureAlgorithmsCert => 50,
+            KeyShare => 51,
+            NextProtocolNegotiation => 13172,
+            EncryptedClientHello => 0xfe0d,
+            RenegotiationInfo => 0xff01,
+            Unknown(v) => v,
+        }
+    }
+
+    pub fn from_u16(v: u16) -> ExtensionType {
+        ExtensionType::ALL
+            .iter()
+            .copied()
+            .find(|t| t.to_u16() == v)
+            .unwrap_or(ExtensionType::Unknown(v))
+    }
+}
+
+/// The extension's name in the RFCs, e.g. `supported_groups`.
+impl fmt::Display for ExtensionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ExtensionType::*;
+        let name = match self {
+            ServerName => "server_name",
+            MaxFragmentLength => "max_fragment_length",
+            ClientCertificateUrl => "client_certificate_url",
+            StatusRequest => "status_request",
+            UserMapping => "user_mapping",
+            SupportedGroups => "supported_groups",
+            EcPointFormats => "ec_point_formats",
+            SignatureAlgorithms => "signature_algorithms",
+            UseSrtp => "use_srtp",
+            Heartbeat => "heartbeat",
+            Alpn => "application_layer_protocol_negotiation",
+            SignedCertificateTimestamp => "signed_certificate_timestamp",
+            ClientCertificateType => "client_certificate_type",
+            ServerCertificateType => "server_certificate_type",
+            Padding => "padding",
+            EncryptThenMac => "encrypt_then_mac",
+            ExtendedMasterSecret => "extended_master_secret",
+            CompressCertificate => "compress_certificate",
+            SessionTicket => "session_ticket",
+            PreSharedKey => "pre_shared_key",
+            EarlyData => "early_data",
+            SupportedVersions => "supported_versions",
+            Cookie => "cookie",
+            PskKeyExchangeModes => "psk_key_exchange_modes",
+            CertificateAuthorities => "certificate_authorities",
+            PostHandshakeAuth => "post_handshake_auth",
+            SignatureAlgorithmsCert => "signature_algorithms_cert",
+            KeyShare => "key_share",
+            NextProtocolNegotiation => "next_protocol_negotiation",
+            EncryptedClientHello => "encrypted_client_hello",
+            RenegotiationInfo => "renegotiation_info",
+            Unknown(v) => return write!(f, "unknown({})", v),
+        };
+        f.write_str(name)
+    }
+}
+
+/// Why extensions couldn't be encoded or decoded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExtensionError {
+    /// The data is malformed.
+    Codec(CodecError),
+    /// Two extensions have the same type (RFC 5246, 7.4.1.4).
+    Duplicate(u16),
+    /// A server_name entry isn't a host_name (RFC 6066, 3).
+    UnknownNameType(u8),
+}
+
+impl fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtensionError::Codec(e) => e.fmt(f),
+            ExtensionError::Duplicate(t) => {
+                write!(f, "duplicate {} extension", ExtensionType::from_u16(*t))
+            }
+            ExtensionError::UnknownNameType(t) => write!(f, "unknown server name type {}", t),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionError {}
+
+impl From<CodecError> for ExtensionError {
+    fn from(e: CodecError) -> ExtensionError {
+        ExtensionError::Codec(e)
+    }
+}
+
+impl ExtensionError {
+    /// The alert a receiver answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            ExtensionError::UnknownNameType(_) => AlertDescription::IllegalParameter,
+            _ => AlertDescription::DecodeError,
+        }
+    }
+}
+
+fn check_duplicates(extensions: &[Extension]) -> Result<(), ExtensionError> {
+    for (i, e) in extensions.iter().enumerate() {
+        if extensions[..i].iter().any(|f| f.extension_type == e.extension_type) {
+            return Err(ExtensionError::Duplicate(e.extension_type));
+        }
+    }
+    Ok(())
+}
+
+/// Encodes `Extension extensions<0..2^16-1>`, with its length.
+pub fn encode_extensions(extensions: &[Extension]) -> Result<Vec<u8>, ExtensionError> {
+    check_duplicates(extensions)?;
+    let mut data = Vec::new();
+    for e in extensions {
+        codec::write_u16(&mut data, e.extension_type);
+        codec::write_u16_vec(&mut data, 0, 0xffff, "extension_data", &e.data)?;
+    }
+    let mut out = Vec::with_capacity(2 + data.len());
+    codec::write_u16_vec(&mut out, 0, 0xffff, "extensions", &data)?;
+    Ok(out)
+}
+
+/// Decodes the extension list at the start of `input`, and returns it with
+/// the number of bytes it took up.
+pub fn decode_extensions(input: &[u8]) -> Result<(Vec<Extension>, usize), ExtensionError> {
+    let mut r = Reader::new(input);
+    let mut list = Reader::new(r.read_u16_vec(0, 0xffff, "extensions")?);
+    let mut extensions = Vec::new();
+    while !list.is_empty() {
+        let extension_type = list.read_u16()?;
+        let data = list.read_u16_vec(0, 0xffff, "extension_data")?.to_vec();
+        extensions.push(Extension { extension_type, data });
+    }
+    check_duplicates(&extensions)?;
+    Ok((extensions, input.len() - r.rest().len()))
+}
+
+/// The data of the extensions this crate understands, and of the rest as
+/// is. A ServerHello acknowledges server_name with empty data, which only
+/// the `Opaque` form can hold.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtensionValue {
+    /// The host_name of a ServerNameList (RFC 6066, 3).
+    ServerName(Vec<u8>),
+    /// NamedGroup named_group_list<2..2^16-1> (RFC 8422, 5.1.1).
+    SupportedGroups(Vec<u16>),
+    /// SignatureScheme supported_signature_algorithms<2..2^16-2>
+    /// (RFC 5246, 7.4.1.4.1 and RFC 8446, 4.2.3).
+    SignatureAlgorithms(Vec<u16>),
+    /// The opaque ticket, empty when none is held (RFC 5077, 3.2).
+    SessionTicket(Vec<u8>),
+    Opaque(Extension),
+}
+
+const HOST_NAME: u8 = 0;
+
+pub fn encode_extension(value: &ExtensionValue) -> Result<Extension, ExtensionError> {
+    let (extension_type, data) = match value {
+        ExtensionValue::ServerName(host) => {
+            let mut name = vec![HOST_NAME];
+            codec::write_u16_vec(&mut name, 1, 0xffff, "host_name", host)?;
+            let mut data = Vec::new();
+            codec::write_u16_vec(&mut data, 1, 0xffff, "server_name_list", &name)?;
+            (ExtensionType::ServerName, data)
+        }
+        ExtensionValue::SupportedGroups(groups) => {
+            let mut data = Vec::new();
+            codec::write_u16_list(&mut data, 2, 0xffff, "named_group_list", groups)?;
+            (ExtensionType::SupportedGroups, data)
+        }
+        ExtensionValue::SignatureAlgorithms(schemes) => {
+            let mut data = Vec::new();
+            let field = "supported_signature_algorithms";
+            codec::write_u16_list(&mut data, 2, 0xfffe, field, schemes)?;
+            (ExtensionType::SignatureAlgorithms, data)
+        }
+        ExtensionValue::SessionTicket(ticket) => (ExtensionType::SessionTicket, ticket.clone()),
+        ExtensionValue::Opaque(e) => return Ok(e.clone()),
+    };
+    Ok(Extension { extension_type: extension_type.to_u16(), data })
+}
+
+/// Decodes an extension's data by its type. An empty server_name and
+/// extensions of other types stay opaque, so encoding the value gives
+/// back the same extension.
+pub fn decode_extension(e: &Extension) -> Result<ExtensionValue, ExtensionError> {
+    match ExtensionType::from_u16(e.extension_type) {
+        ExtensionType::ServerName if !e.data.is_empty() => {
+            let mut r = Reader::new(&e.data);
+            let mut list = Reader::new(r.read_u16_vec(1, 0xffff, "server_name_list")?);
+            r.end()?;
+            // A list holds at most one name of each type, and host_name is
+            // the only type there is.
+            let name_type = list.read_u8()?;
+            if name_type != HOST_NAME {
+                return Err(ExtensionError::UnknownNameType(name_type));
+            }
+            let host = list.read_u16_vec(1, 0xffff, "host_name")?.to_vec();
+            list.end()?;
+            Ok(ExtensionValue::ServerName(host))
+        }
+        ExtensionType::SupportedGroups => {
+            let mut r = Reader::new(&e.data);
+            let groups = r.read_u16_list(2, 0xffff, "named_group_list")?;
+            r.end()?;
+            Ok(ExtensionValue::SupportedGroups(groups))
+        }
+        ExtensionType::SignatureAlgorithms => {
+            let mut r = Reader::new(&e.data);
+            let schemes = r.read_u16_list(2, 0xfffe, "supported_signature_algorithms")?;
+            r.end()?;
+            Ok(ExtensionValue::SignatureAlgorithms(schemes))
+        }
+        ExtensionType::SessionTicket => Ok(ExtensionValue::SessionTicket(e.data.clone())),
+        _ => Ok(ExtensionValue::Opaque(e.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // The extensions of a ClientHello that OpenSSL 3.0 sent to example.com,
+    // capped at TLS 1.2.
+    const CAPTURED: &str = concat!(
+        "006600000010000e00000b6578616d706c652e636f6d000b000403000102000a000c000a001d0017001e0019",
+        "0018002300000016000000170000000d002a0028040305030603080708080809080a080b0804080508060401",
+        "05010601030303010302040205020602",
+    );
+
+    #[test]
+    fn extension_type_codec() {
+        for &t in ExtensionType::ALL.iter() {
+            assert_eq!(ExtensionType::from_u16(t.to_u16()), t);
+        }
+        assert_eq!(ExtensionType::from_u16(0x1a1a), ExtensionType::Unknown(0x1a1a));
+        assert_eq!(ExtensionType::Unknown(0x1a1a).to_u16(), 0x1a1a);
+        assert_eq!(ExtensionType::SupportedGroups.to_string(), "supported_groups");
+    }
+
+    #[test]
+    fn captured_list() {
+        let bytes = hex(CAPTURED);
+        let (list, len) = decode_extensions(&bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        let types = list.iter().map(|e| ExtensionType::from_u16(e.extension_type));
+        use ExtensionType::*;
+        assert_eq!(
+            types.collect::<Vec<_>>(),
+            vec![
+                ServerName,
+                EcPointFormats,
+                SupportedGroups,
+                SessionTicket,
+                EncryptThenMac,
+                ExtendedMasterSecret,
+                SignatureAlgorithms,
+            ]
+        );
+
+        let values = list.iter().map(|e| decode_extension(e).unwrap()).collect::<Vec<_>>();
+        assert_eq!(values[0], ExtensionValue::ServerName(b"example.com".to_vec()));
+        assert_eq!(
+            values[2],
+            ExtensionValue::SupportedGroups(vec![0x001d, 0x0017, 0x001e, 0x0019, 0x0018])
+        );
+        assert_eq!(values[3], ExtensionValue::SessionTicket(vec![]));
+        match &values[6] {
+            ExtensionValue::SignatureAlgorithms(schemes) => {
+                assert_eq!(schemes.len(), 20);
+                assert_eq!(schemes[0], 0x0403);
+            }
+            v => panic!("{:?}", v),
+        }
+        assert_eq!(values[1], ExtensionValue::Opaque(list[1].clone()));
+
+        let encoded = values.iter().map(|v| encode_extension(v).unwrap()).collect::<Vec<_>>();
+        assert_eq!(encoded, list);
+        assert_eq!(encode_extensions(&encoded), Ok(bytes));
+    }
+
+    #[test]
+    fn unknown_extensions_are_opaque() {
+        let grease = Extension { extension_type: 0x2a2a, data: vec![0] };
+        let value = decode_extension(&grease).unwrap();
+        assert_eq!(value, ExtensionValue::Opaque(grease.clone()));
+        assert_eq!(encode_extension(&value), Ok(grease));
+
+        // A server's empty server_name acknowledgement.
+        let ack = Extension { extension_type: 0, data: vec![] };
+        assert_eq!(decode_extension(&ack), Ok(ExtensionValue::Opaque(ack.clone())));
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = hex(CAPTURED);
+        for len in 0..bytes.len() {
+            assert!(decode_extensions(&bytes[..len]).is_err());
+        }
+        let e = Extension { extension_type: 10, data: vec![] };
+        assert_eq!(encode_extensions(&[e.clone(), e]), Err(ExtensionError::Duplicate(10)));
+        assert_eq!(
+            decode_extensions(&[0, 8, 0, 10, 0, 0, 0, 10, 0, 0]),
+            Err(ExtensionError::Duplicate(10))
+        );
+
+        let groups = |data: Vec<u8>| Extension { extension_type: 10, data };
+        assert_eq!(
+            decode_extension(&groups(vec![0, 3, 0, 29, 0])),
+            Err(ExtensionError::Codec(CodecError::BadLength("named_group_list")))
+        );
+        assert_eq!(
+            decode_extension(&groups(vec![0, 2, 0, 29, 0])),
+            Err(ExtensionError::Codec(CodecError::TrailingBytes))
+        );
+        assert_eq!(
+            encode_extension(&ExtensionValue::SupportedGroups(vec![])),
+            Err(ExtensionError::Codec(CodecError::BadLength("named_group_list")))
+        );
+
+        let sni = Extension { extension_type: 0, data: vec![0, 4, 1, 0, 1, b'a'] };
+        assert_eq!(decode_extension(&sni), Err(ExtensionError::UnknownNameType(1)));
+    }
+}
@@ -0,0 +1,163 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use num::BigUint;
+
+use crate::rsa::{i2osp, os2ip};
+
+// Finite field Diffie-Hellman over the groups of RFC 7919 and over the
+// (p, g) a server sends in the ServerDHParams of DHE and DH_anon key
+// exchanges (RFC 5246, section 7.4.3). Integers are big-endian octet strings.
+
+// The primes of RFC 7919, appendix A, all with generator 2. Each is a safe
+// prime p = 2q + 1.
+static FFDHE2048_P: &str = concat!(
+    "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695",
+    "a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a",
+    "d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935",
+    "984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a",
+    "bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4",
+    "ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61",
+    "9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005",
+    "c58ef1837d1683b2c6f34a26c1b2effa886b423861285c97ffffffffffffffff",
+);
+
+static FFDHE3072_P: &str = concat!(
+    "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695",
+    "a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a",
+    "d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935",
+    "984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a",
+    "bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4",
+    "ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61",
+    "9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005",
+    "c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b",
+    "bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c",
+    "aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff",
+    "5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e",
+    "0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b66c62e37ffffffffffffffff",
+);
+
+static FFDHE4096_P: &str = concat!(
+    "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695",
+    "a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a",
+    "d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935",
+    "984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a",
+    "bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4",
+    "ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61",
+    "9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005",
+    "c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b",
+    "bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c",
+    "aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff",
+    "5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e",
+    "0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b669e1ef16e6f52c3164df4fb",
+    "7930e9e4e58857b6ac7d5f42d69f6d187763cf1d5503400487f55ba57e31cc7a",
+    "7135c886efb4318aed6a1e012d9e6832a907600a918130c46dc778f971ad0038",
+    "092999a333cb8b7a1a1db93d7140003c2a4ecea9f98d0acc0a8291cdcec97dcf",
+    "8ec9b55a7f88a46b4db5a851f44182e1c68a007e5e655f6affffffffffffffff",
+);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NamedGroup {
+    Ffdhe2048,
+    Ffdhe3072,
+    Ffdhe4096,
+}
+
+impl NamedGroup {
+    // The NamedGroup code points of RFC 7919, section 6.
+    pub fn code_point(self) -> u16 {
+        match self {
+            NamedGroup::Ffdhe2048 => 0x0100,
+            NamedGroup::Ffdhe3072 => 0x0101,
+            NamedGroup::Ffdhe4096 => 0x0102,
+        }
+    }
+
+    pub fn group(self) -> DhGroup {
+        let p = match self {
+            NamedGroup::Ffdhe2048 => FFDHE2048_P,
+            NamedGroup::Ffdhe3072 => FFDHE3072_P,
+            NamedGroup::Ffdhe4096 => FFDHE4096_P,
+        };
+        let p = Bytes::from(p);
+        let q = (os2ip(&p) - BigUint::from(1u32)) / BigUint::from(2u32);
+        DhGroup { p: p.clone(), g: Bytes::from("02"), q: Some(i2osp(&q, p.len()).unwrap()) }
+    }
+}
+
+// A group is a prime p and a generator g, and the order q of the subgroup g
+// generates when it is known, as for the named groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DhGroup {
+    pub p: Bytes,
+    pub g: Bytes,
+    pub q: Option<Bytes>,
+}
+
+// The named group with the prime and generator a server sent, if any. A
+// client can then check a peer's value against the subgroup (RFC 7919,
+// section 3).
+pub fn named_group(p: &Bytes, g: &Bytes) -> Option<NamedGroup> {
+    for &group in [NamedGroup::Ffdhe2048, NamedGroup::Ffdhe3072, NamedGroup::Ffdhe4096].iter() {
+        let params = group.group();
+        if os2ip(p) == os2ip(&params.p) && os2ip(g) == os2ip(&params.g) {
+            return Some(group);
+        }
+    }
+    None
+}
+
+// The byte length of p; public values and shared secrets are padded to it.
+fn group_len(group: &DhGroup) -> usize {
+    os2ip(&group.p).bits().div_ceil(8)
+}
+
+// Y = g^x mod p for the private key x.
+pub fn public_key(group: &DhGroup, sk: &Bytes) -> Result<Bytes, String> {
+    let p = os2ip(&group.p);
+    let x = os2ip(sk);
+    if x < BigUint::from(1u32) || x >= p {
+        return Err("Invalid private key".to_string());
+    }
+    i2osp(&os2ip(&group.g).modpow(&x, &p), group_len(group))
+}
+
+// A peer's value must satisfy 1 < Y < p - 1, so that it is not of order 1
+// or 2 (RFC 7919, section 5.1), and Y^q = 1 mod p when q is known.
+pub fn validate_public_key(group: &DhGroup, y: &Bytes) -> Result<(), String> {
+    let p = os2ip(&group.p);
+    let y = os2ip(y);
+    let one = BigUint::from(1u32);
+    if y <= one || y >= &p - &one {
+        return Err("Invalid DH public key".to_string());
+    }
+    if let Some(q) = &group.q {
+        if y.modpow(&os2ip(q), &p) != one {
+            return Err("DH public key is not in the subgroup".to_string());
+        }
+    }
+    Ok(())
+}
+
+// Z = Y^x mod p for the peer's value Y, after validating it, padded to the
+// length of p as TLS 1.3 uses it (RFC 8446, section 7.4.1).
+pub fn shared_secret(group: &DhGroup, sk: &Bytes, peer: &Bytes) -> Result<Bytes, String> {
+    validate_public_key(group, peer)?;
+    let p = os2ip(&group.p);
+    let z = os2ip(peer).modpow(&os2ip(sk), &p);
+    if z == BigUint::from(1u32) {
+        return Err("Invalid DH shared secret".to_string());
+    }
+    i2osp(&z, group_len(group))
+}
+
+// TLS 1.2 strips the leading zero bytes of Z for the premaster secret
+// (RFC 5246, section 8.1.2).
+pub fn premaster_secret(z: &Bytes) -> Bytes {
+    let mut start = 0;
+    while start < z.len() && z[start] == 0 {
+        start += 1;
+    }
+    Bytes::from(&z[start..z.len()])
+}
@@ -0,0 +1,56 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+use contracts::*;
+
+// GHASH (NIST SP 800-38D, section 6.4), with the multiplication in GF(2^128)
+// done bit by bit as in the standard's Algorithm 1, without tables.
+
+const BLOCKSIZE: usize = 16;
+bytes!(Block, BLOCKSIZE);
+
+// R = 11100001 || 0^120
+const R: u128 = 0xe1 << 120;
+
+// A block is a string of 128 bits x_0 .. x_127, x_0 being the most
+// significant bit of its first byte; as a u128, x_0 is the top bit.
+fn to_u128(x: Block) -> u128 {
+    u128::from_be_bytes(x.into())
+}
+
+fn from_u128(x: u128) -> Block {
+    Block(x.to_be_bytes())
+}
+
+// Algorithm 1: Z_0 = 0 and V_0 = Y; for each bit x_i, Z_i+1 = Z_i ^ V_i if
+// x_i is set, and V_i+1 is V_i shifted right, reduced by R if its last bit
+// was set. X . Y is Z_128.
+pub fn gf_mul(x: Block, y: Block) -> Block {
+    let x = to_u128(x);
+    let mut z: u128 = 0;
+    let mut v = to_u128(y);
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 0 { v >> 1 } else { (v >> 1) ^ R };
+    }
+    from_u128(z)
+}
+
+// Algorithm 2: Y_0 = 0 and Y_i = (Y_i-1 ^ X_i) . H over the blocks X_i of X;
+// GHASH_H(X) is the last Y_i.
+#[pre(x.len() % BLOCKSIZE == 0)]
+pub fn ghash(h: Block, x: Bytes) -> Block {
+    let mut y = Block::new();
+    for i in 0..x.len() / BLOCKSIZE {
+        let k = i * BLOCKSIZE;
+        let x_i = Block::from(&x[k..k + BLOCKSIZE]);
+        let mut sum = Block::new();
+        for j in 0..BLOCKSIZE {
+            sum[j] = y[j] ^ x_i[j];
+        }
+        y = gf_mul(sum, h);
+    }
+    y
+}
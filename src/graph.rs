@@ -0,0 +1,316 @@
+//! Renderings of the handshake machines as Graphviz DOT and as Mermaid state
+//! diagrams, and questions about which states they reach.
+
+use crate::tls12::Peer;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::hash::Hash;
+
+/// A handshake machine, given by its options. Implemented by the
+/// `ProtocolOptions` of each protocol module.
+pub trait Protocol: Copy {
+    type State: Copy + Eq + Hash + fmt::Debug + Default;
+
+    /// The name of the rendered graph.
+    const NAME: &'static str;
+
+    fn step(self, st: Self::State) -> Self::State;
+
+    /// The peer sending in `st` and what it sends.
+    fn sends(st: Self::State) -> Option<(Peer, String)>;
+
+    /// Every state of the machine.
+    fn states() -> Vec<Self::State>;
+
+    /// Every consistent combination of options.
+    fn combinations() -> Vec<Self>;
+
+    /// Each option's name and value.
+    fn settings(&self) -> Vec<(&'static str, String)>;
+}
+
+/// Combinations of options, built up lazily one field at a time with
+/// `expand`.
+pub(crate) type Combinations<O> = Box<dyn Iterator<Item = O>>;
+
+/// Each of `all` with the field `set` sets to each of `values` in turn,
+/// dropping those `keep` refuses. `keep` sees the fields still to be
+/// expanded at their defaults.
+pub(crate) fn expand<O: Copy + 'static, T: Copy + 'static>(
+    all: Combinations<O>,
+    values: &'static [T],
+    set: fn(&mut O, T),
+    keep: fn(&O) -> bool,
+) -> Combinations<O> {
+    Box::new(all.flat_map(move |opts| {
+        values
+            .iter()
+            .map(move |v| {
+                let mut opts = opts;
+                set(&mut opts, *v);
+                opts
+            })
+            .filter(keep)
+    }))
+}
+
+/// The transitions `step` takes for `opts`, starting from the initial state,
+/// in the order they are first taken. A state left as it is (an established
+/// connection that isn't renegotiated) isn't a transition.
+fn edges<P: Protocol>(opts: P) -> Vec<(P::State, P::State)> {
+    let mut seen = vec![P::State::default()];
+    let mut edges = Vec::new();
+    let mut i = 0;
+    while i < seen.len() {
+        let st = seen[i];
+        let next = opts.step(st);
+        if next != st {
+            edges.push((st, next));
+            if !seen.contains(&next) {
+                seen.push(next);
+            }
+        }
+        i += 1;
+    }
+    edges
+}
+
+/// The edges of `opts`, each labelled with the message sent to take it.
+fn labelled<P: Protocol>(opts: P) -> Vec<(P::State, P::State, String)> {
+    edges(opts)
+        .into_iter()
+        .map(|(from, to)| {
+            let label = P::sends(from).map(|(peer, msg)| format!("{:?}: {}", peer, msg));
+            (from, to, label.unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Each edge with the settings that every combination of options taking it
+/// agrees on; `None` where two of them differ.
+type Guards<S> = Vec<((S, S), Vec<Option<(&'static str, String)>>)>;
+
+/// The edges of all combinations of options, each labelled with the option
+/// settings shared by all the combinations that take it. An edge every
+/// combination takes has no label.
+fn guarded<P: Protocol>() -> Vec<(P::State, P::State, String)> {
+    let mut guards: Guards<P::State> = Vec::new();
+    let mut index = HashMap::new();
+    for opts in P::combinations() {
+        let settings = opts.settings();
+        for edge in edges(opts) {
+            let i = *index.entry(edge).or_insert_with(|| {
+                guards.push((edge, settings.iter().cloned().map(Some).collect()));
+                guards.len() - 1
+            });
+            for (guard, setting) in guards[i].1.iter_mut().zip(&settings) {
+                if guard.as_ref() != Some(setting) {
+                    *guard = None;
+                }
+            }
+        }
+    }
+    guards
+        .into_iter()
+        .map(|((from, to), guard)| {
+            let label = guard
+                .iter()
+                .flatten()
+                .map(|(name, value)| literal(name, value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            (from, to, label)
+        })
+        .collect()
+}
+
+fn literal(name: &str, value: &str) -> String {
+    match value {
+        "true" => name.to_string(),
+        "false" => format!("!{}", name),
+        _ => format!("{}={}", name, value),
+    }
+}
+
+fn dot<S: fmt::Debug>(name: &str, edges: Vec<(S, S, String)>) -> String {
+    let mut out = format!("digraph {} {{\n", name);
+    for (from, to, label) in edges {
+        write!(out, "    \"{:?}\" -> \"{:?}\"", from, to).unwrap();
+        if !label.is_empty() {
+            write!(out, " [label=\"{}\"]", label).unwrap();
+        }
+        out.push_str(";\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// A Mermaid state id: the state's name, with anything but letters and
+/// digits (the parentheses of a wrapped state) replaced.
+fn mermaid_id(name: &str) -> String {
+    let id = name.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    id.trim_end_matches('_').to_string()
+}
+
+fn mermaid<S: fmt::Debug + Default>(edges: Vec<(S, S, String)>) -> String {
+    let mut out = String::from("stateDiagram-v2\n");
+    let mut declared = Vec::new();
+    let mut id = |out: &mut String, st: &S| {
+        let name = format!("{:?}", st);
+        let id = mermaid_id(&name);
+        if id != name && !declared.contains(&id) {
+            writeln!(out, "    state \"{}\" as {}", name, id).unwrap();
+            declared.push(id.clone());
+        }
+        id
+    };
+    let initial = id(&mut out, &S::default());
+    writeln!(out, "    [*] --> {}", initial).unwrap();
+    for (from, to, label) in edges {
+        let from = id(&mut out, &from);
+        let to = id(&mut out, &to);
+        write!(out, "    {} --> {}", from, to).unwrap();
+        if !label.is_empty() {
+            write!(out, ": {}", label).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The states `step` gets to from the initial state for `opts`, including
+/// any renegotiation. States only entered through `tls12::fail` aren't among
+/// them.
+pub fn reachable_states<P: Protocol>(opts: P) -> HashSet<P::State> {
+    let mut reachable = HashSet::new();
+    reachable.insert(P::State::default());
+    reachable.extend(edges(opts).into_iter().map(|(_, to)| to));
+    reachable
+}
+
+/// The states `reachable_states` leaves out.
+pub fn unreachable_states<P: Protocol>(opts: P) -> HashSet<P::State> {
+    let reachable = reachable_states(opts);
+    P::states().into_iter().filter(|st| !reachable.contains(st)).collect()
+}
+
+/// The handshake for `opts` as a DOT digraph, each edge labelled with the
+/// message sent to take it.
+pub fn to_dot<P: Protocol>(opts: P) -> String {
+    dot(P::NAME, labelled(opts))
+}
+
+/// Every transition taken under some consistent combination of options, as
+/// one DOT digraph. Each edge is labelled with the option settings shared by
+/// all the combinations that take it; an edge every combination takes has
+/// no label.
+pub fn to_dot_all<P: Protocol>() -> String {
+    dot(P::NAME, guarded::<P>())
+}
+
+/// The handshake for `opts` as a Mermaid `stateDiagram-v2`, labelled like
+/// `to_dot`.
+pub fn to_mermaid<P: Protocol>(opts: P) -> String {
+    mermaid(labelled(opts))
+}
+
+/// Every transition of every consistent combination of options as a Mermaid
+/// `stateDiagram-v2`, labelled like `to_dot_all`.
+pub fn to_mermaid_all<P: Protocol>() -> String {
+    mermaid(guarded::<P>())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{dtls12, tls12, tls13};
+
+    #[test]
+    fn dot() {
+        let dot = to_dot(tls12::ProtocolOptions::default());
+        assert!(dot.starts_with("digraph tls12 {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(
+            "    \"ClientSendsClientHello\" -> \"ServerSendsServerHello\" \
+             [label=\"Client: ClientHello\"];\n"
+        ));
+        // One edge per message of the handshake.
+        let trace = tls12::Trace::linearise(tls12::ProtocolOptions::default());
+        assert_eq!(dot.lines().count(), trace.len() + 2);
+        assert!(to_dot(tls13::ProtocolOptions::default()).starts_with("digraph tls13 {\n"));
+    }
+
+    #[test]
+    fn dot_all() {
+        let dot = to_dot_all::<tls12::ProtocolOptions>();
+        // Every handshake starts the same way.
+        assert!(dot.contains("    \"ClientSendsClientHello\" -> \"ServerSendsServerHello\";\n"));
+        assert!(dot.contains(
+            "    \"ClientSendsNextProtocol\" -> \"ClientSendsFinished\" [label=\"npn\"];\n"
+        ));
+        let refusal =
+            dot.lines().find(|l| l.contains("-> \"ClientRefusesRenegotiation\"")).unwrap();
+        assert!(refusal.contains("renegotiation=Some(Server)"));
+        assert!(refusal.contains("!secure_renegotiation"));
+        assert!(refusal.contains("!allow_insecure_renegotiation"));
+        // Each edge of a single handshake is in the combined graph.
+        let opts = tls12::ProtocolOptions { resuming: true, npn: true, ..Default::default() };
+        for (from, to) in edges(opts) {
+            assert!(dot.contains(&format!("    \"{:?}\" -> \"{:?}\"", from, to)));
+        }
+    }
+
+    #[test]
+    fn reachability() {
+        use tls13::State::ClientSendsCertificateVerify;
+        for opts in tls13::ProtocolOptions::combinations() {
+            let reachable = reachable_states(opts).contains(&ClientSendsCertificateVerify);
+            let settings = opts.settings();
+            let set = |name| settings.contains(&(name, true.to_string()));
+            assert_eq!(
+                reachable,
+                set("request_client_auth") && set("perform_client_auth") && !set("psk")
+            );
+            let unreachable = unreachable_states(opts);
+            assert_eq!(!reachable, unreachable.contains(&ClientSendsCertificateVerify));
+        }
+
+        let opts = tls12::ProtocolOptions::default();
+        let reachable = reachable_states(opts);
+        assert_eq!(reachable.len(), tls12::Trace::linearise(opts).len() + 1);
+        assert!(reachable.contains(&tls12::State::Term));
+        let unreachable = unreachable_states(opts);
+        assert!(unreachable.contains(&tls12::State::ClientSendsCertificateVerify));
+        assert!(unreachable.contains(&tls12::State::ServerSendsHelloRequest));
+        assert_eq!(
+            reachable.len() + unreachable.len(),
+            <tls12::ProtocolOptions as Protocol>::states().len()
+        );
+
+        let opts = dtls12::ProtocolOptions::default();
+        assert!(unreachable_states(opts).contains(&dtls12::State::ServerSendsHelloVerifyRequest));
+    }
+
+    #[test]
+    fn mermaid() {
+        let diagram = to_mermaid(tls12::ProtocolOptions::default());
+        let mut lines = diagram.lines();
+        assert_eq!(lines.next(), Some("stateDiagram-v2"));
+        assert_eq!(lines.next(), Some("    [*] --> ClientSendsClientHello"));
+        assert_eq!(
+            lines.next(),
+            Some("    ClientSendsClientHello --> ServerSendsServerHello: Client: ClientHello")
+        );
+
+        // Wrapped DTLS states get an id Mermaid accepts, declared once.
+        let diagram = to_mermaid(dtls12::ProtocolOptions::default());
+        assert!(diagram
+            .contains("    state \"Tls(ServerSendsServerHello)\" as Tls_ServerSendsServerHello\n"));
+        assert_eq!(diagram.matches("as Tls_ServerSendsServerHello\n").count(), 1);
+
+        let diagram = to_mermaid_all::<tls13::ProtocolOptions>();
+        let edge = "    ClientSendsCertificate --> ClientSendsCertificateVerify: ";
+        let verify = diagram.lines().find(|l| l.starts_with(edge)).unwrap();
+        assert!(verify.contains(", perform_client_auth"));
+    }
+}
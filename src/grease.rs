@@ -0,0 +1,279 @@
+use crate::codec::{self, Reader};
+use crate::extensions::{Extension, ExtensionError, ExtensionType};
+use crate::hello::{ClientHello, ServerHello};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// The sixteen GREASE values of RFC 8701, 2, reserved in the cipher suite,
+/// extension, named group, signature scheme, version and ALPN registries:
+/// 0x0a0a, 0x1a1a, ..., 0xfafa.
+pub const GREASE_VALUES: [u16; 16] = {
+    let mut values = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        values[i] = 0x0a0a + 0x1010 * i as u16;
+        i += 1;
+    }
+    values
+};
+
+/// The GREASE values of the one-byte PskKeyExchangeMode registry: 0x0b,
+/// 0x2a, ..., 0xe4.
+pub const GREASE_PSK_MODES: [u8; 8] = [0x0b, 0x2a, 0x49, 0x68, 0x87, 0xa6, 0xc5, 0xe4];
+
+pub fn is_grease(v: u16) -> bool {
+    v & 0x0f0f == 0x0a0a && v >> 12 == (v >> 4) & 0xf
+}
+
+/// The `n`th GREASE value, counting round the sixteen.
+pub fn grease_value(n: usize) -> u16 {
+    GREASE_VALUES[n % 16]
+}
+
+/// `codes` with a GREASE value in front.
+fn prepend(codes: &[u16], grease: u16) -> Vec<u16> {
+    let mut v = vec![grease];
+    v.extend_from_slice(codes);
+    v
+}
+
+/// `e` with a GREASE value first in its list, if it's one of the lists
+/// `grease_client_hello` greases.
+fn grease_extension(e: &Extension, seed: usize) -> Result<Extension, ExtensionError> {
+    let group = grease_value(seed + 1);
+    let mut r = Reader::new(&e.data);
+    let mut data = Vec::new();
+    match ExtensionType::from_u16(e.extension_type) {
+        ExtensionType::SupportedGroups => {
+            let field = "named_group_list";
+            let groups = r.read_u16_list(2, 0xffff, field)?;
+            codec::write_u16_list(&mut data, 2, 0xffff, field, &prepend(&groups, group))?;
+        }
+        ExtensionType::SignatureAlgorithms => {
+            let field = "supported_signature_algorithms";
+            let schemes = r.read_u16_list(2, 0xfffe, field)?;
+            let schemes = prepend(&schemes, grease_value(seed + 2));
+            codec::write_u16_list(&mut data, 2, 0xfffe, field, &schemes)?;
+        }
+        ExtensionType::SupportedVersions => {
+            let versions = r.read_u8_vec(2, 254, "versions")?;
+            let mut list = grease_value(seed + 5).to_be_bytes().to_vec();
+            list.extend_from_slice(versions);
+            codec::write_u8_vec(&mut data, 2, 254, "versions", &list)?;
+        }
+        ExtensionType::KeyShare => {
+            // A one-byte share for the GREASE group (RFC 8701, 3.1).
+            let shares = r.read_u16_vec(0, 0xffff, "client_shares")?;
+            let mut list = Vec::new();
+            codec::write_u16(&mut list, group);
+            codec::write_u16_vec(&mut list, 1, 0xffff, "key_exchange", &[0])?;
+            list.extend_from_slice(shares);
+            codec::write_u16_vec(&mut data, 0, 0xffff, "client_shares", &list)?;
+        }
+        ExtensionType::PskKeyExchangeModes => {
+            let modes = r.read_u8_vec(1, 255, "ke_modes")?;
+            let mut list = vec![GREASE_PSK_MODES[seed % 8]];
+            list.extend_from_slice(modes);
+            codec::write_u8_vec(&mut data, 1, 255, "ke_modes", &list)?;
+        }
+        _ => return Ok(e.clone()),
+    }
+    r.end()?;
+    Ok(Extension { extension_type: e.extension_type, data })
+}
+
+/// A copy of `hello` that exercises the peer's tolerance of unknown values
+/// the way RFC 8701, 3.1 has clients do: a GREASE value goes first in the
+/// cipher suites and in each of the supported_groups, key_share,
+/// signature_algorithms, supported_versions and psk_key_exchange_modes
+/// lists the hello has, and GREASE extensions go first, empty, and last,
+/// holding a single zero byte. `seed` picks the values; no two of them are
+/// the same.
+///
+/// A server must treat all of these as values it doesn't know, so it
+/// answers the greased hello as it does `hello`.
+pub fn grease_client_hello(
+    hello: &ClientHello,
+    seed: usize,
+) -> Result<ClientHello, ExtensionError> {
+    let mut extensions = Vec::new();
+    if let Some(list) = &hello.extensions {
+        extensions.push(Extension { extension_type: grease_value(seed + 3), data: vec![] });
+        for e in list {
+            extensions.push(grease_extension(e, seed)?);
+        }
+        extensions.push(Extension { extension_type: grease_value(seed + 4), data: vec![0] });
+    }
+    Ok(ClientHello {
+        cipher_suites: prepend(&hello.cipher_suites, grease_value(seed)),
+        extensions: hello.extensions.as_ref().map(|_| extensions),
+        ..hello.clone()
+    })
+}
+
+/// A GREASE value the peer selected, as RFC 8701, 3.2 forbids.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GreaseError {
+    CipherSuite(u16),
+    Extension(u16),
+    Version(u16),
+    Group(u16),
+}
+
+impl fmt::Display for GreaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GreaseError::CipherSuite(v) => write!(f, "GREASE cipher suite {:#06x} selected", v),
+            GreaseError::Extension(v) => write!(f, "GREASE extension {:#06x} sent", v),
+            GreaseError::Version(v) => write!(f, "GREASE version {:#06x} selected", v),
+            GreaseError::Group(v) => write!(f, "GREASE group {:#06x} selected", v),
+        }
+    }
+}
+
+impl std::error::Error for GreaseError {}
+
+impl GreaseError {
+    /// The alert the client answers with: the same as for any other value
+    /// it didn't offer.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            GreaseError::Extension(_) => AlertDescription::UnsupportedExtension,
+            _ => AlertDescription::IllegalParameter,
+        }
+    }
+}
+
+/// Checks the server selected no GREASE value: not as its cipher suite,
+/// nor as an extension it answers, nor as the version of a TLS 1.3
+/// supported_versions or the group of its key_share. Whatever else the
+/// ServerHello selects, `hello::check_server_hello` already holds to what
+/// was offered.
+pub fn check_server_hello(server: &ServerHello) -> Result<(), GreaseError> {
+    if is_grease(server.cipher_suite) {
+        return Err(GreaseError::CipherSuite(server.cipher_suite));
+    }
+    for e in server.extensions.iter().flatten() {
+        if is_grease(e.extension_type) {
+            return Err(GreaseError::Extension(e.extension_type));
+        }
+    }
+    let selected = |t: ExtensionType| {
+        let data = server.extension(t.to_u16())?;
+        Reader::new(data).read_u16().ok()
+    };
+    if let Some(v) = selected(ExtensionType::SupportedVersions).filter(|&v| is_grease(v)) {
+        return Err(GreaseError::Version(v));
+    }
+    if let Some(g) = selected(ExtensionType::KeyShare).filter(|&g| is_grease(g)) {
+        return Err(GreaseError::Group(g));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extensions::{decode_extension, ExtensionValue};
+    use crate::groups::{self, NamedGroup};
+    use crate::hello::test::*;
+    use crate::hello::{self, decode_client_hello, encode_client_hello};
+    use crate::signature_schemes::{self, Offered, SignatureScheme};
+
+    fn list(hello: &ClientHello, t: ExtensionType) -> Vec<u16> {
+        let data = hello.extension(t.to_u16()).unwrap().to_vec();
+        let e = Extension { extension_type: t.to_u16(), data };
+        match decode_extension(&e).unwrap() {
+            ExtensionValue::SupportedGroups(v) | ExtensionValue::SignatureAlgorithms(v) => v,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(GREASE_VALUES[0], 0x0a0a);
+        assert_eq!(GREASE_VALUES[15], 0xfafa);
+        assert!(GREASE_VALUES.iter().all(|&v| is_grease(v)));
+        assert_eq!((0..=0xffff).filter(|&v| is_grease(v)).count(), 16);
+        assert!(!is_grease(0x0a1a) && !is_grease(0x1301));
+        assert_eq!(grease_value(17), 0x1a1a);
+    }
+
+    #[test]
+    fn greased_client_hellos() {
+        for capture in &[TLS12_CLIENT_HELLO, TLS13_CLIENT_HELLO] {
+            let hello = decode_client_hello(&body(capture)).unwrap();
+            for seed in 0..16 {
+                let greased = grease_client_hello(&hello, seed).unwrap();
+                let encoded = encode_client_hello(&greased).unwrap();
+                assert_eq!(decode_client_hello(&encoded), Ok(greased.clone()));
+                assert!(is_grease(greased.cipher_suites[0]));
+                assert_eq!(greased.cipher_suites[1..], hello.cipher_suites[..]);
+                let extensions = greased.extensions.as_ref().unwrap();
+                assert_eq!(extensions.len(), hello.extensions.as_ref().unwrap().len() + 2);
+
+                // A server ignoring the GREASE values picks what it did.
+                let groups = list(&greased, ExtensionType::SupportedGroups);
+                let offered = list(&hello, ExtensionType::SupportedGroups);
+                let server_groups = [NamedGroup::Secp256r1];
+                assert_eq!(
+                    groups::select_group(&groups, &server_groups),
+                    groups::select_group(&offered, &server_groups)
+                );
+                let schemes = list(&greased, ExtensionType::SignatureAlgorithms);
+                assert!(is_grease(schemes[0]));
+                let rsa = [SignatureScheme::RsaPssRsaeSha256];
+                assert_eq!(
+                    signature_schemes::select_signature_scheme(Offered::Tls13(&schemes), &rsa),
+                    Some(SignatureScheme::RsaPssRsaeSha256)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tls13_lists() {
+        let hello = decode_client_hello(&body(TLS13_CLIENT_HELLO)).unwrap();
+        let greased = grease_client_hello(&hello, 0).unwrap();
+        // supported_versions: GREASE, then TLS 1.3 and 1.2.
+        assert_eq!(greased.extension(43), Some(&[6, 0x5a, 0x5a, 3, 4, 3, 3][..]));
+        assert_eq!(greased.extension(45), Some(&[2, 0x0b, 1][..]));
+        let key_share = greased.extension(51).unwrap();
+        assert_eq!(&key_share[..9], &[0, 0x29, 0x1a, 0x1a, 0, 1, 0, 0, 0x1d]);
+        let groups = list(&greased, ExtensionType::SupportedGroups);
+        assert_eq!(groups[0], 0x1a1a);
+    }
+
+    #[test]
+    fn server_selections() {
+        let client = decode_client_hello(&body(TLS12_CLIENT_HELLO)).unwrap();
+        let client = grease_client_hello(&client, 0).unwrap();
+        let (server, _) = server_hello(FULL_SERVER_HELLO);
+        assert_eq!(hello::check_server_hello(&client, &server), Ok(()));
+        assert_eq!(check_server_hello(&server), Ok(()));
+
+        // The client offered the GREASE values, so only this check catches
+        // a server that picks one.
+        let bad = ServerHello { cipher_suite: 0x0a0a, ..server.clone() };
+        assert_eq!(hello::check_server_hello(&client, &bad), Ok(()));
+        let err = check_server_hello(&bad).unwrap_err();
+        assert_eq!(err, GreaseError::CipherSuite(0x0a0a));
+        assert_eq!(err.alert(), AlertDescription::IllegalParameter);
+
+        let mut bad = server.clone();
+        bad.extensions.as_mut().unwrap().push(Extension { extension_type: 0x3a3a, data: vec![0] });
+        assert_eq!(check_server_hello(&bad), Err(GreaseError::Extension(0x3a3a)));
+        assert_eq!(GreaseError::Extension(0x3a3a).alert(), AlertDescription::UnsupportedExtension);
+
+        let tls13 = |t: u16, data: Vec<u8>| ServerHello {
+            cipher_suite: 0x1301,
+            extensions: Some(vec![Extension { extension_type: t, data }]),
+            ..server.clone()
+        };
+        assert_eq!(check_server_hello(&tls13(43, vec![3, 4])), Ok(()));
+        let version = vec![0x5a, 0x5a];
+        assert_eq!(check_server_hello(&tls13(43, version)), Err(GreaseError::Version(0x5a5a)));
+        let share = vec![0x1a, 0x1a, 0, 1, 0];
+        assert_eq!(check_server_hello(&tls13(51, share)), Err(GreaseError::Group(0x1a1a)));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::tls12::{KeyExchange, ProtocolOptions};
+use std::fmt;
+
+/// The NamedGroup values of the supported_groups extension (RFC 8422, 5.1.1,
+/// RFC 7919, 2 and RFC 8446, 4.2.7) that this crate knows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NamedGroup {
+    Secp256r1,
+    Secp384r1,
+    Secp521r1,
+    X25519,
+    X448,
+    Ffdhe2048,
+    Ffdhe3072,
+    Ffdhe4096,
+    Ffdhe6144,
+    Ffdhe8192,
+}
+
+impl NamedGroup {
+    pub(crate) const ALL: [NamedGroup; 10] = {
+        use NamedGroup::*;
+        [
+            Secp256r1, Secp384r1, Secp521r1, X25519, X448, Ffdhe2048, Ffdhe3072, Ffdhe4096,
+            Ffdhe6144, Ffdhe8192,
+        ]
+    };
+
+    pub fn to_u16(self) -> u16 {
+        use NamedGroup::*;
+        match self {
+            Secp256r1 => 23,
+            Secp384r1 => 24,
+            Secp521r1 => 25,
+            X25519 => 29,
+            X448 => 30,
+            Ffdhe2048 => 0x0100,
+            Ffdhe3072 => 0x0101,
+            Ffdhe4096 => 0x0102,
+            Ffdhe6144 => 0x0103,
+            Ffdhe8192 => 0x0104,
+        }
+    }
+
+    /// The group with code point `v`, if this crate knows it.
+    pub fn from_u16(v: u16) -> Option<NamedGroup> {
+        NamedGroup::ALL.iter().copied().find(|g| g.to_u16() == v)
+    }
+
+    /// Whether this is one of the finite field groups of RFC 7919.
+    pub fn is_ffdhe(self) -> bool {
+        (0x0100..=0x01ff).contains(&self.to_u16())
+    }
+
+    /// Whether the ServerKeyExchange of `key_exchange` can carry this group:
+    /// ECDHE ones take an elliptic curve, DHE ones a finite field group, and
+    /// the anonymous and PSK ones, which come in both kinds, either. The
+    /// other key exchanges send no group.
+    pub fn suits(self, key_exchange: KeyExchange) -> bool {
+        use KeyExchange::*;
+        match key_exchange {
+            EcdheRsa | EcdheEcdsa => !self.is_ffdhe(),
+            DheRsa | DheDss => self.is_ffdhe(),
+            DhAnon | DhePsk => true,
+            RsaKem | StaticDh | Psk | RsaPsk | Srp | SrpRsa | SrpDss => false,
+        }
+    }
+}
+
+impl fmt::Display for NamedGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use NamedGroup::*;
+        let name = match self {
+            Secp256r1 => "secp256r1",
+            Secp384r1 => "secp384r1",
+            Secp521r1 => "secp521r1",
+            X25519 => "x25519",
+            X448 => "x448",
+            Ffdhe2048 => "ffdhe2048",
+            Ffdhe3072 => "ffdhe3072",
+            Ffdhe4096 => "ffdhe4096",
+            Ffdhe6144 => "ffdhe6144",
+            Ffdhe8192 => "ffdhe8192",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The group the server picks: the first of the client's supported_groups,
+/// in its order of preference, that the server supports. Unknown values,
+/// GREASE among them, are skipped.
+pub fn select_group(client_offered: &[u16], server_supported: &[NamedGroup]) -> Option<NamedGroup> {
+    client_offered
+        .iter()
+        .filter_map(|&v| NamedGroup::from_u16(v))
+        .find(|g| server_supported.contains(g))
+}
+
+/// `opts` with the group of its ServerKeyExchange chosen from those that
+/// suit the key exchange, or `None` if the server can't go ahead with it.
+///
+/// A DHE server may still use parameters of its own if the client offered
+/// no finite field group at all (RFC 7919, 4); the group is then left
+/// unset. Key exchanges that don't send a group leave `opts` as it is.
+pub fn choose_server_group(
+    opts: ProtocolOptions,
+    client_offered: &[u16],
+    server_supported: &[NamedGroup],
+) -> Option<ProtocolOptions> {
+    let kx = opts.key_exchange;
+    if !NamedGroup::ALL.iter().any(|g| g.suits(kx)) {
+        return Some(opts);
+    }
+    let suitable = server_supported.iter().copied().filter(|g| g.suits(kx)).collect::<Vec<_>>();
+    match select_group(client_offered, &suitable) {
+        Some(group) => Some(ProtocolOptions { group: Some(group), ..opts }),
+        None => {
+            let offered = client_offered.iter().filter_map(|&v| NamedGroup::from_u16(v));
+            let offered_ffdhe = offered.clone().any(|g| g.is_ffdhe());
+            let custom_dh = matches!(kx, KeyExchange::DheRsa | KeyExchange::DheDss);
+            if custom_dh && !offered_ffdhe {
+                Some(ProtocolOptions { group: None, ..opts })
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tls12::OptionsError;
+    use NamedGroup::*;
+
+    // The supported_groups of the OpenSSL 3.0 ClientHellos in hello's
+    // tests, TLS 1.2 and TLS 1.3.
+    const TLS12_OFFER: [u16; 5] = [29, 23, 30, 25, 24];
+    const TLS13_OFFER: [u16; 10] = [29, 23, 30, 25, 24, 0x100, 0x101, 0x102, 0x103, 0x104];
+
+    #[test]
+    fn group_codec() {
+        for &g in NamedGroup::ALL.iter() {
+            assert_eq!(NamedGroup::from_u16(g.to_u16()), Some(g));
+        }
+        assert_eq!(NamedGroup::from_u16(0x0a0a), None);
+        assert_eq!(X25519.to_string(), "x25519");
+        assert!(Ffdhe2048.is_ffdhe() && !Secp256r1.is_ffdhe());
+    }
+
+    #[test]
+    fn selection() {
+        assert_eq!(select_group(&TLS12_OFFER, &[Secp384r1, Secp256r1]), Some(Secp256r1));
+        assert_eq!(select_group(&TLS12_OFFER, &[Ffdhe2048]), None);
+        assert_eq!(select_group(&TLS13_OFFER, &[Ffdhe4096, Ffdhe2048]), Some(Ffdhe2048));
+        assert_eq!(select_group(&[0x0a0a, 24], &NamedGroup::ALL), Some(Secp384r1));
+        assert_eq!(select_group(&[], &NamedGroup::ALL), None);
+    }
+
+    #[test]
+    fn server_key_exchange() {
+        let server = [Ffdhe3072, Secp256r1, X25519];
+        let ecdhe = ProtocolOptions::default();
+        let chosen = choose_server_group(ecdhe, &TLS12_OFFER, &server).unwrap();
+        assert_eq!(chosen.group, Some(X25519));
+        assert_eq!(chosen.validate(), Ok(()));
+        assert_eq!(choose_server_group(ecdhe, &TLS12_OFFER, &[Ffdhe3072]), None);
+
+        let dhe = ProtocolOptions::builder().key_exchange(KeyExchange::DheRsa).build().unwrap();
+        let chosen = choose_server_group(dhe, &TLS13_OFFER, &server).unwrap();
+        assert_eq!(chosen.group, Some(Ffdhe3072));
+        // Parameters of the server's own unless the client named FFDHE
+        // groups, in which case one of them it must be.
+        assert_eq!(choose_server_group(dhe, &TLS12_OFFER, &server), Some(dhe));
+        assert_eq!(choose_server_group(dhe, &[0x100], &server), None);
+
+        let rsa = ProtocolOptions::builder().key_exchange(KeyExchange::RsaKem).build().unwrap();
+        assert_eq!(choose_server_group(rsa, &[], &[]), Some(rsa));
+    }
+
+    #[test]
+    fn options() {
+        let opts = ProtocolOptions::builder().group(Some(Ffdhe2048)).build();
+        assert_eq!(opts, Err(OptionsError::UnsuitableGroup));
+        let opts = ProtocolOptions::builder().key_exchange(KeyExchange::RsaKem).group(Some(X25519));
+        assert_eq!(opts.build(), Err(OptionsError::UnsuitableGroup));
+        let opts = ProtocolOptions::builder().key_exchange(KeyExchange::DhAnon);
+        assert!(opts.group(Some(X25519)).build().is_ok());
+        assert!(opts.group(Some(Ffdhe2048)).build().is_ok());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::codec::{self, Reader};
+use crate::record::{self, ContentType, Record};
+use crate::tls12::{AlertDescription, MessageType};
+use crate::tls13;
+use std::fmt;
+
+/// Handshake messages are framed as
+///
+/// ```text
+/// struct {
+///     HandshakeType msg_type;
+///     uint24 length;
+///     select (msg_type) { ... } body;
+/// } Handshake;
+/// ```
+///
+/// and carried in handshake records, which may split a message or hold
+/// several (RFC 5246, 7.4 and RFC 8446, 4).
+pub const HEADER_LEN: usize = 4;
+
+/// The largest body a uint24 length can describe.
+pub const MAX_BODY_LEN: usize = (1 << 24) - 1;
+
+/// The HandshakeType of a TLS 1.2 message, or `None` for the entries of
+/// `MessageType` that are not handshake messages.
+pub fn msg_type(t: MessageType) -> Option<u8> {
+    use MessageType::*;
+    match t {
+        HelloRequest => Some(0),
+        ClientHello => Some(1),
+        ServerHello => Some(2),
+        NewSessionTicket => Some(4),
+        Certificate => Some(11),
+        ServerKeyExchange => Some(12),
+        CertificateRequest => Some(13),
+        ServerHelloDone => Some(14),
+        CertificateVerify => Some(15),
+        ClientKeyExchange => Some(16),
+        Finished => Some(20),
+        CertificateUrl => Some(21),
+        CertificateStatus => Some(22),
+        SupplementalData => Some(23),
+        NextProtocol => Some(67),
+        ChangeCipherSpec | Alert | ApplicationData => None,
+    }
+}
+
+pub fn from_msg_type(v: u8) -> Option<MessageType> {
+    MessageType::ALL.iter().copied().find(|&t| msg_type(t) == Some(v))
+}
+
+/// The HandshakeType of a TLS 1.3 message (RFC 8446, 4), or `None` for
+/// early data, ChangeCipherSpec and alerts, which are not handshake
+/// messages.
+pub fn tls13_msg_type(t: tls13::MessageType) -> Option<u8> {
+    use tls13::MessageType::*;
+    match t {
+        ClientHello => Some(1),
+        // A HelloRetryRequest is a ServerHello with a special random
+        // (RFC 8446, 4.1.3), so 2 deframes as a ServerHello.
+        ServerHello | HelloRetryRequest => Some(2),
+        NewSessionTicket => Some(4),
+        EndOfEarlyData => Some(5),
+        EncryptedExtensions => Some(8),
+        Certificate => Some(11),
+        CertificateRequest => Some(13),
+        CertificateVerify => Some(15),
+        Finished => Some(20),
+        KeyUpdate => Some(24),
+        CompressedCertificate => Some(25),
+        EarlyData | ChangeCipherSpec | Alert => None,
+    }
+}
+
+pub fn from_tls13_msg_type(v: u8) -> Option<tls13::MessageType> {
+    use tls13::MessageType::*;
+    [
+        ClientHello,
+        ServerHello,
+        NewSessionTicket,
+        EndOfEarlyData,
+        EncryptedExtensions,
+        Certificate,
+        CertificateRequest,
+        CertificateVerify,
+        Finished,
+        KeyUpdate,
+        CompressedCertificate,
+    ]
+    .iter()
+    .copied()
+    .find(|&t| tls13_msg_type(t) == Some(v))
+}
+
+/// Why handshake messages couldn't be framed or deframed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HandshakeError {
+    /// The body is longer than a uint24, or than the receiver accepts.
+    TooLong(usize),
+    /// A record of another content type arrived in the middle of a message
+    /// (RFC 8446, 5.1).
+    Interleaved(ContentType),
+    /// The records ended in the middle of a message.
+    Truncated,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandshakeError::TooLong(len) => write!(f, "handshake message of {} bytes", len),
+            HandshakeError::Interleaved(t) => {
+                write!(f, "{:?} record inside a handshake message", t)
+            }
+            HandshakeError::Truncated => write!(f, "truncated handshake message"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl HandshakeError {
+    /// The alert a receiver answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            HandshakeError::TooLong(_) => AlertDescription::DecodeError,
+            HandshakeError::Interleaved(_) => AlertDescription::UnexpectedMessage,
+            HandshakeError::Truncated => AlertDescription::DecodeError,
+        }
+    }
+}
+
+/// Prefixes `body` with its 4-byte header.
+pub fn frame(msg_type: u8, body: &[u8]) -> Result<Vec<u8>, HandshakeError> {
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    out.push(msg_type);
+    codec::write_u24_vec(&mut out, 0, MAX_BODY_LEN, "body", body)
+        .map_err(|_| HandshakeError::TooLong(body.len()))?;
+    Ok(out)
+}
+
+/// The msg_type and body length in a header, if `input` holds one.
+pub fn decode_header(input: &[u8]) -> Option<(u8, usize)> {
+    let mut r = Reader::new(input);
+    Some((r.read_u8().ok()?, r.read_u24().ok()? as usize))
+}
+
+/// Frames `messages` and splits them into handshake records of at most
+/// `max_len` bytes, which must not be 0. Messages share records where they
+/// fit.
+pub fn frame_records(
+    messages: &[(u8, Vec<u8>)],
+    version: u16,
+    max_len: usize,
+) -> Result<Vec<Record>, HandshakeError> {
+    let mut data = Vec::new();
+    for (msg_type, body) in messages {
+        data.extend(frame(*msg_type, body)?);
+    }
+    Ok(record::fragment(ContentType::Handshake, version, &data, max_len))
+}
+
+/// Reassembles handshake messages from the fragments of handshake records,
+/// however they are split. Messages are handed out whole, once all their
+/// bytes have arrived.
+#[derive(Debug, Clone)]
+pub struct Deframer {
+    buf: Vec<u8>,
+    max_body_len: usize,
+}
+
+impl Deframer {
+    /// A deframer refusing bodies over `max_body_len` bytes, as soon as their
+    /// header arrives.
+    pub fn new(max_body_len: usize) -> Deframer {
+        Deframer { buf: Vec::new(), max_body_len }
+    }
+
+    /// Whether a message is partway through. Handshake records must not
+    /// end, or give way to other records, in the middle of a message.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Takes a record; records of other types are refused while a message is
+    /// incomplete.
+    pub fn push(&mut self, record: &Record) -> Result<(), HandshakeError> {
+        if record.content_type != ContentType::Handshake {
+            return if self.is_empty() {
+                Ok(())
+            } else {
+                Err(HandshakeError::Interleaved(record.content_type))
+            };
+        }
+        self.buf.extend_from_slice(&record.fragment);
+        Ok(())
+    }
+
+    /// The next complete message, if there is one.
+    pub fn pop(&mut self) -> Result<Option<(u8, Vec<u8>)>, HandshakeError> {
+        match decode_header(&self.buf) {
+            None => Ok(None),
+            Some((_, len)) if len > self.max_body_len => Err(HandshakeError::TooLong(len)),
+            Some((_, len)) if self.buf.len() < HEADER_LEN + len => Ok(None),
+            Some((msg_type, len)) => {
+                let body = self.buf[HEADER_LEN..HEADER_LEN + len].to_vec();
+                self.buf.drain(..HEADER_LEN + len);
+                Ok(Some((msg_type, body)))
+            }
+        }
+    }
+}
+
+/// All the handshake messages in a run of handshake records, which must end
+/// on a message boundary.
+pub fn deframe_records(records: &[Record]) -> Result<Vec<(u8, Vec<u8>)>, HandshakeError> {
+    let mut deframer = Deframer::new(MAX_BODY_LEN);
+    let mut out = Vec::new();
+    for record in records {
+        deframer.push(record)?;
+        while let Some(message) = deframer.pop()? {
+            out.push(message);
+        }
+    }
+    if deframer.is_empty() {
+        Ok(out)
+    } else {
+        Err(HandshakeError::Truncated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn msg_type_codec() {
+        for &t in MessageType::ALL.iter() {
+            match msg_type(t) {
+                Some(v) => assert_eq!(from_msg_type(v), Some(t)),
+                None => assert!(!t.in_handshake_hash() && t != MessageType::HelloRequest),
+            }
+        }
+        assert_eq!(msg_type(MessageType::ServerHelloDone), Some(14));
+        assert_eq!(from_msg_type(3), None);
+        for v in 0..=255 {
+            if let Some(t) = from_tls13_msg_type(v) {
+                assert_eq!(tls13_msg_type(t), Some(v));
+            }
+        }
+        assert_eq!(from_tls13_msg_type(8), Some(tls13::MessageType::EncryptedExtensions));
+        assert_eq!(
+            tls13_msg_type(tls13::MessageType::HelloRetryRequest).and_then(from_tls13_msg_type),
+            Some(tls13::MessageType::ServerHello)
+        );
+        assert_eq!(tls13_msg_type(tls13::MessageType::EarlyData), None);
+        assert_eq!(from_tls13_msg_type(5), Some(tls13::MessageType::EndOfEarlyData));
+    }
+
+    #[test]
+    fn header() {
+        assert_eq!(frame(14, &[]), Ok(vec![14, 0, 0, 0]));
+        let framed = frame(20, &[0xaa; 0x10203]).unwrap();
+        assert_eq!(&framed[..4], &[20, 1, 2, 3]);
+        assert_eq!(decode_header(&framed), Some((20, 0x10203)));
+        assert_eq!(decode_header(&framed[..3]), None);
+        assert_eq!(
+            frame(11, &vec![0; MAX_BODY_LEN + 1]),
+            Err(HandshakeError::TooLong(MAX_BODY_LEN + 1))
+        );
+    }
+
+    #[test]
+    fn messages_across_records() {
+        let messages =
+            vec![(2, vec![1; 70]), (11, vec![2; 40000]), (12, vec![3; 300]), (14, vec![])];
+        for &max_len in &[1, 3, 5, 100, record::MAX_FRAGMENT_LEN] {
+            let records = frame_records(&messages, 0x0303, max_len).unwrap();
+            assert!(records.iter().all(|r| r.fragment.len() <= max_len));
+            assert_eq!(deframe_records(&records), Ok(messages.clone()));
+        }
+
+        // Several messages in one record, and one message over several.
+        let records = frame_records(&messages, 0x0303, record::MAX_FRAGMENT_LEN).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(deframe_records(&records[..1]), Err(HandshakeError::Truncated));
+    }
+
+    #[test]
+    fn deframer() {
+        let mut deframer = Deframer::new(100);
+        let hs = |fragment: &[u8]| Record {
+            content_type: ContentType::Handshake,
+            version: 0x0303,
+            fragment: fragment.to_vec(),
+        };
+        let ccs = Record { content_type: ContentType::ChangeCipherSpec, ..hs(&[1]) };
+
+        deframer.push(&ccs).unwrap();
+        deframer.push(&hs(&[20, 0])).unwrap();
+        assert_eq!(deframer.pop(), Ok(None));
+        assert_eq!(deframer.push(&ccs), Err(HandshakeError::Interleaved(ccs.content_type)));
+        deframer.push(&hs(&[0, 2, 7, 8, 14, 0, 0])).unwrap();
+        assert_eq!(deframer.pop(), Ok(Some((20, vec![7, 8]))));
+        assert_eq!(deframer.pop(), Ok(None));
+        deframer.push(&hs(&[0])).unwrap();
+        assert_eq!(deframer.pop(), Ok(Some((14, vec![]))));
+        assert!(deframer.is_empty());
+
+        // An overlong message is refused from its header.
+        deframer.push(&hs(&[11, 0, 0, 101])).unwrap();
+        assert_eq!(deframer.pop(), Err(HandshakeError::TooLong(101)));
+        assert_eq!(HandshakeError::TooLong(101).alert(), AlertDescription::DecodeError);
+    }
+}
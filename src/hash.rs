@@ -0,0 +1,13 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+// A hash function, as HMAC and the PRF and HKDF built on it see it.
+pub trait HashSpec {
+    // The size of the blocks the message is processed in, in bytes.
+    const BLOCK_LEN: usize;
+    // The size of the digest, in bytes.
+    const HASH_LEN: usize;
+
+    fn hash(msg: Bytes) -> Bytes;
+}
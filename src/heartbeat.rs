@@ -0,0 +1,480 @@
+use crate::codec::{CodecError, Reader};
+use crate::extensions::{Extension, ExtensionType};
+use crate::record::{ContentType, Record, MAX_FRAGMENT_LEN};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+// The heartbeat extension and protocol (RFC 6520). Each peer says in its
+// hello whether it takes HeartbeatRequests; once both have sent the
+// extension, either may send a request after the handshake, and its peer
+// answers with a copy of the payload. A `Heartbeat` is that exchange for
+// one peer, beside the handshake state machines.
+
+/// The smallest padding a heartbeat message carries (RFC 6520, 4).
+pub const MIN_PADDING_LEN: usize = 16;
+
+/// The type byte and payload_length.
+const HEADER_LEN: usize = 3;
+
+/// What a peer's heartbeat extension allows the other peer (RFC 6520, 2):
+///
+/// ```text
+/// enum {
+///    peer_allowed_to_send(1),
+///    peer_not_allowed_to_send(2),
+///    (255)
+/// } HeartbeatMode;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeartbeatMode {
+    PeerAllowedToSend,
+    PeerNotAllowedToSend,
+}
+
+impl HeartbeatMode {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            HeartbeatMode::PeerAllowedToSend => 1,
+            HeartbeatMode::PeerNotAllowedToSend => 2,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<HeartbeatMode> {
+        match v {
+            1 => Some(HeartbeatMode::PeerAllowedToSend),
+            2 => Some(HeartbeatMode::PeerNotAllowedToSend),
+            _ => None,
+        }
+    }
+}
+
+/// ```text
+/// enum {
+///    heartbeat_request(1),
+///    heartbeat_response(2),
+///    (255)
+/// } HeartbeatMessageType;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeartbeatMessageType {
+    Request,
+    Response,
+}
+
+impl HeartbeatMessageType {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            HeartbeatMessageType::Request => 1,
+            HeartbeatMessageType::Response => 2,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<HeartbeatMessageType> {
+        match v {
+            1 => Some(HeartbeatMessageType::Request),
+            2 => Some(HeartbeatMessageType::Response),
+            _ => None,
+        }
+    }
+}
+
+/// A heartbeat record's fragment (RFC 6520, 4):
+///
+/// ```text
+/// struct {
+///    HeartbeatMessageType type;
+///    uint16 payload_length;
+///    opaque payload[HeartbeatMessage.payload_length];
+///    opaque padding[padding_length];
+/// } HeartbeatMessage;
+/// ```
+///
+/// Nothing gives the padding's length but the record's, which is what a
+/// receiver must check payload_length against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatMessage {
+    pub message_type: HeartbeatMessageType,
+    pub payload: Vec<u8>,
+    pub padding: Vec<u8>,
+}
+
+/// Why a heartbeat message couldn't be sent or was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeartbeatError {
+    /// The extension is malformed.
+    Codec(CodecError),
+    /// The extension names a mode other than `HeartbeatMode`'s.
+    UnknownMode(u8),
+    /// The type isn't one of `HeartbeatMessageType`.
+    UnknownMessageType(u8),
+    /// payload_length and the minimal padding don't fit in the record.
+    /// A receiver trusting payload_length reads past the record's end:
+    /// Heartbleed (CVE-2014-0160).
+    PayloadLength { claimed: usize, available: usize },
+    /// Less than `MIN_PADDING_LEN` bytes of padding.
+    ShortPadding(usize),
+    /// The message is longer than a record's fragment may be.
+    TooLong(usize),
+    /// A heartbeat record without both peers having sent the extension.
+    NotNegotiated,
+    /// The receiver's mode says the sender may not send requests.
+    NotAllowed,
+    /// A request is already awaiting its response (RFC 6520, 3).
+    InFlight,
+    /// A request during a handshake (RFC 6520, 3).
+    DuringHandshake,
+    /// A response that doesn't answer the request in flight.
+    UnexpectedPayload,
+}
+
+impl fmt::Display for HeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeartbeatError::Codec(e) => e.fmt(f),
+            HeartbeatError::UnknownMode(m) => write!(f, "unknown heartbeat mode {}", m),
+            HeartbeatError::UnknownMessageType(t) => write!(f, "unknown heartbeat type {}", t),
+            HeartbeatError::PayloadLength { claimed, available } => {
+                write!(f, "heartbeat payload of {} bytes in {} bytes of record", claimed, available)
+            }
+            HeartbeatError::ShortPadding(len) => write!(f, "{} bytes of heartbeat padding", len),
+            HeartbeatError::TooLong(len) => write!(f, "heartbeat of {} bytes is too long", len),
+            HeartbeatError::NotNegotiated => write!(f, "heartbeat not negotiated"),
+            HeartbeatError::NotAllowed => write!(f, "peer may not send heartbeat requests"),
+            HeartbeatError::InFlight => write!(f, "heartbeat request already in flight"),
+            HeartbeatError::DuringHandshake => write!(f, "heartbeat during a handshake"),
+            HeartbeatError::UnexpectedPayload => write!(f, "unexpected heartbeat payload"),
+        }
+    }
+}
+
+impl std::error::Error for HeartbeatError {}
+
+impl From<CodecError> for HeartbeatError {
+    fn from(e: CodecError) -> HeartbeatError {
+        HeartbeatError::Codec(e)
+    }
+}
+
+impl HeartbeatError {
+    /// The alert a receiver answers with, if any. Bad messages are dropped
+    /// silently (RFC 6520, 3 and 4); a receiver that disallowed requests
+    /// may also send unexpected_message, but needn't.
+    pub fn alert(&self) -> Option<AlertDescription> {
+        match self {
+            HeartbeatError::Codec(e) => Some(e.alert()),
+            HeartbeatError::UnknownMode(_) => Some(AlertDescription::IllegalParameter),
+            HeartbeatError::NotNegotiated => Some(AlertDescription::UnexpectedMessage),
+            _ => None,
+        }
+    }
+}
+
+/// The heartbeat extension's data is just the mode.
+pub fn heartbeat_extension(mode: HeartbeatMode) -> Extension {
+    Extension { extension_type: ExtensionType::Heartbeat.to_u16(), data: vec![mode.to_u8()] }
+}
+
+pub fn decode_heartbeat_extension(data: &[u8]) -> Result<HeartbeatMode, HeartbeatError> {
+    let mut r = Reader::new(data);
+    let mode = r.read_u8()?;
+    r.end()?;
+    HeartbeatMode::from_u8(mode).ok_or(HeartbeatError::UnknownMode(mode))
+}
+
+/// Encodes `msg`, which may be at most `max_len` bytes: 2^14, or less
+/// under max_fragment_length (RFC 6066, 4).
+pub fn encode_heartbeat(msg: &HeartbeatMessage, max_len: usize) -> Result<Vec<u8>, HeartbeatError> {
+    if msg.padding.len() < MIN_PADDING_LEN {
+        return Err(HeartbeatError::ShortPadding(msg.padding.len()));
+    }
+    let len = HEADER_LEN + msg.payload.len() + msg.padding.len();
+    if len > max_len {
+        return Err(HeartbeatError::TooLong(len));
+    }
+    let mut out = Vec::with_capacity(len);
+    out.push(msg.message_type.to_u8());
+    out.extend_from_slice(&(msg.payload.len() as u16).to_be_bytes());
+    out.extend_from_slice(&msg.payload);
+    out.extend_from_slice(&msg.padding);
+    Ok(out)
+}
+
+/// Decodes a heartbeat record's fragment, with the bounds check of RFC
+/// 6520, 4: the payload and at least `MIN_PADDING_LEN` bytes of padding
+/// must fit in what the record holds.
+pub fn decode_heartbeat(fragment: &[u8]) -> Result<HeartbeatMessage, HeartbeatError> {
+    let mut r = Reader::new(fragment);
+    let message_type = r.read_u8()?;
+    let claimed = r.read_u16()? as usize;
+    let message_type = HeartbeatMessageType::from_u8(message_type)
+        .ok_or(HeartbeatError::UnknownMessageType(message_type))?;
+    let available = r.rest().len();
+    if claimed + MIN_PADDING_LEN > available {
+        return Err(HeartbeatError::PayloadLength { claimed, available });
+    }
+    let payload = r.take(claimed)?.to_vec();
+    Ok(HeartbeatMessage { message_type, payload, padding: r.rest().to_vec() })
+}
+
+/// How many bytes beyond `fragment` a receiver without that bounds check
+/// copies into its response, as OpenSSL 1.0.1 up to 1.0.1f did.
+pub fn heartbleed_overread(fragment: &[u8]) -> usize {
+    if fragment.len() < HEADER_LEN {
+        return 0;
+    }
+    let claimed = u16::from_be_bytes([fragment[1], fragment[2]]) as usize;
+    (HEADER_LEN + claimed).saturating_sub(fragment.len())
+}
+
+/// One peer's side of the heartbeat protocol, from the modes each peer's
+/// extension gave (`None` if it sent none).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heartbeat {
+    local: Option<HeartbeatMode>,
+    peer: Option<HeartbeatMode>,
+    max_len: usize,
+    handshaking: bool,
+    in_flight: Option<Vec<u8>>,
+}
+
+impl Heartbeat {
+    pub fn new(local: Option<HeartbeatMode>, peer: Option<HeartbeatMode>) -> Heartbeat {
+        Heartbeat { local, peer, max_len: MAX_FRAGMENT_LEN, handshaking: false, in_flight: None }
+    }
+
+    /// Limits messages each way to a negotiated max_fragment_length.
+    pub fn with_max_len(mut self, max_len: usize) -> Heartbeat {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn negotiated(&self) -> bool {
+        self.local.is_some() && self.peer.is_some()
+    }
+
+    /// Whether a renegotiation (or, in DTLS, any handshake) is under way.
+    /// A request in flight when one starts is abandoned.
+    pub fn set_handshaking(&mut self, handshaking: bool) {
+        self.handshaking = handshaking;
+        if handshaking {
+            self.in_flight = None;
+        }
+    }
+
+    /// The payload of the request awaiting its response.
+    pub fn in_flight(&self) -> Option<&[u8]> {
+        self.in_flight.as_deref()
+    }
+
+    /// Sends a request with `payload`, which the peer must echo, and
+    /// `padding`, which should be random. At most one request is in flight
+    /// at a time.
+    pub fn request(&mut self, payload: &[u8], padding: &[u8]) -> Result<Record, HeartbeatError> {
+        if !self.negotiated() {
+            return Err(HeartbeatError::NotNegotiated);
+        }
+        if self.peer != Some(HeartbeatMode::PeerAllowedToSend) {
+            return Err(HeartbeatError::NotAllowed);
+        }
+        if self.handshaking {
+            return Err(HeartbeatError::DuringHandshake);
+        }
+        if self.in_flight.is_some() {
+            return Err(HeartbeatError::InFlight);
+        }
+        let msg = HeartbeatMessage {
+            message_type: HeartbeatMessageType::Request,
+            payload: payload.to_vec(),
+            padding: padding.to_vec(),
+        };
+        let record = self.record(&msg)?;
+        self.in_flight = Some(msg.payload);
+        Ok(record)
+    }
+
+    /// Receives a heartbeat record's fragment: a request gets the record
+    /// of its response, with fresh `padding`, and the response to the
+    /// request in flight gets `None`. Anything else is an error, most of
+    /// which are dropped without a word; see `HeartbeatError::alert`.
+    pub fn receive(
+        &mut self,
+        fragment: &[u8],
+        padding: &[u8],
+    ) -> Result<Option<Record>, HeartbeatError> {
+        if !self.negotiated() {
+            return Err(HeartbeatError::NotNegotiated);
+        }
+        if fragment.len() > self.max_len {
+            return Err(HeartbeatError::TooLong(fragment.len()));
+        }
+        let msg = decode_heartbeat(fragment)?;
+        match msg.message_type {
+            HeartbeatMessageType::Request => {
+                if self.local != Some(HeartbeatMode::PeerAllowedToSend) {
+                    return Err(HeartbeatError::NotAllowed);
+                }
+                if self.handshaking {
+                    return Err(HeartbeatError::DuringHandshake);
+                }
+                let response = HeartbeatMessage {
+                    message_type: HeartbeatMessageType::Response,
+                    payload: msg.payload,
+                    padding: padding.to_vec(),
+                };
+                self.record(&response).map(Some)
+            }
+            HeartbeatMessageType::Response => {
+                if self.in_flight.as_deref() != Some(&msg.payload[..]) {
+                    return Err(HeartbeatError::UnexpectedPayload);
+                }
+                self.in_flight = None;
+                Ok(None)
+            }
+        }
+    }
+
+    fn record(&self, msg: &HeartbeatMessage) -> Result<Record, HeartbeatError> {
+        let fragment = encode_heartbeat(msg, self.max_len)?;
+        Ok(Record { content_type: ContentType::Heartbeat, version: 0x0303, fragment })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::record::{self, Limit};
+
+    const PADDING: [u8; MIN_PADDING_LEN] = [0xa5; MIN_PADDING_LEN];
+
+    fn peers(client: HeartbeatMode, server: HeartbeatMode) -> (Heartbeat, Heartbeat) {
+        (Heartbeat::new(Some(client), Some(server)), Heartbeat::new(Some(server), Some(client)))
+    }
+
+    #[test]
+    fn extension_codec() {
+        for &mode in &[HeartbeatMode::PeerAllowedToSend, HeartbeatMode::PeerNotAllowedToSend] {
+            let ext = heartbeat_extension(mode);
+            assert_eq!(ext.extension_type, 15);
+            assert_eq!(decode_heartbeat_extension(&ext.data), Ok(mode));
+        }
+        assert_eq!(decode_heartbeat_extension(&[1]), Ok(HeartbeatMode::PeerAllowedToSend));
+        let unknown = decode_heartbeat_extension(&[3]).unwrap_err();
+        assert_eq!(unknown, HeartbeatError::UnknownMode(3));
+        assert_eq!(unknown.alert(), Some(AlertDescription::IllegalParameter));
+        let trailing = HeartbeatError::Codec(CodecError::TrailingBytes);
+        assert_eq!(decode_heartbeat_extension(&[1, 1]), Err(trailing));
+    }
+
+    #[test]
+    fn round_trip() {
+        let msg = HeartbeatMessage {
+            message_type: HeartbeatMessageType::Request,
+            payload: b"ping".to_vec(),
+            padding: PADDING.to_vec(),
+        };
+        let bytes = encode_heartbeat(&msg, MAX_FRAGMENT_LEN).unwrap();
+        assert_eq!(&bytes[..7], &[1, 0, 4, b'p', b'i', b'n', b'g']);
+        assert_eq!(bytes.len(), 7 + MIN_PADDING_LEN);
+        assert_eq!(decode_heartbeat(&bytes), Ok(msg.clone()));
+
+        let short = HeartbeatMessage { padding: vec![0; 15], ..msg.clone() };
+        let refused = HeartbeatError::ShortPadding(15);
+        assert_eq!(encode_heartbeat(&short, MAX_FRAGMENT_LEN), Err(refused));
+        assert_eq!(encode_heartbeat(&msg, 22), Err(HeartbeatError::TooLong(23)));
+        let mut unknown = bytes;
+        unknown[0] = 3;
+        assert_eq!(decode_heartbeat(&unknown), Err(HeartbeatError::UnknownMessageType(3)));
+    }
+
+    #[test]
+    fn heartbleed() {
+        // The record of the published Heartbleed exploits: a request of no
+        // payload and no padding, claiming 2^14 bytes of payload.
+        let attack = [0x18, 0x03, 0x02, 0x00, 0x03, 0x01, 0x40, 0x00];
+        let (rec, _) = record::decode_record(&attack, Limit::Plaintext).unwrap();
+        assert_eq!(rec.content_type, ContentType::Heartbeat);
+        let refused = HeartbeatError::PayloadLength { claimed: 0x4000, available: 0 };
+        assert_eq!(decode_heartbeat(&rec.fragment), Err(refused.clone()));
+        assert_eq!(refused.alert(), None);
+        assert_eq!(heartbleed_overread(&rec.fragment), 0x4000);
+        let mode = HeartbeatMode::PeerAllowedToSend;
+        let (_, mut server) = peers(mode, mode);
+        assert_eq!(server.receive(&rec.fragment, &PADDING), Err(refused));
+
+        // Claims that fit the payload but not the padding are refused too,
+        // though they read nothing past the record.
+        let mut fragment = vec![1, 0, 8];
+        fragment.extend_from_slice(&PADDING);
+        let refused = HeartbeatError::PayloadLength { claimed: 8, available: 16 };
+        assert_eq!(decode_heartbeat(&fragment), Err(refused));
+        assert_eq!(heartbleed_overread(&fragment), 0);
+        fragment[2] = 0;
+        assert!(decode_heartbeat(&fragment).is_ok());
+    }
+
+    #[test]
+    fn exchange() {
+        use HeartbeatMode::*;
+        let (mut client, mut server) = peers(PeerAllowedToSend, PeerAllowedToSend);
+        let request = client.request(b"ping", &PADDING).unwrap();
+        assert_eq!(client.in_flight(), Some(&b"ping"[..]));
+        assert_eq!(client.request(b"pong", &PADDING), Err(HeartbeatError::InFlight));
+
+        let response = server.receive(&request.fragment, &[0x5a; 20]).unwrap().unwrap();
+        let msg = decode_heartbeat(&response.fragment).unwrap();
+        assert_eq!(msg.message_type, HeartbeatMessageType::Response);
+        assert_eq!(msg.payload, b"ping");
+        assert_eq!(msg.padding, vec![0x5a; 20]);
+
+        // A response to nothing is dropped.
+        let unexpected = Err(HeartbeatError::UnexpectedPayload);
+        assert_eq!(server.receive(&response.fragment, &PADDING), unexpected);
+        assert_eq!(client.receive(&response.fragment, &PADDING), Ok(None));
+        assert_eq!(client.in_flight(), None);
+        assert_eq!(client.receive(&response.fragment, &PADDING), unexpected);
+    }
+
+    #[test]
+    fn modes() {
+        use HeartbeatMode::*;
+        // The server takes requests; the client doesn't.
+        let (mut client, mut server) = peers(PeerNotAllowedToSend, PeerAllowedToSend);
+        assert_eq!(server.request(b"ping", &PADDING), Err(HeartbeatError::NotAllowed));
+        let request = client.request(b"ping", &PADDING).unwrap();
+        assert!(server.receive(&request.fragment, &PADDING).unwrap().is_some());
+
+        let request = Heartbeat::new(None, None).request(b"ping", &PADDING);
+        assert_eq!(request, Err(HeartbeatError::NotNegotiated));
+        let request = Heartbeat::new(Some(PeerAllowedToSend), Some(PeerAllowedToSend))
+            .request(b"ping", &PADDING)
+            .unwrap();
+        assert_eq!(client.receive(&request.fragment, &PADDING), Err(HeartbeatError::NotAllowed));
+        let mut unnegotiated = Heartbeat::new(Some(PeerAllowedToSend), None);
+        let refused = unnegotiated.receive(&request.fragment, &PADDING).unwrap_err();
+        assert_eq!(refused.alert(), Some(AlertDescription::UnexpectedMessage));
+    }
+
+    #[test]
+    fn handshakes_and_limits() {
+        use HeartbeatMode::*;
+        let (mut client, mut server) = peers(PeerAllowedToSend, PeerAllowedToSend);
+        let request = client.request(b"ping", &PADDING).unwrap();
+        client.set_handshaking(true);
+        assert_eq!(client.in_flight(), None);
+        assert_eq!(client.request(b"ping", &PADDING), Err(HeartbeatError::DuringHandshake));
+        server.set_handshaking(true);
+        let refused = Err(HeartbeatError::DuringHandshake);
+        assert_eq!(server.receive(&request.fragment, &PADDING), refused);
+        server.set_handshaking(false);
+        assert!(server.receive(&request.fragment, &PADDING).unwrap().is_some());
+
+        // Under a max_fragment_length of 2^9, a payload of 500 bytes is too
+        // much once the padding is added.
+        let mut small =
+            Heartbeat::new(Some(PeerAllowedToSend), Some(PeerAllowedToSend)).with_max_len(512);
+        assert_eq!(small.request(&[0; 500], &PADDING), Err(HeartbeatError::TooLong(519)));
+        assert!(small.request(&[0; 493], &PADDING).is_ok());
+    }
+}
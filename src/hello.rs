@@ -0,0 +1,555 @@
+use crate::codec::{self, CodecError, Reader};
+use crate::extensions::{self, Extension, ExtensionError, ExtensionType};
+use crate::tls12::{AlertDescription, ProtocolOptions};
+use std::fmt;
+
+/// The ClientHello body (RFC 5246, 7.4.1.2):
+///
+/// ```text
+/// struct {
+///     ProtocolVersion client_version;
+///     Random random;
+///     SessionID session_id;
+///     CipherSuite cipher_suites<2..2^16-2>;
+///     CompressionMethod compression_methods<1..2^8-1>;
+///     select (extensions_present) {
+///         case false: struct {};
+///         case true: Extension extensions<0..2^16-1>;
+///     };
+/// } ClientHello;
+/// ```
+///
+/// TLS 1.3 keeps the layout, with client_version renamed legacy_version and
+/// held at 0x0303 (RFC 8446, 4.1.2). A ClientHello without extensions is
+/// kept apart from one with an empty list, so both round-trip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientHello {
+    pub client_version: u16,
+    pub random: [u8; 32],
+    pub session_id: Vec<u8>,
+    pub cipher_suites: Vec<u16>,
+    pub compression_methods: Vec<u8>,
+    pub extensions: Option<Vec<Extension>>,
+}
+
+impl ClientHello {
+    /// The data of the extension of the given type, if it was sent.
+    pub fn extension(&self, extension_type: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .flatten()
+            .find(|e| e.extension_type == extension_type)
+            .map(|e| &e.data[..])
+    }
+}
+
+/// Why a hello message couldn't be encoded, decoded or accepted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HelloError {
+    /// The body is malformed.
+    Codec(CodecError),
+    /// The null compression method isn't offered.
+    NoNullCompression,
+    /// The extension list is malformed.
+    Extension(ExtensionError),
+    /// The server picked a cipher suite the client didn't offer.
+    UnofferedCipherSuite(u16),
+    /// The server picked a compression method the client didn't offer.
+    UnofferedCompression(u8),
+    /// The server sent an extension the client didn't.
+    UnsolicitedExtension(u16),
+    /// A TLS 1.3 server didn't echo the client's session id.
+    SessionIdMismatch,
+}
+
+impl fmt::Display for HelloError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HelloError::Codec(e) => e.fmt(f),
+            HelloError::NoNullCompression => write!(f, "null compression not offered"),
+            HelloError::Extension(e) => e.fmt(f),
+            HelloError::UnofferedCipherSuite(s) => write!(f, "cipher suite {:04x} not offered", s),
+            HelloError::UnofferedCompression(c) => {
+                write!(f, "compression method {} not offered", c)
+            }
+            HelloError::UnsolicitedExtension(t) => write!(f, "unsolicited extension {}", t),
+            HelloError::SessionIdMismatch => write!(f, "session id not echoed"),
+        }
+    }
+}
+
+impl std::error::Error for HelloError {}
+
+impl From<CodecError> for HelloError {
+    fn from(e: CodecError) -> HelloError {
+        HelloError::Codec(e)
+    }
+}
+
+impl From<ExtensionError> for HelloError {
+    fn from(e: ExtensionError) -> HelloError {
+        HelloError::Extension(e)
+    }
+}
+
+impl HelloError {
+    /// The alert a receiver answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            HelloError::Codec(e) => e.alert(),
+            HelloError::Extension(e) => e.alert(),
+            HelloError::UnsolicitedExtension(_) => AlertDescription::UnsupportedExtension,
+            _ => AlertDescription::IllegalParameter,
+        }
+    }
+}
+
+fn encode_extensions(
+    out: &mut Vec<u8>,
+    extensions: &Option<Vec<Extension>>,
+) -> Result<(), HelloError> {
+    if let Some(extensions) = extensions {
+        out.extend(extensions::encode_extensions(extensions)?);
+    }
+    Ok(())
+}
+
+// Extensions run to the end of the body, if there are any.
+fn decode_extensions(r: &mut Reader) -> Result<Option<Vec<Extension>>, HelloError> {
+    if r.is_empty() {
+        return Ok(None);
+    }
+    let (extensions, len) = extensions::decode_extensions(r.rest())?;
+    r.take(len)?;
+    Ok(Some(extensions))
+}
+
+pub fn encode_client_hello(hello: &ClientHello) -> Result<Vec<u8>, HelloError> {
+    let mut out = Vec::new();
+    codec::write_u16(&mut out, hello.client_version);
+    out.extend_from_slice(&hello.random);
+    codec::write_u8_vec(&mut out, 0, 32, "session_id", &hello.session_id)?;
+    codec::write_u16_list(&mut out, 2, 0xfffe, "cipher_suites", &hello.cipher_suites)?;
+    codec::write_u8_vec(&mut out, 1, 0xff, "compression_methods", &hello.compression_methods)?;
+    if !hello.compression_methods.contains(&0) {
+        return Err(HelloError::NoNullCompression);
+    }
+    encode_extensions(&mut out, &hello.extensions)?;
+    Ok(out)
+}
+
+pub fn decode_client_hello(body: &[u8]) -> Result<ClientHello, HelloError> {
+    let mut r = Reader::new(body);
+    let client_version = r.read_u16()?;
+    let mut random = [0u8; 32];
+    random.copy_from_slice(r.take(32)?);
+    let session_id = r.read_u8_vec(0, 32, "session_id")?.to_vec();
+    let cipher_suites = r.read_u16_list(2, 0xfffe, "cipher_suites")?;
+    let compression_methods = r.read_u8_vec(1, 0xff, "compression_methods")?.to_vec();
+    if !compression_methods.contains(&0) {
+        return Err(HelloError::NoNullCompression);
+    }
+    let extensions = decode_extensions(&mut r)?;
+    r.end()?;
+    Ok(ClientHello {
+        client_version,
+        random,
+        session_id,
+        cipher_suites,
+        compression_methods,
+        extensions,
+    })
+}
+
+/// The ServerHello body (RFC 5246, 7.4.1.3):
+///
+/// ```text
+/// struct {
+///     ProtocolVersion server_version;
+///     Random random;
+///     SessionID session_id;
+///     CipherSuite cipher_suite;
+///     CompressionMethod compression_method;
+///     select (extensions_present) {
+///         case false: struct {};
+///         case true: Extension extensions<0..2^16-1>;
+///     };
+/// } ServerHello;
+/// ```
+///
+/// In TLS 1.3 the session_id is legacy_session_id_echo (RFC 8446, 4.1.3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerHello {
+    pub server_version: u16,
+    pub random: [u8; 32],
+    pub session_id: Vec<u8>,
+    pub cipher_suite: u16,
+    pub compression_method: u8,
+    pub extensions: Option<Vec<Extension>>,
+}
+
+impl ServerHello {
+    /// The data of the extension of the given type, if it was sent.
+    pub fn extension(&self, extension_type: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .flatten()
+            .find(|e| e.extension_type == extension_type)
+            .map(|e| &e.data[..])
+    }
+}
+
+pub fn encode_server_hello(hello: &ServerHello) -> Result<Vec<u8>, HelloError> {
+    let mut out = Vec::new();
+    codec::write_u16(&mut out, hello.server_version);
+    out.extend_from_slice(&hello.random);
+    codec::write_u8_vec(&mut out, 0, 32, "session_id", &hello.session_id)?;
+    codec::write_u16(&mut out, hello.cipher_suite);
+    out.push(hello.compression_method);
+    encode_extensions(&mut out, &hello.extensions)?;
+    Ok(out)
+}
+
+pub fn decode_server_hello(body: &[u8]) -> Result<ServerHello, HelloError> {
+    let mut r = Reader::new(body);
+    let server_version = r.read_u16()?;
+    let mut random = [0u8; 32];
+    random.copy_from_slice(r.take(32)?);
+    let session_id = r.read_u8_vec(0, 32, "session_id")?.to_vec();
+    let cipher_suite = r.read_u16()?;
+    let compression_method = r.read_u8()?;
+    let extensions = decode_extensions(&mut r)?;
+    r.end()?;
+    Ok(ServerHello {
+        server_version,
+        random,
+        session_id,
+        cipher_suite,
+        compression_method,
+        extensions,
+    })
+}
+
+const DEFLATE: u8 = 1;
+const EMPTY_RENEGOTIATION_INFO_SCSV: u16 = 0x00ff;
+
+/// Checks the ServerHello picks from what the ClientHello offered: one of
+/// its cipher suites and compression methods, and only extensions the
+/// client sent (RFC 5246, 7.4.1.4). renegotiation_info may also answer the
+/// signalling cipher suite (RFC 5746, 3.6).
+pub fn check_server_hello(client: &ClientHello, server: &ServerHello) -> Result<(), HelloError> {
+    if !client.cipher_suites.contains(&server.cipher_suite) {
+        return Err(HelloError::UnofferedCipherSuite(server.cipher_suite));
+    }
+    if !client.compression_methods.contains(&server.compression_method) {
+        return Err(HelloError::UnofferedCompression(server.compression_method));
+    }
+    for e in server.extensions.iter().flatten() {
+        let t = e.extension_type;
+        let scsv = t == ExtensionType::RenegotiationInfo.to_u16()
+            && client.cipher_suites.contains(&EMPTY_RENEGOTIATION_INFO_SCSV);
+        if client.extension(t).is_none() && !scsv {
+            return Err(HelloError::UnsolicitedExtension(t));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a TLS 1.2 server resumed the session: it echoes a non-empty
+/// session id to resume, and sends a fresh or empty one to run a full
+/// handshake (RFC 5246, 7.4.1.3). A client resuming from a ticket makes up
+/// the session id it sends, and the echo tells it the ticket was taken
+/// (RFC 5077, 3.4).
+pub fn resumes_session(client: &ClientHello, server: &ServerHello) -> bool {
+    !client.session_id.is_empty() && server.session_id == client.session_id
+}
+
+/// A TLS 1.3 server echoes the session id whatever it does, so anything
+/// else is an error (RFC 8446, 4.1.3).
+pub fn check_session_id_echo(client: &ClientHello, server: &ServerHello) -> Result<(), HelloError> {
+    if server.session_id == client.session_id {
+        Ok(())
+    } else {
+        Err(HelloError::SessionIdMismatch)
+    }
+}
+
+/// The resumption, ticket, renegotiation, extended master secret,
+/// encrypt-then-MAC, heartbeat and compression decisions of `opts` as a TLS
+/// 1.2 ClientHello and ServerHello make them. A client sending a session id
+/// without a ticket may or may not be offering a session; unless the server
+/// resumes it, that counts as not resuming.
+pub fn hello_options(
+    client: &ClientHello,
+    server: &ServerHello,
+    mut opts: ProtocolOptions,
+) -> ProtocolOptions {
+    let answered = |t: ExtensionType| server.extension(t.to_u16()).is_some();
+    let ticket = client.extension(ExtensionType::SessionTicket.to_u16());
+    let resumed = resumes_session(client, server);
+    opts.client_offers_ticket = ticket.is_some();
+    opts.resume_via_ticket = ticket.is_some_and(|t| !t.is_empty());
+    opts.resuming = resumed || opts.resume_via_ticket;
+    opts.server_accepts_resumption = resumed;
+    opts.server_issues_ticket = answered(ExtensionType::SessionTicket);
+    opts.secure_renegotiation = answered(ExtensionType::RenegotiationInfo);
+    opts.extended_master_secret = answered(ExtensionType::ExtendedMasterSecret);
+    opts.encrypt_then_mac = answered(ExtensionType::EncryptThenMac);
+    opts.heartbeat = answered(ExtensionType::Heartbeat);
+    opts.compression = server.compression_method == DEFLATE;
+    opts
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::handshake;
+    use crate::record::{self, ContentType, Limit};
+
+    // ClientHellos that OpenSSL 3.0 sent to example.com, one capped at
+    // TLS 1.2 and one offering TLS 1.3, as the records on the wire.
+    pub(crate) const TLS12_CLIENT_HELLO: &str = concat!(
+        "160301009b01000097030327ff6382e69cf65ab6881508a8edfb08543cdee534ccf8513e34f85a641049dc00",
+        "0008c02fc02b002f00ff0100006600000010000e00000b6578616d706c652e636f6d000b000403000102000a",
+        "000c000a001d0017001e00190018002300000016000000170000000d002a0028040305030603080708080809",
+        "080a080b080408050806040105010601030303010302040205020602",
+    );
+    pub(crate) const TLS13_CLIENT_HELLO: &str = concat!(
+        "1603010200010001fc0303d834ac53559e6364cd4f93902bf26241ab093ea23414833d92ffd18c8b80c4cb20",
+        "0bb1efe05cedad87d76941734e4f9acf991224c7159293953f6acd31a9f1f209000a130213031301c02f00ff",
+        "010001a900000010000e00000b6578616d706c652e636f6d000b000403000102000a00160014001d0017001e",
+        "0019001801000101010201030104002300000016000000170000000d002a0028040305030603080708080809",
+        "080a080b080408050806040105010601030303010302040205020602002b00050403040303002d0002010100",
+        "3300260024001d002041289560201ef860971f20f36ef406a4ac3b8fbd062964d919cbc45aa766ae2a001500",
+        "fc00000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "000000000000000000000000000000000000000000000000000000000000000000",
+    );
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // The ClientHello body in a captured record.
+    pub(crate) fn body(capture: &str) -> Vec<u8> {
+        let (rec, len) = record::decode_record(&hex(capture), Limit::Plaintext).unwrap();
+        assert_eq!(len, hex(capture).len());
+        assert_eq!(rec.content_type, ContentType::Handshake);
+        let mut messages = handshake::deframe_records(&[rec]).unwrap();
+        assert_eq!(messages.len(), 1);
+        let (msg_type, body) = messages.remove(0);
+        assert_eq!(msg_type, 1);
+        body
+    }
+
+    fn sample() -> ClientHello {
+        ClientHello {
+            client_version: 0x0303,
+            random: [7; 32],
+            session_id: vec![],
+            cipher_suites: vec![0xc02f, 0x009c],
+            compression_methods: vec![0],
+            extensions: None,
+        }
+    }
+
+    #[test]
+    fn captured_tls12() {
+        let body = body(TLS12_CLIENT_HELLO);
+        let hello = decode_client_hello(&body).unwrap();
+        assert_eq!(hello.client_version, 0x0303);
+        assert!(hello.session_id.is_empty());
+        assert_eq!(hello.cipher_suites, vec![0xc02f, 0xc02b, 0x002f, 0x00ff]);
+        assert_eq!(hello.compression_methods, vec![0]);
+        // server_name: a list holding the host_name "example.com".
+        let sni = hello.extension(0).unwrap();
+        assert_eq!(&sni[5..], b"example.com");
+        assert_eq!(encode_client_hello(&hello), Ok(body));
+    }
+
+    #[test]
+    fn captured_tls13() {
+        let body = body(TLS13_CLIENT_HELLO);
+        let hello = decode_client_hello(&body).unwrap();
+        assert_eq!(hello.client_version, 0x0303);
+        // A compatibility-mode session id (RFC 8446, D.4).
+        assert_eq!(hello.session_id.len(), 32);
+        assert_eq!(&hello.cipher_suites[..3], &[0x1302, 0x1303, 0x1301]);
+        // supported_versions lists TLS 1.3 first.
+        assert_eq!(&hello.extension(43).unwrap()[..3], &[4, 3, 4]);
+        assert_eq!(encode_client_hello(&hello), Ok(body));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut hello = sample();
+        let bytes = encode_client_hello(&hello).unwrap();
+        assert_eq!(bytes.len(), 2 + 32 + 1 + 6 + 2);
+        assert_eq!(decode_client_hello(&bytes), Ok(hello.clone()));
+
+        hello.extensions = Some(vec![]);
+        let bytes = encode_client_hello(&hello).unwrap();
+        assert_eq!(&bytes[bytes.len() - 2..], &[0, 0]);
+        assert_eq!(decode_client_hello(&bytes), Ok(hello.clone()));
+
+        hello.session_id = vec![1; 32];
+        hello.extensions = Some(vec![
+            Extension { extension_type: 0xff01, data: vec![0] },
+            Extension { extension_type: 0x1234, data: vec![1, 2, 3] },
+        ]);
+        let bytes = encode_client_hello(&hello).unwrap();
+        assert_eq!(decode_client_hello(&bytes), Ok(hello));
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = encode_client_hello(&sample()).unwrap();
+        for len in 0..bytes.len() {
+            assert!(decode_client_hello(&bytes[..len]).is_err());
+        }
+
+        let mut long = bytes.clone();
+        long.extend_from_slice(&[0, 0, 0]);
+        assert_eq!(decode_client_hello(&long), Err(HelloError::Codec(CodecError::TrailingBytes)));
+
+        // An odd cipher_suites length.
+        let mut odd = bytes.clone();
+        odd[36] = 5;
+        let err = HelloError::Codec(CodecError::BadLength("cipher_suites"));
+        assert_eq!(decode_client_hello(&odd), Err(err));
+
+        let mut hello = sample();
+        hello.session_id = vec![0; 33];
+        let err = HelloError::Codec(CodecError::BadLength("session_id"));
+        assert_eq!(encode_client_hello(&hello), Err(err));
+
+        let mut hello = sample();
+        hello.compression_methods = vec![1];
+        assert_eq!(encode_client_hello(&hello), Err(HelloError::NoNullCompression));
+        assert_eq!(HelloError::NoNullCompression.alert(), AlertDescription::IllegalParameter);
+
+        let mut hello = sample();
+        let e = Extension { extension_type: 10, data: vec![] };
+        hello.extensions = Some(vec![e.clone(), e]);
+        let err = HelloError::Extension(ExtensionError::Duplicate(10));
+        assert_eq!(encode_client_hello(&hello), Err(err));
+    }
+
+    // ServerHellos from OpenSSL 3.0 capped at TLS 1.2, as handshake messages,
+    // with the session ids of the ClientHellos they answer. The first runs a
+    // full handshake and promises a ticket; the second takes that ticket.
+    const FULL_SESSION_ID: &str =
+        "21909baa9b43ed3acd3e0f05d948a85f8434d968b947a1be4ebde9bdeb353f80";
+    pub(crate) const FULL_SERVER_HELLO: &str = concat!(
+        "0200003d030389d8236468e4c839c67751d8cea49857a41cc09b046d0d42726ec2547221618b00c02b000015",
+        "ff01000100000b0004030001020023000000170000",
+    );
+    const RESUMED_SESSION_ID: &str =
+        "01681a56c659b09376d7265c9d9e22091b2d3b5033941e8602928c23dfdb5801";
+    const RESUMED_SERVER_HELLO: &str = concat!(
+        "02000051030324faf5fd726d8ebcb382ef2468464f7237319f56d02f9ea3fa7189e0abb8e00b2001681a56c6",
+        "59b09376d7265c9d9e22091b2d3b5033941e8602928c23dfdb5801c02b000009ff0100010000170000",
+    );
+
+    pub(crate) fn server_hello(message: &str) -> (ServerHello, Vec<u8>) {
+        let message = hex(message);
+        assert_eq!(handshake::decode_header(&message), Some((2, message.len() - 4)));
+        let body = message[4..].to_vec();
+        (decode_server_hello(&body).unwrap(), body)
+    }
+
+    // A ClientHello that offers what the captured ServerHellos pick.
+    fn offer(session_id: &str, ticket: Vec<u8>) -> ClientHello {
+        let extension = |extension_type| Extension { extension_type, data: vec![] };
+        ClientHello {
+            session_id: hex(session_id),
+            cipher_suites: vec![0xc02b, 0x00ff],
+            extensions: Some(vec![
+                extension(11),
+                extension(23),
+                Extension { extension_type: ExtensionType::SessionTicket.to_u16(), data: ticket },
+            ]),
+            ..sample()
+        }
+    }
+
+    #[test]
+    fn captured_server_hellos() {
+        let (full, body) = server_hello(FULL_SERVER_HELLO);
+        assert_eq!(full.server_version, 0x0303);
+        assert!(full.session_id.is_empty());
+        assert_eq!(full.cipher_suite, 0xc02b);
+        assert_eq!(full.compression_method, 0);
+        assert_eq!(full.extension(ExtensionType::SessionTicket.to_u16()), Some(&[][..]));
+        assert_eq!(encode_server_hello(&full), Ok(body));
+
+        let (resumed, body) = server_hello(RESUMED_SERVER_HELLO);
+        assert_eq!(resumed.session_id, hex(RESUMED_SESSION_ID));
+        assert_eq!(resumed.extension(ExtensionType::SessionTicket.to_u16()), None);
+        assert_eq!(encode_server_hello(&resumed), Ok(body));
+    }
+
+    #[test]
+    fn session_id_echo() {
+        let (full, _) = server_hello(FULL_SERVER_HELLO);
+        let client = offer(FULL_SESSION_ID, vec![]);
+        assert_eq!(check_server_hello(&client, &full), Ok(()));
+        assert!(!resumes_session(&client, &full));
+        let opts = hello_options(&client, &full, ProtocolOptions::default());
+        assert!(!opts.resuming && opts.client_offers_ticket && opts.server_issues_ticket);
+        assert!(opts.secure_renegotiation && opts.extended_master_secret);
+        // OpenSSL only agrees to encrypt-then-MAC for a CBC suite.
+        assert!(!opts.encrypt_then_mac && !opts.heartbeat && !opts.compression);
+        assert_eq!(opts.validate(), Ok(()));
+
+        let (resumed, _) = server_hello(RESUMED_SERVER_HELLO);
+        let client = offer(RESUMED_SESSION_ID, vec![0xab; 16]);
+        assert_eq!(check_server_hello(&client, &resumed), Ok(()));
+        assert!(resumes_session(&client, &resumed));
+        let opts = hello_options(&client, &resumed, ProtocolOptions::default());
+        assert!(opts.resuming && opts.server_accepts_resumption && opts.resume_via_ticket);
+        assert!(!opts.server_issues_ticket);
+        assert_eq!(opts.validate(), Ok(()));
+
+        // The ticket was refused: a full handshake.
+        let opts = hello_options(&client, &full, ProtocolOptions::default());
+        assert!(opts.resuming && !opts.server_accepts_resumption);
+
+        // TLS 1.3 requires the echo.
+        assert_eq!(check_session_id_echo(&client, &resumed), Ok(()));
+        assert_eq!(check_session_id_echo(&client, &full), Err(HelloError::SessionIdMismatch));
+    }
+
+    #[test]
+    fn server_hello_checks() {
+        let (full, _) = server_hello(FULL_SERVER_HELLO);
+        let mut client = offer(FULL_SESSION_ID, vec![]);
+        client.cipher_suites = vec![0xc02f, 0x00ff];
+        assert_eq!(
+            check_server_hello(&client, &full),
+            Err(HelloError::UnofferedCipherSuite(0xc02b))
+        );
+
+        // Without the signalling suite renegotiation_info must be solicited.
+        let mut client = offer(FULL_SESSION_ID, vec![]);
+        client.cipher_suites = vec![0xc02b];
+        let err = check_server_hello(&client, &full).unwrap_err();
+        let renegotiation_info = ExtensionType::RenegotiationInfo.to_u16();
+        assert_eq!(err, HelloError::UnsolicitedExtension(renegotiation_info));
+        assert_eq!(err.alert(), AlertDescription::UnsupportedExtension);
+
+        let mut server = full.clone();
+        server.compression_method = 1;
+        let client = offer(FULL_SESSION_ID, vec![]);
+        assert_eq!(check_server_hello(&client, &server), Err(HelloError::UnofferedCompression(1)));
+
+        // Every cut is malformed, but the one that drops all the extensions.
+        let bytes = encode_server_hello(&full).unwrap();
+        for len in 0..bytes.len() {
+            assert_eq!(decode_server_hello(&bytes[..len]).is_ok(), len == 38);
+        }
+    }
+}
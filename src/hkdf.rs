@@ -0,0 +1,43 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+use crate::hmac::hmac;
+
+// HKDF (RFC 5869) over HMAC with the hash H.
+
+// PRK = HMAC-Hash(salt, IKM), where no salt is HashLen zeros.
+pub fn extract<H: HashSpec>(salt: Bytes, ikm: Bytes) -> Bytes {
+    let salt = if salt.len() == 0 { Bytes::new_len(H::HASH_LEN) } else { salt };
+    hmac::<H>(salt, ikm)
+}
+
+// T(0) = empty string, T(i) = HMAC-Hash(PRK, T(i-1) | info | i), and the
+// output is the first len bytes of T(1) | T(2) | ... At most 255 blocks.
+pub fn expand<H: HashSpec>(prk: Bytes, info: Bytes, len: usize) -> Result<Bytes, String> {
+    let n = len.div_ceil(H::HASH_LEN);
+    if n > 255 {
+        return Err("HKDF output too long".to_string());
+    }
+    let mut okm = Bytes::new_len(n * H::HASH_LEN);
+    let mut t = Bytes::new_len(0);
+    for i in 0..n {
+        let mut input = Bytes::new_len(t.len() + info.len() + 1);
+        input.update(0, &t);
+        input.update(t.len(), &info);
+        input[t.len() + info.len()] = (i + 1) as u8;
+        t = hmac::<H>(prk.clone(), input);
+        okm.update(i * H::HASH_LEN, &t);
+    }
+    Ok(Bytes::from(&okm[0..len]))
+}
+
+pub fn hkdf<H: HashSpec>(
+    salt: Bytes,
+    ikm: Bytes,
+    info: Bytes,
+    len: usize,
+) -> Result<Bytes, String> {
+    expand::<H>(extract::<H>(salt, ikm), info, len)
+}
@@ -0,0 +1,34 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+
+// HMAC (RFC 2104) over the hash H.
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+// Keys longer than a block are hashed first, then zero-padded to a block.
+fn block_key<H: HashSpec>(key: Bytes) -> Bytes {
+    let key = if key.len() > H::BLOCK_LEN { H::hash(key) } else { key };
+    let mut k = Bytes::new_len(H::BLOCK_LEN);
+    k.update(0, &key);
+    k
+}
+
+fn xor_pad(k: &Bytes, pad: u8, data: &Bytes) -> Bytes {
+    let mut out = Bytes::new_len(k.len() + data.len());
+    for i in 0..k.len() {
+        out[i] = k[i] ^ pad;
+    }
+    out.update(k.len(), data);
+    out
+}
+
+// HMAC(K, m) = H((K' ^ opad) || H((K' ^ ipad) || m))
+pub fn hmac<H: HashSpec>(key: Bytes, data: Bytes) -> Bytes {
+    let k = block_key::<H>(key);
+    let inner = H::hash(xor_pad(&k, IPAD, &data));
+    H::hash(xor_pad(&k, OPAD, &inner))
+}
@@ -10,12 +10,61 @@ extern crate hacspec;
 hacspec::hacspec_crates!();
 
 pub mod aes;
+pub mod aescbc;
 pub mod aesgcm;
+pub mod alert;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
 pub mod blake2b;
+pub mod certificate;
 pub mod chacha20;
 pub mod chacha20poly1305;
+pub mod ciphersuites;
+pub mod codec;
 pub mod curve25519;
+pub mod der;
+pub mod dtls12;
+pub mod dtls13;
+pub mod dtls_record;
+pub mod ecdsa;
+pub mod ed25519;
+pub mod exporter;
+pub mod extension_negotiation;
+pub mod extensions;
+pub mod ffdhe;
 pub mod gf128;
+pub mod ghash;
+pub mod graph;
+pub mod grease;
+pub mod groups;
+pub mod handshake;
+pub mod hash;
+pub mod heartbeat;
+pub mod hello;
+pub mod hkdf;
+pub mod hmac;
+pub mod md5;
+pub mod montgomery;
 pub mod p256;
+pub mod p384;
 pub mod poly1305;
+pub mod prf;
+pub mod properties;
+pub mod record;
+pub mod rsa;
+pub mod rsapss;
+pub mod sha1;
+pub mod sha256;
+pub mod sha512;
+pub mod signature_schemes;
+pub mod srtp;
+pub mod supplemental_data;
+pub mod ticket;
 pub mod tls12;
+pub mod tls13;
+pub mod tls13_keysched;
+pub mod tls13_record;
+pub mod version_negotiation;
+pub mod weierstrass;
+pub mod x509;
+pub mod x448;
@@ -0,0 +1,119 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+
+// MD5 as in RFC 1321. Broken; here for the PRF of TLS 1.0 and 1.1.
+
+const BLOCKSIZE: usize = 64;
+type Hash = [u32; 4];
+bytes!(Block, BLOCKSIZE);
+bytes!(Digest, 16);
+
+// T[i] = floor(2^32 * abs(sin(i + 1)))
+static T: [u32; 64] = [
+    0xd76a_a478, 0xe8c7_b756, 0x2420_70db, 0xc1bd_ceee, 0xf57c_0faf, 0x4787_c62a,
+    0xa830_4613, 0xfd46_9501, 0x6980_98d8, 0x8b44_f7af, 0xffff_5bb1, 0x895c_d7be,
+    0x6b90_1122, 0xfd98_7193, 0xa679_438e, 0x49b4_0821, 0xf61e_2562, 0xc040_b340,
+    0x265e_5a51, 0xe9b6_c7aa, 0xd62f_105d, 0x0244_1453, 0xd8a1_e681, 0xe7d3_fbc8,
+    0x21e1_cde6, 0xc337_07d6, 0xf4d5_0d87, 0x455a_14ed, 0xa9e3_e905, 0xfcef_a3f8,
+    0x676f_02d9, 0x8d2a_4c8a, 0xfffa_3942, 0x8771_f681, 0x6d9d_6122, 0xfde5_380c,
+    0xa4be_ea44, 0x4bde_cfa9, 0xf6bb_4b60, 0xbebf_bc70, 0x289b_7ec6, 0xeaa1_27fa,
+    0xd4ef_3085, 0x0488_1d05, 0xd9d4_d039, 0xe6db_99e5, 0x1fa2_7cf8, 0xc4ac_5665,
+    0xf429_2244, 0x432a_ff97, 0xab94_23a7, 0xfc93_a039, 0x655b_59c3, 0x8f0c_cc92,
+    0xffef_f47d, 0x8584_5dd1, 0x6fa8_7e4f, 0xfe2c_e6e0, 0xa301_4314, 0x4e08_11a1,
+    0xf753_7e82, 0xbd3a_f235, 0x2ad7_d2bb, 0xeb86_d391,
+];
+
+// The rotation of each step.
+static S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+static H0: Hash = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+
+fn words(block: Block) -> [u32; 16] {
+    let mut x = [0u32; 16];
+    for i in 0..16 {
+        x[i] = u32::from(block[4 * i])
+            | u32::from(block[4 * i + 1]) << 8
+            | u32::from(block[4 * i + 2]) << 16
+            | u32::from(block[4 * i + 3]) << 24;
+    }
+    x
+}
+
+// The function of step i's round and the message word step i takes.
+fn round_function(i: usize, b: u32, c: u32, d: u32) -> (u32, usize) {
+    match i / 16 {
+        0 => ((b & c) | (!b & d), i),
+        1 => ((b & d) | (c & !d), (5 * i + 1) % 16),
+        2 => (b ^ c ^ d, (3 * i + 5) % 16),
+        _ => (c ^ (b | !d), (7 * i) % 16),
+    }
+}
+
+#[wrappit]
+fn compress(h: Hash, block: Block) -> Hash {
+    let x = words(block);
+    let mut v = h;
+    for i in 0..64 {
+        let (f, k) = round_function(i, v[1], v[2], v[3]);
+        let rotated = (v[0] + f + x[k] + T[i]).rotate_left(S[i]);
+        v[0] = v[3];
+        v[3] = v[2];
+        v[2] = v[1];
+        v[1] = v[1] + rotated;
+    }
+
+    let mut compressed: Hash = [0; 4];
+    for i in 0..4 {
+        compressed[i] = h[i] + v[i];
+    }
+    compressed
+}
+
+// Append 0x80, zeros and the message length in bits as a little-endian u64,
+// up to a multiple of the block size.
+fn pad(msg: &Bytes) -> Bytes {
+    let l = msg.len();
+    let zeros = BLOCKSIZE - (l + 9) % BLOCKSIZE;
+    let zeros = if zeros == BLOCKSIZE { 0 } else { zeros };
+    let mut padded = Bytes::new_len(l + 9 + zeros);
+    padded.update(0, msg);
+    padded[l] = 0x80;
+    padded.update_raw(l + 1 + zeros, &(l as u64 * 8).to_le_bytes());
+    padded
+}
+
+pub fn md5(msg: Bytes) -> Digest {
+    let padded = pad(&msg);
+    let mut h = H0;
+    for block in padded.split(BLOCKSIZE) {
+        h = compress(h, Block::from(&block[..]));
+    }
+
+    let mut digest = Digest::new();
+    for i in 0..4 {
+        let bytes = h[i].to_le_bytes();
+        for j in 0..4 {
+            digest[4 * i + j] = bytes[j];
+        }
+    }
+    digest
+}
+
+pub struct Md5;
+
+impl HashSpec for Md5 {
+    const BLOCK_LEN: usize = BLOCKSIZE;
+    const HASH_LEN: usize = 16;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&md5(msg)[..])
+    }
+}
@@ -0,0 +1,49 @@
+// The Montgomery ladder of RFC 7748, section 5, shared by X25519 and X448.
+//
+// A curve v^2 = u^3 + A*u^2 + u is given by its field element type, the type
+// and bit length of its (clamped) scalars, and a24 = (A - 2) / 4. Points are
+// projective (X : Z) with u = X / Z; the ladder keeps (k'P, (k' + 1)P) over
+// the bits k' of k read from the top.
+macro_rules! montgomery_ladder {
+    ($field:ident, $scalar:ident, $bits:expr, $a24:expr) => {
+        type Point = ($field, $field);
+
+        fn point_add_and_double(q: Point, nq: Point, nqp1: Point) -> (Point, Point) {
+            let (x_1, _z_1) = q;
+            let (x_2, z_2) = nq;
+            let (x_3, z_3) = nqp1;
+            let a = x_2 + z_2;
+            let aa = a.pow(2);
+            let b = x_2 - z_2;
+            let bb = b * b;
+            let e = aa - bb;
+            let c = x_3 + z_3;
+            let d = x_3 - z_3;
+            let da = d * a;
+            let cb = c * b;
+
+            let x_3 = (da + cb).pow(2);
+            let z_3 = x_1 * ((da - cb).pow(2));
+            let x_2 = aa * bb;
+            let a24: $field = $a24.into();
+            let z_2 = e * (aa + (a24 * e));
+            ((x_2, z_2), (x_3, z_3))
+        }
+
+        fn montgomery_ladder(k: $scalar, init: Point) -> Point {
+            let mut acc: (Point, Point) = ((1.into(), 0.into()), init);
+            for i in 0..$bits {
+                if k.bit($bits - 1 - i) == 1 {
+                    // TODO: this is ugly
+                    let tmp = point_add_and_double(init, acc.1, acc.0);
+                    acc = (tmp.1, tmp.0);
+                } else {
+                    acc = point_add_and_double(init, acc.0, acc.1);
+                }
+            }
+            acc.0
+        }
+    };
+}
+
+pub(crate) use montgomery_ladder;
@@ -0,0 +1,39 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::ecdsa::ecdsa;
+use crate::weierstrass::weierstrass_curve;
+
+// Define field P384 (prime: 2**384 - 2**128 - 2**96 + 2**32 - 1)
+#[field(fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffff0000000000000000ffffffff)]
+struct FieldElement;
+
+// Define the field mod the group order n, for ECDSA
+#[field(ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77aecec196accc52973)]
+struct OrderElement;
+
+// Scalars are 384-bit integers
+#[bits(384)]
+struct Scalar;
+
+weierstrass_curve!(
+    FieldElement,
+    Scalar,
+    384,
+    48,
+    concat!(
+        "AA87CA22BE8B05378EB1C71EF320AD746E1D3B628BA79B98",
+        "59F741E082542A385502F25DBF55296C3A545E3872760AB7"
+    ),
+    concat!(
+        "3617DE4A96262C6F5D9E98BF9292DC29F8F41DBD289A147C",
+        "E9DA3113B5F0B8C00A60B1CE1D7E819D7A431D7C90EA0E5F"
+    ),
+    concat!(
+        "B3312FA7E23EE7E4988E056BE3F82D19181D9C6EFE814112",
+        "0314088F5013875AC656398D8A2ED19D2A85C8EDD3EC2AEF"
+    )
+);
+
+ecdsa!(OrderElement, Scalar);
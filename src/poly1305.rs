@@ -19,15 +19,28 @@ bytes!(Tag, BLOCKSIZE);
 #[field(3fffffffffffffffffffffffffffffffb)]
 struct FieldElement;
 
-fn key_gen(key: Key, iv: IV) -> Key {
+// The one-time key is the first half of the ChaCha20 block with counter 0
+// (RFC 8439, section 2.6).
+pub fn key_gen(key: Key, iv: IV) -> Key {
     let block = chacha20::block(key, 0, iv);
     Key::from_array(block.get(0..32))
 }
 
+// Clamping clears the top four bits of r[3], r[7], r[11] and r[15], and the
+// bottom two bits of r[4], r[8] and r[12] (RFC 8439, section 2.5).
+pub fn clamp(r: Block) -> Block {
+    let mut clamped = r;
+    for &i in [3, 7, 11, 15].iter() {
+        clamped[i] &= 0x0f;
+    }
+    for &i in [4, 8, 12].iter() {
+        clamped[i] &= 0xfc;
+    }
+    clamped
+}
+
 fn encode_r(r: Block) -> FieldElement {
-    let r_uint = u128::from_le_bytes(r);
-    let r_uint = r_uint & 0x0fff_fffc_0fff_fffc_0fff_fffc_0fff_ffff;
-    FieldElement::from(r_uint)
+    FieldElement::from(u128::from_le_bytes(clamp(r)))
 }
 
 fn encode(block: Bytes) -> FieldElement {
@@ -45,14 +58,22 @@ fn poly_inner(m: Bytes, r: FieldElement) -> FieldElement {
     acc
 }
 
+// The low 128 bits of a field element; its bytes may be fewer than 16.
+fn low_u128(a: FieldElement) -> u128 {
+    let a_bytes = a.to_bytes_le();
+    let mut low = [0u8; BLOCKSIZE];
+    let n = a_bytes.len().min(BLOCKSIZE);
+    low[..n].copy_from_slice(&a_bytes[..n]);
+    u128::from_le_bytes(low)
+}
+
+// The tag is (a + s) mod 2^128. This sum is of integers: adding s in the
+// field would reduce it mod 2^130 - 5 first whenever a + s is above that.
 pub fn poly(m: Bytes, key: Key) -> Tag {
-    let s_elem = FieldElement::from(u128::from_le_bytes(key.get(BLOCKSIZE..2 * BLOCKSIZE)));
+    let s = u128::from_le_bytes(key.get(BLOCKSIZE..2 * BLOCKSIZE));
     let r_elem = encode_r(key.get(0..BLOCKSIZE));
     let a = poly_inner(m, r_elem);
-    let n = a + s_elem;
-    // Note that n might be less than 16 byte -> zero-pad; but might also be
-    // larger than Tag::capacity().
-    Tag::from_vec_lazy(n.to_bytes_le())
+    Tag::from_array(low_u128(a).wrapping_add(s).to_le_bytes())
 }
 
 pub fn poly_mac(m: Bytes, key: Key, iv: IV) -> Tag {
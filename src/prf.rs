@@ -0,0 +1,81 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+use crate::hmac::hmac;
+use crate::md5::Md5;
+use crate::sha1::Sha1;
+
+// The TLS 1.2 PRF (RFC 5246, section 5), over HMAC with the hash H: SHA-256,
+// or SHA-384 for the suites that say so.
+
+fn concat(a: &Bytes, b: &Bytes) -> Bytes {
+    let mut out = Bytes::new_len(a.len() + b.len());
+    out.update(0, a);
+    out.update(a.len(), b);
+    out
+}
+
+// P_hash(secret, seed) = HMAC_hash(secret, A(1) + seed) +
+//                        HMAC_hash(secret, A(2) + seed) + ...
+// with A(0) = seed and A(i) = HMAC_hash(secret, A(i-1)), cut to len bytes.
+pub fn p_hash<H: HashSpec>(secret: Bytes, seed: Bytes, len: usize) -> Bytes {
+    let mut out = Bytes::new_len(len);
+    let mut a = seed.clone();
+    let mut written = 0;
+    while written < len {
+        a = hmac::<H>(secret.clone(), a);
+        let block = hmac::<H>(secret.clone(), concat(&a, &seed));
+        let n = if block.len() < len - written { block.len() } else { len - written };
+        out.update_raw(written, &block[0..n]);
+        written += n;
+    }
+    out
+}
+
+// PRF(secret, label, seed) = P_<hash>(secret, label + seed)
+pub fn prf<H: HashSpec>(secret: Bytes, label: Bytes, seed: Bytes, len: usize) -> Bytes {
+    p_hash::<H>(secret, concat(&label, &seed), len)
+}
+
+// The PRF of TLS 1.0 and 1.1 (RFC 2246, section 5):
+//   PRF(secret, label, seed) = P_MD5(S1, label + seed) XOR
+//                              P_SHA-1(S2, label + seed)
+// where S1 and S2 are the first and last halves of the secret, sharing the
+// middle byte when its length is odd.
+pub fn prf_legacy(secret: Bytes, label: Bytes, seed: Bytes, len: usize) -> Bytes {
+    let half = secret.len().div_ceil(2);
+    let s1 = Bytes::from(&secret[0..half]);
+    let s2 = Bytes::from(&secret[secret.len() - half..secret.len()]);
+    let label_seed = concat(&label, &seed);
+    let md5 = p_hash::<Md5>(s1, label_seed.clone(), len);
+    let sha1 = p_hash::<Sha1>(s2, label_seed, len);
+    let mut out = Bytes::new_len(len);
+    for i in 0..len {
+        out[i] = md5[i] ^ sha1[i];
+    }
+    out
+}
+
+// The master secret of a full handshake (RFC 5246, section 8.1):
+//   master_secret = PRF(pre_master_secret, "master secret",
+//                       ClientHello.random + ServerHello.random)[0..47]
+pub fn master_secret<H: HashSpec>(
+    pre_master_secret: Bytes,
+    client_random: Bytes,
+    server_random: Bytes,
+) -> Bytes {
+    let label = Bytes::from_vec(b"master secret".to_vec());
+    prf::<H>(pre_master_secret, label, concat(&client_random, &server_random), 48)
+}
+
+// With the extended_master_secret extension (RFC 7627, section 4), the
+// randoms give way to the session hash, the hash of every handshake message
+// up to and including the ClientKeyExchange:
+//   master_secret = PRF(pre_master_secret, "extended master secret",
+//                       session_hash)[0..47]
+pub fn extended_master_secret<H: HashSpec>(pre_master_secret: Bytes, session_hash: Bytes) -> Bytes {
+    let label = Bytes::from_vec(b"extended master secret".to_vec());
+    prf::<H>(pre_master_secret, label, session_hash, 48)
+}
@@ -0,0 +1,154 @@
+//! Properties of TLS 1.2 handshakes, checked against the handshake of every
+//! consistent combination of options.
+
+use crate::tls12::{MessageType, Peer, ProtocolOptions, State, Trace};
+
+/// Whether a handshake has a property.
+pub type Predicate = fn(&Trace) -> bool;
+
+/// A handshake on which a property doesn't hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub property: &'static str,
+    pub opts: ProtocolOptions,
+    pub trace: Trace,
+}
+
+/// A set of named predicates over handshakes.
+///
+/// ```
+/// use hacspecs::properties::{self, Properties};
+///
+/// let failures = Properties::new()
+///     .register(
+///         "client CertificateVerify",
+///         properties::certificate_verify_follows_client_certificate,
+///     )
+///     .check();
+/// assert!(failures.is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Properties(Vec<(&'static str, Predicate)>);
+
+impl Properties {
+    pub fn new() -> Properties {
+        Properties::default()
+    }
+
+    pub fn register(mut self, name: &'static str, holds: Predicate) -> Self {
+        self.0.push((name, holds));
+        self
+    }
+
+    /// Check every property against every consistent combination of options.
+    pub fn check(&self) -> Vec<Counterexample> {
+        self.check_with(ProtocolOptions::all_valid())
+    }
+
+    /// Check every property against the handshakes of `options`. A property
+    /// that fails yields one counterexample, the first handshake it fails
+    /// on.
+    pub fn check_with<I>(&self, options: I) -> Vec<Counterexample>
+    where
+        I: IntoIterator<Item = ProtocolOptions>,
+    {
+        let mut failures: Vec<Counterexample> = Vec::new();
+        for opts in options {
+            let trace = Trace::linearise(opts);
+            for (property, holds) in &self.0 {
+                if !failures.iter().any(|c| c.property == *property) && !holds(&trace) {
+                    failures.push(Counterexample { property, opts, trace: trace.clone() });
+                }
+            }
+        }
+        failures
+    }
+}
+
+/// Every Finished comes right after its sender's ChangeCipherSpec.
+pub fn finished_follows_change_cipher_spec(trace: &Trace) -> bool {
+    let steps = trace.steps();
+    steps.iter().enumerate().all(|(i, (_, peer, msg))| {
+        *msg != MessageType::Finished
+            || (i > 0 && steps[i - 1].1 == *peer && steps[i - 1].2 == MessageType::ChangeCipherSpec)
+    })
+}
+
+/// The client only sends a CertificateVerify after a non-empty Certificate,
+/// or a CertificateURL pointing to one.
+pub fn certificate_verify_follows_client_certificate(trace: &Trace) -> bool {
+    let steps = trace.steps();
+    steps.iter().enumerate().all(|(i, (st, ..))| {
+        *st != State::ClientSendsCertificateVerify
+            || steps[..i].iter().any(|(st, ..)| {
+                matches!(st, State::ClientSendsCertificate | State::ClientSendsCertificateUrl)
+            })
+    })
+}
+
+/// Each peer sends exactly one Finished.
+pub fn one_finished_each(trace: &Trace) -> bool {
+    [Peer::Client, Peer::Server].iter().all(|peer| {
+        let finished = trace
+            .steps()
+            .iter()
+            .filter(|(_, p, msg)| p == peer && *msg == MessageType::Finished)
+            .count();
+        finished == 1
+    })
+}
+
+/// The records aren't compressed. Compressed records leak how much of an
+/// attacker's chosen plaintext matches a secret beside it, such as a cookie,
+/// through their lengths (CRIME), so TLS 1.3 drops compression altogether
+/// (RFC 8446, 4.1.2).
+pub fn no_compression(trace: &Trace) -> bool {
+    !trace.compression()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hold() {
+        let failures = Properties::new()
+            .register("verify", certificate_verify_follows_client_certificate)
+            .register("finished", one_finished_each)
+            .check();
+        assert_eq!(failures, vec![]);
+    }
+
+    #[test]
+    fn counterexample() {
+        // NextProtocol goes between the client's ChangeCipherSpec and
+        // Finished.
+        let failures = Properties::new()
+            .register("ccs", finished_follows_change_cipher_spec)
+            .register("finished", one_finished_each)
+            .check();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].property, "ccs");
+        assert!(failures[0].opts.npn);
+        assert_eq!(failures[0].trace, Trace::linearise(failures[0].opts));
+        assert!(failures[0].trace.messages().contains(&MessageType::NextProtocol));
+
+        let without_npn = ProtocolOptions::all_valid().filter(|opts| !opts.npn);
+        let failures = Properties::new()
+            .register("ccs", finished_follows_change_cipher_spec)
+            .check_with(without_npn);
+        assert_eq!(failures, vec![]);
+    }
+
+    #[test]
+    fn compression() {
+        // The DEFLATE configurations are among those checked.
+        let checks = Properties::new().register("no compression", no_compression);
+        let failures = checks.check();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].property, "no compression");
+        assert!(failures[0].opts.compression && failures[0].trace.compression());
+        let null = ProtocolOptions::all_valid().filter(|opts| !opts.compression);
+        assert_eq!(checks.check_with(null), vec![]);
+    }
+}
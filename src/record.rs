@@ -0,0 +1,376 @@
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// The largest fragment of plaintext a record carries (RFC 5246, 6.2.1 and
+/// RFC 8446, 5.1).
+pub const MAX_FRAGMENT_LEN: usize = 1 << 14;
+
+/// The record content types of RFC 5246, 6.2.1, which TLS 1.3 keeps, and
+/// the heartbeat one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContentType {
+    ChangeCipherSpec,
+    Alert,
+    Handshake,
+    ApplicationData,
+    /// RFC 6520, once the heartbeat extension is negotiated.
+    Heartbeat,
+}
+
+impl ContentType {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            ContentType::ChangeCipherSpec => 20,
+            ContentType::Alert => 21,
+            ContentType::Handshake => 22,
+            ContentType::ApplicationData => 23,
+            ContentType::Heartbeat => 24,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<ContentType> {
+        match v {
+            20 => Some(ContentType::ChangeCipherSpec),
+            21 => Some(ContentType::Alert),
+            22 => Some(ContentType::Handshake),
+            23 => Some(ContentType::ApplicationData),
+            24 => Some(ContentType::Heartbeat),
+            _ => None,
+        }
+    }
+}
+
+/// The bound on a record's length, which depends on what its fragment holds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Limit {
+    /// TLSPlaintext: 2^14 bytes.
+    Plaintext,
+    /// A TLS 1.2 TLSCiphertext: 2^14 + 2048 bytes, room for compression,
+    /// a MAC and padding (RFC 5246, 6.2.3).
+    Tls12Ciphertext,
+    /// A TLS 1.3 TLSCiphertext: 2^14 + 256 bytes, room for the content type,
+    /// padding and the AEAD expansion (RFC 8446, 5.2).
+    Tls13Ciphertext,
+}
+
+impl Limit {
+    pub fn max_len(self) -> usize {
+        match self {
+            Limit::Plaintext => MAX_FRAGMENT_LEN,
+            Limit::Tls12Ciphertext => MAX_FRAGMENT_LEN + 2048,
+            Limit::Tls13Ciphertext => MAX_FRAGMENT_LEN + 256,
+        }
+    }
+}
+
+/// A TLSPlaintext or TLSCiphertext record. Both are framed as
+///
+/// ```text
+/// struct {
+///     ContentType type;
+///     ProtocolVersion version;
+///     uint16 length;
+///     opaque fragment[TLSPlaintext.length];
+/// } TLSPlaintext;
+/// ```
+///
+/// TLS 1.3 calls the version legacy_record_version and ignores it on
+/// receipt; it is 0x0303, or 0x0301 for an initial ClientHello.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub content_type: ContentType,
+    pub version: u16,
+    pub fragment: Vec<u8>,
+}
+
+pub const HEADER_LEN: usize = 5;
+
+/// Why a record couldn't be framed or deframed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RecordError {
+    /// The input ends before the record does; more bytes are needed.
+    Truncated,
+    /// The content type isn't one of `ContentType`.
+    UnknownContentType(u8),
+    /// The fragment is longer than the limit allows.
+    Overflow(usize),
+    /// Handshake, alert and ChangeCipherSpec records must not be empty
+    /// (RFC 5246, 6.2.1 and RFC 8446, 5.1).
+    EmptyFragment(ContentType),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Truncated => write!(f, "truncated record"),
+            RecordError::UnknownContentType(t) => write!(f, "unknown content type {}", t),
+            RecordError::Overflow(len) => write!(f, "record of {} bytes is too long", len),
+            RecordError::EmptyFragment(t) => write!(f, "empty {:?} record", t),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl RecordError {
+    /// The alert a receiver answers with, if any. A truncated record may
+    /// just not have arrived in full yet.
+    pub fn alert(self) -> Option<AlertDescription> {
+        match self {
+            RecordError::Truncated => None,
+            RecordError::UnknownContentType(_) => Some(AlertDescription::UnexpectedMessage),
+            RecordError::Overflow(_) => Some(AlertDescription::RecordOverflow),
+            RecordError::EmptyFragment(_) => Some(AlertDescription::UnexpectedMessage),
+        }
+    }
+}
+
+fn check_fragment(content_type: ContentType, len: usize, limit: Limit) -> Result<(), RecordError> {
+    if len > limit.max_len() {
+        return Err(RecordError::Overflow(len));
+    }
+    if len == 0 && content_type != ContentType::ApplicationData {
+        return Err(RecordError::EmptyFragment(content_type));
+    }
+    Ok(())
+}
+
+pub fn encode_record(record: &Record, limit: Limit) -> Result<Vec<u8>, RecordError> {
+    check_fragment(record.content_type, record.fragment.len(), limit)?;
+    let mut out = Vec::with_capacity(HEADER_LEN + record.fragment.len());
+    out.push(record.content_type.to_u8());
+    out.extend_from_slice(&record.version.to_be_bytes());
+    out.extend_from_slice(&(record.fragment.len() as u16).to_be_bytes());
+    out.extend_from_slice(&record.fragment);
+    Ok(out)
+}
+
+/// Decodes the record at the start of `input`, and returns it with the
+/// number of bytes it took up. The length is checked before the fragment
+/// has arrived, so an overlong record is refused at once.
+pub fn decode_record(input: &[u8], limit: Limit) -> Result<(Record, usize), RecordError> {
+    if input.len() < HEADER_LEN {
+        return Err(RecordError::Truncated);
+    }
+    let content_type =
+        ContentType::from_u8(input[0]).ok_or(RecordError::UnknownContentType(input[0]))?;
+    let version = u16::from_be_bytes([input[1], input[2]]);
+    let len = u16::from_be_bytes([input[3], input[4]]) as usize;
+    check_fragment(content_type, len, limit)?;
+    if input.len() < HEADER_LEN + len {
+        return Err(RecordError::Truncated);
+    }
+    let fragment = input[HEADER_LEN..HEADER_LEN + len].to_vec();
+    Ok((Record { content_type, version, fragment }, HEADER_LEN + len))
+}
+
+/// Decodes all the complete records at the start of `input`, and returns
+/// them with the number of bytes they took up; the rest is a partial record.
+pub fn decode_records(input: &[u8], limit: Limit) -> Result<(Vec<Record>, usize), RecordError> {
+    let mut records = Vec::new();
+    let mut read = 0;
+    loop {
+        match decode_record(&input[read..], limit) {
+            Ok((record, len)) => {
+                records.push(record);
+                read += len;
+            }
+            Err(RecordError::Truncated) => return Ok((records, read)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Splits `data` of one content type into records of at most `max_len`
+/// bytes, which is 2^14 or less when max_fragment_length is negotiated
+/// (RFC 6066, 4). Empty data becomes a single empty record if it is
+/// application data, and no record otherwise.
+///
+/// Panics if `max_len` is 0, as no record could hold any data.
+pub fn fragment(
+    content_type: ContentType,
+    version: u16,
+    data: &[u8],
+    max_len: usize,
+) -> Vec<Record> {
+    assert!(max_len > 0, "records of at most 0 bytes");
+    if data.is_empty() {
+        return if content_type == ContentType::ApplicationData {
+            vec![Record { content_type, version, fragment: Vec::new() }]
+        } else {
+            Vec::new()
+        };
+    }
+    data.chunks(max_len)
+        .map(|chunk| Record { content_type, version, fragment: chunk.to_vec() })
+        .collect()
+}
+
+/// Merges consecutive records of the same content type into as few records
+/// of at most `max_len` bytes as possible, as a sender may (RFC 5246,
+/// 6.2.1). Alerts are never merged: TLS 1.3 forbids coalescing them
+/// (RFC 8446, 5.1).
+pub fn coalesce(records: &[Record], max_len: usize) -> Vec<Record> {
+    let mut out: Vec<Record> = Vec::new();
+    for record in records {
+        if let Some(last) = out.last_mut() {
+            if last.content_type == record.content_type
+                && last.version == record.version
+                && record.content_type != ContentType::Alert
+                && last.fragment.len() + record.fragment.len() <= max_len
+            {
+                last.fragment.extend_from_slice(&record.fragment);
+                continue;
+            }
+        }
+        out.push(record.clone());
+    }
+    out
+}
+
+/// What a receiver sees of a run of records: the concatenated fragments of
+/// each run of one content type. Record boundaries carry no meaning within
+/// a run, so this is the same for every fragmentation and coalescing of the
+/// same data.
+pub fn defragment(records: &[Record]) -> Vec<(ContentType, Vec<u8>)> {
+    let mut out: Vec<(ContentType, Vec<u8>)> = Vec::new();
+    for record in records {
+        match out.last_mut() {
+            Some((content_type, data)) if *content_type == record.content_type => {
+                data.extend_from_slice(&record.fragment)
+            }
+            _ => out.push((record.content_type, record.fragment.clone())),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(content_type: ContentType, fragment: &[u8]) -> Record {
+        Record { content_type, version: 0x0303, fragment: fragment.to_vec() }
+    }
+
+    #[test]
+    fn content_type_codec() {
+        for v in 0..=255 {
+            if let Some(t) = ContentType::from_u8(v) {
+                assert_eq!(v, t.to_u8());
+            } else {
+                assert!(!(20..=24).contains(&v));
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let r = record(ContentType::Handshake, &[0x0e, 0, 0, 0]);
+        let bytes = encode_record(&r, Limit::Plaintext).unwrap();
+        assert_eq!(bytes, vec![22, 3, 3, 0, 4, 0x0e, 0, 0, 0]);
+        assert_eq!(decode_record(&bytes, Limit::Plaintext), Ok((r.clone(), 9)));
+
+        // Trailing bytes are left for the next record.
+        let mut two = bytes.clone();
+        two.extend_from_slice(&bytes);
+        two.extend_from_slice(&[23, 3, 3]);
+        assert_eq!(decode_records(&two, Limit::Plaintext), Ok((vec![r.clone(), r], 18)));
+    }
+
+    #[test]
+    fn decode_errors() {
+        for len in 0..9 {
+            let bytes = [22, 3, 3, 0, 4, 0x0e, 0, 0, 0];
+            assert_eq!(decode_record(&bytes[..len], Limit::Plaintext), Err(RecordError::Truncated));
+        }
+        assert_eq!(
+            decode_record(&[25, 3, 3, 0, 1, 0], Limit::Plaintext),
+            Err(RecordError::UnknownContentType(25))
+        );
+        assert_eq!(
+            decode_record(&[21, 3, 3, 0, 0], Limit::Plaintext),
+            Err(RecordError::EmptyFragment(ContentType::Alert))
+        );
+        assert!(decode_record(&[23, 3, 3, 0, 0], Limit::Plaintext).is_ok());
+
+        // An overlong record is refused from its header alone.
+        let header = [23, 3, 3, 0x40, 0x01];
+        assert_eq!(decode_record(&header, Limit::Plaintext), Err(RecordError::Overflow(0x4001)));
+        assert_eq!(decode_record(&header, Limit::Tls13Ciphertext), Err(RecordError::Truncated));
+        assert_eq!(RecordError::Overflow(0x4001).alert(), Some(AlertDescription::RecordOverflow));
+    }
+
+    #[test]
+    fn limits() {
+        for &(limit, max) in &[
+            (Limit::Plaintext, 16384),
+            (Limit::Tls12Ciphertext, 18432),
+            (Limit::Tls13Ciphertext, 16640),
+        ] {
+            let r = record(ContentType::ApplicationData, &vec![0; max]);
+            assert!(encode_record(&r, limit).is_ok());
+            let r = record(ContentType::ApplicationData, &vec![0; max + 1]);
+            assert_eq!(encode_record(&r, limit), Err(RecordError::Overflow(max + 1)));
+        }
+    }
+
+    #[test]
+    fn fragment_and_coalesce() {
+        let data = (0..40000).map(|i| i as u8).collect::<Vec<_>>();
+        let records = fragment(ContentType::Handshake, 0x0303, &data, MAX_FRAGMENT_LEN);
+        assert_eq!(
+            records.iter().map(|r| r.fragment.len()).collect::<Vec<_>>(),
+            vec![16384, 16384, 7232]
+        );
+        assert_eq!(defragment(&records), vec![(ContentType::Handshake, data.clone())]);
+
+        // However the data was split, coalescing and deframing give it back.
+        let small = fragment(ContentType::Handshake, 0x0303, &data, 512);
+        let merged = coalesce(&small, MAX_FRAGMENT_LEN);
+        assert_eq!(merged, records);
+        assert_eq!(defragment(&small), defragment(&merged));
+
+        assert!(fragment(ContentType::Handshake, 0x0303, &[], 512).is_empty());
+        assert_eq!(fragment(ContentType::ApplicationData, 0x0303, &[], 512).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "records of at most 0 bytes")]
+    fn fragment_zero_max_len() {
+        fragment(ContentType::ApplicationData, 0x0303, &[], 0);
+    }
+
+    #[test]
+    fn coalesce_keeps_types_apart() {
+        let records = vec![
+            record(ContentType::Handshake, &[1]),
+            record(ContentType::Handshake, &[2]),
+            record(ContentType::ChangeCipherSpec, &[1]),
+            record(ContentType::Alert, &[1, 0]),
+            record(ContentType::Alert, &[2, 10]),
+            record(ContentType::Handshake, &[3]),
+        ];
+        let merged = coalesce(&records, MAX_FRAGMENT_LEN);
+        assert_eq!(
+            merged,
+            vec![
+                record(ContentType::Handshake, &[1, 2]),
+                record(ContentType::ChangeCipherSpec, &[1]),
+                record(ContentType::Alert, &[1, 0]),
+                record(ContentType::Alert, &[2, 10]),
+                record(ContentType::Handshake, &[3]),
+            ]
+        );
+        assert_eq!(
+            defragment(&records),
+            vec![
+                (ContentType::Handshake, vec![1, 2]),
+                (ContentType::ChangeCipherSpec, vec![1]),
+                (ContentType::Alert, vec![1, 0, 2, 10]),
+                (ContentType::Handshake, vec![3]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use num::BigUint;
+
+use crate::hash::HashSpec;
+use crate::md5::Md5;
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+use crate::sha512::{Sha384, Sha512};
+
+// The RSA primitives of PKCS #1 v2.2 (RFC 8017), the RSAES-PKCS1-v1_5
+// encryption of the TLS 1.2 RSA key exchange and the RSASSA-PKCS1-v1_5
+// signatures of ServerKeyExchange and CertificateVerify. Integers are
+// big-endian octet strings, as on the wire.
+
+// A public key (n, e) or a private key (n, d).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublicKey {
+    pub n: Bytes,
+    pub e: Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrivateKey {
+    pub n: Bytes,
+    pub d: Bytes,
+}
+
+pub(crate) fn os2ip(x: &Bytes) -> BigUint {
+    BigUint::from_bytes_be(&x[..])
+}
+
+// I2OSP(x, len) fails if x needs more than len bytes.
+pub(crate) fn i2osp(x: &BigUint, len: usize) -> Result<Bytes, String> {
+    let b = x.to_bytes_be();
+    let b = if b == [0] { vec![] } else { b };
+    if b.len() > len {
+        return Err("integer too large".to_string());
+    }
+    let mut out = Bytes::new_len(len);
+    out.update_raw(len - b.len(), &b);
+    Ok(out)
+}
+
+// The length of the modulus in bits, and k, its length in bytes.
+pub fn modulus_bits(n: &Bytes) -> usize {
+    os2ip(n).bits()
+}
+
+pub fn modulus_len(n: &Bytes) -> usize {
+    modulus_bits(n).div_ceil(8)
+}
+
+// x^exponent mod n on k-byte strings, for x < n.
+fn rsa_core(n: &Bytes, exponent: &Bytes, x: Bytes) -> Result<Bytes, String> {
+    let k = modulus_len(n);
+    let n = os2ip(n);
+    let x = os2ip(&x);
+    if x >= n {
+        return Err("representative out of range".to_string());
+    }
+    i2osp(&x.modpow(&os2ip(exponent), &n), k)
+}
+
+// RSAEP (RFC 8017, section 5.1.1): c = m^e mod n.
+pub fn rsaep(pk: &PublicKey, m: Bytes) -> Result<Bytes, String> {
+    rsa_core(&pk.n, &pk.e, m)
+}
+
+// RSADP (RFC 8017, section 5.1.2): m = c^d mod n.
+pub fn rsadp(sk: &PrivateKey, c: Bytes) -> Result<Bytes, String> {
+    rsa_core(&sk.n, &sk.d, c)
+}
+
+// RSASP1 and RSAVP1 (RFC 8017, sections 5.2.1 and 5.2.2) are RSADP and
+// RSAEP on signatures.
+pub fn rsasp1(sk: &PrivateKey, m: Bytes) -> Result<Bytes, String> {
+    rsadp(sk, m)
+}
+
+pub fn rsavp1(pk: &PublicKey, s: Bytes) -> Result<Bytes, String> {
+    rsaep(pk, s)
+}
+
+// RSAES-PKCS1-v1_5 (RFC 8017, section 7.2) encrypts the encoded message
+//   EM = 0x00 || 0x02 || PS || 0x00 || M
+// where PS is at least 8 random non-zero bytes filling EM to k bytes. The
+// caller draws PS.
+pub fn pkcs1_encrypt(pk: &PublicKey, padding: Bytes, msg: Bytes) -> Result<Bytes, String> {
+    let k = modulus_len(&pk.n);
+    if msg.len() + 11 > k {
+        return Err("message too long".to_string());
+    }
+    if padding.len() != k - msg.len() - 3 {
+        return Err("Invalid padding length".to_string());
+    }
+    if padding[..].contains(&0) {
+        return Err("Padding must be non-zero".to_string());
+    }
+    let mut em = Bytes::new_len(k);
+    em[1] = 0x02;
+    em.update(2, &padding);
+    em.update(3 + padding.len(), &msg);
+    rsaep(pk, em)
+}
+
+// All malformed encodings give the same error (RFC 8017, section 7.2.2).
+pub fn pkcs1_decrypt(sk: &PrivateKey, cipher_text: Bytes) -> Result<Bytes, String> {
+    let k = modulus_len(&sk.n);
+    if cipher_text.len() != k || k < 11 {
+        return Err("decryption error".to_string());
+    }
+    let em = rsadp(sk, cipher_text).map_err(|_| "decryption error".to_string())?;
+    if em[0] != 0x00 || em[1] != 0x02 {
+        return Err("decryption error".to_string());
+    }
+    let mut separator = None;
+    for i in 2..k {
+        if em[i] == 0 {
+            separator = Some(i);
+            break;
+        }
+    }
+    match separator {
+        Some(i) if i >= 10 => Ok(Bytes::from(&em[i + 1..k])),
+        _ => Err("decryption error".to_string()),
+    }
+}
+
+// The RSA-encrypted premaster secret of the TLS 1.2 ClientKeyExchange
+// (RFC 5246, section 7.4.7.1): client_version followed by 46 random bytes,
+// encrypted with the public key of the server's certificate and sent as an
+// opaque<0..2^16-1>.
+pub fn client_key_exchange(
+    pk: &PublicKey,
+    client_version: u16,
+    random: Bytes,
+    padding: Bytes,
+) -> Result<Bytes, String> {
+    if random.len() != 46 {
+        return Err("Invalid premaster secret random".to_string());
+    }
+    let mut pms = Bytes::new_len(48);
+    pms.update_raw(0, &client_version.to_be_bytes());
+    pms.update(2, &random);
+    let cipher_text = pkcs1_encrypt(pk, padding, pms)?;
+    let mut out = Bytes::new_len(2 + cipher_text.len());
+    out.update_raw(0, &(cipher_text.len() as u16).to_be_bytes());
+    out.update(2, &cipher_text);
+    Ok(out)
+}
+
+// The server's premaster secret, with the countermeasure of RFC 5246,
+// section 7.4.7.1, against Bleichenbacher's attack: if the padding is bad or
+// the plaintext is not 48 bytes long, the server continues with
+// client_version || R for 46 random bytes R it drew beforehand, and it always
+// replaces the version in the plaintext with the ClientHello.client_version.
+// A bad premaster secret then only shows as a bad Finished message. Only a
+// malformed encoding of the ClientKeyExchange is an error.
+pub fn server_premaster_secret(
+    sk: &PrivateKey,
+    client_version: u16,
+    random: Bytes,
+    client_key_exchange: Bytes,
+) -> Result<Bytes, String> {
+    if random.len() != 46 {
+        return Err("Invalid premaster secret random".to_string());
+    }
+    if client_key_exchange.len() < 2 {
+        return Err("Invalid ClientKeyExchange".to_string());
+    }
+    let len = u16::from_be_bytes([client_key_exchange[0], client_key_exchange[1]]) as usize;
+    if client_key_exchange.len() != 2 + len {
+        return Err("Invalid ClientKeyExchange".to_string());
+    }
+    let cipher_text = Bytes::from(&client_key_exchange[2..2 + len]);
+
+    let mut pms = Bytes::new_len(48);
+    pms.update_raw(0, &client_version.to_be_bytes());
+    match pkcs1_decrypt(sk, cipher_text) {
+        Ok(m) if m.len() == 48 => pms.update_raw(2, &m[2..48]),
+        _ => pms.update(2, &random),
+    }
+    Ok(pms)
+}
+
+// The hashes of RSASSA-PKCS1-v1_5 with the DER encoding of their DigestInfo
+// up to the digest (RFC 8017, section 9.2, note 1).
+pub trait Pkcs1Hash: HashSpec {
+    const DIGEST_INFO_PREFIX: &'static [u8];
+}
+
+impl Pkcs1Hash for Md5 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x20, 0x30, 0x0c, 0x06, 0x08, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x05, 0x05,
+        0x00, 0x04, 0x10,
+    ];
+}
+
+impl Pkcs1Hash for Sha1 {
+    const DIGEST_INFO_PREFIX: &'static [u8] =
+        &[0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14];
+}
+
+impl Pkcs1Hash for Sha256 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+        0x05, 0x00, 0x04, 0x20,
+    ];
+}
+
+impl Pkcs1Hash for Sha384 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+        0x05, 0x00, 0x04, 0x30,
+    ];
+}
+
+impl Pkcs1Hash for Sha512 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+        0x05, 0x00, 0x04, 0x40,
+    ];
+}
+
+// EMSA-PKCS1-v1_5 (RFC 8017, section 9.2):
+//   EM = 0x00 || 0x01 || PS || 0x00 || T
+// where T is the DigestInfo of H(M) and PS at least 8 bytes 0xff filling EM
+// to em_len bytes.
+pub fn emsa_pkcs1_encode<H: Pkcs1Hash>(msg: Bytes, em_len: usize) -> Result<Bytes, String> {
+    let prefix = H::DIGEST_INFO_PREFIX;
+    let t_len = prefix.len() + H::HASH_LEN;
+    if em_len < t_len + 11 {
+        return Err("intended encoded message length too short".to_string());
+    }
+    let mut em = Bytes::new_len(em_len);
+    em[1] = 0x01;
+    for i in 2..em_len - t_len - 1 {
+        em[i] = 0xff;
+    }
+    em.update_raw(em_len - t_len, prefix);
+    em.update(em_len - H::HASH_LEN, &H::hash(msg));
+    Ok(em)
+}
+
+// RSASSA-PKCS1-v1_5 signatures (RFC 8017, section 8.2).
+pub fn pkcs1_sign<H: Pkcs1Hash>(sk: &PrivateKey, msg: Bytes) -> Result<Bytes, String> {
+    let em = emsa_pkcs1_encode::<H>(msg, modulus_len(&sk.n))?;
+    rsasp1(sk, em)
+}
+
+// Verification encodes the message again and compares the encodings, rather
+// than parsing the one in the signature, so that no malformed padding or
+// DigestInfo can pass.
+pub fn pkcs1_verify<H: Pkcs1Hash>(
+    pk: &PublicKey,
+    msg: Bytes,
+    signature: Bytes,
+) -> Result<(), String> {
+    let k = modulus_len(&pk.n);
+    if signature.len() != k {
+        return Err("invalid signature".to_string());
+    }
+    let em = rsavp1(pk, signature).map_err(|_| "invalid signature".to_string())?;
+    let expected = emsa_pkcs1_encode::<H>(msg, k)?;
+    if em == expected {
+        Ok(())
+    } else {
+        Err("invalid signature".to_string())
+    }
+}
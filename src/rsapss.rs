@@ -0,0 +1,135 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+use crate::rsa::{modulus_bits, modulus_len, rsasp1, rsavp1, PrivateKey, PublicKey};
+
+// RSASSA-PSS (RFC 8017, section 8.1) with MGF1 over the message hash H, the
+// RSA signatures of TLS 1.3 (RFC 8446, section 4.2.3), which take a salt as
+// long as the hash.
+
+// MGF1(seed, len) = H(seed || C) for the counters C = 0, 1, ... as u32, cut
+// to len bytes (RFC 8017, appendix B.2.1).
+pub fn mgf1<H: HashSpec>(seed: &Bytes, len: usize) -> Bytes {
+    let mut out = Bytes::new_len(len);
+    let mut input = Bytes::new_len(seed.len() + 4);
+    input.update(0, seed);
+    let mut written = 0;
+    let mut counter: u32 = 0;
+    while written < len {
+        input.update_raw(seed.len(), &counter.to_be_bytes());
+        let block = H::hash(input.clone());
+        let n = block.len().min(len - written);
+        out.update_raw(written, &block[0..n]);
+        written += n;
+        counter += 1;
+    }
+    out
+}
+
+// H' = H(0x00 * 8 || H(M) || salt)
+fn salted_hash<H: HashSpec>(msg: Bytes, salt: &Bytes) -> Bytes {
+    let m_hash = H::hash(msg);
+    let mut m_prime = Bytes::new_len(8 + m_hash.len() + salt.len());
+    m_prime.update(8, &m_hash);
+    m_prime.update(8 + m_hash.len(), salt);
+    H::hash(m_prime)
+}
+
+// EMSA-PSS-ENCODE (RFC 8017, section 9.1.1):
+//   EM = (DB ^ MGF1(H', em_len - h_len - 1)) || H' || 0xbc
+// with DB = 0x00... || 0x01 || salt, and the bits of EM above em_bits
+// cleared.
+pub fn emsa_pss_encode<H: HashSpec>(
+    msg: Bytes,
+    salt: &Bytes,
+    em_bits: usize,
+) -> Result<Bytes, String> {
+    let em_len = em_bits.div_ceil(8);
+    if em_len < H::HASH_LEN + salt.len() + 2 {
+        return Err("encoding error".to_string());
+    }
+    let h = salted_hash::<H>(msg, salt);
+    let db_len = em_len - H::HASH_LEN - 1;
+    let mut db = Bytes::new_len(db_len);
+    db[db_len - salt.len() - 1] = 0x01;
+    db.update(db_len - salt.len(), salt);
+    let db_mask = mgf1::<H>(&h, db_len);
+
+    let mut em = Bytes::new_len(em_len);
+    for i in 0..db_len {
+        em[i] = db[i] ^ db_mask[i];
+    }
+    em[0] &= 0xff >> (8 * em_len - em_bits);
+    em.update(db_len, &h);
+    em[em_len - 1] = 0xbc;
+    Ok(em)
+}
+
+// EMSA-PSS-VERIFY (RFC 8017, section 9.1.2) for a salt of salt_len bytes.
+pub fn emsa_pss_verify<H: HashSpec>(
+    msg: Bytes,
+    em: &Bytes,
+    em_bits: usize,
+    salt_len: usize,
+) -> Result<(), String> {
+    let em_len = em_bits.div_ceil(8);
+    if em.len() != em_len || em_len < H::HASH_LEN + salt_len + 2 || em[em_len - 1] != 0xbc {
+        return Err("inconsistent".to_string());
+    }
+    let db_len = em_len - H::HASH_LEN - 1;
+    let top_mask = 0xff >> (8 * em_len - em_bits);
+    if em[0] & !top_mask != 0 {
+        return Err("inconsistent".to_string());
+    }
+    let h = Bytes::from(&em[db_len..em_len - 1]);
+    let db_mask = mgf1::<H>(&h, db_len);
+    let mut db = Bytes::new_len(db_len);
+    for i in 0..db_len {
+        db[i] = em[i] ^ db_mask[i];
+    }
+    db[0] &= top_mask;
+
+    // DB must be zeros, 0x01 and then the salt.
+    let ps_len = db_len - salt_len - 1;
+    if db[0..ps_len].iter().any(|&b| b != 0) || db[ps_len] != 0x01 {
+        return Err("inconsistent".to_string());
+    }
+    let salt = Bytes::from(&db[db_len - salt_len..db_len]);
+    if salted_hash::<H>(msg, &salt) == h {
+        Ok(())
+    } else {
+        Err("inconsistent".to_string())
+    }
+}
+
+// Signatures encode to em_bits = modBits - 1, so that the encoded message is
+// below n. For moduli of 8 * k + 1 bits it is one byte shorter than k.
+pub fn pss_sign<H: HashSpec>(sk: &PrivateKey, salt: Bytes, msg: Bytes) -> Result<Bytes, String> {
+    let k = modulus_len(&sk.n);
+    let em = emsa_pss_encode::<H>(msg, &salt, modulus_bits(&sk.n) - 1)?;
+    let mut m = Bytes::new_len(k);
+    m.update(k - em.len(), &em);
+    rsasp1(sk, m)
+}
+
+pub fn pss_verify<H: HashSpec>(
+    pk: &PublicKey,
+    msg: Bytes,
+    signature: Bytes,
+    salt_len: usize,
+) -> Result<(), String> {
+    let k = modulus_len(&pk.n);
+    if signature.len() != k {
+        return Err("invalid signature".to_string());
+    }
+    let m = rsavp1(pk, signature).map_err(|_| "invalid signature".to_string())?;
+    let em_bits = modulus_bits(&pk.n) - 1;
+    let em_len = em_bits.div_ceil(8);
+    if k > em_len && m[0] != 0 {
+        return Err("invalid signature".to_string());
+    }
+    let em = Bytes::from(&m[k - em_len..k]);
+    emsa_pss_verify::<H>(msg, &em, em_bits, salt_len).map_err(|_| "invalid signature".to_string())
+}
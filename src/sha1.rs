@@ -0,0 +1,104 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+
+// SHA-1 as in FIPS 180-4, section 6.1. Broken; here for the PRF of TLS 1.0
+// and 1.1.
+
+const BLOCKSIZE: usize = 64;
+type Schedule = [u32; 80];
+type Hash = [u32; 5];
+bytes!(Block, BLOCKSIZE);
+bytes!(Digest, 20);
+
+static H0: Hash = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+
+// The function and constant of step t.
+fn f(t: usize, b: u32, c: u32, d: u32) -> (u32, u32) {
+    match t / 20 {
+        0 => ((b & c) ^ (!b & d), 0x5a82_7999),
+        1 => (b ^ c ^ d, 0x6ed9_eba1),
+        2 => ((b & c) ^ (b & d) ^ (c & d), 0x8f1b_bcdc),
+        _ => (b ^ c ^ d, 0xca62_c1d6),
+    }
+}
+
+// The message schedule: the block's 16 big-endian words, extended to 80.
+fn schedule(block: Block) -> Schedule {
+    let mut w: Schedule = [0; 80];
+    for t in 0..16 {
+        w[t] = u32::from(block[4 * t]) << 24
+            | u32::from(block[4 * t + 1]) << 16
+            | u32::from(block[4 * t + 2]) << 8
+            | u32::from(block[4 * t + 3]);
+    }
+    for t in 16..80 {
+        w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+    }
+    w
+}
+
+// The compression function; the working variables a..e are v[0]..v[4].
+#[wrappit]
+fn compress(h: Hash, block: Block) -> Hash {
+    let w = schedule(block);
+    let mut v = h;
+    for t in 0..80 {
+        let (ft, kt) = f(t, v[1], v[2], v[3]);
+        let temp = v[0].rotate_left(5) + ft + v[4] + kt + w[t];
+        v[4] = v[3];
+        v[3] = v[2];
+        v[2] = v[1].rotate_left(30);
+        v[1] = v[0];
+        v[0] = temp;
+    }
+
+    let mut compressed: Hash = [0; 5];
+    for i in 0..5 {
+        compressed[i] = h[i] + v[i];
+    }
+    compressed
+}
+
+// Append 0x80, zeros and the message length in bits as a big-endian u64, up
+// to a multiple of the block size.
+fn pad(msg: &Bytes) -> Bytes {
+    let l = msg.len();
+    let zeros = BLOCKSIZE - (l + 9) % BLOCKSIZE;
+    let zeros = if zeros == BLOCKSIZE { 0 } else { zeros };
+    let mut padded = Bytes::new_len(l + 9 + zeros);
+    padded.update(0, msg);
+    padded[l] = 0x80;
+    padded.update_raw(l + 1 + zeros, &(l as u64 * 8).to_be_bytes());
+    padded
+}
+
+pub fn sha1(msg: Bytes) -> Digest {
+    let padded = pad(&msg);
+    let mut h = H0;
+    for block in padded.split(BLOCKSIZE) {
+        h = compress(h, Block::from(&block[..]));
+    }
+
+    let mut digest = Digest::new();
+    for i in 0..5 {
+        let bytes = h[i].to_be_bytes();
+        for j in 0..4 {
+            digest[4 * i + j] = bytes[j];
+        }
+    }
+    digest
+}
+
+pub struct Sha1;
+
+impl HashSpec for Sha1 {
+    const BLOCK_LEN: usize = BLOCKSIZE;
+    const HASH_LEN: usize = 20;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&sha1(msg)[..])
+    }
+}
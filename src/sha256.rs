@@ -0,0 +1,138 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+
+// SHA-256 as in FIPS 180-4, section 6.2.
+
+const BLOCKSIZE: usize = 64;
+type Schedule = [u32; 64];
+type Hash = [u32; 8];
+bytes!(Block, BLOCKSIZE);
+bytes!(Digest, 32);
+
+static K: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1,
+    0x923f_82a4, 0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3,
+    0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174, 0xe49b_69c1, 0xefbe_4786,
+    0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147,
+    0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13,
+    0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85, 0xa2bf_e8a1, 0xa81a_664b,
+    0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a,
+    0x5b9c_ca4f, 0x682e_6ff3, 0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208,
+    0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+static H0: Hash = [
+    0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+fn ch(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (!x & z)
+}
+
+fn maj(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+fn big_sigma0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+fn big_sigma1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+fn small_sigma0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+fn small_sigma1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+// The message schedule: the block's 16 big-endian words, extended to 64.
+#[wrappit]
+fn schedule(block: Block) -> Schedule {
+    let mut w: Schedule = [0; 64];
+    for t in 0..16 {
+        w[t] = u32::from(block[4 * t]) << 24
+            | u32::from(block[4 * t + 1]) << 16
+            | u32::from(block[4 * t + 2]) << 8
+            | u32::from(block[4 * t + 3]);
+    }
+    for t in 16..64 {
+        w[t] = small_sigma1(w[t - 2]) + w[t - 7] + small_sigma0(w[t - 15]) + w[t - 16];
+    }
+    w
+}
+
+// The compression function; the working variables a..h are v[0]..v[7].
+#[wrappit]
+fn compress(h: Hash, block: Block) -> Hash {
+    let w = schedule(block);
+    let mut v = h;
+    for t in 0..64 {
+        let t1 = v[7] + big_sigma1(v[4]) + ch(v[4], v[5], v[6]) + K[t] + w[t];
+        let t2 = big_sigma0(v[0]) + maj(v[0], v[1], v[2]);
+        v[7] = v[6];
+        v[6] = v[5];
+        v[5] = v[4];
+        v[4] = v[3] + t1;
+        v[3] = v[2];
+        v[2] = v[1];
+        v[1] = v[0];
+        v[0] = t1 + t2;
+    }
+
+    let mut compressed: Hash = [0; 8];
+    for i in 0..8 {
+        compressed[i] = h[i] + v[i];
+    }
+    compressed
+}
+
+// Append 0x80, zeros and the message length in bits as a big-endian u64, up
+// to a multiple of the block size.
+fn pad(msg: &Bytes) -> Bytes {
+    let l = msg.len();
+    let zeros = BLOCKSIZE - (l + 9) % BLOCKSIZE;
+    let zeros = if zeros == BLOCKSIZE { 0 } else { zeros };
+    let mut padded = Bytes::new_len(l + 9 + zeros);
+    padded.update(0, msg);
+    padded[l] = 0x80;
+    padded.update_raw(l + 1 + zeros, &(l as u64 * 8).to_be_bytes());
+    padded
+}
+
+pub fn sha256(msg: Bytes) -> Digest {
+    let padded = pad(&msg);
+    let mut h = H0;
+    for block in padded.split(BLOCKSIZE) {
+        h = compress(h, Block::from(&block[..]));
+    }
+
+    let mut digest = Digest::new();
+    for i in 0..8 {
+        let bytes = h[i].to_be_bytes();
+        for j in 0..4 {
+            digest[4 * i + j] = bytes[j];
+        }
+    }
+    digest
+}
+
+pub struct Sha256;
+
+impl HashSpec for Sha256 {
+    const BLOCK_LEN: usize = BLOCKSIZE;
+    const HASH_LEN: usize = 32;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&sha256(msg)[..])
+    }
+}
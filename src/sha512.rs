@@ -0,0 +1,180 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+
+// SHA-512 and SHA-384 as in FIPS 180-4, sections 6.4 and 6.5. SHA-384 is
+// SHA-512 with a different initial hash value, truncated to 48 bytes.
+
+const BLOCKSIZE: usize = 128;
+type Schedule = [u64; 80];
+type Hash = [u64; 8];
+bytes!(Block, BLOCKSIZE);
+bytes!(Digest, 64);
+bytes!(Digest384, 48);
+
+static K: [u64; 80] = [
+    0x428a_2f98_d728_ae22, 0x7137_4491_23ef_65cd, 0xb5c0_fbcf_ec4d_3b2f, 0xe9b5_dba5_8189_dbbc,
+    0x3956_c25b_f348_b538, 0x59f1_11f1_b605_d019, 0x923f_82a4_af19_4f9b, 0xab1c_5ed5_da6d_8118,
+    0xd807_aa98_a303_0242, 0x1283_5b01_4570_6fbe, 0x2431_85be_4ee4_b28c, 0x550c_7dc3_d5ff_b4e2,
+    0x72be_5d74_f27b_896f, 0x80de_b1fe_3b16_96b1, 0x9bdc_06a7_25c7_1235, 0xc19b_f174_cf69_2694,
+    0xe49b_69c1_9ef1_4ad2, 0xefbe_4786_384f_25e3, 0x0fc1_9dc6_8b8c_d5b5, 0x240c_a1cc_77ac_9c65,
+    0x2de9_2c6f_592b_0275, 0x4a74_84aa_6ea6_e483, 0x5cb0_a9dc_bd41_fbd4, 0x76f9_88da_8311_53b5,
+    0x983e_5152_ee66_dfab, 0xa831_c66d_2db4_3210, 0xb003_27c8_98fb_213f, 0xbf59_7fc7_beef_0ee4,
+    0xc6e0_0bf3_3da8_8fc2, 0xd5a7_9147_930a_a725, 0x06ca_6351_e003_826f, 0x1429_2967_0a0e_6e70,
+    0x27b7_0a85_46d2_2ffc, 0x2e1b_2138_5c26_c926, 0x4d2c_6dfc_5ac4_2aed, 0x5338_0d13_9d95_b3df,
+    0x650a_7354_8baf_63de, 0x766a_0abb_3c77_b2a8, 0x81c2_c92e_47ed_aee6, 0x9272_2c85_1482_353b,
+    0xa2bf_e8a1_4cf1_0364, 0xa81a_664b_bc42_3001, 0xc24b_8b70_d0f8_9791, 0xc76c_51a3_0654_be30,
+    0xd192_e819_d6ef_5218, 0xd699_0624_5565_a910, 0xf40e_3585_5771_202a, 0x106a_a070_32bb_d1b8,
+    0x19a4_c116_b8d2_d0c8, 0x1e37_6c08_5141_ab53, 0x2748_774c_df8e_eb99, 0x34b0_bcb5_e19b_48a8,
+    0x391c_0cb3_c5c9_5a63, 0x4ed8_aa4a_e341_8acb, 0x5b9c_ca4f_7763_e373, 0x682e_6ff3_d6b2_b8a3,
+    0x748f_82ee_5def_b2fc, 0x78a5_636f_4317_2f60, 0x84c8_7814_a1f0_ab72, 0x8cc7_0208_1a64_39ec,
+    0x90be_fffa_2363_1e28, 0xa450_6ceb_de82_bde9, 0xbef9_a3f7_b2c6_7915, 0xc671_78f2_e372_532b,
+    0xca27_3ece_ea26_619c, 0xd186_b8c7_21c0_c207, 0xeada_7dd6_cde0_eb1e, 0xf57d_4f7f_ee6e_d178,
+    0x06f0_67aa_7217_6fba, 0x0a63_7dc5_a2c8_98a6, 0x113f_9804_bef9_0dae, 0x1b71_0b35_131c_471b,
+    0x28db_77f5_2304_7d84, 0x32ca_ab7b_40c7_2493, 0x3c9e_be0a_15c9_bebc, 0x431d_67c4_9c10_0d4c,
+    0x4cc5_d4be_cb3e_42b6, 0x597f_299c_fc65_7e2a, 0x5fcb_6fab_3ad6_faec, 0x6c44_198c_4a47_5817,
+];
+
+static H0_512: Hash = [
+    0x6a09_e667_f3bc_c908,
+    0xbb67_ae85_84ca_a73b,
+    0x3c6e_f372_fe94_f82b,
+    0xa54f_f53a_5f1d_36f1,
+    0x510e_527f_ade6_82d1,
+    0x9b05_688c_2b3e_6c1f,
+    0x1f83_d9ab_fb41_bd6b,
+    0x5be0_cd19_137e_2179,
+];
+
+static H0_384: Hash = [
+    0xcbbb_9d5d_c105_9ed8,
+    0x629a_292a_367c_d507,
+    0x9159_015a_3070_dd17,
+    0x152f_ecd8_f70e_5939,
+    0x6733_2667_ffc0_0b31,
+    0x8eb4_4a87_6858_1511,
+    0xdb0c_2e0d_64f9_8fa7,
+    0x47b5_481d_befa_4fa4,
+];
+
+fn ch(x: u64, y: u64, z: u64) -> u64 {
+    (x & y) ^ (!x & z)
+}
+
+fn maj(x: u64, y: u64, z: u64) -> u64 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+fn big_sigma0(x: u64) -> u64 {
+    x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39)
+}
+
+fn big_sigma1(x: u64) -> u64 {
+    x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41)
+}
+
+fn small_sigma0(x: u64) -> u64 {
+    x.rotate_right(1) ^ x.rotate_right(8) ^ (x >> 7)
+}
+
+fn small_sigma1(x: u64) -> u64 {
+    x.rotate_right(19) ^ x.rotate_right(61) ^ (x >> 6)
+}
+
+// The message schedule: the block's 16 big-endian words, extended to 80.
+#[wrappit]
+fn schedule(block: Block) -> Schedule {
+    let mut w: Schedule = [0; 80];
+    for t in 0..16 {
+        for j in 0..8 {
+            w[t] = w[t] << 8 | u64::from(block[8 * t + j]);
+        }
+    }
+    for t in 16..80 {
+        w[t] = small_sigma1(w[t - 2]) + w[t - 7] + small_sigma0(w[t - 15]) + w[t - 16];
+    }
+    w
+}
+
+// The compression function; the working variables a..h are v[0]..v[7].
+#[wrappit]
+fn compress(h: Hash, block: Block) -> Hash {
+    let w = schedule(block);
+    let mut v = h;
+    for t in 0..80 {
+        let t1 = v[7] + big_sigma1(v[4]) + ch(v[4], v[5], v[6]) + K[t] + w[t];
+        let t2 = big_sigma0(v[0]) + maj(v[0], v[1], v[2]);
+        v[7] = v[6];
+        v[6] = v[5];
+        v[5] = v[4];
+        v[4] = v[3] + t1;
+        v[3] = v[2];
+        v[2] = v[1];
+        v[1] = v[0];
+        v[0] = t1 + t2;
+    }
+
+    let mut compressed: Hash = [0; 8];
+    for i in 0..8 {
+        compressed[i] = h[i] + v[i];
+    }
+    compressed
+}
+
+// Append 0x80, zeros and the message length in bits as a big-endian u128,
+// up to a multiple of the block size.
+fn pad(msg: &Bytes) -> Bytes {
+    let l = msg.len();
+    let zeros = BLOCKSIZE - (l + 17) % BLOCKSIZE;
+    let zeros = if zeros == BLOCKSIZE { 0 } else { zeros };
+    let mut padded = Bytes::new_len(l + 17 + zeros);
+    padded.update(0, msg);
+    padded[l] = 0x80;
+    padded.update_raw(l + 1 + zeros, &(l as u128 * 8).to_be_bytes());
+    padded
+}
+
+fn hash_from(h0: Hash, msg: Bytes) -> Bytes {
+    let padded = pad(&msg);
+    let mut h = h0;
+    for block in padded.split(BLOCKSIZE) {
+        h = compress(h, Block::from(&block[..]));
+    }
+
+    let mut digest = Bytes::new_len(64);
+    for i in 0..8 {
+        digest.update_raw(8 * i, &h[i].to_be_bytes());
+    }
+    digest
+}
+
+pub fn sha512(msg: Bytes) -> Digest {
+    Digest::from(&hash_from(H0_512, msg)[..])
+}
+
+pub fn sha384(msg: Bytes) -> Digest384 {
+    Digest384::from(&hash_from(H0_384, msg)[0..48])
+}
+
+pub struct Sha512;
+pub struct Sha384;
+
+impl HashSpec for Sha512 {
+    const BLOCK_LEN: usize = BLOCKSIZE;
+    const HASH_LEN: usize = 64;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&sha512(msg)[..])
+    }
+}
+
+impl HashSpec for Sha384 {
+    const BLOCK_LEN: usize = BLOCKSIZE;
+    const HASH_LEN: usize = 48;
+
+    fn hash(msg: Bytes) -> Bytes {
+        Bytes::from(&sha384(msg)[..])
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+
+/// The SignatureScheme values of RFC 8446, 4.2.3. In TLS 1.2 the same code
+/// points are HashAlgorithm and SignatureAlgorithm pairs (RFC 5246,
+/// 7.4.1.4.1), and the ECDSA ones name only the hash, not the curve.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignatureScheme {
+    RsaPkcs1Sha256,
+    RsaPkcs1Sha384,
+    RsaPkcs1Sha512,
+    EcdsaSecp256r1Sha256,
+    EcdsaSecp384r1Sha384,
+    EcdsaSecp521r1Sha512,
+    RsaPssRsaeSha256,
+    RsaPssRsaeSha384,
+    RsaPssRsaeSha512,
+    Ed25519,
+    Ed448,
+    RsaPssPssSha256,
+    RsaPssPssSha384,
+    RsaPssPssSha512,
+    RsaPkcs1Sha1,
+    EcdsaSha1,
+}
+
+impl SignatureScheme {
+    pub(crate) const ALL: [SignatureScheme; 16] = {
+        use SignatureScheme::*;
+        [
+            RsaPkcs1Sha256,
+            RsaPkcs1Sha384,
+            RsaPkcs1Sha512,
+            EcdsaSecp256r1Sha256,
+            EcdsaSecp384r1Sha384,
+            EcdsaSecp521r1Sha512,
+            RsaPssRsaeSha256,
+            RsaPssRsaeSha384,
+            RsaPssRsaeSha512,
+            Ed25519,
+            Ed448,
+            RsaPssPssSha256,
+            RsaPssPssSha384,
+            RsaPssPssSha512,
+            RsaPkcs1Sha1,
+            EcdsaSha1,
+        ]
+    };
+
+    /// The schemes a TLS 1.3 implementation must support (RFC 8446, 9.1):
+    /// rsa_pkcs1_sha256 for certificates only, the others for
+    /// CertificateVerify too.
+    pub const MANDATORY: [SignatureScheme; 3] = [
+        SignatureScheme::RsaPkcs1Sha256,
+        SignatureScheme::RsaPssRsaeSha256,
+        SignatureScheme::EcdsaSecp256r1Sha256,
+    ];
+
+    pub fn to_u16(self) -> u16 {
+        use SignatureScheme::*;
+        match self {
+            RsaPkcs1Sha256 => 0x0401,
+            RsaPkcs1Sha384 => 0x0501,
+            RsaPkcs1Sha512 => 0x0601,
+            EcdsaSecp256r1Sha256 => 0x0403,
+            EcdsaSecp384r1Sha384 => 0x0503,
+            EcdsaSecp521r1Sha512 => 0x0603,
+            RsaPssRsaeSha256 => 0x0804,
+            RsaPssRsaeSha384 => 0x0805,
+            RsaPssRsaeSha512 => 0x0806,
+            Ed25519 => 0x0807,
+            Ed448 => 0x0808,
+            RsaPssPssSha256 => 0x0809,
+            RsaPssPssSha384 => 0x080a,
+            RsaPssPssSha512 => 0x080b,
+            RsaPkcs1Sha1 => 0x0201,
+            EcdsaSha1 => 0x0203,
+        }
+    }
+
+    /// The scheme with code point `v`, if this crate knows it. DSA, SHA-224
+    /// and GREASE values are all unknown.
+    pub fn from_u16(v: u16) -> Option<SignatureScheme> {
+        SignatureScheme::ALL.iter().copied().find(|s| s.to_u16() == v)
+    }
+
+    /// Whether the scheme is PKCS #1 v1.5 or uses SHA-1, the legacy schemes
+    /// TLS 1.3 allows only in certificates (RFC 8446, 4.2.3).
+    pub fn is_legacy(self) -> bool {
+        use SignatureScheme::*;
+        matches!(self, RsaPkcs1Sha256 | RsaPkcs1Sha384 | RsaPkcs1Sha512 | RsaPkcs1Sha1 | EcdsaSha1)
+    }
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SignatureScheme::*;
+        let name = match self {
+            RsaPkcs1Sha256 => "rsa_pkcs1_sha256",
+            RsaPkcs1Sha384 => "rsa_pkcs1_sha384",
+            RsaPkcs1Sha512 => "rsa_pkcs1_sha512",
+            EcdsaSecp256r1Sha256 => "ecdsa_secp256r1_sha256",
+            EcdsaSecp384r1Sha384 => "ecdsa_secp384r1_sha384",
+            EcdsaSecp521r1Sha512 => "ecdsa_secp521r1_sha512",
+            RsaPssRsaeSha256 => "rsa_pss_rsae_sha256",
+            RsaPssRsaeSha384 => "rsa_pss_rsae_sha384",
+            RsaPssRsaeSha512 => "rsa_pss_rsae_sha512",
+            Ed25519 => "ed25519",
+            Ed448 => "ed448",
+            RsaPssPssSha256 => "rsa_pss_pss_sha256",
+            RsaPssPssSha384 => "rsa_pss_pss_sha384",
+            RsaPssPssSha512 => "rsa_pss_pss_sha512",
+            RsaPkcs1Sha1 => "rsa_pkcs1_sha1",
+            EcdsaSha1 => "ecdsa_sha1",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The peer's signature_algorithms, as the version negotiated reads it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Offered<'a> {
+    /// The list a TLS 1.2 ClientHello or CertificateRequest carried, if
+    /// any. Without one, the peer is taken to offer SHA-1 with RSA and
+    /// ECDSA (RFC 5246, 7.4.1.4.1).
+    Tls12(Option<&'a [u16]>),
+    /// The list of a TLS 1.3 ClientHello or CertificateRequest, where the
+    /// extension is mandatory (RFC 8446, 4.2.3 and 9.2).
+    Tls13(&'a [u16]),
+}
+
+const TLS12_DEFAULT: [u16; 2] = [0x0201, 0x0203];
+
+/// The scheme to sign the ServerKeyExchange or CertificateVerify with: the
+/// first of the peer's offer, in its order of preference, that `available`
+/// holds. `available` is what the signer's key can do; in TLS 1.2 an ECDSA
+/// key can sign with any hash its ECDSA schemes name, whatever the curve.
+///
+/// TLS 1.3 never picks a legacy scheme, whatever the peer offers. `None`
+/// means there is no common scheme, which the signer answers with a
+/// handshake_failure alert.
+pub fn select_signature_scheme(
+    offered: Offered,
+    available: &[SignatureScheme],
+) -> Option<SignatureScheme> {
+    let (list, tls13) = match offered {
+        Offered::Tls12(list) => (list.unwrap_or(&TLS12_DEFAULT), false),
+        Offered::Tls13(list) => (list, true),
+    };
+    list.iter()
+        .filter_map(|&v| SignatureScheme::from_u16(v))
+        .filter(|s| !(tls13 && s.is_legacy()))
+        .find(|s| available.contains(s))
+}
+
+/// Whether the peer may sign with `scheme` under `offered`: the checks a
+/// receiver of ServerKeyExchange or CertificateVerify makes before looking
+/// at the signature. Anything else is an illegal_parameter.
+pub fn check_signature_scheme(offered: Offered, scheme: u16) -> bool {
+    match SignatureScheme::from_u16(scheme) {
+        Some(s) => select_signature_scheme(offered, &[s]) == Some(s),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use SignatureScheme::*;
+
+    // The signature_algorithms of the OpenSSL 3.0 ClientHellos in hello's
+    // tests, DSA and SHA-224 included.
+    const OPENSSL: [u16; 20] = [
+        0x0403, 0x0503, 0x0603, 0x0807, 0x0808, 0x0809, 0x080a, 0x080b, 0x0804, 0x0805, 0x0806,
+        0x0401, 0x0501, 0x0601, 0x0303, 0x0301, 0x0302, 0x0402, 0x0502, 0x0602,
+    ];
+
+    const RSA_KEY: [SignatureScheme; 5] =
+        [RsaPkcs1Sha1, RsaPkcs1Sha256, RsaPssRsaeSha256, RsaPssRsaeSha384, RsaPkcs1Sha384];
+
+    #[test]
+    fn scheme_codec() {
+        for &s in SignatureScheme::ALL.iter() {
+            assert_eq!(SignatureScheme::from_u16(s.to_u16()), Some(s));
+        }
+        assert_eq!(SignatureScheme::from_u16(0x0402), None);
+        assert_eq!(SignatureScheme::from_u16(0x0a0a), None);
+        assert_eq!(RsaPssRsaeSha256.to_string(), "rsa_pss_rsae_sha256");
+        let known = OPENSSL.iter().filter_map(|&v| SignatureScheme::from_u16(v)).count();
+        assert_eq!(known, 14);
+    }
+
+    #[test]
+    fn mandatory_to_implement() {
+        let mandatory = SignatureScheme::MANDATORY.iter().map(|s| s.to_u16()).collect::<Vec<_>>();
+        let offer = Offered::Tls13(&mandatory);
+        // An RSA key signs CertificateVerify with PSS, never PKCS #1 v1.5.
+        assert_eq!(select_signature_scheme(offer, &RSA_KEY), Some(RsaPssRsaeSha256));
+        assert_eq!(select_signature_scheme(offer, &[RsaPkcs1Sha256]), None);
+        assert_eq!(
+            select_signature_scheme(offer, &[EcdsaSecp256r1Sha256]),
+            Some(EcdsaSecp256r1Sha256)
+        );
+        // Ed25519 is a SHOULD, not a MUST.
+        assert_eq!(select_signature_scheme(offer, &[Ed25519]), None);
+        assert!(!check_signature_scheme(offer, RsaPkcs1Sha256.to_u16()));
+        assert!(check_signature_scheme(offer, RsaPssRsaeSha256.to_u16()));
+    }
+
+    #[test]
+    fn client_preference() {
+        let tls13 = Offered::Tls13(&OPENSSL);
+        let tls12 = Offered::Tls12(Some(&OPENSSL));
+        assert_eq!(select_signature_scheme(tls13, &RSA_KEY), Some(RsaPssRsaeSha256));
+        assert_eq!(select_signature_scheme(tls12, &RSA_KEY), Some(RsaPssRsaeSha256));
+        let ecdsa = [EcdsaSecp384r1Sha384, EcdsaSecp256r1Sha256];
+        assert_eq!(select_signature_scheme(tls13, &ecdsa), Some(EcdsaSecp256r1Sha256));
+        // PKCS #1 v1.5 is fine in TLS 1.2, if that's all the client sends.
+        let pkcs1 = [0x0401];
+        let only_pkcs1 = Offered::Tls12(Some(&pkcs1));
+        assert_eq!(select_signature_scheme(only_pkcs1, &RSA_KEY), Some(RsaPkcs1Sha256));
+        assert_eq!(select_signature_scheme(Offered::Tls13(&pkcs1), &RSA_KEY), None);
+        assert!(!check_signature_scheme(tls12, 0x0402));
+        assert!(!check_signature_scheme(tls12, RsaPkcs1Sha1.to_u16()));
+    }
+
+    #[test]
+    fn tls12_default() {
+        // Without signature_algorithms, only SHA-1 will do.
+        let absent = Offered::Tls12(None);
+        assert_eq!(select_signature_scheme(absent, &RSA_KEY), Some(RsaPkcs1Sha1));
+        assert_eq!(select_signature_scheme(absent, &[EcdsaSecp256r1Sha256]), None);
+        assert_eq!(select_signature_scheme(absent, &[EcdsaSha1]), Some(EcdsaSha1));
+        assert!(check_signature_scheme(absent, EcdsaSha1.to_u16()));
+        assert!(!check_signature_scheme(absent, RsaPkcs1Sha256.to_u16()));
+        // An empty list offers nothing, unlike no list.
+        assert_eq!(select_signature_scheme(Offered::Tls12(Some(&[])), &RSA_KEY), None);
+    }
+}
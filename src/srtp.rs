@@ -0,0 +1,251 @@
+use crate::codec::{self, CodecError, Reader};
+use crate::extensions::{Extension, ExtensionType};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+// The use_srtp extension of DTLS-SRTP (RFC 5764, 4.1). A client offers the
+// SRTP protection profiles it supports, and the server picks one of them;
+// once the handshake is done the SRTP keys come from the keying material
+// exporter, for which see `exporter::dtls_srtp_keys`.
+
+/// An SRTPProtectionProfile, of those RFC 5764, 4.1.2 and RFC 7714, 14.2
+/// define. The NULL profiles, which don't encrypt, are left out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SrtpProtectionProfile {
+    Aes128CmHmacSha1_80,
+    Aes128CmHmacSha1_32,
+    AeadAes128Gcm,
+    AeadAes256Gcm,
+    Unknown(u16),
+}
+
+impl SrtpProtectionProfile {
+    pub fn to_u16(self) -> u16 {
+        match self {
+            SrtpProtectionProfile::Aes128CmHmacSha1_80 => 0x0001,
+            SrtpProtectionProfile::Aes128CmHmacSha1_32 => 0x0002,
+            SrtpProtectionProfile::AeadAes128Gcm => 0x0007,
+            SrtpProtectionProfile::AeadAes256Gcm => 0x0008,
+            SrtpProtectionProfile::Unknown(v) => v,
+        }
+    }
+
+    pub fn from_u16(v: u16) -> SrtpProtectionProfile {
+        match v {
+            0x0001 => SrtpProtectionProfile::Aes128CmHmacSha1_80,
+            0x0002 => SrtpProtectionProfile::Aes128CmHmacSha1_32,
+            0x0007 => SrtpProtectionProfile::AeadAes128Gcm,
+            0x0008 => SrtpProtectionProfile::AeadAes256Gcm,
+            _ => SrtpProtectionProfile::Unknown(v),
+        }
+    }
+
+    /// The lengths of the SRTP master key and master salt the profile
+    /// takes from the exporter, or `None` for an unknown profile.
+    pub fn key_and_salt_len(self) -> Option<(usize, usize)> {
+        match self {
+            SrtpProtectionProfile::Aes128CmHmacSha1_80
+            | SrtpProtectionProfile::Aes128CmHmacSha1_32 => Some((16, 14)),
+            SrtpProtectionProfile::AeadAes128Gcm => Some((16, 12)),
+            SrtpProtectionProfile::AeadAes256Gcm => Some((32, 12)),
+            SrtpProtectionProfile::Unknown(_) => None,
+        }
+    }
+}
+
+/// The extension's data (RFC 5764, 4.1.1):
+///
+/// ```text
+/// uint8 SRTPProtectionProfile[2];
+///
+/// struct {
+///    SRTPProtectionProfiles SRTPProtectionProfiles;
+///    opaque srtp_mki<0..255>;
+/// } UseSRTPData;
+///
+/// SRTPProtectionProfile SRTPProtectionProfiles<2..2^16-1>;
+/// ```
+///
+/// A client lists the profiles it offers, most preferred first; a server
+/// answers with just the one it chose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseSrtp {
+    pub profiles: Vec<SrtpProtectionProfile>,
+    pub mki: Vec<u8>,
+}
+
+/// Why a use_srtp extension was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SrtpError {
+    /// The extension is malformed.
+    Codec(CodecError),
+    /// The server chose other than exactly one profile.
+    ProfileCount(usize),
+    /// The server chose a profile the client didn't offer.
+    NotOffered(SrtpProtectionProfile),
+    /// The server's MKI is neither empty nor the client's.
+    UnexpectedMki,
+}
+
+impl fmt::Display for SrtpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SrtpError::Codec(e) => e.fmt(f),
+            SrtpError::ProfileCount(n) => write!(f, "server chose {} SRTP profiles", n),
+            SrtpError::NotOffered(p) => write!(f, "SRTP profile {:#06x} not offered", p.to_u16()),
+            SrtpError::UnexpectedMki => write!(f, "unexpected SRTP MKI"),
+        }
+    }
+}
+
+impl std::error::Error for SrtpError {}
+
+impl From<CodecError> for SrtpError {
+    fn from(e: CodecError) -> SrtpError {
+        SrtpError::Codec(e)
+    }
+}
+
+impl SrtpError {
+    /// The alert a client answers a bad extension with (RFC 5764, 4.1.3).
+    pub fn alert(&self) -> AlertDescription {
+        match self {
+            SrtpError::Codec(e) => e.alert(),
+            _ => AlertDescription::IllegalParameter,
+        }
+    }
+}
+
+pub fn use_srtp_extension(use_srtp: &UseSrtp) -> Result<Extension, SrtpError> {
+    let profiles = use_srtp.profiles.iter().map(|p| p.to_u16()).collect::<Vec<_>>();
+    let mut data = Vec::with_capacity(3 + 2 * profiles.len() + use_srtp.mki.len());
+    codec::write_u16_list(&mut data, 2, 0xffff, "SRTPProtectionProfiles", &profiles)?;
+    codec::write_u8_vec(&mut data, 0, 0xff, "srtp_mki", &use_srtp.mki)?;
+    Ok(Extension { extension_type: ExtensionType::UseSrtp.to_u16(), data })
+}
+
+pub fn decode_use_srtp_extension(data: &[u8]) -> Result<UseSrtp, SrtpError> {
+    let mut r = Reader::new(data);
+    let profiles = r.read_u16_list(2, 0xffff, "SRTPProtectionProfiles")?;
+    let mki = r.read_u8_vec(0, 0xff, "srtp_mki")?.to_vec();
+    r.end()?;
+    let profiles = profiles.into_iter().map(SrtpProtectionProfile::from_u16).collect();
+    Ok(UseSrtp { profiles, mki })
+}
+
+/// The server's answer to a client's offer: the first of `preferences` the
+/// client offered, with the client's MKI if the server uses MKIs
+/// (RFC 5764, 4.1.1). `None` if they share no profile, in which case the
+/// server sends no use_srtp extension and the connection goes without
+/// SRTP.
+pub fn choose_profile(
+    offer: &UseSrtp,
+    preferences: &[SrtpProtectionProfile],
+    use_mki: bool,
+) -> Option<UseSrtp> {
+    let profile = preferences.iter().find(|p| offer.profiles.contains(p))?;
+    let mki = if use_mki { offer.mki.clone() } else { Vec::new() };
+    Some(UseSrtp { profiles: vec![*profile], mki })
+}
+
+/// The client's check of the server's answer (RFC 5764, 4.1.1 and 4.1.3),
+/// giving the profile chosen.
+pub fn check_server_use_srtp(
+    offer: &UseSrtp,
+    answer: &UseSrtp,
+) -> Result<SrtpProtectionProfile, SrtpError> {
+    if answer.profiles.len() != 1 {
+        return Err(SrtpError::ProfileCount(answer.profiles.len()));
+    }
+    let profile = answer.profiles[0];
+    if !offer.profiles.contains(&profile) {
+        return Err(SrtpError::NotOffered(profile));
+    }
+    if !answer.mki.is_empty() && answer.mki != offer.mki {
+        return Err(SrtpError::UnexpectedMki);
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use SrtpProtectionProfile::*;
+
+    fn offer() -> UseSrtp {
+        UseSrtp { profiles: vec![AeadAes128Gcm, Aes128CmHmacSha1_80], mki: vec![7, 8] }
+    }
+
+    #[test]
+    fn extension_codec() {
+        let ext = use_srtp_extension(&offer()).unwrap();
+        assert_eq!(ext.extension_type, 14);
+        assert_eq!(ext.data, vec![0, 4, 0, 7, 0, 1, 2, 7, 8]);
+        assert_eq!(decode_use_srtp_extension(&ext.data), Ok(offer()));
+
+        // Unknown profiles are kept, for the server to pass over.
+        let unknown = decode_use_srtp_extension(&[0, 4, 0xff, 0x01, 0, 2, 0]).unwrap();
+        assert_eq!(unknown.profiles, vec![Unknown(0xff01), Aes128CmHmacSha1_32]);
+        assert_eq!(unknown.profiles[0].key_and_salt_len(), None);
+        assert!(unknown.mki.is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let empty = UseSrtp { profiles: vec![], mki: vec![] };
+        let no_profiles = SrtpError::Codec(CodecError::BadLength("SRTPProtectionProfiles"));
+        assert_eq!(use_srtp_extension(&empty), Err(no_profiles.clone()));
+        assert_eq!(decode_use_srtp_extension(&[0, 0, 0]), Err(no_profiles.clone()));
+        assert_eq!(decode_use_srtp_extension(&[0, 3, 0, 1, 0, 0]), Err(no_profiles.clone()));
+        assert_eq!(no_profiles.alert(), AlertDescription::DecodeError);
+        let long_mki = UseSrtp { mki: vec![0; 256], ..offer() };
+        assert!(use_srtp_extension(&long_mki).is_err());
+        // The MKI's length is not optional.
+        assert_eq!(
+            decode_use_srtp_extension(&[0, 2, 0, 1]),
+            Err(SrtpError::Codec(CodecError::Truncated))
+        );
+        assert_eq!(
+            decode_use_srtp_extension(&[0, 2, 0, 1, 0, 0]),
+            Err(SrtpError::Codec(CodecError::TrailingBytes))
+        );
+    }
+
+    #[test]
+    fn negotiation() {
+        // The server's preference wins.
+        let answer = choose_profile(&offer(), &[Aes128CmHmacSha1_80, AeadAes128Gcm], false);
+        let answer = answer.unwrap();
+        assert_eq!(answer, UseSrtp { profiles: vec![Aes128CmHmacSha1_80], mki: vec![] });
+        assert_eq!(check_server_use_srtp(&offer(), &answer), Ok(Aes128CmHmacSha1_80));
+        let with_mki = choose_profile(&offer(), &[AeadAes128Gcm], true).unwrap();
+        assert_eq!(with_mki.mki, vec![7, 8]);
+        assert_eq!(check_server_use_srtp(&offer(), &with_mki), Ok(AeadAes128Gcm));
+        assert_eq!(choose_profile(&offer(), &[AeadAes256Gcm], true), None);
+    }
+
+    #[test]
+    fn bad_answers() {
+        let answer = |profiles: Vec<SrtpProtectionProfile>, mki: Vec<u8>| {
+            check_server_use_srtp(&offer(), &UseSrtp { profiles, mki })
+        };
+        let both = answer(vec![AeadAes128Gcm, Aes128CmHmacSha1_80], vec![]);
+        assert_eq!(both, Err(SrtpError::ProfileCount(2)));
+        let other = answer(vec![AeadAes256Gcm], vec![]).unwrap_err();
+        assert_eq!(other, SrtpError::NotOffered(AeadAes256Gcm));
+        assert_eq!(other.alert(), AlertDescription::IllegalParameter);
+        assert_eq!(answer(vec![AeadAes128Gcm], vec![9]), Err(SrtpError::UnexpectedMki));
+    }
+
+    #[test]
+    fn key_lengths() {
+        assert_eq!(Aes128CmHmacSha1_80.key_and_salt_len(), Some((16, 14)));
+        assert_eq!(Aes128CmHmacSha1_32.key_and_salt_len(), Some((16, 14)));
+        assert_eq!(AeadAes128Gcm.key_and_salt_len(), Some((16, 12)));
+        assert_eq!(AeadAes256Gcm.key_and_salt_len(), Some((32, 12)));
+        for p in &[Aes128CmHmacSha1_80, Aes128CmHmacSha1_32, AeadAes128Gcm, AeadAes256Gcm] {
+            assert_eq!(SrtpProtectionProfile::from_u16(p.to_u16()), *p);
+        }
+    }
+}
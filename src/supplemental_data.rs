@@ -0,0 +1,124 @@
+use crate::codec::{self, CodecError, Reader};
+
+// The SupplementalData handshake message (RFC 4680), which carries data
+// about the handshake whose formats hello extensions negotiate. The server
+// sends it right after its ServerHello, and the client before its
+// Certificate; see `tls12::ProtocolOptionsBuilder::supplemental_data`.
+
+/// The SupplementalDataType of the user mapping of RFC 4681.
+pub const USER_MAPPING_DATA: u16 = 0;
+
+/// The UserMappingType of a user principal name and domain (RFC 4681, 2).
+pub const UPN_DOMAIN_HINT: u8 = 64;
+
+/// The upper bound of the entry list.
+const MAX_LIST_LEN: usize = (1 << 24) - 1;
+
+/// One entry of supplemental data:
+///
+/// ```text
+/// struct {
+///     SupplementalDataType supp_data_type;
+///     uint16 supp_data_length;
+///     select(SupplementalDataType) { }
+/// } SupplementalDataEntry;
+/// ```
+///
+/// The data is left as it is; its format is the type's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupplementalDataEntry {
+    pub supp_data_type: u16,
+    pub supp_data: Vec<u8>,
+}
+
+/// The message body:
+///
+/// ```text
+/// struct {
+///     SupplementalDataEntry supp_data<1..2^24-1>;
+/// } SupplementalData;
+/// ```
+pub fn encode_supplemental_data(entries: &[SupplementalDataEntry]) -> Result<Vec<u8>, CodecError> {
+    let mut list = Vec::new();
+    for entry in entries {
+        codec::write_u16(&mut list, entry.supp_data_type);
+        codec::write_u16_vec(&mut list, 0, 0xffff, "supp_data_length", &entry.supp_data)?;
+    }
+    let mut out = Vec::with_capacity(3 + list.len());
+    codec::write_u24_vec(&mut out, 1, MAX_LIST_LEN, "supp_data", &list)?;
+    Ok(out)
+}
+
+pub fn decode_supplemental_data(body: &[u8]) -> Result<Vec<SupplementalDataEntry>, CodecError> {
+    let mut r = Reader::new(body);
+    let mut list = Reader::new(r.read_u24_vec(1, MAX_LIST_LEN, "supp_data")?);
+    r.end()?;
+    let mut entries = Vec::new();
+    while !list.is_empty() {
+        let supp_data_type = list.read_u16()?;
+        let supp_data = list.read_u16_vec(0, 0xffff, "supp_data_length")?.to_vec();
+        entries.push(SupplementalDataEntry { supp_data_type, supp_data });
+    }
+    Ok(entries)
+}
+
+/// The data of the user_mapping extension (RFC 4681, 2): the client offers
+/// the UserMappingTypes it can send, and the server answers with those it
+/// takes.
+///
+/// ```text
+/// struct {
+///     UserMappingType user_mapping_types<1..2^8-1>;
+/// } UserMappingTypeList;
+/// ```
+pub fn encode_user_mapping_types(types: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut out = Vec::with_capacity(1 + types.len());
+    codec::write_u8_vec(&mut out, 1, 0xff, "user_mapping_types", types)?;
+    Ok(out)
+}
+
+pub fn decode_user_mapping_types(data: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut r = Reader::new(data);
+    let types = r.read_u8_vec(1, 0xff, "user_mapping_types")?.to_vec();
+    r.end()?;
+    Ok(types)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handshake;
+
+    #[test]
+    fn round_trip() {
+        let entries = vec![
+            SupplementalDataEntry { supp_data_type: USER_MAPPING_DATA, supp_data: vec![1, 2, 3] },
+            SupplementalDataEntry { supp_data_type: 16386, supp_data: vec![] },
+        ];
+        let body = encode_supplemental_data(&entries).unwrap();
+        assert_eq!(body, vec![0, 0, 11, 0, 0, 0, 3, 1, 2, 3, 0x40, 0x02, 0, 0]);
+        assert_eq!(decode_supplemental_data(&body), Ok(entries));
+        assert_eq!(handshake::frame(23, &body).unwrap()[..4], [23, 0, 0, 14]);
+
+        let types = encode_user_mapping_types(&[UPN_DOMAIN_HINT]).unwrap();
+        assert_eq!(types, vec![1, 64]);
+        assert_eq!(decode_user_mapping_types(&types), Ok(vec![UPN_DOMAIN_HINT]));
+    }
+
+    #[test]
+    fn malformed() {
+        let empty = CodecError::BadLength("supp_data");
+        assert_eq!(encode_supplemental_data(&[]), Err(empty));
+        assert_eq!(decode_supplemental_data(&[0, 0, 0]), Err(empty));
+        let body = [0, 0, 7, 0, 0, 0, 3, 1, 2, 3];
+        for len in 0..body.len() {
+            assert!(decode_supplemental_data(&body[..len]).is_err());
+        }
+        // An entry claiming more data than the list holds.
+        assert_eq!(decode_supplemental_data(&[0, 0, 5, 0, 0, 0, 3, 1]), Err(CodecError::Truncated));
+        assert_eq!(
+            decode_user_mapping_types(&[0]),
+            Err(CodecError::BadLength("user_mapping_types"))
+        );
+    }
+}
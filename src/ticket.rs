@@ -0,0 +1,236 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::aes::Block;
+use crate::aescbc::{cbc_decrypt, cbc_encrypt};
+use crate::hmac::hmac;
+use crate::sha256::Sha256;
+
+// Session tickets (RFC 5077): the NewSessionTicket message a server sends
+// when it issues a ticket, and the ticket construction of section 4, in
+// which the server seals its session state under keys only it holds.
+
+// struct {
+//     uint32 ticket_lifetime_hint;
+//     opaque ticket<0..2^16-1>;
+// } NewSessionTicket;
+// A lifetime hint of 0 says nothing about the lifetime.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewSessionTicket {
+    pub lifetime_hint: u32,
+    pub ticket: Bytes,
+}
+
+pub fn encode_new_session_ticket(msg: &NewSessionTicket) -> Result<Bytes, String> {
+    if msg.ticket.len() > 0xffff {
+        return Err("Ticket too long".to_string());
+    }
+    let mut out = Bytes::new_len(6 + msg.ticket.len());
+    out.update_raw(0, &msg.lifetime_hint.to_be_bytes());
+    out.update_raw(4, &(msg.ticket.len() as u16).to_be_bytes());
+    out.update(6, &msg.ticket);
+    Ok(out)
+}
+
+pub fn decode_new_session_ticket(body: &Bytes) -> Result<NewSessionTicket, String> {
+    if body.len() < 6 {
+        return Err("Truncated NewSessionTicket".to_string());
+    }
+    let lifetime_hint = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+    let len = u16::from_be_bytes([body[4], body[5]]) as usize;
+    if body.len() != 6 + len {
+        return Err("Invalid NewSessionTicket length".to_string());
+    }
+    Ok(NewSessionTicket { lifetime_hint, ticket: Bytes::from(&body[6..6 + len]) })
+}
+
+// The client of the session, as the server saw it:
+// anonymous(0), certificate_based(1) with the certificate type and chain,
+// or psk(2) with the PSK identity.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientIdentity {
+    Anonymous,
+    CertificateBased { certificate_type: u8, certificate_list: Bytes },
+    Psk(Bytes),
+}
+
+// struct {
+//     ProtocolVersion protocol_version;
+//     CipherSuite cipher_suite;
+//     CompressionMethod compression_method;
+//     opaque master_secret[48];
+//     ClientIdentity client_identity;
+//     uint32 timestamp;
+// } StatePlaintext;
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatePlaintext {
+    pub protocol_version: u16,
+    pub cipher_suite: u16,
+    pub compression_method: u8,
+    pub master_secret: Bytes,
+    pub client_identity: ClientIdentity,
+    pub timestamp: u32,
+}
+
+fn concat(a: &[u8], b: &[u8]) -> Bytes {
+    let mut out = Bytes::new_len(a.len() + b.len());
+    out.update_raw(0, a);
+    out.update_raw(a.len(), b);
+    out
+}
+
+pub fn encode_state(state: &StatePlaintext) -> Result<Bytes, String> {
+    if state.master_secret.len() != 48 {
+        return Err("Invalid master secret length".to_string());
+    }
+    let mut head = Bytes::new_len(53);
+    head.update_raw(0, &state.protocol_version.to_be_bytes());
+    head.update_raw(2, &state.cipher_suite.to_be_bytes());
+    head[4] = state.compression_method;
+    head.update(5, &state.master_secret);
+    let identity = match &state.client_identity {
+        ClientIdentity::Anonymous => Bytes::from(&[0u8][..]),
+        ClientIdentity::CertificateBased { certificate_type, certificate_list } => {
+            if certificate_list.len() >= 1 << 24 {
+                return Err("Certificate list too long".to_string());
+            }
+            let len = (certificate_list.len() as u32).to_be_bytes();
+            concat(&[1, *certificate_type, len[1], len[2], len[3]], &certificate_list[..])
+        }
+        ClientIdentity::Psk(identity) => {
+            if identity.len() > 0xffff {
+                return Err("PSK identity too long".to_string());
+            }
+            let len = (identity.len() as u16).to_be_bytes();
+            concat(&[2, len[0], len[1]], &identity[..])
+        }
+    };
+    let out = concat(&head[..], &identity[..]);
+    Ok(concat(&out[..], &state.timestamp.to_be_bytes()))
+}
+
+pub fn decode_state(input: &Bytes) -> Result<StatePlaintext, String> {
+    let err = || "Invalid ticket state".to_string();
+    if input.len() < 53 + 1 + 4 {
+        return Err(err());
+    }
+    let end = input.len() - 4;
+    let client_identity = match input[53] {
+        0 if end == 54 => ClientIdentity::Anonymous,
+        1 if end >= 58 => {
+            let len = u32::from_be_bytes([0, input[55], input[56], input[57]]) as usize;
+            if end != 58 + len {
+                return Err(err());
+            }
+            ClientIdentity::CertificateBased {
+                certificate_type: input[54],
+                certificate_list: Bytes::from(&input[58..end]),
+            }
+        }
+        2 if end >= 56 => {
+            let len = u16::from_be_bytes([input[54], input[55]]) as usize;
+            if end != 56 + len {
+                return Err(err());
+            }
+            ClientIdentity::Psk(Bytes::from(&input[56..end]))
+        }
+        _ => return Err(err()),
+    };
+    Ok(StatePlaintext {
+        protocol_version: u16::from_be_bytes([input[0], input[1]]),
+        cipher_suite: u16::from_be_bytes([input[2], input[3]]),
+        compression_method: input[4],
+        master_secret: Bytes::from(&input[5..53]),
+        client_identity,
+        timestamp: u32::from_be_bytes([input[end], input[end + 1], input[end + 2], input[end + 3]]),
+    })
+}
+
+// The server's ticket keys: a 16-byte name that tells them apart, an
+// AES-128 key and an HMAC-SHA-256 key.
+#[derive(Clone, Debug)]
+pub struct TicketKeys {
+    pub key_name: Bytes,
+    pub aes_key: Bytes,
+    pub hmac_key: Bytes,
+}
+
+const KEY_NAME_LEN: usize = 16;
+const IV_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+
+// The MAC covers key_name, IV and the length and bytes of encrypted_state.
+fn ticket_mac(keys: &TicketKeys, iv: &[u8], encrypted_state: &Bytes) -> Bytes {
+    let len = (encrypted_state.len() as u16).to_be_bytes();
+    let head = concat(&concat(&keys.key_name[..], iv)[..], &len);
+    hmac::<Sha256>(keys.hmac_key.clone(), concat(&head[..], &encrypted_state[..]))
+}
+
+// The recommended ticket (RFC 5077, section 4):
+//   struct {
+//       opaque key_name[16];
+//       opaque iv[16];
+//       opaque encrypted_state<0..2^16-1>;
+//       opaque mac[32];
+//   } ticket;
+// The state is encrypted with AES-128-CBC. RFC 5077 leaves the padding to
+// the server; this spec pads as PKCS #7 does.
+pub fn seal_ticket(keys: &TicketKeys, iv: Block, state: &StatePlaintext) -> Result<Bytes, String> {
+    if keys.key_name.len() != KEY_NAME_LEN {
+        return Err("Invalid key name length".to_string());
+    }
+    let plain_text = encode_state(state)?;
+    let padding = 16 - plain_text.len() % 16;
+    let mut padded = Bytes::new_len(plain_text.len() + padding);
+    padded.update(0, &plain_text);
+    for i in plain_text.len()..padded.len() {
+        padded[i] = padding as u8;
+    }
+    let encrypted_state = cbc_encrypt(keys.aes_key.clone(), iv, padded)?;
+    if encrypted_state.len() > 0xffff {
+        return Err("Ticket state too long".to_string());
+    }
+    let mac = ticket_mac(keys, &iv[..], &encrypted_state);
+
+    let len = (encrypted_state.len() as u16).to_be_bytes();
+    let head = concat(&concat(&keys.key_name[..], &iv[..])[..], &len);
+    Ok(concat(&concat(&head[..], &encrypted_state[..])[..], &mac[..]))
+}
+
+// Opens a ticket this server sealed. A ticket under another key name, or
+// with a bad MAC, is not an error of the handshake: the server just runs a
+// full one (RFC 5077, section 3.4). The MAC is checked before anything is
+// decrypted.
+pub fn open_ticket(keys: &TicketKeys, ticket: &Bytes) -> Result<StatePlaintext, String> {
+    let head = KEY_NAME_LEN + IV_LEN + 2;
+    if ticket.len() < head + MAC_LEN {
+        return Err("Invalid ticket".to_string());
+    }
+    if ticket[0..KEY_NAME_LEN] != keys.key_name[..] {
+        return Err("Unknown ticket key".to_string());
+    }
+    let iv = Block::from(&ticket[KEY_NAME_LEN..KEY_NAME_LEN + IV_LEN]);
+    let len = u16::from_be_bytes([ticket[head - 2], ticket[head - 1]]) as usize;
+    if ticket.len() != head + len + MAC_LEN {
+        return Err("Invalid ticket".to_string());
+    }
+    let encrypted_state = Bytes::from(&ticket[head..head + len]);
+    if ticket_mac(keys, &iv[..], &encrypted_state)[..] != ticket[head + len..ticket.len()] {
+        return Err("Invalid ticket MAC".to_string());
+    }
+    if len == 0 || len % 16 != 0 {
+        return Err("Invalid ticket".to_string());
+    }
+    let padded = cbc_decrypt(keys.aes_key.clone(), iv, encrypted_state)?;
+    let padding = padded[len - 1] as usize;
+    if padding == 0 || padding > 16 {
+        return Err("Invalid ticket padding".to_string());
+    }
+    for i in len - padding..len {
+        if padded[i] as usize != padding {
+            return Err("Invalid ticket padding".to_string());
+        }
+    }
+    decode_state(&Bytes::from(&padded[0..len - padding]))
+}
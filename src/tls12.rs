@@ -1,31 +1,662 @@
-#[derive(Debug, Copy, Clone)]
+use crate::certificate::CertificateType;
+use crate::graph;
+use crate::groups::NamedGroup;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProtocolOptions {
-    resuming: bool,
-    request_client_auth: bool,
-    perform_client_auth: bool,
-    dh_anon: bool,
-    rsa_kem: bool,
-    server_issues_ticket: bool,
+    pub(crate) resuming: bool,
+    pub(crate) client_auth: ClientAuth,
+    pub(crate) key_exchange: KeyExchange,
+    pub(crate) server_issues_ticket: bool,
+    pub(crate) renegotiation: Option<Peer>,
+    pub(crate) server_stapled_ocsp: bool,
+    pub(crate) psk_identity_hint: bool,
+    pub(crate) npn: bool,
+    pub(crate) false_start: bool,
+    pub(crate) client_offers_ticket: bool,
+    pub(crate) resume_via_ticket: bool,
+    pub(crate) server_accepts_resumption: bool,
+    pub(crate) secure_renegotiation: bool,
+    pub(crate) allow_insecure_renegotiation: bool,
+    pub(crate) group: Option<NamedGroup>,
+    pub(crate) extended_master_secret: bool,
+    pub(crate) client_certificate_url: bool,
+    pub(crate) supplemental_data: Option<Peer>,
+    pub(crate) encrypt_then_mac: bool,
+    pub(crate) heartbeat: bool,
+    pub(crate) compression: bool,
+    pub(crate) client_cert_type: CertificateType,
+    pub(crate) server_cert_type: CertificateType,
 }
 
 impl Default for ProtocolOptions {
     fn default() -> Self {
         ProtocolOptions {
             resuming: false,
-            request_client_auth: false,
-            perform_client_auth: false,
-            dh_anon: false,
-            rsa_kem: false,
+            client_auth: ClientAuth::NotRequested,
+            key_exchange: KeyExchange::EcdheRsa,
             server_issues_ticket: false,
+            renegotiation: None,
+            server_stapled_ocsp: false,
+            psk_identity_hint: false,
+            npn: false,
+            false_start: false,
+            client_offers_ticket: false,
+            resume_via_ticket: false,
+            server_accepts_resumption: true,
+            secure_renegotiation: false,
+            allow_insecure_renegotiation: false,
+            group: None,
+            extended_master_secret: false,
+            client_certificate_url: false,
+            supplemental_data: None,
+            encrypt_then_mac: false,
+            heartbeat: false,
+            compression: false,
+            client_cert_type: CertificateType::X509,
+            server_cert_type: CertificateType::X509,
+        }
+    }
+}
+
+/// The key exchange (and server authentication) named by the cipher suite.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyExchange {
+    EcdheRsa,
+    EcdheEcdsa,
+    DheRsa,
+    DheDss,
+    RsaKem,
+    DhAnon,
+    StaticDh,
+    Psk,
+    DhePsk,
+    RsaPsk,
+    Srp,
+    SrpRsa,
+    SrpDss,
+}
+
+impl KeyExchange {
+    pub(crate) const ALL: [KeyExchange; 13] = [
+        KeyExchange::EcdheRsa,
+        KeyExchange::EcdheEcdsa,
+        KeyExchange::DheRsa,
+        KeyExchange::DheDss,
+        KeyExchange::RsaKem,
+        KeyExchange::DhAnon,
+        KeyExchange::StaticDh,
+        KeyExchange::Psk,
+        KeyExchange::DhePsk,
+        KeyExchange::RsaPsk,
+        KeyExchange::Srp,
+        KeyExchange::SrpRsa,
+        KeyExchange::SrpDss,
+    ];
+
+    /// Whether the server sends a Certificate.
+    pub fn server_certificate(self) -> bool {
+        use KeyExchange::*;
+        !matches!(self, DhAnon | Psk | DhePsk | Srp)
+    }
+
+    /// The pre-shared key cipher suites of RFC 4279.
+    pub fn is_psk(self) -> bool {
+        matches!(self, KeyExchange::Psk | KeyExchange::DhePsk | KeyExchange::RsaPsk)
+    }
+
+    /// Whether the key exchange is ephemeral Diffie-Hellman.
+    pub fn forward_secret(self) -> bool {
+        use KeyExchange::*;
+        matches!(self, EcdheRsa | EcdheEcdsa | DheRsa | DheDss | DhAnon | DhePsk)
+    }
+
+    /// The SRP cipher suites of RFC 5054; the certificate, if any, only
+    /// authenticates the server.
+    pub fn is_srp(self) -> bool {
+        matches!(self, KeyExchange::Srp | KeyExchange::SrpRsa | KeyExchange::SrpDss)
+    }
+}
+
+/// How client authentication plays out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClientAuth {
+    /// The server sends no CertificateRequest.
+    NotRequested,
+    /// The client answers the CertificateRequest with an empty Certificate
+    /// and sends no CertificateVerify (RFC 5246, 7.4.6).
+    Declined,
+    /// The client sends its certificate and proves possession of the key.
+    Performed,
+    /// The client certificate carries its Diffie-Hellman key, so the
+    /// ClientKeyExchange is empty and there is no CertificateVerify (RFC 5246,
+    /// 7.4.7.2 and 7.4.8).
+    FixedDh,
+}
+
+impl ClientAuth {
+    pub(crate) const ALL: [ClientAuth; 4] = [
+        ClientAuth::NotRequested,
+        ClientAuth::Declined,
+        ClientAuth::Performed,
+        ClientAuth::FixedDh,
+    ];
+
+    pub fn requested(self) -> bool {
+        self != ClientAuth::NotRequested
+    }
+}
+
+/// Option combinations that don't describe a possible TLS 1.2 handshake.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OptionsError {
+    /// An anonymous server must not request a client certificate (RFC 5246,
+    /// 7.4.4).
+    AnonymousClientAuth,
+    /// There is no certificate to staple an OCSP response to.
+    StapleWithoutCertificate,
+    /// PSK cipher suites authenticate the client through the key; it can't
+    /// also be asked for a certificate (RFC 4279, 2).
+    PskClientAuth,
+    /// Only PSK key exchanges carry a PSK identity hint.
+    IdentityHintWithoutPsk,
+    /// SRP authenticates the client through the password verifier, not a
+    /// certificate (RFC 5054, 2.8).
+    SrpClientAuth,
+    /// A fixed-DH client certificate needs a static DH server key to agree
+    /// with.
+    FixedDhWithoutStaticDh,
+    /// False Start is only safe with a forward-secret key exchange (RFC
+    /// 7918, 4).
+    FalseStartWithoutForwardSecrecy,
+    /// The server can only use the SessionTicket extension, to issue a
+    /// ticket or to resume from one, if the client offered it (RFC 5077,
+    /// 3.2).
+    TicketNotOffered,
+    /// Resuming from a ticket needs a resumption in the first place.
+    TicketWithoutResumption,
+    /// The group doesn't fit the ServerKeyExchange: ECDHE takes an elliptic
+    /// curve and DHE a finite field group, and the other key exchanges send
+    /// none at all.
+    UnsuitableGroup,
+    /// A certificate type was negotiated for a peer that sends no
+    /// Certificate; without one the extension goes unanswered (RFC 7250,
+    /// 4.1).
+    CertificateTypeWithoutCertificate,
+    /// A CertificateURL stands in for the client's certificate chain (RFC
+    /// 6066, 5), so there must be one to send.
+    CertificateUrlWithoutCertificate,
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::AnonymousClientAuth => {
+                write!(f, "client authentication requested with anonymous key exchange")
+            }
+            OptionsError::StapleWithoutCertificate => {
+                write!(f, "OCSP response stapled without a server certificate")
+            }
+            OptionsError::PskClientAuth => {
+                write!(f, "client certificate requested with a PSK key exchange")
+            }
+            OptionsError::IdentityHintWithoutPsk => {
+                write!(f, "PSK identity hint without a PSK key exchange")
+            }
+            OptionsError::SrpClientAuth => {
+                write!(f, "client certificate requested with an SRP key exchange")
+            }
+            OptionsError::FixedDhWithoutStaticDh => {
+                write!(f, "fixed-DH client authentication without a static DH key exchange")
+            }
+            OptionsError::FalseStartWithoutForwardSecrecy => {
+                write!(f, "False Start without a forward-secret key exchange")
+            }
+            OptionsError::TicketNotOffered => {
+                write!(f, "session ticket used without the client offering the extension")
+            }
+            OptionsError::TicketWithoutResumption => {
+                write!(f, "resumption via ticket without resuming")
+            }
+            OptionsError::UnsuitableGroup => {
+                write!(f, "named group unsuitable for the key exchange")
+            }
+            OptionsError::CertificateTypeWithoutCertificate => {
+                write!(f, "certificate type negotiated without a Certificate to carry it")
+            }
+            OptionsError::CertificateUrlWithoutCertificate => {
+                write!(f, "CertificateURL without a client certificate to point to")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
+impl ProtocolOptions {
+    pub fn builder() -> ProtocolOptionsBuilder { ProtocolOptionsBuilder::default() }
+
+    /// Walk the handshake these options describe, from the ClientHello up to
+    /// the established connection.
+    pub fn iter_handshake(self) -> HandshakeIter {
+        HandshakeIter { state: State::default(), opts: self }
+    }
+
+    /// Walk the renegotiation these options describe, from the connection
+    /// `iter_handshake` established up to the one it establishes in turn.
+    /// Nothing if the options call for no renegotiation.
+    pub fn iter_renegotiation(self) -> HandshakeIter {
+        HandshakeIter { state: step(State::Term, self), opts: self }
+    }
+
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.key_exchange.is_psk() && self.client_auth.requested() {
+            return Err(OptionsError::PskClientAuth);
+        }
+        if self.key_exchange.is_srp() && self.client_auth.requested() {
+            return Err(OptionsError::SrpClientAuth);
+        }
+        if self.client_auth == ClientAuth::FixedDh && self.key_exchange != KeyExchange::StaticDh {
+            return Err(OptionsError::FixedDhWithoutStaticDh);
+        }
+        if self.false_start && !self.key_exchange.forward_secret() {
+            return Err(OptionsError::FalseStartWithoutForwardSecrecy);
+        }
+        if (self.server_issues_ticket || self.resume_via_ticket) && !self.client_offers_ticket {
+            return Err(OptionsError::TicketNotOffered);
+        }
+        if self.resume_via_ticket && !self.resuming {
+            return Err(OptionsError::TicketWithoutResumption);
+        }
+        if self.psk_identity_hint && !self.key_exchange.is_psk() {
+            return Err(OptionsError::IdentityHintWithoutPsk);
+        }
+        if !self.key_exchange.server_certificate() && self.client_auth.requested() {
+            return Err(OptionsError::AnonymousClientAuth);
+        }
+        if self.server_stapled_ocsp && !self.key_exchange.server_certificate() {
+            return Err(OptionsError::StapleWithoutCertificate);
         }
+        if self.group.is_some_and(|g| !g.suits(self.key_exchange)) {
+            return Err(OptionsError::UnsuitableGroup);
+        }
+        let raw = CertificateType::RawPublicKey;
+        if (self.server_cert_type == raw && !self.key_exchange.server_certificate())
+            || (self.client_cert_type == raw && !self.client_auth.requested())
+        {
+            return Err(OptionsError::CertificateTypeWithoutCertificate);
+        }
+        let sends_chain = matches!(self.client_auth, ClientAuth::Performed | ClientAuth::FixedDh);
+        if self.client_certificate_url && !sends_chain {
+            return Err(OptionsError::CertificateUrlWithoutCertificate);
+        }
+        Ok(())
+    }
+
+    /// Every combination of options that `validate` accepts.
+    pub fn all_valid() -> impl Iterator<Item = ProtocolOptions> {
+        ProtocolOptions::combinations_where(|opts| opts.validate().is_ok())
+    }
+
+    /// The combinations of option values that `keep` accepts, one field at
+    /// a time. `keep` sees each prefix with the fields still to come at
+    /// their defaults, and what it refuses is not expanded further. Every
+    /// field a check of `validate` depends on comes before the one it
+    /// constrains, so a prefix `validate` refuses has no valid completion.
+    ///
+    /// The group, encrypt_then_mac, heartbeat and certificate types are left
+    /// unset, since none changes the handshake messages or what the peers do
+    /// with their keys. Compression doesn't either, but is enumerated so that
+    /// property checks see the DEFLATE configurations too.
+    fn combinations_where(
+        keep: fn(&ProtocolOptions) -> bool,
+    ) -> impl Iterator<Item = ProtocolOptions> {
+        let b = &[false, true];
+        let peers = &[None, Some(Peer::Server), Some(Peer::Client)];
+        let mut all: graph::Combinations<Self> = Box::new(Some(Self::default()).into_iter());
+        all = graph::expand(all, &KeyExchange::ALL, |o, v| o.key_exchange = v, keep);
+        all = graph::expand(all, &ClientAuth::ALL, |o, v| o.client_auth = v, keep);
+        all = graph::expand(all, peers, |o, v| o.renegotiation = v, keep);
+        all = graph::expand(all, b, |o, v| o.resuming = v, keep);
+        all = graph::expand(all, b, |o, v| o.server_accepts_resumption = v, keep);
+        all = graph::expand(all, b, |o, v| o.client_offers_ticket = v, keep);
+        all = graph::expand(all, b, |o, v| o.resume_via_ticket = v, keep);
+        all = graph::expand(all, b, |o, v| o.server_issues_ticket = v, keep);
+        all = graph::expand(all, b, |o, v| o.server_stapled_ocsp = v, keep);
+        all = graph::expand(all, b, |o, v| o.psk_identity_hint = v, keep);
+        all = graph::expand(all, b, |o, v| o.npn = v, keep);
+        all = graph::expand(all, b, |o, v| o.false_start = v, keep);
+        all = graph::expand(all, b, |o, v| o.secure_renegotiation = v, keep);
+        all = graph::expand(all, b, |o, v| o.allow_insecure_renegotiation = v, keep);
+        all = graph::expand(all, b, |o, v| o.extended_master_secret = v, keep);
+        all = graph::expand(all, b, |o, v| o.client_certificate_url = v, keep);
+        all = graph::expand(all, peers, |o, v| o.supplemental_data = v, keep);
+        graph::expand(all, b, |o, v| o.compression = v, keep)
+    }
+
+    /// Whether this is an abbreviated handshake: the client offered a session
+    /// and the server took it up.
+    pub(crate) fn abbreviated(&self) -> bool {
+        self.resuming && self.server_accepts_resumption
+    }
+
+    /// Whether the established connection may be renegotiated at all. Without
+    /// RFC 5746 the new handshake can't be tied to the old one, so a careful
+    /// peer refuses (RFC 5746, 4.2 and 4.4).
+    fn renegotiation_permitted(&self) -> bool {
+        self.secure_renegotiation || self.allow_insecure_renegotiation
+    }
+
+    /// The server only sends a NewSessionTicket if it echoed the client's
+    /// SessionTicket extension in its ServerHello (RFC 5077, 3.2).
+    pub(crate) fn issues_ticket(&self) -> bool {
+        self.server_issues_ticket && self.client_offers_ticket
+    }
+
+    /// How many NewSessionTickets the server sends: at most one, where
+    /// `tls13::ProtocolOptions::tickets` may be more.
+    pub fn tickets(&self) -> u8 {
+        self.issues_ticket() as u8
+    }
+
+    /// Whether the server sends a ServerKeyExchange. RSA key transport and
+    /// static DH take the server key from its certificate. PSK and RSA_PSK
+    /// only send one to carry an identity hint; DHE_PSK always has DH
+    /// parameters to send (RFC 4279, 2-4), just as SRP always sends its
+    /// group and salt (RFC 5054, 2.5.3).
+    fn server_key_exchange(&self) -> bool {
+        match self.key_exchange {
+            KeyExchange::RsaKem | KeyExchange::StaticDh => false,
+            KeyExchange::Psk | KeyExchange::RsaPsk => self.psk_identity_hint,
+            _ => true,
+        }
+    }
+}
+
+impl graph::Protocol for ProtocolOptions {
+    type State = State;
+
+    const NAME: &'static str = "tls12";
+
+    fn step(self, st: State) -> State {
+        step(st, self)
+    }
+
+    fn sends(st: State) -> Option<(Peer, String)> {
+        st.sends().map(|(peer, msg)| (peer, format!("{:?}", msg)))
+    }
+
+    fn states() -> Vec<State> {
+        use State::*;
+        let mut all = vec![
+            ClientSendsClientHello,
+            ServerSendsServerHello,
+            ServerSendsSupplementalData,
+            ServerSendsCertificate,
+            ServerSendsCertificateStatus,
+            ServerSendsServerKeyExchange,
+            ServerSendsCertificateRequest,
+            ServerSendsServerHelloDone,
+            ClientSendsSupplementalData,
+            ClientSendsCertificate,
+            ClientSendsCertificateUrl,
+            ClientSendsEmptyCertificate,
+            ClientSendsClientKeyExchange,
+            ClientSendsEmptyClientKeyExchange,
+            ClientSendsCertificateVerify,
+            ClientSendsChangeCipherSpec,
+            ClientSendsNextProtocol,
+            ClientSendsFinished,
+            ClientSendsApplicationData,
+            ServerSendsNewSessionTicket,
+            ServerSendsChangeCipherSpec,
+            ServerSendsFinished,
+            Term,
+            ServerSendsHelloRequest,
+            ClientRefusesRenegotiation,
+        ];
+        for desc in &AlertDescription::ALL {
+            all.push(SendsAlert(Peer::Client, *desc));
+            all.push(SendsAlert(Peer::Server, *desc));
+            all.push(Aborted(*desc));
+        }
+        all
+    }
+
+    fn combinations() -> Vec<Self> {
+        ProtocolOptions::all_valid().collect()
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("key_exchange", format!("{:?}", self.key_exchange)),
+            ("client_auth", format!("{:?}", self.client_auth)),
+            ("renegotiation", format!("{:?}", self.renegotiation)),
+            ("resuming", self.resuming.to_string()),
+            ("server_accepts_resumption", self.server_accepts_resumption.to_string()),
+            ("client_offers_ticket", self.client_offers_ticket.to_string()),
+            ("resume_via_ticket", self.resume_via_ticket.to_string()),
+            ("server_issues_ticket", self.server_issues_ticket.to_string()),
+            ("server_stapled_ocsp", self.server_stapled_ocsp.to_string()),
+            ("psk_identity_hint", self.psk_identity_hint.to_string()),
+            ("npn", self.npn.to_string()),
+            ("false_start", self.false_start.to_string()),
+            ("secure_renegotiation", self.secure_renegotiation.to_string()),
+            ("allow_insecure_renegotiation", self.allow_insecure_renegotiation.to_string()),
+            ("extended_master_secret", self.extended_master_secret.to_string()),
+            ("client_certificate_url", self.client_certificate_url.to_string()),
+            ("supplemental_data", format!("{:?}", self.supplemental_data)),
+            ("compression", self.compression.to_string()),
+        ]
+    }
+}
+
+/// Builds `ProtocolOptions`, refusing inconsistent combinations.
+///
+/// ```
+/// use hacspecs::tls12::{ClientAuth, KeyExchange, OptionsError, ProtocolOptions};
+///
+/// let opts = ProtocolOptions::builder().resuming(true).build();
+/// assert!(opts.is_ok());
+/// let opts = ProtocolOptions::builder()
+///     .key_exchange(KeyExchange::DhAnon)
+///     .client_auth(ClientAuth::Performed)
+///     .build();
+/// assert_eq!(opts.unwrap_err(), OptionsError::AnonymousClientAuth);
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ProtocolOptionsBuilder {
+    opts: ProtocolOptions,
+}
+
+impl ProtocolOptionsBuilder {
+    pub fn resuming(mut self, resuming: bool) -> Self {
+        self.opts.resuming = resuming;
+        self
+    }
+
+    /// Whether the server resumes the session the client offered. If it
+    /// doesn't, it carries on with a full handshake (RFC 5246, 7.4.1.2).
+    pub fn server_accepts_resumption(mut self, server_accepts_resumption: bool) -> Self {
+        self.opts.server_accepts_resumption = server_accepts_resumption;
+        self
+    }
+
+    pub fn client_auth(mut self, client_auth: ClientAuth) -> Self {
+        self.opts.client_auth = client_auth;
+        self
+    }
+
+    pub fn key_exchange(mut self, key_exchange: KeyExchange) -> Self {
+        self.opts.key_exchange = key_exchange;
+        self
+    }
+
+    /// Whether the client sends the SessionTicket extension, empty or with a
+    /// ticket to resume from.
+    pub fn client_offers_ticket(mut self, client_offers_ticket: bool) -> Self {
+        self.opts.client_offers_ticket = client_offers_ticket;
+        self
+    }
+
+    /// Whether the session being resumed was carried by a ticket (RFC 5077)
+    /// rather than looked up by session ID. The messages exchanged are the
+    /// same either way.
+    pub fn resume_via_ticket(mut self, resume_via_ticket: bool) -> Self {
+        self.opts.resume_via_ticket = resume_via_ticket;
+        self
+    }
+
+    pub fn server_issues_ticket(mut self, server_issues_ticket: bool) -> Self {
+        self.opts.server_issues_ticket = server_issues_ticket;
+        self
+    }
+
+    /// Which peer, if any, starts a new handshake once the connection is
+    /// established.
+    pub fn renegotiation(mut self, renegotiation: Option<Peer>) -> Self {
+        self.opts.renegotiation = renegotiation;
+        self
+    }
+
+    /// Whether both peers sent the renegotiation_info extension, binding any
+    /// renegotiation to the connection it happens on (RFC 5746).
+    pub fn secure_renegotiation(mut self, secure_renegotiation: bool) -> Self {
+        self.opts.secure_renegotiation = secure_renegotiation;
+        self
+    }
+
+    /// Whether the peers renegotiate even without secure renegotiation, as
+    /// implementations predating RFC 5746 do. This is the handshake the
+    /// renegotiation attack (CVE-2009-3555) relies on.
+    pub fn allow_insecure_renegotiation(mut self, allow_insecure_renegotiation: bool) -> Self {
+        self.opts.allow_insecure_renegotiation = allow_insecure_renegotiation;
+        self
+    }
+
+    /// Whether the server answers a status_request with a CertificateStatus
+    /// (RFC 6066, 8).
+    pub fn server_stapled_ocsp(mut self, server_stapled_ocsp: bool) -> Self {
+        self.opts.server_stapled_ocsp = server_stapled_ocsp;
+        self
+    }
+
+    pub fn psk_identity_hint(mut self, psk_identity_hint: bool) -> Self {
+        self.opts.psk_identity_hint = psk_identity_hint;
+        self
+    }
+
+    /// The group the server chose for its ServerKeyExchange, as
+    /// `groups::choose_server_group` picks it. `None` if the key exchange
+    /// sends none or the server uses DH parameters of its own.
+    pub fn group(mut self, group: Option<NamedGroup>) -> Self {
+        self.opts.group = group;
+        self
+    }
+
+    /// Whether both peers sent the extended_master_secret extension, so the
+    /// master secret is bound to the handshake that made it rather than
+    /// just its randoms (RFC 7627). This shuts out the triple handshake
+    /// attack.
+    pub fn extended_master_secret(mut self, extended_master_secret: bool) -> Self {
+        self.opts.extended_master_secret = extended_master_secret;
+        self
+    }
+
+    /// Whether the client, having negotiated client_certificate_url, sends
+    /// a CertificateURL in place of its Certificate (RFC 6066, 5): links
+    /// to its chain and their SHA-1 hashes, for the server to fetch. This
+    /// spares a constrained client sending the chain itself.
+    pub fn client_certificate_url(mut self, client_certificate_url: bool) -> Self {
+        self.opts.client_certificate_url = client_certificate_url;
+        self
+    }
+
+    /// The peer that sends a SupplementalData message (RFC 4680), as the
+    /// hello extensions of its formats negotiated it: the server right
+    /// after its ServerHello, or the client first in its second flight, as
+    /// for the user mapping of RFC 4681. Abbreviated handshakes carry none.
+    pub fn supplemental_data(mut self, supplemental_data: Option<Peer>) -> Self {
+        self.opts.supplemental_data = supplemental_data;
+        self
+    }
+
+    /// Whether both peers sent the encrypt_then_mac extension, so the
+    /// records of a CBC suite are MACed after encryption (RFC 7366); see
+    /// `aescbc::Construction`. Other suites ignore it.
+    pub fn encrypt_then_mac(mut self, encrypt_then_mac: bool) -> Self {
+        self.opts.encrypt_then_mac = encrypt_then_mac;
+        self
+    }
+
+    /// Whether both peers sent the heartbeat extension (RFC 6520), so that
+    /// heartbeat records may follow the handshake as their modes allow; see
+    /// `heartbeat::Heartbeat`.
+    pub fn heartbeat(mut self, heartbeat: bool) -> Self {
+        self.opts.heartbeat = heartbeat;
+        self
+    }
+
+    /// Whether the server picked the DEFLATE compression method (RFC 3749)
+    /// rather than null, so records are compressed before they are
+    /// protected. That leaks secrets through the records' lengths (CRIME);
+    /// see `properties::no_compression`.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.opts.compression = compression;
+        self
+    }
+
+    /// What the client's Certificate carries, as the client_certificate_type
+    /// extension negotiated it: an X.509 chain, or a raw public key (RFC
+    /// 7250). Either way the messages are the same; only their contents and
+    /// size differ, as `certificate::certificate_len` has it.
+    pub fn client_cert_type(mut self, client_cert_type: CertificateType) -> Self {
+        self.opts.client_cert_type = client_cert_type;
+        self
+    }
+
+    /// What the server's Certificate carries, as server_certificate_type
+    /// negotiated it.
+    pub fn server_cert_type(mut self, server_cert_type: CertificateType) -> Self {
+        self.opts.server_cert_type = server_cert_type;
+        self
+    }
+
+    /// Whether Next Protocol Negotiation (draft-agl-tls-nextprotoneg) was
+    /// negotiated, so that the client sends a NextProtocol message.
+    pub fn npn(mut self, npn: bool) -> Self {
+        self.opts.npn = npn;
+        self
+    }
+
+    /// Whether the client sends application data right after its Finished,
+    /// without waiting for the server's (RFC 7918). This only changes full
+    /// handshakes; in an abbreviated one the client finishes last anyway.
+    pub fn false_start(mut self, false_start: bool) -> Self {
+        self.opts.false_start = false_start;
+        self
+    }
+
+    pub fn build(self) -> Result<ProtocolOptions, OptionsError> {
+        self.opts.validate()?;
+        Ok(self.opts)
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MessageType {
+    HelloRequest,
     ClientHello,
     ServerHello,
+    SupplementalData,
     Certificate,
+    CertificateUrl,
+    CertificateStatus,
     ServerKeyExchange,
     CertificateRequest,
     ServerHelloDone,
@@ -33,59 +664,352 @@ pub enum MessageType {
     CertificateVerify,
     NewSessionTicket,
     ChangeCipherSpec,
+    NextProtocol,
     Finished,
+    Alert,
+    /// Not a handshake message; marks where application data may flow.
+    ApplicationData,
+}
+
+/// The message's name in the RFCs, e.g. `server_hello_done`. The names of
+/// ChangeCipherSpec, Alert and ApplicationData are those of their record
+/// content types (RFC 5246, 6.2.1).
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MessageType::*;
+        let name = match self {
+            HelloRequest => "hello_request",
+            ClientHello => "client_hello",
+            ServerHello => "server_hello",
+            SupplementalData => "supplemental_data",
+            Certificate => "certificate",
+            CertificateUrl => "certificate_url",
+            CertificateStatus => "certificate_status",
+            ServerKeyExchange => "server_key_exchange",
+            CertificateRequest => "certificate_request",
+            ServerHelloDone => "server_hello_done",
+            ClientKeyExchange => "client_key_exchange",
+            CertificateVerify => "certificate_verify",
+            NewSessionTicket => "new_session_ticket",
+            ChangeCipherSpec => "change_cipher_spec",
+            NextProtocol => "next_protocol",
+            Finished => "finished",
+            Alert => "alert",
+            ApplicationData => "application_data",
+        };
+        f.write_str(name)
+    }
+}
+
+impl MessageType {
+    pub(crate) const ALL: [MessageType; 18] = {
+        use MessageType::*;
+        [
+            HelloRequest,
+            ClientHello,
+            ServerHello,
+            SupplementalData,
+            Certificate,
+            CertificateUrl,
+            CertificateStatus,
+            ServerKeyExchange,
+            CertificateRequest,
+            ServerHelloDone,
+            ClientKeyExchange,
+            CertificateVerify,
+            NewSessionTicket,
+            ChangeCipherSpec,
+            NextProtocol,
+            Finished,
+            Alert,
+            ApplicationData,
+        ]
+    };
+
+    /// Whether the message goes into the handshake hash that Finished
+    /// verifies (RFC 5246, 7.4.9). ChangeCipherSpec and alerts aren't
+    /// handshake messages, and HelloRequest is left out explicitly.
+    pub fn in_handshake_hash(self) -> bool {
+        use MessageType::*;
+        !matches!(self, HelloRequest | ChangeCipherSpec | Alert | ApplicationData)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlertLevel {
+    Warning,
+    Fatal,
+}
+
+/// The alerts of RFC 5246, 7.2, and those the extensions and TLS 1.3 add
+/// (RFC 6066, 7301, 7507 and 8446).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlertDescription {
+    CloseNotify,
+    UnexpectedMessage,
+    BadRecordMac,
+    DecryptionFailed,
+    RecordOverflow,
+    DecompressionFailure,
+    HandshakeFailure,
+    NoCertificate,
+    BadCertificate,
+    UnsupportedCertificate,
+    CertificateRevoked,
+    CertificateExpired,
+    CertificateUnknown,
+    IllegalParameter,
+    UnknownCa,
+    AccessDenied,
+    DecodeError,
+    DecryptError,
+    ExportRestriction,
+    ProtocolVersion,
+    InsufficientSecurity,
+    InternalError,
+    UserCanceled,
+    NoRenegotiation,
+    UnsupportedExtension,
+    InappropriateFallback,
+    MissingExtension,
+    CertificateUnobtainable,
+    UnrecognizedName,
+    BadCertificateStatusResponse,
+    BadCertificateHashValue,
+    UnknownPskIdentity,
+    CertificateRequired,
+    NoApplicationProtocol,
+    EchRequired,
+}
+
+impl fmt::Display for AlertDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use AlertDescription::*;
+        let name = match self {
+            CloseNotify => "close_notify",
+            UnexpectedMessage => "unexpected_message",
+            BadRecordMac => "bad_record_mac",
+            DecryptionFailed => "decryption_failed_RESERVED",
+            RecordOverflow => "record_overflow",
+            DecompressionFailure => "decompression_failure",
+            HandshakeFailure => "handshake_failure",
+            NoCertificate => "no_certificate_RESERVED",
+            BadCertificate => "bad_certificate",
+            UnsupportedCertificate => "unsupported_certificate",
+            CertificateRevoked => "certificate_revoked",
+            CertificateExpired => "certificate_expired",
+            CertificateUnknown => "certificate_unknown",
+            IllegalParameter => "illegal_parameter",
+            UnknownCa => "unknown_ca",
+            AccessDenied => "access_denied",
+            DecodeError => "decode_error",
+            DecryptError => "decrypt_error",
+            ExportRestriction => "export_restriction_RESERVED",
+            ProtocolVersion => "protocol_version",
+            InsufficientSecurity => "insufficient_security",
+            InternalError => "internal_error",
+            UserCanceled => "user_canceled",
+            NoRenegotiation => "no_renegotiation",
+            UnsupportedExtension => "unsupported_extension",
+            InappropriateFallback => "inappropriate_fallback",
+            MissingExtension => "missing_extension",
+            CertificateUnobtainable => "certificate_unobtainable_RESERVED",
+            UnrecognizedName => "unrecognized_name",
+            BadCertificateStatusResponse => "bad_certificate_status_response",
+            BadCertificateHashValue => "bad_certificate_hash_value_RESERVED",
+            UnknownPskIdentity => "unknown_psk_identity",
+            CertificateRequired => "certificate_required",
+            NoApplicationProtocol => "no_application_protocol",
+            EchRequired => "ech_required",
+        };
+        f.write_str(name)
+    }
+}
+
+impl AlertDescription {
+    pub(crate) const ALL: [AlertDescription; 35] = {
+        use AlertDescription::*;
+        [
+            CloseNotify,
+            UnexpectedMessage,
+            BadRecordMac,
+            DecryptionFailed,
+            RecordOverflow,
+            DecompressionFailure,
+            HandshakeFailure,
+            NoCertificate,
+            BadCertificate,
+            UnsupportedCertificate,
+            CertificateRevoked,
+            CertificateExpired,
+            CertificateUnknown,
+            IllegalParameter,
+            UnknownCa,
+            AccessDenied,
+            DecodeError,
+            DecryptError,
+            ExportRestriction,
+            ProtocolVersion,
+            InsufficientSecurity,
+            InternalError,
+            UserCanceled,
+            NoRenegotiation,
+            UnsupportedExtension,
+            InappropriateFallback,
+            MissingExtension,
+            CertificateUnobtainable,
+            UnrecognizedName,
+            BadCertificateStatusResponse,
+            BadCertificateHashValue,
+            UnknownPskIdentity,
+            CertificateRequired,
+            NoApplicationProtocol,
+            EchRequired,
+        ]
+    };
+
+    /// The level the alert is sent at. Only close_notify, user_canceled and
+    /// no_renegotiation are warnings; everything else is fatal.
+    pub fn level(self) -> AlertLevel {
+        use AlertDescription::*;
+        match self {
+            CloseNotify | UserCanceled | NoRenegotiation => AlertLevel::Warning,
+            _ => AlertLevel::Fatal,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Peer {
     Server,
     Client,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Peer::Server => f.write_str("server"),
+            Peer::Client => f.write_str("client"),
+        }
+    }
+}
+
+impl Peer {
+    pub fn other(self) -> Peer {
+        match self {
+            Peer::Server => Peer::Client,
+            Peer::Client => Peer::Server,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum State {
+    #[default]
     ClientSendsClientHello,
     ServerSendsServerHello,
+    ServerSendsSupplementalData,
     ServerSendsCertificate,
+    ServerSendsCertificateStatus,
     ServerSendsServerKeyExchange,
     ServerSendsCertificateRequest,
     ServerSendsServerHelloDone,
+    ClientSendsSupplementalData,
     ClientSendsCertificate,
+    /// The client sends URLs of its certificates for the server to fetch
+    /// in place of the certificates themselves (RFC 6066, 5).
+    ClientSendsCertificateUrl,
+    ClientSendsEmptyCertificate,
     ClientSendsClientKeyExchange,
+    ClientSendsEmptyClientKeyExchange,
     ClientSendsCertificateVerify,
     ClientSendsChangeCipherSpec,
+    ClientSendsNextProtocol,
     ClientSendsFinished,
+    ClientSendsApplicationData,
     ServerSendsNewSessionTicket,
     ServerSendsChangeCipherSpec,
     ServerSendsFinished,
+    /// The handshake is complete and the connection established.
     Term,
+    ServerSendsHelloRequest,
+    /// The client answers a HelloRequest with a no_renegotiation warning and
+    /// the connection carries on as it was.
+    ClientRefusesRenegotiation,
+    SendsAlert(Peer, AlertDescription),
+    /// The connection was torn down by an alert.
+    Aborted(AlertDescription),
 }
 
-impl Default for State {
-    fn default() -> Self { State::ClientSendsClientHello }
+/// The peer and the RFC name of what it sends, e.g. `server server_hello`.
+/// Empty messages are marked as such, and alerts carry their description.
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use State::*;
+        match *self {
+            Term => f.write_str("established"),
+            Aborted(desc) => write!(f, "aborted ({})", desc),
+            SendsAlert(peer, desc) => write!(f, "{} alert ({})", peer, desc),
+            ClientRefusesRenegotiation => {
+                write!(f, "client alert ({})", AlertDescription::NoRenegotiation)
+            }
+            ClientSendsEmptyCertificate | ClientSendsEmptyClientKeyExchange => {
+                let (peer, msg) = self.sends().unwrap();
+                write!(f, "{} {} (empty)", peer, msg)
+            }
+            _ => {
+                let (peer, msg) = self.sends().unwrap();
+                write!(f, "{} {}", peer, msg)
+            }
+        }
+    }
 }
 
 impl State {
-    pub fn sends(self) -> (Peer, MessageType) {
+    pub fn sends(self) -> Option<(Peer, MessageType)> {
         use State::*;
         match self {
-            ClientSendsClientHello => (Peer::Client, MessageType::ClientHello),
-            ServerSendsServerHello => (Peer::Server, MessageType::ServerHello),
-            ServerSendsCertificate => (Peer::Server, MessageType::Certificate),
-            ServerSendsServerKeyExchange => (Peer::Server, MessageType::ServerKeyExchange),
-            ServerSendsCertificateRequest => (Peer::Server, MessageType::CertificateRequest),
-            ServerSendsServerHelloDone => (Peer::Server, MessageType::ServerHelloDone),
-            ClientSendsCertificate => (Peer::Client, MessageType::Certificate),
-            ClientSendsClientKeyExchange => (Peer::Client, MessageType::ClientKeyExchange),
-            ClientSendsCertificateVerify => (Peer::Client, MessageType::CertificateVerify),
-            ClientSendsChangeCipherSpec => (Peer::Client, MessageType::ChangeCipherSpec),
-            ClientSendsFinished => (Peer::Client, MessageType::Finished),
-            ServerSendsNewSessionTicket => (Peer::Server, MessageType::NewSessionTicket),
-            ServerSendsChangeCipherSpec => (Peer::Server, MessageType::ChangeCipherSpec),
-            ServerSendsFinished => (Peer::Server, MessageType::Finished),
-            Term => panic!(),
+            ClientSendsClientHello => Some((Peer::Client, MessageType::ClientHello)),
+            ServerSendsServerHello => Some((Peer::Server, MessageType::ServerHello)),
+            ServerSendsSupplementalData => Some((Peer::Server, MessageType::SupplementalData)),
+            ServerSendsCertificate => Some((Peer::Server, MessageType::Certificate)),
+            ServerSendsCertificateStatus => Some((Peer::Server, MessageType::CertificateStatus)),
+            ServerSendsServerKeyExchange => Some((Peer::Server, MessageType::ServerKeyExchange)),
+            ServerSendsCertificateRequest => Some((Peer::Server, MessageType::CertificateRequest)),
+            ServerSendsServerHelloDone => Some((Peer::Server, MessageType::ServerHelloDone)),
+            ClientSendsSupplementalData => Some((Peer::Client, MessageType::SupplementalData)),
+            ClientSendsCertificate => Some((Peer::Client, MessageType::Certificate)),
+            ClientSendsCertificateUrl => Some((Peer::Client, MessageType::CertificateUrl)),
+            ClientSendsEmptyCertificate => Some((Peer::Client, MessageType::Certificate)),
+            ClientSendsClientKeyExchange => Some((Peer::Client, MessageType::ClientKeyExchange)),
+            ClientSendsEmptyClientKeyExchange => {
+                Some((Peer::Client, MessageType::ClientKeyExchange))
+            }
+            ClientSendsCertificateVerify => Some((Peer::Client, MessageType::CertificateVerify)),
+            ClientSendsChangeCipherSpec => Some((Peer::Client, MessageType::ChangeCipherSpec)),
+            ClientSendsNextProtocol => Some((Peer::Client, MessageType::NextProtocol)),
+            ClientSendsFinished => Some((Peer::Client, MessageType::Finished)),
+            ClientSendsApplicationData => Some((Peer::Client, MessageType::ApplicationData)),
+            ServerSendsNewSessionTicket => Some((Peer::Server, MessageType::NewSessionTicket)),
+            ServerSendsChangeCipherSpec => Some((Peer::Server, MessageType::ChangeCipherSpec)),
+            ServerSendsFinished => Some((Peer::Server, MessageType::Finished)),
+            Term => None,
+            ServerSendsHelloRequest => Some((Peer::Server, MessageType::HelloRequest)),
+            ClientRefusesRenegotiation => Some((Peer::Client, MessageType::Alert)),
+            SendsAlert(peer, _) => Some((peer, MessageType::Alert)),
+            Aborted(_) => None,
         }
     }
+
+    /// The peer that has to be ready to receive the message sent in this
+    /// state, and that message.
+    pub fn expects(self) -> Option<(Peer, MessageType)> {
+        self.sends().map(|(peer, msg)| (peer.other(), msg))
+    }
 }
 
 pub fn step(st: State, opts: ProtocolOptions) -> State {
@@ -94,33 +1018,25 @@ pub fn step(st: State, opts: ProtocolOptions) -> State {
     match st {
         ClientSendsClientHello => ServerSendsServerHello,
         ServerSendsServerHello => {
-            if opts.resuming {
-                if opts.server_issues_ticket {
-                    ServerSendsNewSessionTicket
-                } else {
-                    ServerSendsFinished
-                }
+            if opts.abbreviated() {
+                server_finishes(opts)
+            } else if opts.supplemental_data == Some(Peer::Server) {
+                ServerSendsSupplementalData
             } else {
-                if opts.dh_anon {
-                    ServerSendsServerKeyExchange
-                } else {
-                    ServerSendsCertificate
-                }
+                after_server_hello(opts)
             }
         }
+        ServerSendsSupplementalData => after_server_hello(opts),
         ServerSendsCertificate => {
-            if opts.rsa_kem {
-                if opts.request_client_auth {
-                    ServerSendsCertificateRequest
-                } else {
-                    ServerSendsServerHelloDone
-                }
+            if opts.server_stapled_ocsp {
+                ServerSendsCertificateStatus
             } else {
-                ServerSendsServerKeyExchange
+                after_certificate(opts)
             }
         }
+        ServerSendsCertificateStatus => after_certificate(opts),
         ServerSendsServerKeyExchange => {
-            if opts.request_client_auth {
+            if opts.client_auth.requested() {
                 ServerSendsCertificateRequest
             } else {
                 ServerSendsServerHelloDone
@@ -128,64 +1044,424 @@ pub fn step(st: State, opts: ProtocolOptions) -> State {
         }
         ServerSendsCertificateRequest => ServerSendsServerHelloDone,
         ServerSendsServerHelloDone => {
-            if opts.request_client_auth {
-                ClientSendsCertificate
+            if opts.supplemental_data == Some(Peer::Client) {
+                ClientSendsSupplementalData
+            } else {
+                after_server_hello_done(opts)
+            }
+        }
+        ClientSendsSupplementalData => after_server_hello_done(opts),
+        ClientSendsCertificate | ClientSendsCertificateUrl => {
+            if opts.client_auth == ClientAuth::FixedDh {
+                ClientSendsEmptyClientKeyExchange
             } else {
                 ClientSendsClientKeyExchange
             }
         }
-        ClientSendsCertificate => ClientSendsClientKeyExchange,
+        ClientSendsEmptyCertificate => ClientSendsClientKeyExchange,
         ClientSendsClientKeyExchange => {
-            if opts.perform_client_auth {
+            if opts.client_auth == ClientAuth::Performed {
                 ClientSendsCertificateVerify
             } else {
                 ClientSendsChangeCipherSpec
             }
         }
+        ClientSendsEmptyClientKeyExchange => ClientSendsChangeCipherSpec,
         ClientSendsCertificateVerify => ClientSendsChangeCipherSpec,
-        ClientSendsChangeCipherSpec => ClientSendsFinished,
+        // NextProtocol is sent encrypted, right before the client's Finished.
+        ClientSendsChangeCipherSpec => {
+            if opts.npn {
+                ClientSendsNextProtocol
+            } else {
+                ClientSendsFinished
+            }
+        }
+        ClientSendsNextProtocol => ClientSendsFinished,
         ClientSendsFinished => {
-            if opts.resuming {
+            if opts.abbreviated() {
                 Term
+            } else if opts.false_start {
+                ClientSendsApplicationData
             } else {
-                if opts.server_issues_ticket {
-                    ServerSendsNewSessionTicket
-                } else {
-                    ServerSendsChangeCipherSpec
-                }
+                server_finishes(opts)
             }
         }
+        ClientSendsApplicationData => server_finishes(opts),
         ServerSendsNewSessionTicket => ServerSendsChangeCipherSpec,
         ServerSendsChangeCipherSpec => ServerSendsFinished,
         ServerSendsFinished => {
-            if opts.resuming {
+            if opts.abbreviated() {
                 ClientSendsChangeCipherSpec
             } else {
                 Term
             }
         }
-        Term => Term,
+        // Renegotiation starts over on the established connection, either
+        // asked for by the server or directly by the client (RFC 5246, 7.4.1.1).
+        // Without secure renegotiation the client neither starts one nor
+        // follows the server's request.
+        Term => match opts.renegotiation {
+            Some(Peer::Server) => ServerSendsHelloRequest,
+            Some(Peer::Client) if opts.renegotiation_permitted() => ClientSendsClientHello,
+            _ => Term,
+        },
+        ServerSendsHelloRequest => {
+            if opts.renegotiation_permitted() {
+                ClientSendsClientHello
+            } else {
+                ClientRefusesRenegotiation
+            }
+        }
+        ClientRefusesRenegotiation => Term,
+        SendsAlert(_, desc) => Aborted(desc),
+        Aborted(desc) => Aborted(desc),
+    }
+}
+
+/// A key schedule event tied to the handshake.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    /// Both peers hash the handshake so far into the session_hash the
+    /// extended master secret is derived from (RFC 7627, 3).
+    ComputeSessionHash,
+    /// Both peers compute the master secret from the premaster secret.
+    DeriveMasterSecret,
+    /// The peer starts protecting the records it sends.
+    SwitchToWriteKeys(Peer),
+    /// The peer starts expecting protected records.
+    SwitchToReadKeys(Peer),
+}
+
+/// Like `step`, but also returns what the peers do with their keys once the
+/// message sent in `st` has gone over. The master secret follows the
+/// ClientKeyExchange (a resumed session already has one), after the session
+/// hash if the extended master secret is in use, and each ChangeCipherSpec
+/// switches the sender's write keys and the receiver's read keys (RFC 5246,
+/// 7.1).
+pub fn step_with_actions(st: State, opts: ProtocolOptions) -> (State, Vec<Action>) {
+    use State::*;
+    let actions = match st {
+        ClientSendsClientKeyExchange | ClientSendsEmptyClientKeyExchange => {
+            if opts.extended_master_secret {
+                vec![Action::ComputeSessionHash, Action::DeriveMasterSecret]
+            } else {
+                vec![Action::DeriveMasterSecret]
+            }
+        }
+        ClientSendsChangeCipherSpec | ServerSendsChangeCipherSpec => {
+            let (peer, _) = st.sends().unwrap();
+            vec![Action::SwitchToWriteKeys(peer), Action::SwitchToReadKeys(peer.other())]
+        }
+        _ => vec![],
+    };
+    (step(st, opts), actions)
+}
+
+/// The server's first flight goes on with its certificate, if it has one,
+/// and its key exchange, if it sends one.
+fn after_server_hello(opts: ProtocolOptions) -> State {
+    if opts.key_exchange.server_certificate() {
+        State::ServerSendsCertificate
+    } else if opts.server_key_exchange() {
+        State::ServerSendsServerKeyExchange
+    } else {
+        State::ServerSendsServerHelloDone
+    }
+}
+
+/// The client's flight starts with its answer to a CertificateRequest, if
+/// there was one.
+fn after_server_hello_done(opts: ProtocolOptions) -> State {
+    match opts.client_auth {
+        ClientAuth::NotRequested => State::ClientSendsClientKeyExchange,
+        ClientAuth::Declined => State::ClientSendsEmptyCertificate,
+        ClientAuth::Performed | ClientAuth::FixedDh => {
+            if opts.client_certificate_url {
+                State::ClientSendsCertificateUrl
+            } else {
+                State::ClientSendsCertificate
+            }
+        }
+    }
+}
+
+fn after_certificate(opts: ProtocolOptions) -> State {
+    if opts.server_key_exchange() {
+        State::ServerSendsServerKeyExchange
+    } else if opts.client_auth.requested() {
+        State::ServerSendsCertificateRequest
+    } else {
+        State::ServerSendsServerHelloDone
+    }
+}
+
+/// The messages of a handshake in order, with the state sending each. See
+/// `ProtocolOptions::iter_handshake` and `ProtocolOptions::iter_renegotiation`.
+#[derive(Debug, Clone)]
+pub struct HandshakeIter {
+    state: State,
+    opts: ProtocolOptions,
+}
+
+impl Iterator for HandshakeIter {
+    type Item = (State, Peer, MessageType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let st = self.state;
+        let (peer, msg) = st.sends()?;
+        self.state = step(st, self.opts);
+        Some((st, peer, msg))
+    }
+}
+
+/// A linearised handshake: every message in order, with the state sending it,
+/// and whether the records that follow are compressed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    steps: Vec<(State, Peer, MessageType)>,
+    compression: bool,
+}
+
+impl Trace {
+    pub fn linearise(opts: ProtocolOptions) -> Trace {
+        Trace { steps: opts.iter_handshake().collect(), compression: opts.compression }
+    }
+
+    /// The renegotiation after the handshake of `linearise(opts)`, if any.
+    pub fn linearise_renegotiation(opts: ProtocolOptions) -> Trace {
+        Trace { steps: opts.iter_renegotiation().collect(), compression: opts.compression }
+    }
+
+    pub fn steps(&self) -> &[(State, Peer, MessageType)] {
+        &self.steps
+    }
+
+    pub fn messages(&self) -> Vec<MessageType> {
+        self.steps.iter().map(|(_, _, msg)| *msg).collect()
+    }
+
+    pub fn peers(&self) -> Vec<Peer> {
+        self.steps.iter().map(|(_, peer, _)| *peer).collect()
+    }
+
+    /// The messages grouped into flights: each run of messages one peer
+    /// sends before waiting for the other. A flight goes out together, and
+    /// is what DTLS retransmits as a unit (RFC 6347, 4.2.4).
+    pub fn flights(&self) -> Vec<(Peer, Vec<MessageType>)> {
+        let mut flights: Vec<(Peer, Vec<MessageType>)> = Vec::new();
+        for (_, peer, msg) in &self.steps {
+            match flights.last_mut() {
+                Some((last, msgs)) if last == peer => msgs.push(*msg),
+                _ => flights.push((*peer, vec![*msg])),
+            }
+        }
+        flights
+    }
+
+    /// The messages hashed into the verify_data of `peer`'s Finished: every
+    /// handshake message before it, including the other peer's Finished if
+    /// that came first. `None` if `peer` sends no Finished.
+    pub fn transcript(&self, peer: Peer) -> Option<Vec<MessageType>> {
+        let finished = self
+            .steps
+            .iter()
+            .position(|(_, p, msg)| *p == peer && *msg == MessageType::Finished)?;
+        Some(
+            self.steps[..finished]
+                .iter()
+                .map(|(_, _, msg)| *msg)
+                .filter(|msg| msg.in_handshake_hash())
+                .collect(),
+        )
+    }
+
+    /// The messages the session_hash of RFC 7627, 3 covers: every handshake
+    /// message up to and including the first ClientKeyExchange. `None` if
+    /// there is none, as when a session is resumed.
+    pub fn session_hash(&self) -> Option<Vec<MessageType>> {
+        let cke = self.steps.iter().position(|(_, _, msg)| *msg == MessageType::ClientKeyExchange)?;
+        Some(
+            self.steps[..=cke]
+                .iter()
+                .map(|(_, _, msg)| *msg)
+                .filter(|msg| msg.in_handshake_hash())
+                .collect(),
+        )
+    }
+
+    /// Whether the handshake negotiated DEFLATE for the records after it.
+    pub fn compression(&self) -> bool {
+        self.compression
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// One message per line, then the compression method if it isn't null.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (_, peer, msg) in &self.steps {
+            writeln!(f, "{}: {}", peer, msg)?;
+        }
+        if self.compression {
+            writeln!(f, "Compression: DEFLATE")?;
+        }
+        Ok(())
+    }
+}
+
+/// A peer received a message the spec doesn't allow at this point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProtocolViolation {
+    /// What the peer was waiting for, if it was waiting at all.
+    pub expected: Option<MessageType>,
+    pub received: MessageType,
+}
+
+impl fmt::Display for ProtocolViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(f, "expected {:?}, received {:?}", expected, self.received),
+            None => write!(f, "received {:?} while not expecting a message", self.received),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolViolation {}
+
+/// One side of the handshake. It only learns about progress through the
+/// messages it receives, and answers each with the messages it sends next.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    peer: Peer,
+    state: State,
+    opts: ProtocolOptions,
+}
+
+impl Endpoint {
+    fn new(peer: Peer, opts: ProtocolOptions) -> Endpoint {
+        Endpoint { peer, state: State::default(), opts }
+    }
+
+    /// Send everything this peer sends before it has to wait again.
+    fn flush(&mut self) -> Vec<MessageType> {
+        let mut out = Vec::new();
+        while let Some((peer, msg)) = self.state.sends() {
+            if peer != self.peer {
+                break;
+            }
+            out.push(msg);
+            self.state = step(self.state, self.opts);
+        }
+        out
+    }
+
+    fn on_receive(&mut self, msg: MessageType) -> Result<Vec<MessageType>, ProtocolViolation> {
+        match self.state.expects() {
+            Some((peer, expected)) if peer == self.peer => {
+                if expected != msg {
+                    return Err(ProtocolViolation { expected: Some(expected), received: msg });
+                }
+                self.state = step(self.state, self.opts);
+                Ok(self.flush())
+            }
+            _ => Err(ProtocolViolation { expected: None, received: msg }),
+        }
+    }
+}
+
+/// The client's view of a single handshake. A violation leaves the machine
+/// where it was.
+#[derive(Debug, Clone)]
+pub struct ClientMachine(Endpoint);
+
+impl ClientMachine {
+    pub fn new(opts: ProtocolOptions) -> ClientMachine {
+        ClientMachine(Endpoint::new(Peer::Client, opts))
+    }
+
+    /// The client's first flight.
+    pub fn start(&mut self) -> Vec<MessageType> {
+        self.0.flush()
+    }
+
+    pub fn on_receive(&mut self, msg: MessageType) -> Result<Vec<MessageType>, ProtocolViolation> {
+        self.0.on_receive(msg)
+    }
+
+    pub fn state(&self) -> State {
+        self.0.state
+    }
+}
+
+/// The server's view of a single handshake. A violation leaves the machine
+/// where it was.
+#[derive(Debug, Clone)]
+pub struct ServerMachine(Endpoint);
+
+impl ServerMachine {
+    pub fn new(opts: ProtocolOptions) -> ServerMachine {
+        ServerMachine(Endpoint::new(Peer::Server, opts))
+    }
+
+    pub fn on_receive(&mut self, msg: MessageType) -> Result<Vec<MessageType>, ProtocolViolation> {
+        self.0.on_receive(msg)
+    }
+
+    pub fn state(&self) -> State {
+        self.0.state
     }
 }
 
+/// The server's last flight, in both full and abbreviated handshakes: its
+/// ticket, if any, precedes its ChangeCipherSpec (RFC 5077, 3.1 and 3.3).
+fn server_finishes(opts: ProtocolOptions) -> State {
+    if opts.issues_ticket() {
+        State::ServerSendsNewSessionTicket
+    } else {
+        State::ServerSendsChangeCipherSpec
+    }
+}
+
+/// Abandon the handshake in `st`: `by` sends the alert `desc` instead of
+/// continuing, after which the connection is `Aborted`. A connection already
+/// being torn down is left alone.
+pub fn fail(st: State, by: Peer, desc: AlertDescription) -> State {
+    match st {
+        State::SendsAlert(..) | State::Aborted(_) => st,
+        _ => State::SendsAlert(by, desc),
+    }
+}
+
+/// Like `step`, but rejects option combinations that `validate` refuses.
+pub fn step_checked(st: State, opts: ProtocolOptions) -> Result<State, OptionsError> {
+    opts.validate()?;
+    Ok(step(st, opts))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     fn print(opts: ProtocolOptions) {
-        let mut state = State::default();
-        loop {
-            let send = state.sends();
-            println!("{:?} sends {:?}", send.0, send.1);
-            let next = step(state, opts);
-            //println!("{:?} -> {:?}", state, next);
-            if next == State::Term {
-                break;
-            }
-            state = next;
+        for (_, peer, msg) in opts.iter_handshake() {
+            println!("{:?} sends {:?}", peer, msg);
         }
     }
 
+    #[test]
+    fn term_sends_nothing() {
+        assert_eq!(State::Term.sends(), None);
+        assert_eq!(step(State::Term, ProtocolOptions::default()), State::Term);
+    }
+
     #[test]
     fn resume() {
         print(ProtocolOptions { resuming: true, ..Default::default() });
@@ -198,61 +1474,959 @@ mod test {
 
     #[test]
     fn request_client_auth() {
-        print(ProtocolOptions { request_client_auth: true, ..Default::default() });
+        print(ProtocolOptions { client_auth: ClientAuth::Declined, ..Default::default() });
     }
 
     #[test]
     fn perform_client_auth() {
-        print(ProtocolOptions {
-            request_client_auth: true,
-            perform_client_auth: true,
+        print(ProtocolOptions { client_auth: ClientAuth::Performed, ..Default::default() });
+    }
+
+    #[test]
+    fn client_auth_outcomes() {
+        use MessageType::*;
+        let client_flight = |client_auth| {
+            let opts = ProtocolOptions { client_auth, ..Default::default() };
+            Trace::linearise(opts)
+                .steps()
+                .iter()
+                .filter(|(_, peer, msg)| *peer == Peer::Client && *msg != ClientHello)
+                .map(|(st, _, _)| *st)
+                .take_while(|st| *st != State::ClientSendsChangeCipherSpec)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            client_flight(ClientAuth::NotRequested),
+            vec![State::ClientSendsClientKeyExchange]
+        );
+        assert_eq!(
+            client_flight(ClientAuth::Declined),
+            vec![State::ClientSendsEmptyCertificate, State::ClientSendsClientKeyExchange]
+        );
+        assert_eq!(
+            client_flight(ClientAuth::Performed),
+            vec![
+                State::ClientSendsCertificate,
+                State::ClientSendsClientKeyExchange,
+                State::ClientSendsCertificateVerify
+            ]
+        );
+        for client_auth in &[ClientAuth::Declined, ClientAuth::Performed] {
+            let opts = ProtocolOptions { client_auth: *client_auth, ..Default::default() };
+            assert!(Trace::linearise(opts).messages().contains(&CertificateRequest));
+        }
+    }
+
+    #[test]
+    fn client_certificate_url() {
+        let opts = ProtocolOptions::builder()
+            .client_auth(ClientAuth::Performed)
+            .client_certificate_url(true)
+            .build()
+            .unwrap();
+        let messages = Trace::linearise(opts).messages();
+        let url = messages.iter().position(|m| *m == MessageType::CertificateUrl).unwrap();
+        assert_eq!(messages[url - 1], MessageType::ServerHelloDone);
+        assert_eq!(messages[url + 1], MessageType::ClientKeyExchange);
+        assert_eq!(messages[url + 2], MessageType::CertificateVerify);
+        let certificates = messages.iter().filter(|m| **m == MessageType::Certificate).count();
+        assert_eq!(certificates, 1);
+
+        let fixed_dh = ProtocolOptions {
+            key_exchange: KeyExchange::StaticDh,
+            client_auth: ClientAuth::FixedDh,
+            ..opts
+        };
+        assert_eq!(
+            fixed_dh.iter_handshake().nth(5),
+            Some((State::ClientSendsCertificateUrl, Peer::Client, MessageType::CertificateUrl))
+        );
+        let declined = ProtocolOptions { client_auth: ClientAuth::Declined, ..opts };
+        assert_eq!(declined.validate(), Err(OptionsError::CertificateUrlWithoutCertificate));
+    }
+
+    #[test]
+    fn supplemental_data() {
+        use MessageType::*;
+        let opts = ProtocolOptions {
+            supplemental_data: Some(Peer::Server),
+            client_auth: ClientAuth::Performed,
             ..Default::default()
-        });
+        };
+        assert_eq!(
+            Trace::linearise(opts).messages()[..4],
+            [ClientHello, ServerHello, SupplementalData, Certificate]
+        );
+
+        // RFC 4681's user mapping: the client's comes before its Certificate.
+        let opts = ProtocolOptions { supplemental_data: Some(Peer::Client), ..opts };
+        let steps = Trace::linearise(opts);
+        let done = steps.messages().iter().position(|m| *m == ServerHelloDone).unwrap();
+        assert_eq!(steps.messages()[done + 1..done + 3], [SupplementalData, Certificate]);
+        assert_eq!(steps.steps()[done + 1].0, State::ClientSendsSupplementalData);
+        let unauthenticated = ProtocolOptions { client_auth: ClientAuth::NotRequested, ..opts };
+        assert_eq!(
+            Trace::linearise(unauthenticated).messages()[done - 1..done + 2],
+            [ServerHelloDone, SupplementalData, ClientKeyExchange]
+        );
+
+        let resumed = ProtocolOptions { resuming: true, ..opts };
+        assert!(!Trace::linearise(resumed).messages().contains(&SupplementalData));
     }
 
     #[test]
     fn resumes_and_issues_ticket() {
         print(ProtocolOptions {
             resuming: true,
+            client_offers_ticket: true,
             server_issues_ticket: true,
             ..Default::default()
         });
     }
 
-    fn linearise(opts: ProtocolOptions) {
-        let mut state = State::default();
-        let mut v = Vec::new();
-        loop {
-            let send = state.sends();
-            v.push((state, send.0, send.1));
-            let next = step(state, opts);
-            if next == State::Term {
-                break;
+    #[test]
+    fn abbreviated_handshake() {
+        use MessageType::*;
+        let server_flight = |opts| {
+            messages(opts)
+                .into_iter()
+                .filter(|(peer, _)| *peer == Peer::Server)
+                .map(|(_, msg)| msg)
+                .collect::<Vec<_>>()
+        };
+        let opts = ProtocolOptions { resuming: true, ..Default::default() };
+        assert_eq!(server_flight(opts), vec![ServerHello, ChangeCipherSpec, Finished]);
+        for resume_via_ticket in &[false, true] {
+            let opts = ProtocolOptions {
+                resuming: true,
+                client_offers_ticket: true,
+                resume_via_ticket: *resume_via_ticket,
+                server_issues_ticket: true,
+                ..Default::default()
+            };
+            assert_eq!(opts.validate(), Ok(()));
+            assert_eq!(
+                server_flight(opts),
+                vec![ServerHello, NewSessionTicket, ChangeCipherSpec, Finished]
+            );
+        }
+    }
+
+    #[test]
+    fn resumption_declined() {
+        let declined = ProtocolOptions {
+            resuming: true,
+            server_accepts_resumption: false,
+            ..Default::default()
+        };
+        assert_eq!(messages(declined), messages(ProtocolOptions::default()));
+
+        // A server that can't use the offered ticket may still issue a new
+        // one at the end of the full handshake (RFC 5077, 3.3).
+        let opts = ProtocolOptions {
+            resume_via_ticket: true,
+            client_offers_ticket: true,
+            server_issues_ticket: true,
+            ..declined
+        };
+        assert_eq!(opts.validate(), Ok(()));
+        let trace = messages(opts);
+        assert!(trace.contains(&(Peer::Client, MessageType::ClientKeyExchange)));
+        assert_eq!(
+            trace[trace.len() - 3..],
+            [
+                (Peer::Server, MessageType::NewSessionTicket),
+                (Peer::Server, MessageType::ChangeCipherSpec),
+                (Peer::Server, MessageType::Finished),
+            ]
+        );
+    }
+
+    #[test]
+    fn ticket_needs_client_offer() {
+        let opts = ProtocolOptions { server_issues_ticket: true, ..Default::default() };
+        assert_eq!(opts.validate(), Err(OptionsError::TicketNotOffered));
+        assert!(!messages(opts).iter().any(|(_, msg)| *msg == MessageType::NewSessionTicket));
+
+        let opts =
+            ProtocolOptions { resuming: true, resume_via_ticket: true, ..Default::default() };
+        assert_eq!(opts.validate(), Err(OptionsError::TicketNotOffered));
+        let opts = ProtocolOptions {
+            client_offers_ticket: true,
+            resume_via_ticket: true,
+            ..Default::default()
+        };
+        assert_eq!(opts.validate(), Err(OptionsError::TicketWithoutResumption));
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(ProtocolOptions::default().validate(), Ok(()));
+        let opts = ProtocolOptions { client_auth: ClientAuth::Performed, ..Default::default() };
+        assert_eq!(opts.validate(), Ok(()));
+        let opts = ProtocolOptions {
+            key_exchange: KeyExchange::DhAnon,
+            client_auth: ClientAuth::Declined,
+            ..Default::default()
+        };
+        assert_eq!(opts.validate(), Err(OptionsError::AnonymousClientAuth));
+
+        let raw = CertificateType::RawPublicKey;
+        let opts = ProtocolOptions { server_cert_type: raw, ..Default::default() };
+        assert_eq!(opts.validate(), Ok(()));
+        let psk = ProtocolOptions { key_exchange: KeyExchange::Psk, ..opts };
+        assert_eq!(psk.validate(), Err(OptionsError::CertificateTypeWithoutCertificate));
+        let opts = ProtocolOptions { client_cert_type: raw, ..Default::default() };
+        assert_eq!(opts.validate(), Err(OptionsError::CertificateTypeWithoutCertificate));
+        let opts = ProtocolOptions { client_auth: ClientAuth::Performed, ..opts };
+        assert_eq!(opts.validate(), Ok(()));
+    }
+
+    #[test]
+    fn step_checked_rejects_invalid() {
+        let opts = ProtocolOptions {
+            key_exchange: KeyExchange::DhAnon,
+            client_auth: ClientAuth::Performed,
+            ..Default::default()
+        };
+        assert_eq!(step_checked(State::default(), opts), Err(OptionsError::AnonymousClientAuth));
+        assert_eq!(
+            step_checked(State::default(), ProtocolOptions::default()),
+            Ok(State::ServerSendsServerHello)
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let opts = ProtocolOptions::builder()
+            .client_auth(ClientAuth::Performed)
+            .client_offers_ticket(true)
+            .server_issues_ticket(true)
+            .build()
+            .unwrap();
+        assert_eq!(opts.client_auth, ClientAuth::Performed);
+        assert!(opts.server_issues_ticket);
+        assert!(!opts.resuming);
+        assert!(opts.server_accepts_resumption);
+        assert_eq!(opts.key_exchange, KeyExchange::EcdheRsa);
+        let opts = ProtocolOptions::builder()
+            .key_exchange(KeyExchange::DhAnon)
+            .client_auth(ClientAuth::Declined)
+            .build();
+        assert_eq!(opts.unwrap_err(), OptionsError::AnonymousClientAuth);
+    }
+
+    fn messages(opts: ProtocolOptions) -> Vec<(Peer, MessageType)> {
+        opts.iter_handshake().map(|(_, peer, msg)| (peer, msg)).collect()
+    }
+
+    #[test]
+    fn iter_handshake() {
+        let opts = ProtocolOptions::default();
+        let mut iter = opts.iter_handshake();
+        assert_eq!(
+            iter.next(),
+            Some((State::ClientSendsClientHello, Peer::Client, MessageType::ClientHello))
+        );
+        assert_eq!(
+            iter.next(),
+            Some((State::ServerSendsServerHello, Peer::Server, MessageType::ServerHello))
+        );
+        let rest = iter.collect::<Vec<_>>();
+        assert_eq!(
+            rest.last(),
+            Some(&(State::ServerSendsFinished, Peer::Server, MessageType::Finished))
+        );
+        assert!(rest.iter().all(|(st, ..)| *st != State::Term));
+
+        // Stops at Term, even if the connection could renegotiate; the
+        // renegotiation has an iterator of its own.
+        let opts = ProtocolOptions { renegotiation: Some(Peer::Server), ..Default::default() };
+        assert_eq!(opts.iter_handshake().count(), messages(ProtocolOptions::default()).len());
+        assert_eq!(ProtocolOptions::default().iter_renegotiation().next(), None);
+    }
+
+    #[test]
+    fn key_exchanges() {
+        use KeyExchange::*;
+        let server_flight = |kx| {
+            let opts = ProtocolOptions { key_exchange: kx, ..Default::default() };
+            messages(opts)
+                .into_iter()
+                .filter(|(peer, msg)| *peer == Peer::Server && *msg != MessageType::ServerHello)
+                .map(|(_, msg)| msg)
+                .take_while(|msg| *msg != MessageType::ServerHelloDone)
+                .collect::<Vec<_>>()
+        };
+        let cert_ske = vec![MessageType::Certificate, MessageType::ServerKeyExchange];
+        for kx in &[EcdheRsa, EcdheEcdsa, DheRsa, DheDss] {
+            assert_eq!(server_flight(*kx), cert_ske);
+        }
+        for kx in &[RsaKem, StaticDh] {
+            assert_eq!(server_flight(*kx), vec![MessageType::Certificate]);
+        }
+        assert_eq!(server_flight(DhAnon), vec![MessageType::ServerKeyExchange]);
+        assert_eq!(server_flight(Psk), vec![]);
+    }
+
+    #[test]
+    fn renegotiation() {
+        let opts = ProtocolOptions {
+            renegotiation: Some(Peer::Server),
+            secure_renegotiation: true,
+            ..Default::default()
+        };
+        let first = messages(opts);
+        assert!(!first.contains(&(Peer::Server, MessageType::HelloRequest)));
+
+        // Continuing from the established connection runs a second handshake.
+        let second = Trace::linearise_renegotiation(opts);
+        assert_eq!(second.steps()[0].0, State::ServerSendsHelloRequest);
+        assert_eq!(second.steps().last().unwrap().0, State::ServerSendsFinished);
+        let second = second.peers().into_iter().zip(second.messages()).collect::<Vec<_>>();
+        assert_eq!(second[0], (Peer::Server, MessageType::HelloRequest));
+        assert_eq!(second[1..], first[..]);
+
+        let opts = ProtocolOptions { renegotiation: Some(Peer::Client), ..opts };
+        assert_eq!(Trace::linearise_renegotiation(opts), Trace::linearise(opts));
+        assert!(Trace::linearise_renegotiation(ProtocolOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn insecure_renegotiation() {
+        let second = |opts: ProtocolOptions| {
+            opts.iter_renegotiation().map(|(_, peer, msg)| (peer, msg)).collect::<Vec<_>>()
+        };
+        let secure = ProtocolOptions {
+            renegotiation: Some(Peer::Server),
+            secure_renegotiation: true,
+            ..Default::default()
+        };
+        let vulnerable = ProtocolOptions {
+            secure_renegotiation: false,
+            allow_insecure_renegotiation: true,
+            ..secure
+        };
+        // Both run the same messages; only the renegotiation_info contents,
+        // which aren't modelled, tell them apart.
+        assert_eq!(second(vulnerable), second(secure));
+
+        let mitigated = ProtocolOptions { secure_renegotiation: false, ..secure };
+        assert_eq!(
+            second(mitigated),
+            vec![(Peer::Server, MessageType::HelloRequest), (Peer::Client, MessageType::Alert)]
+        );
+        assert_eq!(step(State::ClientRefusesRenegotiation, mitigated), State::Term);
+        let mitigated = ProtocolOptions { renegotiation: Some(Peer::Client), ..mitigated };
+        assert_eq!(second(mitigated), vec![]);
+    }
+
+    #[test]
+    fn actions() {
+        let run = |opts| {
+            let mut state = State::default();
+            let mut v = Vec::new();
+            while let Some((_, msg)) = state.sends() {
+                let (next, actions) = step_with_actions(state, opts);
+                v.push((msg, actions));
+                state = next;
+            }
+            v
+        };
+        use Action::*;
+        let actions = run(ProtocolOptions::default())
+            .into_iter()
+            .filter(|(_, actions)| !actions.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                (MessageType::ClientKeyExchange, vec![DeriveMasterSecret]),
+                (
+                    MessageType::ChangeCipherSpec,
+                    vec![SwitchToWriteKeys(Peer::Client), SwitchToReadKeys(Peer::Server)]
+                ),
+                (
+                    MessageType::ChangeCipherSpec,
+                    vec![SwitchToWriteKeys(Peer::Server), SwitchToReadKeys(Peer::Client)]
+                ),
+            ]
+        );
+
+        // Every Finished is protected: its sender switched write keys, and its
+        // receiver read keys, before it went over.
+        for opts in &[
+            ProtocolOptions { resuming: true, ..Default::default() },
+            ProtocolOptions { npn: true, false_start: true, ..Default::default() },
+            ProtocolOptions { extended_master_secret: true, ..Default::default() },
+            ProtocolOptions {
+                client_auth: ClientAuth::FixedDh,
+                key_exchange: KeyExchange::StaticDh,
+                ..Default::default()
+            },
+        ] {
+            let mut writing = Vec::new();
+            let mut reading = Vec::new();
+            let mut state = State::default();
+            let mut derived = opts.resuming;
+            while let Some((peer, msg)) = state.sends() {
+                if msg == MessageType::Finished {
+                    assert!(derived);
+                    assert!(writing.contains(&peer) && reading.contains(&peer.other()));
+                }
+                let (next, actions) = step_with_actions(state, *opts);
+                for action in actions {
+                    match action {
+                        ComputeSessionHash => assert!(!derived),
+                        DeriveMasterSecret => derived = true,
+                        SwitchToWriteKeys(peer) => writing.push(peer),
+                        SwitchToReadKeys(peer) => reading.push(peer),
+                    }
+                }
+                state = next;
+            }
+            assert_eq!(writing.len(), 2);
+            assert_eq!(reading.len(), 2);
+        }
+    }
+
+    #[test]
+    fn alerts() {
+        let opts = ProtocolOptions::default();
+        let mut state = step(State::default(), opts);
+        state = fail(state, Peer::Client, AlertDescription::ProtocolVersion);
+        assert_eq!(state.sends(), Some((Peer::Client, MessageType::Alert)));
+        state = step(state, opts);
+        assert_eq!(state, State::Aborted(AlertDescription::ProtocolVersion));
+        assert_eq!(state.sends(), None);
+        assert_eq!(step(state, opts), state);
+        assert_eq!(fail(state, Peer::Server, AlertDescription::InternalError), state);
+        assert_eq!(AlertDescription::ProtocolVersion.level(), AlertLevel::Fatal);
+        assert_eq!(AlertDescription::CloseNotify.level(), AlertLevel::Warning);
+    }
+
+    #[test]
+    fn every_state_can_abort() {
+        // Failing at each point of the full handshake gives one error trace
+        // per state, each ending in a single alert.
+        let opts = ProtocolOptions::default();
+        let n = messages(opts).len();
+        for i in 0..n {
+            let mut state = State::default();
+            for _ in 0..i {
+                state = step(state, opts);
             }
-            state = next;
+            state = fail(state, Peer::Server, AlertDescription::HandshakeFailure);
+            let mut alerts = 0;
+            while let Some((_, msg)) = state.sends() {
+                assert_eq!(msg, MessageType::Alert);
+                alerts += 1;
+                state = step(state, opts);
+            }
+            assert_eq!(alerts, 1);
+            assert_eq!(state, State::Aborted(AlertDescription::HandshakeFailure));
+        }
+    }
+
+    #[test]
+    fn psk_key_exchanges() {
+        use MessageType::*;
+        let server_flight = |key_exchange, psk_identity_hint| {
+            let opts = ProtocolOptions { key_exchange, psk_identity_hint, ..Default::default() };
+            assert_eq!(opts.validate(), Ok(()));
+            messages(opts)
+                .into_iter()
+                .filter(|(peer, msg)| *peer == Peer::Server && *msg != ServerHello)
+                .map(|(_, msg)| msg)
+                .take_while(|msg| *msg != ServerHelloDone)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(server_flight(KeyExchange::Psk, false), vec![]);
+        assert_eq!(server_flight(KeyExchange::Psk, true), vec![ServerKeyExchange]);
+        assert_eq!(server_flight(KeyExchange::DhePsk, false), vec![ServerKeyExchange]);
+        assert_eq!(server_flight(KeyExchange::DhePsk, true), vec![ServerKeyExchange]);
+        assert_eq!(server_flight(KeyExchange::RsaPsk, false), vec![Certificate]);
+        assert_eq!(
+            server_flight(KeyExchange::RsaPsk, true),
+            vec![Certificate, ServerKeyExchange]
+        );
+
+        for kx in &[KeyExchange::Psk, KeyExchange::DhePsk, KeyExchange::RsaPsk] {
+            let opts = ProtocolOptions {
+                key_exchange: *kx,
+                client_auth: ClientAuth::Performed,
+                ..Default::default()
+            };
+            assert_eq!(opts.validate(), Err(OptionsError::PskClientAuth));
+        }
+        let opts = ProtocolOptions { psk_identity_hint: true, ..Default::default() };
+        assert_eq!(opts.validate(), Err(OptionsError::IdentityHintWithoutPsk));
+    }
+
+    #[test]
+    fn srp_key_exchanges() {
+        use MessageType::*;
+        let server_flight = |key_exchange| {
+            let opts = ProtocolOptions { key_exchange, ..Default::default() };
+            assert_eq!(opts.validate(), Ok(()));
+            messages(opts)
+                .into_iter()
+                .filter(|(peer, msg)| *peer == Peer::Server && *msg != ServerHello)
+                .map(|(_, msg)| msg)
+                .take_while(|msg| *msg != ServerHelloDone)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(server_flight(KeyExchange::Srp), vec![ServerKeyExchange]);
+        assert_eq!(server_flight(KeyExchange::SrpRsa), vec![Certificate, ServerKeyExchange]);
+        assert_eq!(server_flight(KeyExchange::SrpDss), vec![Certificate, ServerKeyExchange]);
+
+        for kx in &[KeyExchange::Srp, KeyExchange::SrpRsa, KeyExchange::SrpDss] {
+            let opts = ProtocolOptions {
+                key_exchange: *kx,
+                client_auth: ClientAuth::Declined,
+                ..Default::default()
+            };
+            assert_eq!(opts.validate(), Err(OptionsError::SrpClientAuth));
         }
+    }
+
+    #[test]
+    fn static_dh() {
+        let client_flight = |client_auth| {
+            let opts = ProtocolOptions {
+                key_exchange: KeyExchange::StaticDh,
+                client_auth,
+                ..Default::default()
+            };
+            assert_eq!(opts.validate(), Ok(()));
+            Trace::linearise(opts)
+                .steps()
+                .iter()
+                .filter(|(_, peer, msg)| *peer == Peer::Client && *msg != MessageType::ClientHello)
+                .map(|(st, _, _)| *st)
+                .take_while(|st| *st != State::ClientSendsChangeCipherSpec)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            client_flight(ClientAuth::FixedDh),
+            vec![State::ClientSendsCertificate, State::ClientSendsEmptyClientKeyExchange]
+        );
+        assert_eq!(
+            client_flight(ClientAuth::Performed),
+            vec![
+                State::ClientSendsCertificate,
+                State::ClientSendsClientKeyExchange,
+                State::ClientSendsCertificateVerify
+            ]
+        );
+
+        let opts = ProtocolOptions { client_auth: ClientAuth::FixedDh, ..Default::default() };
+        assert_eq!(opts.validate(), Err(OptionsError::FixedDhWithoutStaticDh));
+    }
+
+    #[test]
+    fn npn() {
+        use MessageType::*;
+        for resuming in &[false, true] {
+            let opts = ProtocolOptions { resuming: *resuming, npn: true, ..Default::default() };
+            let trace = messages(opts);
+            let i = trace.iter().position(|m| *m == (Peer::Client, NextProtocol)).unwrap();
+            assert_eq!(trace[i - 1], (Peer::Client, ChangeCipherSpec));
+            assert_eq!(trace[i + 1], (Peer::Client, Finished));
 
-        for (st, peer, msg) in v {
-            print!("{:?} < ", st);
+            let opts = ProtocolOptions { resuming: *resuming, ..Default::default() };
+            assert!(!messages(opts).iter().any(|(_, msg)| *msg == NextProtocol));
         }
-        println!("");
+    }
+
+    #[test]
+    fn false_start() {
+        use MessageType::*;
+        let opts = ProtocolOptions { false_start: true, ..Default::default() };
+        let trace = messages(opts);
+        let i = trace.iter().position(|m| *m == (Peer::Client, ApplicationData)).unwrap();
+        assert_eq!(trace[i - 1], (Peer::Client, Finished));
+        assert_eq!(trace[i + 1], (Peer::Server, ChangeCipherSpec));
+        assert_eq!(trace.last(), Some(&(Peer::Server, Finished)));
+
+        let opts = ProtocolOptions {
+            false_start: true,
+            client_offers_ticket: true,
+            server_issues_ticket: true,
+            ..Default::default()
+        };
+        let trace = messages(opts);
+        let i = trace.iter().position(|m| *m == (Peer::Client, ApplicationData)).unwrap();
+        assert_eq!(trace[i + 1], (Peer::Server, NewSessionTicket));
+
+        // Abbreviated handshakes are unchanged.
+        let opts = ProtocolOptions { resuming: true, false_start: true, ..Default::default() };
+        assert!(!messages(opts).iter().any(|(_, msg)| *msg == ApplicationData));
+
+        let opts = ProtocolOptions {
+            key_exchange: KeyExchange::RsaKem,
+            false_start: true,
+            ..Default::default()
+        };
+        assert_eq!(opts.validate(), Err(OptionsError::FalseStartWithoutForwardSecrecy));
+    }
+
+    #[test]
+    fn ocsp_staple() {
+        use MessageType::*;
+        let server_messages = |opts| {
+            messages(opts)
+                .into_iter()
+                .filter(|(peer, _)| *peer == Peer::Server)
+                .map(|(_, msg)| msg)
+                .collect::<Vec<_>>()
+        };
+        let stapled = ProtocolOptions { server_stapled_ocsp: true, ..Default::default() };
+        assert_eq!(
+            server_messages(stapled)[..4],
+            [ServerHello, Certificate, CertificateStatus, ServerKeyExchange]
+        );
+        assert!(!server_messages(ProtocolOptions::default()).contains(&CertificateStatus));
+
+        let opts = ProtocolOptions {
+            key_exchange: KeyExchange::RsaKem,
+            server_stapled_ocsp: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            server_messages(opts)[..4],
+            [ServerHello, Certificate, CertificateStatus, ServerHelloDone]
+        );
+
+        // Resumption has no Certificate, so nothing is stapled.
+        let opts =
+            ProtocolOptions { resuming: true, server_stapled_ocsp: true, ..Default::default() };
+        assert!(!server_messages(opts).contains(&CertificateStatus));
+
+        let opts = ProtocolOptions {
+            key_exchange: KeyExchange::DhAnon,
+            server_stapled_ocsp: true,
+            ..Default::default()
+        };
+        assert_eq!(opts.validate(), Err(OptionsError::StapleWithoutCertificate));
+    }
+
+    #[test]
+    fn expects() {
+        assert_eq!(
+            State::ServerSendsServerHelloDone.expects(),
+            Some((Peer::Client, MessageType::ServerHelloDone))
+        );
+        assert_eq!(State::Term.expects(), None);
+
+        // The client's receive table for a full handshake.
+        let received = Trace::linearise(ProtocolOptions::default())
+            .steps()
+            .iter()
+            .filter_map(|(st, ..)| st.expects())
+            .filter(|(peer, _)| *peer == Peer::Client)
+            .map(|(_, msg)| msg)
+            .collect::<Vec<_>>();
+        use MessageType::*;
+        assert_eq!(
+            received,
+            vec![
+                ServerHello,
+                Certificate,
+                ServerKeyExchange,
+                ServerHelloDone,
+                ChangeCipherSpec,
+                Finished
+            ]
+        );
+    }
+
+    #[test]
+    fn endpoints() {
+        let opts = ProtocolOptions { client_auth: ClientAuth::Performed, ..Default::default() };
+        let mut client = ClientMachine::new(opts);
+        let mut server = ServerMachine::new(opts);
+
+        // Play the two machines against each other and record what went over
+        // the wire; it has to be the handshake of the global machine.
+        let mut wire = Vec::new();
+        let mut flights = Vec::new();
+        let mut in_flight = client.start();
+        let mut to_server = true;
+        while !in_flight.is_empty() {
+            let sender = if to_server { Peer::Client } else { Peer::Server };
+            flights.push((sender, in_flight.clone()));
+            let mut replies = Vec::new();
+            for msg in in_flight {
+                wire.push(msg);
+                let out = if to_server { server.on_receive(msg) } else { client.on_receive(msg) };
+                replies.extend(out.unwrap());
+            }
+            in_flight = replies;
+            to_server = !to_server;
+        }
+        assert_eq!(wire, Trace::linearise(opts).messages());
+        assert_eq!(flights, Trace::linearise(opts).flights());
+        assert_eq!(client.state(), State::Term);
+        assert_eq!(server.state(), State::Term);
+    }
+
+    #[test]
+    fn endpoint_violations() {
+        let mut client = ClientMachine::new(ProtocolOptions::default());
+        assert_eq!(client.start(), vec![MessageType::ClientHello]);
+        assert_eq!(
+            client.on_receive(MessageType::Certificate),
+            Err(ProtocolViolation {
+                expected: Some(MessageType::ServerHello),
+                received: MessageType::Certificate
+            })
+        );
+        assert_eq!(client.state(), State::ServerSendsServerHello);
+        assert_eq!(client.on_receive(MessageType::ServerHello), Ok(vec![]));
+
+        // The server never receives its own messages.
+        let mut server = ServerMachine::new(ProtocolOptions::default());
+        assert_eq!(
+            server.on_receive(MessageType::ClientHello),
+            Ok(vec![
+                MessageType::ServerHello,
+                MessageType::Certificate,
+                MessageType::ServerKeyExchange,
+                MessageType::ServerHelloDone
+            ])
+        );
+        assert_eq!(
+            server.on_receive(MessageType::ServerHello),
+            Err(ProtocolViolation {
+                expected: Some(MessageType::ClientKeyExchange),
+                received: MessageType::ServerHello
+            })
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let trace = Trace::linearise(ProtocolOptions { resuming: true, ..Default::default() });
+        assert_eq!(trace.len(), trace.messages().len());
+        assert_eq!(trace.messages()[0], MessageType::ClientHello);
+        assert_eq!(trace.peers()[..2], [Peer::Client, Peer::Server]);
+        assert_eq!(trace.steps()[0].0, State::ClientSendsClientHello);
+        assert!(!trace.is_empty());
+        let shown = format!("{}", trace);
+        assert_eq!(shown.lines().count(), trace.len());
+        assert_eq!(shown.lines().next(), Some("client: client_hello"));
+        let opts = ProtocolOptions { compression: true, ..Default::default() };
+        let compressed = Trace::linearise(opts);
+        let shown = format!("{}", compressed);
+        assert_eq!(shown.lines().count(), compressed.len() + 1);
+        assert_eq!(shown.lines().last(), Some("Compression: DEFLATE"));
+    }
+
+    #[test]
+    fn transcript() {
+        use MessageType::*;
+        let trace = Trace::linearise(ProtocolOptions {
+            client_auth: ClientAuth::Performed,
+            npn: true,
+            client_offers_ticket: true,
+            server_issues_ticket: true,
+            ..Default::default()
+        });
+        let client = vec![
+            ClientHello,
+            ServerHello,
+            Certificate,
+            ServerKeyExchange,
+            CertificateRequest,
+            ServerHelloDone,
+            Certificate,
+            ClientKeyExchange,
+            CertificateVerify,
+            NextProtocol,
+        ];
+        assert_eq!(trace.transcript(Peer::Client), Some(client.clone()));
+        let mut server = client;
+        server.extend(&[Finished, NewSessionTicket]);
+        assert_eq!(trace.transcript(Peer::Server), Some(server));
+
+        // In an abbreviated handshake the server finishes first.
+        let trace = Trace::linearise(ProtocolOptions { resuming: true, ..Default::default() });
+        assert_eq!(trace.transcript(Peer::Server), Some(vec![ClientHello, ServerHello]));
+        assert_eq!(
+            trace.transcript(Peer::Client),
+            Some(vec![ClientHello, ServerHello, Finished])
+        );
+
+        // A renegotiation's HelloRequest is not hashed.
+        let opts = ProtocolOptions {
+            renegotiation: Some(Peer::Server),
+            secure_renegotiation: true,
+            ..Default::default()
+        };
+        let renegotiation = Trace::linearise_renegotiation(opts);
+        assert_eq!(renegotiation.messages()[0], HelloRequest);
+        assert_eq!(
+            renegotiation.transcript(Peer::Client),
+            Trace::linearise(opts).transcript(Peer::Client)
+        );
+        assert!(!ChangeCipherSpec.in_handshake_hash());
+        assert!(!Alert.in_handshake_hash());
+    }
+
+    #[test]
+    fn session_hash() {
+        use MessageType::*;
+        let opts = ProtocolOptions {
+            client_auth: ClientAuth::Performed,
+            extended_master_secret: true,
+            ..Default::default()
+        };
+        let trace = Trace::linearise(opts);
+        // The client's CertificateVerify signs the session hash and more, so
+        // it comes after (RFC 7627, 3).
+        let hashed = vec![
+            ClientHello,
+            ServerHello,
+            Certificate,
+            ServerKeyExchange,
+            CertificateRequest,
+            ServerHelloDone,
+            Certificate,
+            ClientKeyExchange,
+        ];
+        assert_eq!(trace.session_hash(), Some(hashed));
+        let (_, actions) = step_with_actions(State::ClientSendsClientKeyExchange, opts);
+        assert_eq!(actions, vec![Action::ComputeSessionHash, Action::DeriveMasterSecret]);
+        // Only the messages tell where the session hash ends; the option
+        // is what says it is computed at all.
+        let without = ProtocolOptions { extended_master_secret: false, ..opts };
+        assert_eq!(Trace::linearise(without), trace);
+        let (_, actions) = step_with_actions(State::ClientSendsClientKeyExchange, without);
+        assert_eq!(actions, vec![Action::DeriveMasterSecret]);
+        let resumed = ProtocolOptions { resuming: true, ..opts };
+        assert_eq!(Trace::linearise(resumed).session_hash(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let opts = ProtocolOptions {
+            resuming: true,
+            renegotiation: Some(Peer::Client),
+            secure_renegotiation: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&opts).unwrap();
+        assert_eq!(serde_json::from_str::<ProtocolOptions>(&json).unwrap(), opts);
+        let trace = Trace::linearise(opts);
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(serde_json::from_str::<Trace>(&json).unwrap(), trace);
+        let first = r#"{"steps":[["ClientSendsClientHello","Client","ClientHello"],"#;
+        assert!(json.starts_with(first));
+        assert!(json.ends_with(r#"]],"compression":false}"#));
+        let alert = State::SendsAlert(Peer::Server, AlertDescription::HandshakeFailure);
+        assert_eq!(
+            serde_json::to_string(&alert).unwrap(),
+            r#"{"SendsAlert":["Server","HandshakeFailure"]}"#
+        );
+    }
+
+    #[test]
+    fn rfc_names() {
+        assert_eq!(MessageType::ServerHelloDone.to_string(), "server_hello_done");
+        assert_eq!(MessageType::ClientKeyExchange.to_string(), "client_key_exchange");
+        assert_eq!(MessageType::ChangeCipherSpec.to_string(), "change_cipher_spec");
+        assert_eq!(State::ServerSendsServerHello.to_string(), "server server_hello");
+        assert_eq!(State::ClientSendsEmptyCertificate.to_string(), "client certificate (empty)");
+        assert_eq!(
+            State::SendsAlert(Peer::Client, AlertDescription::BadRecordMac).to_string(),
+            "client alert (bad_record_mac)"
+        );
+        assert_eq!(State::Term.to_string(), "established");
+        assert_eq!(
+            State::Aborted(AlertDescription::UnknownCa).to_string(),
+            "aborted (unknown_ca)"
+        );
+        // Names are snake case throughout.
+        let npn = ProtocolOptions { npn: true, ..Default::default() };
+        for opts in &[ProtocolOptions::default(), npn] {
+            for (_, _, msg) in Trace::linearise(*opts).steps() {
+                let name = msg.to_string();
+                assert!(name.chars().all(|c| c.is_ascii_lowercase() || c == '_'), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn flights() {
+        use MessageType::*;
+        let trace = Trace::linearise(ProtocolOptions::default());
+        assert_eq!(
+            trace.flights(),
+            vec![
+                (Peer::Client, vec![ClientHello]),
+                (Peer::Server, vec![ServerHello, Certificate, ServerKeyExchange, ServerHelloDone]),
+                (Peer::Client, vec![ClientKeyExchange, ChangeCipherSpec, Finished]),
+                (Peer::Server, vec![ChangeCipherSpec, Finished]),
+            ]
+        );
+        // An abbreviated handshake takes one round trip less.
+        let trace = Trace::linearise(ProtocolOptions { resuming: true, ..Default::default() });
+        assert_eq!(
+            trace.flights(),
+            vec![
+                (Peer::Client, vec![ClientHello]),
+                (Peer::Server, vec![ServerHello, ChangeCipherSpec, Finished]),
+                (Peer::Client, vec![ChangeCipherSpec, Finished]),
+            ]
+        );
+        let flattened = trace.flights().into_iter().flat_map(|(_, msgs)| msgs).collect::<Vec<_>>();
+        assert_eq!(flattened, trace.messages());
+    }
+
+    #[test]
+    fn all_valid() {
+        let all = ProtocolOptions::all_valid().collect::<Vec<_>>();
+        assert!(all.iter().all(|opts| opts.validate().is_ok()));
+        assert!(all.contains(&ProtocolOptions::default()));
+        assert!(!all
+            .iter()
+            .any(|opts| opts.key_exchange == KeyExchange::DhAnon && opts.client_auth.requested()));
+        // Pruning the prefixes `validate` refuses loses no valid combination.
+        let (mut valid, mut invalid) = (0, 0);
+        for opts in ProtocolOptions::combinations_where(|_| true) {
+            match opts.validate() {
+                Ok(()) => valid += 1,
+                Err(_) => invalid += 1,
+            }
+        }
+        assert!(invalid > 0);
+        assert_eq!(valid, all.len());
     }
 
     #[test]
     fn rustls_subset() {
-        for resume in &[ true, false ] {
-            for ticket in &[ true, false ] {
-                for (req_client_auth, do_client_auth) in &[ (false, false), (true, false), (true, true) ] {
-                    let opts = ProtocolOptions {
-                        resuming: *resume,
-                        server_issues_ticket: *ticket,
-                        request_client_auth: *req_client_auth,
-                        perform_client_auth: *do_client_auth,
-                        ..Default::default()
-                    };
-                    linearise(opts);
+        // Resumption, tickets and client authentication, everything else as
+        // by default. tests/test_rustls_conformance.rs runs these against
+        // rustls.
+        let subset = ProtocolOptions::all_valid().filter(|opts| {
+            *opts
+                == ProtocolOptions {
+                    resuming: opts.resuming,
+                    client_offers_ticket: opts.client_offers_ticket,
+                    server_issues_ticket: opts.server_issues_ticket,
+                    client_auth: opts.client_auth,
+                    ..Default::default()
                 }
-            }
+                && opts.client_auth != ClientAuth::FixedDh
+        });
+        let mut n = 0;
+        for opts in subset {
+            println!("{}", Trace::linearise(opts));
+            n += 1;
         }
+        // The server only issues a ticket the client asked for.
+        assert_eq!(n, 2 * 3 * 3);
     }
 }
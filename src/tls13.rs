@@ -0,0 +1,1616 @@
+use crate::graph;
+use crate::tls12::{AlertDescription, Peer};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolOptions {
+    psk: bool,
+    request_client_auth: bool,
+    perform_client_auth: bool,
+    tickets: u8,
+    hello_retry: bool,
+    early_data: bool,
+    accept_early_data: bool,
+    psk_dhe: bool,
+    psk_from_ticket: bool,
+    post_handshake_auth: bool,
+    request_post_handshake_auth: bool,
+    key_update: Option<Peer>,
+    key_update_requested: bool,
+    middlebox_compat: bool,
+    ech: bool,
+    accept_ech: bool,
+    compress_certificate: bool,
+    compress_client_certificate: bool,
+}
+
+/// The most tickets a server issues in `combinations`, and the number of
+/// `ServerSendsNewSessionTicket` states `states` lists.
+pub const MAX_TICKETS: u8 = 2;
+
+impl ProtocolOptions {
+    /// Walk the handshake these options describe, from the ClientHello up to
+    /// the established connection and what follows it.
+    pub fn iter_handshake(self) -> HandshakeIter {
+        HandshakeIter { state: State::default(), opts: self }
+    }
+
+    /// The server sends this many NewSessionTickets once the handshake is
+    /// over, each good for one resumption (RFC 8446, 4.6.1). TLS 1.2 sends
+    /// at most one, inside the handshake (RFC 5077, 3.3).
+    pub fn with_tickets(mut self, tickets: u8) -> Self {
+        self.tickets = tickets;
+        self
+    }
+
+    pub fn tickets(&self) -> u8 {
+        self.tickets
+    }
+
+    /// The handshake is authenticated with a PSK the client offers and the
+    /// server accepts, rather than with the server's certificate
+    /// (RFC 8446, 2.2).
+    pub fn with_psk(mut self, psk: bool) -> Self {
+        self.psk = psk;
+        self
+    }
+
+    /// The PSK is combined with an (EC)DHE exchange (psk_dhe_ke) rather than
+    /// used alone (psk_ke, RFC 8446, 4.2.9).
+    pub fn with_psk_dhe(mut self, psk_dhe: bool) -> Self {
+        self.psk_dhe = psk_dhe;
+        self
+    }
+
+    /// The PSK was established by a NewSessionTicket of an earlier
+    /// connection, rather than agreed out of band (RFC 8446, 4.6.1).
+    pub fn with_psk_from_ticket(mut self, psk_from_ticket: bool) -> Self {
+        self.psk_from_ticket = psk_from_ticket;
+        self
+    }
+
+    /// The client sends post_handshake_auth, so the server may ask it to
+    /// authenticate once the handshake is over (RFC 8446, 4.2.6).
+    pub fn with_post_handshake_auth(mut self, post_handshake_auth: bool) -> Self {
+        self.post_handshake_auth = post_handshake_auth;
+        self
+    }
+
+    /// The server sends a CertificateRequest after the handshake, which it
+    /// can only do if the client sent post_handshake_auth.
+    pub fn with_request_post_handshake_auth(mut self, request: bool) -> Self {
+        self.request_post_handshake_auth = request;
+        self
+    }
+
+    /// Once connected, this peer updates its traffic keys with a KeyUpdate,
+    /// asking the other to update its own if `requested` (RFC 8446, 4.6.3).
+    pub fn with_key_update(mut self, key_update: Option<Peer>, requested: bool) -> Self {
+        self.key_update = key_update;
+        self.key_update_requested = requested;
+        self
+    }
+
+    /// Both peers send a dummy ChangeCipherSpec, so the handshake looks more
+    /// like a resumed TLS 1.2 one to middleboxes (RFC 8446, D.4). The client
+    /// also sends a legacy_session_id for the server to echo.
+    pub fn with_middlebox_compat(mut self, middlebox_compat: bool) -> Self {
+        self.middlebox_compat = middlebox_compat;
+        self
+    }
+
+    pub fn middlebox_compat(&self) -> bool {
+        self.middlebox_compat
+    }
+
+    /// The client encrypts its real ClientHelloInner in the ClientHelloOuter
+    /// it sends, which names only the public name of the server's ECH
+    /// config (RFC 9849).
+    pub fn with_ech(mut self, ech: bool) -> Self {
+        self.ech = ech;
+        self
+    }
+
+    /// The server decrypts the ClientHelloInner and carries on with it. A
+    /// server that rejects it carries on with the ClientHelloOuter,
+    /// authenticating as the public name, and the client aborts once the
+    /// server's Finished checks out (RFC 9849, 6.1.6).
+    pub fn with_accept_ech(mut self, accept_ech: bool) -> Self {
+        self.accept_ech = accept_ech;
+        self
+    }
+
+    /// The client sends compress_certificate with an algorithm the server
+    /// supports, so the server sends a CompressedCertificate in place of
+    /// its Certificate (RFC 8879).
+    pub fn with_compress_certificate(mut self, compress_certificate: bool) -> Self {
+        self.compress_certificate = compress_certificate;
+        self
+    }
+
+    /// Likewise the server in its CertificateRequest, for the client's
+    /// Certificate.
+    pub fn with_compress_client_certificate(mut self, compress: bool) -> Self {
+        self.compress_client_certificate = compress;
+        self
+    }
+
+    /// Whether the client offered ECH and the server turned it down.
+    pub fn ech_rejected(&self) -> bool {
+        self.ech && !self.accept_ech
+    }
+
+    /// Whether this handshake resumes an earlier connection, like tls12's
+    /// `resuming`: its PSK came from a ticket.
+    pub fn resuming(&self) -> bool {
+        self.psk && self.psk_from_ticket
+    }
+
+    /// Whether the handshake secret is extracted from an (EC)DHE shared
+    /// secret: always without a PSK, and with one only in psk_dhe_ke.
+    pub fn uses_dhe(&self) -> bool {
+        !self.psk || self.psk_dhe
+    }
+
+    /// The server answers the first ClientHello with a HelloRetryRequest,
+    /// asking for a key share in another group (RFC 8446, 4.1.4).
+    pub fn with_hello_retry(mut self, hello_retry: bool) -> Self {
+        self.hello_retry = hello_retry;
+        self
+    }
+
+    pub fn hello_retry(&self) -> bool {
+        self.hello_retry
+    }
+
+    /// The client sends early data with its ClientHello, which it can only
+    /// protect with a PSK (RFC 8446, 4.2.10).
+    pub fn with_early_data(mut self, early_data: bool) -> Self {
+        self.early_data = early_data;
+        self
+    }
+
+    /// The server accepts the client's early data, if it sends any.
+    pub fn with_accept_early_data(mut self, accept_early_data: bool) -> Self {
+        self.accept_early_data = accept_early_data;
+        self
+    }
+
+    /// Whether the server reads the client's early data. A server that
+    /// sends a HelloRetryRequest rejects it, whatever it would otherwise
+    /// do; one that rejects it skips the records it can't deprotect.
+    pub fn early_data_accepted(&self) -> bool {
+        self.psk && self.early_data && self.accept_early_data && !self.hello_retry
+    }
+
+    /// Whether the options describe a possible handshake. A client can only
+    /// authenticate when asked to, during or after the handshake, and after
+    /// it only if it sent post_handshake_auth. It can only send early data
+    /// with a PSK. A server can only accept early data that is sent and not
+    /// followed by a HelloRetryRequest. Where a PSK comes from and how it's
+    /// used only matter with one, and whether a KeyUpdate requests an answer
+    /// only if one is sent. A server can only accept an ECH offer that is
+    /// made, and one that rejects it goes no further than its Finished, and
+    /// has no PSK to go on: the ClientHelloOuter carries none. Certificate
+    /// compression only matters where there is a certificate.
+    fn consistent(&self) -> bool {
+        let asked = self.request_client_auth || self.request_post_handshake_auth;
+        (asked || !self.perform_client_auth)
+            && (self.post_handshake_auth || !self.request_post_handshake_auth)
+            && (self.psk || !self.early_data)
+            && (self.early_data_accepted() || !self.accept_early_data)
+            && (self.psk || !(self.psk_dhe || self.psk_from_ticket))
+            && (self.key_update.is_some() || !self.key_update_requested)
+            && (self.ech || !self.accept_ech)
+            && !(self.psk && self.compress_certificate)
+            && (self.request_client_auth && !self.psk || !self.compress_client_certificate)
+            && !(self.ech_rejected()
+                && (self.psk
+                    || self.perform_client_auth
+                    || self.tickets > 0
+                    || self.request_post_handshake_auth
+                    || self.key_update.is_some()))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageType {
+    ClientHello,
+    EarlyData,
+    HelloRetryRequest,
+    ServerHello,
+    EncryptedExtensions,
+    CertificateRequest,
+    Certificate,
+    CertificateVerify,
+    Finished,
+    EndOfEarlyData,
+    NewSessionTicket,
+    KeyUpdate,
+    ChangeCipherSpec,
+    Alert,
+    CompressedCertificate,
+}
+
+/// The message's name in the RFCs, e.g. `encrypted_extensions`. A
+/// HelloRetryRequest goes as a server_hello, but keeps the name its
+/// HandshakeType had before it was reserved (RFC 8446, B.3). The names of
+/// ChangeCipherSpec, Alert and EarlyData are those of their record content
+/// types (RFC 8446, 5.1).
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MessageType::*;
+        let name = match self {
+            ClientHello => "client_hello",
+            EarlyData => "application_data",
+            HelloRetryRequest => "hello_retry_request",
+            ServerHello => "server_hello",
+            EncryptedExtensions => "encrypted_extensions",
+            CertificateRequest => "certificate_request",
+            Certificate => "certificate",
+            CertificateVerify => "certificate_verify",
+            Finished => "finished",
+            EndOfEarlyData => "end_of_early_data",
+            NewSessionTicket => "new_session_ticket",
+            KeyUpdate => "key_update",
+            ChangeCipherSpec => "change_cipher_spec",
+            Alert => "alert",
+            CompressedCertificate => "compressed_certificate",
+        };
+        f.write_str(name)
+    }
+}
+
+impl MessageType {
+    /// Whether the message goes into the transcript hash. Early data is
+    /// application data, the ChangeCipherSpec of middlebox compatibility
+    /// mode is dropped by its receiver (RFC 8446, 5), and alerts are never
+    /// hashed.
+    pub fn in_transcript(self) -> bool {
+        !matches!(self, MessageType::EarlyData | MessageType::ChangeCipherSpec | MessageType::Alert)
+    }
+}
+
+/// Which of the two ClientHellos of an ECH offer the handshake goes on
+/// with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EchClientHello {
+    Outer,
+    Inner,
+}
+
+/// How the server tells the client what it made of its ECH offer
+/// (RFC 9849, 7.2).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EchSignal {
+    /// The last 8 bytes of the ServerHello random confirm acceptance.
+    ServerHelloConfirmation,
+    /// So does an encrypted_client_hello extension in a HelloRetryRequest.
+    HelloRetryRequestConfirmation,
+    /// A server that rejected ECH sends its current configs in an
+    /// encrypted_client_hello extension of its EncryptedExtensions.
+    RetryConfigs,
+}
+
+/// Whether the receiver of a KeyUpdate must answer with its own
+/// (RFC 8446, 4.6.3).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyUpdateRequest {
+    UpdateNotRequested,
+    UpdateRequested,
+}
+
+impl KeyUpdateRequest {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            KeyUpdateRequest::UpdateNotRequested => 0,
+            KeyUpdateRequest::UpdateRequested => 1,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<KeyUpdateRequest> {
+        match v {
+            0 => Some(KeyUpdateRequest::UpdateNotRequested),
+            1 => Some(KeyUpdateRequest::UpdateRequested),
+            _ => None,
+        }
+    }
+
+    /// The KeyUpdate a peer owes in answer to this one, if any. The answer
+    /// never requests another, so updates can't ping-pong.
+    pub fn response(self) -> Option<KeyUpdateRequest> {
+        match self {
+            KeyUpdateRequest::UpdateRequested => Some(KeyUpdateRequest::UpdateNotRequested),
+            KeyUpdateRequest::UpdateNotRequested => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    #[default]
+    ClientSendsClientHello,
+    ClientSendsChangeCipherSpec,
+    ClientSendsEarlyData,
+    ServerSendsHelloRetryRequest,
+    ClientSendsSecondClientHello,
+    ServerSendsServerHello,
+    ServerSendsChangeCipherSpec,
+    ServerSendsEncryptedExtensions,
+    ServerSendsCertificateRequest,
+    ServerSendsCertificate,
+    ServerSendsCompressedCertificate,
+    ServerSendsCertificateVerify,
+    ServerSendsFinished,
+    ClientSendsEndOfEarlyData,
+    ClientSendsCertificate,
+    ClientSendsCompressedCertificate,
+    ClientSendsCertificateVerify,
+    ClientSendsFinished,
+    /// The server sends the ticket with this index, counting from 0.
+    ServerSendsNewSessionTicket(u8),
+    ServerSendsPostHandshakeCertificateRequest,
+    ClientSendsPostHandshakeCertificate,
+    ClientSendsPostHandshakeCertificateVerify,
+    ClientSendsPostHandshakeFinished,
+    ClientSendsKeyUpdate,
+    ServerSendsKeyUpdate,
+    ClientSendsEchRequired,
+    Term,
+    /// The connection was torn down by an alert.
+    Aborted(AlertDescription),
+}
+
+impl State {
+    pub fn sends(self) -> Option<(Peer, MessageType)> {
+        use State::*;
+        match self {
+            ClientSendsClientHello => Some((Peer::Client, MessageType::ClientHello)),
+            ClientSendsChangeCipherSpec => Some((Peer::Client, MessageType::ChangeCipherSpec)),
+            ClientSendsEarlyData => Some((Peer::Client, MessageType::EarlyData)),
+            ServerSendsHelloRetryRequest => Some((Peer::Server, MessageType::HelloRetryRequest)),
+            ClientSendsSecondClientHello => Some((Peer::Client, MessageType::ClientHello)),
+            ServerSendsServerHello => Some((Peer::Server, MessageType::ServerHello)),
+            ServerSendsChangeCipherSpec => Some((Peer::Server, MessageType::ChangeCipherSpec)),
+            ServerSendsEncryptedExtensions => {
+                Some((Peer::Server, MessageType::EncryptedExtensions))
+            }
+            ServerSendsCertificateRequest => Some((Peer::Server, MessageType::CertificateRequest)),
+            ServerSendsCertificate => Some((Peer::Server, MessageType::Certificate)),
+            ServerSendsCompressedCertificate => {
+                Some((Peer::Server, MessageType::CompressedCertificate))
+            }
+            ServerSendsCertificateVerify => Some((Peer::Server, MessageType::CertificateVerify)),
+            ServerSendsFinished => Some((Peer::Server, MessageType::Finished)),
+            ClientSendsEndOfEarlyData => Some((Peer::Client, MessageType::EndOfEarlyData)),
+            ClientSendsCertificate => Some((Peer::Client, MessageType::Certificate)),
+            ClientSendsCompressedCertificate => {
+                Some((Peer::Client, MessageType::CompressedCertificate))
+            }
+            ClientSendsCertificateVerify => Some((Peer::Client, MessageType::CertificateVerify)),
+            ClientSendsFinished => Some((Peer::Client, MessageType::Finished)),
+            ServerSendsNewSessionTicket(_) => Some((Peer::Server, MessageType::NewSessionTicket)),
+            ServerSendsPostHandshakeCertificateRequest => {
+                Some((Peer::Server, MessageType::CertificateRequest))
+            }
+            ClientSendsPostHandshakeCertificate => Some((Peer::Client, MessageType::Certificate)),
+            ClientSendsPostHandshakeCertificateVerify => {
+                Some((Peer::Client, MessageType::CertificateVerify))
+            }
+            ClientSendsPostHandshakeFinished => Some((Peer::Client, MessageType::Finished)),
+            ClientSendsKeyUpdate => Some((Peer::Client, MessageType::KeyUpdate)),
+            ServerSendsKeyUpdate => Some((Peer::Server, MessageType::KeyUpdate)),
+            ClientSendsEchRequired => Some((Peer::Client, MessageType::Alert)),
+            Term | Aborted(_) => None,
+        }
+    }
+
+    /// What the message sent in this state says of the client's ECH offer.
+    pub fn ech_signal(self, opts: ProtocolOptions) -> Option<EchSignal> {
+        match self {
+            State::ServerSendsHelloRetryRequest if opts.ech && opts.accept_ech => {
+                Some(EchSignal::HelloRetryRequestConfirmation)
+            }
+            State::ServerSendsServerHello if opts.ech && opts.accept_ech => {
+                Some(EchSignal::ServerHelloConfirmation)
+            }
+            State::ServerSendsEncryptedExtensions if opts.ech_rejected() => {
+                Some(EchSignal::RetryConfigs)
+            }
+            _ => None,
+        }
+    }
+
+    /// The request_update of the KeyUpdate sent in this state: the
+    /// initiator's as configured, the answer's never requested.
+    pub fn key_update_request(self, opts: ProtocolOptions) -> Option<KeyUpdateRequest> {
+        let (peer, _) = self.sends().filter(|(_, msg)| *msg == MessageType::KeyUpdate)?;
+        if Some(peer) == opts.key_update && opts.key_update_requested {
+            Some(KeyUpdateRequest::UpdateRequested)
+        } else {
+            Some(KeyUpdateRequest::UpdateNotRequested)
+        }
+    }
+}
+
+pub fn step(st: State, opts: ProtocolOptions) -> State {
+    use State::*;
+
+    match st {
+        ClientSendsClientHello => {
+            // A client sending early data sends its ChangeCipherSpec first
+            // (RFC 8446, D.4).
+            if opts.early_data && opts.middlebox_compat {
+                ClientSendsChangeCipherSpec
+            } else if opts.early_data {
+                ClientSendsEarlyData
+            } else {
+                after_client_hello(opts)
+            }
+        }
+        ClientSendsEarlyData => after_client_hello(opts),
+        ServerSendsHelloRetryRequest => {
+            if opts.middlebox_compat {
+                ServerSendsChangeCipherSpec
+            } else {
+                second_client_hello(opts)
+            }
+        }
+        // A server can send at most one HelloRetryRequest: the second
+        // ClientHello gets a ServerHello or the handshake is aborted
+        // (RFC 8446, 4.1.4).
+        ClientSendsSecondClientHello => ServerSendsServerHello,
+        ServerSendsServerHello => {
+            if opts.middlebox_compat && !opts.hello_retry {
+                ServerSendsChangeCipherSpec
+            } else {
+                ServerSendsEncryptedExtensions
+            }
+        }
+        // Each peer sends one ChangeCipherSpec: the server right after its
+        // first message, and the client before its second flight unless
+        // early data came first.
+        ServerSendsChangeCipherSpec => {
+            if opts.hello_retry {
+                second_client_hello(opts)
+            } else {
+                ServerSendsEncryptedExtensions
+            }
+        }
+        ClientSendsChangeCipherSpec => {
+            if opts.early_data {
+                ClientSendsEarlyData
+            } else if opts.hello_retry {
+                ClientSendsSecondClientHello
+            } else {
+                client_flight(opts)
+            }
+        }
+        ServerSendsEncryptedExtensions => {
+            // A server authenticating with a PSK sends neither its certificate
+            // nor a CertificateRequest (RFC 8446, 4.3.2).
+            if opts.psk {
+                ServerSendsFinished
+            } else if opts.request_client_auth {
+                ServerSendsCertificateRequest
+            } else {
+                server_certificate(opts)
+            }
+        }
+        ServerSendsCertificateRequest => server_certificate(opts),
+        ServerSendsCertificate | ServerSendsCompressedCertificate => ServerSendsCertificateVerify,
+        ServerSendsCertificateVerify => ServerSendsFinished,
+        ServerSendsFinished => {
+            // Accepted early data is ended before the client's flight
+            // (RFC 8446, 4.5).
+            if opts.early_data_accepted() {
+                ClientSendsEndOfEarlyData
+            } else if opts.middlebox_compat && !opts.early_data && !opts.hello_retry {
+                ClientSendsChangeCipherSpec
+            } else {
+                client_flight(opts)
+            }
+        }
+        ClientSendsEndOfEarlyData => client_flight(opts),
+        ClientSendsCertificate | ClientSendsCompressedCertificate => {
+            // An empty client Certificate is not followed by CertificateVerify.
+            if opts.ech_rejected() {
+                ClientSendsEchRequired
+            } else if opts.perform_client_auth {
+                ClientSendsCertificateVerify
+            } else {
+                ClientSendsFinished
+            }
+        }
+        ClientSendsCertificateVerify => ClientSendsFinished,
+        ClientSendsFinished => {
+            if opts.tickets > 0 {
+                ServerSendsNewSessionTicket(0)
+            } else {
+                connected(opts)
+            }
+        }
+        ServerSendsNewSessionTicket(n) => {
+            if n + 1 < opts.tickets {
+                ServerSendsNewSessionTicket(n + 1)
+            } else {
+                connected(opts)
+            }
+        }
+        // The post-handshake exchange is protected with the application
+        // keys, its Finished keyed from the client's application traffic
+        // secret (RFC 8446, 4.6.2 and 4.4).
+        ServerSendsPostHandshakeCertificateRequest => ClientSendsPostHandshakeCertificate,
+        ClientSendsPostHandshakeCertificate => {
+            if opts.perform_client_auth {
+                ClientSendsPostHandshakeCertificateVerify
+            } else {
+                ClientSendsPostHandshakeFinished
+            }
+        }
+        ClientSendsPostHandshakeCertificateVerify => ClientSendsPostHandshakeFinished,
+        ClientSendsPostHandshakeFinished => key_update(opts),
+        // A client whose ECH offer was rejected aborts with ech_required, to
+        // retry with the retry_configs.
+        ClientSendsEchRequired => Aborted(AlertDescription::EchRequired),
+        // A requested update obliges the other peer to send its own.
+        ClientSendsKeyUpdate | ServerSendsKeyUpdate => {
+            let request = st.key_update_request(opts).and_then(KeyUpdateRequest::response);
+            match (request, st) {
+                (Some(_), ClientSendsKeyUpdate) => ServerSendsKeyUpdate,
+                (Some(_), _) => ClientSendsKeyUpdate,
+                (None, _) => Term,
+            }
+        }
+        Term => Term,
+        Aborted(desc) => Aborted(desc),
+    }
+}
+
+fn after_client_hello(opts: ProtocolOptions) -> State {
+    if opts.hello_retry {
+        State::ServerSendsHelloRetryRequest
+    } else {
+        State::ServerSendsServerHello
+    }
+}
+
+fn second_client_hello(opts: ProtocolOptions) -> State {
+    if opts.middlebox_compat && !opts.early_data {
+        State::ClientSendsChangeCipherSpec
+    } else {
+        State::ClientSendsSecondClientHello
+    }
+}
+
+fn server_certificate(opts: ProtocolOptions) -> State {
+    if opts.compress_certificate {
+        State::ServerSendsCompressedCertificate
+    } else {
+        State::ServerSendsCertificate
+    }
+}
+
+// A client whose ECH offer was rejected still answers a CertificateRequest,
+// with an empty Certificate, before its alert (RFC 9849, 6.1.6).
+fn client_flight(opts: ProtocolOptions) -> State {
+    if opts.request_client_auth && !opts.psk && opts.compress_client_certificate {
+        State::ClientSendsCompressedCertificate
+    } else if opts.request_client_auth && !opts.psk {
+        State::ClientSendsCertificate
+    } else if opts.ech_rejected() {
+        State::ClientSendsEchRequired
+    } else {
+        State::ClientSendsFinished
+    }
+}
+
+// Once connected, a server can ask a client that sent post_handshake_auth
+// to authenticate.
+fn connected(opts: ProtocolOptions) -> State {
+    if opts.post_handshake_auth && opts.request_post_handshake_auth {
+        State::ServerSendsPostHandshakeCertificateRequest
+    } else {
+        key_update(opts)
+    }
+}
+
+fn key_update(opts: ProtocolOptions) -> State {
+    match opts.key_update {
+        Some(Peer::Client) => State::ClientSendsKeyUpdate,
+        Some(Peer::Server) => State::ServerSendsKeyUpdate,
+        None => State::Term,
+    }
+}
+
+/// The traffic keys a peer protects records with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Keys {
+    Early,
+    Handshake,
+    Application,
+}
+
+/// A key schedule event tied to the handshake, computed by
+/// `tls13_keysched`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    /// The early secret, from the PSK if there is one.
+    DeriveEarlySecret,
+    /// The client's PSK binder: an HMAC keyed from the early secret, with
+    /// "res binder" for a ticket's PSK and "ext binder" otherwise, over the
+    /// transcript up to the ClientHello's binders (RFC 8446, 4.2.11.2).
+    ComputeBinder,
+    /// The client early traffic secret and the early exporter master
+    /// secret, over the transcript up to the ClientHello.
+    DeriveEarlyTrafficSecrets,
+    /// Both peers replace the first ClientHello in the transcript with a
+    /// message_hash of it (RFC 8446, 4.4.1), computed by
+    /// `tls13_keysched::message_hash`.
+    ReplaceClientHelloWithHash,
+    /// The handshake secret and both handshake traffic secrets, over the
+    /// transcript up to the ServerHello. See `ProtocolOptions::uses_dhe`.
+    DeriveHandshakeSecrets,
+    /// The master secret, both application traffic secrets and the exporter
+    /// master secret, over the transcript up to the server's Finished.
+    DeriveApplicationSecrets,
+    /// The resumption master secret, over the transcript up to the client's
+    /// Finished.
+    DeriveResumptionSecret,
+    /// The peer starts protecting the records it sends with these keys.
+    SwitchToWriteKeys(Peer, Keys),
+    /// The peer starts expecting records protected with these keys.
+    SwitchToReadKeys(Peer, Keys),
+    /// The sender of a KeyUpdate moves on to its next application traffic
+    /// secret for writing, and the receiver for reading
+    /// (`tls13_keysched::next_application_traffic_secret`).
+    UpdateWriteKeys(Peer),
+    UpdateReadKeys(Peer),
+    /// Both peers take this ClientHello of an ECH offer as the one the
+    /// transcript starts from (RFC 9849, 6.1).
+    ChooseClientHello(EchClientHello),
+    /// The peer goes back to sending unprotected records, as a client that
+    /// sent early data does for its second ClientHello (RFC 8446, 4.1.2).
+    DiscardWriteKeys(Peer),
+}
+
+/// Like `step`, but also returns what the peers do with their keys once the
+/// message sent in `st` has gone over. Everything after the ServerHello is
+/// protected: the server's flight with its handshake keys, the client's with
+/// its own once it has the server's Finished, and what follows either
+/// Finished with the sender's application keys (RFC 8446, 7.1 and 2).
+/// Early data is protected with the client's early keys, which the server
+/// only reads with if it accepts it; the client then keeps writing with them
+/// until its EndOfEarlyData.
+pub fn step_with_actions(st: State, opts: ProtocolOptions) -> (State, Vec<Action>) {
+    use Action::*;
+    use Peer::*;
+    let actions = match st {
+        State::ClientSendsClientHello if opts.early_data => {
+            let mut actions = vec![
+                DeriveEarlySecret,
+                ComputeBinder,
+                DeriveEarlyTrafficSecrets,
+                SwitchToWriteKeys(Client, Keys::Early),
+            ];
+            if opts.early_data_accepted() {
+                actions.push(SwitchToReadKeys(Server, Keys::Early));
+            }
+            actions
+        }
+        State::ClientSendsClientHello if opts.psk => vec![DeriveEarlySecret, ComputeBinder],
+        State::ClientSendsClientHello => vec![DeriveEarlySecret],
+        State::ServerSendsHelloRetryRequest if opts.early_data => {
+            vec![ReplaceClientHelloWithHash, DiscardWriteKeys(Client)]
+        }
+        State::ServerSendsHelloRetryRequest => vec![ReplaceClientHelloWithHash],
+        // The binders of the second ClientHello cover the new transcript.
+        State::ClientSendsSecondClientHello if opts.psk => vec![ComputeBinder],
+        State::ServerSendsServerHello => vec![
+            DeriveHandshakeSecrets,
+            SwitchToWriteKeys(Server, Keys::Handshake),
+            SwitchToReadKeys(Client, Keys::Handshake),
+        ],
+        State::ServerSendsFinished if opts.early_data_accepted() => vec![
+            DeriveApplicationSecrets,
+            SwitchToWriteKeys(Server, Keys::Application),
+            SwitchToReadKeys(Client, Keys::Application),
+        ],
+        State::ServerSendsFinished => vec![
+            DeriveApplicationSecrets,
+            SwitchToWriteKeys(Server, Keys::Application),
+            SwitchToReadKeys(Client, Keys::Application),
+            SwitchToWriteKeys(Client, Keys::Handshake),
+            SwitchToReadKeys(Server, Keys::Handshake),
+        ],
+        State::ClientSendsKeyUpdate => vec![UpdateWriteKeys(Client), UpdateReadKeys(Server)],
+        State::ServerSendsKeyUpdate => vec![UpdateWriteKeys(Server), UpdateReadKeys(Client)],
+        State::ClientSendsEndOfEarlyData => vec![
+            SwitchToWriteKeys(Client, Keys::Handshake),
+            SwitchToReadKeys(Server, Keys::Handshake),
+        ],
+        State::ClientSendsFinished => vec![
+            DeriveResumptionSecret,
+            SwitchToWriteKeys(Client, Keys::Application),
+            SwitchToReadKeys(Server, Keys::Application),
+        ],
+        _ => vec![],
+    };
+    let mut actions = actions;
+    let first = st == State::ServerSendsHelloRetryRequest
+        || (st == State::ServerSendsServerHello && !opts.hello_retry);
+    if opts.ech && first {
+        let hello = if opts.accept_ech { EchClientHello::Inner } else { EchClientHello::Outer };
+        actions.insert(0, ChooseClientHello(hello));
+    }
+    (step(st, opts), actions)
+}
+
+/// The messages of a handshake in order, with the state sending each. See
+/// `ProtocolOptions::iter_handshake`.
+#[derive(Debug, Clone)]
+pub struct HandshakeIter {
+    state: State,
+    opts: ProtocolOptions,
+}
+
+impl Iterator for HandshakeIter {
+    type Item = (State, Peer, MessageType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let st = self.state;
+        let (peer, msg) = st.sends()?;
+        self.state = step(st, self.opts);
+        Some((st, peer, msg))
+    }
+}
+
+/// A linearised handshake: every message in order, with the state sending
+/// it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    steps: Vec<(State, Peer, MessageType)>,
+}
+
+impl Trace {
+    pub fn linearise(opts: ProtocolOptions) -> Trace {
+        Trace { steps: opts.iter_handshake().collect() }
+    }
+
+    pub fn steps(&self) -> &[(State, Peer, MessageType)] {
+        &self.steps
+    }
+
+    pub fn messages(&self) -> Vec<MessageType> {
+        self.steps.iter().map(|(_, _, msg)| *msg).collect()
+    }
+
+    pub fn peers(&self) -> Vec<Peer> {
+        self.steps.iter().map(|(_, peer, _)| *peer).collect()
+    }
+
+    /// The messages grouped into flights: each run of messages one peer
+    /// sends before waiting for the other.
+    pub fn flights(&self) -> Vec<(Peer, Vec<MessageType>)> {
+        let mut flights: Vec<(Peer, Vec<MessageType>)> = Vec::new();
+        for (_, peer, msg) in &self.steps {
+            match flights.last_mut() {
+                Some((last, msgs)) if last == peer => msgs.push(*msg),
+                _ => flights.push((*peer, vec![*msg])),
+            }
+        }
+        flights
+    }
+
+    /// The messages of the transcript `peer`'s handshake Finished is
+    /// computed over: every one before it that is `in_transcript`. After a
+    /// HelloRetryRequest the first ClientHello stands for the message_hash
+    /// that replaces it (RFC 8446, 4.4.1). `None` if `peer` sends no
+    /// Finished.
+    pub fn transcript(&self, peer: Peer) -> Option<Vec<MessageType>> {
+        let finished = self
+            .steps
+            .iter()
+            .position(|(_, p, msg)| *p == peer && *msg == MessageType::Finished)?;
+        Some(
+            self.steps[..finished]
+                .iter()
+                .map(|(_, _, msg)| *msg)
+                .filter(|msg| msg.in_transcript())
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// One message per line.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (_, peer, msg) in &self.steps {
+            writeln!(f, "{}: {}", peer, msg)?;
+        }
+        Ok(())
+    }
+}
+
+impl graph::Protocol for ProtocolOptions {
+    type State = State;
+
+    const NAME: &'static str = "tls13";
+
+    fn step(self, st: State) -> State {
+        step(st, self)
+    }
+
+    fn sends(st: State) -> Option<(Peer, String)> {
+        st.sends().map(|(peer, msg)| (peer, format!("{:?}", msg)))
+    }
+
+    fn states() -> Vec<State> {
+        use State::*;
+        let mut states = vec![
+            ClientSendsClientHello,
+            ClientSendsChangeCipherSpec,
+            ClientSendsEarlyData,
+            ServerSendsHelloRetryRequest,
+            ClientSendsSecondClientHello,
+            ServerSendsServerHello,
+            ServerSendsChangeCipherSpec,
+            ServerSendsEncryptedExtensions,
+            ServerSendsCertificateRequest,
+            ServerSendsCertificate,
+            ServerSendsCompressedCertificate,
+            ServerSendsCertificateVerify,
+            ServerSendsFinished,
+            ClientSendsEndOfEarlyData,
+            ClientSendsCertificate,
+            ClientSendsCompressedCertificate,
+            ClientSendsCertificateVerify,
+            ClientSendsFinished,
+        ];
+        states.extend((0..MAX_TICKETS).map(ServerSendsNewSessionTicket));
+        states.extend_from_slice(&[
+            ServerSendsPostHandshakeCertificateRequest,
+            ClientSendsPostHandshakeCertificate,
+            ClientSendsPostHandshakeCertificateVerify,
+            ClientSendsPostHandshakeFinished,
+            ClientSendsKeyUpdate,
+            ServerSendsKeyUpdate,
+            ClientSendsEchRequired,
+            Term,
+            Aborted(AlertDescription::EchRequired),
+        ]);
+        states
+    }
+
+    /// Every combination of options that is `consistent`. Each check of it
+    /// comes after the fields it depends on, so the prefixes it refuses can
+    /// be dropped before the remaining fields are expanded.
+    fn combinations() -> Vec<Self> {
+        let keep: fn(&ProtocolOptions) -> bool = ProtocolOptions::consistent;
+        let b = &[false, true];
+        let mut all: graph::Combinations<Self> = Box::new(Some(Self::default()).into_iter());
+        all = graph::expand(all, b, |o, v| o.ech = v, keep);
+        all = graph::expand(all, b, |o, v| o.accept_ech = v, keep);
+        all = graph::expand(all, b, |o, v| o.psk = v, keep);
+        all = graph::expand(all, b, |o, v| o.psk_dhe = v, keep);
+        all = graph::expand(all, b, |o, v| o.psk_from_ticket = v, keep);
+        all = graph::expand(all, b, |o, v| o.hello_retry = v, keep);
+        all = graph::expand(all, b, |o, v| o.early_data = v, keep);
+        all = graph::expand(all, b, |o, v| o.accept_early_data = v, keep);
+        all = graph::expand(all, b, |o, v| o.request_client_auth = v, keep);
+        all = graph::expand(all, b, |o, v| o.post_handshake_auth = v, keep);
+        all = graph::expand(all, b, |o, v| o.request_post_handshake_auth = v, keep);
+        all = graph::expand(all, b, |o, v| o.perform_client_auth = v, keep);
+        all = graph::expand(all, &[0, 1, MAX_TICKETS], |o, v| o.tickets = v, keep);
+        let peers = &[None, Some(Peer::Client), Some(Peer::Server)];
+        all = graph::expand(all, peers, |o, v| o.key_update = v, keep);
+        all = graph::expand(all, b, |o, v| o.key_update_requested = v, keep);
+        all = graph::expand(all, b, |o, v| o.middlebox_compat = v, keep);
+        all = graph::expand(all, b, |o, v| o.compress_certificate = v, keep);
+        all = graph::expand(all, b, |o, v| o.compress_client_certificate = v, keep);
+        all.collect()
+    }
+
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("psk", self.psk.to_string()),
+            ("request_client_auth", self.request_client_auth.to_string()),
+            ("perform_client_auth", self.perform_client_auth.to_string()),
+            ("tickets", self.tickets.to_string()),
+            ("hello_retry", self.hello_retry.to_string()),
+            ("early_data", self.early_data.to_string()),
+            ("accept_early_data", self.accept_early_data.to_string()),
+            ("psk_dhe", self.psk_dhe.to_string()),
+            ("psk_from_ticket", self.psk_from_ticket.to_string()),
+            ("post_handshake_auth", self.post_handshake_auth.to_string()),
+            ("request_post_handshake_auth", self.request_post_handshake_auth.to_string()),
+            ("key_update", format!("{:?}", self.key_update)),
+            ("key_update_requested", self.key_update_requested.to_string()),
+            ("middlebox_compat", self.middlebox_compat.to_string()),
+            ("ech", self.ech.to_string()),
+            ("accept_ech", self.accept_ech.to_string()),
+            ("compress_certificate", self.compress_certificate.to_string()),
+            ("compress_client_certificate", self.compress_client_certificate.to_string()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::Protocol;
+    use crate::tls12::{self, AlertLevel};
+
+    fn linearise(opts: ProtocolOptions) -> Vec<(Peer, MessageType)> {
+        opts.iter_handshake().map(|(_, peer, msg)| (peer, msg)).collect()
+    }
+
+    #[test]
+    fn full() {
+        use MessageType::*;
+        let trace = linearise(ProtocolOptions::default());
+        assert_eq!(
+            trace.iter().map(|(_, m)| *m).collect::<Vec<_>>(),
+            vec![
+                ClientHello,
+                ServerHello,
+                EncryptedExtensions,
+                Certificate,
+                CertificateVerify,
+                Finished,
+                Finished
+            ]
+        );
+        assert_eq!(trace[5].0, Peer::Server);
+        assert_eq!(trace[6].0, Peer::Client);
+    }
+
+    #[test]
+    fn trace() {
+        use MessageType::*;
+        let trace = Trace::linearise(ProtocolOptions::default().with_hello_retry(true));
+        assert_eq!(trace.len(), 9);
+        let second = (State::ClientSendsSecondClientHello, Peer::Client, ClientHello);
+        assert_eq!(trace.steps()[2], second);
+        assert_eq!(trace.peers()[..2], [Peer::Client, Peer::Server]);
+        let flight =
+            vec![ServerHello, EncryptedExtensions, Certificate, CertificateVerify, Finished];
+        assert_eq!(
+            trace.flights(),
+            vec![
+                (Peer::Client, vec![ClientHello]),
+                (Peer::Server, vec![HelloRetryRequest]),
+                (Peer::Client, vec![ClientHello]),
+                (Peer::Server, flight.clone()),
+                (Peer::Client, vec![Finished]),
+            ]
+        );
+        let transcript = [&[ClientHello, HelloRetryRequest, ClientHello], &flight[..]].concat();
+        assert_eq!(trace.transcript(Peer::Server), Some(transcript[..7].to_vec()));
+        assert_eq!(trace.transcript(Peer::Client), Some(transcript));
+        let shown = trace.to_string();
+        assert!(shown.starts_with("client: client_hello\nserver: hello_retry_request\n"));
+        assert_eq!(shown.lines().count(), trace.len());
+
+        // The ChangeCipherSpecs of middlebox compatibility mode are left out
+        // of the transcript.
+        let compat = Trace::linearise(ProtocolOptions::default().with_middlebox_compat(true));
+        assert!(compat.messages().contains(&ChangeCipherSpec));
+        let server = Trace::linearise(ProtocolOptions::default()).transcript(Peer::Server);
+        assert_eq!(compat.transcript(Peer::Server), server);
+
+        // A client that aborts sends no Finished.
+        let rejected = ProtocolOptions::default().with_ech(true);
+        assert_eq!(Trace::linearise(rejected).transcript(Peer::Client), None);
+        assert_eq!(Trace::linearise(rejected).transcript(Peer::Server).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn psk() {
+        use MessageType::*;
+        let trace = linearise(ProtocolOptions { psk: true, ..Default::default() });
+        assert_eq!(
+            trace.iter().map(|(_, m)| *m).collect::<Vec<_>>(),
+            vec![ClientHello, ServerHello, EncryptedExtensions, Finished, Finished]
+        );
+    }
+
+    #[test]
+    fn psk_ignores_client_auth() {
+        let with_auth = linearise(ProtocolOptions {
+            psk: true,
+            request_client_auth: true,
+            perform_client_auth: true,
+            ..Default::default()
+        });
+        let without_auth = linearise(ProtocolOptions { psk: true, ..Default::default() });
+        assert_eq!(with_auth, without_auth);
+    }
+
+    #[test]
+    fn perform_client_auth() {
+        use MessageType::*;
+        let trace = linearise(ProtocolOptions {
+            request_client_auth: true,
+            perform_client_auth: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            trace,
+            vec![
+                (Peer::Client, ClientHello),
+                (Peer::Server, ServerHello),
+                (Peer::Server, EncryptedExtensions),
+                (Peer::Server, CertificateRequest),
+                (Peer::Server, Certificate),
+                (Peer::Server, CertificateVerify),
+                (Peer::Server, Finished),
+                (Peer::Client, Certificate),
+                (Peer::Client, CertificateVerify),
+                (Peer::Client, Finished),
+            ]
+        );
+    }
+
+    #[test]
+    fn request_client_auth() {
+        let trace = linearise(ProtocolOptions { request_client_auth: true, ..Default::default() });
+        assert!(trace.contains(&(Peer::Client, MessageType::Certificate)));
+        assert!(!trace.contains(&(Peer::Client, MessageType::CertificateVerify)));
+    }
+
+    /// The handshake messages of RFC 8448, 3 ("Simple 1-RTT Handshake"), in
+    /// the order they're sent.
+    const RFC8448_SIMPLE_1RTT: &[(Peer, MessageType)] = &[
+        (Peer::Client, MessageType::ClientHello),
+        (Peer::Server, MessageType::ServerHello),
+        (Peer::Server, MessageType::EncryptedExtensions),
+        (Peer::Server, MessageType::Certificate),
+        (Peer::Server, MessageType::CertificateVerify),
+        (Peer::Server, MessageType::Finished),
+        (Peer::Client, MessageType::Finished),
+        (Peer::Server, MessageType::NewSessionTicket),
+    ];
+
+    #[test]
+    fn rfc8448_simple_1rtt() {
+        let opts = ProtocolOptions::default().with_tickets(1);
+        assert_eq!(linearise(opts), RFC8448_SIMPLE_1RTT);
+    }
+
+    /// RFC 8448, 4 ("Resumed 0-RTT Handshake"): psk_dhe_ke with the ticket
+    /// of section 3, and early data the server accepts.
+    const RFC8448_RESUMED_0RTT: &[(Peer, MessageType)] = &[
+        (Peer::Client, MessageType::ClientHello),
+        (Peer::Client, MessageType::EarlyData),
+        (Peer::Server, MessageType::ServerHello),
+        (Peer::Server, MessageType::EncryptedExtensions),
+        (Peer::Server, MessageType::Finished),
+        (Peer::Client, MessageType::EndOfEarlyData),
+        (Peer::Client, MessageType::Finished),
+    ];
+
+    #[test]
+    fn rfc8448_resumed_0rtt() {
+        let opts = ProtocolOptions::default()
+            .with_psk(true)
+            .with_psk_dhe(true)
+            .with_psk_from_ticket(true)
+            .with_early_data(true)
+            .with_accept_early_data(true);
+        assert_eq!(linearise(opts), RFC8448_RESUMED_0RTT);
+        assert_eq!(
+            key_schedule(opts),
+            vec![
+                Action::DeriveEarlySecret,
+                Action::ComputeBinder,
+                Action::DeriveEarlyTrafficSecrets,
+                Action::DeriveHandshakeSecrets,
+                Action::DeriveApplicationSecrets,
+                Action::DeriveResumptionSecret,
+            ]
+        );
+    }
+
+    /// RFC 8448, 5 ("HelloRetryRequest"): the server asks for a P-256 key
+    /// share in place of the client's X25519 one.
+    const RFC8448_HELLO_RETRY: &[(Peer, MessageType)] = &[
+        (Peer::Client, MessageType::ClientHello),
+        (Peer::Server, MessageType::HelloRetryRequest),
+        (Peer::Client, MessageType::ClientHello),
+        (Peer::Server, MessageType::ServerHello),
+        (Peer::Server, MessageType::EncryptedExtensions),
+        (Peer::Server, MessageType::Certificate),
+        (Peer::Server, MessageType::CertificateVerify),
+        (Peer::Server, MessageType::Finished),
+        (Peer::Client, MessageType::Finished),
+    ];
+
+    #[test]
+    fn rfc8448_hello_retry() {
+        let opts = ProtocolOptions::default().with_hello_retry(true);
+        assert_eq!(linearise(opts), RFC8448_HELLO_RETRY);
+        assert_eq!(
+            key_schedule(opts),
+            vec![
+                Action::DeriveEarlySecret,
+                Action::ReplaceClientHelloWithHash,
+                Action::DeriveHandshakeSecrets,
+                Action::DeriveApplicationSecrets,
+                Action::DeriveResumptionSecret,
+            ]
+        );
+    }
+
+    /// The key schedule actions of a handshake in order, leaving out the
+    /// key switches.
+    fn key_schedule(opts: ProtocolOptions) -> Vec<Action> {
+        let mut state = State::default();
+        let mut v = Vec::new();
+        while state.sends().is_some() {
+            let (next, actions) = step_with_actions(state, opts);
+            v.extend(actions.into_iter().filter(|action| {
+                !matches!(action, Action::SwitchToWriteKeys(..) | Action::SwitchToReadKeys(..))
+            }));
+            state = next;
+        }
+        v
+    }
+
+    #[test]
+    fn actions() {
+        let run = |opts| {
+            let mut state = State::default();
+            let mut v = Vec::new();
+            while let Some(send) = state.sends() {
+                let (next, actions) = step_with_actions(state, opts);
+                v.push((send, actions));
+                state = next;
+            }
+            v
+        };
+        let derived = run(ProtocolOptions::default())
+            .into_iter()
+            .flat_map(|(_, actions)| actions)
+            .filter(|action| {
+                !matches!(action, Action::SwitchToWriteKeys(..) | Action::SwitchToReadKeys(..))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            derived,
+            vec![
+                Action::DeriveEarlySecret,
+                Action::DeriveHandshakeSecrets,
+                Action::DeriveApplicationSecrets,
+                Action::DeriveResumptionSecret,
+            ]
+        );
+
+        // Everything after the ServerHello is protected with keys its receiver
+        // reads with.
+        for opts in ProtocolOptions::combinations() {
+            let (mut client, mut server) = ((None, None), (None, None));
+            for ((peer, msg), actions) in run(opts) {
+                let (sender, receiver) = match peer {
+                    Peer::Client => (client.0, server.1),
+                    Peer::Server => (server.0, client.1),
+                };
+                if !matches!(
+                    msg,
+                    MessageType::ClientHello
+                        | MessageType::HelloRetryRequest
+                        | MessageType::ServerHello
+                        | MessageType::ChangeCipherSpec
+                ) {
+                    assert!(sender.is_some(), "{:?} {:?}", opts, msg);
+                }
+                if msg == MessageType::ChangeCipherSpec {
+                    // Always sent unprotected, and dropped.
+                } else if msg == MessageType::EarlyData && !opts.early_data_accepted() {
+                    // Rejected early data is skipped.
+                    assert_eq!(receiver, None, "{:?}", opts);
+                } else {
+                    assert_eq!(sender, receiver, "{:?} {:?}", opts, msg);
+                }
+                for action in actions {
+                    match action {
+                        Action::SwitchToWriteKeys(Peer::Client, keys) => client.0 = Some(keys),
+                        Action::SwitchToReadKeys(Peer::Client, keys) => client.1 = Some(keys),
+                        Action::SwitchToWriteKeys(Peer::Server, keys) => server.0 = Some(keys),
+                        Action::SwitchToReadKeys(Peer::Server, keys) => server.1 = Some(keys),
+                        Action::DiscardWriteKeys(Peer::Client) => client.0 = None,
+                        Action::DiscardWriteKeys(Peer::Server) => server.0 = None,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hello_retry() {
+        use MessageType::*;
+        let opts = ProtocolOptions::default().with_hello_retry(true);
+        assert_eq!(
+            linearise(opts)[..4],
+            [
+                (Peer::Client, ClientHello),
+                (Peer::Server, HelloRetryRequest),
+                (Peer::Client, ClientHello),
+                (Peer::Server, ServerHello),
+            ]
+        );
+        assert_eq!(linearise(opts)[4..], linearise(ProtocolOptions::default())[2..]);
+
+        // The transcript is replaced once, before the second ClientHello.
+        let (_, actions) = step_with_actions(State::ServerSendsHelloRetryRequest, opts);
+        assert_eq!(actions, vec![Action::ReplaceClientHelloWithHash]);
+        let (_, actions) = step_with_actions(State::ClientSendsSecondClientHello, opts);
+        assert_eq!(actions, vec![]);
+    }
+
+    #[test]
+    fn single_hello_retry() {
+        for opts in ProtocolOptions::combinations() {
+            let retries = linearise(opts)
+                .iter()
+                .filter(|(_, msg)| *msg == MessageType::HelloRetryRequest)
+                .count();
+            assert_eq!(retries, opts.hello_retry as usize, "{:?}", opts);
+        }
+    }
+
+    #[test]
+    fn resumption() {
+        let full = ProtocolOptions::default().with_tickets(1);
+        assert!(!full.resuming());
+        assert_eq!(linearise(full).last(), Some(&(Peer::Server, MessageType::NewSessionTicket)));
+
+        // The ticket's PSK resumes the connection, with or without (EC)DHE;
+        // either way the server doesn't authenticate again.
+        let psk = ProtocolOptions { psk: true, ..Default::default() }.with_psk_from_ticket(true);
+        for opts in &[psk, psk.with_psk_dhe(true)] {
+            assert!(opts.resuming());
+            let trace = linearise(*opts);
+            assert!(!trace.contains(&(Peer::Server, MessageType::Certificate)));
+            assert!(!trace.contains(&(Peer::Server, MessageType::CertificateVerify)));
+        }
+        assert!(!psk.uses_dhe() && psk.with_psk_dhe(true).uses_dhe());
+        assert!(ProtocolOptions::default().uses_dhe());
+        assert!(!ProtocolOptions::default().with_psk_from_ticket(true).resuming());
+    }
+
+    #[test]
+    fn binders() {
+        let binders = |opts| {
+            let mut state = State::default();
+            let mut binders = Vec::new();
+            while state.sends().is_some() {
+                let (next, actions) = step_with_actions(state, opts);
+                if actions.contains(&Action::ComputeBinder) {
+                    binders.push(state);
+                }
+                state = next;
+            }
+            binders
+        };
+        for opts in ProtocolOptions::combinations() {
+            let mut expected = Vec::new();
+            if opts.psk {
+                expected.push(State::ClientSendsClientHello);
+                if opts.hello_retry {
+                    expected.push(State::ClientSendsSecondClientHello);
+                }
+            }
+            assert_eq!(binders(opts), expected, "{:?}", opts);
+        }
+    }
+
+    #[test]
+    fn early_data() {
+        use MessageType::*;
+        let opts = ProtocolOptions { psk: true, ..Default::default() }.with_early_data(true);
+        let rejected = linearise(opts);
+        assert_eq!(rejected[..2], [(Peer::Client, ClientHello), (Peer::Client, EarlyData)]);
+        assert!(!rejected.contains(&(Peer::Client, EndOfEarlyData)));
+
+        let accepted = linearise(opts.with_accept_early_data(true));
+        assert_eq!(
+            accepted[accepted.len() - 3..],
+            [(Peer::Server, Finished), (Peer::Client, EndOfEarlyData), (Peer::Client, Finished)]
+        );
+
+        // A HelloRetryRequest rejects early data.
+        let opts = opts.with_accept_early_data(true).with_hello_retry(true);
+        assert!(!opts.early_data_accepted());
+        let trace = linearise(opts);
+        assert_eq!(trace[1..3], [(Peer::Client, EarlyData), (Peer::Server, HelloRetryRequest)]);
+        assert!(!trace.contains(&(Peer::Client, EndOfEarlyData)));
+    }
+
+    #[test]
+    fn early_data_combinations() {
+        for opts in ProtocolOptions::combinations() {
+            let trace = linearise(opts);
+            assert_eq!(trace.contains(&(Peer::Client, MessageType::EarlyData)), opts.early_data);
+            assert!(!opts.early_data || opts.psk, "{:?}", opts);
+            assert_eq!(
+                trace.contains(&(Peer::Client, MessageType::EndOfEarlyData)),
+                opts.early_data_accepted()
+            );
+        }
+    }
+
+    #[test]
+    fn post_handshake_auth() {
+        use MessageType::*;
+        let opts = ProtocolOptions::default()
+            .with_tickets(1)
+            .with_post_handshake_auth(true)
+            .with_request_post_handshake_auth(true);
+        let trace = linearise(ProtocolOptions { perform_client_auth: true, ..opts });
+        assert_eq!(trace[..RFC8448_SIMPLE_1RTT.len()], *RFC8448_SIMPLE_1RTT);
+        assert_eq!(
+            trace[RFC8448_SIMPLE_1RTT.len()..],
+            [
+                (Peer::Server, CertificateRequest),
+                (Peer::Client, Certificate),
+                (Peer::Client, CertificateVerify),
+                (Peer::Client, Finished),
+            ]
+        );
+        let declined = linearise(opts);
+        assert_eq!(declined.len(), RFC8448_SIMPLE_1RTT.len() + 3);
+        assert!(!declined.contains(&(Peer::Client, CertificateVerify)));
+
+        // Without the extension the server can't ask.
+        let unoffered = opts.with_post_handshake_auth(false);
+        assert_eq!(linearise(unoffered), RFC8448_SIMPLE_1RTT);
+        assert!(ProtocolOptions::combinations()
+            .iter()
+            .all(|o| o.post_handshake_auth || !o.request_post_handshake_auth));
+    }
+
+    #[test]
+    fn key_update() {
+        use KeyUpdateRequest::*;
+        let updates = |opts: ProtocolOptions| {
+            let mut state = State::default();
+            let mut v = Vec::new();
+            while let Some((peer, msg)) = state.sends() {
+                if msg == MessageType::KeyUpdate {
+                    v.push((peer, state.key_update_request(opts).unwrap()));
+                }
+                state = step(state, opts);
+            }
+            v
+        };
+        let opts = ProtocolOptions::default();
+        assert_eq!(updates(opts), vec![]);
+        let client = opts.with_key_update(Some(Peer::Client), false);
+        assert_eq!(updates(client), vec![(Peer::Client, UpdateNotRequested)]);
+        let server = opts.with_key_update(Some(Peer::Server), true);
+        assert_eq!(
+            updates(server),
+            vec![(Peer::Server, UpdateRequested), (Peer::Client, UpdateNotRequested)]
+        );
+        assert_eq!(linearise(server)[..linearise(opts).len()], linearise(opts)[..]);
+
+        // Every requested update is answered, by the other peer, and each
+        // KeyUpdate rotates exactly the keys its sender writes with.
+        for opts in ProtocolOptions::combinations() {
+            let updates = updates(opts);
+            for (i, &(peer, request)) in updates.iter().enumerate() {
+                let answer = updates.get(i + 1).map(|&(p, r)| (p == peer, r));
+                assert_eq!(request.response().map(|r| (false, r)), answer, "{:?}", opts);
+            }
+        }
+        let (_, actions) = step_with_actions(State::ClientSendsKeyUpdate, client);
+        assert_eq!(
+            actions,
+            vec![Action::UpdateWriteKeys(Peer::Client), Action::UpdateReadKeys(Peer::Server)]
+        );
+
+        for request in &[UpdateNotRequested, UpdateRequested] {
+            assert_eq!(KeyUpdateRequest::from_u8(request.to_u8()), Some(*request));
+        }
+        assert_eq!(KeyUpdateRequest::from_u8(2), None);
+    }
+
+    #[test]
+    fn tickets() {
+        for tickets in 0..=MAX_TICKETS + 1 {
+            let trace = linearise(ProtocolOptions::default().with_tickets(tickets));
+            let sent = trace.iter().filter(|(_, m)| *m == MessageType::NewSessionTicket).count();
+            assert_eq!(sent, tickets as usize);
+            // All after the handshake, so none is protected with the
+            // handshake keys.
+            let finished = trace.iter().rposition(|(_, m)| *m == MessageType::Finished).unwrap();
+            assert_eq!(finished, trace.len() - 1 - sent);
+        }
+        let states = ProtocolOptions::states();
+        for opts in ProtocolOptions::combinations() {
+            assert!(opts.tickets() <= MAX_TICKETS);
+            let mut state = State::default();
+            while state.sends().is_some() {
+                assert!(states.contains(&state), "{:?}", state);
+                state = step(state, opts);
+            }
+            assert!(states.contains(&state), "{:?}", state);
+        }
+
+        // A TLS 1.2 server issues its one ticket before its Finished.
+        let opts = tls12::ProtocolOptions::builder()
+            .client_offers_ticket(true)
+            .server_issues_ticket(true)
+            .build()
+            .unwrap();
+        assert_eq!(opts.tickets(), 1);
+        let messages = tls12::Trace::linearise(opts).messages();
+        let ticket = messages.iter().position(|m| *m == tls12::MessageType::NewSessionTicket);
+        let finished = messages.iter().rposition(|m| *m == tls12::MessageType::Finished);
+        assert!(ticket < finished);
+    }
+
+    #[test]
+    fn middlebox_compat() {
+        use MessageType::*;
+        let opts = ProtocolOptions::default().with_middlebox_compat(true);
+        assert_eq!(
+            linearise(opts),
+            vec![
+                (Peer::Client, ClientHello),
+                (Peer::Server, ServerHello),
+                (Peer::Server, ChangeCipherSpec),
+                (Peer::Server, EncryptedExtensions),
+                (Peer::Server, Certificate),
+                (Peer::Server, CertificateVerify),
+                (Peer::Server, Finished),
+                (Peer::Client, ChangeCipherSpec),
+                (Peer::Client, Finished),
+            ]
+        );
+        let retry = linearise(opts.with_hello_retry(true));
+        assert_eq!(
+            retry[1..4],
+            [
+                (Peer::Server, HelloRetryRequest),
+                (Peer::Server, ChangeCipherSpec),
+                (Peer::Client, ChangeCipherSpec),
+            ]
+        );
+
+        // One each, with nothing else changed once they're dropped.
+        for opts in ProtocolOptions::combinations() {
+            let trace = linearise(opts);
+            for peer in &[Peer::Client, Peer::Server] {
+                let ccs = trace.iter().filter(|&&m| m == (*peer, ChangeCipherSpec)).count();
+                assert_eq!(ccs, opts.middlebox_compat as usize, "{:?}", opts);
+            }
+            let first = trace.iter().position(|(p, _)| *p == Peer::Server).unwrap();
+            assert_eq!(trace[first + 1].1 == ChangeCipherSpec, opts.middlebox_compat);
+            let transcript = |trace: Vec<(Peer, MessageType)>| {
+                trace.into_iter().filter(|(_, m)| m.in_transcript()).collect::<Vec<_>>()
+            };
+            let plain = linearise(opts.with_middlebox_compat(false));
+            assert_eq!(transcript(trace), transcript(plain), "{:?}", opts);
+        }
+    }
+
+    #[test]
+    fn ech() {
+        use MessageType::*;
+        let opts = ProtocolOptions::default().with_ech(true);
+        let accepted = opts.with_accept_ech(true);
+        assert_eq!(linearise(accepted), linearise(ProtocolOptions::default()));
+        let rejected = linearise(opts);
+        assert_eq!(
+            rejected[rejected.len() - 2..],
+            [(Peer::Server, Finished), (Peer::Client, Alert)]
+        );
+        assert!(!rejected.iter().any(|&(peer, msg)| peer == Peer::Client && msg == Finished));
+        assert_eq!(State::ClientSendsEchRequired.sends(), Some((Peer::Client, MessageType::Alert)));
+        let aborted = State::Aborted(AlertDescription::EchRequired);
+        assert_eq!(step(State::ClientSendsEchRequired, opts), aborted);
+        assert_eq!(step(aborted, opts), aborted);
+        assert_eq!(aborted.sends(), None);
+        assert_eq!(AlertDescription::EchRequired.level(), AlertLevel::Fatal);
+
+        // Asked for a certificate, it sends an empty one before its alert.
+        let asked = ProtocolOptions { request_client_auth: true, ..opts };
+        for compress in &[false, true] {
+            let trace = linearise(asked.with_compress_client_certificate(*compress));
+            let certificate = if *compress { CompressedCertificate } else { Certificate };
+            assert_eq!(
+                trace[trace.len() - 3..],
+                [(Peer::Server, Finished), (Peer::Client, certificate), (Peer::Client, Alert)]
+            );
+        }
+        for opts in ProtocolOptions::combinations().into_iter().filter(|o| o.ech_rejected()) {
+            let last = opts.iter_handshake().last().map(|(st, _, _)| st);
+            assert_eq!(last, Some(State::ClientSendsEchRequired), "{:?}", opts);
+        }
+
+        let signals = |opts: ProtocolOptions| {
+            let mut state = State::default();
+            let mut v = Vec::new();
+            while state.sends().is_some() {
+                v.extend(state.ech_signal(opts));
+                state = step(state, opts);
+            }
+            v
+        };
+        assert_eq!(signals(ProtocolOptions::default()), vec![]);
+        assert_eq!(signals(accepted), vec![EchSignal::ServerHelloConfirmation]);
+        assert_eq!(
+            signals(accepted.with_hello_retry(true)),
+            vec![EchSignal::HelloRetryRequestConfirmation, EchSignal::ServerHelloConfirmation]
+        );
+        assert_eq!(signals(opts), vec![EchSignal::RetryConfigs]);
+
+        // Both peers settle on one ClientHello, before they derive anything
+        // from the transcript.
+        for opts in ProtocolOptions::combinations() {
+            let mut state = State::default();
+            let mut chosen = Vec::new();
+            while state.sends().is_some() {
+                let (next, actions) = step_with_actions(state, opts);
+                for action in actions {
+                    match action {
+                        Action::ChooseClientHello(hello) => chosen.push(hello),
+                        Action::DeriveHandshakeSecrets => {
+                            assert_eq!(chosen.len(), opts.ech as usize, "{:?}", opts)
+                        }
+                        _ => {}
+                    }
+                }
+                state = next;
+            }
+            let inner = opts.ech && opts.accept_ech;
+            assert_eq!(chosen.contains(&EchClientHello::Inner), inner);
+        }
+    }
+
+    #[test]
+    fn compressed_certificate() {
+        use MessageType::*;
+        let plain = ProtocolOptions { request_client_auth: true, ..Default::default() };
+        let compressed = |msg| if msg == Certificate { CompressedCertificate } else { msg };
+        let opts = plain.with_compress_certificate(true).with_compress_client_certificate(true);
+        let expected = linearise(plain).into_iter().map(|(p, m)| (p, compressed(m)));
+        assert_eq!(linearise(opts), expected.collect::<Vec<_>>());
+        let server_only = linearise(plain.with_compress_certificate(true));
+        assert!(server_only.contains(&(Peer::Server, CompressedCertificate)));
+        assert!(server_only.contains(&(Peer::Client, Certificate)));
+        let verified = ProtocolOptions { perform_client_auth: true, ..opts };
+        assert!(linearise(verified).contains(&(Peer::Client, CertificateVerify)));
+
+        for opts in ProtocolOptions::combinations() {
+            let trace = linearise(opts);
+            let sent = |peer| trace.contains(&(peer, CompressedCertificate));
+            assert_eq!(sent(Peer::Server), opts.compress_certificate, "{:?}", opts);
+            assert!(!sent(Peer::Client) || opts.compress_client_certificate);
+        }
+    }
+
+    #[test]
+    fn issues_ticket() {
+        let trace = linearise(ProtocolOptions { psk: true, tickets: 1, ..Default::default() });
+        assert_eq!(trace.last(), Some(&(Peer::Server, MessageType::NewSessionTicket)));
+    }
+}
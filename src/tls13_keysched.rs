@@ -0,0 +1,176 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::hash::HashSpec;
+use crate::hkdf;
+use crate::hmac::hmac;
+
+// The TLS 1.3 key schedule (RFC 8446, section 7.1) over the hash H of the
+// cipher suite. Transcript hashes are H of the handshake messages so far.
+
+fn concat(a: &Bytes, b: &Bytes) -> Bytes {
+    let mut out = Bytes::new_len(a.len() + b.len());
+    out.update(0, a);
+    out.update(a.len(), b);
+    out
+}
+
+// HKDF-Expand-Label(Secret, Label, Context, Length) =
+//     HKDF-Expand(Secret, HkdfLabel, Length)
+// where HkdfLabel is Length as a u16, then "tls13 " + Label and Context,
+// each prefixed by its length as a u8.
+pub fn hkdf_expand_label<H: HashSpec>(
+    secret: Bytes,
+    label: &str,
+    context: Bytes,
+    len: usize,
+) -> Result<Bytes, String> {
+    let label =
+        concat(&Bytes::from_vec(b"tls13 ".to_vec()), &Bytes::from_vec(label.as_bytes().to_vec()));
+    if len > 0xffff || label.len() > 255 || context.len() > 255 {
+        return Err("HkdfLabel field too long".to_string());
+    }
+    let mut info = Bytes::new_len(2 + 1 + label.len() + 1 + context.len());
+    info.update_raw(0, &(len as u16).to_be_bytes());
+    info[2] = label.len() as u8;
+    info.update(3, &label);
+    info[3 + label.len()] = context.len() as u8;
+    info.update(4 + label.len(), &context);
+    hkdf::expand::<H>(secret, info, len)
+}
+
+// Derive-Secret(Secret, Label, Messages) =
+//     HKDF-Expand-Label(Secret, Label, Transcript-Hash(Messages), Hash.length)
+pub fn derive_secret<H: HashSpec>(secret: Bytes, label: &str, transcript_hash: Bytes) -> Bytes {
+    // A label of the schedule and a hash always fit.
+    hkdf_expand_label::<H>(secret, label, transcript_hash, H::HASH_LEN).unwrap()
+}
+
+fn zeros<H: HashSpec>() -> Bytes {
+    Bytes::new_len(H::HASH_LEN)
+}
+
+// The salt each stage extracts its secret with.
+fn derived<H: HashSpec>(secret: Bytes) -> Bytes {
+    derive_secret::<H>(secret, "derived", H::hash(Bytes::new_len(0)))
+}
+
+// Early Secret = HKDF-Extract(0, PSK), with a PSK of zeros without one.
+pub fn early_secret<H: HashSpec>(psk: Option<Bytes>) -> Bytes {
+    hkdf::extract::<H>(zeros::<H>(), psk.unwrap_or_else(zeros::<H>))
+}
+
+// Handshake Secret = HKDF-Extract(Derive-Secret(Early Secret, "derived", ""),
+//                                 (EC)DHE)
+// with a shared secret of zeros for psk_ke, which has no (EC)DHE.
+pub fn handshake_secret<H: HashSpec>(early_secret: Bytes, shared_secret: Option<Bytes>) -> Bytes {
+    hkdf::extract::<H>(derived::<H>(early_secret), shared_secret.unwrap_or_else(zeros::<H>))
+}
+
+// Master Secret = HKDF-Extract(Derive-Secret(Handshake Secret, "derived", ""),
+//                              0)
+pub fn master_secret<H: HashSpec>(handshake_secret: Bytes) -> Bytes {
+    hkdf::extract::<H>(derived::<H>(handshake_secret), zeros::<H>())
+}
+
+// After a HelloRetryRequest, the first ClientHello is replaced in the
+// transcript by a synthetic handshake message carrying its hash
+// (RFC 8446, section 4.4.1):
+//     message_hash(254) || 00 00 Hash.length || Hash(ClientHello1)
+pub fn message_hash<H: HashSpec>(client_hello1: Bytes) -> Bytes {
+    let mut out = Bytes::new_len(4 + H::HASH_LEN);
+    out[0] = 254u8;
+    out[3] = H::HASH_LEN as u8;
+    out.update(4, &H::hash(client_hello1));
+    out
+}
+
+// Transcript-Hash(ClientHello1, HelloRetryRequest, ... Mn) =
+//     Hash(message_hash || HelloRetryRequest || ... || Mn)
+pub fn retry_transcript_hash<H: HashSpec>(client_hello1: Bytes, rest: Bytes) -> Bytes {
+    H::hash(concat(&message_hash::<H>(client_hello1), &rest))
+}
+
+// The binder key of an external PSK, or of one from a ticket.
+pub fn binder_key<H: HashSpec>(early_secret: Bytes, external: bool) -> Bytes {
+    let label = if external { "ext binder" } else { "res binder" };
+    derive_secret::<H>(early_secret, label, H::hash(Bytes::new_len(0)))
+}
+
+// The early secrets hash the transcript up to the ClientHello.
+pub fn client_early_traffic_secret<H: HashSpec>(early_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(early_secret, "c e traffic", th)
+}
+
+pub fn early_exporter_master_secret<H: HashSpec>(early_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(early_secret, "e exp master", th)
+}
+
+// The handshake traffic secrets hash the transcript up to the ServerHello.
+pub fn client_handshake_traffic_secret<H: HashSpec>(handshake_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(handshake_secret, "c hs traffic", th)
+}
+
+pub fn server_handshake_traffic_secret<H: HashSpec>(handshake_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(handshake_secret, "s hs traffic", th)
+}
+
+// The application traffic secrets and the exporter master secret hash the
+// transcript up to the server's Finished.
+pub fn client_application_traffic_secret<H: HashSpec>(master_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(master_secret, "c ap traffic", th)
+}
+
+pub fn server_application_traffic_secret<H: HashSpec>(master_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(master_secret, "s ap traffic", th)
+}
+
+pub fn exporter_master_secret<H: HashSpec>(master_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(master_secret, "exp master", th)
+}
+
+// The resumption master secret hashes the transcript up to the client's
+// Finished.
+pub fn resumption_master_secret<H: HashSpec>(master_secret: Bytes, th: Bytes) -> Bytes {
+    derive_secret::<H>(master_secret, "res master", th)
+}
+
+// Each NewSessionTicket carries a nonce that makes its PSK unique among the
+// tickets of the connection (RFC 8446, 4.6.1):
+//     HKDF-Expand-Label(resumption_master_secret, "resumption", ticket_nonce,
+//                       Hash.length)
+// The nonce is at most 255 bytes.
+pub fn resumption_psk<H: HashSpec>(
+    resumption_master_secret: Bytes,
+    ticket_nonce: Bytes,
+) -> Result<Bytes, String> {
+    hkdf_expand_label::<H>(resumption_master_secret, "resumption", ticket_nonce, H::HASH_LEN)
+}
+
+// application_traffic_secret_N+1 =
+//     HKDF-Expand-Label(application_traffic_secret_N, "traffic upd", "", Hash.length)
+pub fn next_application_traffic_secret<H: HashSpec>(secret: Bytes) -> Bytes {
+    hkdf_expand_label::<H>(secret, "traffic upd", Bytes::new_len(0), H::HASH_LEN).unwrap()
+}
+
+// The record protection key and IV of a traffic secret (RFC 8446, 7.3).
+pub fn traffic_key<H: HashSpec>(secret: Bytes, key_len: usize) -> Bytes {
+    hkdf_expand_label::<H>(secret, "key", Bytes::new_len(0), key_len).unwrap()
+}
+
+pub fn traffic_iv<H: HashSpec>(secret: Bytes, iv_len: usize) -> Bytes {
+    hkdf_expand_label::<H>(secret, "iv", Bytes::new_len(0), iv_len).unwrap()
+}
+
+// finished_key = HKDF-Expand-Label(BaseKey, "finished", "", Hash.length), with
+// the sender's handshake traffic secret as BaseKey (RFC 8446, 4.4.4).
+pub fn finished_key<H: HashSpec>(base_key: Bytes) -> Bytes {
+    hkdf_expand_label::<H>(base_key, "finished", Bytes::new_len(0), H::HASH_LEN).unwrap()
+}
+
+// verify_data = HMAC(finished_key, Transcript-Hash(Handshake Context,
+//                                                  Certificate*, CertificateVerify*))
+pub fn verify_data<H: HashSpec>(base_key: Bytes, th: Bytes) -> Bytes {
+    hmac::<H>(finished_key::<H>(base_key), th)
+}
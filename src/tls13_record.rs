@@ -0,0 +1,187 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::aesgcm;
+use crate::chacha20poly1305;
+
+// TLS 1.3 record protection (RFC 8446, sections 5.2 to 5.4). A protected
+// record always goes out as application_data; the real content type is
+// encrypted with the content, after it and before any zero padding:
+//
+//     struct {
+//         opaque content[TLSPlaintext.length];
+//         ContentType type;
+//         uint8 zeros[length_of_padding];
+//     } TLSInnerPlaintext;
+//
+// The nonce of each record is the IV XORed with its sequence number, and
+// the additional data is the record header.
+
+// The AEADs of the TLS 1.3 cipher suites, which all take a 12 byte nonce
+// and append a 16 byte tag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aead {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Aead {
+    pub fn key_len(self) -> usize {
+        match self {
+            Aead::Aes128Gcm => 16,
+            Aead::Aes256Gcm | Aead::ChaCha20Poly1305 => 32,
+        }
+    }
+}
+
+pub const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = 5;
+const APPLICATION_DATA: u8 = 23;
+const LEGACY_RECORD_VERSION: u16 = 0x0303;
+const MAX_PLAINTEXT_LEN: usize = 1 << 14;
+// The inner plaintext may be one type byte over the plaintext limit, and the
+// encrypted record 255 bytes over that.
+const MAX_INNER_PLAINTEXT_LEN: usize = MAX_PLAINTEXT_LEN + 1;
+const MAX_CIPHERTEXT_LEN: usize = MAX_PLAINTEXT_LEN + 256;
+
+fn seal_aead(
+    aead: Aead,
+    key: &Bytes,
+    nonce: Bytes,
+    aad: Bytes,
+    msg: Bytes,
+) -> Result<Bytes, String> {
+    if key.len() != aead.key_len() {
+        return Err("Invalid key length".to_string());
+    }
+    match aead {
+        Aead::Aes128Gcm | Aead::Aes256Gcm => aesgcm::seal(key.clone(), nonce, aad, msg),
+        Aead::ChaCha20Poly1305 => chacha20poly1305::seal(key.clone(), nonce, aad, msg),
+    }
+}
+
+fn open_aead(
+    aead: Aead,
+    key: &Bytes,
+    nonce: Bytes,
+    aad: Bytes,
+    msg: Bytes,
+) -> Result<Bytes, String> {
+    if key.len() != aead.key_len() {
+        return Err("Invalid key length".to_string());
+    }
+    match aead {
+        Aead::Aes128Gcm | Aead::Aes256Gcm => aesgcm::open(key.clone(), nonce, aad, msg),
+        Aead::ChaCha20Poly1305 => chacha20poly1305::open(key.clone(), nonce, aad, msg),
+    }
+}
+
+// The per-record nonce: the 64-bit sequence number, padded on the left with
+// zeros to the length of the IV, XORed with the IV (RFC 8446, 5.3).
+pub fn nonce(iv: &Bytes, seq: u64) -> Result<Bytes, String> {
+    if iv.len() != IV_LEN {
+        return Err("Invalid IV length".to_string());
+    }
+    let mut out = iv.clone();
+    let seq = seq.to_be_bytes();
+    for i in 0..8 {
+        out[IV_LEN - 8 + i] ^= seq[i];
+    }
+    Ok(out)
+}
+
+// The content, its type, then padding_len zeros. Padding hides how long the
+// content is, up to the limit on the inner plaintext (RFC 8446, 5.4).
+pub fn encode_inner_plaintext(content: &Bytes, content_type: u8, padding_len: usize) -> Bytes {
+    let mut out = Bytes::new_len(content.len() + 1 + padding_len);
+    out.update(0, content);
+    out[content.len()] = content_type;
+    out
+}
+
+// The content type is the last non-zero byte; a plaintext of zeros has
+// none, which a receiver answers with unexpected_message.
+pub fn decode_inner_plaintext(inner: &Bytes) -> Result<(u8, Bytes), String> {
+    let mut l = inner.len();
+    while l > 0 && inner[l - 1] == 0 {
+        l -= 1;
+    }
+    if l == 0 {
+        return Err("No content type in the inner plaintext".to_string());
+    }
+    if l - 1 > MAX_PLAINTEXT_LEN {
+        return Err("Record overflow".to_string());
+    }
+    Ok((inner[l - 1], Bytes::from(&inner[0..l - 1])))
+}
+
+// The record header, which is also the additional data:
+//     opaque_type || legacy_record_version || length
+// with the length of the encrypted record that follows.
+pub fn additional_data(len: usize) -> Bytes {
+    let mut out = Bytes::new_len(HEADER_LEN);
+    out[0] = APPLICATION_DATA;
+    out.update_raw(1, &LEGACY_RECORD_VERSION.to_be_bytes());
+    out.update_raw(3, &(len as u16).to_be_bytes());
+    out
+}
+
+// The whole TLSCiphertext record, header and all, of the record with
+// sequence number seq.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_record(
+    aead: Aead,
+    key: &Bytes,
+    iv: &Bytes,
+    seq: u64,
+    content_type: u8,
+    content: Bytes,
+    padding_len: usize,
+) -> Result<Bytes, String> {
+    if content_type == 0 {
+        return Err("Invalid content type".to_string());
+    }
+    let inner_len = content.len() + 1 + padding_len;
+    if content.len() > MAX_PLAINTEXT_LEN || inner_len > MAX_INNER_PLAINTEXT_LEN {
+        return Err("Record too long".to_string());
+    }
+    let inner = encode_inner_plaintext(&content, content_type, padding_len);
+    let header = additional_data(inner.len() + TAG_LEN);
+    let encrypted = seal_aead(aead, key, nonce(iv, seq)?, header.clone(), inner)?;
+    let mut out = Bytes::new_len(HEADER_LEN + encrypted.len());
+    out.update(0, &header);
+    out.update(HEADER_LEN, &encrypted);
+    Ok(out)
+}
+
+// The content type and content of a TLSCiphertext record. Anything but
+// application_data on the outside, or a length that isn't the rest of the
+// record, is rejected before decrypting.
+pub fn open_record(
+    aead: Aead,
+    key: &Bytes,
+    iv: &Bytes,
+    seq: u64,
+    record: Bytes,
+) -> Result<(u8, Bytes), String> {
+    if record.len() < HEADER_LEN {
+        return Err("Record too short".to_string());
+    }
+    if record[0] != APPLICATION_DATA {
+        return Err("Unprotected record".to_string());
+    }
+    let len = ((record[3] as usize) << 8) | record[4] as usize;
+    if len != record.len() - HEADER_LEN {
+        return Err("Bad record length".to_string());
+    }
+    if len > MAX_CIPHERTEXT_LEN {
+        return Err("Record overflow".to_string());
+    }
+    let header = Bytes::from(&record[0..HEADER_LEN]);
+    let encrypted = Bytes::from(&record[HEADER_LEN..record.len()]);
+    let inner = open_aead(aead, key, nonce(iv, seq)?, header, encrypted)?;
+    decode_inner_plaintext(&inner)
+}
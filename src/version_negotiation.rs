@@ -0,0 +1,433 @@
+use crate::codec::{CodecError, Reader};
+use crate::extensions::ExtensionType;
+use crate::hello::{ClientHello, ServerHello};
+use crate::tls12::AlertDescription;
+use std::fmt;
+
+/// The protocol versions this crate negotiates. SSL 3.0 is left out, as
+/// RFC 7568 has it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtocolVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+impl ProtocolVersion {
+    pub(crate) const ALL: [ProtocolVersion; 4] = [
+        ProtocolVersion::Tls10,
+        ProtocolVersion::Tls11,
+        ProtocolVersion::Tls12,
+        ProtocolVersion::Tls13,
+    ];
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            ProtocolVersion::Tls10 => 0x0301,
+            ProtocolVersion::Tls11 => 0x0302,
+            ProtocolVersion::Tls12 => 0x0303,
+            ProtocolVersion::Tls13 => 0x0304,
+        }
+    }
+
+    pub fn from_u16(v: u16) -> Option<ProtocolVersion> {
+        ProtocolVersion::ALL.iter().copied().find(|p| p.to_u16() == v)
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ProtocolVersion::Tls10 => "TLS 1.0",
+            ProtocolVersion::Tls11 => "TLS 1.1",
+            ProtocolVersion::Tls12 => "TLS 1.2",
+            ProtocolVersion::Tls13 => "TLS 1.3",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which state machine the connection carries on in. TLS 1.0 and 1.1 run
+/// the handshake of TLS 1.2, with the older PRF and record protection;
+/// `tls12` models all three.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Negotiated {
+    Tls12(ProtocolVersion),
+    Tls13,
+}
+
+impl Negotiated {
+    pub fn version(self) -> ProtocolVersion {
+        match self {
+            Negotiated::Tls12(v) => v,
+            Negotiated::Tls13 => ProtocolVersion::Tls13,
+        }
+    }
+
+    fn of(version: ProtocolVersion) -> Negotiated {
+        match version {
+            ProtocolVersion::Tls13 => Negotiated::Tls13,
+            v => Negotiated::Tls12(v),
+        }
+    }
+}
+
+/// Why no version could be agreed on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VersionError {
+    /// supported_versions is malformed.
+    Codec(CodecError),
+    /// The peers have no version in common.
+    NoCommonVersion,
+    /// The server picked a version the client didn't offer.
+    UnofferedVersion(u16),
+    /// A TLS 1.3 ServerHello's legacy_version isn't 0x0303, or a
+    /// supported_versions answer names an earlier version (RFC 8446,
+    /// 4.1.3 and 4.2.1).
+    BadSupportedVersions(u16),
+    /// The server's random carries a downgrade sentinel although the client
+    /// offered a newer version than the one picked (RFC 8446, 4.1.3).
+    Downgrade(ProtocolVersion),
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionError::Codec(e) => e.fmt(f),
+            VersionError::NoCommonVersion => write!(f, "no protocol version in common"),
+            VersionError::UnofferedVersion(v) => write!(f, "unoffered version {:#06x}", v),
+            VersionError::BadSupportedVersions(v) => {
+                write!(f, "version {:#06x} negotiated through supported_versions", v)
+            }
+            VersionError::Downgrade(v) => write!(f, "downgrade to {} detected", v),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+impl From<CodecError> for VersionError {
+    fn from(e: CodecError) -> VersionError {
+        VersionError::Codec(e)
+    }
+}
+
+impl VersionError {
+    /// The alert a receiver answers with.
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            VersionError::Codec(_) => AlertDescription::DecodeError,
+            VersionError::NoCommonVersion => AlertDescription::ProtocolVersion,
+            VersionError::UnofferedVersion(_)
+            | VersionError::BadSupportedVersions(_)
+            | VersionError::Downgrade(_) => AlertDescription::IllegalParameter,
+        }
+    }
+}
+
+/// The versions a ClientHello offers, known ones only and in its order of
+/// preference: those of supported_versions if it has the extension (RFC
+/// 8446, 4.2.1), else every version up to its legacy_version, newest
+/// first. A legacy_version beyond TLS 1.2 offers TLS 1.2 and down, since
+/// TLS 1.3 is only ever negotiated through the extension.
+pub fn offered_versions(client: &ClientHello) -> Result<Vec<ProtocolVersion>, VersionError> {
+    match client.extension(ExtensionType::SupportedVersions.to_u16()) {
+        Some(data) => {
+            let mut r = Reader::new(data);
+            let list = r.read_u8_vec(2, 254, "versions")?;
+            r.end()?;
+            if list.len() % 2 != 0 {
+                return Err(CodecError::BadLength("versions").into());
+            }
+            Ok(list
+                .chunks(2)
+                .filter_map(|v| ProtocolVersion::from_u16(u16::from_be_bytes([v[0], v[1]])))
+                .collect())
+        }
+        None => {
+            let max = client.client_version.min(ProtocolVersion::Tls12.to_u16());
+            Ok(ProtocolVersion::ALL.iter().rev().copied().filter(|v| v.to_u16() <= max).collect())
+        }
+    }
+}
+
+/// The server's decision: the newest of the versions it supports that the
+/// client offers. A server answers TLS 1.3 with supported_versions and any
+/// other version through legacy_version alone.
+pub fn negotiate_version(
+    client: &ClientHello,
+    server_supported: &[ProtocolVersion],
+) -> Result<Negotiated, VersionError> {
+    let offered = offered_versions(client)?;
+    offered
+        .iter()
+        .copied()
+        .filter(|v| server_supported.contains(v))
+        .max()
+        .map(Negotiated::of)
+        .ok_or(VersionError::NoCommonVersion)
+}
+
+/// The client's check of the version the ServerHello picked: one it
+/// offered, and TLS 1.3 only through supported_versions with legacy_version
+/// fixed at TLS 1.2 (RFC 8446, 4.1.3 and 4.2.1).
+pub fn check_server_version(
+    client: &ClientHello,
+    server: &ServerHello,
+) -> Result<Negotiated, VersionError> {
+    let offered = offered_versions(client)?;
+    let selected = match server.extension(ExtensionType::SupportedVersions.to_u16()) {
+        Some(data) => {
+            let mut r = Reader::new(data);
+            let v = r.read_u16()?;
+            r.end()?;
+            if v < ProtocolVersion::Tls13.to_u16() {
+                return Err(VersionError::BadSupportedVersions(v));
+            }
+            if server.server_version != ProtocolVersion::Tls12.to_u16() {
+                return Err(VersionError::BadSupportedVersions(server.server_version));
+            }
+            v
+        }
+        None if server.server_version == ProtocolVersion::Tls13.to_u16() => {
+            return Err(VersionError::BadSupportedVersions(server.server_version));
+        }
+        None => server.server_version,
+    };
+    let negotiated = match ProtocolVersion::from_u16(selected) {
+        Some(v) if offered.contains(&v) => Negotiated::of(v),
+        _ => return Err(VersionError::UnofferedVersion(selected)),
+    };
+    check_downgrade(&offered, negotiated, &server.random)?;
+    Ok(negotiated)
+}
+
+/// The last eight bytes of the random of a server that supports TLS 1.3
+/// but negotiates TLS 1.2: "DOWNGRD" and 0x01 (RFC 8446, 4.1.3).
+pub const DOWNGRADE_TLS12: [u8; 8] = *b"DOWNGRD\x01";
+
+/// The same for a server that supports TLS 1.2 or above but negotiates TLS
+/// 1.1 or below.
+pub const DOWNGRADE_TLS11: [u8; 8] = *b"DOWNGRD\x00";
+
+/// The sentinel a server that supports `server_supported` must end its
+/// random with when it negotiates `negotiated`, if any.
+pub fn downgrade_sentinel(
+    negotiated: Negotiated,
+    server_supported: &[ProtocolVersion],
+) -> Option<[u8; 8]> {
+    let max = server_supported.iter().copied().max()?;
+    match negotiated.version() {
+        ProtocolVersion::Tls12 if max == ProtocolVersion::Tls13 => Some(DOWNGRADE_TLS12),
+        ProtocolVersion::Tls10 | ProtocolVersion::Tls11 if max >= ProtocolVersion::Tls12 => {
+            Some(DOWNGRADE_TLS11)
+        }
+        _ => None,
+    }
+}
+
+/// `random` with the sentinel `downgrade_sentinel` calls for in its last
+/// eight bytes; the server sends the rest at random as before.
+pub fn mark_server_random(
+    mut random: [u8; 32],
+    negotiated: Negotiated,
+    server_supported: &[ProtocolVersion],
+) -> [u8; 32] {
+    if let Some(sentinel) = downgrade_sentinel(negotiated, server_supported) {
+        random[24..].copy_from_slice(&sentinel);
+    }
+    random
+}
+
+/// The check a client that `offered` more than it `negotiated` makes of the
+/// server's random: a TLS 1.3 client refuses both sentinels, and a TLS 1.2
+/// one the second when it ends up on TLS 1.1 or below. A server that
+/// supports the newer version only sends them when an attacker took it out
+/// of the ClientHello, so a false match is a 2^-64 chance.
+pub fn check_downgrade(
+    offered: &[ProtocolVersion],
+    negotiated: Negotiated,
+    random: &[u8; 32],
+) -> Result<(), VersionError> {
+    let max = match offered.iter().copied().max() {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+    let sentinel = &random[24..];
+    let downgraded = match negotiated.version() {
+        ProtocolVersion::Tls13 => false,
+        ProtocolVersion::Tls12 => max == ProtocolVersion::Tls13 && sentinel == DOWNGRADE_TLS12,
+        _ => {
+            (max == ProtocolVersion::Tls13 && sentinel == DOWNGRADE_TLS12)
+                || (max >= ProtocolVersion::Tls12 && sentinel == DOWNGRADE_TLS11)
+        }
+    };
+    if downgraded {
+        Err(VersionError::Downgrade(negotiated.version()))
+    } else {
+        Ok(())
+    }
+}
+
+/// `client` as an attacker in the middle rewrites it to push the server
+/// down to `version`: without supported_versions, and with legacy_version
+/// lowered to match. What the server answers carries a sentinel if it
+/// could have done better, for `check_server_version` against the
+/// original hello to find.
+pub fn downgraded_client_hello(client: &ClientHello, version: ProtocolVersion) -> ClientHello {
+    let version = version.min(ProtocolVersion::Tls12);
+    let supported_versions = ExtensionType::SupportedVersions.to_u16();
+    let extensions = client.extensions.as_ref().map(|list| {
+        list.iter().filter(|e| e.extension_type != supported_versions).cloned().collect()
+    });
+    ClientHello { client_version: version.to_u16(), extensions, ..client.clone() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extensions::Extension;
+    use crate::hello::decode_client_hello;
+    use crate::hello::test::*;
+    use ProtocolVersion::*;
+
+    fn tls13_server_hello(legacy_version: u16, selected: &[u8]) -> ServerHello {
+        let (full, _) = server_hello(FULL_SERVER_HELLO);
+        let extension_type = ExtensionType::SupportedVersions.to_u16();
+        let extension = Extension { extension_type, data: selected.to_vec() };
+        ServerHello {
+            server_version: legacy_version,
+            cipher_suite: 0x1301,
+            extensions: Some(vec![extension]),
+            ..full
+        }
+    }
+
+    #[test]
+    fn version_codec() {
+        for &v in ProtocolVersion::ALL.iter() {
+            assert_eq!(ProtocolVersion::from_u16(v.to_u16()), Some(v));
+        }
+        assert_eq!(ProtocolVersion::from_u16(0x0300), None);
+        assert_eq!(Tls13.to_string(), "TLS 1.3");
+        assert!(Tls10 < Tls13);
+    }
+
+    #[test]
+    fn offered() {
+        let tls12 = decode_client_hello(&body(TLS12_CLIENT_HELLO)).unwrap();
+        assert_eq!(offered_versions(&tls12), Ok(vec![Tls12, Tls11, Tls10]));
+        let tls13 = decode_client_hello(&body(TLS13_CLIENT_HELLO)).unwrap();
+        assert_eq!(offered_versions(&tls13), Ok(vec![Tls13, Tls12]));
+        // A future version in legacy_version still offers TLS 1.2.
+        let future = ClientHello { client_version: 0x0305, ..tls12.clone() };
+        assert_eq!(offered_versions(&future), Ok(vec![Tls12, Tls11, Tls10]));
+        let tls10 = ClientHello { client_version: 0x0301, ..tls12 };
+        assert_eq!(offered_versions(&tls10), Ok(vec![Tls10]));
+    }
+
+    #[test]
+    fn server_decision() {
+        let tls12 = decode_client_hello(&body(TLS12_CLIENT_HELLO)).unwrap();
+        let tls13 = decode_client_hello(&body(TLS13_CLIENT_HELLO)).unwrap();
+        let all = ProtocolVersion::ALL;
+        assert_eq!(negotiate_version(&tls13, &all), Ok(Negotiated::Tls13));
+        assert_eq!(negotiate_version(&tls13, &[Tls12]), Ok(Negotiated::Tls12(Tls12)));
+        assert_eq!(negotiate_version(&tls12, &all), Ok(Negotiated::Tls12(Tls12)));
+        let err = negotiate_version(&tls13, &[Tls10, Tls11]).unwrap_err();
+        assert_eq!(err, VersionError::NoCommonVersion);
+        assert_eq!(err.alert(), AlertDescription::ProtocolVersion);
+        // With supported_versions, legacy_version counts for nothing.
+        let odd = ClientHello { client_version: 0x0301, ..tls13 };
+        assert_eq!(negotiate_version(&odd, &all), Ok(Negotiated::Tls13));
+    }
+
+    #[test]
+    fn client_check() {
+        let tls12 = decode_client_hello(&body(TLS12_CLIENT_HELLO)).unwrap();
+        let tls13 = decode_client_hello(&body(TLS13_CLIENT_HELLO)).unwrap();
+        let (full, _) = server_hello(FULL_SERVER_HELLO);
+        assert_eq!(check_server_version(&tls12, &full), Ok(Negotiated::Tls12(Tls12)));
+        assert_eq!(check_server_version(&tls13, &full), Ok(Negotiated::Tls12(Tls12)));
+
+        let sh13 = tls13_server_hello(0x0303, &[3, 4]);
+        assert_eq!(check_server_version(&tls13, &sh13), Ok(Negotiated::Tls13));
+        // TLS 1.3 is only offered through supported_versions.
+        let err = check_server_version(&tls12, &sh13).unwrap_err();
+        assert_eq!(err, VersionError::UnofferedVersion(0x0304));
+        assert_eq!(err.alert(), AlertDescription::IllegalParameter);
+        let bad = [
+            tls13_server_hello(0x0304, &[3, 4]),
+            tls13_server_hello(0x0303, &[3, 3]),
+            ServerHello { server_version: 0x0304, extensions: None, ..full.clone() },
+        ];
+        for sh in &bad {
+            let err = check_server_version(&tls13, sh).unwrap_err();
+            assert!(matches!(err, VersionError::BadSupportedVersions(_)), "{:?}", err);
+        }
+        let tls10 = ServerHello { server_version: 0x0301, ..full };
+        let err = VersionError::UnofferedVersion(0x0301);
+        assert_eq!(check_server_version(&tls13, &tls10), Err(err));
+        let truncated = tls13_server_hello(0x0303, &[3]);
+        assert!(matches!(check_server_version(&tls13, &truncated), Err(VersionError::Codec(_))));
+    }
+
+    #[test]
+    fn sentinels() {
+        let tls13_server = ProtocolVersion::ALL;
+        let tls12_server = [Tls10, Tls11, Tls12];
+        let tls12 = Negotiated::Tls12(Tls12);
+        let tls11 = Negotiated::Tls12(Tls11);
+        assert_eq!(&DOWNGRADE_TLS12, &[0x44, 0x4f, 0x57, 0x4e, 0x47, 0x52, 0x44, 0x01]);
+        assert_eq!(downgrade_sentinel(tls12, &tls13_server), Some(DOWNGRADE_TLS12));
+        assert_eq!(downgrade_sentinel(tls11, &tls13_server), Some(DOWNGRADE_TLS11));
+        assert_eq!(downgrade_sentinel(tls11, &tls12_server), Some(DOWNGRADE_TLS11));
+        assert_eq!(downgrade_sentinel(tls12, &tls12_server), None);
+        assert_eq!(downgrade_sentinel(Negotiated::Tls13, &tls13_server), None);
+        assert_eq!(downgrade_sentinel(tls11, &[Tls10, Tls11]), None);
+        let random = mark_server_random([7; 32], tls12, &tls13_server);
+        assert_eq!(random[..24], [7; 24]);
+        assert_eq!(random[24..], DOWNGRADE_TLS12);
+    }
+
+    #[test]
+    fn downgrade_attacks() {
+        let tls13 = decode_client_hello(&body(TLS13_CLIENT_HELLO)).unwrap();
+        let (full, _) = server_hello(FULL_SERVER_HELLO);
+        let server_supported = ProtocolVersion::ALL;
+        for &to in &[Tls12, Tls11, Tls10] {
+            let attacked = downgraded_client_hello(&tls13, to);
+            assert_eq!(attacked.extension(ExtensionType::SupportedVersions.to_u16()), None);
+            let negotiated = negotiate_version(&attacked, &server_supported).unwrap();
+            assert_eq!(negotiated, Negotiated::Tls12(to));
+            let random = mark_server_random(full.random, negotiated, &server_supported);
+            let server = ServerHello { server_version: to.to_u16(), random, ..full.clone() };
+            // The server's answer fits the hello it saw, not the one sent,
+            // which offered TLS 1.3 and 1.2 only.
+            assert_eq!(check_server_version(&attacked, &server), Ok(negotiated));
+            let err = check_server_version(&tls13, &server).unwrap_err();
+            if to == Tls12 {
+                assert_eq!(err, VersionError::Downgrade(to));
+            } else {
+                assert_eq!(err, VersionError::UnofferedVersion(to.to_u16()));
+            }
+            assert_eq!(err.alert(), AlertDescription::IllegalParameter);
+        }
+
+        // A TLS 1.2 client pushed to TLS 1.1 catches the second sentinel
+        // only, and a server without TLS 1.3 sends no first one.
+        let tls12 = decode_client_hello(&body(TLS12_CLIENT_HELLO)).unwrap();
+        let attacked = downgraded_client_hello(&tls12, Tls11);
+        let tls12_only = [Tls11, Tls12];
+        let negotiated = negotiate_version(&attacked, &tls12_only).unwrap();
+        let random = mark_server_random(full.random, negotiated, &tls12_only);
+        let server = ServerHello { server_version: 0x0302, random, ..full.clone() };
+        assert_eq!(check_server_version(&tls12, &server), Err(VersionError::Downgrade(Tls11)));
+        let random = mark_server_random(full.random, Negotiated::Tls12(Tls12), &tls12_only);
+        let server = ServerHello { random, ..full };
+        assert_eq!(check_server_version(&tls13, &server), Ok(Negotiated::Tls12(Tls12)));
+    }
+}
@@ -0,0 +1,201 @@
+// Short Weierstrass curves y^2 = x^3 - 3x + b over a prime field, in Jacobian
+// coordinates, as the NIST curves P-256 and P-384 are.
+//
+// A curve is given by its field element and scalar types, the bit length of
+// its scalars, the byte length of its field elements, and the hex strings of
+// the base point's coordinates and of b. Points are encoded as in SEC 1, and
+// ECDH is as in SEC 1, 3.3.1.
+macro_rules! weierstrass_curve {
+    ($field:ident, $scalar:ident, $bits:expr, $len:expr, $gx:expr, $gy:expr, $b:expr) => {
+        const FIELD_LEN: usize = $len;
+        bytes!(FieldBytes, FIELD_LEN);
+
+        // TODO: these two aren't cool
+        #[derive(Copy, Clone, Debug)]
+        struct Jacobian($field, $field, $field);
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        pub struct Affine(pub $field, pub $field);
+
+        fn affine_to_jacobian(p: Affine) -> Jacobian {
+            Jacobian(p.0, p.1, $field::from(1))
+        }
+
+        fn jacobian_to_affine(p: Jacobian) -> Affine {
+            let (x, y, z) = (p.0, p.1, p.2);
+            let z2 = z.pow(2);
+            let z2i = z2.inv();
+            let z3 = z * z2;
+            let z3i = z3.inv();
+            let x = x * z2i;
+            let y = y * z3i;
+            Affine(x, y)
+        }
+
+        fn point_double(p: Jacobian) -> Jacobian {
+            let (x1, y1, z1) = (p.0, p.1, p.2);
+            let delta = z1.pow(2);
+            let gamma = y1.pow(2);
+
+            let beta = x1 * gamma;
+
+            let alpha_1 = x1 - delta;
+            let alpha_2 = x1 + delta;
+            let alpha = $field::from(3) * (alpha_1 * alpha_2);
+
+            let x3 = alpha.pow(2) - ($field::from(8) * beta);
+
+            let z3_ = (y1 + z1).pow(2);
+            let z3 = z3_ - (gamma + delta);
+
+            let y3_1 = ($field::from(4) * beta) - x3;
+            let y3_2 = $field::from(8) * (gamma.pow(2));
+            let y3 = (alpha * y3_1) - y3_2;
+            Jacobian(x3, y3, z3)
+        }
+
+        fn is_point_at_infinity(p: Jacobian) -> bool {
+            p.2 == $field::from(0)
+        }
+
+        fn point_add(p: Jacobian, q: Jacobian) -> Jacobian {
+            if is_point_at_infinity(p) {
+                return q;
+            }
+            if is_point_at_infinity(q) {
+                return p;
+            }
+            let (x1, y1, z1) = (p.0, p.1, p.2);
+            let (x2, y2, z2) = (q.0, q.1, q.2);
+            let z1z1 = z1.pow(2);
+            let z2z2 = z2.pow(2);
+            let u1 = x1 * z2z2;
+            let u2 = x2 * z1z1;
+            let s1 = (y1 * z2) * z2z2;
+            let s2 = (y2 * z1) * z1z1;
+
+            if u1 == u2 {
+                if s1 == s2 {
+                    // ECDSA verification can add a point to itself.
+                    return point_double(p);
+                } else {
+                    return Jacobian($field::from(0), $field::from(1), $field::from(0));
+                }
+            }
+
+            let h = u2 - u1;
+            let i = ($field::from(2) * h).pow(2);
+            let j = h * i;
+            let r = $field::from(2) * (s2 - s1);
+            let v = u1 * i;
+
+            let x3_1 = $field::from(2) * v;
+            let x3_2 = r.pow(2) - j;
+            let x3 = x3_2 - x3_1;
+
+            let y3_1 = ($field::from(2) * s1) * j;
+            let y3_2 = r * (v - x3);
+            let y3 = y3_2 - y3_1;
+
+            let z3_ = (z1 + z2).pow(2);
+            let z3 = (z3_ - (z1z1 + z2z2)) * h;
+            Jacobian(x3, y3, z3)
+        }
+
+        fn montgomery_ladder(k: $scalar, init: Jacobian) -> Jacobian {
+            let mut p_working = (Jacobian($field::from(0), $field::from(1), $field::from(0)), init);
+            for i in 0..$bits {
+                if k.bit($bits - 1 - i) == 1 {
+                    p_working = (p_working.1, p_working.0);
+                }
+                let xx = point_double(p_working.0);
+                let xp1 = point_add(p_working.0, p_working.1);
+                if k.bit($bits - 1 - i) == 1 {
+                    p_working = (xp1, xx);
+                } else {
+                    p_working = (xx, xp1);
+                }
+            }
+            p_working.0
+        }
+
+        fn base_point() -> Jacobian {
+            Jacobian($field::from($gx), $field::from($gy), $field::from(1))
+        }
+
+        pub fn point_mul(k: $scalar) -> Affine {
+            let jac = montgomery_ladder(k, base_point());
+            jacobian_to_affine(jac)
+        }
+
+        // The curve is y^2 = x^3 - 3x + b.
+        pub fn is_on_curve(p: Affine) -> bool {
+            let b = $field::from($b);
+            let Affine(x, y) = p;
+            y.pow(2) == x.pow(3) - $field::from(3) * x + b
+        }
+
+        // Field elements are sent as big-endian integers of the field's byte length
+        // (SEC 1, 2.3.5).
+        fn encode_field(x: $field) -> FieldBytes {
+            let le = x.to_bytes_le();
+            let mut out = FieldBytes::new();
+            for i in 0..le.len().min($len) {
+                out[$len - 1 - i] = le[i];
+            }
+            out
+        }
+
+        fn decode_field(b: FieldBytes) -> $field {
+            let mut le = [0u8; $len];
+            for i in 0..$len {
+                le[i] = b[$len - 1 - i];
+            }
+            $field::from_bytes_le(&le)
+        }
+
+        // The uncompressed encoding 04 || x || y of SEC 1, 2.3.3, the only one TLS
+        // uses.
+        pub fn encode_point(p: Affine) -> Bytes {
+            let mut out = Bytes::new_len(1 + 2 * $len);
+            out[0] = 0x04;
+            out.update_raw(1, &encode_field(p.0)[..]);
+            out.update_raw(1 + $len, &encode_field(p.1)[..]);
+            out
+        }
+
+        // Decoding checks that the coordinates are below the prime and that the
+        // point is on the curve; there is no cofactor to check.
+        pub fn decode_point(b: Bytes) -> Result<Affine, String> {
+            if b.len() != 1 + 2 * $len || b[0] != 0x04 {
+                return Err("Invalid point encoding".to_string());
+            }
+            let x = FieldBytes::from(&b[1..1 + $len]);
+            let y = FieldBytes::from(&b[1 + $len..1 + 2 * $len]);
+            let p = Affine(decode_field(x), decode_field(y));
+            if encode_field(p.0) != x || encode_field(p.1) != y {
+                return Err("Point coordinate out of range".to_string());
+            }
+            if !is_on_curve(p) {
+                return Err("Point is not on the curve".to_string());
+            }
+            Ok(p)
+        }
+
+        pub fn public_key(sk: $scalar) -> Bytes {
+            encode_point(point_mul(sk))
+        }
+
+        // The ECDH shared secret is the x-coordinate of sk * Q for the peer's public
+        // key Q (SEC 1, 3.3.1), which is the TLS premaster secret.
+        pub fn ecdh(sk: $scalar, peer: Bytes) -> Result<Bytes, String> {
+            let q = decode_point(peer)?;
+            let z = montgomery_ladder(sk, affine_to_jacobian(q));
+            if is_point_at_infinity(z) {
+                return Err("ECDH shared point is at infinity".to_string());
+            }
+            Ok(Bytes::from(&encode_field(jacobian_to_affine(z).0)[..]))
+        }
+    };
+}
+
+pub(crate) use weierstrass_curve;
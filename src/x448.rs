@@ -0,0 +1,73 @@
+// Import hacspec and all needed definitions.
+use hacspec::*;
+hacspec_imports!();
+
+use crate::montgomery::montgomery_ladder;
+
+// Define field mod 2^448 - 2^224 - 1
+#[field(fffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffffffffffffffffffffffffffffffffffffffffffffffffffff)]
+struct FieldElement;
+
+// Define 448-bit scalars
+#[bits(448)]
+struct Scalar;
+
+bytes!(SerializedPoint, 56);
+bytes!(SerializedScalar, 56);
+
+// Curve448 has A = 156326; clamped scalars have bit 447 at the top.
+montgomery_ladder!(FieldElement, Scalar, 448, 39081);
+
+// Clamping clears the two low bits, so that the scalar is a multiple of the
+// cofactor 4, and sets bit 447 (RFC 7748, section 5).
+fn mask_scalar(s: SerializedScalar) -> SerializedScalar {
+    let mut k = s;
+    k[0] &= 252;
+    k[55] |= 128;
+    k
+}
+
+fn decode_scalar(s: SerializedScalar) -> Scalar {
+    let k = mask_scalar(s);
+    Scalar::from_bytes_le(&k.raw())
+}
+
+// All 448 bits of u are used, and values of p or more are reduced.
+fn decode_point(u: SerializedPoint) -> Point {
+    (FieldElement::from_bytes_le(&u.raw()), FieldElement::from(1))
+}
+
+fn encode_point(p: Point) -> SerializedPoint {
+    let (x, y) = p;
+    let b = x * y.inv();
+    SerializedPoint::from_field(b)
+}
+
+// X448(k, u) of RFC 7748, section 5.
+pub fn x448(scalar: SerializedScalar, point: SerializedPoint) -> SerializedPoint {
+    let s_ = decode_scalar(scalar);
+    let p_ = decode_point(point);
+    let r = montgomery_ladder(s_, p_);
+    encode_point(r)
+}
+
+// The public key of a scalar: X448 of the base point u = 5.
+pub fn x448_base(scalar: SerializedScalar) -> SerializedPoint {
+    let mut base = SerializedPoint::new();
+    base[0] = 5;
+    x448(scalar, base)
+}
+
+// The shared secret of an (EC)DHE exchange, which TLS refuses when it is all
+// zeros, as for X25519.
+pub fn x448_shared(
+    scalar: SerializedScalar,
+    point: SerializedPoint,
+) -> Result<SerializedPoint, String> {
+    let shared = x448(scalar, point);
+    if shared == SerializedPoint::new() {
+        Err("X448 shared secret is all zeros".to_string())
+    } else {
+        Ok(shared)
+    }
+}
@@ -0,0 +1,494 @@
+use crate::der::{self, DerError, Parser, Time, Tlv};
+use crate::sha256::Sha256;
+use crate::sha512::Sha384;
+use crate::tls12::{fail, step, AlertDescription, Peer, ProtocolOptions, State};
+use crate::{p256, p384};
+use hacspec::Bytes;
+use std::fmt;
+
+/// The bits of the KeyUsage extension (RFC 5280, 4.2.1.3) that TLS cares
+/// about, as `Certificate::key_usage` holds them: bit n of the BIT STRING is
+/// `1 << n`.
+pub const DIGITAL_SIGNATURE: u16 = 1 << 0;
+pub const KEY_ENCIPHERMENT: u16 = 1 << 2;
+pub const KEY_AGREEMENT: u16 = 1 << 4;
+pub const KEY_CERT_SIGN: u16 = 1 << 5;
+
+// 2.5.29.15 and 2.5.29.19
+const ID_CE_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+const ID_CE_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+/// The fields of a certificate (RFC 5280, 4.1) that path validation
+/// looks at. Names are compared as their encodings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate<'a> {
+    /// The encoding of tbsCertificate: what the issuer signed.
+    pub tbs_certificate: &'a [u8],
+    pub signature_algorithm: &'a [u8],
+    pub signature: &'a [u8],
+    pub serial_number: &'a [u8],
+    pub issuer: &'a [u8],
+    pub subject: &'a [u8],
+    pub not_before: Time,
+    pub not_after: Time,
+    pub public_key_algorithm: &'a [u8],
+    /// The named curve of an EC key.
+    pub public_key_curve: Option<&'a [u8]>,
+    pub public_key: &'a [u8],
+    /// cA and pathLenConstraint of BasicConstraints, if present.
+    pub basic_constraints: Option<(bool, Option<u64>)>,
+    pub key_usage: Option<u16>,
+}
+
+/// Why a certificate chain doesn't validate. The index is that of the
+/// certificate in the chain whose check failed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathError {
+    Der(DerError),
+    /// Well-formed DER, but not a certificate.
+    Malformed(&'static str),
+    /// A critical extension this spec doesn't know (RFC 5280, 4.2).
+    UnknownCriticalExtension,
+    UnsupportedAlgorithm(usize),
+    EmptyChain,
+    Expired(usize),
+    NotYetValid(usize),
+    /// The next certificate in the chain isn't the one named as issuer.
+    IssuerMismatch(usize),
+    /// Neither the chain nor the trust anchors hold the issuer.
+    UnknownIssuer(usize),
+    /// The issuer isn't a CA, may not sign certificates, or has more CAs
+    /// below it than its pathLenConstraint allows.
+    NotCa(usize),
+    PathLenExceeded(usize),
+    KeyUsage(usize),
+    BadSignature(usize),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::Der(e) => e.fmt(f),
+            PathError::Malformed(field) => write!(f, "malformed certificate {}", field),
+            PathError::UnknownCriticalExtension => write!(f, "unknown critical extension"),
+            PathError::UnsupportedAlgorithm(i) => {
+                write!(f, "unsupported signature algorithm in certificate {}", i)
+            }
+            PathError::EmptyChain => write!(f, "empty certificate chain"),
+            PathError::Expired(i) => write!(f, "certificate {} has expired", i),
+            PathError::NotYetValid(i) => write!(f, "certificate {} is not yet valid", i),
+            PathError::IssuerMismatch(i) => {
+                write!(f, "certificate {} is not followed by its issuer", i)
+            }
+            PathError::UnknownIssuer(i) => write!(f, "unknown issuer of certificate {}", i),
+            PathError::NotCa(i) => write!(f, "issuer of certificate {} is not a CA", i),
+            PathError::PathLenExceeded(i) => {
+                write!(f, "issuer of certificate {} exceeds its path length", i)
+            }
+            PathError::KeyUsage(i) => write!(f, "key usage of certificate {}", i),
+            PathError::BadSignature(i) => write!(f, "bad signature on certificate {}", i),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl From<DerError> for PathError {
+    fn from(e: DerError) -> PathError {
+        PathError::Der(e)
+    }
+}
+
+impl PathError {
+    /// The alert the receiver of the chain answers with (RFC 5246, 7.2.2).
+    pub fn alert(self) -> AlertDescription {
+        match self {
+            PathError::UnknownCriticalExtension | PathError::UnsupportedAlgorithm(_) => {
+                AlertDescription::UnsupportedCertificate
+            }
+            PathError::EmptyChain => AlertDescription::HandshakeFailure,
+            PathError::Expired(_) | PathError::NotYetValid(_) => {
+                AlertDescription::CertificateExpired
+            }
+            PathError::UnknownIssuer(_) => AlertDescription::UnknownCa,
+            _ => AlertDescription::BadCertificate,
+        }
+    }
+}
+
+// AlgorithmIdentifier ::= SEQUENCE { algorithm OID, parameters ANY OPTIONAL }
+fn read_algorithm<'a>(p: &mut Parser<'a>) -> Result<(&'a [u8], Option<Tlv<'a>>), PathError> {
+    let mut algorithm = p.read_sequence()?;
+    let oid = algorithm.read_oid()?;
+    let parameters = if algorithm.is_empty() { None } else { Some(algorithm.read_tlv()?) };
+    algorithm.end()?;
+    Ok((oid, parameters))
+}
+
+// The named bits of a BIT STRING, which may have unused bits at the end.
+fn read_named_bits(value: &[u8]) -> Result<u16, PathError> {
+    let (unused, bits) = match value {
+        [0] => return Ok(0),
+        [unused, bits @ ..] if *unused < 8 && !bits.is_empty() => (*unused as usize, bits),
+        _ => return Err(PathError::Malformed("key usage")),
+    };
+    let mut out = 0;
+    for n in 0..(bits.len() * 8 - unused).min(16) {
+        if bits[n / 8] & (0x80 >> (n % 8)) != 0 {
+            out |= 1 << n;
+        }
+    }
+    Ok(out)
+}
+
+/// Picks a DER certificate apart, refusing critical extensions other than
+/// BasicConstraints and KeyUsage.
+pub fn parse_certificate(input: &[u8]) -> Result<Certificate<'_>, PathError> {
+    let mut cert = Parser::new(der::parse_one(input, der::SEQUENCE)?.value);
+    let tbs = cert.read(der::SEQUENCE)?;
+    let (signature_algorithm, _) = read_algorithm(&mut cert)?;
+    let signature = cert.read_bit_string()?;
+    cert.end()?;
+
+    let mut p = Parser::new(tbs.value);
+    let version = match p.read_optional(der::context(0))? {
+        Some(v) => der::parse_one(v.value, der::INTEGER)?.value,
+        None => &[0],
+    };
+    let serial_number = p.read(der::INTEGER)?.value;
+    if read_algorithm(&mut p)?.0 != signature_algorithm {
+        return Err(PathError::Malformed("signature algorithm"));
+    }
+    let issuer = p.read(der::SEQUENCE)?.encoded;
+    let mut validity = p.read_sequence()?;
+    let not_before = validity.read_time()?;
+    let not_after = validity.read_time()?;
+    validity.end()?;
+    let subject = p.read(der::SEQUENCE)?.encoded;
+
+    let mut spki = p.read_sequence()?;
+    let (public_key_algorithm, parameters) = read_algorithm(&mut spki)?;
+    let public_key_curve = match parameters {
+        Some(Tlv { tag: der::OID, value, .. }) => Some(value),
+        _ => None,
+    };
+    let public_key = spki.read_bit_string()?;
+    spki.end()?;
+
+    // issuerUniqueID and subjectUniqueID, which only v2 and v3 have.
+    p.read_optional(0x81)?;
+    p.read_optional(0x82)?;
+    let mut basic_constraints = None;
+    let mut key_usage = None;
+    if let Some(extensions) = p.read_optional(der::context(3))? {
+        if version != [2] {
+            return Err(PathError::Malformed("version"));
+        }
+        let mut list = Parser::new(der::parse_one(extensions.value, der::SEQUENCE)?.value);
+        while !list.is_empty() {
+            // Extension ::= SEQUENCE { extnID, critical BOOLEAN DEFAULT FALSE,
+            // extnValue OCTET STRING }
+            let mut extension = list.read_sequence()?;
+            let id = extension.read_oid()?;
+            let critical = match extension.peek_tag() {
+                Some(der::BOOLEAN) => extension.read_boolean()?,
+                _ => false,
+            };
+            let value = extension.read(der::OCTET_STRING)?.value;
+            extension.end()?;
+            if id == ID_CE_BASIC_CONSTRAINTS {
+                let mut bc = Parser::new(der::parse_one(value, der::SEQUENCE)?.value);
+                let ca = match bc.peek_tag() {
+                    Some(der::BOOLEAN) => bc.read_boolean()?,
+                    _ => false,
+                };
+                let path_len = if bc.is_empty() { None } else { Some(bc.read_small_unsigned()?) };
+                bc.end()?;
+                basic_constraints = Some((ca, path_len));
+            } else if id == ID_CE_KEY_USAGE {
+                key_usage = Some(read_named_bits(der::parse_one(value, der::BIT_STRING)?.value)?);
+            } else if critical {
+                return Err(PathError::UnknownCriticalExtension);
+            }
+        }
+    }
+    p.end()?;
+
+    Ok(Certificate {
+        tbs_certificate: tbs.encoded,
+        signature_algorithm,
+        signature,
+        serial_number,
+        issuer,
+        subject,
+        not_before,
+        not_after,
+        public_key_algorithm,
+        public_key_curve,
+        public_key,
+        basic_constraints,
+        key_usage,
+    })
+}
+
+// Ecdsa-Sig-Value ::= SEQUENCE { r INTEGER, s INTEGER }, with r and s
+// padded to the field length the curve's ECDSA takes.
+fn ecdsa_signature(signature: &[u8], len: usize) -> Option<(Bytes, Bytes)> {
+    let mut p = Parser::new(der::parse_one(signature, der::SEQUENCE).ok()?.value);
+    let mut pad = || {
+        let v = p.read_unsigned().ok()?;
+        if v.len() > len {
+            return None;
+        }
+        let mut out = vec![0; len - v.len()];
+        out.extend_from_slice(v);
+        Some(Bytes::from_vec(out))
+    };
+    let (r, s) = (pad()?, pad()?);
+    p.end().ok()?;
+    Some((r, s))
+}
+
+// Whether `issuer`'s key signed `cert`, the certificate at `i`. ECDSA on
+// P-256 and P-384, with SHA-256 or SHA-384, is all this spec verifies.
+fn check_signature(issuer: &Certificate, cert: &Certificate, i: usize) -> Result<(), PathError> {
+    let sha384 = match cert.signature_algorithm {
+        der::ECDSA_WITH_SHA256 => false,
+        der::ECDSA_WITH_SHA384 => true,
+        _ => return Err(PathError::UnsupportedAlgorithm(i)),
+    };
+    let (curve, len) = match issuer.public_key_curve {
+        _ if issuer.public_key_algorithm != der::ID_EC_PUBLIC_KEY => {
+            return Err(PathError::UnsupportedAlgorithm(i))
+        }
+        Some(der::PRIME256V1) => (256, 32),
+        Some(der::SECP384R1) => (384, 48),
+        _ => return Err(PathError::UnsupportedAlgorithm(i)),
+    };
+    let (r, s) = ecdsa_signature(cert.signature, len).ok_or(PathError::BadSignature(i))?;
+    let pk = Bytes::from_vec(issuer.public_key.to_vec());
+    let msg = Bytes::from_vec(cert.tbs_certificate.to_vec());
+    let valid = match (curve, sha384) {
+        (256, false) => p256::ecdsa_verify::<Sha256>(pk, msg, &p256::Signature { r, s }),
+        (256, true) => p256::ecdsa_verify::<Sha384>(pk, msg, &p256::Signature { r, s }),
+        (_, false) => p384::ecdsa_verify::<Sha256>(pk, msg, &p384::Signature { r, s }),
+        (_, true) => p384::ecdsa_verify::<Sha384>(pk, msg, &p384::Signature { r, s }),
+    };
+    valid.map_err(|_| PathError::BadSignature(i))
+}
+
+// Whether `issuer` may issue the certificate at `i`, which has i CAs
+// between it and the end entity.
+fn check_issuer(issuer: &Certificate, i: usize) -> Result<(), PathError> {
+    match issuer.basic_constraints {
+        Some((true, Some(path_len))) if path_len < i as u64 => {
+            return Err(PathError::PathLenExceeded(i))
+        }
+        Some((true, _)) => {}
+        _ => return Err(PathError::NotCa(i)),
+    }
+    if issuer.key_usage.is_some_and(|k| k & KEY_CERT_SIGN == 0) {
+        return Err(PathError::NotCa(i));
+    }
+    Ok(())
+}
+
+/// Basic path validation (RFC 5280, 6.1, without policies or name
+/// constraints) of a chain as the Certificate message carries it: the end
+/// entity first, each certificate followed by its issuer, up to one issued
+/// by a trust anchor or a trust anchor itself.
+///
+/// Every certificate must be valid at `now` and carry its issuer's
+/// signature. Issuers must be CAs allowed to sign certificates, with no
+/// more CAs below them than their pathLenConstraint. The end entity's key
+/// must be usable for each bit of `usage` its KeyUsage names. Trust
+/// anchors are taken as they are.
+pub fn validate_path(
+    chain: &[Vec<u8>],
+    anchors: &[Vec<u8>],
+    now: Time,
+    usage: u16,
+) -> Result<(), PathError> {
+    if chain.is_empty() {
+        return Err(PathError::EmptyChain);
+    }
+    let certs = chain.iter().map(|c| parse_certificate(c)).collect::<Result<Vec<_>, _>>()?;
+    let trusted = anchors.iter().map(|c| parse_certificate(c)).collect::<Result<Vec<_>, _>>()?;
+    if certs[0].key_usage.is_some_and(|k| k & usage != usage) {
+        return Err(PathError::KeyUsage(0));
+    }
+    for (i, cert) in certs.iter().enumerate() {
+        if anchors.contains(&chain[i]) {
+            return Ok(());
+        }
+        if now < cert.not_before {
+            return Err(PathError::NotYetValid(i));
+        }
+        if now > cert.not_after {
+            return Err(PathError::Expired(i));
+        }
+        let issuer = match certs.get(i + 1) {
+            Some(next) if next.subject == cert.issuer => next,
+            Some(_) => return Err(PathError::IssuerMismatch(i)),
+            None => trusted
+                .iter()
+                .find(|a| a.subject == cert.issuer)
+                .ok_or(PathError::UnknownIssuer(i))?,
+        };
+        check_issuer(issuer, i)?;
+        check_signature(issuer, cert, i)?;
+    }
+    Ok(())
+}
+
+/// Steps the TLS 1.2 handshake past a Certificate state with the chain it
+/// sends, or past a CertificateURL with the chain the server fetched: the
+/// receiver validates the chain, and answers one that doesn't validate
+/// with the alert for its error. `usage` is what the key exchange
+/// needs of the end entity's key: a signature for ECDHE_ECDSA, key
+/// encipherment for RSA.
+pub fn step_with_certificate(
+    st: State,
+    opts: ProtocolOptions,
+    chain: &[Vec<u8>],
+    anchors: &[Vec<u8>],
+    now: Time,
+    usage: u16,
+) -> State {
+    let receiver = match st {
+        State::ServerSendsCertificate => Peer::Client,
+        State::ClientSendsCertificate | State::ClientSendsCertificateUrl => Peer::Server,
+        _ => return step(st, opts),
+    };
+    match validate_path(chain, anchors, now, usage) {
+        Ok(()) => step(st, opts),
+        Err(e) => fail(st, receiver, e.alert()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::certificate::test::{hex, INTERMEDIATE, LEAF, ROOT};
+
+    fn at(year: u16, month: u8) -> Time {
+        Time { year, month, day: 1, hour: 0, minute: 0, second: 0 }
+    }
+
+    fn chain() -> Vec<Vec<u8>> {
+        vec![hex(LEAF), hex(INTERMEDIATE)]
+    }
+
+    #[test]
+    fn fields() {
+        let leaf = hex(LEAF);
+        let cert = parse_certificate(&leaf).unwrap();
+        assert_eq!(cert.serial_number, &[3]);
+        assert_eq!(cert.signature_algorithm, der::ECDSA_WITH_SHA256);
+        assert_eq!(cert.public_key_curve, Some(der::PRIME256V1));
+        assert_eq!(cert.public_key.len(), 65);
+        assert_eq!(cert.basic_constraints, Some((false, None)));
+        assert_eq!(cert.key_usage, Some(DIGITAL_SIGNATURE));
+        assert_eq!(cert.not_after.year, 2029);
+
+        let intermediate = hex(INTERMEDIATE);
+        let ca = parse_certificate(&intermediate).unwrap();
+        assert_eq!(ca.subject, cert.issuer);
+        assert_eq!(ca.basic_constraints, Some((true, Some(0))));
+        assert_eq!(ca.key_usage, Some(KEY_CERT_SIGN | 1 << 6));
+        let root = hex(ROOT);
+        let root = parse_certificate(&root).unwrap();
+        assert_eq!((root.subject, root.basic_constraints), (root.issuer, Some((true, None))));
+
+        assert_eq!(read_named_bits(&[7, 0x80]), Ok(DIGITAL_SIGNATURE));
+        assert_eq!(read_named_bits(&[1, 0x86]), Ok(DIGITAL_SIGNATURE | KEY_CERT_SIGN | 1 << 6));
+        assert!(read_named_bits(&[8, 0x80]).is_err());
+    }
+
+    #[test]
+    fn valid_chains() {
+        let anchors = vec![hex(ROOT)];
+        let now = at(2027, 6);
+        assert_eq!(validate_path(&chain(), &anchors, now, DIGITAL_SIGNATURE), Ok(()));
+        // With the root sent too, or the intermediate trusted directly.
+        let mut full = chain();
+        full.push(hex(ROOT));
+        assert_eq!(validate_path(&full, &anchors, now, DIGITAL_SIGNATURE), Ok(()));
+        let anchors = vec![hex(INTERMEDIATE)];
+        assert_eq!(validate_path(&chain()[..1], &anchors, now, DIGITAL_SIGNATURE), Ok(()));
+        assert_eq!(validate_path(&chain(), &anchors, now, DIGITAL_SIGNATURE), Ok(()));
+    }
+
+    #[test]
+    fn invalid_chains() {
+        let anchors = vec![hex(ROOT)];
+        let now = at(2027, 6);
+        let validate = |chain: &[Vec<u8>], now| validate_path(chain, &anchors, now, 0);
+        assert_eq!(validate(&[], now), Err(PathError::EmptyChain));
+        assert_eq!(validate(&chain(), at(2026, 1)), Err(PathError::NotYetValid(0)));
+        assert_eq!(validate(&chain(), at(2029, 2)), Err(PathError::Expired(0)));
+        assert_eq!(validate(&chain()[..1], now), Err(PathError::UnknownIssuer(0)));
+        let reversed = vec![hex(INTERMEDIATE), hex(LEAF)];
+        assert_eq!(validate(&reversed, now), Err(PathError::IssuerMismatch(0)));
+        assert_eq!(validate_path(&chain(), &[], now, 0), Err(PathError::UnknownIssuer(1)));
+        assert_eq!(
+            validate_path(&chain(), &anchors, now, KEY_ENCIPHERMENT),
+            Err(PathError::KeyUsage(0))
+        );
+
+        // A change to the signed part of the leaf: its serial number.
+        let mut forged = chain();
+        assert_eq!(forged[0][15], 0x03);
+        forged[0][15] = 0x04;
+        assert_eq!(validate(&forged, now), Err(PathError::BadSignature(0)));
+        assert_eq!(PathError::BadSignature(0).alert(), AlertDescription::BadCertificate);
+
+        let mut truncated = hex(LEAF);
+        truncated.pop();
+        assert_eq!(validate(&[truncated], now), Err(PathError::Der(DerError::Truncated)));
+    }
+
+    #[test]
+    fn issuers() {
+        let (leaf, intermediate, root) = (hex(LEAF), hex(INTERMEDIATE), hex(ROOT));
+        let leaf = parse_certificate(&leaf).unwrap();
+        let intermediate = parse_certificate(&intermediate).unwrap();
+        let root = parse_certificate(&root).unwrap();
+        assert_eq!(check_signature(&intermediate, &leaf, 0), Ok(()));
+        assert_eq!(check_signature(&root, &intermediate, 1), Ok(()));
+        assert_eq!(check_signature(&root, &leaf, 0), Err(PathError::BadSignature(0)));
+        // ecdsa-with-SHA1
+        let oid = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x01];
+        let sha1 = Certificate { signature_algorithm: &oid, ..leaf.clone() };
+        let unsupported = Err(PathError::UnsupportedAlgorithm(0));
+        assert_eq!(check_signature(&intermediate, &sha1, 0), unsupported);
+
+        // The intermediate's pathLenConstraint of 0 allows no CA below it,
+        // and the leaf isn't a CA.
+        assert_eq!(check_issuer(&intermediate, 0), Ok(()));
+        assert_eq!(check_issuer(&intermediate, 1), Err(PathError::PathLenExceeded(1)));
+        assert_eq!(check_issuer(&root, 1), Ok(()));
+        assert_eq!(check_issuer(&leaf, 0), Err(PathError::NotCa(0)));
+        let no_cert_sign = Certificate { key_usage: Some(DIGITAL_SIGNATURE), ..root };
+        assert_eq!(check_issuer(&no_cert_sign, 1), Err(PathError::NotCa(1)));
+    }
+
+    #[test]
+    fn certificate_states() {
+        let opts = ProtocolOptions::default();
+        let anchors = vec![hex(ROOT)];
+        let st = State::ServerSendsCertificate;
+        let good = step_with_certificate(st, opts, &chain(), &anchors, at(2027, 6), 0);
+        assert_eq!(good, step(st, opts));
+        let expired = step_with_certificate(st, opts, &chain(), &anchors, at(2030, 1), 0);
+        assert_eq!(expired, State::SendsAlert(Peer::Client, AlertDescription::CertificateExpired));
+        let unknown = step_with_certificate(st, opts, &chain(), &[], at(2027, 6), 0);
+        assert_eq!(unknown, State::SendsAlert(Peer::Client, AlertDescription::UnknownCa));
+
+        let st = State::ClientSendsCertificate;
+        let bad = step_with_certificate(st, opts, &chain()[1..], &[], at(2027, 6), 0);
+        assert_eq!(bad, State::SendsAlert(Peer::Server, AlertDescription::UnknownCa));
+        let st = State::ServerSendsServerHelloDone;
+        assert_eq!(step_with_certificate(st, opts, &[], &[], at(2027, 6), 0), step(st, opts));
+    }
+}
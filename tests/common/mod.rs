@@ -0,0 +1,10 @@
+use hacspec::*;
+
+/// The bytes a string of hex digits spells, two digits to a byte.
+pub fn hex(s: &str) -> Bytes {
+    let b = (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    Bytes::from_vec(b)
+}
@@ -0,0 +1,204 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::aes::Block;
+use hacspecs::aescbc::*;
+use hacspecs::sha1::Sha1;
+use hacspecs::sha256::Sha256;
+
+mod common;
+use common::hex;
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+// SP 800-38A, F.2.1 and F.2.5: the first two blocks.
+#[test]
+fn test_cbc_kat() {
+    let iv = Block::from(&iota(0, 16)[..]);
+    let msg = hex(concat!("6bc1bee22e409f96e93d7e117393172a", "ae2d8a571e03ac9c9eb76fac45af8e51"));
+    let keys = [
+        (
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2",
+        ),
+        (
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d",
+        ),
+    ];
+    for (key, expected) in keys.iter() {
+        let c = cbc_encrypt(hex(key), iv, msg.clone()).unwrap();
+        assert_eq!(hex(expected).raw(), c.raw());
+        let m = cbc_decrypt(hex(key), iv, c).unwrap();
+        assert_eq!(msg.raw(), m.raw());
+    }
+    assert!(cbc_encrypt(hex(keys[0].0), iv, Bytes::new_len(17)).is_err());
+    assert!(cbc_encrypt(Bytes::new_len(24), iv, msg).is_err());
+}
+
+// The records below were computed with Python's hmac and the `cryptography`
+// package, for an application_data record with sequence number 1 in TLS 1.2,
+// holding "hello, world" under HMAC-SHA256 and AES-128.
+fn keys() -> CbcKeys {
+    CbcKeys { mac_key: iota(0, 32), enc_key: iota(0x40, 16) }
+}
+
+fn content() -> Bytes {
+    Bytes::from_vec(b"hello, world".to_vec())
+}
+
+static RECORD_MINIMAL: &str = concat!(
+    "808182838485868788898a8b8c8d8e8f8097699e2a544df9a46f9dd38b145ead",
+    "c6731067519b7427266e0f4cbf4e280d17728b7caf4344db24ad77731e2b1831"
+);
+
+// With 19 bytes of padding instead of 3.
+static RECORD_LONG_PADDING: &str = concat!(
+    "808182838485868788898a8b8c8d8e8f8097699e2a544df9a46f9dd38b145ead",
+    "c6731067519b7427266e0f4cbf4e280d1b4757ff0700b7292cd368f1d8d10e7e",
+    "b948ad302344885932331483b4cfd811"
+);
+
+// Padding 03 02 03 03.
+static RECORD_BAD_PADDING_BYTE: &str = concat!(
+    "808182838485868788898a8b8c8d8e8f8097699e2a544df9a46f9dd38b145ead",
+    "c6731067519b7427266e0f4cbf4e280df92741463d09863266c8571c2e0aee2f"
+);
+
+// Padding c8 c8 c8 c8: the padding_length is longer than the record.
+static RECORD_PADDING_TOO_LONG: &str = concat!(
+    "808182838485868788898a8b8c8d8e8f8097699e2a544df9a46f9dd38b145ead",
+    "c6731067519b7427266e0f4cbf4e280d14a48463ad9bac8c739d4ddaee17a3fe"
+);
+
+// Good padding, but the first byte of the MAC flipped.
+static RECORD_BAD_MAC: &str = concat!(
+    "808182838485868788898a8b8c8d8e8f2f656c0fc7aeb23c4ae7d7e576798434",
+    "ce616392ab045bbf7e1a0d507abe72872c93616b7d6dd9ae94a56d144d276d20"
+);
+
+#[test]
+fn test_seal() {
+    let iv = Block::from(&iota(0x80, 16)[..]);
+    assert_eq!(padding_length::<Sha256>(12), 3);
+    let record = seal::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 3).unwrap();
+    assert_eq!(hex(RECORD_MINIMAL).raw(), record.raw());
+    let record = seal::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 19).unwrap();
+    assert_eq!(hex(RECORD_LONG_PADDING).raw(), record.raw());
+    assert!(seal::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 4).is_err());
+}
+
+#[test]
+fn test_open() {
+    for record in [RECORD_MINIMAL, RECORD_LONG_PADDING].iter() {
+        let out = open::<Sha256>(&keys(), 1, 23, 0x0303, hex(record)).unwrap();
+        assert_eq!(content().raw(), out.raw());
+    }
+    // The MAC covers the sequence number, type and version.
+    assert!(open::<Sha256>(&keys(), 2, 23, 0x0303, hex(RECORD_MINIMAL)).is_err());
+    assert!(open::<Sha256>(&keys(), 1, 22, 0x0303, hex(RECORD_MINIMAL)).is_err());
+    assert!(open::<Sha256>(&keys(), 1, 23, 0x0302, hex(RECORD_MINIMAL)).is_err());
+}
+
+#[test]
+fn test_open_malformed() {
+    let open = |record| open::<Sha256>(&keys(), 1, 23, 0x0303, hex(record)).unwrap_err();
+    assert_eq!(open(RECORD_BAD_PADDING_BYTE), "Bad record padding");
+    assert_eq!(open(RECORD_PADDING_TOO_LONG), "Bad record padding");
+    assert_eq!(open(RECORD_BAD_MAC), "Bad record MAC");
+    assert_eq!(open(&RECORD_MINIMAL[0..62]), "Bad record length");
+    assert_eq!(open(&RECORD_MINIMAL[0..126]), "Bad record length");
+}
+
+#[test]
+fn test_check_padding() {
+    assert_eq!(check_padding(&hex("aabb00")), Ok(2));
+    assert!(check_padding(&hex("aa0202")).is_err());
+    assert_eq!(check_padding(&hex("aa020202")), Ok(1));
+    assert_eq!(check_padding(&hex("020202")), Ok(0));
+    assert!(check_padding(&hex("0303")).is_err());
+    assert!(check_padding(&Bytes::new_len(0)).is_err());
+}
+
+// A handshake record of TLS 1.1 under HMAC-SHA1 and AES-256, sequence
+// number 0, with 15 bytes of padding.
+#[test]
+fn test_sha1_aes256() {
+    let keys = CbcKeys { mac_key: iota(0, 20), enc_key: iota(0x40, 32) };
+    let expected = hex(concat!(
+        "808182838485868788898a8b8c8d8e8f743f9d8d70a9fe6fc5c5f0df3230db15",
+        "89284c18a2c8ee76201c93ee3cac4313b09534084db3a3014d240cb819336e93"
+    ));
+    let iv = Block::from(&iota(0x80, 16)[..]);
+    assert_eq!(padding_length::<Sha1>(12), 15);
+    let record = seal::<Sha1>(&keys, iv, 0, 22, 0x0302, content(), 15).unwrap();
+    assert_eq!(expected.raw(), record.raw());
+    let out = open::<Sha1>(&keys, 0, 22, 0x0302, record).unwrap();
+    assert_eq!(content().raw(), out.raw());
+}
+
+// Encrypt-then-MAC records for the same keys and content, from the same
+// tools: with the minimal 3 bytes of padding and with 19.
+static RECORD_ETM: &str = concat!(
+    "808182838485868788898a8b8c8d8e8fb1288e381e1f2bbe8c3a9ec5b6169630",
+    "5f4b4ff00706b25444bfb0bce77fe7e5476116a9d483590a45c53b13d61a648a"
+);
+static RECORD_ETM_LONG_PADDING: &str = concat!(
+    "808182838485868788898a8b8c8d8e8fb163e1c18c368ba9767720d80aab89ea",
+    "53eb983f6f56eb2ce662353db186eaa1b108c14fb33c2b05bc7919b18939f21e",
+    "45298cde219f0b295930e180a08b42c9"
+);
+
+#[test]
+fn test_seal_etm() {
+    let iv = Block::from(&iota(0x80, 16)[..]);
+    assert_eq!(padding_length_etm(12), 3);
+    let record = seal_etm::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 3).unwrap();
+    assert_eq!(hex(RECORD_ETM).raw(), record.raw());
+    let record = seal_etm::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 19).unwrap();
+    assert_eq!(hex(RECORD_ETM_LONG_PADDING).raw(), record.raw());
+    assert!(seal_etm::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 4).is_err());
+    // The same cipher text as MAC-then-encrypt would give a different
+    // record.
+    let mte = seal::<Sha256>(&keys(), iv, 1, 23, 0x0303, content(), 3).unwrap();
+    assert_ne!(mte.raw(), record.raw());
+}
+
+#[test]
+fn test_open_etm() {
+    for record in [RECORD_ETM, RECORD_ETM_LONG_PADDING].iter() {
+        let out = open_etm::<Sha256>(&keys(), 1, 23, 0x0303, hex(record)).unwrap();
+        assert_eq!(content().raw(), out.raw());
+    }
+    let open = |seq, record: Bytes| open_etm::<Sha256>(&keys(), seq, 23, 0x0303, record);
+    assert_eq!(open(2, hex(RECORD_ETM)).unwrap_err(), "Bad record MAC");
+    // Any change to the IV or cipher text fails the MAC, before the
+    // padding is looked at.
+    for &i in &[0, 20, 31] {
+        let mut record = hex(RECORD_ETM);
+        record[i] ^= 1;
+        assert_eq!(open(1, record).unwrap_err(), "Bad record MAC");
+    }
+    let short = Bytes::from(&hex(RECORD_ETM)[0..63]);
+    assert_eq!(open(1, short).unwrap_err(), "Bad record length");
+}
+
+#[test]
+fn test_sha1_aes256_etm() {
+    let keys = CbcKeys { mac_key: iota(0, 20), enc_key: iota(0x40, 32) };
+    let expected = hex(concat!(
+        "808182838485868788898a8b8c8d8e8ffd88e9219a8f421310e64566d713ef08",
+        "7d9723fd900951823b02694795e4e0cd3f7c3f95"
+    ));
+    let iv = Block::from(&iota(0x80, 16)[..]);
+    let etm = Construction::EncryptThenMac;
+    let record = seal_record::<Sha1>(etm, &keys, iv, 0, 22, 0x0302, content(), 3).unwrap();
+    assert_eq!(expected.raw(), record.raw());
+    let out = open_record::<Sha1>(etm, &keys, 0, 22, 0x0302, record.clone()).unwrap();
+    assert_eq!(content().raw(), out.raw());
+    let mte = Construction::MacThenEncrypt;
+    assert!(open_record::<Sha1>(mte, &keys, 0, 22, 0x0302, record).is_err());
+}
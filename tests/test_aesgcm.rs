@@ -66,3 +66,111 @@ fn kat_test() {
         assert_eq!(msg, decrypted_msg.get_slice());
     }
 }
+
+// Test cases 13 to 16 of the GCM specification, for AES-256.
+const KAT_256: [AeadTestVector; 4] = [
+    AeadTestVector {
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        msg: "",
+        aad: "",
+        exp_cipher: "",
+        exp_mac: "530f8afbc74536b9a963b4f1c4cb738b",
+    },
+    AeadTestVector {
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        msg: "00000000000000000000000000000000",
+        aad: "",
+        exp_cipher: "cea7403d4d606b6e074ec5d3baf39d18",
+        exp_mac: "d0d1c8a799996bf0265b98b5d48ab919",
+    },
+    AeadTestVector {
+        key: "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        nonce: "cafebabefacedbaddecaf888",
+        msg: concat!(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72",
+            "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255"
+        ),
+        aad: "",
+        exp_cipher: concat!(
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa",
+            "8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad"
+        ),
+        exp_mac: "b094dac5d93471bdec1a502270e3cc6c",
+    },
+    AeadTestVector {
+        key: "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        nonce: "cafebabefacedbaddecaf888",
+        msg: concat!(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72",
+            "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+        ),
+        aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+        exp_cipher: concat!(
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa",
+            "8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662"
+        ),
+        exp_mac: "76fc6ece0f4e1768cddf8853bb2d551b",
+    },
+];
+
+#[test]
+fn kat_test_256() {
+    for kat in KAT_256.iter() {
+        let k = aes::Key256::from(kat.key);
+        let nonce = aes::Nonce::from(kat.nonce);
+        let exp_mac = gf128::Tag::from(kat.exp_mac);
+        vlbytes!(msg, msg_, kat.msg);
+        vlbytes!(aad, aad_, kat.aad);
+        vlbytes!(exp_cipher, exp_cipher_, kat.exp_cipher);
+
+        let (cipher, mac) = encrypt_256(k, nonce, aad, msg);
+        assert_eq!(exp_cipher, cipher.get_slice());
+        assert_eq!(exp_mac, mac);
+
+        let decrypted_msg = decrypt_256(k, nonce, aad, cipher.get_slice(), mac).unwrap();
+        assert_eq!(msg, decrypted_msg.get_slice());
+    }
+}
+
+#[test]
+fn seal_open_test() {
+    for kat in KAT.iter().chain(KAT_256.iter()) {
+        let key = Bytes::from(kat.key);
+        let nonce = Bytes::from(kat.nonce);
+        let aad = Bytes::from(kat.aad);
+        let msg = Bytes::from(kat.msg);
+        let mut expected = Bytes::from(kat.exp_cipher).raw().to_vec();
+        expected.extend_from_slice(Bytes::from(kat.exp_mac).raw());
+
+        let sealed = seal(key.clone(), nonce.clone(), aad.clone(), msg.clone()).unwrap();
+        assert_eq!(&expected[..], sealed.raw());
+        let opened = open(key, nonce, aad, sealed).unwrap();
+        assert_eq!(msg.raw(), opened.raw());
+    }
+}
+
+#[test]
+fn open_rejects_test() {
+    let kat = &KAT[3];
+    let key = Bytes::from(kat.key);
+    let nonce = Bytes::from(kat.nonce);
+    let aad = Bytes::from(kat.aad);
+    let sealed = seal(key.clone(), nonce.clone(), aad.clone(), Bytes::from(kat.msg)).unwrap();
+
+    // Flipping a bit of the cipher text, the tag or the AAD fails.
+    for &i in [0, sealed.len() - 1].iter() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 1;
+        assert!(open(key.clone(), nonce.clone(), aad.clone(), tampered).is_err());
+    }
+    let mut tampered_aad = aad.clone();
+    tampered_aad[0] ^= 1;
+    assert!(open(key.clone(), nonce.clone(), tampered_aad, sealed.clone()).is_err());
+
+    // So do inputs of the wrong size.
+    assert!(open(key.clone(), nonce.clone(), aad.clone(), Bytes::new_len(15)).is_err());
+    assert!(open(Bytes::new_len(24), nonce.clone(), aad.clone(), sealed.clone()).is_err());
+    assert!(seal(key, Bytes::new_len(8), aad, Bytes::new_len(0)).is_err());
+}
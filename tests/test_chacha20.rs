@@ -4,6 +4,9 @@ use hacspec::*;
 extern crate hacspecs;
 use hacspecs::chacha20::*;
 
+mod common;
+use common::hex;
+
 #[test]
 fn test_quarter_round() {
     let mut state = [
@@ -117,3 +120,52 @@ fn test_kat() {
     ];
     kat_test(m[..].into(), key.into(), iv.into(), exp_cipher.into(), true);
 }
+
+// RFC 8439, appendix A.1, test vectors 1 and 2.
+#[test]
+fn test_block_vectors() {
+    let expected = [
+        concat!(
+            "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7",
+            "da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586"
+        ),
+        concat!(
+            "9f07e7be5551387a98ba977c732d080dcb0f29a048e3656912c6533e32ee7aed",
+            "29b721769ce64e43d57133b074d839d531ed1f28510afb45ace10a1f4b794d6f"
+        ),
+    ];
+    for (ctr, exp) in expected.iter().enumerate() {
+        let key_block = block(Key::new(), ctr as u32, IV::new());
+        assert_eq!(hex(exp).raw(), &key_block[..]);
+    }
+}
+
+// RFC 8439, section 2.4.2.
+#[test]
+fn test_encrypt() {
+    let key: Vec<u8> = (0..32).collect();
+    let iv = IV::from(&hex("000000000000004a00000000")[..]);
+    let m = Bytes::from_vec(
+        b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for \
+          the future, sunscreen would be it."
+            .to_vec(),
+    );
+    let exp_cipher = hex(concat!(
+        "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b",
+        "f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8",
+        "07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736",
+        "5af90bbf74a35be6b40b8eedf2785e42874d"
+    ));
+    let c = chacha20(Key::from(&key[..]), 1, iv, m.clone()).unwrap();
+    assert_eq!(exp_cipher, c);
+    assert_eq!(m, chacha20(Key::from(&key[..]), 1, iv, c).unwrap());
+}
+
+// The block counter is 32 bits; a message may use it up but not wrap it.
+#[test]
+fn test_counter_limit() {
+    let last = chacha20(Key::new(), 0xffff_ffff, IV::new(), Bytes::new_len(64)).unwrap();
+    assert_eq!(&block(Key::new(), 0xffff_ffff, IV::new())[..], last.raw());
+    assert!(chacha20(Key::new(), 0xffff_ffff, IV::new(), Bytes::new_len(65)).is_err());
+    assert!(chacha20(Key::new(), 0, IV::new(), Bytes::new_len(0)).is_ok());
+}
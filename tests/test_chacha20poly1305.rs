@@ -4,6 +4,9 @@ use hacspec::*;
 extern crate hacspecs;
 use hacspecs::{chacha20::*, chacha20poly1305, poly1305::*};
 
+mod common;
+use common::hex;
+
 #[test]
 fn kat_test() {
     let k = Key::from([
@@ -50,3 +53,60 @@ fn kat_test() {
     let decrypted_msg = chacha20poly1305::decrypt(k, iv, aad, cipher, mac).unwrap();
     assert_eq!(msg, decrypted_msg);
 }
+
+fn key() -> Bytes {
+    hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f")
+}
+
+fn nonce() -> Bytes {
+    hex("070000004041424344454647")
+}
+
+// The vector of RFC 8439, section 2.8.2, as one sealed record.
+#[test]
+fn seal_open_test() {
+    let aad = hex("50515253c0c1c2c3c4c5c6c7");
+    let msg = Bytes::from_vec(
+        b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for \
+          the future, sunscreen would be it."
+            .to_vec(),
+    );
+    let expected = hex(concat!(
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+        "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+        "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+        "3ff4def08e4b7a9de576d26586cec64b6116",
+        "1ae10b594f09e26a7e902ecbd0600691"
+    ));
+    let sealed = chacha20poly1305::seal(key(), nonce(), aad.clone(), msg.clone()).unwrap();
+    assert_eq!(expected, sealed);
+    let opened = chacha20poly1305::open(key(), nonce(), aad.clone(), sealed.clone()).unwrap();
+    assert_eq!(msg, opened);
+
+    let mut tampered = sealed.clone();
+    tampered[0] ^= 1;
+    assert!(chacha20poly1305::open(key(), nonce(), aad.clone(), tampered).is_err());
+    let mut tampered_aad = aad;
+    tampered_aad[11] ^= 1;
+    assert!(chacha20poly1305::open(key(), nonce(), tampered_aad, sealed).is_err());
+}
+
+// With an empty message, the tag covers the AAD and the length block alone;
+// computed with Python's `cryptography` package.
+#[test]
+fn empty_msg_test() {
+    let empty = Bytes::new_len(0);
+    for (aad, tag) in [
+        ("", "a0784d7a4716f3feb4f64e7f4b39bf04"),
+        ("50515253c0c1c2c3c4c5c6c7", "e622e5647a38d967a7ecbcb46c7f675c"),
+    ]
+    .iter()
+    {
+        let sealed = chacha20poly1305::seal(key(), nonce(), hex(aad), empty.clone()).unwrap();
+        assert_eq!(hex(tag), sealed);
+        let opened = chacha20poly1305::open(key(), nonce(), hex(aad), sealed).unwrap();
+        assert_eq!(empty, opened);
+    }
+    assert!(chacha20poly1305::open(key(), nonce(), empty.clone(), hex("00")).is_err());
+    assert!(chacha20poly1305::seal(key(), hex("00"), empty.clone(), empty).is_err());
+}
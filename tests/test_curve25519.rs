@@ -68,3 +68,51 @@ fn test_kat() {
         ecdh(s, u, expected);
     }
 }
+
+// RFC 7748, section 5.2: k and u start as the base point; each iteration
+// sets k to X25519(k, u) and u to the old k.
+#[test]
+fn test_iterated() {
+    let mut k = SerializedPoint::new();
+    k[0] = 9;
+    let mut u = k;
+    let expected = [
+        (1, "422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079"),
+        (1000, "684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51"),
+    ];
+    let mut i = 0;
+    for (iterations, result) in expected.iter() {
+        while i < *iterations {
+            let r = x25519(SerializedScalar::from(&k[..]), u);
+            u = k;
+            k = r;
+            i += 1;
+        }
+        assert_eq!(SerializedPoint::from(*result), k);
+    }
+}
+
+// RFC 7748, section 6.1.
+#[test]
+fn test_dh() {
+    let alice = SerializedScalar::from(KAT[0].0);
+    let bob = SerializedScalar::from(KAT[1].0);
+    let alice_public = x25519_base(alice);
+    let bob_public = x25519_base(bob);
+    assert_eq!(SerializedPoint::from(KAT[1].1), alice_public);
+    assert_eq!(SerializedPoint::from(KAT[0].1), bob_public);
+    assert_eq!(
+        x25519_shared(alice, bob_public).unwrap(),
+        x25519_shared(bob, alice_public).unwrap()
+    );
+}
+
+// Points of small order give an all zero secret, which is refused.
+#[test]
+fn test_small_order() {
+    let s = SerializedScalar::from(KAT[0].0);
+    let mut one = SerializedPoint::new();
+    one[0] = 1;
+    assert!(x25519_shared(s, SerializedPoint::new()).is_err());
+    assert!(x25519_shared(s, one).is_err());
+}
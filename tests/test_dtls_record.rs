@@ -0,0 +1,126 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::dtls_record::*;
+use hacspecs::sha256::Sha256;
+use hacspecs::tls13_record::Aead;
+
+mod common;
+use common::hex;
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+// The server handshake traffic secret of RFC 8448, section 3. The sn_keys
+// and masks below were computed with Python's hmac and `cryptography`, for
+// a ciphertext starting 0x10, 0x11, ...
+const SERVER_HS_TRAFFIC: &str = "b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38";
+const SN_KEY_128: &str = "449564e5e9f8e49301987ce0491eaf8d";
+const SN_KEY_256: &str = "151b040f8e6dde3a1c3add5c98be0d0f4f2282ede4b975635eb52782656bf925";
+
+#[test]
+fn test_sequence_numbers() {
+    assert_eq!(next_sequence_number(0), Ok(1));
+    assert_eq!(next_sequence_number(MAX_SEQUENCE_NUMBER - 1), Ok(MAX_SEQUENCE_NUMBER));
+    assert!(next_sequence_number(MAX_SEQUENCE_NUMBER).is_err());
+}
+
+#[test]
+fn test_epochs() {
+    assert_eq!(next_dtls12_epoch(0), Ok(1));
+    assert!(next_dtls12_epoch(MAX_DTLS12_EPOCH).is_err());
+    // Only KeyUpdates move a DTLS 1.3 epoch on, after the handshake.
+    assert_eq!(next_dtls13_epoch(DTLS13_APPLICATION_EPOCH), Ok(4));
+    assert_eq!(next_dtls13_epoch(0x10000), Ok(0x10001));
+    assert!(next_dtls13_epoch(2).is_err());
+    assert!(next_dtls13_epoch(u64::MAX).is_err());
+}
+
+#[test]
+fn test_replay_window() {
+    let mut window = ReplayWindow::new();
+    assert!(window.check(5));
+    window.update(5);
+    assert!(!window.check(5));
+    // Records may arrive out of order, each once.
+    assert!(window.check(3));
+    assert!(window.check(100));
+    window.update(3);
+    assert!(!window.check(3));
+    assert!(window.check(4));
+
+    // A jump slides the window: 37 and up are in it, 36 and below too old.
+    window.update(100);
+    assert!(!window.check(36));
+    assert!(window.check(37));
+    assert!(!window.check(100));
+    window.update(37);
+    assert!(!window.check(37));
+    assert!(window.check(38));
+
+    // Checking alone doesn't mark a record received.
+    assert!(window.check(101));
+    assert!(window.check(101));
+    window.update(101);
+    assert!(!window.check(100));
+    assert!(!window.check(37));
+    window.update(1000);
+    assert!(window.check(999));
+    assert!(!window.check(936));
+}
+
+#[test]
+fn test_reconstruct_sequence_number() {
+    assert_eq!(reconstruct_sequence_number(0, 0, 8), 0);
+    assert_eq!(reconstruct_sequence_number(0x1fe, 0x01, 8), 0x201);
+    assert_eq!(reconstruct_sequence_number(0x201, 0xfe, 8), 0x1fe);
+    assert_eq!(reconstruct_sequence_number(0x1234, 0x37, 8), 0x1237);
+    assert_eq!(reconstruct_sequence_number(0x12345, 0x2340, 16), 0x12340);
+    assert_eq!(reconstruct_sequence_number(0x1ffff, 0x0000, 16), 0x20000);
+    // Nothing below zero.
+    assert_eq!(reconstruct_sequence_number(2, 0xff, 8), 0xff);
+}
+
+#[test]
+fn test_sn_key() {
+    let secret = hex(SERVER_HS_TRAFFIC);
+    assert_eq!(&hex(SN_KEY_128)[..], &sn_key::<Sha256>(secret.clone(), Aead::Aes128Gcm)[..]);
+    assert_eq!(&hex(SN_KEY_256)[..], &sn_key::<Sha256>(secret.clone(), Aead::Aes256Gcm)[..]);
+    assert_eq!(&hex(SN_KEY_256)[..], &sn_key::<Sha256>(secret, Aead::ChaCha20Poly1305)[..]);
+}
+
+#[test]
+fn test_record_number_mask() {
+    let ciphertext = iota(0x10, 24);
+    let mask = record_number_mask(Aead::Aes128Gcm, &hex(SN_KEY_128), &ciphertext).unwrap();
+    assert_eq!(&hex("aab03530142380cf92a150a59dcf80f2")[..], &mask[..]);
+    let mask = record_number_mask(Aead::Aes256Gcm, &hex(SN_KEY_256), &ciphertext).unwrap();
+    assert_eq!(&hex("0f4a533f4b38e470cea22786e30f5e1f")[..], &mask[..]);
+    let mask = record_number_mask(Aead::ChaCha20Poly1305, &hex(SN_KEY_256), &ciphertext).unwrap();
+    assert_eq!(&hex("b23800b74a7513c9504d3d9c53cbd2c3")[..], &mask[..]);
+
+    // Only the first 16 bytes are sampled, and there must be that many.
+    let longer = iota(0x10, 40);
+    let mask = record_number_mask(Aead::Aes128Gcm, &hex(SN_KEY_128), &longer).unwrap();
+    assert_eq!(&hex("aab03530142380cf92a150a59dcf80f2")[..], &mask[..]);
+    let short = iota(0x10, 15);
+    assert!(record_number_mask(Aead::Aes128Gcm, &hex(SN_KEY_128), &short).is_err());
+    assert!(record_number_mask(Aead::Aes256Gcm, &hex(SN_KEY_128), &ciphertext).is_err());
+}
+
+#[test]
+fn test_encrypt_sequence_number() {
+    let (key, ciphertext) = (hex(SN_KEY_128), iota(0x10, 16));
+    let seq = hex("0102");
+    let encrypted = encrypt_sequence_number(Aead::Aes128Gcm, &key, &seq, &ciphertext).unwrap();
+    assert_eq!(&hex("abb2")[..], &encrypted[..]);
+    let decrypted =
+        encrypt_sequence_number(Aead::Aes128Gcm, &key, &encrypted, &ciphertext).unwrap();
+    assert_eq!(&seq[..], &decrypted[..]);
+
+    let short = encrypt_sequence_number(Aead::Aes128Gcm, &key, &hex("01"), &ciphertext).unwrap();
+    assert_eq!(&hex("ab")[..], &short[..]);
+    assert!(encrypt_sequence_number(Aead::Aes128Gcm, &key, &hex("010203"), &ciphertext).is_err());
+}
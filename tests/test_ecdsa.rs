@@ -0,0 +1,133 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::sha256::Sha256;
+use hacspecs::sha512::Sha384;
+use hacspecs::{p256, p384};
+
+mod common;
+use common::hex;
+
+fn msg(m: &str) -> Bytes {
+    Bytes::from_vec(m.as_bytes().to_vec())
+}
+
+// The P-256 key of RFC 6979, appendix A.2.5.
+static P256_SK: &str = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+static P256_PK: &str = concat!(
+    "04",
+    "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6",
+    "7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299"
+);
+
+struct Rfc6979TestVector<'a> {
+    msg: &'a str,
+    k: &'a str,
+    r: &'a str,
+    s: &'a str,
+}
+
+const P256_SHA256: [Rfc6979TestVector; 2] = [
+    Rfc6979TestVector {
+        msg: "sample",
+        k: "a6e3c57dd01abe90086538398355dd4c3b17aa873382b0f24d6129493d8aad60",
+        r: "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+        s: "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+    },
+    Rfc6979TestVector {
+        msg: "test",
+        k: "d16b6ae827f17175e040871a1c7ec3500192c4c92677336ec2537acaee0008e0",
+        r: "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367",
+        s: "019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083",
+    },
+];
+
+#[test]
+fn test_p256_sha256() {
+    let sk = p256::Scalar::from(P256_SK);
+    assert_eq!(hex(P256_PK), p256::public_key(sk));
+    for kat in P256_SHA256.iter() {
+        let k = p256::rfc6979_nonce::<Sha256>(hex(P256_SK), msg(kat.msg));
+        assert_eq!(hex(kat.k), k);
+
+        let sig = p256::ecdsa_sign_deterministic::<Sha256>(hex(P256_SK), msg(kat.msg)).unwrap();
+        assert_eq!(hex(kat.r), sig.r);
+        assert_eq!(hex(kat.s), sig.s);
+        assert_eq!(sig, p256::ecdsa_sign::<Sha256>(hex(P256_SK), k, msg(kat.msg)).unwrap());
+        assert!(p256::ecdsa_verify::<Sha256>(hex(P256_PK), msg(kat.msg), &sig).is_ok());
+    }
+}
+
+// A hash longer than n is cut to its leftmost 256 bits.
+#[test]
+fn test_p256_sha384() {
+    let sig = p256::ecdsa_sign_deterministic::<Sha384>(hex(P256_SK), msg("sample")).unwrap();
+    let r = hex("0eafea039b20e9b42309fb1d89e213057cbf973dc0cfc8f129edddc800ef7719");
+    let s = hex("4861f0491e6998b9455193e34e7b0d284ddd7149a74b95b9261f13abde940954");
+    assert_eq!(r, sig.r);
+    assert_eq!(s, sig.s);
+    assert!(p256::ecdsa_verify::<Sha384>(hex(P256_PK), msg("sample"), &sig).is_ok());
+}
+
+#[test]
+fn test_p256_verify_rejects() {
+    let sig = p256::ecdsa_sign_deterministic::<Sha256>(hex(P256_SK), msg("sample")).unwrap();
+    let verify =
+        |m: &str, sig: &p256::Signature| p256::ecdsa_verify::<Sha256>(hex(P256_PK), msg(m), sig);
+    assert!(verify("test", &sig).is_err());
+
+    let mut bad_s = sig.clone();
+    bad_s.s[31] ^= 1;
+    assert!(verify("sample", &bad_s).is_err());
+
+    // s and r are swapped, zero, or n.
+    let swapped = p256::Signature { r: sig.s.clone(), s: sig.r.clone() };
+    assert!(verify("sample", &swapped).is_err());
+    let zero = p256::Signature { r: sig.r.clone(), s: Bytes::new_len(32) };
+    assert!(verify("sample", &zero).is_err());
+    let n = p256::Signature {
+        r: hex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"),
+        s: sig.s.clone(),
+    };
+    assert!(verify("sample", &n).is_err());
+
+    // A zero nonce or private key cannot sign.
+    let zero = Bytes::new_len(32);
+    assert!(p256::ecdsa_sign::<Sha256>(hex(P256_SK), zero.clone(), msg("sample")).is_err());
+    assert!(p256::ecdsa_sign::<Sha256>(zero, hex(P256_SK), msg("sample")).is_err());
+}
+
+// RFC 6979, appendix A.2.6.
+#[test]
+fn test_p384_sha384() {
+    let sk = hex(concat!(
+        "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba",
+        "9aa47740787137d896d5724e4c70a825f872c9ea60d2edf5"
+    ));
+    let pk = hex(concat!(
+        "04",
+        "ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e",
+        "06aae5286b300c64def8f0ea9055866064a254515480bc13",
+        "8015d9b72d7d57244ea8ef9ac0c621896708a59367f9dfb9",
+        "f54ca84b3f1c9db1288b231c3ae0d4fe7344fd2533264720"
+    ));
+    let k = hex(concat!(
+        "94ed910d1a099dad3254e9242ae85abde4ba15168eaf0ca8",
+        "7a555fd56d10fbca2907e3e83ba95368623b8c4686915cf9"
+    ));
+    let r = hex(concat!(
+        "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa7",
+        "3d64c4ea95ad133c81a648152e44acf96e36dd1e80fabe46"
+    ));
+    let s = hex(concat!(
+        "99ef4aeb15f178cea1fe40db2603138f130e740a19624526",
+        "203b6351d0a3a94fa329c145786e679e7b82c71a38628ac8"
+    ));
+    assert_eq!(k, p384::rfc6979_nonce::<Sha384>(sk.clone(), msg("sample")));
+    let sig = p384::ecdsa_sign_deterministic::<Sha384>(sk, msg("sample")).unwrap();
+    assert_eq!(r, sig.r);
+    assert_eq!(s, sig.s);
+    assert!(p384::ecdsa_verify::<Sha384>(pk.clone(), msg("sample"), &sig).is_ok());
+    assert!(p384::ecdsa_verify::<Sha384>(pk, msg("test"), &sig).is_err());
+}
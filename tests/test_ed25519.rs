@@ -0,0 +1,101 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::ed25519::*;
+use hacspecs::sha512::sha512;
+
+mod common;
+use common::hex;
+
+struct Ed25519TestVector<'a> {
+    sk: &'a str,
+    pk: &'a str,
+    msg: &'a str,
+    sig: &'a str,
+}
+
+// RFC 8032, section 7.1, tests 1 to 3.
+const KAT: [Ed25519TestVector; 3] = [
+    Ed25519TestVector {
+        sk: "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        pk: "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        msg: "",
+        sig: concat!(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+            "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+        ),
+    },
+    Ed25519TestVector {
+        sk: "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        pk: "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        msg: "72",
+        sig: concat!(
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+            "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+        ),
+    },
+    Ed25519TestVector {
+        sk: "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        pk: "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        msg: "af82",
+        sig: concat!(
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac",
+            "18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"
+        ),
+    },
+];
+
+#[test]
+fn test_kat() {
+    for kat in KAT.iter() {
+        let sk = SecretKey::from(kat.sk);
+        let pk = PublicKey::from(kat.pk);
+        let sig = Signature::from(kat.sig);
+        assert_eq!(pk, public_key(sk));
+        assert_eq!(sig, sign(sk, hex(kat.msg)));
+        assert!(verify(pk, hex(kat.msg), sig).is_ok());
+    }
+}
+
+// RFC 8032, section 7.1, test SHA(abc): the message is the SHA-512 of "abc".
+#[test]
+fn test_sha_abc() {
+    let sk = SecretKey::from("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42");
+    let pk = PublicKey::from("ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf");
+    let sig = Signature::from(concat!(
+        "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b589",
+        "09351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704"
+    ));
+    let msg = Bytes::from(&sha512(Bytes::from_vec(b"abc".to_vec()))[..]);
+    assert_eq!(pk, public_key(sk));
+    assert_eq!(sig, sign(sk, msg.clone()));
+    assert!(verify(pk, msg, sig).is_ok());
+}
+
+#[test]
+fn test_verify_rejects() {
+    let pk = PublicKey::from(KAT[0].pk);
+    let sig = Signature::from(KAT[0].sig);
+    assert!(verify(pk, hex("00"), sig).is_err());
+    assert!(verify(PublicKey::from(KAT[1].pk), hex(""), sig).is_err());
+
+    let mut bad_r = sig;
+    bad_r[0] ^= 1;
+    assert!(verify(pk, hex(""), bad_r).is_err());
+
+    // S + L instead of S.
+    let mut big_s = sig;
+    big_s.update_raw(
+        32,
+        &hex("4c8c7872aa064e049dbb3013fbf29380d25bf5f0595bbe24655141438e7a101b")[..],
+    );
+    assert!(verify(pk, hex(""), big_s).is_err());
+
+    // y = p is out of range, and y = 2 has no x on the curve.
+    let y_p = PublicKey::from("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+    assert!(verify(y_p, hex(""), sig).is_err());
+    let mut y_2 = PublicKey::new();
+    y_2[0] = 2;
+    assert!(verify(y_2, hex(""), sig).is_err());
+}
@@ -0,0 +1,134 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::exporter::*;
+use hacspecs::sha256::Sha256;
+
+mod common;
+use common::hex;
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+fn context() -> Bytes {
+    Bytes::from_vec(b"context".to_vec())
+}
+
+// Computed with Python's hmac and hashlib, for a master secret 0x00, 0x01,
+// ..., and randoms 0x40, ... and 0x60, ...
+const TLS12_NO_CONTEXT: &str = "bbf6621755bb7e523da70fdac8ef24cb75c70cc89896aa017cbf480351dff8ba";
+const TLS12_CONTEXT: &str = "80d9a6205b8eed8a81e8f6925324c36c4897cd061fa1a58a7e1417508f3e06b7";
+const TLS12_EMPTY_CONTEXT: &str =
+    "c46f91fe7efaa129cc69fedcb3f36df3541523e6a4936d0e4a3e33335f2f78ae";
+// The 60 bytes of DTLS-SRTP keys and salts for AES-128 and TLS 1.0.
+const LEGACY_SRTP: &str = concat!(
+    "1ad19a938038d5fc983a2891cbeeba79984bab6a7635365b1fc77d11206d3ac6901ee537a65c595e748fefc0",
+    "e22aeaf28e2fbc5abb884b12a59a96ae",
+);
+// The DTLS-SRTP keys and salts for AES-128 and TLS 1.2 with SHA-256, and
+// for AEAD_AES_256_GCM.
+const DTLS12_SRTP: &str = concat!(
+    "c081c2606a3d2df1b5adf38b4cc38b672e87dcccff567b9239d0ca51295d9f256b3505f12f9c4102cabc1993",
+    "64181bfcf8de247189be5811700367ce",
+);
+const DTLS12_SRTP_GCM256: &str = concat!(
+    "c081c2606a3d2df1b5adf38b4cc38b672e87dcccff567b9239d0ca51295d9f256b3505f12f9c4102cabc1993",
+    "64181bfcf8de247189be5811700367ceeb15127d771d4bcbb4ad4c488efa1dae5d2b7f63832d7ed9b0517e24",
+);
+// From the same tools, for an exporter master secret 0x80, 0x81, ...
+const TLS13_NO_CONTEXT: &str = "349d216952ec20bc041cb6b3675a020706af7eb028fe51c7ba7396f9b62861dc";
+const TLS13_CONTEXT: &str = "27358ed20c16cc22a0dee042bcc59d715e24ab88ac1cb17e9824cd77a5061113";
+// The DTLS-SRTP keys and salts for AEAD_AES_128_GCM.
+const DTLS13_SRTP: &str = concat!(
+    "0e9bd04766b3eb2b1b5d560e836a5afd6df8497675f88e0e06ca0f12169ffedb0aa5a4809012ad744ff6a04c",
+    "bb24a4a3d6c159a878847245",
+);
+
+const CHANNEL_BINDING: &str = "EXPORTER-Channel-Binding";
+
+#[test]
+fn test_tls12_exporter() {
+    let export = |context| {
+        export_keying_material::<Sha256>(
+            iota(0, 48),
+            CHANNEL_BINDING,
+            iota(0x40, 32),
+            iota(0x60, 32),
+            context,
+            32,
+        )
+        .unwrap()
+    };
+    assert_eq!(&hex(TLS12_NO_CONTEXT)[..], &export(None)[..]);
+    assert_eq!(&hex(TLS12_CONTEXT)[..], &export(Some(context()))[..]);
+    assert_eq!(&hex(TLS12_EMPTY_CONTEXT)[..], &export(Some(Bytes::new_len(0)))[..]);
+}
+
+#[test]
+fn test_legacy_exporter() {
+    let keys = export_keying_material_legacy(
+        iota(0, 48),
+        DTLS_SRTP_LABEL,
+        iota(0x40, 32),
+        iota(0x60, 32),
+        None,
+        60,
+    )
+    .unwrap();
+    assert_eq!(&hex(LEGACY_SRTP)[..], &keys[..]);
+}
+
+#[test]
+fn test_exporter_labels() {
+    let export = |label, context| {
+        let (ms, client, server) = (iota(0, 48), iota(0x40, 32), iota(0x60, 32));
+        export_keying_material::<Sha256>(ms, label, client, server, context, 32)
+    };
+    for label in &["client finished", "master secret", "key expansion"] {
+        assert_eq!(export(label, None), Err("Reserved exporter label".to_string()));
+    }
+    assert!(export(CHANNEL_BINDING, Some(Bytes::new_len(0xffff))).is_ok());
+    assert!(export(CHANNEL_BINDING, Some(Bytes::new_len(0x10000))).is_err());
+}
+
+#[test]
+fn test_tls13_exporter() {
+    let export =
+        |context, len| tls13_exporter::<Sha256>(iota(0x80, 32), CHANNEL_BINDING, context, len);
+    let keys = export(Bytes::new_len(0), 32).unwrap();
+    assert_eq!(&hex(TLS13_NO_CONTEXT)[..], &keys[..]);
+    let keys = export(context(), 32).unwrap();
+    assert_eq!(&hex(TLS13_CONTEXT)[..], &keys[..]);
+    // The length is part of the HKDF label, so a shorter key is not a prefix.
+    let short = export(context(), 16).unwrap();
+    assert_ne!(&short[..], &keys[0..16]);
+}
+
+#[test]
+fn test_srtp_keys() {
+    let legacy = hex(LEGACY_SRTP);
+    let keys = srtp_keys(&legacy, 16, 14).unwrap();
+    assert_eq!(&keys.client_key[..], &legacy[0..16]);
+    assert_eq!(&keys.server_key[..], &legacy[16..32]);
+    assert_eq!(&keys.client_salt[..], &legacy[32..46]);
+    assert_eq!(&keys.server_salt[..], &legacy[46..60]);
+    assert!(srtp_keys(&legacy, 16, 12).is_err());
+}
+
+#[test]
+fn test_dtls_srtp_keys() {
+    let export = |key_len, salt_len| {
+        let (ms, client, server) = (iota(0, 48), iota(0x40, 32), iota(0x60, 32));
+        dtls_srtp_keys::<Sha256>(ms, client, server, key_len, salt_len).unwrap()
+    };
+    assert_eq!(export(16, 14), srtp_keys(&hex(DTLS12_SRTP), 16, 14).unwrap());
+    // The PRF's output has no length in it, so the AES-128 material is a
+    // prefix of the AES-256 one, split differently.
+    assert_eq!(export(32, 12), srtp_keys(&hex(DTLS12_SRTP_GCM256), 32, 12).unwrap());
+    assert_eq!(&export(32, 12).client_key[0..16], &export(16, 14).client_key[..]);
+
+    let keys = dtls13_srtp_keys::<Sha256>(iota(0x80, 32), 16, 12).unwrap();
+    assert_eq!(keys, srtp_keys(&hex(DTLS13_SRTP), 16, 12).unwrap());
+}
@@ -0,0 +1,99 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::ffdhe::*;
+
+mod common;
+use common::hex;
+
+// An exchange in ffdhe2048, computed with Python's pow.
+static SK_A: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+static SK_B: &str = "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+static PK_A: &str = concat!(
+    "50c8f3181c9e5f6f3e2c57a62384f6a17ac5dbf0cd0e91b99833f8bd7d912be6",
+    "0a5fb9951672598579b9368706106ac949903c45964ed7c328677e3c824d3ab7",
+    "fc30002bd98b7047e4ab2f035817faabdcad61ac8473e9bbd2af6517492852b7",
+    "ca103cea653835faa91d8b9a6de5c22f997c697d66de057723025a0aa4a490f3",
+    "dd2896a9fa82cfa302d900a11a050b68d1692833aedc123534560a984dc72c67",
+    "7f3af9a92bf1c6555f33abb5581af6bbd1cd80575b4662a4cb0cd3dc9f1a9600",
+    "4db7b12fd1788880cd7f2174d9639ffafa83d41c37ce428d8939fdaab5c40ab3",
+    "e6450b7fbd359e95bb0a90f2db7e17f5b4a7de32721e2213660150dc271a3214",
+);
+static PK_B: &str = concat!(
+    "91a7d0938c593391c7ed47f8dc421d1895768d481a6b3cc96c6b2c989d7817be",
+    "77921559945777020e69c87322c6508e836ba2a488c7e444cb0a902f0a400461",
+    "f86389b92ff8f3a935da7726e7c56ebf2644781930228a519a5a25fc81b722fb",
+    "224e49d1d6d40d548314d8bd967e1a1c56c2862804f0e7a7e54f585371999323",
+    "263cfef16ee8e8ac2a9812f832d153dbb98c05ec410e6a46ca2b6793d0bbdb58",
+    "90360cfc434a10d62d5a27e5106d1de0e455bf6be9450a1a509d33171b76c955",
+    "42679668f53cf0ac4c2178ce4b5b417b9fffaafb0fa3aeeeb77d619e5adffaf4",
+    "d878c4b1ab27a673f9fa956eff93e45839edc2de3fdc6105caaba1dce6be7a23",
+);
+static Z: &str = concat!(
+    "16ef1dd979be6f93373b889fe1df9f04449d01efd2b3f03ce18bd9c5930b68ad",
+    "408c0b07c376b3de7eccfd7af5c8a379647eb7aa04bf0276d6745195779acb25",
+    "e3aacbf3ca8e977fa7161d1d47fdb65c263a07a711bd6652721cf34d0930e2f7",
+    "3c215bc5aa05581ecbcd593650c6134ac6035a0ff6f9422e10b8f5217a6b5ecc",
+    "2c56398949a7a77e35ec7d40635bcd1878fd3a4b05e4021433ff2cc2d5f9b697",
+    "32528552f2ecc5ba998bfff050ca8d99cad7e83f75395e37c6a72f475cae33f1",
+    "af2e3f6bab606c39990eefed59b16f3b3c8752d8c3437e5a4bc5fedc0596e8f3",
+    "e8a8d32a7efbff267854ffdb4c9433a09daaddb1b27c4d264599aac800899c6b",
+);
+
+#[test]
+fn test_groups() {
+    let groups = [
+        (NamedGroup::Ffdhe2048, 0x0100, 256),
+        (NamedGroup::Ffdhe3072, 0x0101, 384),
+        (NamedGroup::Ffdhe4096, 0x0102, 512),
+    ];
+    for &(group, code_point, len) in groups.iter() {
+        let params = group.group();
+        assert_eq!(code_point, group.code_point());
+        assert_eq!(len, params.p.len());
+        // p = 2^b - 2^(b-64) + ... - 1 has 64 one bits at either end.
+        assert_eq!(&[0xff; 8], &params.p[0..8]);
+        assert_eq!(&[0xff; 8], &params.p[len - 8..len]);
+        assert_eq!(0x7f, params.q.as_ref().unwrap()[0]);
+        assert_eq!(Some(group), named_group(&params.p, &params.g));
+    }
+    let ffdhe2048 = NamedGroup::Ffdhe2048.group();
+    assert_eq!(None, named_group(&ffdhe2048.p, &hex("05")));
+    assert_eq!(hex("adf85458a2bb4a9a"), Bytes::from(&ffdhe2048.p[8..16]));
+}
+
+#[test]
+fn test_exchange() {
+    let group = NamedGroup::Ffdhe2048.group();
+    assert_eq!(hex(PK_A), public_key(&group, &hex(SK_A)).unwrap());
+    assert_eq!(hex(PK_B), public_key(&group, &hex(SK_B)).unwrap());
+    assert_eq!(hex(Z), shared_secret(&group, &hex(SK_A), &hex(PK_B)).unwrap());
+    assert_eq!(hex(Z), shared_secret(&group, &hex(SK_B), &hex(PK_A)).unwrap());
+    assert!(public_key(&group, &hex("00")).is_err());
+}
+
+#[test]
+fn test_validate_public_key() {
+    let group = NamedGroup::Ffdhe2048.group();
+    let mut p_minus_1 = group.p.clone();
+    p_minus_1[255] -= 1;
+    for y in &[hex("00"), hex("01"), p_minus_1.clone(), group.p.clone()] {
+        assert!(validate_public_key(&group, y).is_err());
+        assert!(shared_secret(&group, &hex(SK_A), y).is_err());
+    }
+    assert!(validate_public_key(&group, &hex("02")).is_ok());
+
+    // 7 is not a square mod p, so it is outside the subgroup of order q. Without
+    // q only the range is checked.
+    assert!(validate_public_key(&group, &hex("07")).is_err());
+    let custom = DhGroup { p: group.p.clone(), g: group.g.clone(), q: None };
+    assert!(validate_public_key(&custom, &hex("07")).is_ok());
+    assert!(validate_public_key(&custom, &p_minus_1).is_err());
+}
+
+#[test]
+fn test_premaster_secret() {
+    assert_eq!(hex("0102"), premaster_secret(&hex("00000102")));
+    assert_eq!(hex("0100"), premaster_secret(&hex("0100")));
+}
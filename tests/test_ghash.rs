@@ -0,0 +1,48 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::ghash::*;
+
+mod common;
+use common::hex;
+
+fn block(s: &str) -> Block {
+    Block::from(&hex(s)[..])
+}
+
+// The GHASH values of test cases 2 and 4 of the GCM specification: H, the
+// padded AAD and cipher text with their lengths, and the result.
+#[test]
+fn test_ghash() {
+    let h = block("66e94bd4ef8a2c3b884cfa59ca342b2e");
+    let x = hex("0388dace60b6a392f328c2b971b2fe7800000000000000000000000000000080");
+    assert_eq!(block("f38cbb1ad69223dcc3457ae5b6b0f885"), ghash(h, x));
+
+    let h = block("b83b533708bf535d0aa6e52980d53b78");
+    let x = hex(concat!(
+        "feedfacedeadbeeffeedfacedeadbeefabaddad2000000000000000000000000",
+        "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e",
+        "21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e09100000000",
+        "00000000000000a000000000000001e0"
+    ));
+    assert_eq!(block("698e57f70e6ecc7fd9463b7260a9ae5f"), ghash(h, x));
+
+    assert_eq!(Block::new(), ghash(h, Bytes::new_len(0)));
+}
+
+// The unit of the field is x_0 = 1, the block 80 00 .. 00.
+#[test]
+fn test_gf_mul() {
+    let one = block("80000000000000000000000000000000");
+    let h = block("66e94bd4ef8a2c3b884cfa59ca342b2e");
+    let y = block("0388dace60b6a392f328c2b971b2fe78");
+    assert_eq!(h, gf_mul(one, h));
+    assert_eq!(h, gf_mul(h, one));
+    assert_eq!(Block::new(), gf_mul(Block::new(), h));
+    assert_eq!(gf_mul(h, y), gf_mul(y, h));
+    // x^127 . x = x^128 = 1 + x + x^2 + x^7, which is R.
+    let x127 = block("00000000000000000000000000000001");
+    let x = block("40000000000000000000000000000000");
+    assert_eq!(block("e1000000000000000000000000000000"), gf_mul(x127, x));
+}
@@ -0,0 +1,165 @@
+extern crate hacspec;
+
+extern crate hacspecs;
+use hacspecs::blake2b::Blake2b;
+use hacspecs::hmac::*;
+use hacspecs::sha256::Sha256;
+use hacspecs::sha512::{Sha384, Sha512};
+
+mod common;
+use common::hex;
+
+struct HmacTestVector<'a> {
+    key: &'a str,
+    data: &'a str,
+    blake2b: &'a str,
+    sha256: &'a str,
+    sha384: &'a str,
+    sha512: &'a str,
+}
+
+// The test cases of RFC 4231 but for the truncated case 5. There are no
+// published HMAC-BLAKE2b vectors; those were computed with Python's hmac
+// and hashlib.
+const KAT: [HmacTestVector; 6] = [
+    HmacTestVector {
+        key: "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+        data: "4869205468657265",
+        blake2b: concat!(
+            "358a6a184924894fc34bee5680eedf57d84a37bb38832f288e3b27dc63a98cc8c91e76da476b508b",
+            "c6b2d408a248857452906e4a20b48c6b4b55d2df0fe1dd24",
+        ),
+        sha256: "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        sha384: concat!(
+            "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f",
+            "4af152e8b2fa9cb6",
+        ),
+        sha512: concat!(
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702",
+            "038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+        ),
+    },
+    HmacTestVector {
+        key: "4a656665",
+        data: "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+        blake2b: concat!(
+            "6ff884f8ddc2a6586b3c98a4cd6ebdf14ec10204b6710073eb5865ade37a2643b8807c1335d107ec",
+            "db9ffeaeb6828c4625ba172c66379efcd222c2de11727ab4",
+        ),
+        sha256: "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        sha384: concat!(
+            "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c7",
+            "8b3239ecfab21649",
+        ),
+        sha512: concat!(
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a",
+            "6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+        ),
+    },
+    HmacTestVector {
+        key: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        data: concat!(
+            "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd",
+            "dddddddddddddddddddd",
+        ),
+        blake2b: concat!(
+            "f43bc62c7a99353c3b2c60e8ef24fbbd42e9547866dc9c5be4edc6f4a7d4bc0ac620c2c60034d040",
+            "f0dbaf86f9e9cd7891a095595eed55e2a996215f0c15c018",
+        ),
+        sha256: "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+        sha384: concat!(
+            "88062608d3e6ad8a0aa2ace014c8a86f0aa635d947ac9febe83ef4e55966144b2a5ab39dc13814b9",
+            "4e3ab6e101a34f27",
+        ),
+        sha512: concat!(
+            "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39bf3e848279a722c8",
+            "06b485a47e67c807b946a337bee8942674278859e13292fb",
+        ),
+    },
+    HmacTestVector {
+        key: "0102030405060708090a0b0c0d0e0f10111213141516171819",
+        data: concat!(
+            "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+            "cdcdcdcdcdcdcdcdcdcd",
+        ),
+        blake2b: concat!(
+            "e5dbb6de2fee42a1caa06e4e7b84ce408ffa5c4a9de2632eca769cde8875014c72d0720feaf53f76",
+            "e6a180357f528d7bf484fa3a14e8cc1f0f3bada717b43491",
+        ),
+        sha256: "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+        sha384: concat!(
+            "3e8a69b7783c25851933ab6290af6ca77a9981480850009cc5577c6e1f573b4e6801dd23c4a7d679",
+            "ccf8a386c674cffb",
+        ),
+        sha512: concat!(
+            "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3dba91ca5c11aa25eb4",
+            "d679275cc5788063a5f19741120c4f2de2adebeb10a298dd",
+        ),
+    },
+    HmacTestVector {
+        key: concat!(
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaa",
+        ),
+        data: concat!(
+            "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a65204b6579202d20",
+            "48617368204b6579204669727374",
+        ),
+        blake2b: concat!(
+            "a54b2943b2a20227d41ca46c0945af09bc1faefb2f49894c23aebc557fb79c4889dca74408dc8650",
+            "86667aedee4a3185c53a49c80b814c4c5813ea0c8b38a8f8",
+        ),
+        sha256: "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        sha384: concat!(
+            "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c60c2ef6ab4030fe82",
+            "96248df163f44952",
+        ),
+        sha512: concat!(
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598",
+            "bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+        ),
+    },
+    HmacTestVector {
+        key: concat!(
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaa",
+        ),
+        data: concat!(
+            "5468697320697320612074657374207573696e672061206c6172676572207468616e20626c6f636b",
+            "2d73697a65206b657920616e642061206c6172676572207468616e20626c6f636b2d73697a652064",
+            "6174612e20546865206b6579206e6565647320746f20626520686173686564206265666f72652062",
+            "65696e6720757365642062792074686520484d414320616c676f726974686d2e",
+        ),
+        blake2b: concat!(
+            "ab347980a64b5e825dd10e7d32fd43a01a8e6dea267ab9ad7d913524526618925311afbcb0c49519",
+            "cbebdd709540a8d725fb911ac2aee9b2a3aa43d796123393",
+        ),
+        sha256: "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+        sha384: concat!(
+            "6617178e941f020d351e2f254e8fd32c602420feb0b8fb9adccebb82461e99c5a678cc31e799176d",
+            "3860e6110c46523e",
+        ),
+        sha512: concat!(
+            "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944b6022cac3c4982b1",
+            "0d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58",
+        ),
+    },
+];
+
+#[test]
+fn kat_test() {
+    for kat in KAT.iter() {
+        let mac = hmac::<Blake2b>(hex(kat.key), hex(kat.data));
+        assert_eq!(&hex(kat.blake2b)[..], &mac[..]);
+        let mac = hmac::<Sha256>(hex(kat.key), hex(kat.data));
+        assert_eq!(&hex(kat.sha256)[..], &mac[..]);
+        let mac = hmac::<Sha384>(hex(kat.key), hex(kat.data));
+        assert_eq!(&hex(kat.sha384)[..], &mac[..]);
+        let mac = hmac::<Sha512>(hex(kat.key), hex(kat.data));
+        assert_eq!(&hex(kat.sha512)[..], &mac[..]);
+    }
+}
@@ -4,6 +4,9 @@ use hacspec::*;
 extern crate hacspecs;
 use hacspecs::p256::*;
 
+mod common;
+use common::hex;
+
 #[test]
 fn test_gmac() {
     let sk = Scalar::from("14");
@@ -33,3 +36,51 @@ fn test_gmac() {
     let point_computed = point_mul(sk);
     assert_eq!(point_computed, point_expected);
 }
+
+// The first P-256 case of the NIST CAVS ECC CDH primitive tests.
+static PEER: &str = concat!(
+    "04",
+    "700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287",
+    "db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac"
+);
+static SK: &str = "7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534";
+static PK: &str = concat!(
+    "04",
+    "ead218590119e8876b29146ff89ca61770c4edbbf97d38ce385ed281d8a6b230",
+    "28af61281fd35e2fa7002523acc85a429cb06ee6648325389f59edfce1405141"
+);
+static SHARED: &str = "46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b";
+
+#[test]
+fn test_ecdh() {
+    let sk = Scalar::from(SK);
+    assert_eq!(hex(PK), public_key(sk));
+    assert_eq!(hex(SHARED), ecdh(sk, hex(PEER)).unwrap());
+}
+
+#[test]
+fn test_decode_point() {
+    let peer = decode_point(hex(PEER)).unwrap();
+    assert!(is_on_curve(peer));
+    assert_eq!(hex(PEER), encode_point(peer));
+
+    // A changed y is off the curve.
+    let mut off_curve = hex(PEER);
+    off_curve[64] ^= 1;
+    assert!(decode_point(off_curve.clone()).is_err());
+    assert!(ecdh(Scalar::from(SK), off_curve).is_err());
+
+    // Compressed points and wrong lengths are refused.
+    let mut compressed = hex(PEER);
+    compressed[0] = 0x02;
+    assert!(decode_point(compressed).is_err());
+    assert!(decode_point(Bytes::from(&hex(PEER)[0..64])).is_err());
+
+    // So is a coordinate of p or more.
+    let big_x = hex(concat!(
+        "04",
+        "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+        "db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac"
+    ));
+    assert!(decode_point(big_x).is_err());
+}
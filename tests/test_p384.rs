@@ -0,0 +1,75 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::p384::*;
+
+mod common;
+use common::hex;
+
+#[test]
+fn test_point_mul() {
+    let point_expected = Affine(
+        FieldElement::from(concat!(
+            "AA87CA22BE8B05378EB1C71EF320AD746E1D3B628BA79B98",
+            "59F741E082542A385502F25DBF55296C3A545E3872760AB7"
+        )),
+        FieldElement::from(concat!(
+            "3617DE4A96262C6F5D9E98BF9292DC29F8F41DBD289A147C",
+            "E9DA3113B5F0B8C00A60B1CE1D7E819D7A431D7C90EA0E5F"
+        )),
+    );
+    let point_computed = point_mul(Scalar::from("1"));
+    assert_eq!(point_computed, point_expected);
+    assert!(is_on_curve(point_computed));
+
+    // 20 * G, computed with Python's `cryptography` package.
+    let point_expected = Affine(
+        FieldElement::from(concat!(
+            "605508EC02C534BCEEE9484C86086D2139849E2B11C1A9CA",
+            "1E2808DEC2EAF161AC8A105D70D4F85C50599BE5800A623F"
+        )),
+        FieldElement::from(concat!(
+            "5158EE87962AC6B81F00A103B8543A07381B7639A3A65F13",
+            "53AEF11B733106DDE92E99B78DE367B48E238C38DAD8EEDD"
+        )),
+    );
+    let point_computed = point_mul(Scalar::from("14"));
+    assert_eq!(point_computed, point_expected);
+}
+
+// The first P-384 case of the NIST CAVS ECC CDH primitive tests.
+static PEER: &str = concat!(
+    "04",
+    "a7c76b970c3b5fe8b05d2838ae04ab47697b9eaf52e764592efda27fe7513272",
+    "734466b400091adbf2d68c58e0c50066",
+    "ac68f19f2e1cb879aed43a9969b91a0839c4c38a49749b661efedf243451915e",
+    "d0905a32b060992b468c64766fc8437a"
+);
+static SK: &str = concat!(
+    "3cc3122a68f0d95027ad38c067916ba0eb8c38894d22e1b1",
+    "5618b6818a661774ad463b205da88cf699ab4d43c9cf98a1"
+);
+static PK: &str = concat!(
+    "04",
+    "9803807f2f6d2fd966cdd0290bd410c0190352fbec7ff6247de1302df86f25d3",
+    "4fe4a97bef60cff548355c015dbb3e5f",
+    "ba26ca69ec2f5b5d9dad20cc9da711383a9dbe34ea3fa5a2af75b46502629ad5",
+    "4dd8b7d73a8abb06a3a3be47d650cc99"
+);
+static SHARED: &str = concat!(
+    "5f9d29dc5e31a163060356213669c8ce132e22f57c9a04f4",
+    "0ba7fcead493b457e5621e766c40a2e3d4d6a04b25e533f1"
+);
+
+#[test]
+fn test_ecdh() {
+    let sk = Scalar::from(SK);
+    assert_eq!(hex(PK), public_key(sk));
+    assert_eq!(hex(SHARED), ecdh(sk, hex(PEER)).unwrap());
+
+    let mut off_curve = hex(PEER);
+    off_curve[96] ^= 1;
+    assert!(ecdh(sk, off_curve).is_err());
+    assert!(decode_point(Bytes::from(&hex(PEER)[0..65])).is_err());
+}
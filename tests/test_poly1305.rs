@@ -4,6 +4,9 @@ use hacspec::*;
 extern crate hacspecs;
 use hacspecs::{chacha20::*, poly1305::*};
 
+mod common;
+use common::hex;
+
 #[test]
 fn basic_test() {
     let key = Key::random();
@@ -29,3 +32,35 @@ fn basic_test() {
     let computed = poly(msg, k);
     assert_eq!(expected, computed);
 }
+
+// The r of RFC 8439, section 2.5.2, before and after clamping.
+#[test]
+fn test_clamp() {
+    let mut r = [0u8; 16];
+    r.copy_from_slice(hex("85d6be7857556d337f4452fe42d506a8").raw());
+    let clamped = clamp(r);
+    assert_eq!(hex("85d6be0854556d037c44520e40d50608").raw(), &clamped[..]);
+    let all_ones = clamp([0xff; 16]);
+    assert_eq!(hex("ffffff0ffcffff0ffcffff0ffcffff0f").raw(), &all_ones[..]);
+}
+
+// RFC 8439, section 2.6.2.
+#[test]
+fn test_key_gen() {
+    let key = hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+    let key = Key::from(&key[..]);
+    let iv = IV::from(&hex("000000000001020304050607")[..]);
+    let expected = hex("8ad5a08b905f81cc815040274ab29471a833b637e3fd0da508dbb8e2fdd1a646");
+    assert_eq!(expected.raw(), &key_gen(key, iv)[..]);
+}
+
+// A key for which a + s is at least 2^130 - 5, so that the tag differs if s
+// is added in the field; computed with Python's `cryptography` package.
+#[test]
+fn test_tag_sum_not_reduced() {
+    let key = hex("c0b796aee179491cae3a58f9ae3e0bf56bc459cb74337faba87decf1bdfc63dd");
+    let key = Key::from(&key[..]);
+    let msg = hex("e1cc3df988404c06c0d4370d265deac1934f4e36");
+    let expected = Tag::from(&hex("918b6da58021c403d0b05fd39d32f33f")[..]);
+    assert_eq!(expected, poly(msg, key));
+}
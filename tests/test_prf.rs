@@ -0,0 +1,168 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::blake2b::Blake2b;
+use hacspecs::hmac::hmac;
+use hacspecs::prf::*;
+use hacspecs::sha256::Sha256;
+use hacspecs::sha512::Sha384;
+
+// The TLS 1.2 PRF vectors posted to the TLS working group list, with
+// "test label" as the label.
+static SECRET_SHA256: [u8; 16] = [
+    0x9b, 0xbe, 0x43, 0x6b, 0xa9, 0x40, 0xf0, 0x17, 0xb1, 0x76, 0x52, 0x84, 0x9a, 0x71, 0xdb, 0x35,
+];
+static SEED_SHA256: [u8; 16] = [
+    0xa0, 0xba, 0x9f, 0x93, 0x6c, 0xda, 0x31, 0x18, 0x27, 0xa6, 0xf7, 0x96, 0xff, 0xd5, 0x19, 0x8c,
+];
+static EXPECTED_SHA256: [u8; 100] = [
+    0xe3, 0xf2, 0x29, 0xba, 0x72, 0x7b, 0xe1, 0x7b, 0x8d, 0x12, 0x26, 0x20, 0x55, 0x7c, 0xd4, 0x53,
+    0xc2, 0xaa, 0xb2, 0x1d, 0x07, 0xc3, 0xd4, 0x95, 0x32, 0x9b, 0x52, 0xd4, 0xe6, 0x1e, 0xdb, 0x5a,
+    0x6b, 0x30, 0x17, 0x91, 0xe9, 0x0d, 0x35, 0xc9, 0xc9, 0xa4, 0x6b, 0x4e, 0x14, 0xba, 0xf9, 0xaf,
+    0x0f, 0xa0, 0x22, 0xf7, 0x07, 0x7d, 0xef, 0x17, 0xab, 0xfd, 0x37, 0x97, 0xc0, 0x56, 0x4b, 0xab,
+    0x4f, 0xbc, 0x91, 0x66, 0x6e, 0x9d, 0xef, 0x9b, 0x97, 0xfc, 0xe3, 0x4f, 0x79, 0x67, 0x89, 0xba,
+    0xa4, 0x80, 0x82, 0xd1, 0x22, 0xee, 0x42, 0xc5, 0xa7, 0x2e, 0x5a, 0x51, 0x10, 0xff, 0xf7, 0x01,
+    0x87, 0x34, 0x7b, 0x66,
+];
+
+static SECRET_SHA384: [u8; 16] = [
+    0xb8, 0x0b, 0x73, 0x3d, 0x6c, 0xee, 0xfc, 0xdc, 0x71, 0x56, 0x6e, 0xa4, 0x8e, 0x55, 0x67, 0xdf,
+];
+static SEED_SHA384: [u8; 16] = [
+    0xcd, 0x66, 0x5c, 0xf6, 0xa8, 0x44, 0x7d, 0xd6, 0xff, 0x8b, 0x27, 0x55, 0x5e, 0xdb, 0x74, 0x65,
+];
+static EXPECTED_SHA384: [u8; 148] = [
+    0x7b, 0x0c, 0x18, 0xe9, 0xce, 0xd4, 0x10, 0xed, 0x18, 0x04, 0xf2, 0xcf, 0xa3, 0x4a, 0x33, 0x6a,
+    0x1c, 0x14, 0xdf, 0xfb, 0x49, 0x00, 0xbb, 0x5f, 0xd7, 0x94, 0x21, 0x07, 0xe8, 0x1c, 0x83, 0xcd,
+    0xe9, 0xca, 0x0f, 0xaa, 0x60, 0xbe, 0x9f, 0xe3, 0x4f, 0x82, 0xb1, 0x23, 0x3c, 0x91, 0x46, 0xa0,
+    0xe5, 0x34, 0xcb, 0x40, 0x0f, 0xed, 0x27, 0x00, 0x88, 0x4f, 0x9d, 0xc2, 0x36, 0xf8, 0x0e, 0xdd,
+    0x8b, 0xfa, 0x96, 0x11, 0x44, 0xc9, 0xe8, 0xd7, 0x92, 0xec, 0xa7, 0x22, 0xa7, 0xb3, 0x2f, 0xc3,
+    0xd4, 0x16, 0xd4, 0x73, 0xeb, 0xc2, 0xc5, 0xfd, 0x4a, 0xbf, 0xda, 0xd0, 0x5d, 0x91, 0x84, 0x25,
+    0x9b, 0x5b, 0xf8, 0xcd, 0x4d, 0x90, 0xfa, 0x0d, 0x31, 0xe2, 0xde, 0xc4, 0x79, 0xe4, 0xf1, 0xa2,
+    0x60, 0x66, 0xf2, 0xee, 0xa9, 0xa6, 0x92, 0x36, 0xa3, 0xe5, 0x26, 0x55, 0xc9, 0xe9, 0xae, 0xe6,
+    0x91, 0xc8, 0xf3, 0xa2, 0x68, 0x54, 0x30, 0x8d, 0x5e, 0xaa, 0x3b, 0xe8, 0x5e, 0x09, 0x90, 0x70,
+    0x3d, 0x73, 0xe5, 0x6f,
+];
+
+// There are no published vectors for the TLS 1.0 PRF; this one was computed
+// with Python's hmac and hashlib, for a 47 byte secret 0x40, 0x41, ... and
+// a 32 byte seed 0xa0, 0xa1, ...
+static EXPECTED_LEGACY: [u8; 104] = [
+    0xec, 0x6b, 0x4c, 0x71, 0x31, 0x19, 0xc6, 0x29, 0x73, 0xfc, 0x13, 0x2a, 0x30, 0x50, 0x89, 0x28,
+    0xcb, 0xc8, 0x1e, 0x6d, 0xe0, 0xdb, 0x5e, 0x10, 0x6d, 0xa0, 0x1c, 0x96, 0xb7, 0xab, 0x92, 0x1c,
+    0xa1, 0x09, 0x4d, 0x37, 0x81, 0x64, 0xa3, 0x52, 0x05, 0xa3, 0xa3, 0xfe, 0x12, 0x9d, 0x50, 0xc8,
+    0x10, 0x07, 0x70, 0x6e, 0x4c, 0x38, 0xf2, 0x11, 0x14, 0xaa, 0xde, 0xa7, 0x4d, 0x48, 0x72, 0xbf,
+    0x3e, 0x22, 0x38, 0x66, 0xf7, 0x27, 0x4a, 0x65, 0x85, 0xdb, 0x9a, 0x42, 0x4b, 0x2c, 0x01, 0xea,
+    0x23, 0xec, 0x84, 0x43, 0x5b, 0x76, 0x84, 0xb8, 0xa6, 0x8a, 0xdf, 0x36, 0xaf, 0x0c, 0x8d, 0xe7,
+    0x45, 0x75, 0x79, 0x13, 0x69, 0xb7, 0xe9, 0x09,
+];
+
+// Master secrets computed with Python's hmac and hashlib, for a premaster
+// secret 0x00, 0x01, ..., randoms 0x40, ... and 0x60, ..., and a session
+// hash 0x80, ... as long as the hash.
+static EXPECTED_MASTER_SECRET: [u8; 48] = [
+    0x33, 0xf1, 0x97, 0x13, 0x02, 0x9a, 0x32, 0x51, 0x81, 0x29, 0xac, 0xfb, 0xd2, 0x62, 0x3a, 0xd9,
+    0xb9, 0xe3, 0xbf, 0xe7, 0x95, 0xf6, 0x0d, 0xbc, 0x02, 0x28, 0xa7, 0xbc, 0x41, 0x42, 0xa4, 0x53,
+    0x70, 0xfa, 0x02, 0xeb, 0xdf, 0xec, 0xbc, 0x1f, 0x5a, 0xc0, 0xd2, 0x66, 0xbe, 0xcf, 0xb5, 0x9a,
+];
+static EXPECTED_EXTENDED_SHA256: [u8; 48] = [
+    0x42, 0x35, 0x80, 0x45, 0x75, 0xeb, 0x9f, 0x5b, 0x15, 0x15, 0x31, 0x60, 0xb1, 0x7c, 0x00, 0x7f,
+    0x89, 0x10, 0xf9, 0xff, 0xea, 0xb6, 0x96, 0x4f, 0xa1, 0x1b, 0xa7, 0x39, 0x0d, 0x8b, 0xde, 0xbb,
+    0x5b, 0x2b, 0x48, 0x8c, 0xa9, 0x19, 0x83, 0x3c, 0x62, 0x02, 0xa1, 0x74, 0x07, 0x42, 0x20, 0xc3,
+];
+static EXPECTED_EXTENDED_SHA384: [u8; 48] = [
+    0x1a, 0x04, 0x3b, 0x61, 0x42, 0xdb, 0x18, 0x4e, 0x11, 0x2f, 0x34, 0x82, 0x61, 0x18, 0xa8, 0x7b,
+    0x22, 0xcb, 0x92, 0xef, 0xb1, 0x41, 0xbb, 0x8a, 0xe0, 0x10, 0xe8, 0x7d, 0xf1, 0x8f, 0x4e, 0xf3,
+    0xe8, 0xa9, 0x7d, 0xd9, 0xc0, 0xaf, 0xeb, 0x1c, 0xaa, 0x1a, 0xbf, 0xd6, 0x87, 0x1a, 0xf6, 0x42,
+];
+
+fn secret() -> Bytes {
+    Bytes::from_vec(b"secret".to_vec())
+}
+
+#[test]
+fn test_p_hash_blocks() {
+    let seed = Bytes::from_vec(b"seed".to_vec());
+    let a1 = hmac::<Blake2b>(secret(), seed.clone());
+    let a2 = hmac::<Blake2b>(secret(), a1.clone());
+    let mut a1_seed = Bytes::new_len(64 + 4);
+    a1_seed.update(0, &a1);
+    a1_seed.update(64, &seed);
+    let mut a2_seed = Bytes::new_len(64 + 4);
+    a2_seed.update(0, &a2);
+    a2_seed.update(64, &seed);
+    let block1 = hmac::<Blake2b>(secret(), a1_seed);
+    let block2 = hmac::<Blake2b>(secret(), a2_seed);
+
+    let out = p_hash::<Blake2b>(secret(), seed, 100);
+    assert_eq!(out.len(), 100);
+    assert_eq!(&block1[..], &out[0..64]);
+    assert_eq!(&block2[0..36], &out[64..100]);
+}
+
+#[test]
+fn test_p_hash_prefix() {
+    let seed = Bytes::from_vec(b"seed".to_vec());
+    let long = p_hash::<Sha256>(secret(), seed.clone(), 200);
+    for len in &[0, 1, 31, 32, 33, 64, 199] {
+        let short = p_hash::<Sha256>(secret(), seed.clone(), *len);
+        assert_eq!(&long[0..*len], &short[..]);
+    }
+}
+
+#[test]
+fn test_prf_label() {
+    let label = Bytes::from_vec(b"master secret".to_vec());
+    let seed = Bytes::from_vec(b"randoms".to_vec());
+    let label_seed = Bytes::from_vec(b"master secretrandoms".to_vec());
+    let out = prf::<Sha256>(secret(), label, seed, 48);
+    assert_eq!(&p_hash::<Sha256>(secret(), label_seed, 48)[..], &out[..]);
+}
+
+#[test]
+fn test_prf_sha256() {
+    let label = Bytes::from_vec(b"test label".to_vec());
+    let seed = Bytes::from_array(&SEED_SHA256);
+    let out = prf::<Sha256>(Bytes::from_array(&SECRET_SHA256), label, seed, 100);
+    assert_eq!(&EXPECTED_SHA256[..], &out[..]);
+}
+
+#[test]
+fn test_prf_sha384() {
+    let label = Bytes::from_vec(b"test label".to_vec());
+    let seed = Bytes::from_array(&SEED_SHA384);
+    let out = prf::<Sha384>(Bytes::from_array(&SECRET_SHA384), label, seed, 148);
+    assert_eq!(&EXPECTED_SHA384[..], &out[..]);
+}
+
+#[test]
+fn test_prf_legacy() {
+    let secret = Bytes::from_vec((0x40..0x40 + 47).collect());
+    let seed = Bytes::from_vec((0xa0..0xa0 + 32).collect());
+    let label = Bytes::from_vec(b"key expansion".to_vec());
+    let out = prf_legacy(secret, label, seed, 104);
+    assert_eq!(&EXPECTED_LEGACY[..], &out[..]);
+}
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+#[test]
+fn test_master_secret() {
+    let out = master_secret::<Sha256>(iota(0, 48), iota(0x40, 32), iota(0x60, 32));
+    assert_eq!(&EXPECTED_MASTER_SECRET[..], &out[..]);
+}
+
+#[test]
+fn test_extended_master_secret() {
+    let out = extended_master_secret::<Sha256>(iota(0, 48), iota(0x80, 32));
+    assert_eq!(&EXPECTED_EXTENDED_SHA256[..], &out[..]);
+    // The session hash in place of the randoms, not just a new label.
+    let label = Bytes::from_vec(b"extended master secret".to_vec());
+    let randoms = prf::<Sha256>(iota(0, 48), label, iota(0x40, 64), 48);
+    assert_ne!(&randoms[..], &out[..]);
+    let out = extended_master_secret::<Sha384>(iota(0, 48), iota(0x80, 48));
+    assert_eq!(&EXPECTED_EXTENDED_SHA384[..], &out[..]);
+}
@@ -0,0 +1,304 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::hash::HashSpec;
+use hacspecs::rsa::*;
+use hacspecs::sha1::Sha1;
+use hacspecs::sha256::Sha256;
+use hacspecs::sha512::Sha384;
+
+mod common;
+use common::hex;
+
+// A 2048-bit key generated with Python's cryptography, whose ciphertexts
+// below it also produced.
+static N: &str = concat!(
+    "9eb1fc7b9477ab7dadbab657a1617a66a44b69859889be7be055ba13da3c2d0d",
+    "4be0a36344e0d5671be1813404bede0ca511a6da81694cd5d47ff9dd086bb6ae",
+    "3c7f54565da7753c07993eb35b9f3c252869eadee614b28ef03e767d67d393c3",
+    "11ad16a3c38045e2f0d783d8b4b3d51807e3ed4cd5cd1805d9ac5970f336b5f0",
+    "c578f014076214092da63547fc206856158f1985cfa096de8d06d0123a194b29",
+    "fd5e7372ad67ee73f397f5b29bbd90ca8460a88532c3b77dd579cb781ef83bf7",
+    "d81a23b227a2783f0977b77aa12094a2054935a0d383259b2eea37598b503656",
+    "de79a5e6a824f6ecfc977809b28de4f58c3221f61153eb0a63539127e6939fe7",
+);
+static D: &str = concat!(
+    "1d6ecf7030321dc95a7e65b14d0a224485364364f6cc9d5aa659e0002c9aba94",
+    "5bbd57306326fc0d7a9b51764f670311ff4f8dcdbaf0c7c264fe335e6e6063ea",
+    "5ee812c14def700be930dff32e0c1380a4c5383d8d18cba13d27a89c4bb46f14",
+    "5fe3e56e0c256f50cef079d0a87b2e0b9df305211a0a46e956dce35dd28e1422",
+    "3fcf7e0a8a1157e5db071cd0c924c071d3eee2b22a0dc1b49d7ef10b6740a536",
+    "243a8f3e913912556177061bb5daa7cb0c49de640d5fe2eba2b4f055bd076ef1",
+    "f82d4dddbc60e52777799970e30df0897198d34f380b46d5a0546d6fc1c0a826",
+    "5733395b069e68792ff851f673e364ef089947cb39634c9d83c5660f4c8f57a9",
+);
+
+// A premaster secret for TLS 1.2, encrypted with random padding.
+static CT: &str = concat!(
+    "365adaadfce34bccca57d9ed6e8350b27113319b8e34af1b62c1145c87f1a193",
+    "99ad11b64300044c7adf7325d74a84e2d2ac9b022f8741ee63a3b69a5328721f",
+    "9496d1fb387dfaa01f18e973bef028f45b92597305cc9370127b021d460f6027",
+    "202c7a0b9a6e8be9c09099408edf6b56f268c6a99e809c4cd3d0ad3c5a0d6a18",
+    "85f909848d8bb320a6f6a44560346c9cd93c2f08cec4b64fa61a4802359c6b14",
+    "0205559f57226808baa40c2b89e44b1e811f282d065f32d104705bb4651dfb94",
+    "6df243d4327bfdfe310bfee3afa6e03c075c1a14803cf2ec2121e617315dfd45",
+    "b4b0e57d7c4b7196c1c4c57a0573fce7461b2871df32cbef72b14bdf235423b3",
+);
+
+// The same premaster secret, encrypted with the padding of `padding()`.
+static CT_PADDING: &str = concat!(
+    "77a85e8aaed5440df017f89c5d71a380b3058bedc9377a8aa6ba6e159be72a19",
+    "fd53423164ecd9b9ae84e9f836fe0e4da2ceedce93907b826c9c14834fa4c6bb",
+    "26ed6941e54e4c5440eebb11638304df39f37e58d467882f68da82a2b5d0bf81",
+    "c97a17a5ca15d8951f2743dc67aa8b4060e81a7a5dc0b0d329df476b91f78a18",
+    "488e50612ded240b2e6b36e2894ff8cd3e876cd879625aea3eebac4a417b90f3",
+    "f78d84b66b585a78af1479cd72915d6ff3818fea703c03750312a0496335a263",
+    "611a6ad79e206b6b4faa724aae97dc608e2561aecb5f6201b8a41c9b79d23fc8",
+    "8e563c1d98228f697aba181b985cb2855d63118c87b6695f838686cbac43edb8",
+);
+
+fn public_key() -> PublicKey {
+    PublicKey { n: hex(N), e: hex("010001") }
+}
+
+fn private_key() -> PrivateKey {
+    PrivateKey { n: hex(N), d: hex(D) }
+}
+
+// The 46 random bytes of the premaster secret.
+fn pms_random() -> Bytes {
+    Bytes::from_vec((0x10..0x10 + 46).collect())
+}
+
+fn pms() -> Bytes {
+    let mut pms = Bytes::new_len(48);
+    pms[0] = 3;
+    pms[1] = 3;
+    pms.update(2, &pms_random());
+    pms
+}
+
+fn padding(len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| ((i * 7 + 1) % 255 + 1) as u8).collect())
+}
+
+// Encrypts an encoded message as it is, padding or not.
+fn encrypt_raw(em: Bytes) -> Bytes {
+    rsaep(&public_key(), em).unwrap()
+}
+
+fn client_key_exchange_of(ct: Bytes) -> Bytes {
+    let mut out = Bytes::new_len(2 + ct.len());
+    out.update_raw(0, &(ct.len() as u16).to_be_bytes());
+    out.update(2, &ct);
+    out
+}
+
+#[test]
+fn test_primitives() {
+    assert_eq!(256, modulus_len(&hex(N)));
+    let m = hex("0102030405060708090a");
+    let c = rsaep(&public_key(), m.clone()).unwrap();
+    assert_eq!(256, c.len());
+    assert_eq!(&m[..], &rsadp(&private_key(), c).unwrap()[246..256]);
+    assert!(rsaep(&public_key(), hex(N)).is_err());
+}
+
+#[test]
+fn test_pkcs1_encrypt() {
+    let ct = pkcs1_encrypt(&public_key(), padding(205), pms()).unwrap();
+    assert_eq!(hex(CT_PADDING), ct);
+    assert_eq!(pms(), pkcs1_decrypt(&private_key(), ct).unwrap());
+    assert_eq!(pms(), pkcs1_decrypt(&private_key(), hex(CT)).unwrap());
+
+    assert!(pkcs1_encrypt(&public_key(), padding(204), pms()).is_err());
+    let mut zero = padding(205);
+    zero[100] = 0;
+    assert!(pkcs1_encrypt(&public_key(), zero, pms()).is_err());
+    assert!(pkcs1_encrypt(&public_key(), padding(8), Bytes::new_len(246)).is_err());
+}
+
+#[test]
+fn test_pkcs1_decrypt_rejects() {
+    let sk = private_key();
+    let em = |first: u8, second: u8, separator: usize| {
+        let mut em = Bytes::new_len(256);
+        em[0] = first;
+        em[1] = second;
+        em.update(2, &padding(254));
+        em[separator] = 0;
+        em
+    };
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(em(0, 2, 207))).is_ok());
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(em(0, 1, 207))).is_err());
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(em(1, 2, 207))).is_err());
+    // A padding string of 7 bytes is too short.
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(em(0, 2, 9))).is_err());
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(em(0, 2, 10))).is_ok());
+    // There is no separator.
+    let mut no_separator = em(0, 2, 207);
+    no_separator[207] = 1;
+    assert!(pkcs1_decrypt(&sk, encrypt_raw(no_separator)).is_err());
+    assert!(pkcs1_decrypt(&sk, hex(&CT[0..510])).is_err());
+}
+
+#[test]
+fn test_client_key_exchange() {
+    let cke = client_key_exchange(&public_key(), 0x0303, pms_random(), padding(205)).unwrap();
+    assert_eq!(client_key_exchange_of(hex(CT_PADDING)), cke);
+    let pms_r = Bytes::new_len(46);
+    assert_eq!(pms(), server_premaster_secret(&private_key(), 0x0303, pms_r, cke).unwrap());
+}
+
+// Whatever the ClientKeyExchange holds, the server gets a premaster secret
+// with the version of the ClientHello.
+#[test]
+fn test_server_premaster_secret() {
+    let sk = private_key();
+    let r = Bytes::from_vec(vec![0xaa; 46]);
+    let fallback = |version: u16| {
+        let mut pms = Bytes::new_len(48);
+        pms.update_raw(0, &version.to_be_bytes());
+        pms.update(2, &r);
+        pms
+    };
+    let server_pms = |version: u16, ct: Bytes| {
+        server_premaster_secret(&sk, version, r.clone(), client_key_exchange_of(ct)).unwrap()
+    };
+
+    assert_eq!(pms(), server_pms(0x0303, hex(CT)));
+
+    // A different version in the premaster secret is replaced.
+    let mut pms_0301 = pms();
+    pms_0301[1] = 1;
+    assert_eq!(pms_0301, server_pms(0x0301, hex(CT)));
+
+    // Bad padding and a bad length both give client_version || R.
+    let mut em = Bytes::new_len(256);
+    em[1] = 1;
+    em.update(2, &padding(205));
+    em.update(208, &pms());
+    assert_eq!(fallback(0x0303), server_pms(0x0303, encrypt_raw(em)));
+    let ct = pkcs1_encrypt(&public_key(), padding(206), Bytes::from(&pms()[0..47])).unwrap();
+    assert_eq!(fallback(0x0303), server_pms(0x0303, ct));
+
+    // Only a malformed message is an error.
+    let mut cke = client_key_exchange_of(hex(CT));
+    cke[1] ^= 1;
+    assert!(server_premaster_secret(&sk, 0x0303, r.clone(), cke).is_err());
+    assert!(server_premaster_secret(&sk, 0x0303, r.clone(), hex("00")).is_err());
+}
+
+// Signatures by the key above of SIGNED_MSG, from Python's cryptography.
+static SIGNED_MSG: &[u8] = b"signed ServerKeyExchange params";
+static SIG_SHA256: &str = concat!(
+    "81dc0b9ed5f3b89dff74defa73ee691b69891fc4356a2fb62409a6dccd9b3c79",
+    "7b715fa507cf0c772cef1b875fa01bc4cac080e0a8a435047829b6c6ff90a440",
+    "9bcb8099e0f387cd3285d81cd14fc92aeb86083021d64b5bffec4a0386ff8cf4",
+    "b2f10f3c7c728ba7618ec440a268cdfd6027ba089ccc44ca73f33fa9da856a83",
+    "8754c932a7b7d99f34390509df553a6af83fa438ff435f4ef87af5e73e85cb3f",
+    "55bff8010e0aa7841132f90fbfd43882605abe2c71ce029a0474601616b993c6",
+    "a4eefd78ff5f605b82478d1ac0fb250cc726a9fb0d0fe050f558ac8d25b34066",
+    "a9b6348b6690a4445ec7206b8c3ec490455a48eac53a96da412336681c5a2fd5",
+);
+static SIG_SHA384: &str = concat!(
+    "7e9b01b3b85fb39e1401a022f347de43de08e309604320bf5c07a7f52f50da2f",
+    "bcd827a7d50adbce7bb5d8aa833f772035c7b5335e21f516837b76b2093e45e8",
+    "74b6cc1d2b225265b599aac849812133e03a1f0548174f09e1cc17cbf9b72576",
+    "780630d37e78f6ad31fa20b68e453184ed29ea1190d116be5d91e376d8884d54",
+    "6d73e0157829e638f0be5ebbf2217fec1ab6a4c7e60aa5f914db33d62e562d55",
+    "6aa6cf534c54d75257c0bfe50af137b5ed2c85c15d9d4334f223071e7ef76a9f",
+    "d8d7bb9cff1b52d0d6ce3ad013cc0e801832f80803e424fec29f84c40e570cfe",
+    "d5474bed41be390d896dd512839b8e086b1e3af0480bb47fd5a760c7accf39af",
+);
+static SIG_SHA1: &str = concat!(
+    "20d2e68bbed2213d8d40fc4ac597aa4a7ef2da9a8c8a32fa5e2a70ef00b3ba79",
+    "ca0ec62b322bfd401e47df10c933c60b2e62d85f361673bf78b09de941c4ec46",
+    "b79f01d3e4f1a3f6ff51de9cd07bd508e7dd11a340604c2bc9de74d65be9b2d8",
+    "c264f4198a9fa915fc4d001190de937d6edc7f940069cac238081a8cee2c68b5",
+    "e9874a1ece958e2d9839ad6593fa1bf931bdf485421e90bd99a5013b36b2b0e2",
+    "68f348bc2d03e83a572964b17ca8e7d327da55b6ac2c85a228ebc456ec5d6441",
+    "8365198fc484095aa297eb7ae52ff50a090e92dcba1305a2e420d17da69f0159",
+    "bd365c04ae9282787b5b975db38411f4b736501c9b55e06b3e6f836cfa7ff732",
+);
+
+static SHA256_PREFIX: &str = "3031300d060960864801650304020105000420";
+
+fn concat(a: &Bytes, b: &Bytes) -> Bytes {
+    let mut out = Bytes::new_len(a.len() + b.len());
+    out.update(0, a);
+    out.update(a.len(), b);
+    out
+}
+
+// 0x00 || 0x01 || 0xff... || 0x00 || t, for any t.
+fn encode_raw(t: &Bytes) -> Bytes {
+    let mut em = Bytes::from_vec(vec![0xff; 256]);
+    em[0] = 0;
+    em[1] = 1;
+    em[255 - t.len()] = 0;
+    em.update(256 - t.len(), t);
+    em
+}
+
+fn signed_msg() -> Bytes {
+    Bytes::from_array(SIGNED_MSG)
+}
+
+#[test]
+fn test_pkcs1_sign() {
+    let sig = pkcs1_sign::<Sha256>(&private_key(), signed_msg()).unwrap();
+    assert_eq!(hex(SIG_SHA256), sig);
+    assert!(pkcs1_verify::<Sha256>(&public_key(), signed_msg(), sig).is_ok());
+
+    let sig = pkcs1_sign::<Sha384>(&private_key(), signed_msg()).unwrap();
+    assert_eq!(hex(SIG_SHA384), sig);
+    assert!(pkcs1_verify::<Sha384>(&public_key(), signed_msg(), sig).is_ok());
+
+    let sig = pkcs1_sign::<Sha1>(&private_key(), signed_msg()).unwrap();
+    assert_eq!(hex(SIG_SHA1), sig);
+    assert!(pkcs1_verify::<Sha1>(&public_key(), signed_msg(), sig).is_ok());
+}
+
+#[test]
+fn test_emsa_pkcs1_encode() {
+    let em = emsa_pkcs1_encode::<Sha256>(signed_msg(), 62).unwrap();
+    assert_eq!(hex("0001ffffffffffffffff00"), Bytes::from(&em[0..11]));
+    assert_eq!(&Sha256::hash(signed_msg())[..], &em[30..62]);
+    assert!(emsa_pkcs1_encode::<Sha256>(signed_msg(), 61).is_err());
+}
+
+#[test]
+fn test_pkcs1_verify_rejects() {
+    let pk = public_key();
+    let verify = |sig: Bytes| pkcs1_verify::<Sha256>(&pk, signed_msg(), sig);
+    assert!(pkcs1_verify::<Sha256>(&pk, hex("00"), hex(SIG_SHA256)).is_err());
+    assert!(pkcs1_verify::<Sha384>(&pk, signed_msg(), hex(SIG_SHA256)).is_err());
+    assert!(verify(hex(&SIG_SHA256[2..512])).is_err());
+    let mut sig = hex(SIG_SHA256);
+    sig[255] ^= 1;
+    assert!(verify(sig).is_err());
+    assert!(verify(hex(N)).is_err());
+
+    // Malformed encodings of the right digest, signed with the private key.
+    let sign_raw = |em: Bytes| rsasp1(&private_key(), em).unwrap();
+    let digest_info = concat(&hex(SHA256_PREFIX), &Sha256::hash(signed_msg()));
+    let good = encode_raw(&digest_info);
+    assert_eq!(emsa_pkcs1_encode::<Sha256>(signed_msg(), 256).unwrap(), good);
+    assert!(verify(sign_raw(good.clone())).is_ok());
+
+    // Block type 2, and a padding byte that is not 0xff.
+    let mut em = good.clone();
+    em[1] = 0x02;
+    assert!(verify(sign_raw(em)).is_err());
+    let mut em = good;
+    em[100] = 0xfe;
+    assert!(verify(sign_raw(em)).is_err());
+
+    // The DigestInfo followed by garbage, and one without NULL parameters.
+    let garbage = concat(&digest_info, &hex("0102030405060708"));
+    assert!(verify(sign_raw(encode_raw(&garbage))).is_err());
+    let no_null = concat(&hex("302f300b06096086480165030402010420"), &Sha256::hash(signed_msg()));
+    assert!(verify(sign_raw(encode_raw(&no_null))).is_err());
+}
@@ -0,0 +1,148 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::rsa::{PrivateKey, PublicKey};
+use hacspecs::rsapss::*;
+use hacspecs::sha256::Sha256;
+use hacspecs::sha512::Sha384;
+
+mod common;
+use common::hex;
+
+// The 2048-bit key of test_rsa.rs. The signatures with fixed salts were
+// checked with Python's cryptography, which made the one with a random salt.
+static N: &str = concat!(
+    "9eb1fc7b9477ab7dadbab657a1617a66a44b69859889be7be055ba13da3c2d0d",
+    "4be0a36344e0d5671be1813404bede0ca511a6da81694cd5d47ff9dd086bb6ae",
+    "3c7f54565da7753c07993eb35b9f3c252869eadee614b28ef03e767d67d393c3",
+    "11ad16a3c38045e2f0d783d8b4b3d51807e3ed4cd5cd1805d9ac5970f336b5f0",
+    "c578f014076214092da63547fc206856158f1985cfa096de8d06d0123a194b29",
+    "fd5e7372ad67ee73f397f5b29bbd90ca8460a88532c3b77dd579cb781ef83bf7",
+    "d81a23b227a2783f0977b77aa12094a2054935a0d383259b2eea37598b503656",
+    "de79a5e6a824f6ecfc977809b28de4f58c3221f61153eb0a63539127e6939fe7",
+);
+static D: &str = concat!(
+    "1d6ecf7030321dc95a7e65b14d0a224485364364f6cc9d5aa659e0002c9aba94",
+    "5bbd57306326fc0d7a9b51764f670311ff4f8dcdbaf0c7c264fe335e6e6063ea",
+    "5ee812c14def700be930dff32e0c1380a4c5383d8d18cba13d27a89c4bb46f14",
+    "5fe3e56e0c256f50cef079d0a87b2e0b9df305211a0a46e956dce35dd28e1422",
+    "3fcf7e0a8a1157e5db071cd0c924c071d3eee2b22a0dc1b49d7ef10b6740a536",
+    "243a8f3e913912556177061bb5daa7cb0c49de640d5fe2eba2b4f055bd076ef1",
+    "f82d4dddbc60e52777799970e30df0897198d34f380b46d5a0546d6fc1c0a826",
+    "5733395b069e68792ff851f673e364ef089947cb39634c9d83c5660f4c8f57a9",
+);
+
+static MSG: &[u8] = b"TLS 1.3, server CertificateVerify";
+
+// With the salt 0x00, 0x01, ... of the hash's length.
+static SIG_SHA256: &str = concat!(
+    "90ccc2082de79114cc217fc3e1e70e51180ad1f4cc0fe5b67c105bffc61dbd64",
+    "14491eb2712237ff02b9af8def8a63c2b09b7aaa9adeacaab798816e4a291c7b",
+    "0ed57d30f51ffc628826575e99315f296fdcc7b1ce22ae48ce7826705f8ce73e",
+    "be32707a0bda803231706712c8ae1a6a509cf5d0cf9533d533e709b603843266",
+    "481e209cef004dc7b4acfef7d548c2ccfd0f5d3f667fb3ed9fd8ec23f3ef7104",
+    "365e7d6428343aabb940f896c356982acee38f5faf3e5a44b405d4c54fdc37d2",
+    "e52fea5ad8322c2ce0fee49ab4151965205d229a8a4bf0dab23e5036a9efae55",
+    "ec5dd30ec89f9f0d39e92b10867c84153941d25de46ba7e3d1702dda4db86af1",
+);
+static SIG_SHA384: &str = concat!(
+    "926c33451439cd2b43b815c06c21101e327aaaa0ae2c6b8f84e9503c23f91728",
+    "3d7596876e23027f449f24692aa2900fbd4f31b0d4dbe5d378e7ce5aee0da763",
+    "5e2b2f9d5cc516c78da3f51ca28e483d69ac27c113597d02b1a2c8b7dbc864dc",
+    "f093cb1d346910297d4babf808403dbab725bff4e6728d0ff1372e855488fb7a",
+    "3f0d83e1403a20cab0d42f793fefca7f2520def411149b6a00ce7b18af231eb7",
+    "df959031e62c9336a63424232ad30da8bd9a1ff97f69500df77d0f69c984677c",
+    "c3e3c7da359453cfe370af0506e0aee65d4432be82d29955f64ff203a4fd1b9b",
+    "bf8db6a3c58f605e94b7aaa1d70375defd830fdea14a49901e44b999fb495e5c",
+);
+static SIG_RANDOM_SALT: &str = concat!(
+    "3daf0b63efcb174cdc1ce4dae31c06999e4a6c0a7019b7d2d28f86ca2df7e755",
+    "0e99d68f32e7eb70d4560a063dbeff33fa018e9db82841dafc332723bf2989bd",
+    "36003a39572d53496beef3225125adf51dd68464a673a7e9759e6b4c7a87fe9f",
+    "480661cdb7edbc360ddcc9f0f52e7fdf0b01001087e82b9021ef43c755b1329d",
+    "d609975045d4e6d1465591ae2efe022293d9e7648fc8906a8c3f6fa28afe9265",
+    "88e3c629c4c04b9efb73ad273eaa19b076b88a1e923d2fa55e025a5597bd7673",
+    "c0c524ebf2491b445c5394a05fcb8898373c6096960de16a6005056f624ff02f",
+    "5e36a61d44caa70e7b1b26c0961bffaeb824c078374a04fdf98509d417e6a073",
+);
+
+fn public_key() -> PublicKey {
+    PublicKey { n: hex(N), e: hex("010001") }
+}
+
+fn private_key() -> PrivateKey {
+    PrivateKey { n: hex(N), d: hex(D) }
+}
+
+fn msg() -> Bytes {
+    Bytes::from_array(MSG)
+}
+
+fn salt(len: usize) -> Bytes {
+    Bytes::from_vec((0..len as u8).collect())
+}
+
+#[test]
+fn test_mgf1() {
+    let mask = mgf1::<Sha256>(&Bytes::from_vec(b"seed".to_vec()), 40);
+    let expected = concat!(
+        "336f28a022193939585a1b4edc989f870917f3a5f6ddd16e4fb357084a6bdfc2",
+        "73a649427664d03b"
+    );
+    assert_eq!(hex(expected), mask);
+    assert_eq!(Bytes::from(&mask[0..7]), mgf1::<Sha256>(&Bytes::from_vec(b"seed".to_vec()), 7));
+}
+
+#[test]
+fn test_pss_sign() {
+    let sig = pss_sign::<Sha256>(&private_key(), salt(32), msg()).unwrap();
+    assert_eq!(hex(SIG_SHA256), sig);
+    assert!(pss_verify::<Sha256>(&public_key(), msg(), sig, 32).is_ok());
+
+    let sig = pss_sign::<Sha384>(&private_key(), salt(48), msg()).unwrap();
+    assert_eq!(hex(SIG_SHA384), sig);
+    assert!(pss_verify::<Sha384>(&public_key(), msg(), sig, 48).is_ok());
+
+    assert!(pss_verify::<Sha256>(&public_key(), msg(), hex(SIG_RANDOM_SALT), 32).is_ok());
+}
+
+#[test]
+fn test_pss_verify_rejects() {
+    let pk = public_key();
+    assert!(pss_verify::<Sha256>(&pk, hex("00"), hex(SIG_SHA256), 32).is_err());
+    assert!(pss_verify::<Sha384>(&pk, msg(), hex(SIG_SHA256), 32).is_err());
+    assert!(pss_verify::<Sha256>(&pk, msg(), hex(SIG_SHA256), 31).is_err());
+    assert!(pss_verify::<Sha256>(&pk, msg(), hex(SIG_SHA256), 0).is_err());
+    assert!(pss_verify::<Sha256>(&pk, msg(), hex(&SIG_SHA256[2..512]), 32).is_err());
+    let mut sig = hex(SIG_SHA256);
+    sig[7] ^= 0x10;
+    assert!(pss_verify::<Sha256>(&pk, msg(), sig, 32).is_err());
+}
+
+#[test]
+fn test_emsa_pss() {
+    let em = emsa_pss_encode::<Sha256>(msg(), &salt(32), 2047).unwrap();
+    assert_eq!(256, em.len());
+    assert_eq!(0xbc, em[255]);
+    assert_eq!(0, em[0] & 0x80);
+    assert!(emsa_pss_verify::<Sha256>(msg(), &em, 2047, 32).is_ok());
+
+    // The salt and hash must fit, and the trailer and top bit are checked.
+    assert!(emsa_pss_encode::<Sha256>(msg(), &salt(32), 8 * 65).is_err());
+    assert!(emsa_pss_encode::<Sha256>(msg(), &salt(32), 8 * 65 + 1).is_ok());
+    let mut bad = em.clone();
+    bad[255] = 0xcc;
+    assert!(emsa_pss_verify::<Sha256>(msg(), &bad, 2047, 32).is_err());
+    let mut bad = em.clone();
+    bad[0] |= 0x80;
+    assert!(emsa_pss_verify::<Sha256>(msg(), &bad, 2047, 32).is_err());
+    let mut bad = em;
+    bad[240] ^= 1;
+    assert!(emsa_pss_verify::<Sha256>(msg(), &bad, 2047, 32).is_err());
+
+    // With em_bits a multiple of 8, no bits are cleared.
+    let em = emsa_pss_encode::<Sha256>(msg(), &salt(20), 8 * 100).unwrap();
+    assert_eq!(100, em.len());
+    assert!(emsa_pss_verify::<Sha256>(msg(), &em, 8 * 100, 20).is_ok());
+}
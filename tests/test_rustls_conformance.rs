@@ -0,0 +1,200 @@
+//! Runs rustls TLS 1.2 handshakes in memory and checks the messages that go
+//! over the wire against the spec machine. Enabled by the
+//! `rustls-conformance` feature.
+#![cfg(feature = "rustls-conformance")]
+
+extern crate hacspecs;
+use hacspecs::tls12::{ClientAuth, KeyExchange, MessageType, Peer, ProtocolOptions, Trace};
+
+use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+use rustls::client::{Resumption, Tls12Resumption};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    ClientConfig, ClientConnection, Connection, RootCertStore, ServerConfig, ServerConnection,
+};
+use std::sync::Arc;
+
+struct Pki {
+    roots: Arc<RootCertStore>,
+    cert: CertificateDer<'static>,
+    key: Vec<u8>,
+}
+
+impl Pki {
+    /// A CA and one certificate for "localhost" it issued, used by both
+    /// peers.
+    fn new() -> Pki {
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&key, &ca, &ca_key)
+            .unwrap();
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        Pki { roots: Arc::new(roots), cert: cert.der().clone(), key: key.serialize_der() }
+    }
+
+    fn key(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.key.clone()))
+    }
+}
+
+fn configs(
+    pki: &Pki,
+    ticket: bool,
+    client_auth: ClientAuth,
+) -> (Arc<ClientConfig>, Arc<ServerConfig>) {
+    let tls12 = &[&rustls::version::TLS12];
+
+    let client = ClientConfig::builder_with_protocol_versions(tls12)
+        .with_root_certificates(pki.roots.clone());
+    let mut client = match client_auth {
+        ClientAuth::Performed => {
+            client.with_client_auth_cert(vec![pki.cert.clone()], pki.key()).unwrap()
+        }
+        _ => client.with_no_client_auth(),
+    };
+    let tls12_resumption =
+        if ticket { Tls12Resumption::SessionIdOrTickets } else { Tls12Resumption::SessionIdOnly };
+    client.resumption = Resumption::in_memory_sessions(8).tls12_resumption(tls12_resumption);
+
+    let server = ServerConfig::builder_with_protocol_versions(tls12);
+    let verifier = WebPkiClientVerifier::builder(pki.roots.clone());
+    let server = match client_auth {
+        ClientAuth::NotRequested => server.with_no_client_auth(),
+        ClientAuth::Declined => {
+            server.with_client_cert_verifier(verifier.allow_unauthenticated().build().unwrap())
+        }
+        _ => server.with_client_cert_verifier(verifier.build().unwrap()),
+    };
+    let mut server = server.with_single_cert(vec![pki.cert.clone()], pki.key()).unwrap();
+    if ticket {
+        server.ticketer = rustls::crypto::ring::Ticketer::new().unwrap();
+    }
+    (Arc::new(client), Arc::new(server))
+}
+
+fn handshake_type(typ: u8) -> MessageType {
+    match typ {
+        0 => MessageType::HelloRequest,
+        1 => MessageType::ClientHello,
+        2 => MessageType::ServerHello,
+        4 => MessageType::NewSessionTicket,
+        11 => MessageType::Certificate,
+        12 => MessageType::ServerKeyExchange,
+        13 => MessageType::CertificateRequest,
+        14 => MessageType::ServerHelloDone,
+        15 => MessageType::CertificateVerify,
+        16 => MessageType::ClientKeyExchange,
+        20 => MessageType::Finished,
+        21 => MessageType::CertificateUrl,
+        22 => MessageType::CertificateStatus,
+        23 => MessageType::SupplementalData,
+        67 => MessageType::NextProtocol,
+        _ => panic!("unknown handshake message type {}", typ),
+    }
+}
+
+/// Record what `peer` sent in `data`. Once a peer has sent its
+/// ChangeCipherSpec its handshake records are encrypted; without NPN or
+/// renegotiation the only one is its Finished.
+fn observe(peer: Peer, mut data: &[u8], encrypted: &mut bool, seen: &mut Vec<(Peer, MessageType)>) {
+    while !data.is_empty() {
+        let len = u16::from_be_bytes([data[3], data[4]]) as usize;
+        let (typ, mut payload) = (data[0], &data[5..5 + len]);
+        data = &data[5 + len..];
+        match typ {
+            20 => {
+                seen.push((peer, MessageType::ChangeCipherSpec));
+                *encrypted = true;
+            }
+            21 => seen.push((peer, MessageType::Alert)),
+            22 if *encrypted => seen.push((peer, MessageType::Finished)),
+            22 => {
+                while !payload.is_empty() {
+                    let len = u32::from_be_bytes([0, payload[1], payload[2], payload[3]]) as usize;
+                    seen.push((peer, handshake_type(payload[0])));
+                    payload = &payload[4 + len..];
+                }
+            }
+            23 => seen.push((peer, MessageType::ApplicationData)),
+            _ => panic!("unknown record type {}", typ),
+        }
+    }
+}
+
+/// Move everything `from` has to send over to `to`. Whether there was
+/// anything.
+fn transfer(
+    peer: Peer,
+    from: &mut Connection,
+    to: &mut Connection,
+    encrypted: &mut bool,
+    seen: &mut Vec<(Peer, MessageType)>,
+) -> bool {
+    let mut buf = Vec::new();
+    while from.wants_write() {
+        from.write_tls(&mut buf).unwrap();
+    }
+    observe(peer, &buf, encrypted, seen);
+    let mut rd = &buf[..];
+    while !rd.is_empty() {
+        to.read_tls(&mut rd).unwrap();
+        to.process_new_packets().unwrap();
+    }
+    !buf.is_empty()
+}
+
+fn run(client: &Arc<ClientConfig>, server: &Arc<ServerConfig>) -> Vec<(Peer, MessageType)> {
+    let name = ServerName::try_from("localhost").unwrap();
+    let mut client = Connection::from(ClientConnection::new(client.clone(), name).unwrap());
+    let mut server = Connection::from(ServerConnection::new(server.clone()).unwrap());
+    let mut seen = Vec::new();
+    let (mut client_encrypted, mut server_encrypted) = (false, false);
+    loop {
+        let c = transfer(Peer::Client, &mut client, &mut server, &mut client_encrypted, &mut seen);
+        let s = transfer(Peer::Server, &mut server, &mut client, &mut server_encrypted, &mut seen);
+        if !c && !s {
+            break;
+        }
+    }
+    assert!(!client.is_handshaking() && !server.is_handshaking());
+    seen
+}
+
+#[test]
+fn rustls_conformance() {
+    let pki = Pki::new();
+    let client_auths = [ClientAuth::NotRequested, ClientAuth::Declined, ClientAuth::Performed];
+    for resuming in &[false, true] {
+        for ticket in &[false, true] {
+            for client_auth in &client_auths {
+                let opts = ProtocolOptions::builder()
+                    .key_exchange(KeyExchange::EcdheEcdsa)
+                    .resuming(*resuming)
+                    .client_offers_ticket(*ticket)
+                    .resume_via_ticket(*resuming && *ticket)
+                    .server_issues_ticket(*ticket)
+                    .client_auth(*client_auth)
+                    .build()
+                    .unwrap();
+                let (client, server) = configs(&pki, *ticket, *client_auth);
+                if *resuming {
+                    // Leave a session behind to resume.
+                    run(&client, &server);
+                }
+                let expected = Trace::linearise(opts)
+                    .steps()
+                    .iter()
+                    .map(|(_, peer, msg)| (*peer, *msg))
+                    .collect::<Vec<_>>();
+                assert_eq!(run(&client, &server), expected, "{:?}", opts);
+            }
+        }
+    }
+}
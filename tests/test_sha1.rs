@@ -0,0 +1,53 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::sha1::*;
+
+// The examples of FIPS 180-2, appendix A, and the empty message.
+
+static EXPECTED_ABC: [u8; 20] = [
+    0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50, 0xc2, 0x6c,
+    0x9c, 0xd0, 0xd8, 0x9d,
+];
+
+#[test]
+fn test_single_block() {
+    let m = Bytes::from_array(&[0x61u8, 0x62, 0x63]);
+    let h = sha1(m);
+    assert_eq!(&EXPECTED_ABC[..], &h[..]);
+}
+
+#[test]
+fn test_multi_block_string() {
+    let m = String::from("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+    let h = sha1(Bytes::from_vec(m.into_bytes()));
+
+    let expected: [u8; 20] = [
+        0x84, 0x98, 0x3e, 0x44, 0x1c, 0x3b, 0xd2, 0x6e, 0xba, 0xae, 0x4a, 0xa1, 0xf9, 0x51, 0x29,
+        0xe5, 0xe5, 0x46, 0x70, 0xf1,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
+
+#[test]
+fn test_empty() {
+    let h = sha1(Bytes::new_len(0));
+
+    let expected: [u8; 20] = [
+        0xda, 0x39, 0xa3, 0xee, 0x5e, 0x6b, 0x4b, 0x0d, 0x32, 0x55, 0xbf, 0xef, 0x95, 0x60, 0x18,
+        0x90, 0xaf, 0xd8, 0x07, 0x09,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
+
+#[test]
+fn test_million_a() {
+    let h = sha1(Bytes::from_vec(vec![0x61u8; 1_000_000]));
+
+    let expected: [u8; 20] = [
+        0x34, 0xaa, 0x97, 0x3c, 0xd4, 0xc4, 0xda, 0xa4, 0xf6, 0x1e, 0xeb, 0x2b, 0xdb, 0xad, 0x27,
+        0x31, 0x65, 0x34, 0x01, 0x6f,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
@@ -0,0 +1,56 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::sha256::*;
+
+// The examples of FIPS 180-2, appendix B, and the empty message.
+
+static EXPECTED_ABC: [u8; 32] = [
+    0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+    0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+#[test]
+fn test_single_block() {
+    let m = Bytes::from_array(&[0x61u8, 0x62, 0x63]);
+    let h = sha256(m);
+    assert_eq!(&EXPECTED_ABC[..], &h[..]);
+}
+
+#[test]
+fn test_multi_block_string() {
+    let m = String::from("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+    let h = sha256(Bytes::from_vec(m.into_bytes()));
+
+    let expected: [u8; 32] = [
+        0x24, 0x8d, 0x6a, 0x61, 0xd2, 0x06, 0x38, 0xb8, 0xe5, 0xc0, 0x26, 0x93, 0x0c, 0x3e, 0x60,
+        0x39, 0xa3, 0x3c, 0xe4, 0x59, 0x64, 0xff, 0x21, 0x67, 0xf6, 0xec, 0xed, 0xd4, 0x19, 0xdb,
+        0x06, 0xc1,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
+
+#[test]
+fn test_empty() {
+    let h = sha256(Bytes::new_len(0));
+
+    let expected: [u8; 32] = [
+        0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9,
+        0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52,
+        0xb8, 0x55,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
+
+#[test]
+fn test_million_a() {
+    let h = sha256(Bytes::from_vec(vec![0x61u8; 1_000_000]));
+
+    let expected: [u8; 32] = [
+        0xcd, 0xc7, 0x6e, 0x5c, 0x99, 0x14, 0xfb, 0x92, 0x81, 0xa1, 0xc7, 0xe2, 0x84, 0xd7, 0x3e,
+        0x67, 0xf1, 0x80, 0x9a, 0x48, 0xa4, 0x97, 0x20, 0x0e, 0x04, 0x6d, 0x39, 0xcc, 0xc7, 0x11,
+        0x2c, 0xd0,
+    ];
+    assert_eq!(&expected[..], &h[..]);
+}
@@ -0,0 +1,103 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::aes::Block;
+use hacspecs::ticket::*;
+
+mod common;
+use common::hex;
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+fn keys() -> TicketKeys {
+    TicketKeys { key_name: iota(0, 16), aes_key: iota(16, 16), hmac_key: iota(32, 32) }
+}
+
+fn state(client_identity: ClientIdentity) -> StatePlaintext {
+    StatePlaintext {
+        protocol_version: 0x0303,
+        cipher_suite: 0xc02f,
+        compression_method: 0,
+        master_secret: iota(0x80, 48),
+        client_identity,
+        timestamp: 0x5f5e1000,
+    }
+}
+
+// Tickets sealed with the Python cryptography package and hmac module.
+const PSK_TICKET: &str = concat!(
+    "000102030405060708090a0b0c0d0e0f404142434445464748494a4b4c4d4e4f005082a4d91223cf83b86596",
+    "fb7e5dfefab00afb7ae3ba957271a0ab3e19173c9b2e04e9d058f8e9a8dc9351b860ae2bfade7ccc93a54f1a",
+    "37d5c4a4abe9b7b684fd5583cd416a1e7f038314accb9a31479faa7aa06dda66767840f96e6fccd2423bda5f",
+    "29a2760a011561f08e46b6555810",
+);
+const ANONYMOUS_TICKET: &str = concat!(
+    "000102030405060708090a0b0c0d0e0f404142434445464748494a4b4c4d4e4f004082a4d91223cf83b86596",
+    "fb7e5dfefab00afb7ae3ba957271a0ab3e19173c9b2e04e9d058f8e9a8dc9351b860ae2bfade4db4fef7f5c3",
+    "6ff9a0dd9c1a00f025a36fadaa7dcc39410dce66b0777442edd9c048e05c8c4907c2c1c74786fb4759fa",
+);
+
+#[test]
+fn test_seal_ticket() {
+    let iv = Block::from(&iota(64, 16)[..]);
+    let psk = state(ClientIdentity::Psk(Bytes::from_vec(b"client".to_vec())));
+    let ticket = seal_ticket(&keys(), iv, &psk).unwrap();
+    assert_eq!(ticket, hex(PSK_TICKET));
+    assert_eq!(open_ticket(&keys(), &ticket), Ok(psk));
+
+    let anonymous = state(ClientIdentity::Anonymous);
+    let ticket = seal_ticket(&keys(), iv, &anonymous).unwrap();
+    assert_eq!(ticket, hex(ANONYMOUS_TICKET));
+    assert_eq!(open_ticket(&keys(), &ticket), Ok(anonymous));
+}
+
+#[test]
+fn test_state_round_trip() {
+    let with_cert = state(ClientIdentity::CertificateBased {
+        certificate_type: 1,
+        certificate_list: iota(1, 40),
+    });
+    let encoded = encode_state(&with_cert).unwrap();
+    assert_eq!(encoded.len(), 53 + 5 + 40 + 4);
+    assert_eq!(decode_state(&encoded), Ok(with_cert));
+
+    let mut short = state(ClientIdentity::Anonymous);
+    short.master_secret = iota(0, 47);
+    assert!(encode_state(&short).is_err());
+    assert!(decode_state(&iota(0, 57)).is_err());
+}
+
+#[test]
+fn test_rejected_tickets() {
+    let ticket = hex(PSK_TICKET);
+    let mut other = keys();
+    other.key_name = iota(1, 16);
+    assert_eq!(open_ticket(&other, &ticket), Err("Unknown ticket key".to_string()));
+
+    // Any change to the IV, state or MAC is caught by the MAC.
+    for &i in &[16, 40, 100, ticket.len() - 1] {
+        let mut bad = ticket.clone();
+        bad[i] ^= 1;
+        assert_eq!(open_ticket(&keys(), &bad), Err("Invalid ticket MAC".to_string()));
+    }
+    let truncated = Bytes::from(&ticket[0..ticket.len() - 1]);
+    assert!(open_ticket(&keys(), &truncated).is_err());
+}
+
+#[test]
+fn test_new_session_ticket() {
+    let msg = NewSessionTicket { lifetime_hint: 7200, ticket: hex(ANONYMOUS_TICKET) };
+    let body = encode_new_session_ticket(&msg).unwrap();
+    assert_eq!(body[0..6], [0x00, 0x00, 0x1c, 0x20, 0x00, 0x82]);
+    assert_eq!(decode_new_session_ticket(&body), Ok(msg));
+
+    // The empty ticket a server sends when it won't issue one after all.
+    let empty = NewSessionTicket { lifetime_hint: 0, ticket: Bytes::new_len(0) };
+    let body = encode_new_session_ticket(&empty).unwrap();
+    assert_eq!(body.len(), 6);
+    assert_eq!(decode_new_session_ticket(&body), Ok(empty));
+    assert!(decode_new_session_ticket(&Bytes::from(&body[0..5])).is_err());
+}
@@ -0,0 +1,216 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::exporter::tls13_exporter;
+use hacspecs::sha256::Sha256;
+use hacspecs::tls13_keysched::*;
+
+mod common;
+use common::hex;
+
+// RFC 8448, section 3 ("Simple 1-RTT Handshake"), with
+// TLS_AES_128_GCM_SHA256.
+const ECDHE: &str = "8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d";
+// The transcript hash of the ClientHello and ServerHello.
+const TH_SERVER_HELLO: &str = "860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8";
+
+const EARLY_SECRET: &str = "33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a";
+const HANDSHAKE_SECRET: &str = "1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac";
+const MASTER_SECRET: &str = "18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919";
+const CLIENT_HS_TRAFFIC: &str = "b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21";
+const SERVER_HS_TRAFFIC: &str = "b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38";
+// The transcript hash up to the server's Finished.
+const TH_SERVER_FINISHED: &str = "9608102a0f1ccc6db6250b7b7e417b1a000eaada3daae4777a7686c9ff83df13";
+// The transcript hash up to the client's Finished.
+const TH_CLIENT_FINISHED: &str = "209145a96ee8e2a122ff810047cc952684658d6049e86429426db87c54ad143d";
+
+#[test]
+fn test_rfc8448_secrets() {
+    let early = early_secret::<Sha256>(None);
+    assert_eq!(&hex(EARLY_SECRET)[..], &early[..]);
+    let handshake = handshake_secret::<Sha256>(early, Some(hex(ECDHE)));
+    assert_eq!(&hex(HANDSHAKE_SECRET)[..], &handshake[..]);
+
+    let client = client_handshake_traffic_secret::<Sha256>(handshake.clone(), hex(TH_SERVER_HELLO));
+    assert_eq!(&hex(CLIENT_HS_TRAFFIC)[..], &client[..]);
+    let server = server_handshake_traffic_secret::<Sha256>(handshake.clone(), hex(TH_SERVER_HELLO));
+    assert_eq!(&hex(SERVER_HS_TRAFFIC)[..], &server[..]);
+
+    let master = master_secret::<Sha256>(handshake);
+    assert_eq!(&hex(MASTER_SECRET)[..], &master[..]);
+
+    let th = hex(TH_SERVER_FINISHED);
+    let client = client_application_traffic_secret::<Sha256>(master.clone(), th.clone());
+    let expected = "9e40646ce79a7f9dc05af8889bce6552875afa0b06df0087f792ebb7c17504a5";
+    assert_eq!(&hex(expected)[..], &client[..]);
+    let server = server_application_traffic_secret::<Sha256>(master.clone(), th.clone());
+    let expected = "a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643";
+    assert_eq!(&hex(expected)[..], &server[..]);
+    let exporter = exporter_master_secret::<Sha256>(master, th);
+    let expected = "fe22f881176eda18eb8f44529e6792c50c9a3f89452f68d8ae311b4309d3cf50";
+    assert_eq!(&hex(expected)[..], &exporter[..]);
+}
+
+#[test]
+fn test_rfc8448_handshake_keys() {
+    let server = hex(SERVER_HS_TRAFFIC);
+    let key = traffic_key::<Sha256>(server.clone(), 16);
+    assert_eq!(&hex("3fce516009c21727d0f2e4e86ee403bc")[..], &key[..]);
+    let iv = traffic_iv::<Sha256>(server.clone(), 12);
+    assert_eq!(&hex("5d313eb2671276ee13000b30")[..], &iv[..]);
+    let finished = finished_key::<Sha256>(server);
+    let expected = "008d3b66f816ea559f96b537e885c31fc068bf492c652f01f288a1d8cdc19fc8";
+    assert_eq!(&hex(expected)[..], &finished[..]);
+
+    let client = hex(CLIENT_HS_TRAFFIC);
+    let key = traffic_key::<Sha256>(client.clone(), 16);
+    assert_eq!(&hex("dbfaa693d1762c5b666af5d950258d01")[..], &key[..]);
+    let iv = traffic_iv::<Sha256>(client.clone(), 12);
+    assert_eq!(&hex("5bd3c71b836e0b76bb73265f")[..], &iv[..]);
+    let finished = finished_key::<Sha256>(client);
+    let expected = "b80ad01015fb2f0bd65ff7d4da5d6bf83f84821d1f87fdc7d3c75b5a7b42d9c4";
+    assert_eq!(&hex(expected)[..], &finished[..]);
+}
+
+#[test]
+fn test_hkdf_label_length() {
+    let secret = hex(EARLY_SECRET);
+    let label = "x".repeat(250);
+    assert!(hkdf_expand_label::<Sha256>(secret.clone(), &label, Bytes::new_len(0), 32).is_err());
+    let context = Bytes::new_len(256);
+    assert!(hkdf_expand_label::<Sha256>(secret, "key", context, 16).is_err());
+}
+
+#[test]
+fn test_message_hash() {
+    let client_hello1 = hex("010000020303");
+    let expected = "fe000020905138690ad1281569e5871222bd92b80c9aa52cbdad29f8bd7cb46fa1f362d9";
+    assert_eq!(&hex(expected)[..], &message_hash::<Sha256>(client_hello1.clone())[..]);
+
+    let th = retry_transcript_hash::<Sha256>(client_hello1, hex("020000020303"));
+    let expected = "72fe20a96f8ba2325679c33ed31f6c18a2bf5d76574de158f020de80f95c0fdb";
+    assert_eq!(&hex(expected)[..], &th[..]);
+}
+
+// The ticket of RFC 8448, section 3, with nonce 00 00, resumes the
+// connection of section 4 ("Resumed 0-RTT Handshake").
+const RESUMED_EARLY_SECRET: &str =
+    "9b2188e9b2fc6d64d71dc329900e20bb41915000f678aa839cbb797cb7d8332c";
+// The transcript hash of section 4's ClientHello, binders and all.
+const TH_RESUMED_CLIENT_HELLO: &str =
+    "08ad0fa05d7c7233b1775ba2ff9f4c5b8b59276b7f227f13a976245f5d960913";
+
+#[test]
+fn test_rfc8448_resumption() {
+    let resumption =
+        resumption_master_secret::<Sha256>(hex(MASTER_SECRET), hex(TH_CLIENT_FINISHED));
+    let expected = "7df235f2031d2a051287d02b0241b0bfdaf86cc856231f2d5aba46c434ec196c";
+    assert_eq!(&hex(expected)[..], &resumption[..]);
+
+    let psk = resumption_psk::<Sha256>(resumption.clone(), hex("0000")).unwrap();
+    let expected = "4ecd0eb6ec3b4d87f5d6028f922ca4c5851a277fd41311c9e62d2c9492e1c4f3";
+    assert_eq!(&hex(expected)[..], &psk[..]);
+    let other = resumption_psk::<Sha256>(resumption.clone(), hex("0001")).unwrap();
+    assert_ne!(&psk[..], &other[..]);
+    assert!(resumption_psk::<Sha256>(resumption, Bytes::new_len(256)).is_err());
+
+    let early = early_secret::<Sha256>(Some(psk));
+    assert_eq!(&hex(RESUMED_EARLY_SECRET)[..], &early[..]);
+    let binder = binder_key::<Sha256>(early.clone(), false);
+    let expected = "69fe131a3bbad5d63c64eebcc30e395b9d8107726a13d074e389dbc8a4e47256";
+    assert_eq!(&hex(expected)[..], &binder[..]);
+
+    let client = client_early_traffic_secret::<Sha256>(early, hex(TH_RESUMED_CLIENT_HELLO));
+    let expected = "3fbbe6a60deb66c30a32795aba0eff7eaa10105586e7be5c09678d63b6caab62";
+    assert_eq!(&hex(expected)[..], &client[..]);
+}
+
+// The rest of section 4's key schedule. The handshake is psk_dhe_ke, so
+// the handshake secret takes an X25519 shared secret on top of the PSK.
+const RESUMED_ECDHE: &str = "f44194756ff9ec9d25180635d66ea6824c6ab3bf179977be37f723570e7ccb2e";
+
+#[test]
+fn test_rfc8448_resumed_secrets() {
+    let early = hex(RESUMED_EARLY_SECRET);
+    // The binder covers the ClientHello up to the binders themselves.
+    let th = hex("63224b2e4573f2d3454ca84b9d009a04f6be9e05711a8396473aefa01e924a14");
+    let binder = verify_data::<Sha256>(binder_key::<Sha256>(early.clone(), false), th);
+    let expected = "3add4fb2d8fdf822a0ca3cf7678ef5e88dae990141c5924d57bb6fa31b9e5f9d";
+    assert_eq!(&hex(expected)[..], &binder[..]);
+    let th = hex(TH_RESUMED_CLIENT_HELLO);
+    let exporter = early_exporter_master_secret::<Sha256>(early.clone(), th);
+    let expected = "b2026866610937d7423e5be90862ccf24c0e6091186d34f812089ff5be2ef7df";
+    assert_eq!(&hex(expected)[..], &exporter[..]);
+
+    let handshake = handshake_secret::<Sha256>(early, Some(hex(RESUMED_ECDHE)));
+    let expected = "005cb112fd8eb4ccc623bb88a07c64b3ede1605363fc7d0df8c7ce4ff0fb4ae6";
+    assert_eq!(&hex(expected)[..], &handshake[..]);
+    let th = hex("f736cb34fe25e701551bee6fd24c1cc7102a7daf9405cb15d97aafe16f757d03");
+    let client = client_handshake_traffic_secret::<Sha256>(handshake.clone(), th.clone());
+    let expected = "2faac08f851d35fea3604fcb4de82dc62c9b164a70974d0462e27f1ab278700f";
+    assert_eq!(&hex(expected)[..], &client[..]);
+    let server = server_handshake_traffic_secret::<Sha256>(handshake.clone(), th);
+    let expected = "fe927ae271312e8bf0275b581c54eef020450dc4ecffaa05a1a35d27518e7803";
+    assert_eq!(&hex(expected)[..], &server[..]);
+
+    let master = master_secret::<Sha256>(handshake);
+    let expected = "e2d32d4ed66dd37897a0e80c84107503ce58bf8aad4cb55a5002d77ecb890ece";
+    assert_eq!(&hex(expected)[..], &master[..]);
+    let th = hex("b0aeffc46a2cfe33114e6fd7d51f9f04b1ca3c497dab08934a774a9d9ad7dbf3");
+    let client = client_application_traffic_secret::<Sha256>(master.clone(), th.clone());
+    let expected = "2abbf2b8e381d23dbebe1dd2a7d16a8bf484cb4950d23fb7fb7fa8547062d9a1";
+    assert_eq!(&hex(expected)[..], &client[..]);
+    let server = server_application_traffic_secret::<Sha256>(master.clone(), th.clone());
+    let expected = "cc21f1bf8feb7dd5fa505bd9c4b468a9984d554a993dc49e6d285598fb672691";
+    assert_eq!(&hex(expected)[..], &server[..]);
+    let exporter = exporter_master_secret::<Sha256>(master.clone(), th);
+    let expected = "3fd93d4ffddc98e64b14dd107aedf8ee4add23f4510f58a4592d0b201bee56b4";
+    assert_eq!(&hex(expected)[..], &exporter[..]);
+    // The client's Finished follows its EndOfEarlyData, which the
+    // transcript includes.
+    let th = hex("c3c122e0bd907a4a3ff6112d8fd53dbf89c773d9552e8b6b9d56d361b3a97bf6");
+    let resumption = resumption_master_secret::<Sha256>(master, th);
+    let expected = "5e95bdf1f89005ea2e9aa0ba85e728e3c19c5fe0c699e3f5bee59faebd0b5406";
+    assert_eq!(&hex(expected)[..], &resumption[..]);
+}
+
+// RFC 8448, section 5 ("HelloRetryRequest"): a full handshake over P-256,
+// whose transcript starts with the message_hash of the first ClientHello.
+const RETRY_ECDHE: &str = "c142ce13ca11b5c2233652e63ad3d97844f1621fbfb9de69d547dc8fedeabeb4";
+
+#[test]
+fn test_rfc8448_hello_retry_secrets() {
+    let early = early_secret::<Sha256>(None);
+    assert_eq!(&hex(EARLY_SECRET)[..], &early[..]);
+    let handshake = handshake_secret::<Sha256>(early, Some(hex(RETRY_ECDHE)));
+    let expected = "ce022e5e6e81e50736d773f2d3adfce8220d049bf510f0dbfac927ef4243b148";
+    assert_eq!(&hex(expected)[..], &handshake[..]);
+    // message_hash, HelloRetryRequest, ClientHello, ServerHello.
+    let th = hex("8aa8e828ec2f8a884fec95a3139de01c15a3daa7ff5bfc3f4bfcc21b438d7bf8");
+    let client = client_handshake_traffic_secret::<Sha256>(handshake.clone(), th.clone());
+    let expected = "158aa7ab8855073582b41d674b4055cabcc534728f659314861b4e08e2011566";
+    assert_eq!(&hex(expected)[..], &client[..]);
+    let server = server_handshake_traffic_secret::<Sha256>(handshake.clone(), th);
+    let expected = "3403e781e2af7b6508da28574f6e95a1abf162de83a97927c37672a4a0cef8a1";
+    assert_eq!(&hex(expected)[..], &server[..]);
+
+    let master = master_secret::<Sha256>(handshake);
+    let expected = "1131545d0baf79ddce9b87f06945781a57dd18ef378dcd2060f8f9a569027ed8";
+    assert_eq!(&hex(expected)[..], &master[..]);
+}
+
+// Keying material exported from the master secret of RFC 8448, section 3,
+// for a made-up transcript hash. Computed with Python's hmac and hashlib.
+#[test]
+fn test_exported_keying_material() {
+    let th = Bytes::from_vec((0x20..0x40).collect());
+    let exporter = exporter_master_secret::<Sha256>(hex(MASTER_SECRET), th);
+    let expected = "06349f774e2be1708c416a53bd4b2baf501f30727561cf269eeaaaea690eaa13";
+    assert_eq!(&hex(expected)[..], &exporter[..]);
+
+    let context = Bytes::from_vec(b"context".to_vec());
+    let keys = tls13_exporter::<Sha256>(exporter, "EXPORTER-Channel-Binding", context, 32).unwrap();
+    let expected = "a79d583d7d7cd559a348875a9eeea4c11941ba6fc7dbe321f50d6faade0f485c";
+    assert_eq!(&hex(expected)[..], &keys[..]);
+}
@@ -0,0 +1,135 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::aesgcm;
+use hacspecs::tls13_record::*;
+
+mod common;
+use common::hex;
+
+fn iota(start: u8, len: usize) -> Bytes {
+    Bytes::from_vec((0..len).map(|i| start + i as u8).collect())
+}
+
+// The server handshake traffic key and IV of RFC 8448, section 3, with
+// TLS_AES_128_GCM_SHA256. The records below were computed with Python's
+// `cryptography`.
+const SERVER_HS_KEY: &str = "3fce516009c21727d0f2e4e86ee403bc";
+const SERVER_HS_IV: &str = "5d313eb2671276ee13000b30";
+
+// A Finished message with a made-up verify_data.
+fn finished() -> Bytes {
+    let mut msg = Bytes::new_len(36);
+    msg.update_raw(0, &[20, 0, 0, 32]);
+    msg.update(4, &iota(0, 32));
+    msg
+}
+
+#[test]
+fn test_nonce() {
+    let iv = hex(SERVER_HS_IV);
+    assert_eq!(iv.raw(), nonce(&iv, 0).unwrap().raw());
+    assert_eq!(hex("5d313eb2671276ee13000b31").raw(), nonce(&iv, 1).unwrap().raw());
+    let expected = hex("5d313eb2661075ea16060c38");
+    assert_eq!(expected.raw(), nonce(&iv, 0x0102030405060708).unwrap().raw());
+    assert!(nonce(&Bytes::new_len(8), 0).is_err());
+}
+
+#[test]
+fn test_aes128gcm_records() {
+    let (key, iv) = (hex(SERVER_HS_KEY), hex(SERVER_HS_IV));
+    let record = seal_record(Aead::Aes128Gcm, &key, &iv, 0, 22, finished(), 0).unwrap();
+    let expected = hex(concat!(
+        "1703030035cdff334e56d6bdff5d5b01d98fa18a3f33450153f598f4e12fe449cb",
+        "6174e6f316bb54bcae0f19aea2ec6ca966caa9127454fc4806"
+    ));
+    assert_eq!(expected.raw(), record.raw());
+    let (content_type, content) = open_record(Aead::Aes128Gcm, &key, &iv, 0, record).unwrap();
+    assert_eq!(content_type, 22);
+    assert_eq!(finished().raw(), content.raw());
+
+    // Padded, and the next in sequence.
+    let record = seal_record(Aead::Aes128Gcm, &key, &iv, 1, 22, finished(), 7).unwrap();
+    let expected = hex(concat!(
+        "170303003c690609278a6d33437f4c8cc438a0a29009ab2ca0cb57f9f2d8f2a544",
+        "ef26ffbbbfe5deba3f9dc3cf7a4a33f67a49057bb7b00a44cfa6db651bea459f"
+    ));
+    assert_eq!(expected.raw(), record.raw());
+    let (_, content) = open_record(Aead::Aes128Gcm, &key, &iv, 1, record.clone()).unwrap();
+    assert_eq!(finished().raw(), content.raw());
+    assert!(open_record(Aead::Aes128Gcm, &key, &iv, 0, record).is_err());
+}
+
+#[test]
+fn test_other_aeads() {
+    let (key, iv) = (iota(0, 32), iota(0x40, 12));
+    let hello = Bytes::from_vec(b"hello".to_vec());
+    let record = seal_record(Aead::ChaCha20Poly1305, &key, &iv, 5, 23, hello.clone(), 3).unwrap();
+    let expected = hex("17030300192d41a6c87831a2af776f89b85254ed2c999cea2b321e2b344f");
+    assert_eq!(expected.raw(), record.raw());
+    let (content_type, content) =
+        open_record(Aead::ChaCha20Poly1305, &key, &iv, 5, record).unwrap();
+    assert_eq!((content_type, hello.raw()), (23, content.raw()));
+
+    // A close_notify alert.
+    let alert = Bytes::from_vec(vec![1, 0]);
+    let record = seal_record(Aead::Aes256Gcm, &key, &iv, 2, 21, alert.clone(), 0).unwrap();
+    let expected = hex("1703030013aa9413604bb4f235ca89a83a7e3dda69c3a2e2");
+    assert_eq!(expected.raw(), record.raw());
+    let (content_type, content) = open_record(Aead::Aes256Gcm, &key, &iv, 2, record).unwrap();
+    assert_eq!((content_type, alert.raw()), (21, content.raw()));
+    assert!(seal_record(Aead::Aes128Gcm, &key, &iv, 0, 23, alert, 0).is_err());
+}
+
+#[test]
+fn test_inner_plaintext() {
+    let inner = encode_inner_plaintext(&finished(), 22, 4);
+    assert_eq!(inner.len(), 36 + 1 + 4);
+    assert_eq!(&inner[36..41], &[22, 0, 0, 0, 0]);
+    let (content_type, content) = decode_inner_plaintext(&inner).unwrap();
+    assert_eq!((content_type, content.raw()), (22, finished().raw()));
+    // Content may end in zeros of its own, as long as a type follows.
+    let zeros = iota(0, 1);
+    let (_, content) = decode_inner_plaintext(&encode_inner_plaintext(&zeros, 23, 2)).unwrap();
+    assert_eq!(zeros.raw(), content.raw());
+    assert!(decode_inner_plaintext(&Bytes::new_len(8)).is_err());
+    assert!(decode_inner_plaintext(&Bytes::new_len(0)).is_err());
+}
+
+#[test]
+fn test_malformed_records() {
+    let (key, iv) = (hex(SERVER_HS_KEY), hex(SERVER_HS_IV));
+    let record = seal_record(Aead::Aes128Gcm, &key, &iv, 0, 22, finished(), 0).unwrap();
+    let open = |record: Bytes| open_record(Aead::Aes128Gcm, &key, &iv, 0, record);
+
+    let mut tampered = record.clone();
+    tampered[20] ^= 1;
+    assert!(open(tampered).is_err());
+    // The header is authenticated too.
+    let mut version = record.clone();
+    version[2] = 1;
+    assert!(open(version).is_err());
+    let mut handshake = record.clone();
+    handshake[0] = 22;
+    assert!(open(handshake).is_err());
+    assert!(open(Bytes::from(&record[0..record.len() - 1])).is_err());
+    assert!(open(Bytes::from(&record[0..4])).is_err());
+
+    // A record of padding alone, which decrypts but carries no type.
+    let header = additional_data(8 + 16);
+    assert_eq!(&header[..], &[23, 3, 3, 0, 24]);
+    let nonce = nonce(&iv, 0).unwrap();
+    let padding = aesgcm::seal(key.clone(), nonce, header.clone(), Bytes::new_len(8)).unwrap();
+    let mut record = Bytes::new_len(5 + padding.len());
+    record.update(0, &header);
+    record.update(5, &padding);
+    assert!(open(record).is_err());
+    assert!(seal_record(Aead::Aes128Gcm, &key, &iv, 0, 0, finished(), 0).is_err());
+
+    let full = Bytes::new_len(1 << 14);
+    assert!(seal_record(Aead::Aes128Gcm, &key, &iv, 0, 23, full.clone(), 0).is_ok());
+    assert!(seal_record(Aead::Aes128Gcm, &key, &iv, 0, 23, full, 1).is_err());
+    let over = Bytes::new_len((1 << 14) + 1);
+    assert!(seal_record(Aead::Aes128Gcm, &key, &iv, 0, 23, over, 0).is_err());
+}
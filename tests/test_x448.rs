@@ -0,0 +1,120 @@
+extern crate hacspec;
+use hacspec::*;
+
+extern crate hacspecs;
+use hacspecs::x448::*;
+
+// RFC 7748, section 5.2: scalar, u-coordinate and result.
+const KAT: [(&str, &str, &str); 2] = [
+    (
+        concat!(
+            "3d262fddf9ec8e88495266fea19a34d28882acef045104d0d1aae121700a779c",
+            "984c24f8cdd78fbff44943eba368f54b29259a4f1c600ad3"
+        ),
+        concat!(
+            "06fce640fa3487bfda5f6cf2d5263f8aad88334cbd07437f020f08f9814dc031",
+            "ddbdc38c19c6da2583fa5429db94ada18aa7a7fb4ef8a086"
+        ),
+        concat!(
+            "ce3e4ff95a60dc6697da1db1d85e6afbdf79b50a2412d7546d5f239fe14fbaad",
+            "eb445fc66a01b0779d98223961111e21766282f73dd96b6f"
+        ),
+    ),
+    (
+        concat!(
+            "203d494428b8399352665ddca42f9de8fef600908e0d461cb021f8c538345dd7",
+            "7c3e4806e25f46d3315c44e0a5b4371282dd2c8d5be3095f"
+        ),
+        concat!(
+            "0fbcc2f993cd56d3305b0b7d9e55d4c1a8fb5dbb52f8e9a1e9b6201b165d0158",
+            "94e56c4d3570bee52fe205e28a78b91cdfbde71ce8d157db"
+        ),
+        concat!(
+            "884a02576239ff7a2f2f63b2db6a9ff37047ac13568e1e30fe63c4a7ad1b3ee3",
+            "a5700df34321d62077e63633c575c1c954514e99da7c179d"
+        ),
+    ),
+];
+
+#[test]
+fn test_kat() {
+    for kat in KAT.iter() {
+        let s = SerializedScalar::from(kat.0);
+        let u = SerializedPoint::from(kat.1);
+        let expected = SerializedPoint::from(kat.2);
+        assert_eq!(expected, x448(s, u));
+    }
+}
+
+// RFC 7748, section 5.2: k and u start as the base point; each iteration
+// sets k to X448(k, u) and u to the old k.
+#[test]
+fn test_iterated() {
+    let mut k = SerializedPoint::new();
+    k[0] = 5;
+    let mut u = k;
+    let expected = [
+        (
+            1,
+            concat!(
+                "3f482c8a9f19b01e6c46ee9711d9dc14fd4bf67af30765c2ae2b846a4d23a8cd",
+                "0db897086239492caf350b51f833868b9bc2b3bca9cf4113"
+            ),
+        ),
+        (
+            1000,
+            concat!(
+                "aa3b4749d55b9daf1e5b00288826c467274ce3ebbdd5c17b975e09d4af6c67cf",
+                "10d087202db88286e2b79fceea3ec353ef54faa26e219f38"
+            ),
+        ),
+    ];
+    let mut i = 0;
+    for (iterations, result) in expected.iter() {
+        while i < *iterations {
+            let r = x448(SerializedScalar::from(&k[..]), u);
+            u = k;
+            k = r;
+            i += 1;
+        }
+        assert_eq!(SerializedPoint::from(*result), k);
+    }
+}
+
+// RFC 7748, section 6.2.
+#[test]
+fn test_dh() {
+    let alice = SerializedScalar::from(concat!(
+        "9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28dd9c9baf5",
+        "74a9419744897391006382a6f127ab1d9ac2d8c0a598726b"
+    ));
+    let alice_public = SerializedPoint::from(concat!(
+        "9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c22c5d9bb",
+        "c836647241d953d40c5b12da88120d53177f80e532c41fa0"
+    ));
+    let bob = SerializedScalar::from(concat!(
+        "1c306a7ac2a0e2e0990b294470cba339e6453772b075811d8fad0d1d6927c120",
+        "bb5ee8972b0d3e21374c9c921b09d1b0366f10b65173992d"
+    ));
+    let bob_public = SerializedPoint::from(concat!(
+        "3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b43027d8b972",
+        "fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609"
+    ));
+    let shared = SerializedPoint::from(concat!(
+        "07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282bb60c0b56",
+        "fd2464c335543936521c24403085d59a449a5037514a879d"
+    ));
+    assert_eq!(alice_public, x448_base(alice));
+    assert_eq!(bob_public, x448_base(bob));
+    assert_eq!(shared, x448_shared(alice, bob_public).unwrap());
+    assert_eq!(shared, x448_shared(bob, alice_public).unwrap());
+}
+
+#[test]
+fn test_small_order() {
+    let s = SerializedScalar::from(KAT[0].0);
+    let mut one = SerializedPoint::new();
+    one[0] = 1;
+    assert!(x448_shared(s, SerializedPoint::new()).is_err());
+    assert!(x448_shared(s, one).is_err());
+}